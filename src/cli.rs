@@ -1,14 +1,23 @@
 // One module per subcommand
+mod collection;
 mod collections;
+mod export;
 mod generate;
+mod history;
 mod import;
 mod request;
+mod run;
+mod secrets;
 mod show;
+mod test;
 
 use crate::{
     cli::{
-        collections::CollectionsCommand, generate::GenerateCommand,
-        import::ImportCommand, request::RequestCommand, show::ShowCommand,
+        collection::CollectionCommand, collections::CollectionsCommand,
+        export::ExportCommand, generate::GenerateCommand,
+        history::HistoryCommand, import::ImportCommand,
+        request::RequestCommand, run::RunCommand, secrets::SecretsCommand,
+        show::ShowCommand, test::TestCommand,
     },
     GlobalArgs,
 };
@@ -18,10 +27,16 @@ use std::process::ExitCode;
 #[derive(Clone, Debug, clap::Subcommand)]
 pub enum CliCommand {
     Request(RequestCommand),
+    Run(RunCommand),
+    Test(TestCommand),
     Generate(GenerateCommand),
     Import(ImportCommand),
+    Export(ExportCommand),
+    Collection(CollectionCommand),
     Collections(CollectionsCommand),
     Show(ShowCommand),
+    History(HistoryCommand),
+    Secrets(SecretsCommand),
 }
 
 /// An executable subcommand. This trait isn't strictly necessary because we do
@@ -38,9 +53,15 @@ impl CliCommand {
         match self {
             Self::Generate(command) => command.execute(global).await,
             Self::Request(command) => command.execute(global).await,
+            Self::Run(command) => command.execute(global).await,
+            Self::Test(command) => command.execute(global).await,
             Self::Import(command) => command.execute(global).await,
+            Self::Export(command) => command.execute(global).await,
+            Self::Collection(command) => command.execute(global).await,
             Self::Collections(command) => command.execute(global).await,
             Self::Show(command) => command.execute(global).await,
+            Self::History(command) => command.execute(global).await,
+            Self::Secrets(command) => command.execute(global).await,
         }
     }
 }
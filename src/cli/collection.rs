@@ -0,0 +1,225 @@
+use crate::{
+    cli::Subcommand,
+    collection::{
+        self, CollectionFile, HeaderEdit, RecipeField, RecipeId, SearchMatch,
+    },
+    GlobalArgs,
+};
+use anyhow::{bail, Context};
+use clap::Parser;
+use itertools::Itertools;
+use std::process::ExitCode;
+
+/// Modify the current request collection file
+#[derive(Clone, Debug, Parser)]
+pub struct CollectionCommand {
+    #[command(subcommand)]
+    subcommand: CollectionSubcommand,
+}
+
+#[derive(Clone, Debug, clap::Subcommand)]
+enum CollectionSubcommand {
+    /// Copy a recipe to a new ID, then open the collection file for editing.
+    ///
+    /// The copy is inserted directly after the original recipe. Its ID is the
+    /// original ID plus `_copy`.
+    #[command(visible_alias = "dup")]
+    Duplicate {
+        /// ID of the recipe to copy
+        recipe_id: RecipeId,
+        /// Don't open the collection file after duplicating
+        #[clap(long)]
+        no_edit: bool,
+    },
+
+    /// Change the ID of a recipe.
+    ///
+    /// References to the recipe from chains and from other recipes' `extends`
+    /// field are updated too.
+    #[command(visible_alias = "mv")]
+    Rename {
+        /// ID of the recipe to rename
+        recipe_id: RecipeId,
+        /// New ID for the recipe
+        new_id: RecipeId,
+    },
+
+    /// Remove a recipe from the collection.
+    ///
+    /// Comments directly above the recipe are removed too. This fails if the
+    /// recipe is referenced anywhere else in the collection file.
+    #[command(visible_alias = "rm")]
+    Delete {
+        /// ID of the recipe to remove
+        recipe_id: RecipeId,
+    },
+
+    /// Rewrite headers across every recipe in the collection.
+    ///
+    /// Header names are matched case-insensitively. Renames are applied
+    /// first, then sets, then removals. Each flag can be given multiple times.
+    Refactor {
+        /// Rename a header, keeping its value
+        #[clap(long, num_args = 2, value_names = ["OLD", "NEW"])]
+        rename_header: Vec<String>,
+        /// Set a header in every recipe, replacing the value if it's already
+        /// defined. Recipes that inherit their headers via a YAML merge
+        /// (`<<: *base`) are skipped.
+        #[clap(long, num_args = 2, value_names = ["NAME", "VALUE"])]
+        set_header: Vec<String>,
+        /// Remove a header
+        #[clap(long, value_name = "NAME")]
+        remove_header: Vec<String>,
+    },
+
+    /// Search recipe IDs, names, URLs, headers, and bodies for some text.
+    ///
+    /// Matching is case-insensitive, and templates are searched in their raw
+    /// form. Each matching line is printed, prefixed by the recipe ID and
+    /// field. Exits with status 1 if nothing matches.
+    Grep {
+        /// Text to search for
+        pattern: String,
+        /// Only print the IDs of matching recipes
+        #[clap(short = 'l', long)]
+        list: bool,
+    },
+
+    /// Print the JSON Schema for collection files.
+    ///
+    /// Point your editor at this to get autocomplete and validation while
+    /// editing a collection. This doesn't need a collection file.
+    Schema,
+}
+
+impl Subcommand for CollectionCommand {
+    async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        // Schema doesn't need a collection, so load it lazily
+        let load = || async {
+            let collection_path = CollectionFile::try_path(None, global.file)?;
+            CollectionFile::load(collection_path).await
+        };
+        match self.subcommand {
+            CollectionSubcommand::Duplicate { recipe_id, no_edit } => {
+                let collection_file = load().await?;
+                let new_id = collection_file.duplicate_recipe(&recipe_id)?;
+                println!("{new_id}");
+                if !no_edit {
+                    let path = collection_file.path();
+                    open::that(path)
+                        .with_context(|| format!("Error opening {path:?}"))?;
+                }
+            }
+            CollectionSubcommand::Rename { recipe_id, new_id } => {
+                load().await?.rename_recipe(&recipe_id, &new_id)?;
+            }
+            CollectionSubcommand::Delete { recipe_id } => {
+                load().await?.delete_recipe(&recipe_id)?;
+            }
+            CollectionSubcommand::Refactor {
+                rename_header,
+                set_header,
+                remove_header,
+            } => {
+                let collection_file = load().await?;
+                // Each pair of args is collected flat, so we have to re-pair
+                let edits =
+                    rename_header
+                        .into_iter()
+                        .tuples()
+                        .map(|(from, to)| HeaderEdit::Rename { from, to })
+                        .chain(set_header.into_iter().tuples().map(
+                            |(name, value)| HeaderEdit::Set { name, value },
+                        ))
+                        .chain(
+                            remove_header
+                                .into_iter()
+                                .map(|name| HeaderEdit::Remove { name }),
+                        )
+                        .collect_vec();
+                if edits.is_empty() {
+                    bail!(
+                        "No changes given; pass at least one of \
+                        `--rename-header`, `--set-header`, or `--remove-header`"
+                    );
+                }
+
+                let report = collection_file.edit_headers(&edits)?;
+                println!(
+                    "Updated {} header(s) in {}",
+                    report.changes,
+                    collection_file.path().display()
+                );
+                if !report.skipped.is_empty() {
+                    eprintln!(
+                        "Skipped recipes that inherit their headers: {}",
+                        report.skipped.iter().join(", ")
+                    );
+                }
+            }
+            CollectionSubcommand::Grep { pattern, list } => {
+                let collection_file = load().await?;
+                return Ok(grep(&collection_file, &pattern, list));
+            }
+            CollectionSubcommand::Schema => {
+                let schema = collection::collection_schema();
+                println!("{}", serde_json::to_string_pretty(&schema)?);
+            }
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Search all recipes for a pattern and print the matches
+fn grep(
+    collection_file: &CollectionFile,
+    pattern: &str,
+    list: bool,
+) -> ExitCode {
+    let mut found = false;
+    let recipes = collection_file
+        .collection
+        .recipes
+        .iter()
+        .filter_map(|(_, node)| node.recipe());
+    for recipe in recipes {
+        let matches = recipe.search(pattern);
+        if matches.is_empty() {
+            continue;
+        }
+        found = true;
+        if list {
+            println!("{}", recipe.id);
+            continue;
+        }
+        for SearchMatch { field, text } in matches {
+            for line in matching_lines(field, &text, pattern) {
+                println!("{}:{field}: {line}", recipe.id);
+            }
+        }
+    }
+    if found {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Get the lines of a matched field to print. For multi-line fields (i.e.
+/// bodies), only the lines containing the pattern are included.
+fn matching_lines<'a>(
+    field: RecipeField,
+    text: &'a str,
+    pattern: &str,
+) -> Vec<&'a str> {
+    match field {
+        RecipeField::Body => {
+            let pattern = pattern.to_lowercase();
+            text.lines()
+                .filter(|line| line.to_lowercase().contains(&pattern))
+                .map(str::trim)
+                .collect()
+        }
+        _ => vec![text],
+    }
+}
@@ -0,0 +1,97 @@
+use crate::{
+    cli::Subcommand,
+    collection::{CollectionFile, ProfileId, RecipeId, RecipeNode, RecipeTree},
+    GlobalArgs,
+};
+use anyhow::{anyhow, Context};
+use clap::{Parser, ValueEnum};
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+/// Export the request collection, or a single recipe, to another HTTP
+/// client's format
+#[derive(Clone, Debug, Parser)]
+pub struct ExportCommand {
+    /// Output format
+    #[clap(long)]
+    format: Format,
+    /// Profile whose data is exported as variables. Only used for Postman and
+    /// .http files, which don't store environments with requests. Insomnia
+    /// exports include every profile as an environment.
+    #[clap(long, short)]
+    profile: Option<ProfileId>,
+    /// Export a single recipe instead of the whole collection
+    #[clap(long, short)]
+    recipe: Option<RecipeId>,
+    /// Destination file [default: stdout]
+    output_file: Option<PathBuf>,
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum Format {
+    Postman,
+    Insomnia,
+    /// JetBrains HTTP client/VS Code REST Client
+    Http,
+}
+
+impl Subcommand for ExportCommand {
+    async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        let collection_path = CollectionFile::try_path(None, global.file)?;
+        let mut collection_file = CollectionFile::load(collection_path).await?;
+        // Name the exported collection after its directory, because the file
+        // name is usually just slumber.yml
+        let name = collection_file
+            .path()
+            .canonicalize()
+            .ok()
+            .and_then(|path| {
+                Some(path.parent()?.file_name()?.to_string_lossy().into_owned())
+            })
+            .unwrap_or_else(|| "Slumber".into());
+        let collection = &mut collection_file.collection;
+        if let Some(recipe_id) = &self.recipe {
+            let recipe = collection
+                .recipes
+                .get_recipe(recipe_id)
+                .ok_or_else(|| anyhow!("No recipe with ID `{recipe_id}`"))?
+                .clone();
+            collection.recipes = RecipeTree::new(
+                [(recipe.id.clone(), RecipeNode::Recipe(recipe))].into(),
+            )
+            .expect("Single recipe cannot have duplicate IDs");
+        }
+
+        let exported = match self.format {
+            Format::Postman => {
+                to_json(&collection.to_postman(&name, self.profile.as_ref())?)
+            }
+            Format::Insomnia => to_json(&collection.to_insomnia(&name)),
+            Format::Http => collection.to_http_file(self.profile.as_ref())?,
+        };
+
+        let mut writer: Box<dyn Write> = match self.output_file {
+            Some(output_file) => {
+                Box::new(File::create(&output_file).with_context(|| {
+                    format!("Error opening output file {output_file:?}")
+                })?)
+            }
+            None => Box::new(io::stdout()),
+        };
+        writer.write_all(exported.as_bytes())?;
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+fn to_json(value: &serde_json::Value) -> String {
+    // Panic is intentional, indicates a wonky bug
+    let mut json =
+        serde_json::to_string_pretty(value).expect("Error serializing");
+    json.push('\n');
+    json
+}
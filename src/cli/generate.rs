@@ -1,5 +1,6 @@
 use crate::{
     cli::{request::BuildRequestCommand, Subcommand},
+    http::BuildOptions,
     template::TemplateError,
     GlobalArgs,
 };
@@ -27,10 +28,14 @@ pub enum GenerateFormat {
 
 impl Subcommand for GenerateCommand {
     async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
-        let (_, ticket) = self
+        let (_, _, ticket) = self
             .build_request
             // User has to explicitly opt into executing triggered requests
-            .build_request(global, self.execute_triggers)
+            .build_request(
+                global,
+                self.execute_triggers,
+                BuildOptions::default(),
+            )
             .await
             .map_err(|error| {
                 // If the build failed because triggered requests are disabled,
@@ -44,7 +49,7 @@ impl Subcommand for GenerateCommand {
                     error
                 }
             })?;
-        println!("{}", ticket.record().to_curl()?);
+        println!("{}", ticket.unredacted_record().to_curl()?);
         Ok(ExitCode::SUCCESS)
     }
 }
@@ -0,0 +1,92 @@
+use crate::{
+    cli::Subcommand,
+    collection::{CollectionFile, ProfileId, RecipeId},
+    db::Database,
+    export::Har,
+    GlobalArgs,
+};
+use anyhow::Context;
+use clap::{Parser, ValueEnum};
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+/// Access request history for the current collection
+#[derive(Clone, Debug, Parser)]
+pub struct HistoryCommand {
+    #[command(subcommand)]
+    subcommand: HistorySubcommand,
+}
+
+#[derive(Clone, Debug, clap::Subcommand)]
+enum HistorySubcommand {
+    /// Export past requests and responses to a file.
+    ///
+    /// Only requests that received a response are stored in history, so
+    /// failed requests are never included. Exchanges are written oldest first.
+    Export {
+        /// Output format
+        #[clap(long, default_value = "har")]
+        format: ExportFormat,
+        /// Only include requests for this recipe
+        #[clap(long, short)]
+        recipe: Option<RecipeId>,
+        /// Only include requests made with this profile
+        #[clap(long, short)]
+        profile: Option<ProfileId>,
+        /// Only include the N most recent requests
+        #[clap(long, short = 'n')]
+        limit: Option<usize>,
+        /// Destination file [default: stdout]
+        #[clap(long, short)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum ExportFormat {
+    /// HTTP Archive 1.2
+    Har,
+}
+
+impl Subcommand for HistoryCommand {
+    async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        let collection_path = CollectionFile::try_path(None, global.file)?;
+        let database = Database::load()?.into_collection(&collection_path)?;
+        match self.subcommand {
+            HistorySubcommand::Export {
+                format,
+                recipe,
+                profile,
+                limit,
+                output,
+            } => {
+                let exchanges = database.get_exchanges(
+                    profile.as_ref(),
+                    recipe.as_ref(),
+                    limit,
+                )?;
+
+                let mut writer: Box<dyn Write> = match output {
+                    Some(output) => {
+                        Box::new(File::create(&output).with_context(|| {
+                            format!("Error opening output file {output:?}")
+                        })?)
+                    }
+                    None => Box::new(io::stdout()),
+                };
+                match format {
+                    ExportFormat::Har => serde_json::to_writer_pretty(
+                        &mut writer,
+                        &Har::new(&exchanges),
+                    )?,
+                }
+                writeln!(writer)?;
+            }
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+}
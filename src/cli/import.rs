@@ -13,8 +13,8 @@ use std::{
 pub struct ImportCommand {
     /// Input format
     format: Format,
-    /// Collection to import
-    input_file: PathBuf,
+    /// Collection file to import. For `curl`, this is the command itself
+    input: String,
     /// Destination for the new slumber collection file [default: stdout]
     output_file: Option<PathBuf>,
 }
@@ -22,13 +22,17 @@ pub struct ImportCommand {
 #[derive(Copy, Clone, Debug, ValueEnum)]
 enum Format {
     Insomnia,
+    Har,
+    Curl,
 }
 
 impl Subcommand for ImportCommand {
     async fn execute(self, _global: GlobalArgs) -> anyhow::Result<ExitCode> {
         // Load the input
         let collection = match self.format {
-            Format::Insomnia => Collection::from_insomnia(&self.input_file)?,
+            Format::Insomnia => Collection::from_insomnia(&self.input)?,
+            Format::Har => Collection::from_har(&self.input)?,
+            Format::Curl => Collection::from_curl(&self.input)?,
         };
 
         // Write the output
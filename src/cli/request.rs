@@ -1,32 +1,50 @@
 use crate::{
     cli::Subcommand,
-    collection::{CollectionFile, ProfileId, RecipeId},
+    collection::{
+        serde_duration, CollectionFile, Method, ProfileId, Recipe, RecipeBody,
+        RecipeId, RecipeKind,
+    },
     config::Config,
     db::{CollectionDatabase, Database},
-    http::{BuildOptions, HttpEngine, RequestSeed, RequestTicket},
-    template::{Prompt, Prompter, TemplateContext, TemplateError},
-    util::{MaybeStr, ResultExt},
+    http::{
+        check_assertions, BuildOptions, HttpEngine, RequestSeed, RequestTicket,
+    },
+    template::{
+        self, FilePrompt, Prompt, Prompter, Template, TemplateContext,
+        TemplateError,
+    },
+    util::{parse_send_at, MaybeStr, ResultExt},
     GlobalArgs,
 };
-use anyhow::{anyhow, Context};
-use clap::Parser;
+use anyhow::{anyhow, bail, Context};
+use chrono::{DateTime, Local};
+use clap::{Parser, ValueEnum};
 use dialoguer::{console::Style, Input, Password};
+use futures::future;
 use indexmap::IndexMap;
 use itertools::Itertools;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use reqwest::header::HeaderMap;
 use std::{
     error::Error,
     fmt::{self, Display, Formatter},
-    io::{self, Write},
+    io::{self, Read, Write},
+    path::PathBuf,
     process::ExitCode,
     str::FromStr,
+    time::Duration,
 };
+use tokio::{sync::mpsc, time};
 use tracing::warn;
 
 /// Exit code to return when `exit_status` flag is set and the HTTP response has
 /// an error status code
 const HTTP_ERROR_EXIT_CODE: u8 = 2;
 
+/// With `--watch-file`, wait this long after a change before sending, in case
+/// more changes are coming
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(100);
+
 /// Execute a single request, and print its response
 #[derive(Clone, Debug, Parser)]
 #[clap(aliases=&["req", "rq"])]
@@ -47,7 +65,8 @@ pub struct RequestCommand {
     no_body: bool,
 
     /// Set process exit code based on HTTP response status. If the status is
-    /// <400, exit code is 0. If it's >=400, exit code is 2.
+    /// <400, exit code is 0. If it's >=400, or any of the recipe's assertions
+    /// fail, exit code is 2.
     #[clap(long)]
     exit_status: bool,
 
@@ -55,14 +74,111 @@ pub struct RequestCommand {
     /// sub-requests will also not be executed.
     #[clap(long)]
     dry_run: bool,
+
+    /// Print a tree of how each template in the request was rendered: which
+    /// profile field, chain, environment variable, or override supplied each
+    /// value, with timing and errors. Printed to stderr, even if the build
+    /// fails. Combine with `--dry-run` to skip sending the request.
+    #[clap(long)]
+    explain: bool,
+
+    /// How to print the request for `--dry-run`. `curl` prints an equivalent
+    /// curl command, like `slumber generate curl`
+    #[clap(long, value_enum, default_value_t, requires = "dry_run")]
+    format: DryRunFormat,
+
+    /// Resume a partial download from a previous attempt, stored in the given
+    /// file. Only the missing content will be requested, and the existing
+    /// content will be prepended to the response body.
+    #[clap(long)]
+    resume: Option<PathBuf>,
+
+    /// Re-send the request on an interval (e.g. `5s`) until the process is
+    /// killed. Errors are printed, but don't stop the loop.
+    #[clap(
+        long,
+        value_parser = serde_duration::parse,
+        conflicts_with_all = ["dry_run", "resume", "save_as"],
+    )]
+    watch: Option<Duration>,
+
+    /// Re-send the request whenever a file under this path changes, until the
+    /// process is killed. Directories are watched recursively. Can be given
+    /// multiple times, and combined with `--watch`.
+    #[clap(long, conflicts_with_all = ["dry_run", "resume", "save_as"])]
+    watch_file: Vec<PathBuf>,
+
+    /// Wait this long (e.g. `30s`, `5m`) before sending the request. Useful
+    /// for testing time-dependent behavior, like token expiry
+    #[clap(
+        long,
+        value_parser = serde_duration::parse,
+        conflicts_with_all = ["dry_run", "at"],
+    )]
+    after: Option<Duration>,
+
+    /// Wait until this time before sending the request. Accepts a local time
+    /// of day (`HH:MM` or `HH:MM:SS`) or an RFC 3339 timestamp. A time of day
+    /// that has already passed today refers to tomorrow.
+    #[clap(
+        long,
+        value_parser = |input: &str| parse_send_at(input, Local::now()),
+        conflicts_with = "dry_run",
+    )]
+    at: Option<DateTime<Local>>,
 }
 
+/// Output format for a dry run
+#[derive(Copy, Clone, Debug, Default, ValueEnum)]
+enum DryRunFormat {
+    /// Print the request in Slumber's internal debug format
+    #[default]
+    Debug,
+    /// Print an equivalent curl command
+    Curl,
+}
+
+/// ID for ad hoc requests built from `--url`, which aren't in the collection.
+/// Request history is stored under this ID.
+const AD_HOC_RECIPE_ID: &str = "_ad_hoc";
+
 /// A helper for any subcommand that needs to build requests. This handles
 /// common args, as well as setting up context for rendering requests
 #[derive(Clone, Debug, Parser)]
 pub struct BuildRequestCommand {
-    /// ID of the recipe to render into a request
-    recipe_id: RecipeId,
+    /// ID of the recipe to render into a request. Omit to build an ad hoc
+    /// request with `--url` instead
+    #[clap(required_unless_present = "url")]
+    recipe_id: Option<RecipeId>,
+
+    /// URL for an ad hoc request that isn't defined in the collection. Can
+    /// contain templates, e.g. `{{host}}/fishes`
+    #[clap(long, conflicts_with = "recipe_id")]
+    url: Option<String>,
+
+    /// HTTP method for an ad hoc request [default: GET]
+    #[clap(long, short = 'X', requires = "url")]
+    method: Option<String>,
+
+    /// Header for an ad hoc request, as `Name: value`. Can be given multiple
+    /// times
+    #[clap(
+        id = "header",
+        long = "header",
+        short = 'H',
+        requires = "url",
+        value_parser = parse_header,
+    )]
+    headers: Vec<(String, String)>,
+
+    /// Body for an ad hoc request
+    #[clap(long, short = 'd', requires = "url")]
+    body: Option<String>,
+
+    /// Save the ad hoc request to the collection file as a new recipe with
+    /// this ID. The recipe is saved once the request has been built
+    #[clap(long, requires = "url")]
+    save_as: Option<RecipeId>,
 
     /// ID of the profile to pull template values from
     #[clap(long = "profile", short)]
@@ -79,25 +195,97 @@ pub struct BuildRequestCommand {
 
 impl Subcommand for RequestCommand {
     async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
-        let (database, ticket) = self
+        // Scheduled send: wait before doing anything. The collection is loaded
+        // afterward, so any edits made while waiting are picked up
+        let send_at = match (self.at, self.after) {
+            (Some(at), _) => Some(at),
+            (None, Some(after)) => Some(
+                chrono::Duration::from_std(after)
+                    .ok()
+                    .and_then(|after| Local::now().checked_add_signed(after))
+                    .ok_or_else(|| anyhow!("`--after` delay is too large"))?,
+            ),
+            (None, None) => None,
+        };
+        if let Some(send_at) = send_at {
+            eprintln!("Sending at {}", send_at.format("%Y-%m-%d %H:%M:%S"));
+            let delay = (send_at - Local::now()).to_std().unwrap_or_default();
+            time::sleep(delay).await;
+        }
+
+        if self.watch.is_none() && self.watch_file.is_empty() {
+            return self.send(global).await;
+        }
+
+        // Watch mode: send over and over until the process is killed. The
+        // collection is reloaded each time, so edits are picked up too
+        let (changes_tx, mut changes_rx) = mpsc::unbounded_channel();
+        let _watcher = watch_files(&self.watch_file, changes_tx)?;
+        loop {
+            if let Err(error) = self.clone().send(global.clone()).await {
+                eprintln!("{error:#}");
+            }
+
+            let interval = async {
+                match self.watch {
+                    Some(interval) => time::sleep(interval).await,
+                    None => future::pending().await,
+                }
+            };
+            tokio::select! {
+                _ = interval => {}
+                Some(()) = changes_rx.recv() => {
+                    // A single save can generate several events, so wait for
+                    // them to settle and send once
+                    time::sleep(WATCH_DEBOUNCE).await;
+                    while changes_rx.try_recv().is_ok() {}
+                }
+            }
+        }
+    }
+}
+
+impl RequestCommand {
+    /// Build and send the request once, and print the response
+    async fn send(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        let build = self
             .build_request
             // Don't execute sub-requests in a dry run
-            .build_request(global, !self.dry_run)
-            .await
-            .map_err(|error| {
-                // If the build failed because triggered requests are disabled,
-                // replace it with a custom error message
-                if TemplateError::has_trigger_disabled_error(&error) {
-                    error.context(
-                        "Triggered requests are disabled with `--dry-run`",
-                    )
-                } else {
-                    error
-                }
-            })?;
+            .build_request(
+                global,
+                !self.dry_run,
+                BuildOptions {
+                    resume_download: self.resume,
+                    ..Default::default()
+                },
+            );
+        let result = if self.explain {
+            let (result, trace) = template::trace(build).await;
+            eprint!("{trace}");
+            result
+        } else {
+            build.await
+        };
+        let (database, template_context, ticket) = result.map_err(|error| {
+            // If the build failed because triggered requests are disabled,
+            // replace it with a custom error message
+            if TemplateError::has_trigger_disabled_error(&error) {
+                error
+                    .context("Triggered requests are disabled with `--dry-run`")
+            } else {
+                error
+            }
+        })?;
 
         if self.dry_run {
-            println!("{:#?}", ticket.record());
+            match self.format {
+                DryRunFormat::Debug => {
+                    println!("{:#?}", ticket.unredacted_record())
+                }
+                DryRunFormat::Curl => {
+                    println!("{}", ticket.unredacted_record().to_curl()?)
+                }
+            }
             Ok(ExitCode::SUCCESS)
         } else {
             // Everything other than the body prints to stderr, to make it easy
@@ -106,8 +294,23 @@ impl Subcommand for RequestCommand {
                 eprintln!("{}", HeaderDisplay(&ticket.record().headers));
             }
 
-            // Run the request
-            let exchange = ticket.send(&database).await?;
+            // Run the request. If the body fails partway through, save what we
+            // got so it can be resumed
+            let exchange = match ticket.send(&database).await {
+                Ok(exchange) => exchange,
+                Err(mut error) => {
+                    if let Some(path) =
+                        error.save_partial_response(&template_context).await?
+                    {
+                        eprintln!(
+                            "Saved partial response body to {}; \
+                            pass `--resume {0}` to continue it",
+                            path.display()
+                        );
+                    }
+                    return Err(error.into());
+                }
+            };
             let status = exchange.response.status;
 
             // Print stuff!
@@ -121,7 +324,12 @@ impl Subcommand for RequestCommand {
                 // If body is not UTF-8, write the raw bytes instead (e.g if
                 // downloading an image)
                 let body = &exchange.response.body;
-                if let Some(text) = body.text() {
+                if body.is_spooled() {
+                    // Too big to hold in memory, so stream it from disk
+                    body.write_to(&mut tokio::io::stdout())
+                        .await
+                        .context("Error writing to stdout")?;
+                } else if let Some(text) = body.text() {
                     print!("{}", text);
                 } else {
                     io::stdout()
@@ -130,7 +338,29 @@ impl Subcommand for RequestCommand {
                 }
             }
 
-            if self.exit_status && status.as_u16() >= 400 {
+            // Write the body to a file if the recipe asks for it. This comes
+            // after printing, so the response isn't lost if saving fails
+            if let Some(path) =
+                exchange.save_response(&template_context).await?
+            {
+                eprintln!("Saved response body to {}", path.display());
+            }
+
+            // Check the recipe's assertions. Failures go to stderr with
+            // everything else that isn't the body
+            let failures = template_context
+                .collection
+                .recipes
+                .get_recipe(&exchange.request.recipe_id)
+                .map(|recipe| check_assertions(&recipe.assertions, &exchange))
+                .unwrap_or_default();
+            let assertions_failed = !failures.is_empty();
+            for error in failures {
+                eprintln!("Assertion failed: {:#}", anyhow::Error::from(error));
+            }
+
+            if self.exit_status && (status.as_u16() >= 400 || assertions_failed)
+            {
                 Ok(ExitCode::from(HTTP_ERROR_EXIT_CODE))
             } else {
                 Ok(ExitCode::SUCCESS)
@@ -139,10 +369,44 @@ impl Subcommand for RequestCommand {
     }
 }
 
+/// Watch files for `--watch-file`, sending a message on the channel whenever
+/// one changes. Returns `None` if there's nothing to watch. The watcher stops
+/// when dropped.
+fn watch_files(
+    paths: &[PathBuf],
+    changes_tx: mpsc::UnboundedSender<()>,
+) -> anyhow::Result<Option<RecommendedWatcher>> {
+    if paths.is_empty() {
+        return Ok(None);
+    }
+    let mut watcher =
+        notify::recommended_watcher(move |result: notify::Result<Event>| {
+            match result {
+                Ok(event)
+                    if matches!(
+                        event.kind,
+                        EventKind::Create(_)
+                            | EventKind::Modify(_)
+                            | EventKind::Remove(_)
+                    ) =>
+                {
+                    let _ = changes_tx.send(());
+                }
+                Ok(_) => {}
+                Err(error) => warn!(%error, "Error watching files"),
+            }
+        })?;
+    for path in paths {
+        watcher
+            .watch(path, RecursiveMode::Recursive)
+            .with_context(|| format!("Error watching `{}`", path.display()))?;
+    }
+    Ok(Some(watcher))
+}
+
 impl BuildRequestCommand {
-    /// Render the request specified by the user. This returns the HTTP engine
-    /// too so it can be re-used if necessary (iff `trigger_dependencies` is
-    /// enabled).
+    /// Render the request specified by the user. This returns the database and
+    /// template context too so they can be re-used after the request is sent.
     ///
     /// `trigger_dependencies` controls whether chained requests can be executed
     /// if their triggers apply.
@@ -150,13 +414,17 @@ impl BuildRequestCommand {
         self,
         global: GlobalArgs,
         trigger_dependencies: bool,
-    ) -> anyhow::Result<(CollectionDatabase, RequestTicket)> {
+        options: BuildOptions,
+    ) -> anyhow::Result<(CollectionDatabase, TemplateContext, RequestTicket)>
+    {
         let collection_path = CollectionFile::try_path(None, global.file)?;
         let database = Database::load()?.into_collection(&collection_path)?;
         let collection_file = CollectionFile::load(collection_path).await?;
-        let collection = collection_file.collection;
+        // The file is kept in case an ad hoc recipe needs to be saved to it
+        let collection = collection_file.collection.clone();
         let config = Config::load()?;
         let http_engine = HttpEngine::new(&config);
+        http_engine.load_cookies(&database)?;
 
         // Validate profile ID, so we can provide a good error if it's invalid
         if let Some(profile_id) = &self.profile {
@@ -168,18 +436,28 @@ impl BuildRequestCommand {
             })?;
         }
 
-        // Find recipe by ID
-        let recipe = collection
-            .recipes
-            .get_recipe(&self.recipe_id)
-            .ok_or_else(|| {
-                anyhow!(
-                    "No recipe with ID `{}`; options are: {}",
-                    self.recipe_id,
-                    collection.recipes.recipe_ids().format(", ")
-                )
-            })?
-            .clone();
+        // Find recipe by ID, or build one from the ad hoc args
+        let recipe = if let Some(recipe_id) = &self.recipe_id {
+            collection
+                .recipes
+                .get_recipe(recipe_id)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "No recipe with ID `{recipe_id}`; options are: {}",
+                        collection.recipes.recipe_ids().format(", ")
+                    )
+                })?
+                .clone()
+        } else {
+            self.ad_hoc_recipe(&collection_file)?
+        };
+        if recipe.kind == RecipeKind::WebSocket {
+            bail!(
+                "Recipe `{}` is a WebSocket recipe, which can only be used in \
+                the TUI",
+                recipe.id
+            );
+        }
 
         // Build the request
         let overrides: IndexMap<_, _> = self.overrides.into_iter().collect();
@@ -197,23 +475,93 @@ impl BuildRequestCommand {
             overrides,
             prompter: Box::new(CliPrompter),
             recursion_count: Default::default(),
+            sensitive_values: Default::default(),
         };
-        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let seed = RequestSeed::new(recipe.clone(), options);
         let request = http_engine.build(seed, &template_context).await?;
-        Ok((database, request))
+
+        if self.save_as.is_some() {
+            collection_file.add_recipe(&recipe)?;
+            eprintln!(
+                "Saved recipe `{}` to {}",
+                recipe.id,
+                collection_file.path().display()
+            );
+        }
+
+        Ok((database, template_context, request))
+    }
+
+    /// Build a recipe from the `--url`, `--method`, `--header` and `--body`
+    /// args
+    fn ad_hoc_recipe(
+        &self,
+        collection_file: &CollectionFile,
+    ) -> anyhow::Result<Recipe> {
+        let id = self
+            .save_as
+            .clone()
+            .unwrap_or_else(|| AD_HOC_RECIPE_ID.to_owned().into());
+        // Check this up front, so the request isn't sent if it can't be saved
+        if self.save_as.is_some()
+            && collection_file.collection.recipes.get(&id).is_some()
+        {
+            bail!("Recipe ID `{id}` is already in use");
+        }
+
+        let url = self.url.clone().unwrap_or_default();
+        let method = match &self.method {
+            Some(method) => method
+                .parse::<Method>()
+                .map_err(|_| anyhow!("Invalid HTTP method `{method}`"))?,
+            None => Method::Get,
+        };
+        let headers = self
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                let value =
+                    Template::try_from(value.clone()).with_context(|| {
+                        format!("Invalid template for header `{name}`")
+                    })?;
+                Ok((name.clone(), value))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let body = self
+            .body
+            .clone()
+            .map(|body| {
+                Template::try_from(body).context("Invalid body template")
+            })
+            .transpose()?
+            .map(RecipeBody::Raw);
+
+        Ok(Recipe {
+            method,
+            url: Template::try_from(url).context("Invalid URL template")?,
+            headers,
+            body,
+            ..Recipe::empty(id)
+        })
     }
 }
 
 /// Prompt the user for input on the CLI
 #[derive(Debug)]
-struct CliPrompter;
+pub(super) struct CliPrompter;
 
 impl Prompter for CliPrompter {
     fn prompt(&self, prompt: Prompt) {
         // This will implicitly queue the prompts by blocking the main thread.
         // Since the CLI has nothing else to do while waiting on a response,
         // that's fine.
-        let result = if prompt.sensitive {
+        let result = if !prompt.options.is_empty() {
+            select_option(
+                prompt.message,
+                &prompt.options,
+                prompt.default.as_deref(),
+            )
+        } else if prompt.sensitive {
             // Dialoguer doesn't support default values here so there's nothing
             // we can do
             if prompt.default.is_some() {
@@ -226,6 +574,8 @@ impl Prompter for CliPrompter {
                 .with_prompt(prompt.message)
                 .allow_empty_password(true)
                 .interact()
+        } else if prompt.multiline {
+            read_multiline(&prompt.message, prompt.default).map_err(Into::into)
         } else {
             let mut input =
                 Input::new().with_prompt(prompt.message).allow_empty(true);
@@ -242,10 +592,75 @@ impl Prompter for CliPrompter {
             prompt.channel.respond(value);
         }
     }
+
+    /// There's no file browser in the CLI, so just ask for a path. Relative
+    /// paths are relative to the prompt's directory
+    fn prompt_file(&self, prompt: FilePrompt) {
+        let result = Input::<String>::new()
+            .with_prompt(format!("{} (path)", prompt.message))
+            .interact()
+            .context("Error reading path from prompt")
+            .traced();
+        if let Ok(path) = result {
+            prompt.channel.respond(prompt.directory.join(path));
+        }
+    }
+}
+
+/// Print a numbered list of options, and ask the user to pick one by number
+fn select_option(
+    message: String,
+    options: &[String],
+    default: Option<&str>,
+) -> dialoguer::Result<String> {
+    eprintln!("{message}");
+    for (i, option) in options.iter().enumerate() {
+        eprintln!("  {}) {option}", i + 1);
+    }
+    let mut input = Input::<usize>::new()
+        .with_prompt(format!("Select 1-{}", options.len()))
+        .validate_with(|number: &usize| {
+            if (1..=options.len()).contains(number) {
+                Ok(())
+            } else {
+                Err("Invalid option")
+            }
+        });
+    if let Some(index) = default
+        .and_then(|default| options.iter().position(|option| option == default))
+    {
+        input = input.default(index + 1);
+    }
+    let number = input.interact()?;
+    Ok(options[number - 1].clone())
+}
+
+/// Read a multi-line value from stdin, until EOF. If nothing is entered, use
+/// the default
+fn read_multiline(
+    message: &str,
+    default: Option<String>,
+) -> io::Result<String> {
+    match &default {
+        Some(_) => eprintln!(
+            "{message} (Ctrl-D to finish, leave empty to use the default)"
+        ),
+        None => eprintln!("{message} (Ctrl-D to finish)"),
+    }
+    let mut value = String::new();
+    io::stdin().read_to_string(&mut value)?;
+    // The final newline is just there to end the last line
+    if value.ends_with('\n') {
+        value.pop();
+    }
+    match default {
+        Some(default) if value.is_empty() => Ok(default),
+        _ => Ok(value),
+    }
 }
 
 /// Parse a single key=value pair for an argument
-fn parse_key_val<T, U>(
+pub(super) fn parse_key_val<T, U>(
     s: &str,
 ) -> Result<(T, U), Box<dyn Error + Send + Sync + 'static>>
 where
@@ -260,6 +675,14 @@ where
     Ok((key.parse()?, value.parse()?))
 }
 
+/// Parse a `Name: value` header argument
+fn parse_header(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("invalid header: no \":\" found in `{s}`"))?;
+    Ok((name.trim().to_owned(), value.trim().to_owned()))
+}
+
 /// Wrapper making it easy to print a header map
 struct HeaderDisplay<'a>(&'a HeaderMap);
 
@@ -277,3 +700,23 @@ impl<'a> Display for HeaderDisplay<'a> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::error::ErrorKind;
+    use rstest::rstest;
+
+    /// Saving an ad hoc recipe can only happen once, so it can't be repeated
+    /// in watch mode
+    #[rstest]
+    #[case::watch(&["--watch", "5s"])]
+    #[case::watch_file(&["--watch-file", "data.json"])]
+    fn test_save_as_conflicts_with_watch(#[case] watch_args: &[&str]) {
+        let args = ["request", "--url", "http://localhost", "--save-as", "new"]
+            .into_iter()
+            .chain(watch_args.iter().copied());
+        let error = RequestCommand::try_parse_from(args).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ArgumentConflict);
+    }
+}
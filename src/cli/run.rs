@@ -0,0 +1,165 @@
+use crate::{
+    cli::{
+        request::{parse_key_val, CliPrompter},
+        Subcommand,
+    },
+    collection::{CollectionFile, ProfileId, RecipeId, RecipeKind},
+    config::Config,
+    db::Database,
+    http::{BuildOptions, HttpEngine, RequestSeed, RunOutcome, RunResult},
+    template::TemplateContext,
+    util::format_duration,
+    GlobalArgs,
+};
+use anyhow::anyhow;
+use clap::Parser;
+use itertools::Itertools;
+use std::process::ExitCode;
+
+/// Send every recipe in a folder, in order, then print a summary.
+///
+/// Recipes are sent one at a time, so later recipes can use chains that
+/// depend on the responses of earlier ones. Nested folders are included.
+/// WebSocket recipes are skipped.
+#[derive(Clone, Debug, Parser)]
+pub struct RunCommand {
+    /// ID of the folder to run
+    folder_id: RecipeId,
+
+    /// ID of the profile to pull template values from
+    #[clap(long = "profile", short)]
+    profile: Option<ProfileId>,
+
+    /// List of key=value template field overrides
+    #[clap(
+        long = "override",
+        short = 'o',
+        value_parser = parse_key_val::<String, String>,
+    )]
+    overrides: Vec<(String, String)>,
+
+    /// Keep going after a request fails. By default the run stops at the
+    /// first failure, unless `continue_on_failure` is set in the config.
+    /// A request fails if it can't be built or sent, or if the response has
+    /// a 4xx/5xx status.
+    #[clap(long)]
+    continue_on_failure: bool,
+}
+
+impl Subcommand for RunCommand {
+    async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        let collection_path = CollectionFile::try_path(None, global.file)?;
+        let database = Database::load()?.into_collection(&collection_path)?;
+        let collection =
+            CollectionFile::load(collection_path).await?.collection;
+        let config = Config::load()?;
+        let http_engine = HttpEngine::new(&config);
+        http_engine.load_cookies(&database)?;
+        let continue_on_failure =
+            self.continue_on_failure || config.continue_on_failure;
+
+        if let Some(profile_id) = &self.profile {
+            collection.profiles.get(profile_id).ok_or_else(|| {
+                anyhow!(
+                    "No profile with ID `{profile_id}`; options are: {}",
+                    collection.profiles.keys().format(", ")
+                )
+            })?;
+        }
+        let folder = collection
+            .recipes
+            .get(&self.folder_id)
+            .and_then(|node| node.folder())
+            .ok_or_else(|| anyhow!("No folder with ID `{}`", self.folder_id))?;
+        let recipes = folder
+            .recipes()
+            .into_iter()
+            .filter(|recipe| recipe.kind != RecipeKind::WebSocket)
+            .cloned()
+            .collect_vec();
+
+        let template_context = TemplateContext {
+            selected_profile: self.profile,
+            collection: collection.clone(),
+            http_engine: Some(http_engine.clone()),
+            database: database.clone(),
+            overrides: self.overrides.into_iter().collect(),
+            prompter: Box::new(CliPrompter),
+            recursion_count: Default::default(),
+            sensitive_values: Default::default(),
+        };
+
+        let mut results: Vec<RunResult> = Vec::with_capacity(recipes.len());
+        for recipe in recipes {
+            // Once a request fails, everything after it is skipped
+            if !continue_on_failure
+                && results.last().is_some_and(|result| !result.is_success())
+            {
+                results.push(RunResult::skipped(recipe.id));
+                continue;
+            }
+
+            let seed = RequestSeed::new(recipe, BuildOptions::default());
+            let result = match http_engine.build(seed, &template_context).await
+            {
+                Ok(ticket) => match ticket.send(&database).await {
+                    Ok(exchange) => {
+                        // Write the body to a file if the recipe asks for it
+                        match exchange.save_response(&template_context).await {
+                            Ok(Some(path)) => eprintln!(
+                                "Saved response body to {}",
+                                path.display()
+                            ),
+                            Ok(None) => {}
+                            Err(error) => eprintln!("{error:#}"),
+                        }
+                        RunResult::from(&exchange)
+                    }
+                    Err(error) => RunResult::from(&error),
+                },
+                Err(error) => RunResult::from(&error),
+            };
+            // Print progress as we go, since the whole run could take a while
+            eprintln!("{}: {}", result.recipe_id, result.outcome);
+            results.push(result);
+        }
+
+        print_summary(folder.name(), &results);
+        if results.iter().all(RunResult::is_success) {
+            Ok(ExitCode::SUCCESS)
+        } else {
+            Ok(ExitCode::FAILURE)
+        }
+    }
+}
+
+/// Print a table with the outcome of each recipe
+fn print_summary(folder_name: &str, results: &[RunResult]) {
+    let passed = results.iter().filter(|result| result.is_success()).count();
+    let skipped = results
+        .iter()
+        .filter(|result| matches!(result.outcome, RunOutcome::Skipped))
+        .count();
+    println!(
+        "{folder_name}: {passed} passed, {} failed, {skipped} skipped",
+        results.len() - passed - skipped
+    );
+    let width = results
+        .iter()
+        .map(|result| result.recipe_id.len())
+        .max()
+        .unwrap_or_default();
+    for result in results {
+        let duration = result
+            .duration
+            .as_ref()
+            .map(format_duration)
+            .unwrap_or_default();
+        println!(
+            "  {:<width$}  {:>8}  {}",
+            result.recipe_id.as_str(),
+            duration,
+            result.outcome
+        );
+    }
+}
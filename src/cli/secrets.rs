@@ -0,0 +1,71 @@
+use crate::{cli::Subcommand, secrets, GlobalArgs};
+use clap::Parser;
+use dialoguer::Password;
+use std::process::ExitCode;
+
+/// View and modify secrets stored in the OS keyring
+///
+/// Secrets can be used in any template with `{{secret.<name>}}`, and are
+/// shared between all collections.
+#[derive(Clone, Debug, Parser)]
+pub struct SecretsCommand {
+    #[command(subcommand)]
+    subcommand: SecretsSubcommand,
+}
+
+#[derive(Clone, Debug, clap::Subcommand)]
+enum SecretsSubcommand {
+    /// Create or update a secret
+    Set {
+        /// Name of the secret. Can contain letters, numbers, `-`, and `_`
+        name: String,
+        /// Value of the secret. If omitted, you'll be prompted for it. Passing
+        /// the value here may leave it in your shell history!
+        value: Option<String>,
+    },
+    /// Print the value of a secret
+    Get {
+        /// Name of the secret
+        name: String,
+    },
+    /// List the names of all secrets
+    #[command(visible_alias = "ls")]
+    List,
+    /// Delete a secret
+    #[command(visible_alias = "rm")]
+    Delete {
+        /// Name of the secret
+        name: String,
+    },
+}
+
+impl Subcommand for SecretsCommand {
+    async fn execute(self, _global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        match self.subcommand {
+            SecretsSubcommand::Set { name, value } => {
+                let value = match value {
+                    Some(value) => value,
+                    None => Password::new()
+                        .with_prompt(format!("Value for `{name}`"))
+                        .allow_empty_password(true)
+                        .interact()?,
+                };
+                secrets::set(&name, &value)?;
+            }
+            SecretsSubcommand::Get { name } => {
+                let value = secrets::get(&name).await.map_err(|error| {
+                    anyhow::Error::from(error)
+                        .context(format!("Loading secret `{name}`"))
+                })?;
+                println!("{value}");
+            }
+            SecretsSubcommand::List => {
+                for name in secrets::list()? {
+                    println!("{name}");
+                }
+            }
+            SecretsSubcommand::Delete { name } => secrets::delete(&name)?,
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+}
@@ -0,0 +1,283 @@
+use crate::{
+    cli::{
+        request::{parse_key_val, CliPrompter},
+        Subcommand,
+    },
+    collection::{
+        CollectionFile, ProfileId, Recipe, RecipeId, RecipeKind, RecipeNode,
+    },
+    config::Config,
+    db::Database,
+    http::{check_assertions, BuildOptions, HttpEngine, RequestSeed},
+    template::TemplateContext,
+    GlobalArgs,
+};
+use anyhow::anyhow;
+use chrono::Duration;
+use clap::{Parser, ValueEnum};
+use itertools::Itertools;
+use std::process::ExitCode;
+
+/// Send recipes and check their responses against the recipes' assertions.
+///
+/// Each recipe is one test case. A case fails if the request can't be built or
+/// sent, or if any of its assertions fail. Recipes without assertions fail on
+/// a 4xx/5xx status. Recipes are sent one at a time, in the order they're
+/// defined. A report is printed to stdout, and the command exits with an
+/// error if any case failed.
+#[derive(Clone, Debug, Parser)]
+pub struct TestCommand {
+    /// IDs of the recipes and folders to test. Folders include every recipe
+    /// in them, including nested folders. If omitted, every recipe in the
+    /// collection is tested
+    ids: Vec<RecipeId>,
+
+    /// ID of the profile to pull template values from
+    #[clap(long = "profile", short)]
+    profile: Option<ProfileId>,
+
+    /// List of key=value template field overrides
+    #[clap(
+        long = "override",
+        short = 'o',
+        value_parser = parse_key_val::<String, String>,
+    )]
+    overrides: Vec<(String, String)>,
+
+    /// Format of the report
+    #[clap(long, value_enum, default_value_t)]
+    format: ReportFormat,
+}
+
+/// Output format for the test report
+#[derive(Copy, Clone, Debug, Default, ValueEnum)]
+enum ReportFormat {
+    /// Test Anything Protocol
+    #[default]
+    Tap,
+    /// JUnit XML, which most CI systems can display
+    Junit,
+}
+
+/// Outcome of testing a single recipe
+#[derive(Debug)]
+struct TestCase {
+    recipe_id: RecipeId,
+    /// Elapsed time for the request. `None` if it was never sent
+    duration: Option<Duration>,
+    /// Why the case failed. Empty if it passed
+    failures: Vec<String>,
+}
+
+impl Subcommand for TestCommand {
+    async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        let collection_path = CollectionFile::try_path(None, global.file)?;
+        let database = Database::load()?.into_collection(&collection_path)?;
+        let collection =
+            CollectionFile::load(collection_path).await?.collection;
+        let config = Config::load()?;
+        let http_engine = HttpEngine::new(&config);
+        http_engine.load_cookies(&database)?;
+
+        if let Some(profile_id) = &self.profile {
+            collection.profiles.get(profile_id).ok_or_else(|| {
+                anyhow!(
+                    "No profile with ID `{profile_id}`; options are: {}",
+                    collection.profiles.keys().format(", ")
+                )
+            })?;
+        }
+        let selected: Vec<&Recipe> = if self.ids.is_empty() {
+            collection
+                .recipes
+                .iter()
+                .filter_map(|(_, node)| node.recipe())
+                .collect()
+        } else {
+            self.ids
+                .iter()
+                .map(|id| match collection.recipes.get(id) {
+                    Some(RecipeNode::Recipe(recipe)) => Ok(vec![recipe]),
+                    Some(RecipeNode::Folder(folder)) => Ok(folder.recipes()),
+                    None => Err(anyhow!("No recipe or folder with ID `{id}`")),
+                })
+                .flatten_ok()
+                .collect::<anyhow::Result<_>>()?
+        };
+        let recipes: Vec<Recipe> = selected
+            .into_iter()
+            .filter(|recipe| recipe.kind != RecipeKind::WebSocket)
+            // A recipe could be given directly *and* through its folder
+            .unique_by(|recipe| &recipe.id)
+            .cloned()
+            .collect();
+
+        let template_context = TemplateContext {
+            selected_profile: self.profile,
+            collection: collection.clone(),
+            http_engine: Some(http_engine.clone()),
+            database: database.clone(),
+            overrides: self.overrides.into_iter().collect(),
+            prompter: Box::new(CliPrompter),
+            recursion_count: Default::default(),
+            sensitive_values: Default::default(),
+        };
+
+        let mut cases = Vec::with_capacity(recipes.len());
+        for recipe in recipes {
+            let recipe_id = recipe.id.clone();
+            let assertions = recipe.assertions.clone();
+            let seed = RequestSeed::new(recipe, BuildOptions::default());
+            let case = match http_engine.build(seed, &template_context).await {
+                Ok(ticket) => match ticket.send(&database).await {
+                    Ok(exchange) => {
+                        if let Err(error) =
+                            exchange.save_response(&template_context).await
+                        {
+                            eprintln!("{error:#}");
+                        }
+                        let status = exchange.response.status;
+                        let failures = if assertions.is_empty() {
+                            if status.is_client_error()
+                                || status.is_server_error()
+                            {
+                                vec![format!("Response has status {status}")]
+                            } else {
+                                vec![]
+                            }
+                        } else {
+                            check_assertions(&assertions, &exchange)
+                                .into_iter()
+                                .map(|error| {
+                                    format!("{:#}", anyhow::Error::from(error))
+                                })
+                                .collect()
+                        };
+                        TestCase {
+                            recipe_id,
+                            duration: Some(exchange.duration()),
+                            failures,
+                        }
+                    }
+                    Err(error) => TestCase {
+                        recipe_id,
+                        duration: Some(error.end_time - error.start_time),
+                        failures: vec![format!("{:#}", error.error)],
+                    },
+                },
+                Err(error) => TestCase {
+                    recipe_id,
+                    duration: None,
+                    failures: vec![format!("{:#}", error.error)],
+                },
+            };
+            // Print progress as we go, since the whole run could take a while
+            eprintln!(
+                "{}: {}",
+                case.recipe_id,
+                if case.passed() { "ok" } else { "FAILED" }
+            );
+            cases.push(case);
+        }
+
+        match self.format {
+            ReportFormat::Tap => print_tap(&cases),
+            ReportFormat::Junit => print_junit(&cases),
+        }
+        if cases.iter().all(TestCase::passed) {
+            Ok(ExitCode::SUCCESS)
+        } else {
+            Ok(ExitCode::FAILURE)
+        }
+    }
+}
+
+impl TestCase {
+    fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Duration in seconds, for the report
+    fn seconds(&self) -> f64 {
+        self.duration
+            .map(|duration| duration.num_milliseconds() as f64 / 1000.0)
+            .unwrap_or_default()
+    }
+}
+
+/// Print a report in TAP version 13 format. Failures are listed in a YAML
+/// block under the failed case
+fn print_tap(cases: &[TestCase]) {
+    println!("TAP version 13");
+    println!("1..{}", cases.len());
+    for (i, case) in cases.iter().enumerate() {
+        let number = i + 1;
+        if case.passed() {
+            println!("ok {number} - {}", case.recipe_id);
+        } else {
+            println!("not ok {number} - {}", case.recipe_id);
+            println!("  ---");
+            println!("  duration_ms: {}", (case.seconds() * 1000.0) as i64);
+            println!("  failures:");
+            for failure in &case.failures {
+                // A JSON string is also a valid YAML string, and handles all
+                // the escaping for us
+                println!("    - {}", serde_json::Value::from(failure.as_str()));
+            }
+            println!("  ...");
+        }
+    }
+}
+
+/// Print a report in JUnit XML format, with one test suite for the whole run
+fn print_junit(cases: &[TestCase]) {
+    let failures = cases.iter().filter(|case| !case.passed()).count();
+    let time: f64 = cases.iter().map(TestCase::seconds).sum();
+    println!(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    println!(
+        r#"<testsuites tests="{}" failures="{failures}" time="{time:.3}">"#,
+        cases.len()
+    );
+    println!(
+        r#"  <testsuite name="slumber" tests="{}" failures="{failures}" time="{time:.3}">"#,
+        cases.len()
+    );
+    for case in cases {
+        let name = escape_xml(&case.recipe_id);
+        let time = case.seconds();
+        if case.passed() {
+            println!(
+                r#"    <testcase name="{name}" classname="slumber" time="{time:.3}"/>"#
+            );
+        } else {
+            println!(
+                r#"    <testcase name="{name}" classname="slumber" time="{time:.3}">"#
+            );
+            for failure in &case.failures {
+                println!(
+                    r#"      <failure message="{}"/>"#,
+                    escape_xml(failure)
+                );
+            }
+            println!("    </testcase>");
+        }
+    }
+    println!("  </testsuite>");
+    println!("</testsuites>");
+}
+
+/// Escape text for use in an XML attribute
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\n' => escaped.push_str("&#10;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
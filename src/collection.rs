@@ -2,14 +2,27 @@
 //! possible
 
 mod cereal;
+mod curl;
+mod dotenv;
+mod edit;
+mod export;
+mod extends;
+mod har;
+mod imports;
 mod insomnia;
 mod models;
 mod recipe_tree;
+mod schema;
+mod search;
 
+pub use cereal::serde_duration;
+pub use edit::{find_text, replace_text, HeaderEdit};
 pub use models::*;
 pub use recipe_tree::*;
+pub use schema::collection_schema;
+pub use search::{RecipeField, SearchMatch};
 
-use crate::util::{parse_yaml, ResultExt};
+use crate::util::ResultExt;
 use anyhow::{anyhow, Context};
 use itertools::Itertools;
 use std::{
@@ -17,6 +30,7 @@ use std::{
     fmt::Debug,
     fs,
     future::Future,
+    iter,
     path::{Path, PathBuf},
 };
 use tokio::task;
@@ -38,6 +52,10 @@ pub const CONFIG_FILES: &[&str] = &[
 pub struct CollectionFile {
     /// Path to the file that this collection was loaded from
     path: PathBuf,
+    /// Every other file that went into the collection: imported files and
+    /// `.env` files. Changes to these should trigger a reload, just like the
+    /// root file.
+    dependencies: Vec<PathBuf>,
     pub collection: Collection,
 }
 
@@ -48,6 +66,7 @@ impl CollectionFile {
     pub fn with_path(path: PathBuf) -> Self {
         Self {
             path,
+            dependencies: Vec::new(),
             collection: Default::default(),
         }
     }
@@ -56,15 +75,20 @@ impl CollectionFile {
     /// [Self::try_path] to find the file themself. This pattern enables the
     /// TUI to start up and watch the collection file, even if it's invalid.
     pub async fn load(path: PathBuf) -> anyhow::Result<Self> {
-        let collection = load_collection(path.clone()).await?;
-        Ok(Self { path, collection })
+        let (collection, dependencies) = load_collection(path.clone()).await?;
+        Ok(Self {
+            path,
+            dependencies,
+            collection,
+        })
     }
 
-    /// Reload a new collection from the same file used for this one.
+    /// Reload a new collection from the same file used for this one. The list
+    /// of dependencies is reloaded too, because imports may have changed.
     ///
     /// Returns `impl Future` to unlink the future from `&self`'s lifetime.
-    pub fn reload(&self) -> impl Future<Output = anyhow::Result<Collection>> {
-        load_collection(self.path.clone())
+    pub fn reload(&self) -> impl Future<Output = anyhow::Result<Self>> {
+        Self::load(self.path.clone())
     }
 
     /// Get the path of the file that this collection was loaded from
@@ -72,6 +96,14 @@ impl CollectionFile {
         &self.path
     }
 
+    /// Get every file that this collection was loaded from: the root file,
+    /// then any imported and `.env` files. `.env` files are included even if
+    /// they don't exist, because creating one should trigger a reload.
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        iter::once(self.path.as_path())
+            .chain(self.dependencies.iter().map(PathBuf::as_path))
+    }
+
     /// Get the path to the collection file, returning an error if none is
     /// available. This will use the override if given, otherwise it will fall
     /// back to searching the given directory for a collection. If the directory
@@ -98,6 +130,7 @@ impl CollectionFile {
     pub fn testing(collection: Collection) -> Self {
         Self {
             path: PathBuf::default(),
+            dependencies: Vec::new(),
             collection,
         }
     }
@@ -148,21 +181,31 @@ fn detect_path(dir: &Path) -> Option<PathBuf> {
 }
 
 /// Load a collection from the given file. Takes an owned path because it
-/// needs to be passed to a future
-async fn load_collection(path: PathBuf) -> anyhow::Result<Collection> {
+/// needs to be passed to a future. Along with the collection, return the path
+/// of every other file that was loaded (see [CollectionFile::files])
+async fn load_collection(
+    path: PathBuf,
+) -> anyhow::Result<(Collection, Vec<PathBuf>)> {
     info!(?path, "Loading collection file");
     // A bit pessimistic, huh... This gets around some lifetime struggles
     let error_context = format!("Error loading data from {path:?}");
 
     // YAML parsing is blocking so do it in a different thread. We could use
     // tokio::fs for this but that just uses std::fs underneath anyway.
-    let result =
-        task::spawn_blocking::<_, anyhow::Result<Collection>>(move || {
-            let bytes = fs::read(path)?;
-            let collection = parse_yaml(&bytes)?;
-            Ok(collection)
-        })
-        .await;
+    let result = task::spawn_blocking(move || -> anyhow::Result<_> {
+        let bytes = fs::read(&path)?;
+        let mut collection = parse_collection(&bytes)?;
+        let mut dependencies = Vec::new();
+        if let Some(dir) = path.parent() {
+            dependencies.extend(dotenv::load_dotenv(&mut collection, dir)?);
+            collection.recipes.set_dir(dir);
+        }
+        let mut collection =
+            imports::resolve_imports(&path, collection, &mut dependencies)?;
+        collection.recipes.apply_defaults(&collection.defaults);
+        Ok((collection, dependencies))
+    })
+    .await;
 
     // Flatten the join error result into the inner task result. Result::flatten
     // is experimental :(
@@ -175,6 +218,28 @@ async fn load_collection(path: PathBuf) -> anyhow::Result<Collection> {
     result.context(error_context).traced()
 }
 
+/// Parse a single collection file. This resolves YAML merges and recipe
+/// inheritance, but *not* imports or defaults
+fn parse_collection(bytes: &[u8]) -> anyhow::Result<Collection> {
+    // Multi-step parsing is required for anchor/alias merging and `extends`
+    let mut yaml = serde_yaml::from_slice::<serde_yaml::Value>(bytes)?;
+    yaml.apply_merge()?;
+    extends::resolve_extends(&mut yaml)?;
+    let collection: Collection = serde_yaml::from_value(yaml)?;
+
+    // A selector's language depends on another field, so it can't be
+    // validated during deserialization
+    for chain in collection.chains.values() {
+        if let Some(selector) = &chain.selector {
+            chain
+                .selector_type
+                .validate(selector)
+                .with_context(|| format!("Chain `{}`", chain.id))?;
+        }
+    }
+    Ok(collection)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -226,4 +291,31 @@ mod tests {
         );
         drop(temp_dir); // Dropping deletes the directory
     }
+
+    /// Selectors are validated according to their type when loading
+    #[rstest]
+    #[case::jsonpath(
+        "selector: $.",
+        "Chain `c1`: Invalid JSONPath selector `$.`"
+    )]
+    #[case::xpath(
+        "selector: '//', selector_type: xpath",
+        "Chain `c1`: Invalid XPath selector `//`"
+    )]
+    #[case::css(
+        "selector: 'a[', selector_type: css",
+        "Chain `c1`: Invalid CSS selector `a[`"
+    )]
+    #[case::regex(
+        "selector: '(', selector_type: regex",
+        "Chain `c1`: Invalid regex selector `(`"
+    )]
+    fn test_parse_collection_invalid_selector(
+        #[case] fields: &str,
+        #[case] expected_error: &str,
+    ) {
+        let yaml =
+            format!("chains: {{c1: {{source: !prompt {{}}, {fields}}}}}");
+        assert_err!(parse_collection(yaml.as_bytes()), expected_error);
+    }
 }
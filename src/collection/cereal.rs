@@ -2,13 +2,15 @@
 
 use crate::{
     collection::{
-        recipe_tree::RecipeNode, Chain, ChainId, Profile, ProfileId, RecipeId,
+        recipe_tree::RecipeNode, Chain, ChainId, MultipartField, Profile,
+        ProfileId, RecipeBody, RecipeId,
     },
-    template::Template,
+    template::{Template, TemplateKey},
 };
+use indexmap::IndexMap;
 use serde::{
-    de::{Error, Visitor},
-    Deserialize, Deserializer,
+    de::{EnumAccess, Error, VariantAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
 };
 use std::hash::Hash;
 
@@ -68,6 +70,33 @@ where
     Ok(map)
 }
 
+/// Implement a [Visitor] method for a primitive type. The value is parsed as a
+/// template, then converted to the visitor's output type via `From`
+macro_rules! visit_primitive {
+    ($func:ident, $type:ty) => {
+        fn $func<E>(self, v: $type) -> Result<Self::Value, E>
+        where
+            E: Error,
+        {
+            Template::try_from(v.to_string())
+                .map(<Self::Value>::from)
+                .map_err(E::custom)
+        }
+    };
+}
+
+/// Implement [Visitor] methods for all primitive types that can be treated as
+/// templates. See [visit_primitive]
+macro_rules! visit_primitives {
+    () => {
+        visit_primitive!(visit_bool, bool);
+        visit_primitive!(visit_u64, u64);
+        visit_primitive!(visit_i64, i64);
+        visit_primitive!(visit_f64, f64);
+        visit_primitive!(visit_str, &str);
+    };
+}
+
 // Custom deserializer for `Template`. This is useful for deserializing values
 // that are not strings, but should be treated as strings such as numbers,
 // booleans, and nulls.
@@ -78,17 +107,6 @@ impl<'de> Deserialize<'de> for Template {
     {
         struct TemplateVisitor;
 
-        macro_rules! visit_primitive {
-            ($func:ident, $type:ty) => {
-                fn $func<E>(self, v: $type) -> Result<Self::Value, E>
-                where
-                    E: Error,
-                {
-                    Template::try_from(v.to_string()).map_err(E::custom)
-                }
-            };
-        }
-
         impl<'de> Visitor<'de> for TemplateVisitor {
             type Value = Template;
 
@@ -99,24 +117,247 @@ impl<'de> Deserialize<'de> for Template {
                 formatter.write_str("string, number, or boolean")
             }
 
-            visit_primitive!(visit_bool, bool);
-            visit_primitive!(visit_u64, u64);
-            visit_primitive!(visit_i64, i64);
-            visit_primitive!(visit_f64, f64);
-            visit_primitive!(visit_str, &str);
+            visit_primitives!();
         }
 
         deserializer.deserialize_any(TemplateVisitor)
     }
 }
 
-/// Serialize/deserialize a duration with unit shorthand. This does *not* handle
-/// subsecond precision. Supported units are:
+impl Serialize for RecipeBody {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::Raw(template) => template.serialize(serializer),
+            Self::FormMultipart(fields) => serializer
+                .serialize_newtype_variant(
+                    "RecipeBody",
+                    1,
+                    Self::FORM_MULTIPART,
+                    fields,
+                ),
+            Self::File(path) => serializer.serialize_newtype_variant(
+                "RecipeBody",
+                2,
+                Self::FILE,
+                path,
+            ),
+        }
+    }
+}
+
+// A plain value is a raw body, and a tagged value is a structured body
+impl<'de> Deserialize<'de> for RecipeBody {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct RecipeBodyVisitor;
+
+        impl<'de> Visitor<'de> for RecipeBodyVisitor {
+            type Value = RecipeBody;
+
+            fn expecting(
+                &self,
+                formatter: &mut std::fmt::Formatter,
+            ) -> std::fmt::Result {
+                write!(
+                    formatter,
+                    "string or tagged body (`!{}` or `!{}`)",
+                    RecipeBody::FORM_MULTIPART,
+                    RecipeBody::FILE
+                )
+            }
+
+            visit_primitives!();
+
+            fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+            where
+                A: EnumAccess<'de>,
+            {
+                let (tag, value) = data.variant::<String>()?;
+                match tag.as_str() {
+                    RecipeBody::FORM_MULTIPART => {
+                        Ok(RecipeBody::FormMultipart(value.newtype_variant()?))
+                    }
+                    RecipeBody::FILE => {
+                        Ok(RecipeBody::File(value.newtype_variant()?))
+                    }
+                    _ => Err(A::Error::unknown_variant(
+                        &tag,
+                        &[RecipeBody::FORM_MULTIPART, RecipeBody::FILE],
+                    )),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(RecipeBodyVisitor)
+    }
+}
+
+impl Serialize for MultipartField {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::Text(template) => template.serialize(serializer),
+            Self::File(file) => serializer.serialize_newtype_variant(
+                "MultipartField",
+                1,
+                Self::FILE,
+                file,
+            ),
+        }
+    }
+}
+
+// Same as the body: plain values are text, and files are tagged
+impl<'de> Deserialize<'de> for MultipartField {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct MultipartFieldVisitor;
+
+        impl<'de> Visitor<'de> for MultipartFieldVisitor {
+            type Value = MultipartField;
+
+            fn expecting(
+                &self,
+                formatter: &mut std::fmt::Formatter,
+            ) -> std::fmt::Result {
+                write!(formatter, "string or `!{}`", MultipartField::FILE)
+            }
+
+            visit_primitives!();
+
+            fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+            where
+                A: EnumAccess<'de>,
+            {
+                let (tag, value) = data.variant::<String>()?;
+                match tag.as_str() {
+                    MultipartField::FILE => {
+                        Ok(MultipartField::File(value.newtype_variant()?))
+                    }
+                    _ => Err(A::Error::unknown_variant(
+                        &tag,
+                        &[MultipartField::FILE],
+                    )),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(MultipartFieldVisitor)
+    }
+}
+
+/// Deserialize profile data. Each value is a template, `!env <variable>`,
+/// which is shorthand for the `{{env.<variable>}}` template, or
+/// `!secret <name>`, which is shorthand for `{{secret.<name>}}`.
+pub fn deserialize_profile_data<'de, D>(
+    deserializer: D,
+) -> Result<IndexMap<String, Template>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    /// A single profile value. This only exists to attach a deserialize impl
+    struct ProfileValue(Template);
+
+    impl ProfileValue {
+        const ENV: &'static str = "env";
+        const SECRET: &'static str = "secret";
+    }
+
+    impl From<Template> for ProfileValue {
+        fn from(template: Template) -> Self {
+            Self(template)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for ProfileValue {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            struct ProfileValueVisitor;
+
+            impl<'de> Visitor<'de> for ProfileValueVisitor {
+                type Value = ProfileValue;
+
+                fn expecting(
+                    &self,
+                    formatter: &mut std::fmt::Formatter,
+                ) -> std::fmt::Result {
+                    write!(
+                        formatter,
+                        "string, `!{}`, or `!{}`",
+                        ProfileValue::ENV,
+                        ProfileValue::SECRET
+                    )
+                }
+
+                visit_primitives!();
+
+                fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+                where
+                    A: EnumAccess<'de>,
+                {
+                    let (tag, value) = data.variant::<String>()?;
+                    match tag.as_str() {
+                        ProfileValue::ENV => {
+                            let variable: String = value.newtype_variant()?;
+                            let key = TemplateKey::Environment(&variable);
+                            Template::try_from(format!("{{{{{key}}}}}"))
+                                .map(ProfileValue)
+                                .map_err(|_| {
+                                    A::Error::custom(format!(
+                                        "Invalid environment variable \
+                                        `{variable}`"
+                                    ))
+                                })
+                        }
+                        ProfileValue::SECRET => {
+                            let name: String = value.newtype_variant()?;
+                            let key = TemplateKey::Secret(&name);
+                            Template::try_from(format!("{{{{{key}}}}}"))
+                                .map(ProfileValue)
+                                .map_err(|_| {
+                                    A::Error::custom(format!(
+                                        "Invalid secret name `{name}`"
+                                    ))
+                                })
+                        }
+                        _ => Err(A::Error::unknown_variant(
+                            &tag,
+                            &[ProfileValue::ENV, ProfileValue::SECRET],
+                        )),
+                    }
+                }
+            }
+
+            deserializer.deserialize_any(ProfileValueVisitor)
+        }
+    }
+
+    let data = IndexMap::<String, ProfileValue>::deserialize(deserializer)?;
+    Ok(data
+        .into_iter()
+        .map(|(field, value)| (field, value.0))
+        .collect())
+}
+
+/// Serialize/deserialize a duration with unit shorthand. Precision is limited
+/// to milliseconds. Supported units are:
+/// - ms
 /// - s
 /// - m
 /// - h
 /// - d
-/// Examples: `30s`, `5m`, `12h`, `3d`
+/// Examples: `500ms`, `30s`, `5m`, `12h`, `3d`
 pub mod serde_duration {
     use derive_more::Display;
     use itertools::Itertools;
@@ -133,6 +374,9 @@ pub mod serde_duration {
 
     #[derive(Debug, Display, EnumIter, EnumString)]
     enum Unit {
+        #[display("ms")]
+        #[strum(serialize = "ms")]
+        Millisecond,
         #[display("s")]
         #[strum(serialize = "s")]
         Second,
@@ -154,15 +398,26 @@ pub mod serde_duration {
     where
         S: Serializer,
     {
-        // Always serialize as seconds, because it's easiest. Sub-second
-        // precision is lost
-        S::serialize_str(serializer, &format!("{}s", duration.as_secs()))
+        // Use seconds where possible, because it's easiest to read. Anything
+        // below a millisecond is lost
+        if duration.subsec_millis() == 0 {
+            S::serialize_str(serializer, &format!("{}s", duration.as_secs()))
+        } else {
+            S::serialize_str(serializer, &format!("{}ms", duration.as_millis()))
+        }
     }
 
     pub fn deserialize<'de, D>(deserializer: D) -> Result<Duration, D::Error>
     where
         D: Deserializer<'de>,
     {
+        let input = String::deserialize(deserializer)?;
+        parse(&input).map_err(D::Error::custom)
+    }
+
+    /// Parse a duration from a string with a unit, e.g. `30s`. This is the
+    /// same format used for deserialization, so it can be used for CLI args
+    pub fn parse(input: &str) -> Result<Duration, String> {
         fn quantity(input: &str) -> IResult<&str, u64> {
             map_res(digit1, str::parse)(input)
         }
@@ -171,28 +426,57 @@ pub mod serde_duration {
             take_while(char::is_alphabetic)(input)
         }
 
-        let input = String::deserialize(deserializer)?;
         let (_, (quantity, unit)) =
-            all_consuming(tuple((quantity, unit)))(&input).map_err(|_| {
-                D::Error::custom(
-                    "Invalid duration, must be `<quantity><unit>` (e.g. `12d`)",
-                )
+            all_consuming(tuple((quantity, unit)))(input).map_err(|_| {
+                "Invalid duration, must be `<quantity><unit>` (e.g. `12d`)"
+                    .to_owned()
             })?;
 
         let unit = unit.parse().map_err(|_| {
-            D::Error::custom(format!(
+            format!(
                 "Unknown duration unit `{unit}`; must be one of {}",
                 Unit::iter()
                     .format_with(", ", |unit, f| f(&format_args!("`{unit}`")))
-            ))
+            )
         })?;
-        let seconds = match unit {
-            Unit::Second => quantity,
-            Unit::Minute => quantity * 60,
-            Unit::Hour => quantity * 60 * 60,
-            Unit::Day => quantity * 60 * 60 * 24,
+        let duration = match unit {
+            Unit::Millisecond => Duration::from_millis(quantity),
+            Unit::Second => Duration::from_secs(quantity),
+            Unit::Minute => Duration::from_secs(quantity * 60),
+            Unit::Hour => Duration::from_secs(quantity * 60 * 60),
+            Unit::Day => Duration::from_secs(quantity * 60 * 60 * 24),
         };
-        Ok(Duration::from_secs(seconds))
+        Ok(duration)
+    }
+
+    /// Serialize/deserialize an optional duration. Use with
+    /// `#[serde(default)]`, so a missing field is `None`
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+        use std::time::Duration;
+
+        #[derive(Serialize, Deserialize)]
+        #[serde(transparent)]
+        struct Wrap(#[serde(with = "super")] Duration);
+
+        pub fn serialize<S>(
+            duration: &Option<Duration>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            duration.map(Wrap).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D>(
+            deserializer: D,
+        ) -> Result<Option<Duration>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            Ok(Option::<Wrap>::deserialize(deserializer)?.map(|wrap| wrap.0))
+        }
     }
 
     #[cfg(test)]
@@ -213,9 +497,11 @@ pub mod serde_duration {
         #[rstest]
         #[case::seconds_short(Duration::from_secs(3), "3s")]
         #[case::seconds_long(Duration::from_secs(3000), "3000s")]
-        // Subsecond precision is lost
-        #[case::seconds_subsecond_lost(Duration::from_millis(400), "0s")]
-        #[case::seconds_subsecond_round_down(Duration::from_millis(1999), "1s")]
+        #[case::milliseconds(Duration::from_millis(400), "400ms")]
+        #[case::milliseconds_long(Duration::from_millis(1999), "1999ms")]
+        // Sub-millisecond precision is lost
+        #[case::submillisecond_lost(Duration::from_micros(400), "0s")]
+        #[case::submillisecond_round_down(Duration::from_micros(1999), "1ms")]
         fn test_serialize(
             #[case] duration: Duration,
             #[case] expected: &'static str,
@@ -224,6 +510,7 @@ pub mod serde_duration {
         }
 
         #[rstest]
+        #[case::milliseconds("250ms", Duration::from_millis(250))]
         #[case::seconds_zero("0s", Duration::from_secs(0))]
         #[case::seconds_short("1s", Duration::from_secs(1))]
         #[case::seconds_longer("100s", Duration::from_secs(100))]
@@ -256,7 +543,7 @@ pub mod serde_duration {
         )]
         #[case::invalid_unit(
             "3hr",
-            "Unknown duration unit `hr`; must be one of `s`, `m`, `h`, `d`"
+            "Unknown duration unit `hr`; must be one of `ms`, `s`, `m`, `h`, `d`"
         )]
         fn test_deserialize_error(
             #[case] s: &'static str,
@@ -269,7 +556,14 @@ pub mod serde_duration {
 
 #[cfg(test)]
 mod tests {
-    use crate::template::Template;
+    use super::*;
+    use crate::{
+        collection::{MultipartFile, ProfileProxy},
+        template::Template,
+        test_util::assert_err,
+        util::parse_yaml,
+    };
+    use indexmap::indexmap;
     use rstest::rstest;
     use serde_test::{assert_de_tokens, Token};
 
@@ -290,4 +584,96 @@ mod tests {
     fn test_deserialize_template(#[case] token: Token, #[case] expected: &str) {
         assert_de_tokens(&Template::from(expected), &[token]);
     }
+
+    #[rstest]
+    #[case::raw("'{\"a\": 1}'", RecipeBody::from("{\"a\": 1}"))]
+    #[case::form_multipart(
+        "!form_multipart
+        name: Ted
+        photo: !file
+          path: ./ted.png
+          content_type: image/png",
+        RecipeBody::FormMultipart(indexmap! {
+            "name".into() => MultipartField::Text("Ted".into()),
+            "photo".into() => MultipartField::File(MultipartFile {
+                path: "./ted.png".into(),
+                content_type: Some("image/png".into()),
+                filename: None,
+            }),
+        }),
+    )]
+    #[case::file(
+        "!file ./big.bin",
+        RecipeBody::File("./big.bin".into())
+    )]
+    fn test_deserialize_body(#[case] yaml: &str, #[case] expected: RecipeBody) {
+        let body: RecipeBody = parse_yaml(yaml.as_bytes()).unwrap();
+        assert_eq!(body, expected);
+        // Make sure it round trips
+        let serialized = serde_yaml::to_string(&body).unwrap();
+        let body: RecipeBody = parse_yaml(serialized.as_bytes()).unwrap();
+        assert_eq!(body, expected);
+    }
+
+    #[rstest]
+    #[case::unknown_tag("!form_urlencoded\nname: Ted", "unknown variant")]
+    #[case::unknown_field_tag(
+        "!form_multipart\nphoto: !url http://localhost",
+        "unknown variant"
+    )]
+    fn test_deserialize_body_error(#[case] yaml: &str, #[case] error: &str) {
+        assert_err!(parse_yaml::<RecipeBody>(yaml.as_bytes()), error);
+    }
+
+    #[test]
+    fn test_deserialize_profile_data() {
+        let yaml = "data:\n  host: localhost\n  token: !env API_TOKEN\n  \
+            password: !secret db-password";
+        let profile: Profile = parse_yaml(yaml.as_bytes()).unwrap();
+        assert_eq!(
+            profile.data,
+            indexmap! {
+                "host".into() => "localhost".into(),
+                "token".into() => "{{env.API_TOKEN}}".into(),
+                "password".into() => "{{secret.db-password}}".into(),
+            }
+        );
+    }
+
+    /// `direct` is a keyword; anything else is a proxy URL
+    #[rstest]
+    #[case::none("name: Local", None)]
+    #[case::direct("proxy: direct", Some(ProfileProxy::Direct))]
+    #[case::url(
+        "proxy: http://proxy:8080",
+        Some(ProfileProxy::Url("http://proxy:8080".into()))
+    )]
+    fn test_deserialize_profile_proxy(
+        #[case] yaml: &str,
+        #[case] expected: Option<ProfileProxy>,
+    ) {
+        let profile: Profile = parse_yaml(yaml.as_bytes()).unwrap();
+        assert_eq!(profile.proxy, expected);
+        // Make sure it round trips
+        let serialized = serde_yaml::to_string(&profile).unwrap();
+        let profile: Profile = parse_yaml(serialized.as_bytes()).unwrap();
+        assert_eq!(profile.proxy, expected);
+    }
+
+    #[rstest]
+    #[case::unknown_tag("data:\n  token: !file ./token.txt", "unknown variant")]
+    #[case::invalid_variable(
+        "data:\n  token: !env a.b",
+        "Invalid environment variable `a.b`"
+    )]
+    #[case::invalid_secret(
+        "data:\n  token: !secret a.b",
+        "Invalid secret name `a.b`"
+    )]
+    fn test_deserialize_profile_data_error(
+        #[case] yaml: &str,
+        #[case] error: &str,
+    ) {
+        assert_err!(parse_yaml::<Profile>(yaml.as_bytes()), error);
+    }
 }
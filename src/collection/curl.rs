@@ -0,0 +1,514 @@
+//! Import recipes from curl commands, e.g. the snippets in API docs
+
+use crate::{
+    collection::{
+        har::{split_query, unique_id},
+        Authentication, Collection, Defaults, Method, MultipartField,
+        MultipartFile, Recipe, RecipeBody, RecipeKind, RecipeNode, RecipeTree,
+    },
+    template::Template,
+};
+use anyhow::{anyhow, bail, Context};
+use indexmap::IndexMap;
+use reqwest::header;
+use std::{collections::HashSet, path::PathBuf};
+use tracing::info;
+use url::{form_urlencoded, Url};
+
+/// Flags that don't take a value and don't affect the recipe, e.g. output
+/// control. Commands copied from devtools and docs are full of these.
+const IGNORED_FLAGS: &[&str] = &[
+    "-s",
+    "--silent",
+    "-S",
+    "--show-error",
+    "-v",
+    "--verbose",
+    "-L",
+    "--location",
+    "-i",
+    "--include",
+    "-k",
+    "--insecure",
+    "-f",
+    "--fail",
+    "--compressed",
+];
+
+impl Collection {
+    /// Convert a curl command into a collection with a single recipe
+    pub fn from_curl(command: &str) -> anyhow::Result<Self> {
+        let recipe = Recipe::from_curl(command)?;
+        let recipes = RecipeTree::new(IndexMap::from([(
+            recipe.id.clone(),
+            RecipeNode::Recipe(recipe),
+        )]))
+        .map_err(|duplicate_id| {
+            anyhow!("Duplicate recipe ID `{duplicate_id}`")
+        })?;
+        Ok(Collection {
+            imports: Vec::new(),
+            profiles: IndexMap::new(),
+            defaults: Defaults::default(),
+            recipes,
+            chains: IndexMap::new(),
+            hooks: Default::default(),
+            _ignore: serde::de::IgnoredAny,
+        })
+    }
+}
+
+impl Recipe {
+    /// Parse a curl command into a recipe. The command is split according to
+    /// POSIX shell rules, so quoting and line continuations work as they
+    /// would in a terminal. The leading `curl` is optional. The ID and name
+    /// are generated from the method and URL.
+    ///
+    /// Supported options are `-X`, `-H`, `-d` (and its variants), `--json`,
+    /// `--data-urlencode`, `-u`, `-F`, `-G`, `-A`, `-e`, `-b`, and `--url`.
+    /// Options that don't affect the request, like `-s`, are ignored. Any
+    /// other option is an error, because ignoring it could silently produce a
+    /// different request.
+    pub fn from_curl(command: &str) -> anyhow::Result<Self> {
+        info!(command, "Importing curl command");
+        let words =
+            shell_words::split(command).context("Invalid shell syntax")?;
+        let mut args = words.into_iter().peekable();
+        if args.peek().map(String::as_str) == Some("curl") {
+            args.next();
+        }
+
+        let mut method: Option<Method> = None;
+        let mut url: Option<String> = None;
+        let mut headers: IndexMap<String, Template> = IndexMap::new();
+        let mut authentication = None;
+        // Each -d value is a chunk of the body, joined with &
+        let mut data: Vec<String> = Vec::new();
+        let mut data_file: Option<String> = None;
+        let mut form: IndexMap<String, MultipartField> = IndexMap::new();
+        let mut get = false;
+
+        while let Some(arg) = args.next() {
+            if IGNORED_FLAGS.contains(&arg.as_str()) || is_ignored_cluster(&arg)
+            {
+                continue;
+            }
+
+            // Short options can have the value attached, e.g. -XPOST
+            let (flag, attached) = if arg.len() > 2
+                && arg.starts_with('-')
+                && !arg.starts_with("--")
+                && arg.is_char_boundary(2)
+            {
+                (arg[..2].to_owned(), Some(arg[2..].to_owned()))
+            } else {
+                (arg, None)
+            };
+            let mut value = || {
+                attached
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| anyhow!("Missing value for `{flag}`"))
+            };
+
+            match flag.as_str() {
+                "-X" | "--request" => {
+                    let value = value()?;
+                    method = Some(value.parse().map_err(|_| {
+                        anyhow!("Unsupported HTTP method `{value}`")
+                    })?);
+                }
+                "--url" => set_url(&mut url, value()?)?,
+                "-H" | "--header" => {
+                    let value = value()?;
+                    let (name, value) = value
+                        .split_once(':')
+                        .ok_or_else(|| anyhow!("Invalid header `{value}`"))?;
+                    headers.insert(
+                        name.trim().to_lowercase(),
+                        Template::dangerous(value.trim().to_owned()),
+                    );
+                }
+                "-A" | "--user-agent" => {
+                    headers.insert(
+                        header::USER_AGENT.as_str().into(),
+                        Template::dangerous(value()?),
+                    );
+                }
+                "-e" | "--referer" => {
+                    headers.insert(
+                        header::REFERER.as_str().into(),
+                        Template::dangerous(value()?),
+                    );
+                }
+                "-b" | "--cookie" => {
+                    let value = value()?;
+                    // Without a =, the value is a cookie jar file
+                    if !value.contains('=') {
+                        bail!(
+                            "Cookie files (`{flag} {value}`) aren't supported"
+                        );
+                    }
+                    headers.insert(
+                        header::COOKIE.as_str().into(),
+                        Template::dangerous(value),
+                    );
+                }
+                "-u" | "--user" => {
+                    let value = value()?;
+                    let (username, password) = match value.split_once(':') {
+                        Some((username, password)) => {
+                            (username.to_owned(), Some(password.to_owned()))
+                        }
+                        None => (value, None),
+                    };
+                    authentication = Some(Authentication::Basic {
+                        username: Template::dangerous(username),
+                        password: password.map(Template::dangerous),
+                    });
+                }
+                "-d" | "--data" | "--data-ascii" | "--data-binary" => {
+                    let value = value()?;
+                    // @ loads from a file
+                    match value.strip_prefix('@') {
+                        Some(path) => data_file = Some(path.to_owned()),
+                        None => data.push(value),
+                    }
+                }
+                "--data-raw" => data.push(value()?),
+                // Shorthand for JSON data and headers. Explicit headers win
+                "--json" => {
+                    data.push(value()?);
+                    for name in [header::CONTENT_TYPE, header::ACCEPT] {
+                        headers.entry(name.as_str().into()).or_insert_with(
+                            || Template::dangerous("application/json".into()),
+                        );
+                    }
+                }
+                "--data-urlencode" => data.push(url_encode_data(&value()?)?),
+                "-F" | "--form" => {
+                    let value = value()?;
+                    let (name, value) =
+                        value.split_once('=').ok_or_else(|| {
+                            anyhow!("Invalid form field `{value}`")
+                        })?;
+                    form.insert(name.to_owned(), form_field(value));
+                }
+                "-G" | "--get" => get = true,
+                _ if flag.starts_with('-') => {
+                    bail!("Unsupported curl option `{flag}`")
+                }
+                _ => set_url(&mut url, flag)?,
+            }
+        }
+
+        let url = url.ok_or_else(|| anyhow!("No URL given"))?;
+        // curl assumes HTTP if there's no scheme
+        let url: Url = if url.contains("://") {
+            url.parse()
+        } else {
+            format!("http://{url}").parse()
+        }
+        .with_context(|| format!("Invalid URL `{url}`"))?;
+        let (url, mut query) = split_query(url);
+
+        let has_data = !data.is_empty() || data_file.is_some();
+        let body = if get {
+            // -G moves the data into the query
+            if data_file.is_some() {
+                bail!("`-G` can't be used with data from a file");
+            }
+            for chunk in &data {
+                query.extend(form_urlencoded::parse(chunk.as_bytes()).map(
+                    |(name, value)| {
+                        (
+                            name.into_owned(),
+                            Template::dangerous(value.into_owned()),
+                        )
+                    },
+                ));
+            }
+            None
+        } else if !form.is_empty() {
+            if has_data {
+                bail!("`-F` can't be combined with `-d`");
+            }
+            Some(RecipeBody::FormMultipart(form))
+        } else if has_data {
+            // Like curl, default to a URL-encoded form
+            headers
+                .entry(header::CONTENT_TYPE.as_str().into())
+                .or_insert_with(|| {
+                    Template::dangerous(
+                        "application/x-www-form-urlencoded".into(),
+                    )
+                });
+            match data_file {
+                Some(path) if data.is_empty() => {
+                    Some(RecipeBody::File(Template::dangerous(path)))
+                }
+                Some(_) => {
+                    bail!("Data from a file can't be combined with `-d`")
+                }
+                None => {
+                    Some(RecipeBody::Raw(Template::dangerous(data.join("&"))))
+                }
+            }
+        } else {
+            None
+        };
+
+        // Sending a body implies POST, unless the method is given
+        let method = method.unwrap_or(if body.is_some() {
+            Method::Post
+        } else {
+            Method::Get
+        });
+
+        Ok(Recipe {
+            id: unique_id(&method, &url, &mut HashSet::new()),
+            kind: RecipeKind::Http,
+            name: Some(format!("{method} {}", url.path())),
+            method,
+            url: Template::dangerous(url.to_string()),
+            base_url: None,
+            dir: PathBuf::new(),
+            body,
+            authentication,
+            query,
+            headers,
+            save_response_to: None,
+            throttle: None,
+            timeout: None,
+            retry: None,
+            http_version: None,
+            conditional: false,
+            cache_ttl: None,
+            rate_limit: None,
+            compression: None,
+            connection: None,
+            assertions: Vec::new(),
+            tags: Vec::new(),
+            graphql: false,
+        })
+    }
+}
+
+/// Is this a cluster of ignored short flags, e.g. `-sSL`?
+fn is_ignored_cluster(arg: &str) -> bool {
+    arg.strip_prefix('-').is_some_and(|flags| {
+        !flags.is_empty()
+            && !flags.starts_with('-')
+            && flags
+                .chars()
+                .all(|c| IGNORED_FLAGS.contains(&format!("-{c}").as_str()))
+    })
+}
+
+fn set_url(url: &mut Option<String>, value: String) -> anyhow::Result<()> {
+    if let Some(url) = url {
+        bail!("Multiple URLs given (`{url}` and `{value}`)");
+    }
+    *url = Some(value);
+    Ok(())
+}
+
+/// Encode a `--data-urlencode` value. The formats are `content`, `=content`,
+/// and `name=content`, where only the content is encoded. Loading content
+/// from a file (`@file`, `name@file`) isn't supported.
+fn url_encode_data(value: &str) -> anyhow::Result<String> {
+    let encode = |content: &str| {
+        form_urlencoded::byte_serialize(content.as_bytes()).collect::<String>()
+    };
+    // Whichever of = or @ comes first determines the format
+    match value.find(['=', '@']).map(|i| value.split_at(i)) {
+        Some(("", content)) if content.starts_with('=') => {
+            Ok(encode(&content[1..]))
+        }
+        Some((name, content)) if content.starts_with('=') => {
+            Ok(format!("{name}={}", encode(&content[1..])))
+        }
+        Some(_) => {
+            bail!("Loading `--data-urlencode` from a file isn't supported")
+        }
+        None => Ok(encode(value)),
+    }
+}
+
+/// Parse the value of a `-F` field. `@path` uploads a file, and `;type=` sets
+/// its content type.
+fn form_field(value: &str) -> MultipartField {
+    match value.strip_prefix('@') {
+        Some(path) => {
+            let (path, content_type) = match path.split_once(";type=") {
+                Some((path, content_type)) => {
+                    (path, Some(content_type.to_owned()))
+                }
+                None => (path, None),
+            };
+            MultipartField::File(MultipartFile {
+                path: Template::dangerous(path.to_owned()),
+                content_type,
+                filename: None,
+            })
+        }
+        None => MultipartField::Text(Template::dangerous(value.to_owned())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_err, Factory};
+    use indexmap::indexmap;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::get(
+        "curl https://example.com/users?page=1",
+        Recipe {
+            id: "get_users".into(),
+            name: Some("GET /users".into()),
+            url: "https://example.com/users".into(),
+            query: indexmap! {"page".into() => "1".into()},
+            ..Recipe::factory(())
+        }
+    )]
+    #[case::no_scheme(
+        "curl example.com",
+        Recipe {
+            id: "get".into(),
+            name: Some("GET /".into()),
+            url: "http://example.com/".into(),
+            ..Recipe::factory(())
+        }
+    )]
+    // This is the format that "Copy as cURL" generates
+    #[case::json(
+        "curl -XPUT --url 'https://example.com/users/1' \\\n  \
+        --header 'Content-Type: application/json' --data '{\"name\": \"Barry\"}'",
+        Recipe {
+            id: "put_users_1".into(),
+            name: Some("PUT /users/1".into()),
+            method: Method::Put,
+            url: "https://example.com/users/1".into(),
+            headers: indexmap! {
+                "content-type".into() => "application/json".into(),
+            },
+            body: Some(RecipeBody::Raw(
+                Template::dangerous(r#"{"name": "Barry"}"#.into()),
+            )),
+            ..Recipe::factory(())
+        }
+    )]
+    #[case::form_data(
+        "curl -sSL https://example.com/login -d user=barry -d 'pass=hunter2' \
+        --data-urlencode 'note=hi there' -u barry:hunter2",
+        Recipe {
+            id: "post_login".into(),
+            name: Some("POST /login".into()),
+            method: Method::Post,
+            url: "https://example.com/login".into(),
+            headers: indexmap! {
+                "content-type".into() =>
+                    "application/x-www-form-urlencoded".into(),
+            },
+            body: Some(RecipeBody::Raw(
+                "user=barry&pass=hunter2&note=hi+there".into(),
+            )),
+            authentication: Some(Authentication::Basic {
+                username: "barry".into(),
+                password: Some("hunter2".into()),
+            }),
+            ..Recipe::factory(())
+        }
+    )]
+    #[case::json_shorthand(
+        "curl https://example.com/users --json '{}' -H 'accept: */*'",
+        Recipe {
+            id: "post_users".into(),
+            name: Some("POST /users".into()),
+            method: Method::Post,
+            url: "https://example.com/users".into(),
+            headers: indexmap! {
+                "content-type".into() => "application/json".into(),
+                "accept".into() => "*/*".into(),
+            },
+            body: Some(RecipeBody::Raw("{}".into())),
+            ..Recipe::factory(())
+        }
+    )]
+    #[case::get_data(
+        "curl -G https://example.com/search -d q=fish",
+        Recipe {
+            id: "get_search".into(),
+            name: Some("GET /search".into()),
+            url: "https://example.com/search".into(),
+            query: indexmap! {"q".into() => "fish".into()},
+            ..Recipe::factory(())
+        }
+    )]
+    #[case::data_file(
+        "curl https://example.com/upload --data-binary @data.bin \
+        -H 'content-type: application/octet-stream'",
+        Recipe {
+            id: "post_upload".into(),
+            name: Some("POST /upload".into()),
+            method: Method::Post,
+            url: "https://example.com/upload".into(),
+            headers: indexmap! {
+                "content-type".into() => "application/octet-stream".into(),
+            },
+            body: Some(RecipeBody::File("data.bin".into())),
+            ..Recipe::factory(())
+        }
+    )]
+    #[case::multipart(
+        "curl https://example.com/upload -F name=barry \
+        -F 'photo=@fish.png;type=image/png'",
+        Recipe {
+            id: "post_upload".into(),
+            name: Some("POST /upload".into()),
+            method: Method::Post,
+            url: "https://example.com/upload".into(),
+            body: Some(RecipeBody::FormMultipart(indexmap! {
+                "name".into() => MultipartField::Text("barry".into()),
+                "photo".into() => MultipartField::File(MultipartFile {
+                    path: "fish.png".into(),
+                    content_type: Some("image/png".into()),
+                    filename: None,
+                }),
+            })),
+            ..Recipe::factory(())
+        }
+    )]
+    fn test_from_curl(#[case] command: &str, #[case] expected: Recipe) {
+        assert_eq!(Recipe::from_curl(command).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::no_url("curl -XGET", "No URL given")]
+    #[case::multiple_urls("curl a.com b.com", "Multiple URLs given")]
+    #[case::unknown_option(
+        "curl --proxy http://proxy example.com",
+        "Unsupported curl option `--proxy`"
+    )]
+    #[case::missing_value("curl example.com -H", "Missing value for `-H`")]
+    #[case::form_and_data(
+        "curl example.com -F a=b -d c",
+        "`-F` can't be combined with `-d`"
+    )]
+    #[case::bad_quotes("curl 'example.com", "Invalid shell syntax")]
+    fn test_from_curl_error(#[case] command: &str, #[case] expected: &str) {
+        assert_err!(Recipe::from_curl(command), expected);
+    }
+
+    #[rstest]
+    #[case::plain("hi there", "hi+there")]
+    #[case::leading_equals("=a=b", "a%3Db")]
+    #[case::named("note=a&b", "note=a%26b")]
+    fn test_url_encode_data(#[case] value: &str, #[case] expected: &str) {
+        assert_eq!(url_encode_data(value).unwrap(), expected);
+    }
+}
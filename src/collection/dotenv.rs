@@ -0,0 +1,231 @@
+//! Load profile data from `.env` files
+
+use crate::{collection::Collection, template::Template};
+use anyhow::{anyhow, bail, Context};
+use indexmap::IndexMap;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+
+/// Load the `.env` file for each profile that has one, and add its variables
+/// to the profile's data. Explicit `data` fields take precedence over the
+/// file. Paths are relative to `dir`, which should be the directory of the
+/// file that defined the profile. Each profile's `dotenv` field is cleared
+/// after loading, so this is safe to call multiple times. Return the path of
+/// every `.env` file that was referenced, whether or not it exists.
+///
+/// A missing file is *not* an error, because `.env` files are typically not
+/// committed. Each user may or may not have one.
+///
+/// This is blocking, so it should be called from a blocking task.
+pub(super) fn load_dotenv(
+    collection: &mut Collection,
+    dir: &Path,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for profile in collection.profiles.values_mut() {
+        let Some(path) = profile.dotenv.take() else {
+            continue;
+        };
+        let path = dir.join(path);
+        paths.push(path.clone());
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                warn!(
+                    profile_id = %profile.id,
+                    ?path,
+                    "`.env` file does not exist, skipping"
+                );
+                continue;
+            }
+            Err(error) => {
+                return Err(anyhow!(error).context(format!(
+                    "Error loading `.env` file {path:?} for profile `{}`",
+                    profile.id
+                )))
+            }
+        };
+        info!(profile_id = %profile.id, ?path, "Loading `.env` file");
+        let variables = parse_dotenv(&content).with_context(|| {
+            format!(
+                "Error loading `.env` file {path:?} for profile `{}`",
+                profile.id
+            )
+        })?;
+        for (variable, value) in variables {
+            // Values are plain text, *not* templates
+            profile
+                .data
+                .entry(variable)
+                .or_insert_with(|| Template::raw(value));
+        }
+    }
+    Ok(paths)
+}
+
+/// Parse the contents of a `.env` file. Supported syntax:
+/// - `KEY=value`, with an optional `export ` prefix
+/// - Blank lines and `#` comments
+/// - Unquoted values, which are trimmed and end at a ` #` comment
+/// - Single-quoted values, which are taken literally
+/// - Double-quoted values, which support `\n`, `\t`, `\"`, and `\\` escapes
+fn parse_dotenv(content: &str) -> anyhow::Result<IndexMap<String, String>> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                None
+            } else {
+                Some(
+                    parse_line(line).with_context(|| format!("Line {}", i + 1)),
+                )
+            }
+        })
+        .collect()
+}
+
+/// Parse a single non-empty, non-comment line
+fn parse_line(line: &str) -> anyhow::Result<(String, String)> {
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    let Some((variable, value)) = line.split_once('=') else {
+        bail!("Expected `KEY=value`");
+    };
+    let variable = variable.trim();
+    if variable.is_empty()
+        || !variable.chars().all(|c| c.is_alphanumeric() || c == '_')
+    {
+        bail!("Invalid variable name `{variable}`");
+    }
+
+    let value = value.trim_start();
+    let value = if let Some(value) = value.strip_prefix('\'') {
+        let Some((value, _)) = value.split_once('\'') else {
+            bail!("Unclosed `'`");
+        };
+        value.to_owned()
+    } else if let Some(value) = value.strip_prefix('"') {
+        let mut parsed = String::with_capacity(value.len());
+        let mut chars = value.chars();
+        loop {
+            match chars.next() {
+                Some('"') => break,
+                Some('\\') => match chars.next() {
+                    Some('n') => parsed.push('\n'),
+                    Some('t') => parsed.push('\t'),
+                    Some(c @ ('"' | '\\')) => parsed.push(c),
+                    Some(c) => {
+                        parsed.push('\\');
+                        parsed.push(c);
+                    }
+                    None => bail!("Unclosed `\"`"),
+                },
+                Some(c) => parsed.push(c),
+                None => bail!("Unclosed `\"`"),
+            }
+        }
+        parsed
+    } else {
+        // Unquoted values can have a trailing comment
+        let value = match value.find(" #") {
+            Some(index) => &value[..index],
+            None => value,
+        };
+        value.trim_end().to_owned()
+    };
+    Ok((variable.to_owned(), value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collection::{Profile, ProfileId},
+        test_util::{assert_err, temp_dir, Factory, TempDir},
+    };
+    use indexmap::indexmap;
+    use rstest::rstest;
+
+    #[test]
+    fn test_parse_dotenv() {
+        let content = r#"
+# Comment
+TOKEN=abc123
+export USER = fish  # trailing comment
+EMPTY=
+SINGLE='{{literal}} # not a comment'
+DOUBLE="line1\nline2 \"quoted\""
+URL=http://localhost:3000/#anchor
+"#;
+        assert_eq!(
+            parse_dotenv(content).unwrap(),
+            indexmap! {
+                "TOKEN".into() => String::from("abc123"),
+                "USER".into() => String::from("fish"),
+                "EMPTY".into() => String::from(""),
+                "SINGLE".into() => String::from("{{literal}} # not a comment"),
+                "DOUBLE".into() => String::from("line1\nline2 \"quoted\""),
+                "URL".into() => String::from("http://localhost:3000/#anchor"),
+            }
+        );
+    }
+
+    #[rstest]
+    #[case::no_equals("TOKEN", "Line 1: Expected `KEY=value`")]
+    #[case::invalid_name("MY-TOKEN=1", "Invalid variable name `MY-TOKEN`")]
+    #[case::empty_name("=1", "Invalid variable name ``")]
+    #[case::unclosed_single("\nA='abc", "Line 2: Unclosed `'`")]
+    #[case::unclosed_double("A=\"abc", "Unclosed `\"`")]
+    fn test_parse_dotenv_error(
+        #[case] content: &str,
+        #[case] expected_error: &str,
+    ) {
+        assert_err!(parse_dotenv(content), expected_error);
+    }
+
+    #[rstest]
+    fn test_load_dotenv(temp_dir: TempDir) {
+        fs::write(temp_dir.join(".env"), "TOKEN=from_file\nUSER={{user}}")
+            .unwrap();
+        let profile = Profile {
+            dotenv: Some(".env".into()),
+            data: indexmap! {"TOKEN".into() => "explicit".into()},
+            ..Profile::factory(())
+        };
+        let missing = Profile {
+            id: "missing".into(),
+            dotenv: Some("missing.env".into()),
+            ..Profile::factory(())
+        };
+        let mut collection = Collection {
+            profiles: indexmap! {
+                profile.id.clone() => profile,
+                missing.id.clone() => missing,
+            },
+            ..Collection::default()
+        };
+
+        let paths = load_dotenv(&mut collection, &temp_dir).unwrap();
+        assert_eq!(
+            paths,
+            [temp_dir.join(".env"), temp_dir.join("missing.env")]
+        );
+        let profile = &collection.profiles[&ProfileId::from("profile1")];
+        assert_eq!(profile.dotenv, None);
+        assert_eq!(
+            profile.data,
+            indexmap! {
+                "TOKEN".into() => "explicit".into(),
+                "USER".into() => Template::raw("{{user}}".into()),
+            }
+        );
+        assert_eq!(
+            collection.profiles[&ProfileId::from("missing")].data,
+            indexmap! {}
+        );
+    }
+}
@@ -0,0 +1,2001 @@
+//! Programmatic edits to a collection file. Edits are made to the raw YAML
+//! text rather than by re-serializing the collection, so that comments,
+//! anchors, and formatting are all preserved.
+
+use crate::collection::{
+    CollectionFile, Recipe, RecipeBody, RecipeId, RecipeNode,
+};
+use anyhow::{anyhow, bail, Context};
+use std::{fs, ops::Range};
+use tracing::info;
+
+/// Suffix appended to the ID of a duplicated recipe
+const COPY_ID_SUFFIX: &str = "_copy";
+/// Suffix appended to the display name of a duplicated recipe
+const COPY_NAME_SUFFIX: &str = " (Copy)";
+/// Indentation added for a new level of nesting
+const INDENT: &str = "  ";
+/// Max number of characters to show on either side of a match in a preview
+const PREVIEW_CONTEXT: usize = 30;
+
+/// A bulk modification to request headers across the collection. Header names
+/// are matched case-insensitively.
+#[derive(Clone, Debug)]
+pub enum HeaderEdit {
+    /// Rename a header, keeping its value
+    Rename { from: String, to: String },
+    /// Set a header in every recipe, replacing its value if already present
+    Set { name: String, value: String },
+    /// Remove a header
+    Remove { name: String },
+}
+
+/// Outcome of [CollectionFile::edit_headers]
+#[derive(Debug, Default, PartialEq)]
+pub struct HeaderEditReport {
+    /// Number of headers renamed, set, or removed
+    pub changes: usize,
+    /// Recipes that couldn't be modified because they inherit their headers
+    /// from elsewhere. Defining `headers` in these recipes would override
+    /// *all* inherited headers, because YAML merges are shallow.
+    pub skipped: Vec<RecipeId>,
+}
+
+/// An occurrence of some text in a collection file, found by [find_text]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextMatch {
+    /// Byte offset of the match within the file
+    offset: usize,
+    /// Length of the match, in bytes
+    len: usize,
+    /// 1-indexed line number of the match
+    pub line_number: usize,
+    /// Text of the line containing the match, excluding the newline
+    line: String,
+    /// Byte offset of the match within its line
+    column: usize,
+}
+
+impl TextMatch {
+    /// Show the match in the context of its line, along with what it would be
+    /// replaced with, e.g. `url: {{host}}/[v1 → v2]/users`. Long lines are
+    /// trimmed down to the area around the match.
+    pub fn preview(&self, replace: &str) -> String {
+        let before = self.line[..self.column].trim_start();
+        let found = &self.line[self.column..self.column + self.len];
+        let after = self.line[self.column + self.len..].trim_end();
+
+        let before_chars = before.chars().count();
+        let before = if before_chars > PREVIEW_CONTEXT {
+            let skip = before_chars - PREVIEW_CONTEXT;
+            format!("…{}", before.chars().skip(skip).collect::<String>())
+        } else {
+            before.to_owned()
+        };
+        let after = if after.chars().count() > PREVIEW_CONTEXT {
+            format!(
+                "{}…",
+                after.chars().take(PREVIEW_CONTEXT).collect::<String>()
+            )
+        } else {
+            after.to_owned()
+        };
+        format!("{before}[{found} → {replace}]{after}")
+    }
+}
+
+/// Find every occurrence of some text in a collection file. Matching is exact
+/// and case-sensitive. Pass the matches to [replace_text] to replace some or
+/// all of them.
+pub fn find_text(content: &str, find: &str) -> Vec<TextMatch> {
+    if find.is_empty() {
+        return vec![];
+    }
+    content
+        .match_indices(find)
+        .map(|(offset, _)| {
+            let line_start =
+                content[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+            let line_end = content[offset..]
+                .find('\n')
+                .map(|i| i + offset)
+                .unwrap_or(content.len());
+            TextMatch {
+                offset,
+                len: find.len(),
+                line_number: content[..offset].matches('\n').count() + 1,
+                line: content[line_start..line_end].to_owned(),
+                column: offset - line_start,
+            }
+        })
+        .collect()
+}
+
+/// Replace the given matches (from [find_text]) with new text. Returns an
+/// error if the content doesn't match what the matches were found in, e.g.
+/// because the file was modified in the meantime.
+pub fn replace_text(
+    content: &str,
+    replace: &str,
+    matches: &[TextMatch],
+) -> anyhow::Result<String> {
+    let mut output = String::with_capacity(content.len());
+    let mut position = 0;
+    let mut matches = matches.iter().collect::<Vec<_>>();
+    matches.sort_by_key(|m| m.offset);
+    for m in matches {
+        let found = &m.line[m.column..m.column + m.len];
+        if m.offset < position
+            || content.get(m.offset..m.offset + m.len) != Some(found)
+        {
+            bail!(
+                "Text on line {} has changed since it was searched",
+                m.line_number
+            );
+        }
+        output.push_str(&content[position..m.offset]);
+        output.push_str(replace);
+        position = m.offset + m.len;
+    }
+    output.push_str(&content[position..]);
+    Ok(output)
+}
+
+impl CollectionFile {
+    /// Duplicate a recipe in the collection file. The copy will be given a
+    /// new unique ID, and inserted directly after the original. Returns the
+    /// ID of the new recipe. The in-memory collection is *not* modified; the
+    /// caller is responsible for reloading it.
+    pub fn duplicate_recipe(
+        &self,
+        recipe_id: &RecipeId,
+    ) -> anyhow::Result<RecipeId> {
+        self.check_recipe(recipe_id, "duplicated")?;
+
+        // Find the first unused ID of the form `<id>_copy`, `<id>_copy2`, ...
+        let new_id: RecipeId = (1..)
+            .map(|i| {
+                let suffix = if i == 1 { String::new() } else { i.to_string() };
+                RecipeId::from(format!("{recipe_id}{COPY_ID_SUFFIX}{suffix}"))
+            })
+            .find(|id| self.collection.recipes.get(id).is_none())
+            .expect("Infinite iterator always finds an ID");
+
+        self.edit(|content| duplicate_node(content, recipe_id, &new_id))?;
+        info!(%recipe_id, %new_id, path = ?self.path(), "Duplicated recipe");
+        Ok(new_id)
+    }
+
+    /// Apply a series of header edits to every recipe in the collection file,
+    /// in order. Headers defined in shared mappings outside of any recipe
+    /// (e.g. a base referenced via YAML anchors) are renamed and removed too.
+    /// The in-memory collection is *not* modified; the caller is responsible
+    /// for reloading it.
+    pub fn edit_headers(
+        &self,
+        edits: &[HeaderEdit],
+    ) -> anyhow::Result<HeaderEditReport> {
+        let mut report = HeaderEditReport::default();
+        self.edit(|content| {
+            let (content, r) = edit_headers(content, edits)?;
+            report = r;
+            Ok(content)
+        })?;
+        info!(?edits, ?report, path = ?self.path(), "Edited headers");
+        Ok(report)
+    }
+
+    /// Add a new recipe to the end of the collection's top-level `requests`
+    /// section. Only the name, method, URL, headers, and body are written. The
+    /// in-memory collection is *not* modified; the caller is responsible for
+    /// reloading it.
+    pub fn add_recipe(&self, recipe: &Recipe) -> anyhow::Result<()> {
+        if self.collection.recipes.get(&recipe.id).is_some() {
+            bail!("Recipe ID `{}` is already in use", recipe.id);
+        }
+        self.edit(|content| add_node(content, recipe))?;
+        info!(recipe_id = %recipe.id, path = ?self.path(), "Added recipe");
+        Ok(())
+    }
+
+    /// Add an empty folder to the end of a folder's `requests`, or to the
+    /// top-level `requests` section if no parent is given. The in-memory
+    /// collection is *not* modified; the caller is responsible for reloading
+    /// it.
+    pub fn add_folder(
+        &self,
+        folder_id: &RecipeId,
+        parent_id: Option<&RecipeId>,
+    ) -> anyhow::Result<()> {
+        if folder_id.is_empty() {
+            bail!("Folder ID cannot be empty");
+        }
+        if self.collection.recipes.get(folder_id).is_some() {
+            bail!("Recipe ID `{folder_id}` is already in use");
+        }
+        if let Some(parent_id) = parent_id {
+            self.check_folder(parent_id)?;
+        }
+        self.edit(|content| add_folder_node(content, folder_id, parent_id))?;
+        info!(%folder_id, ?parent_id, path = ?self.path(), "Added folder");
+        Ok(())
+    }
+
+    /// Move a recipe or folder to the end of another folder, or to the
+    /// top-level `requests` section if no folder is given. Folders are moved
+    /// along with everything in them. The in-memory collection is *not*
+    /// modified; the caller is responsible for reloading it.
+    pub fn move_recipe(
+        &self,
+        recipe_id: &RecipeId,
+        folder_id: Option<&RecipeId>,
+    ) -> anyhow::Result<()> {
+        let recipes = &self.collection.recipes;
+        let lookup_key = recipes
+            .get_lookup_key(recipe_id)
+            .ok_or_else(|| anyhow!("No recipe with ID `{recipe_id}`"))?;
+        if let Some(folder_id) = folder_id {
+            self.check_folder(folder_id)?;
+            let folder_key = recipes
+                .get_lookup_key(folder_id)
+                .expect("Folder is in the tree");
+            if folder_key.as_slice().contains(recipe_id) {
+                bail!("Cannot move `{recipe_id}` into itself");
+            }
+        }
+        let [parents @ .., _] = lookup_key.as_slice() else {
+            unreachable!("Lookup key cannot be empty")
+        };
+        if parents.last() == folder_id {
+            bail!("`{recipe_id}` is already there");
+        }
+
+        self.edit(|content| move_node(content, recipe_id, folder_id))?;
+        info!(%recipe_id, ?folder_id, path = ?self.path(), "Moved recipe");
+        Ok(())
+    }
+
+    /// Change the ID of a recipe or folder in the collection file. References
+    /// to the recipe from chains (`recipe: <id>`) and other recipes
+    /// (`extends: <id>`) are updated too. The in-memory collection is *not*
+    /// modified; the caller is responsible for reloading it.
+    pub fn rename_recipe(
+        &self,
+        recipe_id: &RecipeId,
+        new_id: &RecipeId,
+    ) -> anyhow::Result<()> {
+        if self.collection.recipes.get(recipe_id).is_none() {
+            bail!("No recipe with ID `{recipe_id}`");
+        }
+        if new_id.is_empty() {
+            bail!("Recipe ID cannot be empty");
+        }
+        if self.collection.recipes.get(new_id).is_some() {
+            bail!("Recipe ID `{new_id}` is already in use");
+        }
+        self.edit(|content| rename_node(content, recipe_id, new_id))?;
+        info!(%recipe_id, %new_id, path = ?self.path(), "Renamed recipe");
+        Ok(())
+    }
+
+    /// Remove a recipe from the collection file, along with any comments
+    /// directly above it. This fails if anything else in the file refers to
+    /// the recipe, because removing it would break the collection. The
+    /// in-memory collection is *not* modified; the caller is responsible for
+    /// reloading it.
+    pub fn delete_recipe(&self, recipe_id: &RecipeId) -> anyhow::Result<()> {
+        self.check_recipe(recipe_id, "deleted")?;
+        self.edit(|content| delete_node(content, recipe_id))?;
+        info!(%recipe_id, path = ?self.path(), "Deleted recipe");
+        Ok(())
+    }
+
+    /// Make sure an ID refers to a recipe (not a folder) in the collection
+    fn check_recipe(
+        &self,
+        recipe_id: &RecipeId,
+        verb: &str,
+    ) -> anyhow::Result<()> {
+        match self.collection.recipes.get(recipe_id) {
+            Some(RecipeNode::Recipe(_)) => Ok(()),
+            Some(RecipeNode::Folder(_)) => {
+                bail!("`{recipe_id}` is a folder; only recipes can be {verb}")
+            }
+            None => bail!("No recipe with ID `{recipe_id}`"),
+        }
+    }
+
+    /// Make sure an ID refers to a folder (not a recipe) in the collection
+    fn check_folder(&self, folder_id: &RecipeId) -> anyhow::Result<()> {
+        match self.collection.recipes.get(folder_id) {
+            Some(RecipeNode::Folder(_)) => Ok(()),
+            Some(RecipeNode::Recipe(_)) => {
+                bail!("`{folder_id}` is a recipe, not a folder")
+            }
+            None => bail!("No folder with ID `{folder_id}`"),
+        }
+    }
+
+    /// Read the collection file, modify its text, then write it back
+    fn edit(
+        &self,
+        f: impl FnOnce(&str) -> anyhow::Result<String>,
+    ) -> anyhow::Result<()> {
+        let path = self.path();
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Error reading {path:?}"))?;
+        let edited = f(&content)?;
+        if edited != content {
+            fs::write(path, edited)
+                .with_context(|| format!("Error writing {path:?}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Copy the YAML block for a recipe, giving it a new ID, and insert it after
+/// the original
+fn duplicate_node(
+    content: &str,
+    recipe_id: &RecipeId,
+    new_id: &RecipeId,
+) -> anyhow::Result<String> {
+    let mut lines = split_lines(content);
+    let recipe = find_recipe(&lines, recipe_id)?;
+
+    let mut copy: Vec<String> = Vec::with_capacity(recipe.len() + 1);
+    copy.push("\n".into());
+    // The key line gets the new ID. Drop any anchor, because anchors have to
+    // be unique and we don't want to steal references from the original
+    let (_, rest) =
+        split_key(&lines[recipe.start]).expect("Key already parsed");
+    let rest = strip_anchor(rest.trim_start());
+    copy.push(format!("{}{new_id}: {rest}", recipe.indent_str(&lines)));
+
+    // Mark the name of the copy so the two can be told apart
+    let name = children(&lines, &recipe)
+        .into_iter()
+        .find(|entry| entry.key == "name");
+    for (i, line) in lines
+        .iter()
+        .enumerate()
+        .take(recipe.end)
+        .skip(recipe.start + 1)
+    {
+        let line = match (&name, split_key(line)) {
+            (Some(name), Some((_, value))) if name.start == i => {
+                append_to_value(line, value, COPY_NAME_SUFFIX)
+                    .unwrap_or_else(|| line.clone())
+            }
+            _ => line.clone(),
+        };
+        copy.push(line);
+    }
+
+    lines.splice(recipe.end..recipe.end, copy);
+    Ok(lines.concat())
+}
+
+/// Change the key of a recipe's YAML block, and update all references to it
+fn rename_node(
+    content: &str,
+    recipe_id: &RecipeId,
+    new_id: &RecipeId,
+) -> anyhow::Result<String> {
+    let mut lines = split_lines(content);
+    let recipe = find_node(&lines, recipe_id)?;
+    let new_id = yaml_scalar(new_id.as_str());
+
+    // Everything after the colon (anchor, tag, comment) stays the same
+    let (_, rest) =
+        split_key(&lines[recipe.start]).expect("Key already parsed");
+    lines[recipe.start] =
+        format!("{}{new_id}:{rest}", recipe.indent_str(&lines));
+    for i in references(&lines, recipe_id) {
+        let (_, value) = split_key(&lines[i]).expect("Key already parsed");
+        lines[i] = replace_value(&lines[i], value, &new_id)
+            .expect("Reference is a scalar");
+    }
+    Ok(lines.concat())
+}
+
+/// Remove a recipe's YAML block, along with any comments directly above it
+fn delete_node(content: &str, recipe_id: &RecipeId) -> anyhow::Result<String> {
+    let mut lines = split_lines(content);
+    let recipe = find_recipe(&lines, recipe_id)?;
+    if let Some(i) = references(&lines, recipe_id).first() {
+        bail!(
+            "`{recipe_id}` is referenced on line {}; remove the reference \
+            before deleting it",
+            i + 1
+        );
+    }
+    // Aliases to an anchor defined in the recipe would be left dangling
+    for i in key_lines(&lines, recipe.start, recipe.end) {
+        let (_, value) = split_key(&lines[i]).expect("Key already parsed");
+        let Some(anchor) = value.trim_start().strip_prefix('&') else {
+            continue;
+        };
+        let anchor = anchor.split_whitespace().next().unwrap_or_default();
+        let alias = format!("*{anchor}");
+        if lines[..recipe.start]
+            .iter()
+            .chain(&lines[recipe.end..])
+            .any(|line| line.contains(&alias))
+        {
+            bail!(
+                "`{recipe_id}` defines the anchor `&{anchor}`, which is used \
+                elsewhere in the file"
+            );
+        }
+    }
+
+    remove_node(&mut lines, &recipe);
+    Ok(lines.concat())
+}
+
+/// Remove a node's YAML block, along with any comments directly above it.
+/// Surrounding blank lines are cleaned up, and a `requests` mapping left
+/// empty is replaced with `{}`. Returns the removed lines.
+fn remove_node(lines: &mut Vec<String>, recipe: &Entry) -> Vec<String> {
+    let key_indent = indent(&lines[recipe.start]);
+    let mut start = recipe.start;
+    while start > 0
+        && !is_content(&lines[start - 1])
+        && !lines[start - 1].trim().is_empty()
+        && indent(&lines[start - 1]) == key_indent
+    {
+        start -= 1;
+    }
+    let removed = lines.drain(start..recipe.end).collect();
+    // Don't leave behind a double blank line, or a leading/trailing one
+    let is_first = start == 0
+        || (is_content(&lines[start - 1])
+            && indent(&lines[start - 1]) < key_indent);
+    let has_sibling = lines[start..]
+        .iter()
+        .find(|line| is_content(line))
+        .is_some_and(|line| indent(line) >= key_indent);
+    while is_first
+        && has_sibling
+        && lines.get(start).is_some_and(|line| line.trim().is_empty())
+    {
+        lines.remove(start);
+    }
+    while start > 0
+        && lines[start - 1].trim().is_empty()
+        && lines.get(start).map_or(true, |line| line.trim().is_empty())
+    {
+        lines.remove(start - 1);
+        start -= 1;
+    }
+
+    // An empty block mapping would be null, which isn't a valid `requests`
+    let parent = (0..start)
+        .rev()
+        .find(|&i| is_content(&lines[i]) && indent(&lines[i]) < key_indent);
+    if let Some(parent) = parent {
+        if block_end(lines, parent) == parent + 1 {
+            if let Some((_, rest)) = split_key(&lines[parent]) {
+                let line = &lines[parent];
+                lines[parent] =
+                    format!("{} {{}}{rest}", &line[..line.len() - rest.len()]);
+            }
+        }
+    }
+    removed
+}
+
+/// Append a recipe to the end of the top-level `requests` section, creating
+/// the section if necessary
+fn add_node(content: &str, recipe: &Recipe) -> anyhow::Result<String> {
+    let mut lines = split_lines(content);
+    let (insert_at, indent_str) = requests_insert_point(&mut lines, None)?;
+    let mut node = separator(&lines, insert_at, &indent_str);
+    node.push(format!(
+        "{indent_str}{}: !request\n",
+        yaml_scalar(recipe.id.as_str())
+    ));
+    let field_indent = format!("{indent_str}{INDENT}");
+    if let Some(name) = &recipe.name {
+        node.push(format!("{field_indent}name: {}\n", yaml_scalar(name)));
+    }
+    node.push(format!("{field_indent}method: {}\n", recipe.method));
+    node.push(format!(
+        "{field_indent}url: {}\n",
+        yaml_scalar(recipe.url.as_str())
+    ));
+    if !recipe.headers.is_empty() {
+        node.push(format!("{field_indent}headers:\n"));
+        for (name, value) in &recipe.headers {
+            node.push(format!(
+                "{field_indent}{INDENT}{}: {}\n",
+                yaml_scalar(name),
+                yaml_scalar(value.as_str())
+            ));
+        }
+    }
+    if let Some(body) = &recipe.body {
+        let RecipeBody::Raw(body) = body else {
+            bail!("Cannot save structured body for recipe `{}`", recipe.id);
+        };
+        let body = body.as_str();
+        // Multi-line bodies (e.g. pretty JSON) are much more readable as a
+        // literal block. A leading space would require an explicit indentation
+        // indicator, so just quote those.
+        if body.contains('\n') && !body.starts_with(' ') {
+            // Pick a chomping indicator to preserve trailing newlines
+            let (body, chomp) = match body.strip_suffix('\n') {
+                Some(body) if body.ends_with('\n') => (body, "+"),
+                Some(body) => (body, ""),
+                None => (body, "-"),
+            };
+            node.push(format!("{field_indent}body: |{chomp}\n"));
+            for line in body.split('\n') {
+                if line.is_empty() {
+                    node.push("\n".into());
+                } else {
+                    node.push(format!("{field_indent}{INDENT}{line}\n"));
+                }
+            }
+        } else {
+            node.push(format!("{field_indent}body: {}\n", yaml_scalar(body)));
+        }
+    }
+
+    lines.splice(insert_at..insert_at, node);
+    Ok(lines.concat())
+}
+
+/// Add an empty folder to the end of a folder's `requests`, or the top-level
+/// `requests` section if no parent is given
+fn add_folder_node(
+    content: &str,
+    folder_id: &RecipeId,
+    parent_id: Option<&RecipeId>,
+) -> anyhow::Result<String> {
+    let mut lines = split_lines(content);
+    let (insert_at, indent_str) = requests_insert_point(&mut lines, parent_id)?;
+    let mut node = separator(&lines, insert_at, &indent_str);
+    node.push(format!(
+        "{indent_str}{}: !folder\n",
+        yaml_scalar(folder_id.as_str())
+    ));
+    node.push(format!("{indent_str}{INDENT}requests: {{}}\n"));
+    lines.splice(insert_at..insert_at, node);
+    Ok(lines.concat())
+}
+
+/// Move a recipe or folder's YAML block, along with any comments directly
+/// above it, to the end of a folder's `requests`. If no folder is given, move
+/// it to the end of the top-level `requests` section.
+fn move_node(
+    content: &str,
+    recipe_id: &RecipeId,
+    folder_id: Option<&RecipeId>,
+) -> anyhow::Result<String> {
+    let mut lines = split_lines(content);
+    let node = find_node(&lines, recipe_id)?;
+    let old_indent = indent(&lines[node.start]);
+    let removed = remove_node(&mut lines, &node);
+
+    let (insert_at, indent_str) = requests_insert_point(&mut lines, folder_id)?;
+    let mut node = separator(&lines, insert_at, &indent_str);
+    // Shift the whole block, so relative indentation (e.g. in block scalars)
+    // is preserved
+    node.extend(removed.into_iter().map(|line| {
+        if line.trim().is_empty() {
+            line
+        } else {
+            let stripped = &line[indent(&line).min(old_indent)..];
+            format!("{indent_str}{stripped}")
+        }
+    }));
+    lines.splice(insert_at..insert_at, node);
+    let output = lines.concat();
+
+    // Anchors have to be defined before they're used, so moving a block can
+    // break aliases
+    serde_yaml::from_str::<serde_yaml::Value>(&output).with_context(|| {
+        format!("Moving `{recipe_id}` would make the collection file invalid")
+    })?;
+    Ok(output)
+}
+
+/// Apply a series of header edits to the file, returning the modified content
+fn edit_headers(
+    content: &str,
+    edits: &[HeaderEdit],
+) -> anyhow::Result<(String, HeaderEditReport)> {
+    let mut lines = split_lines(content);
+    let mut report = HeaderEditReport::default();
+    for edit in edits {
+        match edit {
+            HeaderEdit::Rename { from, to } => {
+                for mapping in header_mappings(&lines) {
+                    let headers = children(&lines, &mapping);
+                    let Some(header) =
+                        headers.iter().find(|header| header.is_header(from))
+                    else {
+                        continue;
+                    };
+                    if headers.iter().any(|other| {
+                        other.start != header.start && other.is_header(to)
+                    }) {
+                        bail!(
+                            "Cannot rename `{from}` to `{to}` on line {}; \
+                            `{to}` is already defined",
+                            header.start + 1
+                        );
+                    }
+                    let (_, rest) = split_key(&lines[header.start])
+                        .expect("Key already parsed");
+                    let new_line = format!(
+                        "{}{}:{rest}",
+                        header.indent_str(&lines),
+                        yaml_scalar(to)
+                    );
+                    lines[header.start] = new_line;
+                    report.changes += 1;
+                }
+            }
+
+            HeaderEdit::Set { name, value } => {
+                let mut skipped = Vec::new();
+                // Go bottom-up, so insertions don't invalidate line numbers
+                for recipe in recipe_entries(&lines)?.into_iter().rev() {
+                    let fields = children(&lines, &recipe);
+                    let Some(first) = fields.first() else {
+                        // Recipe is empty or defined inline; leave it alone
+                        skipped.push(recipe.key.into());
+                        continue;
+                    };
+                    let field_indent = first.indent_str(&lines).to_owned();
+
+                    let new_lines = match fields
+                        .iter()
+                        .find(|field| field.key == "headers")
+                    {
+                        Some(mapping) if mapping.is_block(&lines) => {
+                            let headers = children(&lines, mapping);
+                            let header_indent = headers
+                                .first()
+                                .map(|header| header.indent_str(&lines).into())
+                                .unwrap_or_else(|| {
+                                    format!("{field_indent}{INDENT}")
+                                });
+                            if let Some(header) =
+                                headers.iter().find(|h| h.is_header(name))
+                            {
+                                // Keep the existing spelling of the name
+                                let line = &lines[header.start];
+                                let (_, rest) = split_key(line)
+                                    .expect("Key already parsed");
+                                let key = &line[..line.len() - rest.len()];
+                                let new_line =
+                                    format!("{key} {}\n", yaml_scalar(value));
+                                lines.splice(
+                                    header.start..header.end,
+                                    [new_line],
+                                );
+                                report.changes += 1;
+                                continue;
+                            }
+                            (
+                                mapping.end,
+                                vec![format!(
+                                    "{header_indent}{}: {}\n",
+                                    yaml_scalar(name),
+                                    yaml_scalar(value)
+                                )],
+                            )
+                        }
+                        // Flow mappings, anchors, etc.
+                        Some(_) => {
+                            skipped.push(recipe.key.into());
+                            continue;
+                        }
+                        None if fields
+                            .iter()
+                            .any(|field| field.key == "<<") =>
+                        {
+                            skipped.push(recipe.key.into());
+                            continue;
+                        }
+                        None => (
+                            recipe.end,
+                            vec![
+                                format!("{field_indent}headers:\n"),
+                                format!(
+                                    "{field_indent}{INDENT}{}: {}\n",
+                                    yaml_scalar(name),
+                                    yaml_scalar(value)
+                                ),
+                            ],
+                        ),
+                    };
+                    let (index, new_lines) = new_lines;
+                    lines.splice(index..index, new_lines);
+                    report.changes += 1;
+                }
+                // Recipes were visited bottom-up, so flip them back to file
+                // order
+                skipped.reverse();
+                report.skipped.extend(skipped);
+            }
+
+            HeaderEdit::Remove { name } => {
+                // Go bottom-up, so removals don't invalidate line numbers
+                for mapping in header_mappings(&lines).into_iter().rev() {
+                    let headers = children(&lines, &mapping);
+                    let removed: Vec<&Entry> =
+                        headers.iter().filter(|h| h.is_header(name)).collect();
+                    if removed.is_empty() {
+                        continue;
+                    }
+                    report.changes += removed.len();
+
+                    if removed.len() == headers.len() {
+                        // Remove the now-empty mapping entirely. If it's
+                        // anchored, something else may reference it so leave
+                        // an empty mapping behind
+                        let (_, rest) = split_key(&lines[mapping.start])
+                            .expect("Key already parsed");
+                        let replacement = if rest.trim_start().starts_with('&')
+                        {
+                            vec![format!(
+                                "{}{{}}\n",
+                                lines[mapping.start].trim_end()
+                            )]
+                        } else {
+                            vec![]
+                        };
+                        lines.splice(mapping.start..mapping.end, replacement);
+                    } else {
+                        for header in removed.into_iter().rev() {
+                            lines.drain(header.start..header.end);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok((lines.concat(), report))
+}
+
+/// A `key: value` entry in a YAML mapping, located by its line range
+/// `[start, end)`. `start` is the line containing the key, and `end` excludes
+/// any trailing blank lines or comments, which belong to whatever comes next.
+#[derive(Debug)]
+struct Entry {
+    key: String,
+    start: usize,
+    end: usize,
+}
+
+impl Entry {
+    /// Number of lines in the entry
+    fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Leading whitespace of the key line
+    fn indent_str<'a>(&self, lines: &'a [String]) -> &'a str {
+        let line = &lines[self.start];
+        &line[..indent(line)]
+    }
+
+    /// Is the value of this entry a block mapping (as opposed to a scalar or
+    /// a flow mapping)?
+    fn is_block(&self, lines: &[String]) -> bool {
+        let (_, rest) =
+            split_key(&lines[self.start]).expect("Key already parsed");
+        let rest = strip_anchor(rest.trim());
+        rest.is_empty() || rest.starts_with('#')
+    }
+
+    /// Does this entry's key match a header name?
+    fn is_header(&self, name: &str) -> bool {
+        self.key.eq_ignore_ascii_case(name)
+    }
+}
+
+/// Split file content into lines, each including its trailing newline
+fn split_lines(content: &str) -> Vec<String> {
+    let mut lines: Vec<String> =
+        content.split_inclusive('\n').map(String::from).collect();
+    if let Some(last) = lines.last_mut() {
+        if !last.ends_with('\n') {
+            last.push('\n');
+        }
+    }
+    lines
+}
+
+/// Find a top-level section of the file, e.g. `requests`
+fn section(lines: &[String], key: &str) -> Option<Entry> {
+    let start = lines.iter().position(|line| {
+        indent(line) == 0
+            && split_key(line).is_some_and(|(other, _)| other == key)
+    })?;
+    Some(Entry {
+        key: key.to_owned(),
+        start,
+        end: block_end(lines, start),
+    })
+}
+
+/// Find every recipe definition in the top-level `requests` section,
+/// including those nested in folders
+fn recipe_entries(lines: &[String]) -> anyhow::Result<Vec<Entry>> {
+    tagged_entries(lines, &["!request", "!websocket"])
+}
+
+/// Find every recipe and folder definition in the top-level `requests`
+/// section, including those nested in folders
+fn node_entries(lines: &[String]) -> anyhow::Result<Vec<Entry>> {
+    tagged_entries(lines, &["!request", "!websocket", "!folder"])
+}
+
+/// Find every entry in the top-level `requests` section whose value has one
+/// of the given tags
+fn tagged_entries(
+    lines: &[String],
+    tags: &[&str],
+) -> anyhow::Result<Vec<Entry>> {
+    let section = section(lines, "requests")
+        .ok_or_else(|| anyhow!("Collection has no `requests` section"))?;
+
+    Ok(key_lines(lines, section.start + 1, section.end)
+        .filter_map(|i| {
+            let (key, rest) = split_key(&lines[i])?;
+            let tag = strip_anchor(rest.trim_start());
+            tags.iter().any(|t| tag.starts_with(t)).then(|| Entry {
+                key: key.to_owned(),
+                start: i,
+                end: block_end(lines, i),
+            })
+        })
+        .collect())
+}
+
+/// Find the definition of a single recipe
+fn find_recipe(
+    lines: &[String],
+    recipe_id: &RecipeId,
+) -> anyhow::Result<Entry> {
+    find_entry(recipe_entries(lines)?, recipe_id)
+}
+
+/// Find the definition of a single recipe or folder
+fn find_node(lines: &[String], recipe_id: &RecipeId) -> anyhow::Result<Entry> {
+    find_entry(node_entries(lines)?, recipe_id)
+}
+
+fn find_entry(
+    entries: Vec<Entry>,
+    recipe_id: &RecipeId,
+) -> anyhow::Result<Entry> {
+    entries
+        .into_iter()
+        .find(|entry| entry.key == recipe_id.as_str())
+        .ok_or_else(|| {
+            anyhow!("Could not find definition of `{recipe_id}` in file")
+        })
+}
+
+/// Get the position and indentation for a new node at the end of a folder's
+/// `requests`, or the top-level `requests` section if no folder is given.
+/// Missing or empty (`{}`) `requests` mappings are created/expanded as needed.
+fn requests_insert_point(
+    lines: &mut Vec<String>,
+    folder_id: Option<&RecipeId>,
+) -> anyhow::Result<(usize, String)> {
+    let requests = if let Some(folder_id) = folder_id {
+        let folder = find_node(lines, folder_id)?;
+        let requests = children(lines, &folder)
+            .into_iter()
+            .find(|child| child.key == "requests");
+        match requests {
+            Some(requests) => requests,
+            // Add the mapping to the end of the folder
+            None => {
+                let indent_str = children(lines, &folder)
+                    .first()
+                    .map(|child| child.indent_str(lines).to_owned())
+                    .unwrap_or_else(|| {
+                        format!("{}{INDENT}", folder.indent_str(lines))
+                    });
+                lines.insert(folder.end, format!("{indent_str}requests:\n"));
+                Entry {
+                    key: "requests".into(),
+                    start: folder.end,
+                    end: folder.end + 1,
+                }
+            }
+        }
+    } else {
+        match section(lines, "requests") {
+            Some(section) => section,
+            None => {
+                if lines.last().is_some_and(|line| is_content(line)) {
+                    lines.push("\n".into());
+                }
+                lines.push("requests:\n".into());
+                return Ok((lines.len(), INDENT.to_owned()));
+            }
+        }
+    };
+
+    // An empty flow mapping can be turned into a block mapping
+    let (_, rest) =
+        split_key(&lines[requests.start]).expect("Key already parsed");
+    if let Some(after) = rest.trim_start().strip_prefix("{}") {
+        let line = &lines[requests.start];
+        let new_line = format!("{}{after}", &line[..line.len() - rest.len()]);
+        lines[requests.start] = new_line;
+    } else if !requests.is_block(lines) {
+        bail!(
+            "Cannot add to `requests` on line {}; it must be a block mapping",
+            requests.start + 1
+        );
+    }
+
+    // Match the indentation of the existing nodes
+    let indent_str = lines[requests.start + 1..requests.end]
+        .iter()
+        .find(|line| is_content(line))
+        .map(|line| line[..indent(line)].to_owned())
+        .unwrap_or_else(|| format!("{}{INDENT}", requests.indent_str(lines)));
+    Ok((requests.end, indent_str))
+}
+
+/// Get a blank line to separate a new node from the previous one, unless it
+/// will be the first in its mapping
+fn separator(
+    lines: &[String],
+    insert_at: usize,
+    indent_str: &str,
+) -> Vec<String> {
+    if insert_at > 0
+        && is_content(&lines[insert_at - 1])
+        && indent(&lines[insert_at - 1]) >= indent_str.len()
+    {
+        vec!["\n".into()]
+    } else {
+        vec![]
+    }
+}
+
+/// Find every line that refers to a recipe by ID: `recipe: <id>` in chains,
+/// and `extends: <id>` in other recipes
+fn references(lines: &[String], recipe_id: &RecipeId) -> Vec<usize> {
+    [("chains", "recipe"), ("requests", "extends")]
+        .into_iter()
+        .filter_map(|(section_key, key)| {
+            Some((section(lines, section_key)?, key))
+        })
+        .flat_map(|(section, key)| {
+            key_lines(lines, section.start + 1, section.end).filter(move |&i| {
+                split_key(&lines[i]).is_some_and(|(other, value)| {
+                    other == key
+                        && parse_scalar(value).is_some_and(|(value, _)| {
+                            value == recipe_id.as_str()
+                        })
+                })
+            })
+        })
+        .collect()
+}
+
+/// Find every `headers` block mapping in the file. This includes mappings
+/// outside of recipes, so that headers shared via anchors are included.
+fn header_mappings(lines: &[String]) -> Vec<Entry> {
+    key_lines(lines, 0, lines.len())
+        .filter_map(|i| {
+            let (key, _) = split_key(&lines[i])?;
+            let entry = Entry {
+                key: key.to_owned(),
+                start: i,
+                end: block_end(lines, i),
+            };
+            (key == "headers" && entry.is_block(lines)).then_some(entry)
+        })
+        .collect()
+}
+
+/// Get the direct children of a mapping entry
+fn children(lines: &[String], parent: &Entry) -> Vec<Entry> {
+    // All children have the same indentation as the first one
+    let Some(child_indent) = lines[parent.start + 1..parent.end]
+        .iter()
+        .find(|line| is_content(line))
+        .map(|line| indent(line))
+    else {
+        return vec![];
+    };
+    key_lines(lines, parent.start + 1, parent.end)
+        .filter(|&i| indent(&lines[i]) == child_indent)
+        .filter_map(|i| {
+            let (key, _) = split_key(&lines[i])?;
+            Some(Entry {
+                key: key.to_owned(),
+                start: i,
+                end: block_end(lines, i).min(parent.end),
+            })
+        })
+        .collect()
+}
+
+/// Get the indexes of all `key: value` lines in a range, skipping over the
+/// contents of block scalars (which may contain text that looks like keys)
+fn key_lines(
+    lines: &[String],
+    start: usize,
+    end: usize,
+) -> impl '_ + Iterator<Item = usize> {
+    let mut i = start;
+    std::iter::from_fn(move || {
+        while i < end {
+            let index = i;
+            i += 1;
+            if let Some((_, rest)) = split_key(&lines[index]) {
+                let rest = rest.trim_start();
+                if rest.starts_with('|') || rest.starts_with('>') {
+                    i = block_end(lines, index);
+                }
+                return Some(index);
+            }
+        }
+        None
+    })
+}
+
+/// Find the end of the block whose key is on the given line. The block runs
+/// until the next content line at the same (or lesser) indentation, excluding
+/// trailing blank lines and comments.
+fn block_end(lines: &[String], start: usize) -> usize {
+    let key_indent = indent(&lines[start]);
+    let mut end = lines[start + 1..]
+        .iter()
+        .position(|line| is_content(line) && indent(line) <= key_indent)
+        .map(|i| i + start + 1)
+        .unwrap_or(lines.len());
+    while end > start + 1 && !is_content(&lines[end - 1]) {
+        end -= 1;
+    }
+    end
+}
+
+/// Get the number of leading spaces on a line
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// Is this line anything other than whitespace or a comment?
+fn is_content(line: &str) -> bool {
+    let trimmed = line.trim();
+    !trimmed.is_empty() && !trimmed.starts_with('#')
+}
+
+/// Serialize a string as a YAML scalar, quoting only if necessary
+fn yaml_scalar(value: &str) -> String {
+    serde_yaml::to_string(value)
+        .map(|s| s.trim_end().to_owned())
+        // Strings always serialize, but just in case...
+        .unwrap_or_else(|_| format!("{value:?}"))
+}
+
+/// Split a `key: value` line into its (unquoted) key and everything after
+/// the colon. Returns `None` if the line isn't a mapping entry.
+fn split_key(line: &str) -> Option<(&str, &str)> {
+    let trimmed = line.trim_start();
+    let (key, rest) = match trimmed.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let close = trimmed[1..].find(quote)? + 1;
+            (&trimmed[1..close], trimmed[close + 1..].strip_prefix(':')?)
+        }
+        _ => {
+            let colon = trimmed.find(':')?;
+            (trimmed[..colon].trim_end(), &trimmed[colon + 1..])
+        }
+    };
+    // `key:value` isn't a mapping entry in YAML
+    if !(rest.is_empty() || rest.starts_with(char::is_whitespace)) {
+        return None;
+    }
+    Some((key, rest))
+}
+
+/// Remove a leading `&anchor` from a value
+fn strip_anchor(value: &str) -> &str {
+    match value.strip_prefix('&') {
+        Some(rest) => rest
+            .find(char::is_whitespace)
+            .map(|i| rest[i..].trim_start())
+            .unwrap_or_default(),
+        None => value,
+    }
+}
+
+/// Parse the value from a `key: value` line as a simple plain or quoted
+/// scalar. Returns the unquoted value, and the range of the scalar (including
+/// quotes) within `value`. Returns `None` if the value is anything more
+/// complex, in which case the line should be left alone. Escape sequences in
+/// quoted values are not handled.
+fn parse_scalar(value: &str) -> Option<(&str, Range<usize>)> {
+    let trimmed = value.trim();
+    let start = value.len() - value.trim_start().len();
+    match trimmed.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let close = trimmed[1..].find(quote)? + 1;
+            Some((&trimmed[1..close], start..start + close + 1))
+        }
+        // Block scalars, aliases, tags, and flow collections are too complex
+        '|' | '>' | '&' | '*' | '!' | '{' | '[' | '#' => None,
+        _ => {
+            // Plain scalars end at a comment or the end of the line
+            let len = trimmed
+                .find(" #")
+                .map(|i| trimmed[..i].trim_end().len())
+                .unwrap_or(trimmed.len());
+            Some((&trimmed[..len], start..start + len))
+        }
+    }
+}
+
+/// Append some text to the scalar value of a `key: value` line. Returns
+/// `None` if the value isn't a simple scalar (see [parse_scalar])
+fn append_to_value(line: &str, value: &str, suffix: &str) -> Option<String> {
+    let (_, range) = parse_scalar(value)?;
+    // The value runs to the end of the line, so we can find its offset
+    let value_start = line.len() - value.len();
+    // Quoted text goes inside the closing quote
+    let insert_at = if value[range.clone()].starts_with(['"', '\'']) {
+        value_start + range.end - 1
+    } else {
+        value_start + range.end
+    };
+    Some(format!(
+        "{}{suffix}{}",
+        &line[..insert_at],
+        &line[insert_at..]
+    ))
+}
+
+/// Replace the scalar value of a `key: value` line with some YAML text.
+/// Returns `None` if the value isn't a simple scalar (see [parse_scalar])
+fn replace_value(line: &str, value: &str, replacement: &str) -> Option<String> {
+    let (_, range) = parse_scalar(value)?;
+    let value_start = line.len() - value.len();
+    Some(format!(
+        "{}{replacement}{}",
+        &line[..value_start + range.start],
+        &line[value_start + range.end..]
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collection::{Collection, Method},
+        test_util::{assert_err, temp_dir, Factory, TempDir},
+        util::parse_yaml,
+    };
+    use indexmap::indexmap;
+    use itertools::Itertools;
+    use rstest::rstest;
+
+    const COLLECTION: &str = r#"# Comment at the top
+.ignore:
+  base: &base
+    headers:
+      Accept: application/json
+
+chains:
+  login:
+    source: !request
+      recipe: login
+
+requests:
+  login: &login_recipe !request
+    name: Login # Sign in
+    method: POST
+    url: "{{host}}/login"
+
+  users: !folder
+    name: Users
+    requests:
+      get_user: !request
+        <<: *base
+        name: "Get User"
+        method: GET
+        url: "{{host}}/users/1"
+        # Trailing comment
+
+      'delete_user': !request
+        method: DELETE
+        url: "{{host}}/users/1"
+"#;
+
+    /// Duplicate a top-level recipe
+    #[test]
+    fn test_duplicate_top_level() {
+        let output =
+            duplicate_node(COLLECTION, &"login".into(), &"login_copy".into())
+                .unwrap();
+        let expected = COLLECTION.replace(
+            "\n  users: !folder",
+            r#"
+  login_copy: !request
+    name: Login (Copy) # Sign in
+    method: POST
+    url: "{{host}}/login"
+
+  users: !folder"#,
+        );
+        assert_eq!(output, expected);
+    }
+
+    /// Duplicate recipes within a folder. The copy should go in the same
+    /// folder
+    #[test]
+    fn test_duplicate_nested() {
+        let output = duplicate_node(
+            COLLECTION,
+            &"get_user".into(),
+            &"get_user_copy".into(),
+        )
+        .unwrap();
+        let output = duplicate_node(
+            &output,
+            &"delete_user".into(),
+            &"delete_user_copy".into(),
+        )
+        .unwrap();
+        let expected = COLLECTION.replace(
+            "\n        # Trailing comment",
+            r#"
+
+      get_user_copy: !request
+        <<: *base
+        name: "Get User (Copy)"
+        method: GET
+        url: "{{host}}/users/1"
+        # Trailing comment"#,
+        ) + r#"
+      delete_user_copy: !request
+        method: DELETE
+        url: "{{host}}/users/1"
+"#;
+        assert_eq!(output, expected);
+    }
+
+    /// Add a new recipe to the end of the `requests` section. It should
+    /// deserialize back to the same values
+    #[test]
+    fn test_add_recipe() {
+        let recipe = Recipe {
+            id: "create_user".into(),
+            name: Some("Create User".into()),
+            method: Method::Post,
+            url: "{{host}}/users".parse().unwrap(),
+            headers: indexmap! {
+                "Content-Type".into() => "application/json".parse().unwrap(),
+            },
+            body: Some("{\n  \"name\": \"Ted\"\n}\n".into()),
+            ..Recipe::factory(())
+        };
+        let output = add_node(COLLECTION, &recipe).unwrap();
+        let expected = COLLECTION.to_owned()
+            + r#"
+  create_user: !request
+    name: Create User
+    method: POST
+    url: '{{host}}/users'
+    headers:
+      Content-Type: application/json
+    body: |
+      {
+        "name": "Ted"
+      }
+"#;
+        assert_eq!(output, expected);
+
+        let collection: Collection = parse_yaml(output.as_bytes()).unwrap();
+        let added = collection.recipes.get_recipe(&recipe.id).unwrap();
+        assert_eq!(added.name, recipe.name);
+        assert_eq!(added.url, recipe.url);
+        assert_eq!(added.headers, recipe.headers);
+        assert_eq!(added.body, recipe.body);
+    }
+
+    /// If there's no `requests` section, one should be created
+    #[rstest]
+    #[case::empty("", "requests:\n  new: !request\n")]
+    #[case::no_requests(
+        "profiles: {}\n",
+        "profiles: {}\n\nrequests:\n  new: !request\n"
+    )]
+    #[case::empty_requests("requests:\n", "requests:\n  new: !request\n")]
+    fn test_add_recipe_new_section(
+        #[case] content: &str,
+        #[case] expected_prefix: &str,
+    ) {
+        let recipe = Recipe {
+            id: "new".into(),
+            body: Some("a\n\nb".into()),
+            ..Recipe::factory(())
+        };
+        let output = add_node(content, &recipe).unwrap();
+        assert!(
+            output.starts_with(expected_prefix),
+            "Unexpected output: {output}"
+        );
+        let collection: Collection = parse_yaml(output.as_bytes()).unwrap();
+        assert_eq!(
+            collection.recipes.get_recipe(&recipe.id).unwrap().body,
+            recipe.body
+        );
+    }
+
+    #[rstest]
+    #[case::unknown("unknown", "Could not find definition of `unknown`")]
+    #[case::folder("users", "Could not find definition of `users`")]
+    fn test_duplicate_error(#[case] id: &str, #[case] expected_error: &str) {
+        assert_err!(
+            duplicate_node(COLLECTION, &id.into(), &"new".into()),
+            expected_error
+        );
+    }
+
+    /// Test the full process of duplicating in the file, then reloading
+    #[rstest]
+    #[tokio::test]
+    async fn test_duplicate_recipe(temp_dir: TempDir) {
+        let path = temp_dir.join("slumber.yml");
+        fs::write(&path, COLLECTION).unwrap();
+        let collection_file = CollectionFile::load(path.clone()).await.unwrap();
+
+        let new_id = collection_file.duplicate_recipe(&"login".into()).unwrap();
+        assert_eq!(new_id, RecipeId::from("login_copy"));
+        let collection = collection_file.reload().await.unwrap().collection;
+        let recipe = collection
+            .recipes
+            .get_recipe(&new_id)
+            .expect("Copied recipe missing");
+        assert_eq!(recipe.name.as_deref(), Some("Login (Copy)"));
+
+        // Second copy gets an incremented ID
+        let collection_file = CollectionFile::load(path).await.unwrap();
+        let new_id = collection_file.duplicate_recipe(&"login".into()).unwrap();
+        assert_eq!(new_id, RecipeId::from("login_copy2"));
+
+        assert_err!(
+            collection_file.duplicate_recipe(&"users".into()),
+            "`users` is a folder"
+        );
+    }
+
+    /// Renaming a recipe updates references to it
+    #[test]
+    fn test_rename() {
+        let output =
+            rename_node(COLLECTION, &"login".into(), &"sign_in".into())
+                .unwrap();
+        let expected = COLLECTION
+            .replace("recipe: login", "recipe: sign_in")
+            .replace("  login: &login_recipe", "  sign_in: &login_recipe");
+        assert_eq!(output, expected);
+
+        // Quoted keys and values are fine too
+        let content = r#"requests:
+  'login': !request
+    method: POST
+    url: /login
+  logout: !request
+    extends: "login" # Same as login
+    url: /logout
+"#;
+        let output =
+            rename_node(content, &"login".into(), &"sign in".into()).unwrap();
+        let expected = content
+            .replace("'login':", "sign in:")
+            .replace(r#""login" #"#, "sign in #");
+        assert_eq!(output, expected);
+    }
+
+    /// Each case gives the text that should be removed
+    #[rstest]
+    #[case::first(
+        "login",
+        r#"  login: &login_recipe !request
+    name: Login # Sign in
+    method: POST
+    url: "{{host}}/login"
+
+"#
+    )]
+    #[case::nested(
+        "get_user",
+        r#"      get_user: !request
+        <<: *base
+        name: "Get User"
+        method: GET
+        url: "{{host}}/users/1"
+"#
+    )]
+    #[case::last(
+        "delete_user",
+        r#"
+      'delete_user': !request
+        method: DELETE
+        url: "{{host}}/users/1"
+"#
+    )]
+    fn test_delete(#[case] id: &str, #[case] removed: &str) {
+        // Remove the chain's reference so login can be deleted
+        let content = COLLECTION.replace("recipe: login", "recipe: sign_in");
+        let output = delete_node(&content, &id.into()).unwrap();
+        assert_eq!(output, content.replace(removed, ""));
+    }
+
+    /// Deleting the only recipe leaves an empty mapping, and takes the
+    /// recipe's comments with it
+    #[test]
+    fn test_delete_only() {
+        let content = r#"requests:
+  # The only recipe
+  only: !request
+    method: GET
+    url: /
+
+chains: {}
+"#;
+        let output = delete_node(content, &"only".into()).unwrap();
+        assert_eq!(
+            output,
+            "requests: {}
+
+chains: {}
+"
+        );
+        let collection: Collection = parse_yaml(output.as_bytes()).unwrap();
+        assert!(collection.recipes.iter().next().is_none());
+    }
+
+    #[rstest]
+    #[case::unknown(COLLECTION, "unknown", "Could not find definition")]
+    #[case::chain(COLLECTION, "login", "`login` is referenced on line 10")]
+    #[case::extends(
+        "requests:\n  a: !request\n    url: /\n  b: !request\n    \
+        extends: a\n",
+        "a",
+        "`a` is referenced on line 5"
+    )]
+    #[case::anchor(
+        "requests:\n  a: &a !request\n    url: /\n  b: !request\n    \
+        <<: *a\n",
+        "a",
+        "`a` defines the anchor `&a`, which is used elsewhere in the file"
+    )]
+    fn test_delete_error(
+        #[case] content: &str,
+        #[case] id: &str,
+        #[case] expected_error: &str,
+    ) {
+        assert_err!(delete_node(content, &id.into()), expected_error);
+    }
+
+    /// Test the full process of renaming and deleting in the file, then
+    /// reloading
+    #[rstest]
+    #[tokio::test]
+    async fn test_rename_delete_recipe(temp_dir: TempDir) {
+        let path = temp_dir.join("slumber.yml");
+        fs::write(&path, COLLECTION).unwrap();
+        let collection_file = CollectionFile::load(path.clone()).await.unwrap();
+
+        collection_file
+            .rename_recipe(&"get_user".into(), &"get_user_1".into())
+            .unwrap();
+        let collection_file = collection_file.reload().await.unwrap();
+        let recipes = &collection_file.collection.recipes;
+        assert!(recipes.get_recipe(&"get_user".into()).is_none());
+        assert!(recipes.get_recipe(&"get_user_1".into()).is_some());
+
+        collection_file
+            .delete_recipe(&"delete_user".into())
+            .unwrap();
+        let collection_file = collection_file.reload().await.unwrap();
+        let recipes = &collection_file.collection.recipes;
+        assert!(recipes.get_recipe(&"delete_user".into()).is_none());
+
+        assert_err!(
+            collection_file
+                .rename_recipe(&"login".into(), &"get_user_1".into()),
+            "Recipe ID `get_user_1` is already in use"
+        );
+        assert_err!(
+            collection_file.delete_recipe(&"users".into()),
+            "`users` is a folder; only recipes can be deleted"
+        );
+    }
+
+    /// Add folders at the top level and nested in another folder
+    #[test]
+    fn test_add_folder() {
+        let output =
+            add_folder_node(COLLECTION, &"admin".into(), None).unwrap();
+        assert_eq!(
+            output,
+            COLLECTION.to_owned() + "\n  admin: !folder\n    requests: {}\n"
+        );
+
+        let output =
+            add_folder_node(&output, &"roles".into(), Some(&"admin".into()))
+                .unwrap();
+        assert_eq!(
+            output,
+            COLLECTION.to_owned()
+                + "
+  admin: !folder
+    requests:
+      roles: !folder
+        requests: {}
+"
+        );
+        let collection: Collection = parse_yaml(output.as_bytes()).unwrap();
+        assert_eq!(
+            collection
+                .recipes
+                .get_lookup_key(&"roles".into())
+                .unwrap()
+                .as_slice(),
+            &["admin", "roles"].map(RecipeId::from)
+        );
+    }
+
+    /// A folder with no `requests` gets one
+    #[test]
+    fn test_add_folder_no_requests() {
+        let content = "requests:\n  f1: !folder\n    name: Folder\n";
+        let output =
+            add_folder_node(content, &"f2".into(), Some(&"f1".into())).unwrap();
+        assert_eq!(
+            output,
+            "requests:
+  f1: !folder
+    name: Folder
+    requests:
+      f2: !folder
+        requests: {}
+"
+        );
+    }
+
+    /// Move recipes and folders between folders. Each case gives the expected
+    /// output. Like with deletion, trailing comments belong to whatever comes
+    /// next so they stay put
+    #[rstest]
+    #[case::to_top_level(
+        "get_user",
+        None,
+        r#"# Comment at the top
+.ignore:
+  base: &base
+    headers:
+      Accept: application/json
+
+chains:
+  login:
+    source: !request
+      recipe: login
+
+requests:
+  login: &login_recipe !request
+    name: Login # Sign in
+    method: POST
+    url: "{{host}}/login"
+
+  users: !folder
+    name: Users
+    requests:
+        # Trailing comment
+
+      'delete_user': !request
+        method: DELETE
+        url: "{{host}}/users/1"
+
+  get_user: !request
+    <<: *base
+    name: "Get User"
+    method: GET
+    url: "{{host}}/users/1"
+"#
+    )]
+    #[case::into_folder(
+        "login",
+        Some("users"),
+        r#"# Comment at the top
+.ignore:
+  base: &base
+    headers:
+      Accept: application/json
+
+chains:
+  login:
+    source: !request
+      recipe: login
+
+requests:
+  users: !folder
+    name: Users
+    requests:
+      get_user: !request
+        <<: *base
+        name: "Get User"
+        method: GET
+        url: "{{host}}/users/1"
+        # Trailing comment
+
+      'delete_user': !request
+        method: DELETE
+        url: "{{host}}/users/1"
+
+      login: &login_recipe !request
+        name: Login # Sign in
+        method: POST
+        url: "{{host}}/login"
+"#
+    )]
+    fn test_move(
+        #[case] id: &str,
+        #[case] folder_id: Option<&str>,
+        #[case] expected: &str,
+    ) {
+        let folder_id = folder_id.map(RecipeId::from);
+        let output =
+            move_node(COLLECTION, &id.into(), folder_id.as_ref()).unwrap();
+        assert_eq!(output, expected);
+        let _: Collection = parse_yaml(output.as_bytes()).unwrap();
+    }
+
+    /// Moving into a folder with no recipes expands its `requests`, and
+    /// block scalars keep their relative indentation
+    #[test]
+    fn test_move_into_empty() {
+        let content = "requests:
+  # Comment
+  r1: !request
+    url: /
+    body: |
+      {
+        \"a\": 1
+      }
+  f1: !folder
+    requests: {} # Empty
+";
+        let output =
+            move_node(content, &"r1".into(), Some(&"f1".into())).unwrap();
+        assert_eq!(
+            output,
+            "requests:
+  f1: !folder
+    requests: # Empty
+      # Comment
+      r1: !request
+        url: /
+        body: |
+          {
+            \"a\": 1
+          }
+"
+        );
+    }
+
+    #[rstest]
+    #[case::unknown(COLLECTION, "unknown", None, "Could not find definition")]
+    #[case::anchor(
+        "requests:\n  a: &a !request\n    url: /\n  b: !request\n    \
+        <<: *a\n",
+        "a",
+        None,
+        "Moving `a` would make the collection file invalid"
+    )]
+    fn test_move_error(
+        #[case] content: &str,
+        #[case] id: &str,
+        #[case] folder_id: Option<&str>,
+        #[case] expected_error: &str,
+    ) {
+        let folder_id = folder_id.map(RecipeId::from);
+        assert_err!(
+            move_node(content, &id.into(), folder_id.as_ref()),
+            expected_error
+        );
+    }
+
+    /// Test the full process of creating folders, moving recipes, and
+    /// renaming folders in the file, then reloading
+    #[rstest]
+    #[tokio::test]
+    async fn test_folders(temp_dir: TempDir) {
+        let path = temp_dir.join("slumber.yml");
+        fs::write(&path, COLLECTION).unwrap();
+        let collection_file = CollectionFile::load(path.clone()).await.unwrap();
+
+        collection_file
+            .add_folder(&"admin".into(), Some(&"users".into()))
+            .unwrap();
+        let collection_file = collection_file.reload().await.unwrap();
+        collection_file
+            .move_recipe(&"delete_user".into(), Some(&"admin".into()))
+            .unwrap();
+        let collection_file = collection_file.reload().await.unwrap();
+        collection_file
+            .rename_recipe(&"admin".into(), &"administration".into())
+            .unwrap();
+        let collection_file = collection_file.reload().await.unwrap();
+        let recipes = &collection_file.collection.recipes;
+        assert_eq!(
+            recipes
+                .get_lookup_key(&"delete_user".into())
+                .unwrap()
+                .as_slice(),
+            &["users", "administration", "delete_user"].map(RecipeId::from)
+        );
+
+        assert_err!(
+            collection_file.add_folder(&"login".into(), None),
+            "Recipe ID `login` is already in use"
+        );
+        assert_err!(
+            collection_file.add_folder(&"new".into(), Some(&"login".into())),
+            "`login` is a recipe, not a folder"
+        );
+        assert_err!(
+            collection_file.move_recipe(&"users".into(), Some(&"users".into())),
+            "Cannot move `users` into itself"
+        );
+        assert_err!(
+            collection_file
+                .move_recipe(&"users".into(), Some(&"administration".into())),
+            "Cannot move `users` into itself"
+        );
+        assert_err!(
+            collection_file.move_recipe(&"login".into(), None),
+            "`login` is already there"
+        );
+    }
+
+    const HEADERS_COLLECTION: &str = r#".ignore:
+  base: &base
+    headers:
+      Accept: application/json
+      X-Old: base
+
+requests:
+  login: !request
+    method: POST
+    url: "{{host}}/login"
+    headers:
+      X-Old: "{{token}}" # Auth
+      Content-Type: application/json
+    body: |
+      headers:
+        X-Old: not a header
+
+  get_user: !request
+    <<: *base
+    method: GET
+    url: "{{host}}/users/1"
+
+  list_users: !request
+    method: GET
+    url: "{{host}}/users"
+"#;
+
+    /// Test header edits. Each case gives a list of text replacements to
+    /// apply to the input to get the expected output
+    #[rstest]
+    #[case::rename(
+        HeaderEdit::Rename { from: "x-old".into(), to: "X-New".into() },
+        &[
+            ("      X-Old: base", "      X-New: base"),
+            (r#"      X-Old: "{{token}}""#, r#"      X-New: "{{token}}""#),
+        ],
+        2,
+        &[],
+    )]
+    #[case::rename_missing(
+        HeaderEdit::Rename { from: "X-Missing".into(), to: "X-New".into() },
+        &[],
+        0,
+        &[],
+    )]
+    #[case::set_new(
+        HeaderEdit::Set { name: "X-Trace".into(), value: "{{trace}}".into() },
+        &[
+            (
+                "      Content-Type: application/json\n",
+                "      Content-Type: application/json\n      X-Trace: '{{trace}}'\n",
+            ),
+            (
+                "    url: \"{{host}}/users\"\n",
+                "    url: \"{{host}}/users\"\n    headers:\n      X-Trace: '{{trace}}'\n",
+            ),
+        ],
+        2,
+        &["get_user"],
+    )]
+    #[case::set_existing(
+        HeaderEdit::Set { name: "content-type".into(), value: "text/plain".into() },
+        &[
+            ("      Content-Type: application/json", "      Content-Type: text/plain"),
+            (
+                "    url: \"{{host}}/users\"\n",
+                "    url: \"{{host}}/users\"\n    headers:\n      content-type: text/plain\n",
+            ),
+        ],
+        2,
+        &["get_user"],
+    )]
+    #[case::remove(
+        HeaderEdit::Remove { name: "X-Old".into() },
+        &[
+            ("      X-Old: base\n", ""),
+            ("      X-Old: \"{{token}}\" # Auth\n", ""),
+        ],
+        2,
+        &[],
+    )]
+    #[case::remove_one(
+        HeaderEdit::Remove { name: "Content-Type".into() },
+        &[("      Content-Type: application/json\n", "")],
+        1,
+        &[],
+    )]
+    fn test_edit_headers(
+        #[case] edit: HeaderEdit,
+        #[case] replacements: &[(&str, &str)],
+        #[case] expected_changes: usize,
+        #[case] expected_skipped: &[&str],
+    ) {
+        let (output, report) =
+            edit_headers(HEADERS_COLLECTION, &[edit]).unwrap();
+        let expected = replacements.iter().fold(
+            HEADERS_COLLECTION.to_owned(),
+            |acc, (from, to)| {
+                assert!(acc.contains(from), "Replacement `{from}` not found");
+                acc.replacen(from, to, 1)
+            },
+        );
+        assert_eq!(output, expected);
+        assert_eq!(
+            report,
+            HeaderEditReport {
+                changes: expected_changes,
+                skipped: expected_skipped.iter().map(|&id| id.into()).collect(),
+            }
+        );
+    }
+
+    /// Skipped recipes are reported in file order for each edit, even with
+    /// multiple edits
+    #[test]
+    fn test_edit_headers_skipped_order() {
+        let content = "requests:
+  first: !request { method: GET, url: /first }
+  middle: !request
+    method: GET
+    url: /middle
+  last: !request { method: GET, url: /last }
+";
+        let edits = ["X-A", "X-B", "X-C"].map(|name| HeaderEdit::Set {
+            name: name.into(),
+            value: "1".into(),
+        });
+        let (_, report) = edit_headers(content, &edits).unwrap();
+        assert_eq!(
+            report,
+            HeaderEditReport {
+                changes: 3,
+                skipped: ["first", "last", "first", "last", "first", "last"]
+                    .map(RecipeId::from)
+                    .into(),
+            }
+        );
+    }
+
+    /// Removing every header from a mapping should remove the mapping too
+    #[test]
+    fn test_remove_last_header() {
+        let (output, _) = edit_headers(
+            HEADERS_COLLECTION,
+            &[
+                HeaderEdit::Remove {
+                    name: "X-Old".into(),
+                },
+                HeaderEdit::Remove {
+                    name: "content-type".into(),
+                },
+            ],
+        )
+        .unwrap();
+        assert!(!output.contains("  headers:\n    body"));
+        assert_eq!(
+            output.matches("headers:").count(),
+            // Base mapping and the body text remain
+            2
+        );
+        // Result should still be a valid collection
+        crate::util::parse_yaml::<crate::collection::Collection>(
+            output.as_bytes(),
+        )
+        .unwrap();
+    }
+
+    /// Renaming a header to one that already exists would produce a duplicate
+    /// key, so it's an error
+    #[test]
+    fn test_rename_header_conflict() {
+        assert_err!(
+            edit_headers(
+                HEADERS_COLLECTION,
+                &[HeaderEdit::Rename {
+                    from: "X-Old".into(),
+                    to: "content-type".into()
+                }]
+            ),
+            "Cannot rename `X-Old` to `content-type` on line 12"
+        );
+    }
+
+    /// Find text and preview replacements, including in long lines
+    #[test]
+    fn test_find_text() {
+        let content = "requests:
+  login: !request
+    url: \"{{host}}/v1/login\"
+    body: the quick brown fox jumps over the lazy dog /v1/ the quick brown fox jumps over the lazy dog
+";
+        let matches = find_text(content, "/v1/");
+        assert_eq!(
+            matches
+                .iter()
+                .map(|m| (m.line_number, m.preview("/v2/")))
+                .collect_vec(),
+            [
+                (3, "url: \"{{host}}[/v1/ → /v2/]login\"".to_owned()),
+                (
+                    4,
+                    "…n fox jumps over the lazy dog [/v1/ → /v2/] the quick brown fox jumps ove…".to_owned()
+                ),
+            ]
+        );
+        assert_eq!(find_text(content, ""), []);
+        assert_eq!(find_text(content, "/V1/"), []);
+    }
+
+    /// Replace a subset of matches
+    #[test]
+    fn test_replace_text() {
+        let content = "a: /v1/x\nb: /v1/y\nc: /v1/z\n";
+        let matches = find_text(content, "/v1/");
+        let output = replace_text(
+            content,
+            "/v2/",
+            &[matches[2].clone(), matches[0].clone()],
+        )
+        .unwrap();
+        assert_eq!(output, "a: /v2/x\nb: /v1/y\nc: /v2/z\n");
+
+        // Content changed since the search
+        assert_err!(
+            replace_text("a: /v2/x\n", "/v3/", &matches[..1]),
+            "Text on line 1 has changed since it was searched"
+        );
+    }
+}
@@ -0,0 +1,90 @@
+//! Export request collections to the formats of other HTTP clients, so they
+//! can be shared with people who don't use Slumber. Like the importers, this is
+//! approximate. Anything without an equivalent in the target format is dropped
+//! with a warning.
+
+mod http_file;
+mod insomnia;
+mod postman;
+
+use crate::{
+    collection::{Profile, Recipe, RecipeKind},
+    template::{Template, TemplateKey},
+};
+use indexmap::IndexMap;
+use tracing::warn;
+
+/// Translate a template to another tool's variable syntax. Profile fields map
+/// directly to variables. Chains and environment variables have no
+/// equivalent, so they're written as variables of the same name (e.g.
+/// `chains.token`), which the user will have to define themselves.
+fn translate(template: &Template, variable: impl Fn(&str) -> String) -> String {
+    template.map_keys(|key, functions| {
+        if !matches!(key, TemplateKey::Field(_)) {
+            warn!(
+                "Template key `{key}` has no equivalent in the export format; \
+                it will need to be defined as a variable"
+            );
+        }
+        if !functions.is_empty() {
+            warn!(
+                "Functions applied to template key `{key}` have no equivalent \
+                in the export format; they will be dropped"
+            );
+        }
+        variable(&key.to_string())
+    })
+}
+
+/// Translate a recipe's URL. Relative URLs are prefixed with the base URL
+/// inherited from the collection defaults (if any), and then the `base_url`
+/// variable, which is defined by [profile_variables]
+fn translate_url(recipe: &Recipe, variable: impl Fn(&str) -> String) -> String {
+    let mut url = translate(&recipe.url, &variable);
+    // Apply the base URL inherited from the collection defaults first
+    if let Some(base_url) =
+        recipe.base_url.as_ref().filter(|_| url.starts_with('/'))
+    {
+        url = format!(
+            "{}{url}",
+            translate(base_url, &variable).trim_end_matches('/')
+        );
+    }
+    if url.starts_with('/') {
+        format!("{}{url}", variable("base_url"))
+    } else {
+        url
+    }
+}
+
+/// Get the variables defined by a profile. This is its data, plus `base_url`
+/// if defined.
+fn profile_variables(profile: &Profile) -> IndexMap<&str, &Template> {
+    profile
+        .base_url
+        .as_ref()
+        .map(|base_url| ("base_url", base_url))
+        .into_iter()
+        .chain(
+            profile
+                .data
+                .iter()
+                .map(|(key, value)| (key.as_str(), value)),
+        )
+        .collect()
+}
+
+/// Neither format supports WebSocket recipes in its collection format. Return
+/// `false` (with a warning) for any recipe that should be skipped.
+fn is_exportable(recipe: &Recipe) -> bool {
+    match recipe.kind {
+        RecipeKind::Http => true,
+        RecipeKind::WebSocket => {
+            warn!(
+                recipe_id = %recipe.id,
+                "Skipping WebSocket recipe; not supported by the export format"
+            );
+            false
+        }
+    }
+}
@@ -0,0 +1,242 @@
+//! Export to a `.http` file, as used by the JetBrains HTTP client and the VS
+//! Code REST Client extension. Both use the same `{{variable}}` syntax as
+//! Slumber, so templates translate cleanly.
+
+use crate::{
+    collection::{
+        export::{is_exportable, profile_variables, translate, translate_url},
+        Authentication, Collection, MultipartField, ProfileId, Recipe,
+        RecipeBody, RecipeNode,
+    },
+    template::Template,
+};
+use anyhow::anyhow;
+use itertools::Itertools;
+use reqwest::header;
+use std::fmt::Write;
+use tracing::warn;
+
+/// Boundary for multipart bodies. This just has to not appear in the content
+const BOUNDARY: &str = "slumber-boundary";
+
+impl Collection {
+    /// Convert to a `.http` file. If a profile is given, its data is defined
+    /// as `@variables` at the top of the file. Folders have no equivalent, so
+    /// the recipes are flattened into a single list.
+    pub fn to_http_file(
+        &self,
+        profile_id: Option<&ProfileId>,
+    ) -> anyhow::Result<String> {
+        // Writing to a string is infallible, so all the unwraps are safe
+        let mut buf = String::new();
+        if let Some(profile_id) = profile_id {
+            let profile = self
+                .profiles
+                .get(profile_id)
+                .ok_or_else(|| anyhow!("No profile with ID `{profile_id}`"))?;
+            for (key, value) in profile_variables(profile) {
+                writeln!(buf, "@{key} = {}", http(value)).unwrap();
+            }
+        }
+
+        let recipes = self
+            .recipes
+            .iter()
+            .filter_map(|(_, node)| match node {
+                RecipeNode::Recipe(recipe) if is_exportable(recipe) => {
+                    Some(recipe)
+                }
+                _ => None,
+            })
+            .collect_vec();
+        for recipe in recipes {
+            if !buf.is_empty() {
+                buf.push('\n');
+            }
+            write_request(&mut buf, recipe);
+        }
+        Ok(buf)
+    }
+}
+
+/// Translate a template to `{{variable}}` syntax
+fn http(template: &Template) -> String {
+    translate(template, variable)
+}
+
+fn variable(name: &str) -> String {
+    format!("{{{{{name}}}}}")
+}
+
+fn write_request(buf: &mut String, recipe: &Recipe) {
+    writeln!(buf, "### {}", recipe.name()).unwrap();
+    writeln!(buf, "# @name {}", recipe.id).unwrap();
+
+    let mut url = translate_url(recipe, variable);
+    if !recipe.query.is_empty() {
+        let query = recipe
+            .query
+            .iter()
+            .map(|(param, value)| format!("{param}={}", http(value)))
+            .join("&");
+        let separator = if url.contains('?') { '&' } else { '?' };
+        url = format!("{url}{separator}{query}");
+    }
+    writeln!(buf, "{} {url}", recipe.method).unwrap();
+
+    for (header, value) in &recipe.headers {
+        writeln!(buf, "{header}: {}", http(value)).unwrap();
+    }
+    if let Some(authentication) = &recipe.authentication {
+        // Both clients encode credentials given as `<scheme> user password`
+        let value = match authentication {
+            Authentication::Basic { username, password } => Some(format!(
+                "Basic {} {}",
+                http(username),
+                password.as_ref().map(http).unwrap_or_default()
+            )),
+            Authentication::Digest { username, password } => Some(format!(
+                "Digest {} {}",
+                http(username),
+                password.as_ref().map(http).unwrap_or_default()
+            )),
+            Authentication::Bearer(token) => {
+                Some(format!("Bearer {}", http(token)))
+            }
+            _ => {
+                warn!(
+                    recipe_id = %recipe.id,
+                    "Skipping authentication; type not supported by .http files"
+                );
+                None
+            }
+        };
+        if let Some(value) = value {
+            writeln!(buf, "{}: {value}", header::AUTHORIZATION).unwrap();
+        }
+    }
+
+    match &recipe.body {
+        None => {}
+        Some(RecipeBody::Raw(body)) => {
+            writeln!(buf, "\n{}", http(body)).unwrap();
+        }
+        Some(RecipeBody::File(path)) => {
+            writeln!(buf, "\n< {}", http(path)).unwrap();
+        }
+        Some(RecipeBody::FormMultipart(fields)) => {
+            writeln!(
+                buf,
+                "{}: multipart/form-data; boundary={BOUNDARY}\n",
+                header::CONTENT_TYPE
+            )
+            .unwrap();
+            for (field, value) in fields {
+                writeln!(buf, "--{BOUNDARY}").unwrap();
+                match value {
+                    MultipartField::Text(value) => {
+                        writeln!(
+                            buf,
+                            "Content-Disposition: form-data; \
+                            name=\"{field}\"\n\n{}",
+                            http(value)
+                        )
+                        .unwrap();
+                    }
+                    MultipartField::File(file) => {
+                        let path = http(&file.path);
+                        let filename =
+                            file.filename.clone().unwrap_or_else(|| {
+                                path.rsplit(['/', '\\'])
+                                    .next()
+                                    .unwrap_or_default()
+                                    .to_owned()
+                            });
+                        writeln!(
+                            buf,
+                            "Content-Disposition: form-data; \
+                            name=\"{field}\"; filename=\"{filename}\""
+                        )
+                        .unwrap();
+                        if let Some(content_type) = &file.content_type {
+                            writeln!(
+                                buf,
+                                "{}: {content_type}",
+                                header::CONTENT_TYPE
+                            )
+                            .unwrap();
+                        }
+                        writeln!(buf, "\n< {path}").unwrap();
+                    }
+                }
+            }
+            writeln!(buf, "--{BOUNDARY}--").unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        collection::CollectionFile,
+        test_util::{assert_err, test_data_dir},
+    };
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use std::path::PathBuf;
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_http_file_export(test_data_dir: PathBuf) {
+        let collection = CollectionFile::load(test_data_dir.join("export.yml"))
+            .await
+            .unwrap()
+            .collection;
+        let exported = collection.to_http_file(Some(&"local".into())).unwrap();
+        let expected = r#"@base_url = http://localhost:3000
+@token = abc123
+
+### Login
+# @name login
+POST {{base}}/login
+content-type: application/json
+
+{"token": "{{token}}"}
+
+### list_fishes
+# @name list_fishes
+GET {{base_url}}/fishes?big=true
+authorization: Bearer {{chains.token}}
+
+### Upload Photo
+# @name upload
+POST {{base_url}}/fishes/photo
+authorization: Basic user {{token}}
+content-type: multipart/form-data; boundary=slumber-boundary
+
+--slumber-boundary
+Content-Disposition: form-data; name="name"
+
+Barry
+--slumber-boundary
+Content-Disposition: form-data; name="photo"; filename="barry.png"
+
+< ./barry.png
+--slumber-boundary--
+"#;
+        assert_eq!(exported, expected);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_http_file_export_unknown_profile(test_data_dir: PathBuf) {
+        let collection = CollectionFile::load(test_data_dir.join("export.yml"))
+            .await
+            .unwrap()
+            .collection;
+        assert_err!(
+            collection.to_http_file(Some(&"remote".into())),
+            "No profile with ID `remote`"
+        );
+    }
+}
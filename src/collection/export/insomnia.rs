@@ -0,0 +1,333 @@
+//! Export to the Insomnia v4 export format, the same format that the Insomnia
+//! importer reads
+
+use crate::{
+    collection::{
+        export::{is_exportable, profile_variables, translate, translate_url},
+        Authentication, Collection, MultipartField, Recipe, RecipeBody,
+        RecipeId, RecipeNode,
+    },
+    template::Template,
+};
+use indexmap::IndexMap;
+use itertools::Itertools;
+use reqwest::header;
+use serde_json::{json, Value};
+use tracing::warn;
+
+const WORKSPACE_ID: &str = "wrk_slumber";
+const BASE_ENVIRONMENT_ID: &str = "env_slumber_base";
+
+impl Collection {
+    /// Convert to an Insomnia export. Each profile becomes an environment
+    pub fn to_insomnia(&self, name: &str) -> Value {
+        let mut resources = vec![
+            json!({
+                "_id": WORKSPACE_ID,
+                "_type": "workspace",
+                "parentId": null,
+                "name": name,
+            }),
+            // Insomnia requires a base environment to hang the others off of
+            json!({
+                "_id": BASE_ENVIRONMENT_ID,
+                "_type": "environment",
+                "parentId": WORKSPACE_ID,
+                "name": "Base Environment",
+                "metaSortKey": 0,
+                "data": {},
+            }),
+        ];
+        resources.extend(self.profiles.values().enumerate().map(
+            |(i, profile)| {
+                json!({
+                    "_id": format!("env_{}", profile.id),
+                    "_type": "environment",
+                    "parentId": BASE_ENVIRONMENT_ID,
+                    "name": profile.name(),
+                    "metaSortKey": i,
+                    "data": profile_variables(profile)
+                        .into_iter()
+                        .map(|(key, value)| (key, insomnia(value)))
+                        .collect::<IndexMap<_, _>>(),
+                })
+            },
+        ));
+        add_nodes(&mut resources, WORKSPACE_ID, self.recipes.tree());
+
+        json!({
+            "_type": "export",
+            "__export_format": 4,
+            "__export_source": concat!("slumber:v", env!("CARGO_PKG_VERSION")),
+            "resources": resources,
+        })
+    }
+}
+
+/// Translate a template to Insomnia's `{{ _.variable }}` syntax
+fn insomnia(template: &Template) -> String {
+    translate(template, variable)
+}
+
+fn variable(name: &str) -> String {
+    format!("{{{{ _.{name} }}}}")
+}
+
+/// Insomnia resources are a flat list, with each one pointing to its parent.
+/// Recursively add a level of the recipe tree to the list. Insomnia orders
+/// siblings by `metaSortKey`, so that's set to match the collection order.
+fn add_nodes(
+    resources: &mut Vec<Value>,
+    parent_id: &str,
+    nodes: &IndexMap<RecipeId, RecipeNode>,
+) {
+    for (i, node) in nodes.values().enumerate() {
+        match node {
+            RecipeNode::Folder(folder) => {
+                let id = format!("fld_{}", folder.id);
+                resources.push(json!({
+                    "_id": id,
+                    "_type": "request_group",
+                    "parentId": parent_id,
+                    "name": node.name(),
+                    "metaSortKey": i,
+                }));
+                add_nodes(resources, &id, &folder.children);
+            }
+            RecipeNode::Recipe(recipe) if is_exportable(recipe) => {
+                let mut request = request(parent_id, recipe);
+                request["metaSortKey"] = i.into();
+                resources.push(request);
+            }
+            RecipeNode::Recipe(_) => {}
+        }
+    }
+}
+
+fn request(parent_id: &str, recipe: &Recipe) -> Value {
+    let mut request = json!({
+        "_id": format!("req_{}", recipe.id),
+        "_type": "request",
+        "parentId": parent_id,
+        "name": recipe.name(),
+        "method": recipe.method.to_string(),
+        "url": translate_url(recipe, variable),
+        "parameters": recipe
+            .query
+            .iter()
+            .map(|(param, value)| {
+                json!({"name": param, "value": insomnia(value)})
+            })
+            .collect_vec(),
+        "headers": recipe
+            .headers
+            .iter()
+            .map(|(header, value)| {
+                json!({"name": header, "value": insomnia(value)})
+            })
+            .collect_vec(),
+        "body": {},
+        "authentication": {},
+    });
+    if let Some(body) = &recipe.body {
+        request["body"] = self::body(recipe, body);
+    }
+    if let Some(auth) = recipe.authentication.as_ref().and_then(|auth| {
+        let auth = self::auth(auth);
+        if auth.is_none() {
+            warn!(
+                recipe_id = %recipe.id,
+                "Skipping authentication; type not supported by Insomnia"
+            );
+        }
+        auth
+    }) {
+        request["authentication"] = auth;
+    }
+    request
+}
+
+fn body(recipe: &Recipe, body: &RecipeBody) -> Value {
+    // Insomnia stores the content type on the body as well as in the headers
+    let content_type = recipe
+        .headers
+        .iter()
+        .find(|(header, _)| {
+            header.eq_ignore_ascii_case(header::CONTENT_TYPE.as_str())
+        })
+        .map(|(_, value)| insomnia(value));
+    match body {
+        RecipeBody::Raw(body) => json!({
+            "mimeType": content_type.unwrap_or_default(),
+            "text": insomnia(body),
+        }),
+        RecipeBody::FormMultipart(fields) => json!({
+            "mimeType": "multipart/form-data",
+            "params": fields
+                .iter()
+                .map(|(field, value)| match value {
+                    MultipartField::Text(value) => {
+                        json!({"name": field, "value": insomnia(value)})
+                    }
+                    MultipartField::File(file) => json!({
+                        "name": field,
+                        "type": "file",
+                        "fileName": insomnia(&file.path),
+                    }),
+                })
+                .collect_vec(),
+        }),
+        RecipeBody::File(path) => json!({
+            "mimeType": content_type
+                .unwrap_or_else(|| "application/octet-stream".into()),
+            "fileName": insomnia(path),
+        }),
+    }
+}
+
+/// Convert authentication, or `None` if Insomnia doesn't support the type
+fn auth(authentication: &Authentication) -> Option<Value> {
+    let password = |password: &Option<Template>| {
+        password.as_ref().map(insomnia).unwrap_or_default()
+    };
+    match authentication {
+        Authentication::Basic {
+            username,
+            password: pw,
+        } => Some(json!({
+            "type": "basic",
+            "username": insomnia(username),
+            "password": password(pw),
+        })),
+        Authentication::Digest {
+            username,
+            password: pw,
+        } => Some(json!({
+            "type": "digest",
+            "username": insomnia(username),
+            "password": password(pw),
+        })),
+        Authentication::Bearer(token) => {
+            Some(json!({"type": "bearer", "token": insomnia(token)}))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{collection::CollectionFile, test_util::test_data_dir};
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use std::path::PathBuf;
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_insomnia_export(test_data_dir: PathBuf) {
+        let collection = CollectionFile::load(test_data_dir.join("export.yml"))
+            .await
+            .unwrap()
+            .collection;
+        let exported = collection.to_insomnia("Fish");
+        assert_eq!(
+            exported["resources"],
+            json!([
+                {
+                    "_id": WORKSPACE_ID,
+                    "_type": "workspace",
+                    "parentId": null,
+                    "name": "Fish",
+                },
+                {
+                    "_id": BASE_ENVIRONMENT_ID,
+                    "_type": "environment",
+                    "parentId": WORKSPACE_ID,
+                    "name": "Base Environment",
+                    "metaSortKey": 0,
+                    "data": {},
+                },
+                {
+                    "_id": "env_local",
+                    "_type": "environment",
+                    "parentId": BASE_ENVIRONMENT_ID,
+                    "name": "Local",
+                    "metaSortKey": 0,
+                    "data": {
+                        "base_url": "http://localhost:3000",
+                        "token": "abc123",
+                    },
+                },
+                {
+                    "_id": "req_login",
+                    "_type": "request",
+                    "parentId": WORKSPACE_ID,
+                    "name": "Login",
+                    "metaSortKey": 0,
+                    "method": "POST",
+                    "url": "{{ _.base }}/login",
+                    "parameters": [],
+                    "headers": [
+                        {"name": "content-type", "value": "application/json"},
+                    ],
+                    "body": {
+                        "mimeType": "application/json",
+                        "text": r#"{"token": "{{ _.token }}"}"#,
+                    },
+                    "authentication": {},
+                },
+                {
+                    "_id": "fld_fishes",
+                    "_type": "request_group",
+                    "parentId": WORKSPACE_ID,
+                    "name": "Fishes",
+                    "metaSortKey": 1,
+                },
+                {
+                    "_id": "req_list_fishes",
+                    "_type": "request",
+                    "parentId": "fld_fishes",
+                    "name": "list_fishes",
+                    "metaSortKey": 0,
+                    "method": "GET",
+                    "url": "{{ _.base_url }}/fishes",
+                    "parameters": [{"name": "big", "value": "true"}],
+                    "headers": [],
+                    "body": {},
+                    "authentication": {
+                        "type": "bearer",
+                        "token": "{{ _.chains.token }}",
+                    },
+                },
+                {
+                    "_id": "req_upload",
+                    "_type": "request",
+                    "parentId": "fld_fishes",
+                    "name": "Upload Photo",
+                    "metaSortKey": 1,
+                    "method": "POST",
+                    "url": "{{ _.base_url }}/fishes/photo",
+                    "parameters": [],
+                    "headers": [],
+                    "body": {
+                        "mimeType": "multipart/form-data",
+                        "params": [
+                            {"name": "name", "value": "Barry"},
+                            {
+                                "name": "photo",
+                                "type": "file",
+                                "fileName": "./barry.png",
+                            },
+                        ],
+                    },
+                    "authentication": {
+                        "type": "basic",
+                        "username": "user",
+                        "password": "{{ _.token }}",
+                    },
+                },
+                // WebSocket recipe is skipped
+            ])
+        );
+    }
+}
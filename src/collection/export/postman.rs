@@ -0,0 +1,308 @@
+//! Export to a Postman v2.1 collection. Spec:
+//! <https://schema.postman.com/collection/json/v2.1.0/draft-07/docs/index.html>
+
+use crate::{
+    collection::{
+        export::{is_exportable, profile_variables, translate, translate_url},
+        Authentication, Collection, MultipartField, ProfileId, Recipe,
+        RecipeBody, RecipeId, RecipeNode,
+    },
+    template::Template,
+};
+use anyhow::anyhow;
+use indexmap::IndexMap;
+use itertools::Itertools;
+use serde_json::{json, Value};
+use tracing::warn;
+
+const SCHEMA: &str =
+    "https://schema.getpostman.com/json/collection/v2.1.0/collection.json";
+
+impl Collection {
+    /// Convert to a Postman collection. Postman stores environments separately
+    /// from collections, so if a profile is given, its data is exported as
+    /// collection variables instead.
+    pub fn to_postman(
+        &self,
+        name: &str,
+        profile_id: Option<&ProfileId>,
+    ) -> anyhow::Result<Value> {
+        let variables = match profile_id {
+            Some(profile_id) => {
+                let profile =
+                    self.profiles.get(profile_id).ok_or_else(|| {
+                        anyhow!("No profile with ID `{profile_id}`")
+                    })?;
+                profile_variables(profile)
+                    .into_iter()
+                    .map(|(key, value)| {
+                        json!({"key": key, "value": postman(value)})
+                    })
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+        Ok(json!({
+            "info": {"name": name, "schema": SCHEMA},
+            "item": items(self.recipes.tree()),
+            "variable": variables,
+        }))
+    }
+}
+
+/// Translate a template to Postman's `{{variable}}` syntax
+fn postman(template: &Template) -> String {
+    translate(template, variable)
+}
+
+fn variable(name: &str) -> String {
+    format!("{{{{{name}}}}}")
+}
+
+/// Convert a level of the recipe tree. Folders become nested item lists
+fn items(nodes: &IndexMap<RecipeId, RecipeNode>) -> Vec<Value> {
+    nodes
+        .values()
+        .filter_map(|node| match node {
+            RecipeNode::Folder(folder) => Some(json!({
+                "name": node.name(),
+                "item": items(&folder.children),
+            })),
+            RecipeNode::Recipe(recipe) => is_exportable(recipe).then(
+                || json!({"name": node.name(), "request": request(recipe)}),
+            ),
+        })
+        .collect()
+}
+
+fn request(recipe: &Recipe) -> Value {
+    let mut url = translate_url(recipe, variable);
+    if !recipe.query.is_empty() {
+        let query = recipe
+            .query
+            .iter()
+            .map(|(param, value)| format!("{param}={}", postman(value)))
+            .join("&");
+        let separator = if url.contains('?') { '&' } else { '?' };
+        url = format!("{url}{separator}{query}");
+    }
+
+    let mut request = json!({
+        "method": recipe.method.to_string(),
+        "header": recipe
+            .headers
+            .iter()
+            .map(|(header, value)| {
+                json!({"key": header, "value": postman(value)})
+            })
+            .collect_vec(),
+        "url": url,
+    });
+    if let Some(body) = &recipe.body {
+        request["body"] = self::body(body);
+    }
+    if let Some(auth) = recipe.authentication.as_ref().and_then(|auth| {
+        let auth = self::auth(auth);
+        if auth.is_none() {
+            warn!(
+                recipe_id = %recipe.id,
+                "Skipping authentication; type not supported by Postman"
+            );
+        }
+        auth
+    }) {
+        request["auth"] = auth;
+    }
+    request
+}
+
+fn body(body: &RecipeBody) -> Value {
+    match body {
+        RecipeBody::Raw(body) => json!({"mode": "raw", "raw": postman(body)}),
+        RecipeBody::FormMultipart(fields) => json!({
+            "mode": "formdata",
+            "formdata": fields
+                .iter()
+                .map(|(field, value)| match value {
+                    MultipartField::Text(value) => json!({
+                        "key": field,
+                        "type": "text",
+                        "value": postman(value),
+                    }),
+                    MultipartField::File(file) => json!({
+                        "key": field,
+                        "type": "file",
+                        "src": postman(&file.path),
+                    }),
+                })
+                .collect_vec(),
+        }),
+        RecipeBody::File(path) => {
+            json!({"mode": "file", "file": {"src": postman(path)}})
+        }
+    }
+}
+
+/// Convert authentication, or `None` if Postman doesn't support the type
+fn auth(authentication: &Authentication) -> Option<Value> {
+    // Postman stores auth parameters as a list of key-value pairs
+    let params = |params: &[(&str, Option<&Template>)]| {
+        params
+            .iter()
+            .filter_map(|(key, value)| {
+                Some(json!({
+                    "key": key,
+                    "value": postman((*value)?),
+                    "type": "string",
+                }))
+            })
+            .collect_vec()
+    };
+    match authentication {
+        Authentication::Basic { username, password } => Some(json!({
+            "type": "basic",
+            "basic": params(&[
+                ("username", Some(username)),
+                ("password", password.as_ref()),
+            ]),
+        })),
+        Authentication::Digest { username, password } => Some(json!({
+            "type": "digest",
+            "digest": params(&[
+                ("username", Some(username)),
+                ("password", password.as_ref()),
+            ]),
+        })),
+        Authentication::Bearer(token) => Some(json!({
+            "type": "bearer",
+            "bearer": params(&[("token", Some(token))]),
+        })),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collection::CollectionFile,
+        test_util::{assert_err, test_data_dir},
+    };
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use std::path::PathBuf;
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_postman_export(test_data_dir: PathBuf) {
+        let collection = CollectionFile::load(test_data_dir.join("export.yml"))
+            .await
+            .unwrap()
+            .collection;
+        let exported = collection
+            .to_postman("Fish", Some(&"local".into()))
+            .unwrap();
+        assert_eq!(
+            exported,
+            json!({
+                "info": {"name": "Fish", "schema": SCHEMA},
+                "item": [
+                    {
+                        "name": "Login",
+                        "request": {
+                            "method": "POST",
+                            "url": "{{base}}/login",
+                            "header": [
+                                {
+                                    "key": "content-type",
+                                    "value": "application/json",
+                                },
+                            ],
+                            "body": {
+                                "mode": "raw",
+                                "raw": r#"{"token": "{{token}}"}"#,
+                            },
+                        },
+                    },
+                    {
+                        "name": "Fishes",
+                        "item": [
+                            {
+                                "name": "list_fishes",
+                                "request": {
+                                    "method": "GET",
+                                    "url": "{{base_url}}/fishes?big=true",
+                                    "header": [],
+                                    "auth": {
+                                        "type": "bearer",
+                                        "bearer": [{
+                                            "key": "token",
+                                            "value": "{{chains.token}}",
+                                            "type": "string",
+                                        }],
+                                    },
+                                },
+                            },
+                            {
+                                "name": "Upload Photo",
+                                "request": {
+                                    "method": "POST",
+                                    "url": "{{base_url}}/fishes/photo",
+                                    "header": [],
+                                    "body": {
+                                        "mode": "formdata",
+                                        "formdata": [
+                                            {
+                                                "key": "name",
+                                                "type": "text",
+                                                "value": "Barry",
+                                            },
+                                            {
+                                                "key": "photo",
+                                                "type": "file",
+                                                "src": "./barry.png",
+                                            },
+                                        ],
+                                    },
+                                    "auth": {
+                                        "type": "basic",
+                                        "basic": [
+                                            {
+                                                "key": "username",
+                                                "value": "user",
+                                                "type": "string",
+                                            },
+                                            {
+                                                "key": "password",
+                                                "value": "{{token}}",
+                                                "type": "string",
+                                            },
+                                        ],
+                                    },
+                                },
+                            },
+                        ],
+                    },
+                    // WebSocket recipe is skipped
+                ],
+                "variable": [
+                    {"key": "base_url", "value": "http://localhost:3000"},
+                    {"key": "token", "value": "abc123"},
+                ],
+            })
+        );
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_postman_export_unknown_profile(test_data_dir: PathBuf) {
+        let collection = CollectionFile::load(test_data_dir.join("export.yml"))
+            .await
+            .unwrap()
+            .collection;
+        assert_err!(
+            collection.to_postman("Fish", Some(&"remote".into())),
+            "No profile with ID `remote`"
+        );
+    }
+}
@@ -0,0 +1,296 @@
+//! Recipe inheritance. A recipe can declare `extends: <recipe_id>` to inherit
+//! all the fields of another recipe, overriding just the fields it defines
+//! itself. This is resolved on the raw YAML before deserialization, so the
+//! child can omit required fields (e.g. `method`) that it inherits.
+
+use anyhow::bail;
+use itertools::Itertools;
+use serde_yaml::{Mapping, Value};
+use std::collections::HashMap;
+
+const EXTENDS: &str = "extends";
+/// Fields that are never inherited from the parent
+const NOT_INHERITED: &[&str] = &["name"];
+/// Fields that are merged key-by-key with the parent's, rather than replaced
+/// wholesale
+const MERGED: &[&str] = &["headers", "query"];
+
+/// Resolve every `extends` field in the collection's recipe tree. Recipes can
+/// extend any other recipe in the same file, regardless of folder structure.
+/// After this, no `extends` fields remain in the YAML.
+pub(super) fn resolve_extends(yaml: &mut Value) -> anyhow::Result<()> {
+    let Some(requests) = yaml.get_mut("requests") else {
+        return Ok(());
+    };
+
+    let mut nodes = HashMap::new();
+    collect_nodes(requests, &mut nodes);
+    let has_extends = nodes.values().any(|node| {
+        matches!(node, Node::Recipe(recipe) if recipe.contains_key(EXTENDS))
+    });
+    if !has_extends {
+        return Ok(());
+    }
+
+    let mut resolver = Resolver {
+        nodes,
+        resolved: HashMap::new(),
+        stack: Vec::new(),
+    };
+    replace_recipes(requests, &mut resolver)
+}
+
+/// A node in the recipe tree. For recipes, we need the original mapping. For
+/// folders, we just need to know it's a folder so we can give a good error if
+/// someone tries to extend one.
+enum Node {
+    Folder,
+    Recipe(Mapping),
+}
+
+/// Recursively build a map of every node in the tree by ID. Anything that
+/// doesn't look like a valid node is skipped, and will be caught during
+/// deserialization instead.
+fn collect_nodes(requests: &Value, nodes: &mut HashMap<String, Node>) {
+    let Value::Mapping(requests) = requests else {
+        return;
+    };
+    for (id, node) in requests {
+        let (Value::String(id), Value::Tagged(node)) = (id, node) else {
+            continue;
+        };
+        if node.tag == "folder" {
+            nodes.insert(id.clone(), Node::Folder);
+            if let Some(children) = node.value.get("requests") {
+                collect_nodes(children, nodes);
+            }
+        } else if let Value::Mapping(recipe) = &node.value {
+            nodes.insert(id.clone(), Node::Recipe(recipe.clone()));
+        }
+    }
+}
+
+/// Recursively replace every recipe that has an `extends` field with its
+/// resolved version
+fn replace_recipes(
+    requests: &mut Value,
+    resolver: &mut Resolver,
+) -> anyhow::Result<()> {
+    let Value::Mapping(requests) = requests else {
+        return Ok(());
+    };
+    for (id, node) in requests {
+        let (Value::String(id), Value::Tagged(node)) = (id, node) else {
+            continue;
+        };
+        if node.tag == "folder" {
+            if let Some(children) = node.value.get_mut("requests") {
+                replace_recipes(children, resolver)?;
+            }
+        } else if node.value.get(EXTENDS).is_some() {
+            node.value = Value::Mapping(resolver.resolve(id)?);
+        }
+    }
+    Ok(())
+}
+
+struct Resolver {
+    nodes: HashMap<String, Node>,
+    /// Cache of recipes that have already been resolved, in case a recipe is
+    /// extended multiple times
+    resolved: HashMap<String, Mapping>,
+    /// Recipes currently being resolved, used to detect cycles
+    stack: Vec<String>,
+}
+
+impl Resolver {
+    /// Get the full mapping for a recipe, including everything it inherits
+    fn resolve(&mut self, id: &str) -> anyhow::Result<Mapping> {
+        if let Some(recipe) = self.resolved.get(id) {
+            return Ok(recipe.clone());
+        }
+        if self.stack.iter().any(|other| other == id) {
+            let cycle = self
+                .stack
+                .iter()
+                .skip_while(|other| *other != id)
+                .chain([&id.to_owned()])
+                .map(|id| format!("`{id}`"))
+                .join(" -> ");
+            bail!("Cycle in `{EXTENDS}`: {cycle}");
+        }
+
+        let Some(Node::Recipe(recipe)) = self.nodes.get(id) else {
+            // Callers only pass IDs of recipes
+            bail!("Unknown recipe `{id}`");
+        };
+        let mut recipe = recipe.clone();
+        let Some(parent_id) = recipe.remove(EXTENDS) else {
+            return Ok(recipe);
+        };
+        let Value::String(parent_id) = parent_id else {
+            bail!("Recipe `{id}`: `{EXTENDS}` must be a recipe ID");
+        };
+        match self.nodes.get(&parent_id) {
+            Some(Node::Recipe(_)) => {}
+            Some(Node::Folder) => bail!(
+                "Recipe `{id}` cannot extend `{parent_id}` because it is a \
+                folder"
+            ),
+            None => {
+                bail!("Recipe `{id}` extends unknown recipe `{parent_id}`")
+            }
+        }
+
+        self.stack.push(id.to_owned());
+        let parent = self.resolve(&parent_id)?;
+        self.stack.pop();
+
+        let merged = merge(parent, recipe);
+        self.resolved.insert(id.to_owned(), merged.clone());
+        Ok(merged)
+    }
+}
+
+/// Merge a child recipe on top of its parent
+fn merge(parent: Mapping, child: Mapping) -> Mapping {
+    let mut merged: Mapping = parent
+        .into_iter()
+        .filter(|(key, _)| {
+            !key.as_str().is_some_and(|key| NOT_INHERITED.contains(&key))
+        })
+        .collect();
+    for (key, value) in child {
+        let is_merged = key.as_str().is_some_and(|key| MERGED.contains(&key));
+        match (merged.get_mut(&key), value) {
+            (Some(Value::Mapping(parent)), Value::Mapping(child))
+                if is_merged =>
+            {
+                let is_headers = key.as_str() == Some("headers");
+                for (child_key, child_value) in child {
+                    // Header names are case-insensitive
+                    if let (true, Some(name)) = (is_headers, child_key.as_str())
+                    {
+                        parent.retain(|key, _| {
+                            !key.as_str().is_some_and(|key| {
+                                key.eq_ignore_ascii_case(name)
+                            })
+                        });
+                    }
+                    parent.insert(child_key, child_value);
+                }
+            }
+            (_, value) => {
+                merged.insert(key, value);
+            }
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        collection::{parse_collection, Authentication, Method, RecipeBody},
+        test_util::assert_err,
+    };
+    use indexmap::indexmap;
+    use rstest::rstest;
+
+    #[test]
+    fn test_extends() {
+        let collection = parse_collection(
+            br#"
+requests:
+  base: !request
+    name: Base
+    method: POST
+    url: /fishes
+    headers:
+      Accept: application/json
+      X-Fish: base
+    query:
+      big: "true"
+    authentication: !bearer token
+    body: "{}"
+  folder: !folder
+    requests:
+      child: !request
+        extends: base
+        url: /fishes/1
+        headers:
+          x-fish: child
+        query:
+          page: "2"
+      grandchild: !request
+        extends: child
+        method: PUT
+        body: "[]"
+"#,
+        )
+        .unwrap();
+
+        let child = collection.recipes.get_recipe(&"child".into()).unwrap();
+        // Name isn't inherited
+        assert_eq!(child.name(), "child");
+        assert_eq!(child.method, Method::Post);
+        assert_eq!(child.url, "/fishes/1".into());
+        assert_eq!(
+            child.headers,
+            indexmap! {
+                "Accept".into() => "application/json".into(),
+                "x-fish".into() => "child".into(),
+            }
+        );
+        assert_eq!(
+            child.query,
+            indexmap! {
+                "big".into() => "true".into(),
+                "page".into() => "2".into(),
+            }
+        );
+        assert_eq!(
+            child.authentication,
+            Some(Authentication::Bearer("token".into()))
+        );
+        assert_eq!(child.body, Some(RecipeBody::Raw("{}".into())));
+
+        let grandchild =
+            collection.recipes.get_recipe(&"grandchild".into()).unwrap();
+        assert_eq!(grandchild.method, Method::Put);
+        assert_eq!(grandchild.url, "/fishes/1".into());
+        assert_eq!(grandchild.headers, child.headers);
+        assert_eq!(grandchild.body, Some(RecipeBody::Raw("[]".into())));
+    }
+
+    #[rstest]
+    #[case::unknown(
+        "r1: !request {extends: r2}",
+        "Recipe `r1` extends unknown recipe `r2`"
+    )]
+    #[case::folder(
+        "r1: !request {extends: f1}\nf1: !folder {}",
+        "Recipe `r1` cannot extend `f1` because it is a folder"
+    )]
+    #[case::cycle(
+        "r1: !request {extends: r2}\nr2: !request {extends: r1}",
+        "Cycle in `extends`: `r1` -> `r2` -> `r1`"
+    )]
+    #[case::self_cycle(
+        "r1: !request {extends: r1}",
+        "Cycle in `extends`: `r1` -> `r1`"
+    )]
+    fn test_extends_error(
+        #[case] requests: &str,
+        #[case] expected_error: &str,
+    ) {
+        let yaml = format!(
+            "requests:\n{}",
+            requests
+                .lines()
+                .map(|line| format!("  {line}\n"))
+                .collect::<String>()
+        );
+        assert_err!(parse_collection(yaml.as_bytes()), expected_error);
+    }
+}
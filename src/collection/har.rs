@@ -0,0 +1,232 @@
+//! Import request collections from HAR (HTTP Archive) files, e.g. as exported
+//! by browser devtools
+
+use crate::{
+    collection::{
+        Collection, Defaults, Method, Recipe, RecipeBody, RecipeId, RecipeKind,
+        RecipeNode, RecipeTree,
+    },
+    export::har::{self, Har},
+    template::Template,
+};
+use anyhow::{anyhow, Context};
+use indexmap::IndexMap;
+use itertools::Itertools;
+use reqwest::header;
+use std::{
+    collections::HashSet,
+    fs::File,
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+use url::Url;
+
+/// Headers that are specific to a single connection, and will be set by the
+/// HTTP client anyway
+const IGNORED_HEADERS: &[&str] = &["host", "content-length", "connection"];
+
+impl Collection {
+    /// Convert a HAR file into the slumber format. Each unique method+URL
+    /// becomes a recipe. If the same request appears multiple times, the first
+    /// occurrence is used.
+    ///
+    /// This is not async because it's only called by the CLI, where we don't
+    /// care about blocking. It keeps the code simpler.
+    pub fn from_har(har_file: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let har_file = har_file.as_ref();
+        info!(file = ?har_file, "Loading HAR file");
+        let file = File::open(har_file)
+            .context(format!("Error opening HAR file {har_file:?}"))?;
+        let har: Har = serde_json::from_reader(file)
+            .context(format!("Error deserializing HAR file {har_file:?}"))?;
+
+        let mut seen: HashSet<(String, String)> = HashSet::new();
+        let mut ids: HashSet<RecipeId> = HashSet::new();
+        let recipes = har
+            .log
+            .entries
+            .into_iter()
+            .map(|entry| entry.request)
+            .filter(|request| {
+                seen.insert((
+                    request.method.to_uppercase(),
+                    request.url.clone(),
+                ))
+            })
+            .filter_map(|request| {
+                let url = request.url.clone();
+                let result = build_recipe(request, &mut ids);
+                if let Err(error) = &result {
+                    warn!("Skipping request `{url}`: {error:#}");
+                }
+                result.ok()
+            })
+            .map(|recipe| (recipe.id.clone(), RecipeNode::Recipe(recipe)))
+            .collect::<IndexMap<_, _>>();
+
+        Ok(Collection {
+            imports: Vec::new(),
+            profiles: IndexMap::new(),
+            defaults: Defaults::default(),
+            recipes: RecipeTree::new(recipes).map_err(|duplicate_id| {
+                anyhow!("Duplicate recipe ID `{duplicate_id}`")
+            })?,
+            chains: IndexMap::new(),
+            hooks: Default::default(),
+            _ignore: serde::de::IgnoredAny,
+        })
+    }
+}
+
+/// Convert a single HAR request into a recipe. The given set of IDs is used to
+/// generate a unique ID, and the new ID is added to it.
+fn build_recipe(
+    request: har::Request,
+    ids: &mut HashSet<RecipeId>,
+) -> anyhow::Result<Recipe> {
+    let method: Method = request
+        .method
+        .parse()
+        .map_err(|_| anyhow!("Unsupported method `{}`", request.method))?;
+    let (url, query) = split_query(request.url.parse().context("Invalid URL")?);
+
+    let mut headers: IndexMap<String, Template> = request
+        .headers
+        .into_iter()
+        // HTTP/2 pseudo-headers (e.g. `:authority`) aren't real headers
+        .filter(|header| !header.name.starts_with(':'))
+        .map(|header| (header.name.to_lowercase(), header.value))
+        .filter(|(name, _)| !IGNORED_HEADERS.contains(&name.as_str()))
+        .map(|(name, value)| (name, Template::dangerous(value)))
+        .collect();
+    let body = request
+        .post_data
+        .filter(|post_data| !post_data.text.is_empty())
+        .map(|post_data| {
+            if !post_data.mime_type.is_empty() {
+                headers
+                    .entry(header::CONTENT_TYPE.as_str().into())
+                    .or_insert_with(|| {
+                        Template::dangerous(post_data.mime_type)
+                    });
+            }
+            RecipeBody::Raw(Template::dangerous(post_data.text))
+        });
+
+    let id = unique_id(&method, &url, ids);
+    Ok(Recipe {
+        id,
+        kind: RecipeKind::Http,
+        name: Some(format!("{method} {}", url.path())),
+        method,
+        url: Template::dangerous(url.to_string()),
+        base_url: None,
+        dir: PathBuf::new(),
+        body,
+        authentication: None,
+        query,
+        headers,
+        save_response_to: None,
+        throttle: None,
+        timeout: None,
+        retry: None,
+        http_version: None,
+        conditional: false,
+        cache_ttl: None,
+        rate_limit: None,
+        compression: None,
+        connection: None,
+        assertions: Vec::new(),
+        tags: Vec::new(),
+        graphql: false,
+    })
+}
+
+/// Pull the query out of a URL into a separate map, so it's easier to edit in
+/// the recipe
+pub(super) fn split_query(mut url: Url) -> (Url, IndexMap<String, Template>) {
+    let query = url
+        .query_pairs()
+        .map(|(name, value)| {
+            (name.into_owned(), Template::dangerous(value.into_owned()))
+        })
+        .collect();
+    url.set_query(None);
+    (url, query)
+}
+
+/// Generate a readable recipe ID from the method and URL path, e.g.
+/// `get_users_123`. If the ID is already taken, append a number to it.
+pub(super) fn unique_id(
+    method: &Method,
+    url: &Url,
+    ids: &mut HashSet<RecipeId>,
+) -> RecipeId {
+    let path = url
+        .path()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .join("_");
+    let base = if path.is_empty() {
+        method.to_string().to_lowercase()
+    } else {
+        format!("{}_{}", method.to_string().to_lowercase(), path)
+    };
+
+    let mut id: RecipeId = base.clone().into();
+    for i in 2.. {
+        if !ids.contains(&id) {
+            break;
+        }
+        id = format!("{base}_{i}").into();
+    }
+    ids.insert(id.clone());
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{collection::CollectionFile, test_util::test_data_dir};
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use std::path::PathBuf;
+
+    const HAR_FILE: &str = "har.json";
+    /// Assertion expectation is stored in a separate file, like the Insomnia
+    /// importer
+    const HAR_IMPORTED_FILE: &str = "har_imported.yml";
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_har_import(test_data_dir: PathBuf) {
+        let mut imported =
+            Collection::from_har(test_data_dir.join(HAR_FILE)).unwrap();
+        let expected =
+            CollectionFile::load(test_data_dir.join(HAR_IMPORTED_FILE))
+                .await
+                .unwrap()
+                .collection;
+        // Loaded recipes know which file they came from
+        imported.recipes.set_dir(&test_data_dir);
+        assert_eq!(imported, expected);
+    }
+
+    #[rstest]
+    #[case::root("https://example.com", "get")]
+    #[case::path("https://example.com/users/123", "get_users_123")]
+    #[case::special_chars("https://example.com/a-b/c.json/", "get_a_b_c_json")]
+    fn test_unique_id(#[case] url: &str, #[case] expected: &str) {
+        let mut ids = HashSet::new();
+        let url = url.parse().unwrap();
+        assert_eq!(
+            unique_id(&Method::Get, &url, &mut ids),
+            RecipeId::from(expected.to_owned())
+        );
+        // Second time around, we get a suffix
+        assert_eq!(
+            unique_id(&Method::Get, &url, &mut ids),
+            RecipeId::from(format!("{expected}_2"))
+        );
+    }
+}
@@ -0,0 +1,429 @@
+//! Collection composition. A collection file can list other collection files
+//! under `imports`, and their profiles, chains, and recipes are merged into
+//! the importing collection at load time.
+
+use crate::collection::{
+    dotenv, parse_collection, Chain, ChainId, Collection, Profile, ProfileId,
+    RecipeId, RecipeNode, RecipeTree,
+};
+use anyhow::{anyhow, bail, Context};
+use indexmap::IndexMap;
+use itertools::Itertools;
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Resolve all imports (recursively) of a freshly parsed collection, merging
+/// them into a single collection. The root file's own definitions come first,
+/// followed by each import in the order listed. Each file is only loaded once,
+/// so two files can import the same shared file. Import cycles and IDs defined
+/// in more than one file are errors. The path of every imported file (and its
+/// `.env` files) is added to `dependencies`.
+///
+/// This is blocking, so it should be called from a blocking task.
+pub(super) fn resolve_imports(
+    path: &Path,
+    collection: Collection,
+    dependencies: &mut Vec<PathBuf>,
+) -> anyhow::Result<Collection> {
+    if collection.imports.is_empty() {
+        return Ok(collection);
+    }
+
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("Error resolving path {path:?}"))?;
+    let mut merger = Merger::default();
+    let hooks = collection.hooks.clone();
+    let defaults = collection.defaults.clone();
+    merger.add(path, collection)?;
+    dependencies.append(&mut merger.dependencies);
+
+    let recipes = RecipeTree::new(merger.recipes).map_err(|id| {
+        // Should be impossible because we checked for duplicates already
+        anyhow!("Duplicate recipe/folder ID `{id}`")
+    })?;
+    Ok(Collection {
+        profiles: merger.profiles,
+        chains: merger.chains,
+        recipes,
+        defaults,
+        hooks,
+        ..Collection::default()
+    })
+}
+
+/// Accumulator for merging collection files together
+#[derive(Default)]
+struct Merger {
+    profiles: IndexMap<ProfileId, Profile>,
+    chains: IndexMap<ChainId, Chain>,
+    recipes: IndexMap<RecipeId, RecipeNode>,
+    /// The file that defined each ID, so conflicts can name both files. IDs
+    /// are namespaced by type, because a profile and a recipe can share an ID
+    origins: HashMap<(&'static str, String), PathBuf>,
+    /// Files currently being loaded, in import order. Used to detect cycles
+    stack: Vec<PathBuf>,
+    /// Every file that's been loaded so far
+    loaded: HashSet<PathBuf>,
+    /// Imported files and `.env` files, in load order
+    dependencies: Vec<PathBuf>,
+}
+
+impl Merger {
+    /// Merge a collection, then recursively load and merge its imports.
+    /// `path` must be canonicalized.
+    fn add(
+        &mut self,
+        path: PathBuf,
+        collection: Collection,
+    ) -> anyhow::Result<()> {
+        for id in collection.profiles.keys() {
+            self.claim("Profile", id, &path)?;
+        }
+        for id in collection.chains.keys() {
+            self.claim("Chain", id, &path)?;
+        }
+        for (_, node) in collection.recipes.iter() {
+            self.claim("Recipe/folder", node.id(), &path)?;
+        }
+        self.profiles.extend(collection.profiles);
+        self.chains.extend(collection.chains);
+        self.recipes.extend(collection.recipes.tree().clone());
+
+        self.loaded.insert(path.clone());
+        self.stack.push(path);
+        let dir = self.stack.last().and_then(|path| path.parent());
+        let import_paths = collection
+            .imports
+            .iter()
+            .map(|import| {
+                let import_path = dir.map(|dir| dir.join(import));
+                let import_path = import_path.as_deref().unwrap_or(import);
+                import_path.canonicalize().with_context(|| {
+                    format!("Error loading imported file {import_path:?}")
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        for import_path in import_paths {
+            if self.stack.contains(&import_path) {
+                let cycle = self
+                    .stack
+                    .iter()
+                    .skip_while(|path| **path != import_path)
+                    .chain([&import_path])
+                    .map(|path| format!("{path:?}"))
+                    .join(" -> ");
+                bail!("Import cycle: {cycle}");
+            }
+            if self.loaded.contains(&import_path) {
+                continue;
+            }
+
+            self.dependencies.push(import_path.clone());
+            let imported = load_import(&import_path, &mut self.dependencies)
+                .with_context(|| {
+                    format!("Error loading imported file {import_path:?}")
+                })?;
+            self.add(import_path, imported)?;
+        }
+        self.stack.pop();
+        Ok(())
+    }
+
+    /// Record that an ID was defined in a file. If it was already defined by
+    /// another file (or the same file), return an error naming both
+    fn claim(
+        &mut self,
+        kind: &'static str,
+        id: &str,
+        path: &Path,
+    ) -> anyhow::Result<()> {
+        match self.origins.entry((kind, id.to_owned())) {
+            Entry::Occupied(entry) => Err(anyhow!(
+                "{kind} ID `{id}` is defined in both {:?} and {path:?}",
+                entry.get()
+            )),
+            Entry::Vacant(entry) => {
+                entry.insert(path.to_owned());
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Load and parse a single imported file, without resolving its imports. The
+/// file's own `defaults` are applied to its recipes immediately, because they
+/// only apply within that file. The root file's defaults are applied to the
+/// merged collection later. Similarly, `.env` files are loaded immediately
+/// because their paths are relative to this file. Their paths are added to
+/// `dependencies`. For the same reason, each recipe is tagged with this file's
+/// directory.
+fn load_import(
+    path: &Path,
+    dependencies: &mut Vec<PathBuf>,
+) -> anyhow::Result<Collection> {
+    let bytes = fs::read(path)?;
+    let mut collection = parse_collection(&bytes)?;
+    if let Some(dir) = path.parent() {
+        dependencies.extend(dotenv::load_dotenv(&mut collection, dir)?);
+        collection.recipes.set_dir(dir);
+    }
+    collection.recipes.apply_defaults(&collection.defaults);
+    // Hooks apply to every request, so allowing them in imports would make it
+    // hard to tell where they're coming from
+    if collection.hooks.pre_request.is_some()
+        || collection.hooks.post_response.is_some()
+    {
+        bail!("`hooks` can only be defined in the root collection file");
+    }
+    Ok(collection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collection::CollectionFile,
+        test_util::{assert_err, temp_dir, TempDir},
+    };
+    use rstest::rstest;
+
+    /// Write a file into the temp directory
+    fn write(dir: &Path, name: &str, content: &str) -> PathBuf {
+        let path = dir.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_imports(temp_dir: TempDir) {
+        let path = write(
+            &temp_dir,
+            "slumber.yml",
+            r#"
+imports:
+  - ./users/users.yml
+  - ./profiles.yml
+requests:
+  login: !request
+    method: POST
+    url: "{{base_url}}/login"
+"#,
+        );
+        write(
+            &temp_dir,
+            "users/users.yml",
+            r#"
+imports:
+  # Relative to this file
+  - ../chains.yml
+requests:
+  users: !folder
+    requests:
+      get_user: !request
+        method: GET
+        url: "{{base_url}}/users/{{chains.user_id}}"
+"#,
+        );
+        write(
+            &temp_dir,
+            "chains.yml",
+            r#"
+chains:
+  user_id:
+    source: !prompt
+      message: User ID
+"#,
+        );
+        write(
+            &temp_dir,
+            "profiles.yml",
+            r#"
+profiles:
+  local:
+    data:
+      base_url: http://localhost
+"#,
+        );
+
+        let collection_file = CollectionFile::load(path.clone()).await.unwrap();
+        // Imports are watched for changes too
+        let dir = temp_dir.canonicalize().unwrap();
+        assert_eq!(
+            collection_file.files().collect_vec(),
+            [
+                path,
+                dir.join("users/users.yml"),
+                dir.join("chains.yml"),
+                dir.join("profiles.yml")
+            ]
+        );
+        let collection = collection_file.collection;
+        assert!(collection.imports.is_empty());
+        assert_eq!(
+            collection
+                .profiles
+                .keys()
+                .map(|id| id.as_str())
+                .collect_vec(),
+            ["local"]
+        );
+        assert_eq!(
+            collection.chains.keys().map(|id| id.as_str()).collect_vec(),
+            ["user_id"]
+        );
+        assert_eq!(
+            collection
+                .recipes
+                .iter()
+                .map(|(_, node)| node.id().as_str())
+                .collect_vec(),
+            ["login", "users", "get_user"]
+        );
+    }
+
+    /// An imported file's defaults only apply to its own recipes, but the root
+    /// file's defaults apply to everything
+    #[rstest]
+    #[tokio::test]
+    async fn test_imports_defaults(temp_dir: TempDir) {
+        let path = write(
+            &temp_dir,
+            "slumber.yml",
+            r#"
+imports: [other.yml]
+defaults:
+  headers:
+    accept: application/json
+    x-file: root
+requests:
+  root: !request
+    method: GET
+    url: /root
+"#,
+        );
+        write(
+            &temp_dir,
+            "other.yml",
+            r#"
+defaults:
+  headers:
+    x-file: other
+requests:
+  other: !request
+    method: GET
+    url: /other
+"#,
+        );
+
+        let collection = CollectionFile::load(path).await.unwrap().collection;
+        let headers = |id: &str| {
+            collection
+                .recipes
+                .get_recipe(&id.into())
+                .unwrap()
+                .headers
+                .iter()
+                .map(|(header, value)| (header.as_str(), value.to_string()))
+                .collect_vec()
+        };
+        assert_eq!(
+            headers("root"),
+            [
+                ("accept", "application/json".into()),
+                ("x-file", "root".into())
+            ]
+        );
+        assert_eq!(
+            headers("other"),
+            [
+                ("accept", "application/json".into()),
+                ("x-file", "other".into())
+            ]
+        );
+    }
+
+    /// Two files importing the same file is fine
+    #[rstest]
+    #[tokio::test]
+    async fn test_imports_diamond(temp_dir: TempDir) {
+        let path = write(&temp_dir, "slumber.yml", "imports: [a.yml, b.yml]");
+        write(&temp_dir, "a.yml", "imports: [shared.yml]");
+        write(&temp_dir, "b.yml", "imports: [shared.yml]");
+        write(&temp_dir, "shared.yml", "profiles: {shared: {data: {}}}");
+
+        let collection = CollectionFile::load(path).await.unwrap().collection;
+        assert_eq!(
+            collection
+                .profiles
+                .keys()
+                .map(|id| id.as_str())
+                .collect_vec(),
+            ["shared"]
+        );
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_imports_cycle(temp_dir: TempDir) {
+        let path = write(&temp_dir, "slumber.yml", "imports: [a.yml]");
+        write(&temp_dir, "a.yml", "imports: [b.yml]");
+        write(&temp_dir, "b.yml", "imports: [a.yml]");
+
+        assert_err!(CollectionFile::load(path).await, "Import cycle");
+    }
+
+    #[rstest]
+    #[case::profile(
+        "profiles: {p1: {data: {}}}",
+        "Profile ID `p1` is defined in both"
+    )]
+    #[case::chain(
+        "chains: {c1: {source: !prompt {}}}",
+        "Chain ID `c1` is defined in both"
+    )]
+    #[case::recipe(
+        "requests: {r1: !request {method: GET, url: /}}",
+        "Recipe/folder ID `r1` is defined in both"
+    )]
+    #[tokio::test]
+    async fn test_imports_conflict(
+        temp_dir: TempDir,
+        #[case] content: &str,
+        #[case] expected_error: &str,
+    ) {
+        let path = write(
+            &temp_dir,
+            "slumber.yml",
+            &format!("imports: [other.yml]\n{content}"),
+        );
+        write(&temp_dir, "other.yml", content);
+
+        assert_err!(CollectionFile::load(path).await, expected_error);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_imports_errors(temp_dir: TempDir) {
+        let path = write(&temp_dir, "slumber.yml", "imports: [missing.yml]");
+        assert_err!(
+            CollectionFile::load(path).await,
+            "Error loading imported file"
+        );
+
+        let path = write(&temp_dir, "hooks.yml", "imports: [other.yml]");
+        write(&temp_dir, "other.yml", "hooks: {pre_request: [echo]}");
+        assert_err!(
+            CollectionFile::load(path).await,
+            "`hooks` can only be defined in the root collection file"
+        );
+    }
+}
@@ -3,8 +3,8 @@
 
 use crate::{
     collection::{
-        self, Collection, Folder, Method, Profile, ProfileId, Recipe, RecipeId,
-        RecipeNode, RecipeTree,
+        self, Collection, Defaults, Folder, Method, Profile, ProfileId, Recipe,
+        RecipeBody, RecipeId, RecipeKind, RecipeNode, RecipeTree,
     },
     template::Template,
 };
@@ -13,7 +13,11 @@ use indexmap::IndexMap;
 use itertools::Itertools;
 use reqwest::header;
 use serde::{Deserialize, Deserializer};
-use std::{collections::HashMap, fs::File, path::Path};
+use std::{
+    collections::HashMap,
+    fs::File,
+    path::{Path, PathBuf},
+};
 use tracing::{info, warn};
 
 impl Collection {
@@ -62,11 +66,14 @@ impl Collection {
             build_recipe_tree(&workspace_id, request_groups, requests)?;
 
         Ok(Collection {
+            imports: Vec::new(),
             profiles,
+            defaults: Defaults::default(),
             recipes,
             // Parse templates into chains:
             // https://github.com/LucasPickering/slumber/issues/164
             chains: IndexMap::new(),
+            hooks: Default::default(),
             _ignore: serde::de::IgnoredAny,
         })
     }
@@ -158,6 +165,10 @@ enum Authentication {
     Bearer {
         token: String,
     },
+    Digest {
+        username: String,
+        password: String,
+    },
     /// Catch-all for unknown variants
     #[serde(untagged)]
     Other {
@@ -244,6 +255,11 @@ impl From<Environment> for Profile {
         Profile {
             id: environment.id.into(),
             name: Some(environment.name),
+            base_url: None,
+            proxy: None,
+            no_proxy: None,
+            dotenv: None,
+            sensitive: Vec::new(),
             data: environment
                 .data
                 .into_iter()
@@ -259,6 +275,7 @@ impl From<RequestGroup> for RecipeNode {
             id: folder.id.into(),
             name: Some(folder.name),
             // This will be populated later
+            defaults: Defaults::default(),
             children: IndexMap::new(),
         })
     }
@@ -297,10 +314,13 @@ impl From<Request> for RecipeNode {
 
         RecipeNode::Recipe(Recipe {
             id: request.id.into(),
+            kind: RecipeKind::Http,
             name: Some(request.name),
             method: request.method,
             url: request.url,
-            body: request.body.map(|body| body.text),
+            base_url: None,
+            dir: PathBuf::new(),
+            body: request.body.map(|body| RecipeBody::Raw(body.text)),
             query: request
                 .parameters
                 .into_iter()
@@ -308,6 +328,19 @@ impl From<Request> for RecipeNode {
                 .collect(),
             headers,
             authentication,
+            save_response_to: None,
+            throttle: None,
+            timeout: None,
+            retry: None,
+            http_version: None,
+            conditional: false,
+            cache_ttl: None,
+            rate_limit: None,
+            compression: None,
+            connection: None,
+            assertions: Vec::new(),
+            tags: Vec::new(),
+            graphql: false,
         })
     }
 }
@@ -327,6 +360,12 @@ impl TryFrom<Authentication> for collection::Authentication {
             Authentication::Bearer { token } => Ok(
                 collection::Authentication::Bearer(Template::dangerous(token)),
             ),
+            Authentication::Digest { username, password } => {
+                Ok(collection::Authentication::Digest {
+                    username: Template::dangerous(username),
+                    password: Some(Template::dangerous(password)),
+                })
+            }
             // Caller should print a warning for this
             Authentication::Other { kind } => Err(kind),
         }
@@ -373,6 +412,11 @@ fn build_profiles(
                 Profile {
                     id,
                     name: Some(environment.name),
+                    base_url: None,
+                    proxy: None,
+                    no_proxy: None,
+                    dotenv: None,
+                    sensitive: Vec::new(),
                     data,
                 },
             )
@@ -480,7 +524,7 @@ mod tests {
     #[rstest]
     #[tokio::test]
     async fn test_insomnia_import(test_data_dir: PathBuf) {
-        let imported =
+        let mut imported =
             Collection::from_insomnia(test_data_dir.join(INSOMNIA_FILE))
                 .unwrap();
         let expected =
@@ -488,6 +532,8 @@ mod tests {
                 .await
                 .unwrap()
                 .collection;
+        // Loaded recipes know which file they came from
+        imported.recipes.set_dir(&test_data_dir);
         assert_eq!(imported, expected);
     }
 
@@ -4,50 +4,161 @@ use crate::{
     collection::{
         cereal,
         recipe_tree::{RecipeNode, RecipeTree},
+        schema,
     },
-    http::{ContentType, Query},
+    http::{ContentType, Query, SelectorType},
     template::Template,
 };
 use anyhow::anyhow;
+use bytesize::ByteSize;
 use derive_more::{Deref, Display, From, FromStr};
 use equivalent::Equivalent;
 use indexmap::IndexMap;
 use itertools::Itertools;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
-use strum::{EnumIter, IntoEnumIterator};
+use std::{net::IpAddr, path::PathBuf, time::Duration};
+use strum::{EnumIter, IntoEnumIterator, IntoStaticStr};
 
 /// A collection of profiles, requests, etc. This is the primary Slumber unit
 /// of configuration.
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(deny_unknown_fields)]
 pub struct Collection {
+    /// Other collection files to merge into this one, relative to this file.
+    /// Resolved at load time, so this is always empty on a loaded collection
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub imports: Vec<PathBuf>,
     #[serde(default, deserialize_with = "cereal::deserialize_id_map")]
     pub profiles: IndexMap<ProfileId, Profile>,
+    /// Values inherited by every recipe in the collection
+    #[serde(default)]
+    pub defaults: Defaults,
     #[serde(default, deserialize_with = "cereal::deserialize_id_map")]
     pub chains: IndexMap<ChainId, Chain>,
     /// Internally we call these recipes, but to a user `requests` is more
     /// intuitive
     #[serde(default, rename = "requests")]
+    #[schemars(description = "Request recipes and folders")]
     pub recipes: RecipeTree,
+    #[serde(default)]
+    pub hooks: Hooks,
     /// A hack-ish to allow users to add arbitrary data to their collection
     /// file without triggering a unknown field error. Ideally we could
     /// ignore anything that starts with `.` (recursively) but that
     /// requires a custom serde impl for each type, or changes to the macro
     #[serde(default, skip_serializing, rename = ".ignore")]
+    #[schemars(
+        with = "Option<serde_json::Value>",
+        description = "Arbitrary data (e.g. YAML anchors), ignored by Slumber"
+    )]
     pub _ignore: serde::de::IgnoredAny,
 }
 
+/// Recipe fields that are inherited by every recipe in a collection or folder,
+/// unless the recipe sets its own value. Folder defaults take precedence over
+/// the defaults of their parents. These are applied to each recipe when the
+/// collection is loaded.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(test, derive(PartialEq))]
+#[serde(deny_unknown_fields)]
+pub struct Defaults {
+    /// Prefix for recipes with relative URLs. If the result is still relative
+    /// (e.g. `/api`), the profile's `base_url` is applied on top of it.
+    pub base_url: Option<Template>,
+    /// Recipe headers with the same name (case-insensitive) take precedence
+    #[serde(default)]
+    pub headers: IndexMap<String, Template>,
+    /// Recipe query parameters with the same name take precedence
+    #[serde(default)]
+    pub query: IndexMap<String, Template>,
+    pub authentication: Option<Authentication>,
+    /// Template fields, used the same way as a profile's `data`. These take
+    /// precedence over profile fields with the same name. They're substituted
+    /// into the recipe's templates at load time.
+    #[serde(default)]
+    pub data: IndexMap<String, Template>,
+}
+
+/// External commands that run around every HTTP request in the collection.
+/// Each command receives the request (and response, for `post_response`) as
+/// JSON on stdin, and can print JSON to stdout to modify it.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(test, derive(PartialEq))]
+#[serde(deny_unknown_fields)]
+pub struct Hooks {
+    /// Run after the request is built, right before it's sent
+    pub pre_request: Option<Vec<Template>>,
+    /// Run after the response is received, before it's stored
+    pub post_response: Option<Vec<Template>>,
+}
+
 /// Mutually exclusive hot-swappable config group
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(deny_unknown_fields)]
 pub struct Profile {
     #[serde(skip)] // This will be auto-populated from the map key
     pub id: ProfileId,
     pub name: Option<String>,
+    /// Prefix for recipes with relative URLs (e.g. `/users`). Absolute recipe
+    /// URLs are used as-is.
+    pub base_url: Option<Template>,
+    /// Proxy for requests made under this profile. Overrides the global
+    /// `proxy` config field, including with [ProfileProxy::Direct] to not use
+    /// a proxy at all
+    #[schemars(with = "Option<String>")]
+    pub proxy: Option<ProfileProxy>,
+    /// Hosts that should bypass the proxy. Overrides the global `no_proxy`
+    /// config field
+    pub no_proxy: Option<String>,
+    /// Path to a `.env` file, relative to the collection file. Each variable
+    /// in the file is added to `data`, unless `data` already has a field with
+    /// the same name. Loaded (and cleared) when the collection is loaded
+    pub dotenv: Option<PathBuf>,
+    #[serde(default, deserialize_with = "cereal::deserialize_profile_data")]
+    // Null is accepted as an empty map
+    #[schemars(with = "Option<IndexMap<String, Template>>")]
     pub data: IndexMap<String, Template>,
+    /// Fields in `data` whose values should be masked in the UI and redacted
+    /// from request history
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sensitive: Vec<String>,
+}
+
+/// Proxy setting for a profile. Deserialized from a string: `direct`, or a
+/// proxy URL.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum ProfileProxy {
+    /// Connect directly, even if the config has a proxy
+    Direct,
+    /// Route requests through the proxy at this URL
+    Url(String),
+}
+
+impl ProfileProxy {
+    const DIRECT: &'static str = "direct";
+}
+
+impl From<String> for ProfileProxy {
+    fn from(value: String) -> Self {
+        if value == Self::DIRECT {
+            Self::Direct
+        } else {
+            Self::Url(value)
+        }
+    }
+}
+
+impl From<ProfileProxy> for String {
+    fn from(value: ProfileProxy) -> Self {
+        match value {
+            ProfileProxy::Direct => ProfileProxy::DIRECT.into(),
+            ProfileProxy::Url(url) => url,
+        }
+    }
 }
 
 #[derive(
@@ -65,20 +176,86 @@ pub struct Profile {
 )]
 pub struct ProfileId(String);
 
+/// Temporary replacements for profile fields, keyed by profile and then by
+/// field. These live only as long as the TUI session, and are never written
+/// to the collection file.
+#[derive(Clone, Debug, Default)]
+pub struct ProfileOverrides(IndexMap<ProfileId, IndexMap<String, Template>>);
+
+impl ProfileOverrides {
+    /// Override a single profile field. The field doesn't need to be defined
+    /// in the profile already.
+    pub fn set(
+        &mut self,
+        profile_id: ProfileId,
+        field: String,
+        value: Template,
+    ) {
+        self.0.entry(profile_id).or_default().insert(field, value);
+    }
+
+    /// Remove the override for a single profile field, if any
+    pub fn remove(&mut self, profile_id: &ProfileId, field: &str) {
+        if let Some(fields) = self.0.get_mut(profile_id) {
+            fields.shift_remove(field);
+        }
+    }
+
+    /// Remove all overrides, for all profiles
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.values().all(IndexMap::is_empty)
+    }
+
+    /// Has the given profile field been overridden?
+    pub fn is_overridden(&self, profile_id: &ProfileId, field: &str) -> bool {
+        self.0
+            .get(profile_id)
+            .is_some_and(|fields| fields.contains_key(field))
+    }
+
+    /// Apply all overrides to a collection. Overrides for profiles that aren't
+    /// in the collection are ignored.
+    pub fn apply(&self, collection: &mut Collection) {
+        for profile in collection.profiles.values_mut() {
+            self.apply_profile(profile);
+        }
+    }
+
+    /// Apply this profile's overrides to it
+    pub fn apply_profile(&self, profile: &mut Profile) {
+        if let Some(fields) = self.0.get(&profile.id) {
+            profile.data.extend(
+                fields
+                    .iter()
+                    .map(|(field, value)| (field.clone(), value.clone())),
+            );
+        }
+    }
+}
+
 /// A gathering of like-minded recipes and/or folders
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(deny_unknown_fields)]
 pub struct Folder {
     #[serde(skip)] // This will be auto-populated from the map key
     pub id: RecipeId,
     pub name: Option<String>,
+    /// Values inherited by every recipe in this folder, including nested
+    /// folders. These override the defaults of any parent
+    #[serde(default)]
+    pub defaults: Defaults,
     /// RECURSION. Use `requests` in serde to match the root field.
     #[serde(
         default,
         deserialize_with = "cereal::deserialize_id_map",
         rename = "requests"
     )]
+    #[schemars(description = "Request recipes and folders")]
     pub children: IndexMap<RecipeId, RecipeNode>,
 }
 
@@ -86,24 +263,98 @@ pub struct Folder {
 /// order to distinguish it from a single instance of an HTTP request. And it's
 /// not called `RequestTemplate` because the word "template" has a specific
 /// meaning related to string interpolation.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(deny_unknown_fields)]
+#[schemars(description = "A request definition")]
 pub struct Recipe {
     #[serde(skip)] // This will be auto-populated from the map key
     pub id: RecipeId,
+    #[serde(skip)] // Determined by the node's tag in the collection file
+    pub kind: RecipeKind,
     pub name: Option<String>,
     /// *Not* a template string because the usefulness doesn't justify the
     /// complexity. This gives the user an immediate error if the method is
     /// wrong which is helpful.
+    #[schemars(description = "HTTP method")]
     pub method: Method,
     pub url: Template,
-    pub body: Option<Template>,
+    /// Prefix for a relative `url`, inherited from the collection/folder
+    /// `defaults` at load time. This can't be set on the recipe directly
+    #[serde(skip)]
+    pub base_url: Option<Template>,
+    /// Directory of the collection file that defined this recipe, populated
+    /// at load time. Relative paths in the recipe (e.g. a `!file` body) are
+    /// resolved against it. If empty, they're relative to the current
+    /// directory instead.
+    #[serde(skip)]
+    pub dir: PathBuf,
+    pub body: Option<RecipeBody>,
     pub authentication: Option<Authentication>,
     #[serde(default)]
     pub query: IndexMap<String, Template>,
     #[serde(default)]
     pub headers: IndexMap<String, Template>,
+    /// Path to automatically write the response body to after each successful
+    /// request. In addition to the normal template sources, this can use the
+    /// `status` and `date` fields of the response.
+    pub save_response_to: Option<Template>,
+    /// Artificial bandwidth limits, to simulate a slow client
+    pub throttle: Option<Throttle>,
+    /// Abort the request if it hasn't completed within this time. This covers
+    /// the entire request, from connecting until the response body is read.
+    #[serde(default, with = "cereal::serde_duration::option")]
+    #[schemars(with = "Option<schema::Duration>")]
+    pub timeout: Option<Duration>,
+    /// Automatically retry failed requests. Overrides the policy from the
+    /// config
+    pub retry: Option<RetryPolicy>,
+    /// Force a specific HTTP version. Overrides the version from the config.
+    /// If neither is set, the version is negotiated with the server.
+    pub http_version: Option<HttpVersion>,
+    /// Attach `If-None-Match`/`If-Modified-Since` headers based on the most
+    /// recent response to this recipe. If the server responds `304 Not
+    /// Modified`, the body from that response is reused.
+    #[serde(default)]
+    pub conditional: bool,
+    /// Reuse the most recent response to this recipe, instead of sending a
+    /// new request, if it was received within this long ago
+    #[serde(default, with = "cereal::serde_duration::option")]
+    #[schemars(with = "Option<schema::Duration>")]
+    pub cache_ttl: Option<Duration>,
+    /// Cap the rate of requests to this recipe's host. Overrides the limit for
+    /// the host from the config
+    pub rate_limit: Option<RateLimit>,
+    /// Compression settings for the request and response bodies. If
+    /// omitted, responses are still decompressed
+    pub compression: Option<Compression>,
+    /// Connection settings. Each field overrides the same field from the
+    /// config
+    pub connection: Option<ConnectionOptions>,
+    /// Checks to run against each response. Failures are reported, but don't
+    /// affect the response otherwise
+    #[serde(default)]
+    pub assertions: Vec<Assertion>,
+    /// Labels for organizing recipes. The recipe list filter matches them
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Treat the body as a GraphQL query. It's sent as JSON, in the
+    /// `{"query": ...}` format that GraphQL servers expect
+    #[serde(default)]
+    pub graphql: bool,
+}
+
+/// The type of connection a recipe makes. Each kind has its own tag in the
+/// collection file (`!request` or `!websocket`), but they share the [Recipe]
+/// type so they can share the rendering and display logic.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum RecipeKind {
+    /// A single HTTP request
+    #[default]
+    Http,
+    /// A long-lived WebSocket connection. The recipe defines the handshake
+    /// request, which is always a `GET` with no body
+    WebSocket,
 }
 
 #[derive(
@@ -118,6 +369,7 @@ pub struct Recipe {
     PartialEq,
     Serialize,
     Deserialize,
+    JsonSchema,
 )]
 pub struct RecipeId(String);
 
@@ -152,26 +404,380 @@ pub enum Method {
     Trace,
 }
 
+/// Template for a request body. A plain string is sent as-is (after
+/// rendering). Structured bodies are selected with a YAML tag, and encoded by
+/// Slumber. Serialization/deserialization is implemented manually so plain
+/// strings don't need a tag.
+#[derive(Clone, Debug, From)]
+#[cfg_attr(test, derive(PartialEq))]
+pub enum RecipeBody {
+    /// Plain body, e.g. JSON or XML
+    Raw(Template),
+    /// `multipart/form-data` body, with one part per field. Fields can be
+    /// text or files
+    FormMultipart(IndexMap<String, MultipartField>),
+    /// Path to a file whose content is the body. The file is streamed while
+    /// the request is sent, rather than loaded into memory
+    #[from(skip)]
+    File(Template),
+}
+
+impl RecipeBody {
+    /// Tag for [Self::FormMultipart]
+    pub const FORM_MULTIPART: &'static str = "form_multipart";
+    /// Tag for [Self::File]
+    pub const FILE: &'static str = "file";
+}
+
+#[cfg(test)]
+impl From<&str> for RecipeBody {
+    fn from(template: &str) -> Self {
+        Self::Raw(template.into())
+    }
+}
+
+/// One field of a `multipart/form-data` body. Like [RecipeBody], a plain string
+/// is text, and files require a tag.
+#[derive(Clone, Debug, From)]
+#[cfg_attr(test, derive(PartialEq))]
+pub enum MultipartField {
+    /// Plain text value
+    Text(Template),
+    /// Load the value from a file
+    File(MultipartFile),
+}
+
+impl MultipartField {
+    /// Tag for [Self::File]
+    pub const FILE: &'static str = "file";
+}
+
+/// A file to upload as part of a `multipart/form-data` body
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(test, derive(PartialEq))]
+#[serde(deny_unknown_fields)]
+pub struct MultipartFile {
+    /// Path to the file. Relative paths are resolved from the current
+    /// directory
+    pub path: Template,
+    /// MIME type of the part. Defaults to `application/octet-stream`
+    pub content_type: Option<String>,
+    /// File name to send with the part. Defaults to the name of the file at
+    /// [Self::path]
+    pub filename: Option<String>,
+}
+
+/// Bandwidth caps for a single request. Each limit is a number of bytes per
+/// second, e.g. `10 KB`. Omitting a limit means that direction is unlimited.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(test, derive(PartialEq))]
+#[serde(deny_unknown_fields)]
+pub struct Throttle {
+    /// Max rate for sending the request body
+    #[schemars(with = "Option<schema::ByteSize>")]
+    pub upload: Option<ByteSize>,
+    /// Max rate for receiving the response body
+    #[schemars(with = "Option<schema::ByteSize>")]
+    pub download: Option<ByteSize>,
+}
+
+/// When and how to automatically retry a failed request
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(test, derive(PartialEq))]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// Max number of attempts, *including* the first one
+    pub max_attempts: u32,
+    /// Time to wait before the first retry. The wait doubles after each
+    /// subsequent attempt.
+    #[serde(with = "cereal::serde_duration")]
+    #[schemars(with = "schema::Duration")]
+    pub backoff: Duration,
+    /// Retry when the response has one of these status codes
+    pub on_status: Vec<u16>,
+    /// Retry when the request fails without a response, e.g. because the
+    /// connection was refused or timed out
+    pub on_connection_error: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff: Duration::from_secs(1),
+            on_status: vec![429, 502, 503, 504],
+            on_connection_error: true,
+        }
+    }
+}
+
+/// Settings for the connections that requests are sent over. Every field is
+/// optional, so a recipe can override individual settings from the config.
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    Eq,
+    Hash,
+    PartialEq,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConnectionOptions {
+    /// Keep connections open to reuse for later requests to the same host.
+    /// Defaults to `true`
+    pub keep_alive: Option<bool>,
+    /// Close pooled connections that have been idle for this long. Defaults
+    /// to 90 seconds
+    #[serde(with = "cereal::serde_duration::option")]
+    #[schemars(with = "Option<schema::Duration>")]
+    pub pool_idle_timeout: Option<Duration>,
+    /// Max number of idle connections to keep open for each host. Defaults to
+    /// no limit
+    pub pool_max_idle: Option<usize>,
+    /// Send requests from this local IP address
+    pub local_address: Option<IpAddr>,
+    /// Send requests from this network interface (e.g. `eth0`). Only
+    /// supported on Linux
+    pub interface: Option<String>,
+}
+
+impl ConnectionOptions {
+    /// Fill in any unset fields from another set of options
+    pub fn or(self, other: &Self) -> Self {
+        Self {
+            keep_alive: self.keep_alive.or(other.keep_alive),
+            pool_idle_timeout: self
+                .pool_idle_timeout
+                .or(other.pool_idle_timeout),
+            pool_max_idle: self.pool_max_idle.or(other.pool_max_idle),
+            local_address: self.local_address.or(other.local_address),
+            interface: self.interface.or_else(|| other.interface.clone()),
+        }
+    }
+}
+
+/// Max rate of requests to a single host. Requests over the limit wait until
+/// the rate drops, rather than failing.
+#[derive(
+    Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimit {
+    /// Number of requests allowed in each period. This is also how many
+    /// requests can be sent at once after a quiet spell
+    pub requests: u32,
+    /// Length of the period
+    #[serde(with = "cereal::serde_duration")]
+    #[schemars(with = "schema::Duration")]
+    pub per: Duration,
+}
+
+/// How to compress request bodies, and decompress response bodies
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(test, derive(PartialEq))]
+#[serde(default, deny_unknown_fields)]
+pub struct Compression {
+    /// Decode response bodies according to their `Content-Encoding` header.
+    /// If disabled, the body is shown exactly as it was sent
+    pub decompress: bool,
+    /// Encodings to list in the `Accept-Encoding` header. If empty, the
+    /// header isn't sent and the server will typically not compress
+    pub accept: Vec<ContentEncoding>,
+    /// Compress the request body with this encoding, and set the
+    /// `Content-Encoding` header accordingly
+    pub request_body: Option<ContentEncoding>,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self {
+            decompress: true,
+            accept: Vec::new(),
+            request_body: None,
+        }
+    }
+}
+
+/// A check on a response, e.g. for its status or the content of its body
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(test, derive(PartialEq))]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+#[schemars(
+    untagged,
+    rename = "Assertion",
+    description = "Response assertion: `!status`, `!header`, `!jsonpath`, or \
+    `!max_latency`"
+)]
+pub enum Assertion {
+    /// Response has exactly this status code
+    Status(u16),
+    /// Response has a header. If `equals` or `contains` is given, the value
+    /// must also match
+    Header {
+        name: String,
+        equals: Option<String>,
+        contains: Option<String>,
+    },
+    /// Query the response body with JSONPath. The query must return exactly
+    /// one value, which is compared to `equals`
+    Jsonpath {
+        query: Query,
+        equals: serde_json::Value,
+    },
+    /// Request completes within this time
+    MaxLatency(
+        #[serde(with = "cereal::serde_duration")]
+        #[schemars(with = "schema::Duration")]
+        Duration,
+    ),
+}
+
+/// A compression scheme, as used in the `Content-Encoding` and
+/// `Accept-Encoding` headers
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Display,
+    Eq,
+    Hash,
+    PartialEq,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentEncoding {
+    #[display("gzip")]
+    Gzip,
+    /// zlib-wrapped DEFLATE, which is what HTTP calls `deflate`
+    #[display("deflate")]
+    Deflate,
+    #[display("br")]
+    #[serde(rename = "br")]
+    Brotli,
+}
+
+/// HTTP protocol version to force for a request
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Display,
+    Eq,
+    Hash,
+    PartialEq,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+pub enum HttpVersion {
+    /// Only use HTTP/1.1, even if the server supports HTTP/2
+    #[display("HTTP/1.1")]
+    #[serde(rename = "HTTP/1.1")]
+    Http1_1,
+    /// Only use HTTP/2. Over plain HTTP, this assumes the server supports
+    /// HTTP/2 without an upgrade (a.k.a. "prior knowledge")
+    #[display("HTTP/2")]
+    #[serde(rename = "HTTP/2")]
+    Http2,
+}
+
 /// Shortcut for defining authentication method. If this is defined in addition
 /// to the `Authorization` header, that header will end up being included in the
 /// request twice.
 ///
 /// Type parameter allows this to be re-used for post-render purposes (with
 /// `T=String`).
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
+#[schemars(
+    untagged,
+    rename = "Authentication",
+    description = "Authentication method: `!basic`, `!bearer`, `!digest`, \
+    `!oauth2`, or `!aws_sigv4`"
+)]
 pub enum Authentication<T = Template> {
     /// `Authorization: Basic {username:password | base64}`
     Basic { username: T, password: Option<T> },
     /// `Authorization: Bearer {token}`
     Bearer(T),
+    /// HTTP Digest authentication (RFC 7616). The request is sent without
+    /// credentials first, then re-sent with a response to the server's
+    /// challenge.
+    Digest { username: T, password: Option<T> },
+    /// `Authorization: Bearer {token}`, where the token is fetched from an
+    /// OAuth 2.0 token endpoint. Tokens are cached and refreshed as needed.
+    #[serde(rename = "oauth2")]
+    OAuth2(OAuth2<T>),
+    /// Sign the request with AWS Signature Version 4, for AWS APIs and
+    /// S3-compatible services
+    #[serde(rename = "aws_sigv4")]
+    AwsSigV4 {
+        access_key_id: T,
+        secret_access_key: T,
+        /// Only needed for temporary credentials
+        session_token: Option<T>,
+        region: T,
+        /// Service name, e.g. `s3` or `execute-api`
+        service: T,
+    },
+}
+
+/// Settings for fetching an OAuth 2.0 access token. The type parameter serves
+/// the same purpose as on [Authentication].
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(test, derive(PartialEq))]
+#[serde(deny_unknown_fields)]
+#[schemars(rename = "OAuth2", description = "OAuth 2.0 token settings")]
+pub struct OAuth2<T = Template> {
+    pub grant_type: OAuth2GrantType,
+    /// Endpoint to fetch and refresh tokens from
+    pub token_url: T,
+    /// Page to send the user to for login. Required for the authorization
+    /// code grant, ignored otherwise.
+    pub authorization_url: Option<T>,
+    pub client_id: T,
+    pub client_secret: Option<T>,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// Local port to listen on for the authorization code redirect. The
+    /// redirect URI is `http://localhost:{port}/callback`
+    pub redirect_port: Option<u16>,
+}
+
+/// How an OAuth 2.0 access token is obtained
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Display,
+    Eq,
+    PartialEq,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum OAuth2GrantType {
+    /// Exchange the client ID and secret directly for a token. For
+    /// machine-to-machine access.
+    #[display("client_credentials")]
+    ClientCredentials,
+    /// Open a browser for the user to log in, then exchange the resulting
+    /// code for a token
+    #[display("authorization_code")]
+    AuthorizationCode,
 }
 
 /// A chain is a means to data from one response in another request. The chain
 /// is the middleman: it defines where and how to pull the value, then recipes
 /// can use it in a template via `{{chains.<chain_id>}}`.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(deny_unknown_fields)]
 pub struct Chain {
@@ -181,10 +787,14 @@ pub struct Chain {
     /// Mask chained value in the UI
     #[serde(default)]
     pub sensitive: bool,
-    /// Selector to extract a value from the response. This uses JSONPath
-    /// regardless of the content type. Non-JSON values will be converted to
-    /// JSON, then converted back.
-    pub selector: Option<Query>,
+    /// Selector to extract a value from the response. The query language is
+    /// determined by `selector_type`. JSONPath can be used regardless of the
+    /// content type; non-JSON values will be converted to JSON, then
+    /// converted back.
+    pub selector: Option<String>,
+    /// Query language of `selector`
+    #[serde(default)]
+    pub selector_type: SelectorType,
     /// Hard-code the content type of the response. Only needed if a selector
     /// is given and the content type can't be dynamically determined
     /// correctly. This is needed if the chain source is not an HTTP
@@ -231,10 +841,13 @@ impl Equivalent<ChainId> for ChainId<&str> {
     }
 }
 
-/// The source of data for a chain
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// The source of data for a chain. The static string of each variant is its
+/// YAML tag, e.g. `request` for `!request`
+#[derive(Clone, Debug, Serialize, Deserialize, IntoStaticStr, JsonSchema)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
+#[schemars(untagged)]
+#[strum(serialize_all = "snake_case")]
 pub enum ChainSource {
     /// Load data from the most recent response of a particular request recipe
     Request {
@@ -256,19 +869,98 @@ pub enum ChainSource {
     Prompt {
         /// Descriptor to show to the user
         message: Option<Template>,
-        /// Default value for the shown textbox
+        /// Default value for the shown textbox. If `options` is given, this
+        /// selects the matching option
         default: Option<Template>,
+        /// Fixed list of values. If given, the user picks one of these instead
+        /// of entering text
+        #[serde(default)]
+        options: Vec<Template>,
+        /// Allow the entered value to span multiple lines
+        #[serde(default)]
+        multiline: bool,
+    },
+    /// Ask the user to pick a file. The TUI shows a file browser, and the CLI
+    /// asks for a path
+    FilePicker {
+        /// Descriptor to show to the user
+        message: Option<Template>,
+        /// Directory to start in. Defaults to the current directory
+        directory: Option<Template>,
+        /// Use the file's contents or its path
+        #[serde(default)]
+        output: FilePickerOutput,
+    },
+    /// Compute a value with a [Rhai](https://rhai.rs) script. The script
+    /// is *not* a template, because Rhai syntax conflicts with template
+    /// syntax. Dynamic values are passed in via `inputs` instead.
+    Script {
+        /// Rhai source code. The value of the last expression is the output
+        script: String,
+        /// Templates to render and pass to the script as string variables,
+        /// keyed by variable name
+        #[serde(default)]
+        inputs: IndexMap<String, Template>,
+    },
+    /// Read a secret from HashiCorp Vault's KV store, via the `vault` CLI
+    Vault {
+        /// Path of the secret, including the mount (e.g. `secret/my-app`)
+        path: Template,
+        /// Field within the secret to read
+        field: Template,
+        /// Reuse the fetched value for this long, instead of querying Vault
+        /// on every render
+        #[serde(default, with = "cereal::serde_duration::option")]
+        #[schemars(with = "Option<schema::Duration>")]
+        cache_ttl: Option<Duration>,
+    },
+    /// Read a secret from 1Password, via the `op` CLI
+    OnePassword {
+        /// Secret reference, in the format `op://<vault>/<item>/<field>`
+        reference: Template,
+        /// Reuse the fetched value for this long, instead of querying
+        /// 1Password on every render
+        #[serde(default, with = "cereal::serde_duration::option")]
+        #[schemars(with = "Option<schema::Duration>")]
+        cache_ttl: Option<Duration>,
+    },
+    /// Read a secret string from AWS Secrets Manager, via the `aws` CLI
+    AwsSecret {
+        /// Name or ARN of the secret
+        secret_id: Template,
+        /// AWS region. If omitted, the CLI's configured region is used
+        region: Option<Template>,
+        /// Reuse the fetched value for this long, instead of querying AWS on
+        /// every render
+        #[serde(default, with = "cereal::serde_duration::option")]
+        #[schemars(with = "Option<schema::Duration>")]
+        cache_ttl: Option<Duration>,
     },
 }
 
 /// The component of the response to use as the chain source
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
+#[schemars(untagged)]
 pub enum ChainRequestSection {
     #[default]
     Body,
     Header(String),
+    /// The status code, e.g. `200`
+    Status,
+}
+
+/// The value that a file picker chain renders to
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(test, derive(PartialEq))]
+#[serde(rename_all = "snake_case")]
+pub enum FilePickerOutput {
+    /// Contents of the selected file
+    #[default]
+    Contents,
+    /// Path to the selected file
+    Path,
 }
 
 /// Define when a recipe with a chained request should auto-execute the
@@ -292,7 +984,7 @@ pub enum ChainRequestTrigger {
 }
 
 /// Trim whitespace from rendered output
-#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
 pub enum ChainOutputTrim {
@@ -354,11 +1046,179 @@ impl crate::test_util::Factory for RecipeId {
     }
 }
 
+impl Defaults {
+    /// Layer these defaults on top of a parent's. Any value set here takes
+    /// precedence over the parent's.
+    pub fn or(&self, parent: &Self) -> Self {
+        Self {
+            base_url: self.base_url.clone().or_else(|| parent.base_url.clone()),
+            headers: merge_headers(&parent.headers, &self.headers),
+            query: merge_query(&parent.query, &self.query),
+            authentication: self
+                .authentication
+                .clone()
+                .or_else(|| parent.authentication.clone()),
+            data: merge_query(&parent.data, &self.data),
+        }
+    }
+
+    /// Fill in every field that the recipe doesn't define itself, then
+    /// substitute `data` fields into the recipe's templates
+    pub fn apply(&self, recipe: &mut Recipe) {
+        if recipe.base_url.is_none() {
+            recipe.base_url = self.base_url.clone();
+        }
+        recipe.headers = merge_headers(&self.headers, &recipe.headers);
+        recipe.query = merge_query(&self.query, &recipe.query);
+        if recipe.authentication.is_none() {
+            recipe.authentication = self.authentication.clone();
+        }
+
+        if !self.data.is_empty() {
+            let data = self.resolve_data();
+            for template in recipe.templates_mut() {
+                *template = template.substitute(|field| data.get(field));
+            }
+        }
+    }
+
+    /// Substitute `data` fields into each other, so a field can refer to
+    /// other fields in the same set. A field that refers to itself (e.g.
+    /// `host: "{{host}}/admin"`) refers to the profile field instead. Cycles
+    /// between fields are cut off after a fixed number of passes.
+    fn resolve_data(&self) -> IndexMap<String, Template> {
+        const MAX_PASSES: usize = 10;
+        let mut data = self.data.clone();
+        for _ in 0..MAX_PASSES {
+            let resolved: IndexMap<String, Template> = data
+                .iter()
+                .map(|(field, template)| {
+                    let template = template.substitute(|other| {
+                        (other != field).then(|| data.get(other)).flatten()
+                    });
+                    (field.clone(), template)
+                })
+                .collect();
+            let done = resolved
+                .values()
+                .zip(data.values())
+                .all(|(a, b)| a.as_str() == b.as_str());
+            data = resolved;
+            if done {
+                break;
+            }
+        }
+        data
+    }
+}
+
+/// Merge two sets of headers. Header names are case-insensitive, so a header
+/// in `overrides` replaces any header in `base` with the same name, regardless
+/// of case.
+fn merge_headers(
+    base: &IndexMap<String, Template>,
+    overrides: &IndexMap<String, Template>,
+) -> IndexMap<String, Template> {
+    base.iter()
+        .filter(|(header, _)| {
+            !overrides
+                .keys()
+                .any(|other| other.eq_ignore_ascii_case(header))
+        })
+        .chain(overrides)
+        .map(|(header, value)| (header.clone(), value.clone()))
+        .collect()
+}
+
+/// Merge two sets of query parameters. A param in `overrides` replaces the
+/// param with the same name in `base`.
+fn merge_query(
+    base: &IndexMap<String, Template>,
+    overrides: &IndexMap<String, Template>,
+) -> IndexMap<String, Template> {
+    base.iter()
+        .filter(|(param, _)| !overrides.contains_key(*param))
+        .chain(overrides)
+        .map(|(param, value)| (param.clone(), value.clone()))
+        .collect()
+}
+
+impl Recipe {
+    /// Get every template in this recipe, so they can be modified in place
+    fn templates_mut(&mut self) -> Vec<&mut Template> {
+        let mut templates = vec![&mut self.url];
+        templates.extend(&mut self.base_url);
+        templates.extend(self.query.values_mut());
+        templates.extend(self.headers.values_mut());
+        match &mut self.body {
+            Some(RecipeBody::Raw(template) | RecipeBody::File(template)) => {
+                templates.push(template)
+            }
+            Some(RecipeBody::FormMultipart(fields)) => {
+                templates.extend(fields.values_mut().map(|field| match field {
+                    MultipartField::Text(template) => template,
+                    MultipartField::File(file) => &mut file.path,
+                }))
+            }
+            None => {}
+        }
+        if let Some(authentication) = &mut self.authentication {
+            templates.extend(authentication.templates_mut());
+        }
+        templates.extend(&mut self.save_response_to);
+        templates
+    }
+}
+
+impl Authentication {
+    /// Get every template in this authentication scheme
+    fn templates_mut(&mut self) -> Vec<&mut Template> {
+        match self {
+            Self::Basic { username, password }
+            | Self::Digest { username, password } => {
+                let mut templates = vec![username];
+                templates.extend(password);
+                templates
+            }
+            Self::Bearer(token) => vec![token],
+            Self::OAuth2(OAuth2 {
+                token_url,
+                authorization_url,
+                client_id,
+                client_secret,
+                ..
+            }) => {
+                let mut templates = vec![token_url, client_id];
+                templates.extend(authorization_url);
+                templates.extend(client_secret);
+                templates
+            }
+            Self::AwsSigV4 {
+                access_key_id,
+                secret_access_key,
+                session_token,
+                region,
+                service,
+            } => {
+                let mut templates =
+                    vec![access_key_id, secret_access_key, region, service];
+                templates.extend(session_token);
+                templates
+            }
+        }
+    }
+}
+
 impl Profile {
     /// Get a presentable name for this profile
     pub fn name(&self) -> &str {
         self.name.as_deref().unwrap_or(&self.id)
     }
+
+    /// Should the value of this field be masked?
+    pub fn is_sensitive(&self, field: &str) -> bool {
+        self.sensitive.iter().any(|sensitive| sensitive == field)
+    }
 }
 
 #[cfg(test)]
@@ -367,6 +1227,11 @@ impl crate::test_util::Factory for Profile {
         Self {
             id: "profile1".into(),
             name: None,
+            base_url: None,
+            proxy: None,
+            no_proxy: None,
+            dotenv: None,
+            sensitive: Vec::new(),
             data: IndexMap::new(),
         }
     }
@@ -377,6 +1242,18 @@ impl Folder {
     pub fn name(&self) -> &str {
         self.name.as_deref().unwrap_or(&self.id)
     }
+
+    /// Every recipe in this folder, including those in nested folders, in the
+    /// order they're defined
+    pub fn recipes(&self) -> Vec<&Recipe> {
+        self.children
+            .values()
+            .flat_map(|node| match node {
+                RecipeNode::Folder(folder) => folder.recipes(),
+                RecipeNode::Recipe(recipe) => vec![recipe],
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -385,12 +1262,43 @@ impl crate::test_util::Factory for Folder {
         Self {
             id: "folder1".into(),
             name: None,
+            defaults: Defaults::default(),
             children: IndexMap::new(),
         }
     }
 }
 
 impl Recipe {
+    /// A GET recipe with no URL, to be filled in by the caller
+    pub fn empty(id: RecipeId) -> Self {
+        Self {
+            id,
+            kind: RecipeKind::Http,
+            name: None,
+            method: Method::Get,
+            url: Template::dangerous(String::new()),
+            base_url: None,
+            dir: PathBuf::new(),
+            body: None,
+            authentication: None,
+            query: Default::default(),
+            headers: Default::default(),
+            save_response_to: None,
+            throttle: None,
+            timeout: None,
+            retry: None,
+            http_version: None,
+            conditional: false,
+            cache_ttl: None,
+            rate_limit: None,
+            compression: None,
+            connection: None,
+            assertions: Vec::new(),
+            tags: Vec::new(),
+            graphql: false,
+        }
+    }
+
     /// Get a presentable name for this recipe
     pub fn name(&self) -> &str {
         self.name.as_deref().unwrap_or(&self.id)
@@ -402,13 +1310,29 @@ impl crate::test_util::Factory for Recipe {
     fn factory(_: ()) -> Self {
         Self {
             id: "recipe1".into(),
+            kind: RecipeKind::Http,
             name: None,
             method: Method::Get,
             url: "http://localhost/url".into(),
+            base_url: None,
+            dir: PathBuf::new(),
             body: None,
             authentication: None,
             query: IndexMap::new(),
             headers: IndexMap::new(),
+            save_response_to: None,
+            throttle: None,
+            timeout: None,
+            retry: None,
+            http_version: None,
+            conditional: false,
+            cache_ttl: None,
+            rate_limit: None,
+            compression: None,
+            connection: None,
+            assertions: Vec::new(),
+            tags: Vec::new(),
+            graphql: false,
         }
     }
 }
@@ -447,6 +1371,7 @@ impl crate::test_util::Factory for Chain {
             },
             sensitive: false,
             selector: None,
+            selector_type: SelectorType::default(),
             content_type: None,
             trim: ChainOutputTrim::default(),
         }
@@ -1,9 +1,27 @@
 //! Recipe/folder tree structure
 
-use crate::collection::{cereal::deserialize_id_map, Folder, Recipe, RecipeId};
+use crate::{
+    collection::{
+        cereal::{self, deserialize_id_map},
+        schema, Authentication, Defaults, Folder, Method, Recipe, RecipeId,
+        RecipeKind,
+    },
+    template::Template,
+};
 use derive_more::From;
 use indexmap::{map::Values, IndexMap};
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, Metadata, Schema, SchemaObject},
+    JsonSchema,
+};
 use serde::{de::Error, Deserialize, Deserializer, Serialize};
+use std::{
+    collections::HashMap,
+    iter, mem,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 /// A folder/recipe tree. This is exactly what the user inputs in their
 /// collection file. IDs in this tree are **globally* unique, meaning no two
@@ -33,14 +51,102 @@ pub struct RecipeLookupKey(Vec<RecipeId>);
 /// A node in the recipe tree, either a folder or recipe
 #[derive(Clone, Debug, From, Serialize, Deserialize)]
 #[cfg_attr(test, derive(PartialEq))]
-#[serde(rename_all = "snake_case", deny_unknown_fields)]
+#[serde(from = "RecipeNodeRepr", into = "RecipeNodeRepr")]
 #[allow(clippy::large_enum_variant)]
 pub enum RecipeNode {
+    Folder(Folder),
+    Recipe(Recipe),
+}
+
+/// Serialized form of [RecipeNode]. WebSocket recipes get their own tag, so
+/// they only accept the fields that make sense for a WebSocket. In memory
+/// they're regular recipes with [RecipeKind::WebSocket].
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+#[allow(clippy::large_enum_variant)]
+enum RecipeNodeRepr {
     Folder(Folder),
     /// Rename this variant to match the `requests` field in the root and
     /// folders
     #[serde(rename = "request")]
     Recipe(Recipe),
+    #[serde(rename = "websocket")]
+    WebSocket(WebSocketRecipe),
+}
+
+/// Fields for a `!websocket` recipe. These all apply to the handshake request
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct WebSocketRecipe {
+    name: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    url: Template,
+    authentication: Option<Authentication>,
+    #[serde(default)]
+    query: IndexMap<String, Template>,
+    #[serde(default)]
+    headers: IndexMap<String, Template>,
+    /// Give up if the connection isn't open within this time
+    #[serde(default, with = "cereal::serde_duration::option")]
+    #[schemars(with = "Option<schema::Duration>")]
+    timeout: Option<Duration>,
+}
+
+impl From<RecipeNodeRepr> for RecipeNode {
+    fn from(node: RecipeNodeRepr) -> Self {
+        match node {
+            RecipeNodeRepr::Folder(folder) => Self::Folder(folder),
+            RecipeNodeRepr::Recipe(recipe) => Self::Recipe(recipe),
+            RecipeNodeRepr::WebSocket(recipe) => Self::Recipe(Recipe {
+                // ID is populated from the map key after deserialization
+                id: RecipeId::default(),
+                kind: RecipeKind::WebSocket,
+                name: recipe.name,
+                method: Method::Get,
+                url: recipe.url,
+                base_url: None,
+                dir: PathBuf::new(),
+                body: None,
+                authentication: recipe.authentication,
+                query: recipe.query,
+                headers: recipe.headers,
+                save_response_to: None,
+                throttle: None,
+                timeout: recipe.timeout,
+                retry: None,
+                http_version: None,
+                conditional: false,
+                cache_ttl: None,
+                rate_limit: None,
+                compression: None,
+                connection: None,
+                assertions: Vec::new(),
+                tags: recipe.tags,
+                graphql: false,
+            }),
+        }
+    }
+}
+
+impl From<RecipeNode> for RecipeNodeRepr {
+    fn from(node: RecipeNode) -> Self {
+        match node {
+            RecipeNode::Folder(folder) => Self::Folder(folder),
+            RecipeNode::Recipe(recipe) => match recipe.kind {
+                RecipeKind::Http => Self::Recipe(recipe),
+                RecipeKind::WebSocket => Self::WebSocket(WebSocketRecipe {
+                    name: recipe.name,
+                    tags: recipe.tags,
+                    url: recipe.url,
+                    authentication: recipe.authentication,
+                    query: recipe.query,
+                    headers: recipe.headers,
+                    timeout: recipe.timeout,
+                }),
+            },
+        }
+    }
 }
 
 impl RecipeTree {
@@ -64,6 +170,122 @@ impl RecipeTree {
         Ok(new)
     }
 
+    /// Insert a recipe at the very top of the tree. If the recipe's ID is
+    /// already in use, return it as an `Err` and leave the tree unchanged.
+    pub fn insert_first(&mut self, recipe: Recipe) -> Result<(), RecipeId> {
+        if self.nodes_by_id.contains_key(&recipe.id) {
+            return Err(recipe.id);
+        }
+        let tree = iter::once((recipe.id.clone(), recipe.into()))
+            .chain(mem::take(&mut self.tree))
+            .collect();
+        *self = Self::new(tree)?;
+        Ok(())
+    }
+
+    /// Get the IDs of the direct children of a folder, or of the top level of
+    /// the tree if `None`, in order. Return `None` if the ID isn't a folder
+    pub fn child_ids(
+        &self,
+        folder_id: Option<&RecipeId>,
+    ) -> Option<Vec<RecipeId>> {
+        let children = match folder_id {
+            None => &self.tree,
+            Some(folder_id) => &self.get(folder_id)?.folder()?.children,
+        };
+        Some(children.keys().cloned().collect())
+    }
+
+    /// Reorder the direct children of a folder (or the top level of the tree,
+    /// if `None`) to match the given IDs. IDs that aren't children of the
+    /// folder are ignored, and children that aren't listed go after the
+    /// listed ones, in their current order. Lookup keys are unaffected.
+    pub fn sort_children(
+        &mut self,
+        folder_id: Option<&RecipeId>,
+        order: &[RecipeId],
+    ) {
+        let mut children = &mut self.tree;
+        if let Some(folder_id) = folder_id {
+            let Some(lookup_key) = self.nodes_by_id.get(folder_id) else {
+                return;
+            };
+            for step in &lookup_key.0 {
+                match children.get_mut(step) {
+                    Some(RecipeNode::Folder(folder)) => {
+                        children = &mut folder.children
+                    }
+                    _ => return,
+                }
+            }
+        }
+
+        let positions: HashMap<&RecipeId, usize> = order
+            .iter()
+            .enumerate()
+            .map(|(index, id)| (id, index))
+            .collect();
+        let position =
+            |id: &RecipeId| positions.get(id).copied().unwrap_or(usize::MAX);
+        // Stable sort, so unlisted children keep their order
+        children.sort_by(|a, _, b, _| position(a).cmp(&position(b)));
+
+        // Keep the flat map in iteration order too
+        let nodes_by_id = self
+            .iter()
+            .map(|(lookup_key, node)| (node.id().clone(), lookup_key))
+            .collect();
+        self.nodes_by_id = nodes_by_id;
+    }
+
+    /// Apply defaults to every recipe in the tree. Each folder's own defaults
+    /// are layered on top of the given defaults for the recipes within it.
+    /// Applying the same defaults multiple times has no additional effect.
+    /// Folder `data` is substituted into templates, so it's removed once
+    /// applied. Otherwise a field that refers to itself would be expanded again
+    /// when an imported file's recipes get the root file's defaults.
+    pub fn apply_defaults(&mut self, defaults: &Defaults) {
+        fn apply(
+            nodes: &mut IndexMap<RecipeId, RecipeNode>,
+            defaults: &Defaults,
+        ) {
+            for node in nodes.values_mut() {
+                match node {
+                    RecipeNode::Folder(folder) => {
+                        let defaults = folder.defaults.or(defaults);
+                        folder.defaults.data.clear();
+                        apply(&mut folder.children, &defaults);
+                    }
+                    RecipeNode::Recipe(recipe) => defaults.apply(recipe),
+                }
+            }
+        }
+
+        apply(&mut self.tree, defaults);
+    }
+
+    /// Set the directory of the collection file that defined every recipe in
+    /// the tree. See [Recipe::dir]
+    pub fn set_dir(&mut self, dir: &Path) {
+        fn set(nodes: &mut IndexMap<RecipeId, RecipeNode>, dir: &Path) {
+            for node in nodes.values_mut() {
+                match node {
+                    RecipeNode::Folder(folder) => {
+                        set(&mut folder.children, dir)
+                    }
+                    RecipeNode::Recipe(recipe) => recipe.dir = dir.to_owned(),
+                }
+            }
+        }
+
+        set(&mut self.tree, dir);
+    }
+
+    /// Get the top level of the tree. Folders contain their own children
+    pub fn tree(&self) -> &IndexMap<RecipeId, RecipeNode> {
+        &self.tree
+    }
+
     /// Get a recipe/folder's tree lookup key by is unique ID
     pub fn get_lookup_key(&self, id: &RecipeId) -> Option<&RecipeLookupKey> {
         self.nodes_by_id.get(id)
@@ -181,6 +403,57 @@ impl<'de> Deserialize<'de> for RecipeTree {
     }
 }
 
+impl JsonSchema for RecipeTree {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        "RecipeTree".into()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        gen.subschema_for::<IndexMap<RecipeId, RecipeNode>>()
+    }
+}
+
+impl JsonSchema for RecipeNode {
+    fn schema_name() -> String {
+        "RecipeNode".into()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        // A recipe can omit any field that it inherits via `extends`. That's
+        // resolved before deserialization, so it isn't on the type
+        let mut recipe = Recipe::json_schema(gen).into_object();
+        let object = recipe.object();
+        object.required.clear();
+        object.properties.insert(
+            "extends".into(),
+            SchemaObject {
+                instance_type: Some(InstanceType::String.into()),
+                metadata: Some(Box::new(Metadata {
+                    description: Some(
+                        "ID of a recipe to inherit fields from".into(),
+                    ),
+                    ..Default::default()
+                })),
+                ..Default::default()
+            }
+            .into(),
+        );
+
+        schema::any_of(
+            "`!request`, `!websocket`, or `!folder`",
+            [
+                recipe.into(),
+                gen.subschema_for::<WebSocketRecipe>(),
+                gen.subschema_for::<Folder>(),
+            ],
+        )
+    }
+}
+
 impl RecipeNode {
     /// Get the ID of the inner folder or recipe
     pub fn id(&self) -> &RecipeId {
@@ -319,6 +592,75 @@ mod tests {
         );
     }
 
+    /// Recipes in a folder include nested folders, in definition order
+    #[rstest]
+    fn test_folder_recipes(tree: IndexMap<RecipeId, RecipeNode>) {
+        let tree = RecipeTree::new(tree).unwrap();
+        let folder = tree.get(&id("f1")).and_then(RecipeNode::folder).unwrap();
+        assert_eq!(
+            folder
+                .recipes()
+                .into_iter()
+                .map(|recipe| recipe.id.clone())
+                .collect_vec(),
+            vec![id("r2"), id("r3")]
+        );
+    }
+
+    /// Inserted recipes go at the front, and lookup keys are rebuilt
+    #[rstest]
+    fn test_insert_first(tree: IndexMap<RecipeId, RecipeNode>) {
+        let mut tree = RecipeTree::new(tree).unwrap();
+        tree.insert_first(Recipe {
+            id: id("new"),
+            ..Recipe::factory(())
+        })
+        .unwrap();
+        assert_eq!(tree.iter().next().unwrap().1.id(), &id("new"));
+        assert_eq!(
+            tree.get_lookup_key(&id("r2")),
+            Some(&["f1", "f2", "r2"].into())
+        );
+        assert!(tree.get_recipe(&id("new")).is_some());
+
+        // Duplicate ID is rejected
+        assert_eq!(
+            tree.insert_first(Recipe {
+                id: id("r3"),
+                ..Recipe::factory(())
+            }),
+            Err(id("r3"))
+        );
+        assert_eq!(tree.recipe_ids().count(), 5);
+    }
+
+    /// Children can be reordered at any level. Unlisted children go last
+    #[rstest]
+    fn test_sort_children(tree: IndexMap<RecipeId, RecipeNode>) {
+        let mut tree = RecipeTree::new(tree).unwrap();
+        tree.sort_children(None, &[id("r4"), id("r1"), id("unknown")]);
+        assert_eq!(
+            tree.child_ids(None),
+            Some(vec![id("r4"), id("r1"), id("f1")])
+        );
+        tree.sort_children(Some(&id("f1")), &[id("r3"), id("f2")]);
+        assert_eq!(
+            tree.iter().map(|(_, node)| node.id().clone()).collect_vec(),
+            vec![id("r4"), id("r1"), id("f1"), id("r3"), id("f2"), id("r2")]
+        );
+        assert_eq!(
+            tree.recipe_ids().collect_vec(),
+            vec![&id("r4"), &id("r1"), &id("r3"), &id("r2")]
+        );
+        assert_eq!(
+            tree.get_lookup_key(&id("r2")),
+            Some(&["f1", "f2", "r2"].into())
+        );
+
+        // Recipes don't have children
+        assert_eq!(tree.child_ids(Some(&id("r1"))), None);
+    }
+
     /// Deserializing with a duplicate ID anywhere in the tree should fail
     #[rstest]
     #[case::anywhere(
@@ -457,4 +799,177 @@ mod tests {
             "Deserialization failed"
         );
     }
+    /// `!websocket` nodes are loaded as recipes with the WebSocket kind, and
+    /// should serialize back to the same tag
+    #[test]
+    fn test_websocket() {
+        let yaml = mapping([(
+            "ws",
+            tagged_mapping("!websocket", [("url", "ws://localhost/ws".into())]),
+        )]);
+        let tree = serde_yaml::from_value::<RecipeTree>(yaml).unwrap();
+        let recipe = tree.get_recipe(&id("ws")).unwrap();
+        assert_eq!(
+            recipe,
+            &Recipe {
+                id: id("ws"),
+                kind: RecipeKind::WebSocket,
+                url: "ws://localhost/ws".into(),
+                ..Recipe::factory(())
+            }
+        );
+
+        let serialized = serde_yaml::to_value(&tree).unwrap();
+        let Value::Mapping(mapping) = &serialized else {
+            panic!("Expected mapping, got {serialized:?}")
+        };
+        assert!(
+            matches!(
+                mapping.get("ws"),
+                Some(Value::Tagged(tagged)) if tagged.tag == "websocket"
+            ),
+            "Expected !websocket tag, got {serialized:?}"
+        );
+        assert_eq!(
+            serde_yaml::from_value::<RecipeTree>(serialized).unwrap(),
+            tree
+        );
+    }
+
+    /// HTTP-only fields aren't allowed on WebSocket recipes
+    #[test]
+    fn test_websocket_unknown_field() {
+        let yaml = mapping([(
+            "ws",
+            tagged_mapping(
+                "!websocket",
+                [("method", "POST".into()), ("url", "ws://localhost".into())],
+            ),
+        )]);
+        assert_err!(
+            serde_yaml::from_value::<RecipeTree>(yaml),
+            "unknown field `method`"
+        );
+    }
+
+    /// Folder defaults override collection defaults, and recipe values
+    /// override both
+    #[test]
+    fn test_apply_defaults() {
+        let mut tree: RecipeTree = serde_yaml::from_str(
+            r#"
+r1: !request
+  method: GET
+  url: /r1
+  headers:
+    accept: text/plain
+f1: !folder
+  defaults:
+    base_url: /api
+    headers:
+      X-Folder: folder
+    query:
+      page: "2"
+  requests:
+    r2: !request
+      method: GET
+      url: /r2
+      authentication: !bearer recipe
+"#,
+        )
+        .unwrap();
+        let defaults = Defaults {
+            base_url: Some("http://localhost".into()),
+            headers: indexmap! {
+                "Accept".into() => "application/json".into(),
+                "X-Folder".into() => "collection".into(),
+            },
+            query: indexmap! {"page".into() => "1".into()},
+            authentication: Some(Authentication::Bearer("default".into())),
+            data: IndexMap::new(),
+        };
+        tree.apply_defaults(&defaults);
+        // Applying twice is a no-op
+        tree.apply_defaults(&defaults);
+
+        let r1 = tree.get_recipe(&id("r1")).unwrap();
+        assert_eq!(r1.base_url, Some("http://localhost".into()));
+        assert_eq!(
+            r1.headers,
+            indexmap! {
+                "X-Folder".into() => "collection".into(),
+                "accept".into() => "text/plain".into(),
+            }
+        );
+        assert_eq!(r1.query, indexmap! {"page".into() => "1".into()});
+        assert_eq!(
+            r1.authentication,
+            Some(Authentication::Bearer("default".into()))
+        );
+
+        let r2 = tree.get_recipe(&id("r2")).unwrap();
+        assert_eq!(r2.base_url, Some("/api".into()));
+        assert_eq!(
+            r2.headers,
+            indexmap! {
+                "Accept".into() => "application/json".into(),
+                "X-Folder".into() => "folder".into(),
+            }
+        );
+        assert_eq!(r2.query, indexmap! {"page".into() => "2".into()});
+        assert_eq!(
+            r2.authentication,
+            Some(Authentication::Bearer("recipe".into()))
+        );
+    }
+
+    /// Folder data is substituted into recipe templates, and cascades to
+    /// nested folders
+    #[test]
+    fn test_apply_defaults_data() {
+        let mut tree: RecipeTree = serde_yaml::from_str(
+            r#"
+r1: !request
+  method: GET
+  url: "{{host}}/r1"
+admin: !folder
+  defaults:
+    authentication: !bearer "{{admin_token}}"
+    data:
+      host: "{{host}}/admin"
+      admin_token: "{{chains.admin_token}}"
+      user: "{{role}}-user"
+  requests:
+    users: !folder
+      defaults:
+        data:
+          role: admin
+      requests:
+        r2: !request
+          method: GET
+          url: "{{host}}/users/{{user}}"
+          headers:
+            X-Env: "{{env.ENV}}"
+"#,
+        )
+        .unwrap();
+        let defaults = Defaults {
+            data: indexmap! {"role".into() => "guest".into()},
+            ..Defaults::default()
+        };
+        tree.apply_defaults(&defaults);
+        // Self-referential fields aren't expanded twice
+        tree.apply_defaults(&defaults);
+
+        let r1 = tree.get_recipe(&id("r1")).unwrap();
+        assert_eq!(r1.url.as_str(), "{{host}}/r1");
+
+        let r2 = tree.get_recipe(&id("r2")).unwrap();
+        assert_eq!(r2.url.as_str(), "{{host}}/admin/users/admin-user");
+        assert_eq!(r2.headers["X-Env"].as_str(), "{{env.ENV}}");
+        assert_eq!(
+            r2.authentication,
+            Some(Authentication::Bearer("{{chains.admin_token}}".into()))
+        );
+    }
 }
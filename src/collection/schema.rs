@@ -0,0 +1,399 @@
+//! JSON Schema for the collection format, so editors can provide autocomplete
+//! and validation. Most of the schema is derived from the collection types.
+//! Types with a hand-written serde impl get a hand-written schema here.
+//!
+//! YAML tags (e.g. `!request`) can't be represented in JSON Schema. Editors
+//! validate a tagged value by its content and ignore the tag, so enums that are
+//! selected by tag are represented as a union of their variants' contents.
+
+use crate::{
+    collection::{ChainRequestTrigger, Method, MultipartField, RecipeBody},
+    template::Template,
+};
+use indexmap::IndexMap;
+use schemars::{
+    gen::{SchemaGenerator, SchemaSettings},
+    schema::{
+        InstanceType, Metadata, RootSchema, Schema, SchemaObject,
+        StringValidation, SubschemaValidation,
+    },
+    JsonSchema,
+};
+use serde_json::Value;
+use strum::IntoEnumIterator;
+
+/// Generate the JSON Schema for a collection file
+pub fn collection_schema() -> RootSchema {
+    let mut schema = SchemaSettings::draft07()
+        .into_generator()
+        .into_root_schema_for::<super::Collection>();
+    schema.schema.metadata().title = Some("Slumber collection".into());
+    schema
+}
+
+/// A schema that matches any of the given schemas
+pub(super) fn any_of(
+    description: &str,
+    schemas: impl IntoIterator<Item = Schema>,
+) -> Schema {
+    SchemaObject {
+        metadata: Some(Box::new(Metadata {
+            description: Some(description.into()),
+            ..Default::default()
+        })),
+        subschemas: Some(Box::new(SubschemaValidation {
+            any_of: Some(schemas.into_iter().collect()),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+/// A string schema, with an optional pattern
+fn string(description: &str, pattern: Option<&str>) -> SchemaObject {
+    SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        metadata: Some(Box::new(Metadata {
+            description: Some(description.into()),
+            ..Default::default()
+        })),
+        string: pattern.map(|pattern| {
+            Box::new(StringValidation {
+                pattern: Some(pattern.into()),
+                ..Default::default()
+            })
+        }),
+        ..Default::default()
+    }
+}
+
+/// Schema for a duration field. Use with `#[schemars(with = "...")]` on
+/// fields that use [cereal::serde_duration](super::cereal::serde_duration)
+pub struct Duration;
+
+impl JsonSchema for Duration {
+    fn schema_name() -> String {
+        "Duration".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        string(
+            "Integer followed by a unit: `ms`, `s`, `m`, `h`, or `d` (e.g. \
+            `30s`)",
+            Some(r"^\d+(ms|s|m|h|d)$"),
+        )
+        .into()
+    }
+}
+
+/// Schema for a byte size field, which can be a number of bytes or a string
+/// with a unit (e.g. `10 KB`). Use with `#[schemars(with = "...")]`
+pub struct ByteSize;
+
+impl JsonSchema for ByteSize {
+    fn schema_name() -> String {
+        "ByteSize".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(
+                vec![InstanceType::Integer, InstanceType::String].into(),
+            ),
+            metadata: Some(Box::new(Metadata {
+                description: Some(
+                    "Number of bytes, or a size with a unit (e.g. `10 KB`)"
+                        .into(),
+                ),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+/// Numbers and booleans are accepted as templates too, so YAML users don't
+/// have to quote them
+impl JsonSchema for Template {
+    fn schema_name() -> String {
+        "Template".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(
+                vec![
+                    InstanceType::String,
+                    InstanceType::Number,
+                    InstanceType::Boolean,
+                ]
+                .into(),
+            ),
+            metadata: Some(Box::new(Metadata {
+                description: Some(
+                    "String that can contain dynamic values with `{{...}}`"
+                        .into(),
+                ),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+/// Method parsing is case-insensitive, but we only suggest the two common
+/// spellings
+impl JsonSchema for Method {
+    fn schema_name() -> String {
+        "Method".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        let mut schema = string("HTTP method", None);
+        schema.enum_values = Some(
+            Method::iter()
+                .map(|method| method.to_string())
+                .flat_map(|method| {
+                    [
+                        Value::String(method.to_lowercase()),
+                        Value::String(method),
+                    ]
+                })
+                .collect(),
+        );
+        schema.into()
+    }
+}
+
+impl JsonSchema for RecipeBody {
+    fn schema_name() -> String {
+        "RecipeBody".into()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        any_of(
+            "Request body. A plain string is sent as-is. Use \
+            `!form_multipart` for a multipart form, or `!file` to stream the \
+            body from a file",
+            [
+                // Raw or `!file`
+                gen.subschema_for::<Template>(),
+                // `!form_multipart`
+                gen.subschema_for::<IndexMap<String, MultipartField>>(),
+            ],
+        )
+    }
+}
+
+impl JsonSchema for MultipartField {
+    fn schema_name() -> String {
+        "MultipartField".into()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        any_of(
+            "Text value, or `!file` to upload a file",
+            [
+                gen.subschema_for::<Template>(),
+                gen.subschema_for::<super::MultipartFile>(),
+            ],
+        )
+    }
+}
+
+/// Unit variants can be written with a tag and no value (`!always`) or as a
+/// plain string (`always`), which a derived schema can't express
+impl JsonSchema for ChainRequestTrigger {
+    fn schema_name() -> String {
+        "ChainRequestTrigger".into()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let unit = SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            enum_values: Some(
+                ["never", "no_history", "always"]
+                    .into_iter()
+                    .map(Value::from)
+                    .collect(),
+            ),
+            ..Default::default()
+        };
+        any_of(
+            "When the upstream request should be executed: `!never`, \
+            `!no_history`, `!expire <duration>`, or `!always`",
+            [
+                gen.subschema_for::<()>(),
+                unit.into(),
+                gen.subschema_for::<Duration>(),
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collection::parse_collection;
+    use jsonschema::JSONSchema;
+    use rstest::rstest;
+    use serde_yaml::Value as YamlValue;
+    use std::{fs, path::PathBuf};
+
+    /// Convert YAML to JSON, the same way an editor does for validation: tags
+    /// are dropped and merge keys are applied
+    fn yaml_to_json(yaml: &[u8]) -> Value {
+        fn convert(yaml: YamlValue) -> Value {
+            match yaml {
+                YamlValue::Null => Value::Null,
+                YamlValue::Bool(b) => b.into(),
+                YamlValue::Number(number) => {
+                    serde_json::to_value(number).unwrap()
+                }
+                YamlValue::String(s) => s.into(),
+                YamlValue::Sequence(sequence) => {
+                    sequence.into_iter().map(convert).collect()
+                }
+                YamlValue::Mapping(mapping) => Value::Object(
+                    mapping
+                        .into_iter()
+                        .map(|(key, value)| {
+                            (key.as_str().unwrap().to_owned(), convert(value))
+                        })
+                        .collect(),
+                ),
+                YamlValue::Tagged(tagged) => convert(tagged.value),
+            }
+        }
+
+        let mut yaml: YamlValue = serde_yaml::from_slice(yaml).unwrap();
+        yaml.apply_merge().unwrap();
+        convert(yaml)
+    }
+
+    fn validate(yaml: &[u8]) -> Result<(), String> {
+        let schema = serde_json::to_value(collection_schema()).unwrap();
+        let schema = JSONSchema::compile(&schema).unwrap();
+        let instance = yaml_to_json(yaml);
+        schema.validate(&instance).map_err(|errors| {
+            errors
+                .map(|error| format!("{}: {error}", error.instance_path))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+    }
+
+    /// Every collection that Slumber accepts should pass validation
+    #[rstest]
+    #[case::example("slumber.yml")]
+    #[case::export("test_data/export.yml")]
+    #[case::insomnia("test_data/insomnia_imported.yml")]
+    #[case::har("test_data/har_imported.yml")]
+    fn test_schema_valid(#[case] path: PathBuf) {
+        let yaml = fs::read(&path).unwrap();
+        // Make sure the test case is actually valid
+        parse_collection(&yaml).unwrap();
+        if let Err(errors) = validate(&yaml) {
+            panic!("{path:?} failed schema validation:\n{errors}");
+        }
+    }
+
+    /// Tagged values, extended recipes, and primitive templates are valid
+    #[test]
+    fn test_schema_valid_features() {
+        let yaml = br#"
+profiles:
+  local:
+    sensitive: [password]
+    data:
+      token: !env TOKEN
+      password: !secret password
+      port: 3000
+chains:
+  token:
+    source: !request
+      recipe: login
+      trigger: !expire 12h
+  trigger_plain:
+    source: !request
+      recipe: login
+      trigger: always
+  trigger_tag:
+    source: !request
+      recipe: login
+      trigger: !never
+      section: !header Token
+  status:
+    source: !request
+      recipe: login
+      trigger: !no_history
+      section: !status
+  xml:
+    source: !request
+      recipe: login
+    selector: //user/@id
+    selector_type: xpath
+  tenant:
+    source: !prompt
+      options: [acme, globex]
+      default: globex
+      multiline: false
+  upload:
+    source: !file_picker
+      directory: ./uploads
+      output: path
+  total:
+    source: !script
+      script: "40 + parse_int(x)"
+      inputs:
+        x: "{{port}}"
+requests:
+  login: !request
+    method: post
+    url: /login
+    body: !form_multipart
+      user: fish
+      avatar: !file
+        path: ./fish.png
+    timeout: 30s
+    assertions:
+      - !status 200
+      - !header
+        name: content-type
+        contains: json
+      - !jsonpath
+        query: $.token
+        equals: abc
+      - !max_latency 500ms
+  logout: !request
+    extends: login
+    url: /logout
+  folder: !folder
+    requests:
+      ws: !websocket
+        url: ws://localhost
+"#;
+        parse_collection(yaml).unwrap();
+        validate(yaml).unwrap();
+    }
+
+    /// Anything Slumber rejects should fail validation. The schema isn't
+    /// quite as strict though, so this isn't true in reverse
+    #[rstest]
+    #[case::unknown_field(
+        "requests: {r1: !request {method: GET, url: /, bogus: 1}}"
+    )]
+    #[case::unknown_root_field("bogus: 1")]
+    #[case::method("requests: {r1: !request {method: FETCH, url: /}}")]
+    #[case::duration(
+        "requests: {r1: !request {method: GET, url: /, timeout: 30 s}}"
+    )]
+    #[case::chain_source("chains: {c1: {source: !prompt {bogus: 1}}}")]
+    fn test_schema_invalid(#[case] yaml: &str) {
+        assert!(parse_collection(yaml.as_bytes()).is_err());
+        assert!(validate(yaml.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+//! Search recipes by their content
+
+use crate::{
+    collection::{MultipartField, Recipe, RecipeBody},
+    util::fuzzy_match,
+};
+use derive_more::Display;
+
+/// A recipe field that can be searched
+#[derive(Copy, Clone, Debug, Display, PartialEq)]
+pub enum RecipeField {
+    #[display("id")]
+    Id,
+    #[display("name")]
+    Name,
+    #[display("url")]
+    Url,
+    #[display("tag")]
+    Tag,
+    #[display("header")]
+    Header,
+    #[display("body")]
+    Body,
+}
+
+/// A recipe field that contains a search pattern
+#[derive(Debug, PartialEq)]
+pub struct SearchMatch {
+    pub field: RecipeField,
+    /// Full text of the field. For headers and multipart body fields, this is
+    /// `<name>: <value>`
+    pub text: String,
+}
+
+impl Recipe {
+    /// Search the recipe's ID, name, URL, tags, headers (names and values) and
+    /// body for a pattern. Matching is case-insensitive. Templates are searched
+    /// in their raw, unrendered form. Returns every field that matches.
+    pub fn search(&self, pattern: &str) -> Vec<SearchMatch> {
+        let pattern = pattern.to_lowercase();
+        let is_match = |text: &str| text.to_lowercase().contains(&pattern);
+
+        let tags = self.tags.iter().map(|tag| (RecipeField::Tag, tag.clone()));
+        let headers = self.headers.iter().map(|(name, value)| {
+            (RecipeField::Header, format!("{name}: {value}"))
+        });
+        let body = self.body.iter().flat_map(|body| match body {
+            RecipeBody::Raw(template) => {
+                vec![(RecipeField::Body, template.to_string())]
+            }
+            RecipeBody::FormMultipart(fields) => fields
+                .iter()
+                .map(|(name, field)| {
+                    let value = match field {
+                        MultipartField::Text(template) => template,
+                        MultipartField::File(file) => &file.path,
+                    };
+                    (RecipeField::Body, format!("{name}: {value}"))
+                })
+                .collect(),
+            RecipeBody::File(path) => {
+                vec![(RecipeField::Body, path.to_string())]
+            }
+        });
+        [
+            (RecipeField::Id, self.id.to_string()),
+            (RecipeField::Name, self.name.clone().unwrap_or_default()),
+            (RecipeField::Url, self.url.to_string()),
+        ]
+        .into_iter()
+        .chain(tags)
+        .chain(headers)
+        .chain(body)
+        .filter(|(_, text)| is_match(text))
+        .map(|(field, text)| SearchMatch { field, text })
+        .collect()
+    }
+
+    /// Does any searchable field in the recipe contain the pattern? See
+    /// [Self::search]
+    pub fn matches(&self, pattern: &str) -> bool {
+        !self.search(pattern).is_empty()
+    }
+
+    /// Score how well the recipe's ID, name, URL, or tags fuzzy-match a query.
+    /// The best-matching field determines the score. Return `None` if no field
+    /// matches. See [fuzzy_match]
+    pub fn fuzzy_score(&self, query: &str) -> Option<i64> {
+        [self.id.as_str(), self.name(), &self.url.to_string()]
+            .into_iter()
+            .chain(self.tags.iter().map(String::as_str))
+            .filter_map(|text| fuzzy_match(query, text))
+            .max()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Factory;
+    use indexmap::indexmap;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::id("LOGIN", &[(RecipeField::Id, "login")])]
+    #[case::name("sign", &[(RecipeField::Name, "Sign In")])]
+    #[case::url(
+        "/v1/",
+        &[(RecipeField::Url, "{{host}}/api/v1/session")]
+    )]
+    #[case::header_name(
+        "x-api",
+        &[(RecipeField::Header, "X-Api-Key: {{chains.key}}")]
+    )]
+    #[case::header_value(
+        "chains.key",
+        &[(RecipeField::Header, "X-Api-Key: {{chains.key}}")]
+    )]
+    #[case::tag("auth", &[(RecipeField::Tag, "auth")])]
+    #[case::body(
+        "password",
+        &[(RecipeField::Body, r#"{"password": "hunter2"}"#)]
+    )]
+    #[case::multiple(
+        "in",
+        &[
+            (RecipeField::Id, "login"),
+            (RecipeField::Name, "Sign In"),
+            (RecipeField::Header, "X-Api-Key: {{chains.key}}"),
+        ]
+    )]
+    #[case::no_match("v2", &[])]
+    fn test_search(
+        #[case] pattern: &str,
+        #[case] expected: &[(RecipeField, &str)],
+    ) {
+        let recipe = Recipe {
+            id: "login".into(),
+            name: Some("Sign In".into()),
+            url: "{{host}}/api/v1/session".parse().unwrap(),
+            headers: indexmap! {
+                "X-Api-Key".into() => "{{chains.key}}".parse().unwrap(),
+            },
+            body: Some(r#"{"password": "hunter2"}"#.into()),
+            tags: vec!["auth".into()],
+            ..Recipe::factory(())
+        };
+        let expected: Vec<SearchMatch> = expected
+            .iter()
+            .map(|(field, text)| SearchMatch {
+                field: *field,
+                text: (*text).to_owned(),
+            })
+            .collect();
+        assert_eq!(recipe.search(pattern), expected);
+        assert_eq!(recipe.matches(pattern), !expected.is_empty());
+    }
+
+    #[rstest]
+    #[case::id("lgn", true)]
+    #[case::name("sgnin", true)]
+    #[case::url("apiv1", true)]
+    #[case::tag("ath", true)]
+    // Headers and body aren't fuzzy matched
+    #[case::header("xapikey", false)]
+    #[case::no_match("zzz", false)]
+    fn test_fuzzy_score(#[case] query: &str, #[case] expected: bool) {
+        let recipe = Recipe {
+            id: "login".into(),
+            name: Some("Sign In".into()),
+            url: "{{host}}/api/v1/session".parse().unwrap(),
+            headers: indexmap! {
+                "X-Api-Key".into() => "{{chains.key}}".parse().unwrap(),
+            },
+            tags: vec!["auth".into()],
+            ..Recipe::factory(())
+        };
+        assert_eq!(recipe.fuzzy_score(query).is_some(), expected);
+    }
+}
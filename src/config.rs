@@ -1,7 +1,10 @@
 use crate::{
+    collection::{
+        serde_duration, ConnectionOptions, HttpVersion, RateLimit, RetryPolicy,
+    },
     tui::{
         input::{Action, InputBinding},
-        view::Theme,
+        view::ThemeConfig,
     },
     util::{
         parse_yaml,
@@ -10,9 +13,10 @@ use crate::{
     },
 };
 use anyhow::Context;
+use bytesize::ByteSize;
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
-use std::fs;
+use std::{fs, num::NonZeroUsize, time::Duration};
 use tracing::info;
 
 /// App-level configuration, which is global across all sessions and
@@ -25,13 +29,50 @@ pub struct Config {
     /// TLS cert errors on these hostnames are ignored. Be careful!
     #[serde(default)]
     pub ignore_certificate_hosts: Vec<String>,
+    /// Use credentials from the user's `.netrc` file for recipes that don't
+    /// define any authentication
+    pub use_netrc: bool,
+    /// Save cookies to the database after each request, so they're restored
+    /// in later sessions. Otherwise, cookies only last until exit.
+    pub persist_cookies: bool,
+    /// Send all requests through this proxy (`http://`, `https://`, or
+    /// `socks5://`). Can be overridden per profile.
+    pub proxy: Option<String>,
+    /// Comma-separated list of hosts that should bypass the proxy. Can be
+    /// overridden per profile.
+    pub no_proxy: Option<String>,
+    /// Response bodies larger than this are written to a temp file instead
+    /// of being held in memory. Only a preview is kept in memory.
+    pub large_body_size: ByteSize,
+    /// Default policy for retrying failed requests. Recipes can override
+    /// this with their own policy.
+    pub retry: Option<RetryPolicy>,
+    /// Force this HTTP version for all requests. Recipes can override this.
+    /// If not set, the version is negotiated with the server.
+    pub http_version: Option<HttpVersion>,
+    /// Max request rate for each host, keyed by hostname. Recipes can
+    /// override the limit for their host.
+    pub rate_limits: IndexMap<String, RateLimit>,
+    /// Max number of requests to have in flight at once. Additional requests
+    /// wait for a free slot. Unlimited if not set.
+    pub max_concurrent_requests: Option<NonZeroUsize>,
+    /// When running a folder of recipes, keep going after a request fails.
+    /// Otherwise, the run stops at the first failure.
+    pub continue_on_failure: bool,
+    /// How often to re-send a watched request in the TUI
+    #[serde(with = "serde_duration")]
+    pub watch_interval: Duration,
+    /// Settings for the connections requests are sent over. Recipes can
+    /// override each setting.
+    pub connection: ConnectionOptions,
     /// Should templates be rendered inline in the UI, or should we show the
     /// raw text?
     pub preview_templates: bool,
     /// Overrides for default key bindings
     pub input_bindings: IndexMap<Action, InputBinding>,
-    /// Visual configuration for the TUI (e.g. colors)
-    pub theme: Theme,
+    /// Visual configuration for the TUI (e.g. colors). Either the name of a
+    /// theme or an inline theme definition
+    pub theme: ThemeConfig,
 }
 
 impl Config {
@@ -72,9 +113,21 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             ignore_certificate_hosts: Vec::new(),
+            use_netrc: false,
+            persist_cookies: false,
+            proxy: None,
+            no_proxy: None,
+            large_body_size: ByteSize::mib(50),
+            retry: None,
+            http_version: None,
+            rate_limits: IndexMap::default(),
+            max_concurrent_requests: None,
+            continue_on_failure: false,
+            watch_interval: Duration::from_secs(5),
+            connection: ConnectionOptions::default(),
             preview_templates: true,
             input_bindings: IndexMap::default(),
-            theme: Theme::default(),
+            theme: ThemeConfig::default(),
         }
     }
 }
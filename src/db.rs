@@ -3,13 +3,15 @@
 
 use crate::{
     collection::{ProfileId, RecipeId},
-    http::{Exchange, ExchangeSummary, RequestId},
+    http::{Exchange, ExchangeSummary, GraphQlSchema, OAuth2Token, RequestId},
     util::{
         paths::{DataDirectory, FileGuard},
         ResultExt,
     },
+    ws::WebSocketExchange,
 };
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail, Context};
+use cookie_store::CookieStore;
 use derive_more::Display;
 use reqwest::StatusCode;
 use rusqlite::{
@@ -20,6 +22,7 @@ use rusqlite::{
 use rusqlite_migration::{Migrations, M};
 use serde::{de::DeserializeOwned, Serialize};
 use std::{
+    collections::HashMap,
     fmt::Debug,
     ops::Deref,
     path::{Path, PathBuf},
@@ -130,6 +133,97 @@ impl Database {
             // serialization of all binary blobs, so there's no easy way to
             // migrate it all. It's easiest just to wipe it all out.
             M::up("DELETE FROM requests; DELETE FROM ui_state;").down(""),
+            M::up(
+                // The cookie store is serialized as JSON
+                "CREATE TABLE cookies (
+                    collection_id   UUID PRIMARY KEY NOT NULL,
+                    store           TEXT NOT NULL,
+                    FOREIGN KEY(collection_id) REFERENCES collections(id)
+                )",
+            )
+            .down("DROP TABLE cookies"),
+            // Retried attempts are serialized as msgpack. Null for exchanges
+            // from before retries were added
+            M::up("ALTER TABLE requests ADD COLUMN retries BLOB")
+                .down("ALTER TABLE requests DROP COLUMN retries"),
+            M::up(
+                // Tokens are keyed by the settings used to fetch them, so
+                // recipes with the same OAuth client share a token
+                "CREATE TABLE oauth2_tokens (
+                    collection_id   UUID NOT NULL,
+                    key             TEXT NOT NULL,
+                    token           BLOB NOT NULL,
+                    PRIMARY KEY (collection_id, key),
+                    FOREIGN KEY(collection_id) REFERENCES collections(id)
+                )",
+            )
+            .down("DROP TABLE oauth2_tokens"),
+            M::up(
+                // Same layout as the requests table, but instead of a
+                // response there's a msgpack list of every message sent over
+                // the connection
+                "CREATE TABLE websocket_exchanges (
+                    id              UUID PRIMARY KEY NOT NULL,
+                    collection_id   UUID NOT NULL,
+                    profile_id      TEXT,
+                    recipe_id       TEXT NOT NULL,
+                    start_time      TEXT NOT NULL,
+                    end_time        TEXT NOT NULL,
+                    request         BLOB NOT NULL,
+                    messages        BLOB NOT NULL,
+                    FOREIGN KEY(collection_id) REFERENCES collections(id)
+                )",
+            )
+            .down("DROP TABLE websocket_exchanges"),
+            // Timing breakdown is serialized as msgpack. Null for exchanges
+            // from before timing was tracked, or cached responses
+            M::up("ALTER TABLE requests ADD COLUMN timing BLOB")
+                .down("ALTER TABLE requests DROP COLUMN timing"),
+            // Free-form Markdown notes. Exchange notes live on the request
+            // row, so they go away with the exchange
+            M::up("ALTER TABLE requests ADD COLUMN note TEXT")
+                .down("ALTER TABLE requests DROP COLUMN note"),
+            M::up(
+                "CREATE TABLE recipe_notes (
+                    collection_id   UUID NOT NULL,
+                    recipe_id       TEXT NOT NULL,
+                    note            TEXT NOT NULL,
+                    PRIMARY KEY (collection_id, recipe_id),
+                    FOREIGN KEY(collection_id) REFERENCES collections(id)
+                )",
+            )
+            .down("DROP TABLE recipe_notes"),
+            M::up(
+                // Schema is serialized as msgpack
+                "CREATE TABLE graphql_schemas (
+                    collection_id   UUID NOT NULL,
+                    recipe_id       TEXT NOT NULL,
+                    schema          BLOB NOT NULL,
+                    PRIMARY KEY (collection_id, recipe_id),
+                    FOREIGN KEY(collection_id) REFERENCES collections(id)
+                )",
+            )
+            .down("DROP TABLE graphql_schemas"),
+            M::up(
+                // One golden exchange per recipe. Golden exchanges are a
+                // permanent baseline, so anything that prunes history must
+                // leave the exchanges referenced here alone
+                "CREATE TABLE golden_exchanges (
+                    collection_id   UUID NOT NULL,
+                    recipe_id       TEXT NOT NULL,
+                    name            TEXT NOT NULL,
+                    request_id      UUID NOT NULL,
+                    PRIMARY KEY (collection_id, recipe_id),
+                    FOREIGN KEY(collection_id) REFERENCES collections(id),
+                    FOREIGN KEY(request_id) REFERENCES requests(id)
+                )",
+            )
+            .down("DROP TABLE golden_exchanges"),
+            // Hash of the unredacted request, used to find cached responses.
+            // Null for exchanges from recipes without a cache TTL, which are
+            // never reused
+            M::up("ALTER TABLE requests ADD COLUMN fingerprint TEXT")
+                .down("ALTER TABLE requests DROP COLUMN fingerprint"),
         ]);
         migrations.to_latest(connection)?;
         Ok(())
@@ -198,6 +292,14 @@ impl Database {
             )
             .context("Error migrating table `requests`")
             .traced()?;
+        connection
+            .execute(
+                "UPDATE websocket_exchanges SET collection_id = :target
+                WHERE collection_id = :source",
+                named_params! {":source": source, ":target": target},
+            )
+            .context("Error migrating table `websocket_exchanges`")
+            .traced()?;
         connection
             .execute(
                 // Overwrite UI state. Maybe this isn't the best UX, but sqlite
@@ -209,6 +311,42 @@ impl Database {
             )
             .context("Error migrating table `ui_state`")
             .traced()?;
+        connection
+            .execute(
+                // Same deal as UI state: the source overwrites the target
+                "UPDATE OR REPLACE cookies SET collection_id = :target
+                WHERE collection_id = :source",
+                named_params! {":source": source, ":target": target},
+            )
+            .context("Error migrating table `cookies`")
+            .traced()?;
+        connection
+            .execute(
+                // Same deal as UI state: the source overwrites the target
+                "UPDATE OR REPLACE recipe_notes SET collection_id = :target
+                WHERE collection_id = :source",
+                named_params! {":source": source, ":target": target},
+            )
+            .context("Error migrating table `recipe_notes`")
+            .traced()?;
+        connection
+            .execute(
+                // Same deal as UI state: the source overwrites the target
+                "UPDATE OR REPLACE graphql_schemas SET collection_id = :target
+                WHERE collection_id = :source",
+                named_params! {":source": source, ":target": target},
+            )
+            .context("Error migrating table `graphql_schemas`")
+            .traced()?;
+        connection
+            .execute(
+                // Same deal as UI state: the source overwrites the target
+                "UPDATE OR REPLACE golden_exchanges SET collection_id = :target
+                WHERE collection_id = :source",
+                named_params! {":source": source, ":target": target},
+            )
+            .context("Error migrating table `golden_exchanges`")
+            .traced()?;
 
         connection
             .execute(
@@ -308,190 +446,811 @@ impl CollectionDatabase {
                 },
                 |row| row.try_into(),
             )
-            .optional()
+            .optional()
+            .with_context(|| {
+                format!("Error fetching request {} from database", request_id)
+            })
+            .traced()
+    }
+
+    /// Get the most recent request+response for a profile+recipe, or `None` if
+    /// there has never been one received. If the given profile is `None`, match
+    /// all requests that have no associated profile.
+    pub fn get_latest_request(
+        &self,
+        profile_id: Option<&ProfileId>,
+        recipe_id: &RecipeId,
+    ) -> anyhow::Result<Option<Exchange>> {
+        trace!(
+            profile_id = ?profile_id,
+            recipe_id = %recipe_id,
+            "Fetching last request from database"
+        );
+        self.database
+            .connection()
+            .query_row(
+                // `IS` needed for profile_id so `None` will match `NULL`
+                "SELECT * FROM requests
+                WHERE collection_id = :collection_id
+                    AND profile_id IS :profile_id
+                    AND recipe_id = :recipe_id
+                ORDER BY start_time DESC LIMIT 1",
+                named_params! {
+                    ":collection_id": self.collection_id,
+                    ":profile_id": profile_id,
+                    ":recipe_id": recipe_id,
+                },
+                |row| row.try_into(),
+            )
+            .optional()
+            .with_context(|| {
+                format!(
+                    "Error fetching request [profile={}; recipe={}] \
+                    from database",
+                    profile_id.map(ProfileId::to_string).unwrap_or_default(),
+                    recipe_id
+                )
+            })
+            .traced()
+    }
+
+    /// Get the most recent request+response for a profile+recipe whose
+    /// unredacted request had the given
+    /// [fingerprint](crate::http::RequestRecord::fingerprint), or `None` if
+    /// there isn't one. Profile matching works the same as
+    /// [Self::get_latest_request].
+    pub fn get_cached_exchange(
+        &self,
+        profile_id: Option<&ProfileId>,
+        recipe_id: &RecipeId,
+        fingerprint: &str,
+    ) -> anyhow::Result<Option<Exchange>> {
+        trace!(
+            profile_id = ?profile_id,
+            recipe_id = %recipe_id,
+            fingerprint,
+            "Fetching cached request from database"
+        );
+        self.database
+            .connection()
+            .query_row(
+                "SELECT * FROM requests
+                WHERE collection_id = :collection_id
+                    AND profile_id IS :profile_id
+                    AND recipe_id = :recipe_id
+                    AND fingerprint = :fingerprint
+                ORDER BY start_time DESC LIMIT 1",
+                named_params! {
+                    ":collection_id": self.collection_id,
+                    ":profile_id": profile_id,
+                    ":recipe_id": recipe_id,
+                    ":fingerprint": fingerprint,
+                },
+                |row| row.try_into(),
+            )
+            .optional()
+            .with_context(|| {
+                format!(
+                    "Error fetching cached request [profile={}; recipe={}] \
+                    from database",
+                    profile_id.map(ProfileId::to_string).unwrap_or_default(),
+                    recipe_id
+                )
+            })
+            .traced()
+    }
+
+    /// Add a new exchange to history. The HTTP engine is responsible for
+    /// inserting its own exchanges. Only requests that received a valid HTTP
+    /// response should be stored. In-flight requests, invalid requests, and
+    /// requests that failed to complete (e.g. because of a network error)
+    /// should not (and cannot) be stored.
+    pub fn insert_exchange(&self, exchange: &Exchange) -> anyhow::Result<()> {
+        self.insert_exchange_with_fingerprint(exchange, None)
+    }
+
+    /// [Self::insert_exchange], with a
+    /// [fingerprint](crate::http::RequestRecord::fingerprint) of the
+    /// unredacted request. Only exchanges with a fingerprint can be returned
+    /// by [Self::get_cached_exchange].
+    pub fn insert_exchange_with_fingerprint(
+        &self,
+        exchange: &Exchange,
+        fingerprint: Option<&str>,
+    ) -> anyhow::Result<()> {
+        debug!(
+            id = %exchange.id,
+            url = %exchange.request.url,
+            "Adding exchange to database",
+        );
+        self.database
+            .connection()
+            .execute(
+                "INSERT INTO
+                requests (
+                    id,
+                    collection_id,
+                    profile_id,
+                    recipe_id,
+                    start_time,
+                    end_time,
+                    request,
+                    response,
+                    status_code,
+                    retries,
+                    timing,
+                    fingerprint
+                )
+                VALUES (:id, :collection_id, :profile_id, :recipe_id,
+                    :start_time, :end_time, :request, :response, :status_code,
+                    :retries, :timing, :fingerprint)",
+                named_params! {
+                    ":id": exchange.id,
+                    ":collection_id": self.collection_id,
+                    ":profile_id": &exchange.request.profile_id,
+                    ":recipe_id": &exchange.request.recipe_id,
+                    ":start_time": &exchange.start_time,
+                    ":end_time": &exchange.end_time,
+                    ":request": &ByteEncoded(&*exchange.request),
+                    ":response": &ByteEncoded(&*exchange.response),
+                    ":status_code": exchange.response.status.as_u16(),
+                    ":retries": &ByteEncoded(&exchange.retries),
+                    ":timing": exchange.timing.as_ref().map(ByteEncoded),
+                    ":fingerprint": fingerprint,
+                },
+            )
+            .context(format!(
+                "Error saving request {} to database",
+                exchange.id
+            ))
+            .traced()?;
+        Ok(())
+    }
+
+    /// Get the most recent WebSocket conversation for a profile+recipe, or
+    /// `None` if a connection has never been opened. Profile matching works
+    /// the same as [Self::get_latest_request].
+    pub fn get_latest_websocket_exchange(
+        &self,
+        profile_id: Option<&ProfileId>,
+        recipe_id: &RecipeId,
+    ) -> anyhow::Result<Option<WebSocketExchange>> {
+        trace!(
+            profile_id = ?profile_id,
+            recipe_id = %recipe_id,
+            "Fetching last WebSocket exchange from database"
+        );
+        self.database
+            .connection()
+            .query_row(
+                "SELECT * FROM websocket_exchanges
+                WHERE collection_id = :collection_id
+                    AND profile_id IS :profile_id
+                    AND recipe_id = :recipe_id
+                ORDER BY start_time DESC LIMIT 1",
+                named_params! {
+                    ":collection_id": self.collection_id,
+                    ":profile_id": profile_id,
+                    ":recipe_id": recipe_id,
+                },
+                |row| row.try_into(),
+            )
+            .optional()
+            .with_context(|| {
+                format!(
+                    "Error fetching WebSocket exchange [profile={}; recipe={}] \
+                    from database",
+                    profile_id.map(ProfileId::to_string).unwrap_or_default(),
+                    recipe_id
+                )
+            })
+            .traced()
+    }
+
+    /// Add a finished WebSocket conversation to history. Only connections
+    /// that were successfully opened should be stored.
+    pub fn insert_websocket_exchange(
+        &self,
+        exchange: &WebSocketExchange,
+    ) -> anyhow::Result<()> {
+        debug!(
+            id = %exchange.id,
+            url = %exchange.request.url,
+            "Adding WebSocket exchange to database",
+        );
+        self.database
+            .connection()
+            .execute(
+                "INSERT INTO
+                websocket_exchanges (
+                    id,
+                    collection_id,
+                    profile_id,
+                    recipe_id,
+                    start_time,
+                    end_time,
+                    request,
+                    messages
+                )
+                VALUES (:id, :collection_id, :profile_id, :recipe_id,
+                    :start_time, :end_time, :request, :messages)",
+                named_params! {
+                    ":id": exchange.id,
+                    ":collection_id": self.collection_id,
+                    ":profile_id": &exchange.request.profile_id,
+                    ":recipe_id": &exchange.request.recipe_id,
+                    ":start_time": &exchange.start_time,
+                    ":end_time": &exchange.end_time,
+                    ":request": &ByteEncoded(&*exchange.request),
+                    ":messages": &ByteEncoded(&exchange.messages),
+                },
+            )
+            .context(format!(
+                "Error saving WebSocket exchange {} to database",
+                exchange.id
+            ))
+            .traced()?;
+        Ok(())
+    }
+
+    /// Get a list of all requests for a profile+recipe combo
+    pub fn get_all_requests(
+        &self,
+        profile_id: Option<&ProfileId>,
+        recipe_id: &RecipeId,
+    ) -> anyhow::Result<Vec<ExchangeSummary>> {
+        trace!(
+            profile_id = ?profile_id,
+            recipe_id = %recipe_id,
+            "Fetching request history from database"
+        );
+        self.database
+            .connection()
+            .prepare(
+                "SELECT id, profile_id, recipe_id, start_time, end_time,
+                    status_code
+                FROM requests
+                WHERE collection_id = :collection_id
+                    AND profile_id IS :profile_id
+                    AND recipe_id = :recipe_id
+                ORDER BY start_time DESC",
+            )?
+            .query_map(
+                named_params! {
+                    ":collection_id": self.collection_id,
+                    ":profile_id": profile_id,
+                    ":recipe_id": recipe_id,
+                },
+                |row| row.try_into(),
+            )
+            .context("Error fetching request history from database")
+            .traced()?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Error extracting request history")
+    }
+
+    /// Get a list of all requests in the collection, across every profile and
+    /// recipe
+    pub fn get_collection_requests(
+        &self,
+    ) -> anyhow::Result<Vec<ExchangeSummary>> {
+        trace!("Fetching collection request history from database");
+        self.database
+            .connection()
+            .prepare(
+                "SELECT id, profile_id, recipe_id, start_time, end_time,
+                    status_code
+                FROM requests
+                WHERE collection_id = :collection_id
+                ORDER BY start_time DESC",
+            )?
+            .query_map(
+                named_params! {":collection_id": self.collection_id},
+                |row| row.try_into(),
+            )
+            .context("Error fetching request history from database")
+            .traced()?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Error extracting request history")
+    }
+
+    /// Get the most recent request for each recipe under a profile. If the
+    /// given profile is `None`, match all requests that have no associated
+    /// profile. Recipes with no history are omitted.
+    pub fn get_latest_summaries(
+        &self,
+        profile_id: Option<&ProfileId>,
+    ) -> anyhow::Result<Vec<ExchangeSummary>> {
+        trace!(
+            profile_id = ?profile_id,
+            "Fetching latest request for each recipe from database"
+        );
+        self.database
+            .connection()
+            .prepare(
+                // SQLite takes the bare columns from the row with the max value
+                "SELECT id, profile_id, recipe_id, MAX(start_time) AS start_time,
+                    end_time, status_code
+                FROM requests
+                WHERE collection_id = :collection_id
+                    AND profile_id IS :profile_id
+                GROUP BY recipe_id",
+            )?
+            .query_map(
+                named_params! {
+                    ":collection_id": self.collection_id,
+                    ":profile_id": profile_id,
+                },
+                |row| row.try_into(),
+            )
+            .context("Error fetching request history from database")
+            .traced()?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Error extracting request history")
+    }
+
+    /// Get full exchanges from history, for exporting. Unlike other queries,
+    /// `None` for profile or recipe means *any* profile/recipe. If a limit is
+    /// given, only the most recent exchanges are included. Results are
+    /// returned in chronological order (oldest first).
+    pub fn get_exchanges(
+        &self,
+        profile_id: Option<&ProfileId>,
+        recipe_id: Option<&RecipeId>,
+        limit: Option<usize>,
+    ) -> anyhow::Result<Vec<Exchange>> {
+        trace!(
+            profile_id = ?profile_id,
+            recipe_id = ?recipe_id,
+            limit = ?limit,
+            "Fetching exchanges from database"
+        );
+        let mut exchanges = self
+            .database
+            .connection()
+            .prepare(
+                // A negative limit means no limit
+                "SELECT * FROM requests
+                WHERE collection_id = :collection_id
+                    AND (:profile_id IS NULL OR profile_id = :profile_id)
+                    AND (:recipe_id IS NULL OR recipe_id = :recipe_id)
+                ORDER BY start_time DESC LIMIT :limit",
+            )?
+            .query_map(
+                named_params! {
+                    ":collection_id": self.collection_id,
+                    ":profile_id": profile_id,
+                    ":recipe_id": recipe_id,
+                    ":limit": limit.map_or(-1, |limit| limit as i64),
+                },
+                |row| row.try_into(),
+            )
+            .context("Error fetching exchanges from database")
+            .traced()?
+            .collect::<rusqlite::Result<Vec<Exchange>>>()
+            .context("Error extracting exchanges")?;
+        exchanges.reverse();
+        Ok(exchanges)
+    }
+
+    /// Get the value of a UI state field
+    pub fn get_ui<K, V>(&self, key: K) -> anyhow::Result<Option<V>>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned,
+    {
+        let value = self
+            .database
+            .connection()
+            .query_row(
+                "SELECT value FROM ui_state
+                WHERE collection_id = :collection_id AND key = :key",
+                named_params! {
+                    ":collection_id": self.collection_id,
+                    ":key": ByteEncoded(&key),
+                },
+                |row| {
+                    let value: ByteEncoded<V> = row.get("value")?;
+                    Ok(value.0)
+                },
+            )
+            .optional()
+            .context(format!("Error fetching UI state for {key:?}"))
+            .traced()?;
+        debug!(?key, ?value, "Fetched UI state");
+        Ok(value)
+    }
+
+    /// Set the value of a UI state field
+    pub fn set_ui<K, V>(&self, key: K, value: V) -> anyhow::Result<()>
+    where
+        K: Debug + Serialize,
+        V: Debug + Serialize,
+    {
+        debug!(?key, ?value, "Setting UI state");
+        self.database
+            .connection()
+            .execute(
+                // Upsert!
+                "INSERT INTO ui_state (collection_id, key, value)
+                VALUES (:collection_id, :key, :value)
+                ON CONFLICT DO UPDATE SET value = excluded.value",
+                named_params! {
+                    ":collection_id": self.collection_id,
+                    ":key": ByteEncoded(key),
+                    ":value": ByteEncoded(value),
+                },
+            )
+            .context("Error saving UI state to database")
+            .traced()?;
+        Ok(())
+    }
+
+    /// Get the persisted cookie store for this collection. Return `None` if
+    /// cookies have never been saved
+    pub fn get_cookies(&self) -> anyhow::Result<Option<CookieStore>> {
+        let store = self
+            .database
+            .connection()
+            .query_row(
+                "SELECT store FROM cookies WHERE collection_id = :collection_id",
+                named_params! {":collection_id": self.collection_id},
+                |row| row.get::<_, String>("store"),
+            )
+            .optional()
+            .context("Error fetching cookies")
+            .traced()?;
+        store
+            .map(|store| {
+                cookie_store::serde::json::load(store.as_bytes())
+                    .map_err(|error| anyhow!(error))
+                    .context("Error deserializing cookies")
+            })
+            .transpose()
+            .traced()
+    }
+
+    /// Save the cookie store for this collection, replacing whatever was
+    /// there. Expired and session cookies are not saved.
+    pub fn set_cookies(
+        &self,
+        cookie_store: &CookieStore,
+    ) -> anyhow::Result<()> {
+        let mut store = Vec::new();
+        cookie_store::serde::json::save(cookie_store, &mut store)
+            .map_err(|error| anyhow!(error))
+            .context("Error serializing cookies")?;
+        let store = String::from_utf8(store)?;
+        debug!("Saving cookies");
+        self.database
+            .connection()
+            .execute(
+                "INSERT INTO cookies (collection_id, store)
+                VALUES (:collection_id, :store)
+                ON CONFLICT DO UPDATE SET store = excluded.store",
+                named_params! {
+                    ":collection_id": self.collection_id,
+                    ":store": store,
+                },
+            )
+            .context("Error saving cookies to database")
+            .traced()?;
+        Ok(())
+    }
+
+    /// Get the note attached to a recipe, if any
+    pub fn get_recipe_note(
+        &self,
+        recipe_id: &RecipeId,
+    ) -> anyhow::Result<Option<String>> {
+        self.database
+            .connection()
+            .query_row(
+                "SELECT note FROM recipe_notes
+                WHERE collection_id = :collection_id
+                    AND recipe_id = :recipe_id",
+                named_params! {
+                    ":collection_id": self.collection_id,
+                    ":recipe_id": recipe_id,
+                },
+                |row| row.get("note"),
+            )
+            .optional()
+            .with_context(|| {
+                format!("Error fetching note for recipe `{recipe_id}`")
+            })
+            .traced()
+    }
+
+    /// Attach a note to a recipe, replacing any existing note. `None` deletes
+    /// the note
+    pub fn set_recipe_note(
+        &self,
+        recipe_id: &RecipeId,
+        note: Option<&str>,
+    ) -> anyhow::Result<()> {
+        debug!(%recipe_id, "Saving recipe note");
+        let connection = self.database.connection();
+        if let Some(note) = note {
+            connection.execute(
+                "INSERT INTO recipe_notes (collection_id, recipe_id, note)
+                VALUES (:collection_id, :recipe_id, :note)
+                ON CONFLICT DO UPDATE SET note = excluded.note",
+                named_params! {
+                    ":collection_id": self.collection_id,
+                    ":recipe_id": recipe_id,
+                    ":note": note,
+                },
+            )
+        } else {
+            connection.execute(
+                "DELETE FROM recipe_notes
+                WHERE collection_id = :collection_id
+                    AND recipe_id = :recipe_id",
+                named_params! {
+                    ":collection_id": self.collection_id,
+                    ":recipe_id": recipe_id,
+                },
+            )
+        }
+        .with_context(|| format!("Error saving note for recipe `{recipe_id}`"))
+        .traced()?;
+        Ok(())
+    }
+
+    /// Get the note attached to an exchange, if any
+    pub fn get_exchange_note(
+        &self,
+        request_id: RequestId,
+    ) -> anyhow::Result<Option<String>> {
+        self.database
+            .connection()
+            .query_row(
+                "SELECT note FROM requests
+                WHERE collection_id = :collection_id AND id = :request_id",
+                named_params! {
+                    ":collection_id": self.collection_id,
+                    ":request_id": request_id,
+                },
+                |row| row.get("note"),
+            )
+            .optional()
+            .with_context(|| {
+                format!("Error fetching note for request `{request_id}`")
+            })
+            .traced()
+            .map(Option::flatten)
+    }
+
+    /// Get every exchange note in the collection, keyed by request ID
+    pub fn get_exchange_notes(
+        &self,
+    ) -> anyhow::Result<HashMap<RequestId, String>> {
+        self.database
+            .connection()
+            .prepare(
+                "SELECT id, note FROM requests
+                WHERE collection_id = :collection_id AND note IS NOT NULL",
+            )?
+            .query_map(
+                named_params! {":collection_id": self.collection_id},
+                |row| Ok((row.get("id")?, row.get("note")?)),
+            )
+            .context("Error fetching exchange notes from database")
+            .traced()?
+            .collect::<rusqlite::Result<_>>()
+            .context("Error extracting exchange notes")
+    }
+
+    /// Attach a note to an exchange, replacing any existing note. `None`
+    /// deletes the note. Only exchanges that were stored in history can have
+    /// notes.
+    pub fn set_exchange_note(
+        &self,
+        request_id: RequestId,
+        note: Option<&str>,
+    ) -> anyhow::Result<()> {
+        debug!(%request_id, "Saving exchange note");
+        let updated = self
+            .database
+            .connection()
+            .execute(
+                "UPDATE requests SET note = :note
+                WHERE collection_id = :collection_id AND id = :request_id",
+                named_params! {
+                    ":collection_id": self.collection_id,
+                    ":request_id": request_id,
+                    ":note": note,
+                },
+            )
             .with_context(|| {
-                format!("Error fetching request {} from database", request_id)
+                format!("Error saving note for request `{request_id}`")
             })
-            .traced()
+            .traced()?;
+        if updated == 0 {
+            bail!("Request `{request_id}` is not in history");
+        }
+        Ok(())
     }
 
-    /// Get the most recent request+response for a profile+recipe, or `None` if
-    /// there has never been one received. If the given profile is `None`, match
-    /// all requests that have no associated profile.
-    pub fn get_latest_request(
+    /// Get the GraphQL schema fetched for a recipe, if any
+    pub fn get_graphql_schema(
         &self,
-        profile_id: Option<&ProfileId>,
         recipe_id: &RecipeId,
-    ) -> anyhow::Result<Option<Exchange>> {
-        trace!(
-            profile_id = ?profile_id,
-            recipe_id = %recipe_id,
-            "Fetching last request from database"
-        );
+    ) -> anyhow::Result<Option<GraphQlSchema>> {
         self.database
             .connection()
             .query_row(
-                // `IS` needed for profile_id so `None` will match `NULL`
-                "SELECT * FROM requests
+                "SELECT schema FROM graphql_schemas
                 WHERE collection_id = :collection_id
-                    AND profile_id IS :profile_id
-                    AND recipe_id = :recipe_id
-                ORDER BY start_time DESC LIMIT 1",
+                    AND recipe_id = :recipe_id",
                 named_params! {
                     ":collection_id": self.collection_id,
-                    ":profile_id": profile_id,
                     ":recipe_id": recipe_id,
                 },
-                |row| row.try_into(),
+                |row| Ok(row.get::<_, ByteEncoded<_>>("schema")?.0),
             )
             .optional()
             .with_context(|| {
                 format!(
-                    "Error fetching request [profile={}; recipe={}] \
-                    from database",
-                    profile_id.map(ProfileId::to_string).unwrap_or_default(),
-                    recipe_id
+                    "Error fetching GraphQL schema for recipe `{recipe_id}`"
                 )
             })
             .traced()
     }
 
-    /// Add a new exchange to history. The HTTP engine is responsible for
-    /// inserting its own exchanges. Only requests that received a valid HTTP
-    /// response should be stored. In-flight requests, invalid requests, and
-    /// requests that failed to complete (e.g. because of a network error)
-    /// should not (and cannot) be stored.
-    pub fn insert_exchange(&self, exchange: &Exchange) -> anyhow::Result<()> {
-        debug!(
-            id = %exchange.id,
-            url = %exchange.request.url,
-            "Adding exchange to database",
-        );
+    /// Store the GraphQL schema for a recipe, replacing any existing schema
+    pub fn set_graphql_schema(
+        &self,
+        recipe_id: &RecipeId,
+        schema: &GraphQlSchema,
+    ) -> anyhow::Result<()> {
+        debug!(%recipe_id, "Saving GraphQL schema");
         self.database
             .connection()
             .execute(
-                "INSERT INTO
-                requests (
-                    id,
-                    collection_id,
-                    profile_id,
-                    recipe_id,
-                    start_time,
-                    end_time,
-                    request,
-                    response,
-                    status_code
-                )
-                VALUES (:id, :collection_id, :profile_id, :recipe_id,
-                    :start_time, :end_time, :request, :response, :status_code)",
+                "INSERT INTO graphql_schemas (collection_id, recipe_id, schema)
+                VALUES (:collection_id, :recipe_id, :schema)
+                ON CONFLICT DO UPDATE SET schema = excluded.schema",
                 named_params! {
-                    ":id": exchange.id,
                     ":collection_id": self.collection_id,
-                    ":profile_id": &exchange.request.profile_id,
-                    ":recipe_id": &exchange.request.recipe_id,
-                    ":start_time": &exchange.start_time,
-                    ":end_time": &exchange.end_time,
-                    ":request": &ByteEncoded(&*exchange.request),
-                    ":response": &ByteEncoded(&*exchange.response),
-                    ":status_code": exchange.response.status.as_u16(),
+                    ":recipe_id": recipe_id,
+                    ":schema": ByteEncoded(schema),
                 },
             )
-            .context(format!(
-                "Error saving request {} to database",
-                exchange.id
-            ))
+            .with_context(|| {
+                format!("Error saving GraphQL schema for recipe `{recipe_id}`")
+            })
             .traced()?;
         Ok(())
     }
 
-    /// Get a list of all requests for a profile+recipe combo
-    pub fn get_all_requests(
+    /// Get the golden exchange for a recipe, if one has been marked
+    pub fn get_golden_exchange(
         &self,
-        profile_id: Option<&ProfileId>,
         recipe_id: &RecipeId,
-    ) -> anyhow::Result<Vec<ExchangeSummary>> {
-        trace!(
-            profile_id = ?profile_id,
-            recipe_id = %recipe_id,
-            "Fetching request history from database"
-        );
-        self.database
+    ) -> anyhow::Result<Option<GoldenExchange>> {
+        let golden = self
+            .database
             .connection()
-            .prepare(
-                "SELECT id, start_time, end_time, status_code FROM requests
+            .query_row(
+                "SELECT name, request_id FROM golden_exchanges
                 WHERE collection_id = :collection_id
-                    AND profile_id IS :profile_id
-                    AND recipe_id = :recipe_id
-                ORDER BY start_time DESC",
-            )?
-            .query_map(
+                    AND recipe_id = :recipe_id",
                 named_params! {
                     ":collection_id": self.collection_id,
-                    ":profile_id": profile_id,
                     ":recipe_id": recipe_id,
                 },
-                |row| row.try_into(),
+                |row| {
+                    Ok((
+                        row.get::<_, String>("name")?,
+                        row.get::<_, RequestId>("request_id")?,
+                    ))
+                },
             )
-            .context("Error fetching request history from database")
-            .traced()?
-            .collect::<rusqlite::Result<Vec<_>>>()
-            .context("Error extracting request history")
+            .optional()
+            .with_context(|| {
+                format!(
+                    "Error fetching golden exchange for recipe `{recipe_id}`"
+                )
+            })
+            .traced()?;
+        let Some((name, request_id)) = golden else {
+            return Ok(None);
+        };
+        Ok(self
+            .get_request(request_id)?
+            .map(|exchange| GoldenExchange { name, exchange }))
     }
 
-    /// Get the value of a UI state field
-    pub fn get_ui<K, V>(&self, key: K) -> anyhow::Result<Option<V>>
-    where
-        K: Debug + Serialize,
-        V: Debug + DeserializeOwned,
-    {
-        let value = self
-            .database
+    /// Mark an exchange as the golden exchange for its recipe, replacing the
+    /// previous one
+    pub fn set_golden_exchange(
+        &self,
+        recipe_id: &RecipeId,
+        name: &str,
+        request_id: RequestId,
+    ) -> anyhow::Result<()> {
+        debug!(%recipe_id, name, %request_id, "Marking golden exchange");
+        self.database
+            .connection()
+            .execute(
+                "INSERT INTO golden_exchanges
+                    (collection_id, recipe_id, name, request_id)
+                VALUES (:collection_id, :recipe_id, :name, :request_id)
+                ON CONFLICT DO UPDATE SET
+                    name = excluded.name,
+                    request_id = excluded.request_id",
+                named_params! {
+                    ":collection_id": self.collection_id,
+                    ":recipe_id": recipe_id,
+                    ":name": name,
+                    ":request_id": request_id,
+                },
+            )
+            .with_context(|| {
+                format!("Error saving golden exchange for recipe `{recipe_id}`")
+            })
+            .traced()?;
+        Ok(())
+    }
+
+    /// Get a cached OAuth 2.0 token. Return `None` if no token has been
+    /// fetched for this key
+    pub fn get_oauth2_token(
+        &self,
+        key: &str,
+    ) -> anyhow::Result<Option<OAuth2Token>> {
+        self.database
             .connection()
             .query_row(
-                "SELECT value FROM ui_state
+                "SELECT token FROM oauth2_tokens
                 WHERE collection_id = :collection_id AND key = :key",
                 named_params! {
                     ":collection_id": self.collection_id,
-                    ":key": ByteEncoded(&key),
-                },
-                |row| {
-                    let value: ByteEncoded<V> = row.get("value")?;
-                    Ok(value.0)
+                    ":key": key,
                 },
+                |row| Ok(row.get::<_, ByteEncoded<_>>("token")?.0),
             )
             .optional()
-            .context(format!("Error fetching UI state for {key:?}"))
-            .traced()?;
-        debug!(?key, ?value, "Fetched UI state");
-        Ok(value)
+            .context("Error fetching OAuth 2.0 token")
+            .traced()
     }
 
-    /// Set the value of a UI state field
-    pub fn set_ui<K, V>(&self, key: K, value: V) -> anyhow::Result<()>
-    where
-        K: Debug + Serialize,
-        V: Debug + Serialize,
-    {
-        debug!(?key, ?value, "Setting UI state");
+    /// Cache an OAuth 2.0 token, replacing any existing token for the key
+    pub fn set_oauth2_token(
+        &self,
+        key: &str,
+        token: &OAuth2Token,
+    ) -> anyhow::Result<()> {
+        debug!(key, "Saving OAuth 2.0 token");
         self.database
             .connection()
             .execute(
-                // Upsert!
-                "INSERT INTO ui_state (collection_id, key, value)
-                VALUES (:collection_id, :key, :value)
-                ON CONFLICT DO UPDATE SET value = excluded.value",
+                "INSERT INTO oauth2_tokens (collection_id, key, token)
+                VALUES (:collection_id, :key, :token)
+                ON CONFLICT DO UPDATE SET token = excluded.token",
                 named_params! {
                     ":collection_id": self.collection_id,
-                    ":key": ByteEncoded(key),
-                    ":value": ByteEncoded(value),
+                    ":key": key,
+                    ":token": ByteEncoded(token),
                 },
             )
-            .context("Error saving UI state to database")
+            .context("Error saving OAuth 2.0 token to database")
             .traced()?;
         Ok(())
     }
 }
 
+/// An exchange the user has marked as the baseline for its recipe, so later
+/// responses can be compared against it
+#[derive(Debug)]
+pub struct GoldenExchange {
+    /// User-given label for the baseline, e.g. `v2 contract`
+    pub name: String,
+    pub exchange: Exchange,
+}
+
 /// Create an in-memory DB, only for testing
 #[cfg(test)]
 impl crate::test_util::Factory for Database {
@@ -631,6 +1390,29 @@ impl<'a, 'b> TryFrom<&'a Row<'b>> for Exchange {
             // Deserialize from bytes
             request: Arc::new(row.get::<_, ByteEncoded<_>>("request")?.0),
             response: Arc::new(row.get::<_, ByteEncoded<_>>("response")?.0),
+            retries: row
+                .get::<_, Option<ByteEncoded<_>>>("retries")?
+                .map(|retries| retries.0)
+                .unwrap_or_default(),
+            timing: row
+                .get::<_, Option<ByteEncoded<_>>>("timing")?
+                .map(|timing| timing.0),
+        })
+    }
+}
+
+/// Convert from `SELECT * FROM websocket_exchanges`
+impl<'a, 'b> TryFrom<&'a Row<'b>> for WebSocketExchange {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &'a Row<'b>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.get("id")?,
+            start_time: row.get("start_time")?,
+            end_time: row.get("end_time")?,
+            // Deserialize from bytes
+            request: Arc::new(row.get::<_, ByteEncoded<_>>("request")?.0),
+            messages: row.get::<_, ByteEncoded<_>>("messages")?.0,
         })
     }
 }
@@ -658,6 +1440,8 @@ impl<'a, 'b> TryFrom<&'a Row<'b>> for ExchangeSummary {
 
         Ok(Self {
             id: row.get("id")?,
+            profile_id: row.get("profile_id")?,
+            recipe_id: row.get("recipe_id")?,
             start_time: row.get("start_time")?,
             end_time: row.get("end_time")?,
             status: row.get::<_, StatusCodeWrapper>("status_code")?.0,
@@ -668,9 +1452,13 @@ impl<'a, 'b> TryFrom<&'a Row<'b>> for ExchangeSummary {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_util::Factory;
+    use crate::{
+        http::RequestRecord,
+        test_util::{assert_err, Factory},
+        ws::{Direction, MessageContent, WebSocketMessage},
+    };
+    use chrono::Utc;
     use itertools::Itertools;
-    use std::collections::HashMap;
 
     #[test]
     fn test_merge() {
@@ -876,6 +1664,101 @@ mod tests {
                 );
             }
         }
+
+        // Load everything at once, newest first
+        let all = database.get_collection_requests().unwrap();
+        assert_eq!(all.len(), 18);
+        assert!(all
+            .iter()
+            .tuple_windows()
+            .all(|(a, b)| a.start_time >= b.start_time));
+        for summary in all {
+            let key = (summary.profile_id, summary.recipe_id);
+            assert!(request_ids[&key].contains(&summary.id));
+        }
+    }
+
+    /// Get the latest request for each recipe under a profile
+    #[test]
+    fn test_get_latest_summaries() {
+        let database = CollectionDatabase::factory(());
+        let profile_id: ProfileId = "profile1".into();
+        let exchanges = [
+            (Some(profile_id.clone()), "recipe1".into()),
+            (Some(profile_id.clone()), "recipe1".into()),
+            (Some(profile_id.clone()), "recipe2".into()),
+            (None, "recipe1".into()),
+            (Some("profile2".into()), "recipe3".into()),
+        ]
+        .map(|params| {
+            let exchange = Exchange::factory(params);
+            database.insert_exchange(&exchange).unwrap();
+            exchange.id
+        });
+
+        let get_ids = |profile_id: Option<&ProfileId>| {
+            database
+                .get_latest_summaries(profile_id)
+                .unwrap()
+                .into_iter()
+                .map(|summary| (summary.recipe_id.to_string(), summary.id))
+                .sorted_by(|a, b| a.0.cmp(&b.0))
+                .collect_vec()
+        };
+        assert_eq!(
+            get_ids(Some(&profile_id)),
+            vec![
+                ("recipe1".to_owned(), exchanges[1]),
+                ("recipe2".to_owned(), exchanges[2])
+            ]
+        );
+        assert_eq!(get_ids(None), vec![("recipe1".to_owned(), exchanges[3])]);
+        assert_eq!(get_ids(Some(&"profile3".into())), vec![]);
+    }
+
+    #[test]
+    fn test_get_exchanges() {
+        let database = CollectionDatabase::factory(());
+        let profile_id: ProfileId = "profile1".into();
+        let recipe_id: RecipeId = "recipe1".into();
+        let exchanges = [
+            (Some(profile_id.clone()), recipe_id.clone()),
+            (None, recipe_id.clone()),
+            (Some(profile_id.clone()), "recipe2".into()),
+            (Some(profile_id.clone()), recipe_id.clone()),
+        ]
+        .map(|params| {
+            let exchange = Exchange::factory(params);
+            database.insert_exchange(&exchange).unwrap();
+            exchange.id
+        });
+
+        let get_ids = |profile_id: Option<&ProfileId>,
+                       recipe_id: Option<&RecipeId>,
+                       limit: Option<usize>| {
+            database
+                .get_exchanges(profile_id, recipe_id, limit)
+                .unwrap()
+                .into_iter()
+                .map(|exchange| exchange.id)
+                .collect_vec()
+        };
+
+        // Everything, oldest first
+        assert_eq!(get_ids(None, None, None), exchanges.to_vec());
+        assert_eq!(
+            get_ids(Some(&profile_id), None, None),
+            vec![exchanges[0], exchanges[2], exchanges[3]]
+        );
+        assert_eq!(
+            get_ids(None, Some(&recipe_id), None),
+            vec![exchanges[0], exchanges[1], exchanges[3]]
+        );
+        // Limit takes the most recent
+        assert_eq!(
+            get_ids(Some(&profile_id), Some(&recipe_id), Some(1)),
+            vec![exchanges[3]]
+        );
     }
 
     /// Test UI state storage and retrieval
@@ -904,4 +1787,191 @@ mod tests {
             Some("value2".into())
         );
     }
+
+    /// Test OAuth 2.0 token storage and retrieval. Tokens should be isolated
+    /// by collection, and saving should replace the existing token.
+    #[test]
+    fn test_oauth2_token() {
+        let database = Database::factory(());
+        let collection1 = database
+            .clone()
+            .into_collection(Path::new("slumber.yml"))
+            .unwrap();
+        let collection2 = database
+            .clone()
+            .into_collection(Path::new("README.md"))
+            .unwrap();
+        let token = |access_token: &str| OAuth2Token {
+            access_token: access_token.into(),
+            refresh_token: Some("refresh".into()),
+            expires_at: Some(Utc::now()),
+        };
+
+        let key = "key1";
+        assert_eq!(collection1.get_oauth2_token(key).unwrap(), None);
+        collection1.set_oauth2_token(key, &token("token1")).unwrap();
+        collection1.set_oauth2_token(key, &token("token2")).unwrap();
+        collection2.set_oauth2_token(key, &token("token3")).unwrap();
+
+        assert_eq!(
+            collection1
+                .get_oauth2_token(key)
+                .unwrap()
+                .map(|token| token.access_token),
+            Some("token2".into())
+        );
+        assert_eq!(
+            collection2
+                .get_oauth2_token(key)
+                .unwrap()
+                .map(|token| token.access_token),
+            Some("token3".into())
+        );
+    }
+
+    /// Each recipe has at most one golden exchange. Marking another replaces
+    /// it
+    #[test]
+    fn test_golden_exchange() {
+        let collection = CollectionDatabase::factory(());
+        let recipe_id = RecipeId::from("recipe1");
+        let exchange1 = Exchange::factory(());
+        let exchange2 = Exchange::factory(());
+        collection.insert_exchange(&exchange1).unwrap();
+        collection.insert_exchange(&exchange2).unwrap();
+
+        assert!(collection
+            .get_golden_exchange(&recipe_id)
+            .unwrap()
+            .is_none());
+        collection
+            .set_golden_exchange(&recipe_id, "first", exchange1.id)
+            .unwrap();
+        collection
+            .set_golden_exchange(&recipe_id, "second", exchange2.id)
+            .unwrap();
+        let golden =
+            collection.get_golden_exchange(&recipe_id).unwrap().unwrap();
+        assert_eq!(golden.name, "second");
+        assert_eq!(golden.exchange.id, exchange2.id);
+        assert!(collection
+            .get_golden_exchange(&"recipe2".into())
+            .unwrap()
+            .is_none());
+    }
+
+    /// GraphQL schemas are stored per recipe, and replaced when fetched again
+    #[test]
+    fn test_graphql_schema() {
+        let collection = CollectionDatabase::factory(());
+        let recipe_id = RecipeId::from("recipe1");
+        let schema = GraphQlSchema::factory(());
+
+        assert_eq!(collection.get_graphql_schema(&recipe_id).unwrap(), None);
+        collection
+            .set_graphql_schema(&recipe_id, &GraphQlSchema::default())
+            .unwrap();
+        collection.set_graphql_schema(&recipe_id, &schema).unwrap();
+        assert_eq!(
+            collection.get_graphql_schema(&recipe_id).unwrap(),
+            Some(schema)
+        );
+        assert_eq!(
+            collection.get_graphql_schema(&"recipe2".into()).unwrap(),
+            None
+        );
+    }
+
+    /// Notes can be attached to recipes and exchanges, and removed again
+    #[test]
+    fn test_notes() {
+        let database = CollectionDatabase::factory(());
+        let recipe_id: RecipeId = "recipe1".into();
+        assert_eq!(database.get_recipe_note(&recipe_id).unwrap(), None);
+        database.set_recipe_note(&recipe_id, Some("first")).unwrap();
+        database
+            .set_recipe_note(&recipe_id, Some("second"))
+            .unwrap();
+        assert_eq!(
+            database.get_recipe_note(&recipe_id).unwrap(),
+            Some("second".into())
+        );
+        database.set_recipe_note(&recipe_id, None).unwrap();
+        assert_eq!(database.get_recipe_note(&recipe_id).unwrap(), None);
+
+        let exchange1 = Exchange::factory(());
+        let exchange2 = Exchange::factory(());
+        database.insert_exchange(&exchange1).unwrap();
+        database.insert_exchange(&exchange2).unwrap();
+        assert_eq!(database.get_exchange_note(exchange1.id).unwrap(), None);
+        database
+            .set_exchange_note(exchange1.id, Some("repro for bug"))
+            .unwrap();
+        assert_eq!(
+            database.get_exchange_note(exchange1.id).unwrap(),
+            Some("repro for bug".into())
+        );
+        assert_eq!(
+            database.get_exchange_notes().unwrap(),
+            HashMap::from([(exchange1.id, "repro for bug".to_owned())])
+        );
+        database.set_exchange_note(exchange1.id, None).unwrap();
+        assert_eq!(database.get_exchange_notes().unwrap(), HashMap::new());
+
+        // Exchanges that aren't in history can't have notes
+        assert_err!(
+            database.set_exchange_note(RequestId::new(), Some("note")),
+            "is not in history"
+        );
+    }
+
+    #[test]
+    fn test_websocket_exchange() {
+        let database = CollectionDatabase::factory(());
+        let profile_id: ProfileId = "profile1".into();
+        let recipe_id: RecipeId = "recipe1".into();
+        let request = RequestRecord::factory((
+            Some(profile_id.clone()),
+            recipe_id.clone(),
+        ));
+        let exchange = WebSocketExchange {
+            id: request.id,
+            request: request.into(),
+            start_time: Utc::now(),
+            end_time: Utc::now(),
+            messages: vec![
+                WebSocketMessage {
+                    time: Utc::now(),
+                    direction: Direction::Sent,
+                    content: MessageContent::Text("hello".into()),
+                },
+                WebSocketMessage {
+                    time: Utc::now(),
+                    direction: Direction::Received,
+                    content: MessageContent::Binary(
+                        b"\x00\x01".to_vec().into(),
+                    ),
+                },
+            ],
+        };
+        database.insert_websocket_exchange(&exchange).unwrap();
+
+        let loaded = database
+            .get_latest_websocket_exchange(Some(&profile_id), &recipe_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded.id, exchange.id);
+        assert_eq!(loaded.request, exchange.request);
+        assert_eq!(loaded.messages, exchange.messages);
+
+        // HTTP history is separate
+        assert!(database
+            .get_latest_request(Some(&profile_id), &recipe_id)
+            .unwrap()
+            .is_none());
+        assert!(database
+            .get_latest_websocket_exchange(None, &recipe_id)
+            .unwrap()
+            .is_none());
+    }
 }
@@ -0,0 +1,5 @@
+//! Export request history to formats that other tools understand
+
+pub mod har;
+
+pub use har::Har;
@@ -0,0 +1,469 @@
+//! [HAR 1.2](http://www.softwareishard.com/blog/har-12-spec/) (HTTP Archive)
+//! format, as used by browser devtools and many other HTTP tools. These types
+//! are also used to import HAR files. Deserialization is lenient, because many
+//! tools leave out fields that the spec requires.
+
+use crate::http::{Exchange, ResponseBody, Timing};
+use base64::{prelude::BASE64_STANDARD, Engine};
+use chrono::{DateTime, SecondsFormat, Utc};
+use reqwest::{
+    header::{self, HeaderMap},
+    Version,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Root of a HAR file
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Har {
+    pub log: Log,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Log {
+    pub version: String,
+    pub creator: Creator,
+    pub entries: Vec<Entry>,
+}
+
+/// The application that created the file
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Creator {
+    pub name: String,
+    pub version: String,
+}
+
+/// A single request/response pair
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Entry {
+    /// ISO 8601 timestamp
+    pub started_date_time: String,
+    /// Total elapsed time, in milliseconds
+    pub time: f64,
+    pub request: Request,
+    pub response: Response,
+    /// Required by the spec, but we don't have anything to put in it
+    pub cache: serde_json::Map<String, serde_json::Value>,
+    pub timings: Timings,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Request {
+    pub method: String,
+    pub url: String,
+    pub http_version: String,
+    pub cookies: Vec<NameValue>,
+    pub headers: Vec<NameValue>,
+    pub query_string: Vec<NameValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<PostData>,
+    /// Always -1 (unknown), because we don't see the raw request
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Response {
+    pub status: u16,
+    pub status_text: String,
+    pub http_version: String,
+    pub cookies: Vec<NameValue>,
+    pub headers: Vec<NameValue>,
+    pub content: Content,
+    #[serde(rename = "redirectURL")]
+    pub redirect_url: String,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+/// A header, query parameter, or cookie
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NameValue {
+    pub name: String,
+    pub value: String,
+}
+
+/// Request body
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PostData {
+    pub mime_type: String,
+    pub text: String,
+}
+
+/// Response body
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Content {
+    /// Size of the decoded body, in bytes
+    pub size: i64,
+    pub mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// `base64` for binary bodies, otherwise omitted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+/// Time spent in each phase of the request, in milliseconds. -1 means the
+/// phase doesn't apply, e.g. DNS for a reused connection.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Timings {
+    pub blocked: f64,
+    pub dns: f64,
+    /// Includes the TLS handshake, per the spec
+    pub connect: f64,
+    pub send: f64,
+    pub wait: f64,
+    pub receive: f64,
+    pub ssl: f64,
+}
+
+impl Har {
+    /// Build a HAR log from a list of exchanges. Entries are in the same
+    /// order as the exchanges.
+    pub fn new<'a>(exchanges: impl IntoIterator<Item = &'a Exchange>) -> Self {
+        Self {
+            log: Log {
+                version: "1.2".into(),
+                creator: Creator {
+                    name: env!("CARGO_PKG_NAME").into(),
+                    version: env!("CARGO_PKG_VERSION").into(),
+                },
+                entries: exchanges.into_iter().map(Entry::from).collect(),
+            },
+        }
+    }
+}
+
+impl From<&Exchange> for Entry {
+    fn from(exchange: &Exchange) -> Self {
+        let request = &exchange.request;
+        let response = &exchange.response;
+        // We only know the version from the response, but they're the same
+        // connection so it applies to both
+        let http_version = format_version(response.version);
+        let total = exchange.duration().to_std().unwrap_or_default();
+
+        let request_body = request.body.as_ref().map(ResponseBody::bytes);
+        let (response_text, encoding) = encode_body(&response.body);
+        Self {
+            started_date_time: format_time(exchange.start_time),
+            time: millis(total),
+            request: Request {
+                method: request.method.to_string(),
+                url: request.url.to_string(),
+                http_version: http_version.clone(),
+                cookies: request_cookies(&request.headers),
+                headers: headers(&request.headers),
+                query_string: request
+                    .url
+                    .query_pairs()
+                    .map(|(name, value)| NameValue {
+                        name: name.into_owned(),
+                        value: value.into_owned(),
+                    })
+                    .collect(),
+                post_data: request_body.map(|body| PostData {
+                    mime_type: content_type(&request.headers),
+                    text: String::from_utf8_lossy(body).into_owned(),
+                }),
+                headers_size: -1,
+                body_size: request_body.map_or(0, |body| body.len() as i64),
+            },
+            response: Response {
+                status: response.status.as_u16(),
+                status_text: response
+                    .status
+                    .canonical_reason()
+                    .unwrap_or_default()
+                    .to_owned(),
+                http_version,
+                cookies: response_cookies(&response.headers),
+                headers: headers(&response.headers),
+                content: Content {
+                    size: response.body.size().as_u64() as i64,
+                    mime_type: content_type(&response.headers),
+                    text: response_text,
+                    encoding,
+                },
+                redirect_url: response
+                    .headers
+                    .get(header::LOCATION)
+                    .map(|value| {
+                        String::from_utf8_lossy(value.as_bytes()).into_owned()
+                    })
+                    .unwrap_or_default(),
+                headers_size: -1,
+                body_size: response
+                    .encoded_size
+                    .unwrap_or_else(|| response.body.size())
+                    .as_u64() as i64,
+            },
+            cache: Default::default(),
+            timings: Timings::new(exchange.timing.as_ref(), total),
+        }
+    }
+}
+
+impl Timings {
+    fn new(timing: Option<&Timing>, total: Duration) -> Self {
+        let optional =
+            |duration: Option<Duration>| duration.map_or(-1.0, millis);
+        match timing {
+            Some(timing) => Self {
+                blocked: -1.0,
+                dns: optional(timing.dns),
+                // HAR's connect time includes TLS, ours doesn't
+                connect: match (timing.connect, timing.tls) {
+                    (None, None) => -1.0,
+                    (connect, tls) => millis(
+                        connect.unwrap_or_default() + tls.unwrap_or_default(),
+                    ),
+                },
+                send: 0.0,
+                wait: millis(timing.wait),
+                receive: millis(timing.download),
+                ssl: optional(timing.tls),
+            },
+            // All we know is the total, so call it all waiting
+            None => Self {
+                blocked: -1.0,
+                dns: -1.0,
+                connect: -1.0,
+                send: 0.0,
+                wait: millis(total),
+                receive: 0.0,
+                ssl: -1.0,
+            },
+        }
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+fn format_version(version: Version) -> String {
+    match version {
+        Version::HTTP_09 => "HTTP/0.9",
+        Version::HTTP_10 => "HTTP/1.0",
+        Version::HTTP_2 => "HTTP/2.0",
+        Version::HTTP_3 => "HTTP/3.0",
+        _ => "HTTP/1.1",
+    }
+    .into()
+}
+
+fn headers(headers: &HeaderMap) -> Vec<NameValue> {
+    headers
+        .iter()
+        .map(|(name, value)| NameValue {
+            name: name.to_string(),
+            value: String::from_utf8_lossy(value.as_bytes()).into_owned(),
+        })
+        .collect()
+}
+
+fn content_type(headers: &HeaderMap) -> String {
+    headers
+        .get(header::CONTENT_TYPE)
+        .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+        .unwrap_or_default()
+}
+
+/// Parse cookies from the `Cookie` header, which has the format
+/// `name1=value1; name2=value2`
+fn request_cookies(headers: &HeaderMap) -> Vec<NameValue> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(parse_cookie)
+        .collect()
+}
+
+/// Parse cookies from `Set-Cookie` headers. Attributes (e.g. `Path`) aren't
+/// included
+fn response_cookies(headers: &HeaderMap) -> Vec<NameValue> {
+    headers
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(|value| parse_cookie(value.split(';').next()?))
+        .collect()
+}
+
+fn parse_cookie(pair: &str) -> Option<NameValue> {
+    let (name, value) = pair.trim().split_once('=')?;
+    Some(NameValue {
+        name: name.to_owned(),
+        value: value.to_owned(),
+    })
+}
+
+/// Get the text of a response body for HAR. Binary bodies are base64 encoded.
+/// Truncated bodies are omitted, because we only have a preview.
+fn encode_body(body: &ResponseBody) -> (Option<String>, Option<String>) {
+    if body.is_truncated() {
+        (None, None)
+    } else if let Some(text) = body.text() {
+        (Some(text.to_owned()), None)
+    } else {
+        (
+            Some(BASE64_STANDARD.encode(body.bytes())),
+            Some("base64".into()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::{RequestRecord, ResponseRecord},
+        test_util::{header_map, Factory},
+    };
+    use pretty_assertions::assert_eq;
+    use reqwest::{Method, StatusCode};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_har() {
+        let start_time = "2024-05-01T12:00:00Z".parse().unwrap();
+        let exchange = Exchange {
+            request: Arc::new(RequestRecord {
+                method: Method::POST,
+                url: "https://example.com/fish?kind=cod".parse().unwrap(),
+                headers: header_map([
+                    ("content-type", "application/json"),
+                    ("cookie", "session=abc; theme=dark"),
+                ]),
+                body: Some(ResponseBody::new(r#"{"name":"Barry"}"#.into())),
+                ..RequestRecord::factory(())
+            }),
+            response: Arc::new(ResponseRecord {
+                version: Version::HTTP_2,
+                status: StatusCode::CREATED,
+                headers: header_map([
+                    ("content-type", "application/octet-stream"),
+                    ("set-cookie", "id=1; Path=/; HttpOnly"),
+                ]),
+                body: ResponseBody::new(b"\xff\x00"[..].into()),
+                ..ResponseRecord::factory(())
+            }),
+            start_time,
+            end_time: start_time + chrono::Duration::milliseconds(150),
+            timing: Some(Timing {
+                dns: Some(Duration::from_millis(10)),
+                connect: Some(Duration::from_millis(20)),
+                tls: Some(Duration::from_millis(30)),
+                wait: Duration::from_millis(80),
+                download: Duration::from_millis(10),
+            }),
+            ..Exchange::factory(())
+        };
+
+        let har = serde_json::to_value(Har::new([&exchange])).unwrap();
+        assert_eq!(
+            har,
+            json!({
+                "log": {
+                    "version": "1.2",
+                    "creator": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                    "entries": [{
+                        "startedDateTime": "2024-05-01T12:00:00.000Z",
+                        "time": 150.0,
+                        "request": {
+                            "method": "POST",
+                            "url": "https://example.com/fish?kind=cod",
+                            "httpVersion": "HTTP/2.0",
+                            "cookies": [
+                                {"name": "session", "value": "abc"},
+                                {"name": "theme", "value": "dark"},
+                            ],
+                            "headers": [
+                                {
+                                    "name": "content-type",
+                                    "value": "application/json",
+                                },
+                                {
+                                    "name": "cookie",
+                                    "value": "session=abc; theme=dark",
+                                },
+                            ],
+                            "queryString": [{"name": "kind", "value": "cod"}],
+                            "postData": {
+                                "mimeType": "application/json",
+                                "text": r#"{"name":"Barry"}"#,
+                            },
+                            "headersSize": -1,
+                            "bodySize": 16,
+                        },
+                        "response": {
+                            "status": 201,
+                            "statusText": "Created",
+                            "httpVersion": "HTTP/2.0",
+                            "cookies": [{"name": "id", "value": "1"}],
+                            "headers": [
+                                {
+                                    "name": "content-type",
+                                    "value": "application/octet-stream",
+                                },
+                                {
+                                    "name": "set-cookie",
+                                    "value": "id=1; Path=/; HttpOnly",
+                                },
+                            ],
+                            "content": {
+                                "size": 2,
+                                "mimeType": "application/octet-stream",
+                                "text": "/wA=",
+                                "encoding": "base64",
+                            },
+                            "redirectURL": "",
+                            "headersSize": -1,
+                            "bodySize": 2,
+                        },
+                        "cache": {},
+                        "timings": {
+                            "blocked": -1.0,
+                            "dns": 10.0,
+                            "connect": 50.0,
+                            "send": 0.0,
+                            "wait": 80.0,
+                            "receive": 10.0,
+                            "ssl": 30.0,
+                        },
+                    }],
+                },
+            })
+        );
+    }
+
+    /// Without a timing breakdown, the whole duration counts as waiting
+    #[test]
+    fn test_timings_unknown() {
+        let timings = Timings::new(None, Duration::from_millis(42));
+        assert_eq!(timings.wait, 42.0);
+        assert_eq!(timings.dns, -1.0);
+        assert_eq!(timings.connect, -1.0);
+    }
+}
@@ -33,34 +33,86 @@
 //! | Exchange |
 //! +----------+
 
+mod assertion;
 mod cereal;
+mod compression;
 mod content_type;
+mod graphql;
+mod hook;
+mod instrument;
+mod markup;
 mod models;
+mod netrc;
+mod oauth2;
 mod query;
+mod rate_limit;
+mod sigv4;
+mod sse;
+mod throttle;
+mod tls;
+mod x509;
 
+pub use assertion::*;
 pub use content_type::*;
+pub use graphql::{GraphQlSchema, INTROSPECTION_QUERY};
 pub use models::*;
+pub use oauth2::*;
 pub use query::*;
+pub use sse::*;
 
 use crate::{
-    collection::{Authentication, Method, Recipe},
+    collection::{
+        Authentication, ConnectionOptions, HttpVersion, Method, MultipartField,
+        OAuth2, ProfileProxy, RateLimit, Recipe, RecipeBody, RecipeId,
+        RetryPolicy,
+    },
     config::Config,
     db::CollectionDatabase,
+    http::{
+        compression::{compress_request_body, Decoder},
+        hook::HookCommand,
+        instrument::{AttemptRecorder, TimingResolver},
+        models::{DigestCredentials, ResumeDownload, SpoolFile},
+        netrc::Netrc,
+        rate_limit::{RateLimiters, TokenBucket},
+        sigv4::SigningParams,
+        throttle::{throttle_request_body, throttle_stream, RateLimiter},
+        tls::{tls_config, TlsInfoCache},
+    },
     template::{Template, TemplateContext},
     util::ResultExt,
 };
-use anyhow::Context;
-use bytes::Bytes;
-use chrono::Utc;
+use anyhow::{anyhow, bail, Context};
+use bytes::{Bytes, BytesMut};
+use bytesize::ByteSize;
+use chrono::{DateTime, Local, Utc};
+use digest_auth::{AuthContext, HttpMethod};
 use futures::future::{self, OptionFuture};
+use http_body_util::BodyExt;
 use indexmap::IndexMap;
+use itertools::Itertools;
 use reqwest::{
-    header::{HeaderMap, HeaderName, HeaderValue},
-    Client, Response, Url,
+    cookie::CookieStore as _,
+    header::{self, HeaderMap, HeaderName, HeaderValue},
+    multipart::{Form, Part},
+    Body, Client, ClientBuilder, Request, Response, StatusCode, Url,
 };
-use std::{collections::HashSet, sync::Arc};
-use tokio::try_join;
+use reqwest_cookie_store::CookieStoreMutex;
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncWriteExt},
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time, try_join,
+};
+use tokio_util::io::ReaderStream;
 use tracing::{info, info_span};
+use url::Position;
 
 const USER_AGENT: &str =
     concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
@@ -79,27 +131,163 @@ pub struct HttpEngine {
     danger_client: Client,
     /// Hostnames for which we should ignore TLS
     danger_hostnames: HashSet<String>,
+    /// Fallback credentials for requests without authentication. Only loaded
+    /// if the user opts in via config
+    netrc: Option<Arc<Netrc>>,
+    /// Cookies set by responses, to be sent with subsequent requests to the
+    /// same domain. Shared by both clients
+    cookie_store: Arc<CookieStoreMutex>,
+    /// Should the cookie store be saved to the DB after each request?
+    persist_cookies: bool,
+    /// Global proxy settings, from the config. Profiles can override these
+    proxy: ProxySettings,
+    /// Clients that use a proxy or a forced HTTP version, built on demand
+    /// because each combination of settings requires its own client. Keyed
+    /// by settings and whether the client ignores TLS errors.
+    custom_clients: Arc<Mutex<HashMap<ClientSettings, Client>>>,
+    /// Response bodies larger than this are spooled to disk
+    large_body_size: ByteSize,
+    /// Default retry policy, for recipes that don't define their own
+    retry: Option<RetryPolicy>,
+    /// Default HTTP version, for recipes that don't define their own
+    http_version: Option<HttpVersion>,
+    /// Request rate limits from the config, keyed by hostname
+    rate_limits: HashMap<String, RateLimit>,
+    /// Token buckets for rate-limited hosts
+    rate_limiters: Arc<RateLimiters>,
+    /// Limits the number of requests in flight at once, if the config sets a
+    /// limit. Each request holds a permit until its response is loaded
+    request_slots: Option<Arc<Semaphore>>,
+    /// Connection settings from the config. Recipes can override these
+    connection: ConnectionOptions,
+    /// TLS details for each server, for requests that reuse a connection
+    tls_info: Arc<TlsInfoCache>,
+}
+
+/// Settings that require a dedicated client
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct ClientSettings {
+    proxy: ProxySettings,
+    http_version: Option<HttpVersion>,
+    connection: ConnectionOptions,
+    danger: bool,
+}
+
+/// Which proxy (if any) to route a request through
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+struct ProxySettings {
+    proxy: Option<String>,
+    no_proxy: Option<String>,
+    /// Skip all proxies, including any from the environment
+    direct: bool,
+}
+
+impl ProxySettings {
+    /// Decide how a client built with these settings should handle proxies
+    fn mode(&self) -> ProxyMode<'_> {
+        match self {
+            Self { direct: true, .. } => ProxyMode::Direct,
+            Self {
+                proxy: Some(url),
+                no_proxy,
+                ..
+            } => ProxyMode::Url {
+                url,
+                no_proxy: no_proxy.as_deref(),
+            },
+            Self { proxy: None, .. } => ProxyMode::Environment,
+        }
+    }
+}
+
+/// How a client handles proxies, as decided by [ProxySettings::mode]
+#[derive(Debug, PartialEq)]
+enum ProxyMode<'a> {
+    /// Use the proxy from the environment (`HTTP_PROXY`, etc.), if any. This
+    /// is reqwest's default behavior.
+    Environment,
+    /// Don't use any proxy, even one from the environment
+    Direct,
+    /// Route requests through this proxy, except for hosts in `no_proxy`
+    Url {
+        url: &'a str,
+        no_proxy: Option<&'a str>,
+    },
 }
 
 impl HttpEngine {
     /// Build a new HTTP engine, which can be used for the entire program life
     pub fn new(config: &Config) -> Self {
+        let cookie_store = Arc::new(CookieStoreMutex::default());
         Self {
-            client: Client::builder()
-                .user_agent(USER_AGENT)
-                .build()
-                .expect("Error building reqwest client"),
-            danger_client: Client::builder()
-                .user_agent(USER_AGENT)
-                .danger_accept_invalid_certs(true)
-                .build()
-                .expect("Error building reqwest client"),
+            client: client_builder(
+                &cookie_store,
+                false,
+                None,
+                &config.connection,
+            )
+            .build()
+            .expect("Error building reqwest client"),
+            danger_client: client_builder(
+                &cookie_store,
+                true,
+                None,
+                &config.connection,
+            )
+            .build()
+            .expect("Error building reqwest client"),
             danger_hostnames: config
                 .ignore_certificate_hosts
                 .iter()
                 .cloned()
                 .collect(),
+            // A bad netrc file shouldn't prevent startup, so just log it
+            netrc: if config.use_netrc {
+                Netrc::load().traced().ok().flatten().map(Arc::new)
+            } else {
+                None
+            },
+            cookie_store,
+            persist_cookies: config.persist_cookies,
+            proxy: ProxySettings {
+                proxy: config.proxy.clone(),
+                no_proxy: config.no_proxy.clone(),
+                direct: false,
+            },
+            custom_clients: Default::default(),
+            large_body_size: config.large_body_size,
+            retry: config.retry.clone(),
+            http_version: config.http_version,
+            rate_limits: config
+                .rate_limits
+                .iter()
+                .map(|(host, limit)| (host.clone(), limit.clone()))
+                .collect(),
+            rate_limiters: Default::default(),
+            request_slots: config
+                .max_concurrent_requests
+                .map(|max| Arc::new(Semaphore::new(max.get()))),
+            connection: config.connection.clone(),
+            tls_info: Default::default(),
+        }
+    }
+
+    /// Replace the cookie store with the one persisted for a collection. Do
+    /// nothing if cookie persistence is disabled, or there are no cookies
+    /// saved yet.
+    pub fn load_cookies(
+        &self,
+        database: &CollectionDatabase,
+    ) -> anyhow::Result<()> {
+        if self.persist_cookies {
+            if let Some(cookie_store) = database.get_cookies()? {
+                *self
+                    .cookie_store
+                    .lock()
+                    .expect("Cookie store lock poisoned") = cookie_store;
+            }
         }
+        Ok(())
     }
 
     /// Build a [RequestTicket] from a [RequestSeed]. This will render the
@@ -117,44 +305,289 @@ impl HttpEngine {
         let _ =
             info_span!("Build request", request_id = %id, ?recipe, ?options)
                 .entered();
+        let throttle = recipe.throttle.clone().unwrap_or_default();
+        let compression = recipe.compression.clone().unwrap_or_default();
+        let retry = recipe.retry.clone().or_else(|| self.retry.clone());
+        // A streamed file isn't in the request record, so we can't tell if it
+        // changed since the cached response
+        let cache_ttl = recipe.cache_ttl.filter(|_| {
+            options.body.is_some()
+                || !matches!(recipe.body, Some(RecipeBody::File(_)))
+        });
 
-        let (client, request) = async {
-            // Render everything up front so we can parallelize it
-            let (url, query, headers, authentication, body) = try_join!(
-                recipe.render_url(template_context),
-                recipe.render_query(options, template_context),
-                recipe.render_headers(options, template_context),
-                recipe.render_authentication(template_context),
-                recipe.render_body(template_context),
-            )?;
+        // Encoded credentials (e.g. base64 for basic auth) can't be matched
+        // literally during redaction, so if they include a sensitive value the
+        // whole header has to be redacted
+        let is_sensitive = |values: &[&str]| {
+            let sensitive_values = template_context
+                .sensitive_values
+                .lock()
+                .expect("Sensitive values lock poisoned");
+            values.iter().any(|value| {
+                sensitive_values
+                    .iter()
+                    .any(|sensitive| value.contains(sensitive.as_str()))
+            })
+        };
+        let mut sensitive_authorization = false;
+        let (client, mut request, resume_download, digest, cached_response) =
+            async {
+                // Render everything up front so we can parallelize it
+                let (url, query, mut headers, authentication, body) = try_join!(
+                    recipe.render_url(template_context),
+                    recipe.render_query(options, template_context),
+                    recipe.render_headers(options, template_context),
+                    recipe.render_authentication(template_context),
+                    recipe.render_body(options, template_context),
+                )?;
 
-            // Build the reqwest request first, so we can have it do all the
-            // hard work of encoding query params/authorization/etc.
-            // We'll just copy its homework at the end to get our
-            // RequestRecord
-            let client = self.get_client(&url);
-            let mut builder = client
-                .request(recipe.method.into(), url)
-                .query(&query)
-                .headers(headers);
+                if recipe.graphql && !headers.contains_key(header::CONTENT_TYPE)
+                {
+                    headers.insert(
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static("application/json"),
+                    );
+                }
+
+                // Build the reqwest request first, so we can have it do all the
+                // hard work of encoding query params/authorization/etc.
+                // We'll just copy its homework at the end to get our
+                // RequestRecord
+                let client = self.get_client(
+                    &url,
+                    recipe.http_version.or(self.http_version),
+                    recipe.connection.as_ref(),
+                    template_context,
+                )?;
+                // If the recipe has no auth of its own, fall back to netrc
+                let netrc_entry = if authentication.is_none()
+                    && !headers.contains_key(header::AUTHORIZATION)
+                {
+                    self.netrc
+                        .as_ref()
+                        .and_then(|netrc| netrc.get(url.host_str()?))
+                } else {
+                    None
+                };
+                let mut builder = client
+                    .request(recipe.method.into(), url)
+                    .query(&query)
+                    .headers(headers);
 
-            match authentication {
-                Some(Authentication::Basic { username, password }) => {
-                    builder = builder.basic_auth(username, password)
+                let mut digest = None;
+                let mut sigv4 = None;
+                match authentication {
+                    Some(Authentication::Basic { username, password }) => {
+                        sensitive_authorization = is_sensitive(&[
+                            username.as_str(),
+                            password.as_deref().unwrap_or_default(),
+                        ]);
+                        builder = builder.basic_auth(username, password)
+                    }
+                    Some(Authentication::Bearer(token)) => {
+                        sensitive_authorization =
+                            is_sensitive(&[token.as_str()]);
+                        builder = builder.bearer_auth(token)
+                    }
+                    // Digest credentials are sent in response to the server's
+                    // challenge, so we can't attach anything yet
+                    Some(Authentication::Digest { username, password }) => {
+                        digest = Some(DigestCredentials { username, password })
+                    }
+                    // Fetch a token, or use the cached one. Use the same client
+                    // settings as we would for a request to the token URL
+                    Some(Authentication::OAuth2(oauth2)) => {
+                        let token_url =
+                            oauth2.token_url.parse().with_context(|| {
+                                format!(
+                                    "Invalid token URL `{}`",
+                                    oauth2.token_url
+                                )
+                            })?;
+                        let token_client = self.get_client(
+                            &token_url,
+                            None,
+                            None,
+                            template_context,
+                        )?;
+                        let token = oauth2
+                            .get_token(
+                                &token_client,
+                                &template_context.database,
+                            )
+                            .await
+                            .context("Error getting OAuth 2.0 token")?;
+                        builder = builder.bearer_auth(token)
+                    }
+                    // The signature covers the whole request, so it's added
+                    // once everything else is done
+                    Some(Authentication::AwsSigV4 {
+                        access_key_id,
+                        secret_access_key,
+                        session_token,
+                        region,
+                        service,
+                    }) => {
+                        sigv4 = Some(SigningParams {
+                            access_key_id,
+                            secret_access_key,
+                            session_token,
+                            region,
+                            service,
+                        })
+                    }
+                    None => {
+                        if let Some(entry) = netrc_entry {
+                            builder = builder.basic_auth(
+                                &entry.login,
+                                entry.password.as_ref(),
+                            )
+                        }
+                    }
+                };
+                let is_multipart =
+                    matches!(body, Some(RenderedBody::FormMultipart(_)));
+                match body {
+                    Some(RenderedBody::Raw(body)) => {
+                        builder = builder.body(body)
+                    }
+                    Some(RenderedBody::FormMultipart(form)) => {
+                        builder = builder.multipart(form)
+                    }
+                    Some(RenderedBody::File { file, size }) => {
+                        // Stream the file so it never has to be fully loaded.
+                        // Streamed bodies are sent with chunked encoding by
+                        // default, so set the length explicitly
+                        let stream = ReaderStream::new(file);
+                        let body = match throttle.upload {
+                            Some(limit) => Body::wrap_stream(throttle_stream(
+                                stream, limit,
+                            )),
+                            None => Body::wrap_stream(stream),
+                        };
+                        builder = builder
+                            .header(header::CONTENT_LENGTH, size)
+                            .body(body);
+                    }
+                    None => {}
                 }
-                Some(Authentication::Bearer(token)) => {
-                    builder = builder.bearer_auth(token)
+                if let Some(timeout) = recipe.timeout {
+                    builder = builder.timeout(timeout);
                 }
-                None => {}
-            };
-            if let Some(body) = body {
-                builder = builder.body(body);
+
+                // If resuming a previous download, only ask for what's missing
+                let resume_download = match &options.resume_download {
+                    Some(path) => {
+                        let offset = fs::metadata(path)
+                            .await
+                            .with_context(|| {
+                                format!(
+                                    "Error reading partial download {path:?}"
+                                )
+                            })?
+                            .len();
+                        builder = builder
+                            .header(header::RANGE, format!("bytes={offset}-"));
+                        Some(ResumeDownload {
+                            path: path.clone(),
+                            offset,
+                        })
+                    }
+                    None => None,
+                };
+
+                let mut request = builder.build()?;
+                // Multipart forms are encoded as a stream. Buffer it so the
+                // body can be included in the request record.
+                // File parts are already loaded into memory, so
+                // this doesn't cost much.
+                if is_multipart {
+                    if let Some(body) = request.body_mut().take() {
+                        let body = body.collect().await?.to_bytes();
+                        *request.body_mut() = Some(body.into());
+                    }
+                }
+                // The client would attach stored cookies itself, but do it here
+                // so they show up in the request record. An explicit Cookie
+                // header from the recipe takes precedence.
+                if !request.headers().contains_key(header::COOKIE) {
+                    if let Some(cookies) =
+                        self.cookie_store.cookies(request.url())
+                    {
+                        request.headers_mut().insert(header::COOKIE, cookies);
+                    }
+                }
+                // Ask the server to skip the body if it hasn't changed since
+                // the last response. Not compatible with
+                // resuming, which relies on the Range header
+                // instead
+                let cached_response =
+                    if recipe.conditional && resume_download.is_none() {
+                        attach_validators(
+                            &mut request,
+                            &recipe.id,
+                            template_context,
+                        )?
+                    } else {
+                        None
+                    };
+                if !compression.accept.is_empty()
+                    && !request.headers().contains_key(header::ACCEPT_ENCODING)
+                {
+                    let accept = compression.accept.iter().join(", ");
+                    request.headers_mut().insert(
+                        header::ACCEPT_ENCODING,
+                        HeaderValue::from_str(&accept)
+                            .expect("Encoding names are valid header values"),
+                    );
+                }
+                // Compress before signing, so the signature covers the bytes
+                // that are actually sent. This means the request record
+                // contains the compressed body too.
+                if let Some(encoding) = compression.request_body {
+                    compress_request_body(&mut request, encoding);
+                }
+                // Hooks see the request as it will be sent, except for the
+                // AWS signature. The signature covers every header and the
+                // body, so it has to include the hook's changes.
+                if let Some(command) =
+                    &template_context.collection.hooks.pre_request
+                {
+                    let hook = HookCommand::render(command, template_context)
+                        .await
+                        .context("Error in `pre_request` hook")?;
+                    hook.pre_request(&recipe.id, &mut request)
+                        .await
+                        .context("Error in `pre_request` hook")?;
+                }
+                if let Some(sigv4) = sigv4 {
+                    sigv4
+                        .sign(&mut request, Utc::now())
+                        .context("Error signing request")?;
+                }
+                Ok((client, request, resume_download, digest, cached_response))
             }
+            .await
+            .traced()
+            .map_err(|error| {
+                RequestBuildError::new(
+                    error,
+                    &seed,
+                    template_context.selected_profile.clone(),
+                )
+            })?;
 
-            let request = builder.build()?;
-            Ok((client, request))
-        }
+        let post_response_hook = OptionFuture::from(
+            template_context
+                .collection
+                .hooks
+                .post_response
+                .as_ref()
+                .map(|command| HookCommand::render(command, template_context)),
+        )
         .await
+        .transpose()
+        .context("Error in `post_response` hook")
         .traced()
         .map_err(|error| {
             RequestBuildError::new(
@@ -164,18 +597,80 @@ impl HttpEngine {
             )
         })?;
 
+        let rate_limiter = self.get_rate_limiter(recipe, request.url());
+        let options = Box::new(options.clone());
+        let record = RequestRecord::new(
+            seed,
+            template_context.selected_profile.clone(),
+            &request,
+        );
+        // Hash *before* redaction, so a changed secret isn't a cache hit
+        let fingerprint = cache_ttl.map(|_| record.fingerprint());
+        // Keep sensitive values out of the UI and history
+        let (record, unredacted_record) = {
+            let sensitive_values = template_context
+                .sensitive_values
+                .lock()
+                .expect("Sensitive values lock poisoned");
+            if sensitive_values.is_empty() {
+                (record, None)
+            } else {
+                (
+                    record.redact(&sensitive_values, sensitive_authorization),
+                    Some(record),
+                )
+            }
+        };
+
+        // Throttling the upload means streaming the body. This has to happen
+        // *after* creating the record, because the record copies the raw body
+        if let Some(limit) = throttle.upload {
+            throttle_request_body(&mut request, limit);
+        }
+
         Ok(RequestTicket {
-            record: RequestRecord::new(
-                seed,
-                template_context.selected_profile.clone(),
-                &request,
-            )
-            .into(),
-            client: client.clone(),
+            record: record.into(),
+            unredacted_record,
+            client,
             request,
+            options,
+            resume_download,
+            download_limit: throttle.download,
+            large_body_size: self.large_body_size,
+            decompress: compression.decompress,
+            retry,
+            digest,
+            cookie_store: self
+                .persist_cookies
+                .then(|| Arc::clone(&self.cookie_store)),
+            event_stream_listener: None,
+            cached_response,
+            cache_ttl,
+            fingerprint,
+            rate_limiter,
+            request_slots: self.request_slots.clone(),
+            queue_listener: None,
+            tls_info: Arc::clone(&self.tls_info),
+            post_response_hook,
+            persist: true,
         })
     }
 
+    /// Get the token bucket that limits requests to a URL's host, if there's
+    /// a limit. The recipe's limit takes precedence over the config.
+    fn get_rate_limiter(
+        &self,
+        recipe: &Recipe,
+        url: &Url,
+    ) -> Option<Arc<TokenBucket>> {
+        let host = url.host_str()?;
+        let limit = recipe
+            .rate_limit
+            .as_ref()
+            .or_else(|| self.rate_limits.get(host))?;
+        Some(self.rate_limiters.get(host, limit))
+    }
+
     /// Render *just* the URL of a request, including query parameters
     pub async fn build_url(
         &self,
@@ -199,8 +694,8 @@ impl HttpEngine {
             )?;
 
             // Use RequestBuilder so we can offload the handling of query params
-            let client = self.get_client(&url);
-            let request = client
+            let request = self
+                .client
                 .request(recipe.method.into(), url)
                 .query(&query)
                 .build()?;
@@ -225,36 +720,249 @@ impl HttpEngine {
         seed: RequestSeed,
         template_context: &TemplateContext,
     ) -> Result<Option<Bytes>, RequestBuildError> {
-        let RequestSeed { id, recipe, .. } = &seed;
+        let RequestSeed {
+            id,
+            recipe,
+            options,
+        } = &seed;
         let _ = info_span!("Build request body", request_id = %id, ?recipe)
             .entered();
 
-        let body = recipe
-            .render_body(template_context)
-            .await
-            .traced()
-            .map_err(|error| {
-                RequestBuildError::new(
-                    error,
-                    &seed,
-                    template_context.selected_profile.clone(),
-                )
-            })?;
+        let body = async {
+            match recipe.render_body(options, template_context).await? {
+                Some(RenderedBody::Raw(body)) => Ok(Some(body)),
+                Some(RenderedBody::FormMultipart(_)) => Err(anyhow!(
+                    "Multipart bodies can only be rendered as part of a \
+                    full request"
+                )),
+                Some(RenderedBody::File { mut file, size }) => {
+                    let mut body = Vec::with_capacity(size as usize);
+                    file.read_to_end(&mut body)
+                        .await
+                        .context("Error reading body file")?;
+                    Ok(Some(body.into()))
+                }
+                None => Ok(None),
+            }
+        }
+        .await
+        .traced()
+        .map_err(|error| {
+            RequestBuildError::new(
+                error,
+                &seed,
+                template_context.selected_profile.clone(),
+            )
+        })?;
 
         Ok(body)
     }
 
     /// Get the appropriate client to use for this request. If the request URL's
     /// host is one for which the user wants to ignore TLS certs, use the
-    /// dangerous client.
-    fn get_client(&self, url: &Url) -> &Client {
+    /// dangerous client. If a proxy is configured, globally or in the selected
+    /// profile, use a client that routes through it. If an HTTP version is
+    /// forced, use a client that only speaks that version.
+    fn get_client(
+        &self,
+        url: &Url,
+        http_version: Option<HttpVersion>,
+        connection: Option<&ConnectionOptions>,
+        template_context: &TemplateContext,
+    ) -> anyhow::Result<Client> {
         let host = url.host_str().unwrap_or_default();
-        if self.danger_hostnames.contains(host) {
-            &self.danger_client
-        } else {
-            &self.client
+        let danger = self.danger_hostnames.contains(host);
+
+        // Profile settings take precedence over the config
+        let profile =
+            template_context
+                .selected_profile
+                .as_ref()
+                .and_then(|profile_id| {
+                    template_context.collection.profiles.get(profile_id)
+                });
+        let profile_proxy = profile.and_then(|profile| profile.proxy.as_ref());
+        let proxy = ProxySettings {
+            proxy: match profile_proxy {
+                Some(ProfileProxy::Direct) => None,
+                Some(ProfileProxy::Url(url)) => Some(url.clone()),
+                None => self.proxy.proxy.clone(),
+            },
+            no_proxy: profile
+                .and_then(|profile| profile.no_proxy.clone())
+                .or_else(|| self.proxy.no_proxy.clone()),
+            direct: matches!(profile_proxy, Some(ProfileProxy::Direct)),
+        };
+
+        // Recipe settings take precedence over the config
+        let connection = match connection {
+            Some(connection) => connection.clone().or(&self.connection),
+            None => self.connection.clone(),
+        };
+
+        // The default clients pick up proxies from the environment, so a direct
+        // connection needs its own client
+        if proxy.mode() == ProxyMode::Environment
+            && http_version.is_none()
+            && connection == self.connection
+        {
+            return Ok(if danger {
+                self.danger_client.clone()
+            } else {
+                self.client.clone()
+            });
+        }
+
+        let mut custom_clients = self
+            .custom_clients
+            .lock()
+            .expect("Custom client lock poisoned");
+        let key = ClientSettings {
+            proxy,
+            http_version,
+            connection,
+            danger,
+        };
+        if let Some(client) = custom_clients.get(&key) {
+            return Ok(client.clone());
+        }
+
+        let mut builder = client_builder(
+            &self.cookie_store,
+            danger,
+            http_version,
+            &key.connection,
+        );
+        match key.proxy.mode() {
+            ProxyMode::Environment => {}
+            ProxyMode::Direct => builder = builder.no_proxy(),
+            ProxyMode::Url { url, no_proxy } => {
+                let mut reqwest_proxy = reqwest::Proxy::all(url)
+                    .with_context(|| format!("Invalid proxy URL `{url}`"))?;
+                if let Some(no_proxy) = no_proxy {
+                    reqwest_proxy = reqwest_proxy
+                        .no_proxy(reqwest::NoProxy::from_string(no_proxy));
+                }
+                builder = builder.proxy(reqwest_proxy);
+            }
+        }
+        builder = match http_version {
+            Some(HttpVersion::Http1_1) => builder.http1_only(),
+            Some(HttpVersion::Http2) => builder.http2_prior_knowledge(),
+            None => builder,
+        };
+        let client = builder.build().context("Error building HTTP client")?;
+        custom_clients.insert(key, client.clone());
+        Ok(client)
+    }
+}
+
+/// Attach `If-None-Match`/`If-Modified-Since` headers to a request, based on
+/// the most recent response for its recipe. Headers that the recipe already
+/// sets are left alone. Returns the response that the headers came from, so its
+/// body can be reused if the server responds `304 Not Modified`. Returns `None`
+/// if nothing was attached.
+fn attach_validators(
+    request: &mut Request,
+    recipe_id: &RecipeId,
+    template_context: &TemplateContext,
+) -> anyhow::Result<Option<Arc<ResponseRecord>>> {
+    let Some(exchange) = template_context
+        .database
+        .get_latest_request(
+            template_context.selected_profile.as_ref(),
+            recipe_id,
+        )
+        .context("Error loading previous response")?
+    else {
+        return Ok(None);
+    };
+    let response = exchange.response;
+    if !response.is_revalidatable() {
+        return Ok(None);
+    }
+
+    let headers = request.headers_mut();
+    let mut attached = false;
+    for (validator, condition) in [
+        (header::ETAG, header::IF_NONE_MATCH),
+        (header::LAST_MODIFIED, header::IF_MODIFIED_SINCE),
+    ] {
+        if let Some(value) = response.headers.get(validator) {
+            if !headers.contains_key(&condition) {
+                headers.insert(condition, value.clone());
+                attached = true;
+            }
+        }
+    }
+    Ok(attached.then_some(response))
+}
+
+/// Get a client builder with all the settings shared by every client. The DNS
+/// resolver and TLS config are instrumented to measure request timing.
+fn client_builder(
+    cookie_store: &Arc<CookieStoreMutex>,
+    danger: bool,
+    http_version: Option<HttpVersion>,
+    connection: &ConnectionOptions,
+) -> ClientBuilder {
+    let mut builder = Client::builder()
+        .user_agent(USER_AGENT)
+        .cookie_provider(Arc::clone(cookie_store))
+        .dns_resolver(Arc::new(TimingResolver))
+        .use_preconfigured_tls(tls_config(danger, http_version))
+        .local_address(connection.local_address);
+    if let Some(timeout) = connection.pool_idle_timeout {
+        builder = builder.pool_idle_timeout(timeout);
+    }
+    // An empty pool means every request gets a fresh connection
+    if connection.keep_alive == Some(false) {
+        builder = builder.pool_max_idle_per_host(0);
+    } else if let Some(max) = connection.pool_max_idle {
+        builder = builder.pool_max_idle_per_host(max);
+    }
+    if let Some(interface) = &connection.interface {
+        #[cfg(any(
+            target_os = "android",
+            target_os = "fuchsia",
+            target_os = "linux"
+        ))]
+        {
+            builder = builder.interface(interface);
         }
+        #[cfg(not(any(
+            target_os = "android",
+            target_os = "fuchsia",
+            target_os = "linux"
+        )))]
+        tracing::warn!(
+            interface,
+            "Binding to an interface isn't supported on this platform"
+        );
+    }
+    builder
+}
+
+/// Wait for a free slot under the engine's concurrency limit. The listener is
+/// only notified if there isn't a slot available right away.
+async fn acquire_slot(
+    slots: Arc<Semaphore>,
+    queue_listener: Option<&QueueListener>,
+) -> Option<OwnedSemaphorePermit> {
+    if let Ok(permit) = Arc::clone(&slots).try_acquire_owned() {
+        return Some(permit);
     }
+    let notify = |queued| {
+        if let Some(listener) = queue_listener {
+            listener(queued);
+        }
+    };
+    info!("Waiting for a free request slot");
+    notify(true);
+    // This only fails if the semaphore is closed, which we never do
+    let permit = slots.acquire_owned().await.ok();
+    notify(false);
+    permit
 }
 
 impl RequestTicket {
@@ -276,30 +984,156 @@ impl RequestTicket {
         // Capture the rest of this method in a span
         let _ = info_span!("HTTP request", request_id = %id).entered();
 
-        // This start time will be accurate because the request doesn't launch
-        // until this whole future is awaited
-        let start_time = Utc::now();
-        let result = async {
-            let response = self.client.execute(self.request).await?;
-            // Load the full response and convert it to our format
-            ResponseRecord::from_response(response).await
+        if let Some(exchange) = self.get_cached(database) {
+            return Ok(exchange);
         }
-        .await;
-        let end_time = Utc::now();
+
+        // If concurrent requests are limited, wait for a free slot. The slot
+        // is held until the response is loaded, including any retries
+        let _permit = match &self.request_slots {
+            Some(slots) => {
+                acquire_slot(Arc::clone(slots), self.queue_listener.as_ref())
+                    .await
+            }
+            None => None,
+        };
+
+        let mut request = self.request;
+        let mut retries = Vec::new();
+        let (result, start_time, end_time, timing, tls) = loop {
+            // Grab a copy of the request in case we need to retry. Requests
+            // with streamed bodies can't be copied, so those never retry.
+            let retry_request = self
+                .retry
+                .as_ref()
+                .filter(|policy| {
+                    retries.len() + 1 < policy.max_attempts as usize
+                })
+                .and_then(|_| request.try_clone());
+
+            // Each attempt counts against the host's rate limit. Don't start
+            // the clock until we're clear to send
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.acquire().await;
+            }
+
+            // This start time will be accurate because the request doesn't
+            // launch until this whole future is awaited
+            let start_time = Utc::now();
+            let mut timing = AttemptRecorder::start();
+            let result = async {
+                let response = timing
+                    .send(async {
+                        match &self.digest {
+                            Some(digest) => {
+                                digest.execute(&self.client, request).await
+                            }
+                            None => Ok(self.client.execute(request).await?),
+                        }
+                    })
+                    .await?;
+                // Load the full response and convert it to our format
+                ResponseRecord::from_response(
+                    response,
+                    start_time,
+                    self.resume_download.as_ref(),
+                    self.download_limit,
+                    self.large_body_size,
+                    self.decompress,
+                    self.event_stream_listener.as_ref(),
+                )
+                .await
+            }
+            .await;
+            let end_time = Utc::now();
+            let tls = timing.tls_info();
+            let timing = timing.finish(result.is_ok());
+
+            let retry = self.retry.as_ref().and_then(|policy| {
+                let outcome = policy.check(&result)?;
+                Some((policy, outcome, retry_request?))
+            });
+            let Some((policy, outcome, retry_request)) = retry else {
+                break (result, start_time, end_time, timing, tls);
+            };
+            // Double the wait after each attempt
+            let backoff = policy
+                .backoff
+                .saturating_mul(2u32.saturating_pow(retries.len() as u32));
+            info!(attempt = retries.len() + 1, %outcome, ?backoff, "Retrying");
+            retries.push(RetryAttempt {
+                start_time,
+                end_time,
+                outcome,
+            });
+            time::sleep(backoff).await;
+            request = retry_request;
+        };
 
         match result {
-            Ok(response) => {
+            Ok(mut response) => {
                 info!(status = response.status.as_u16(), "Response");
+                let url = &self.record.url;
+                response.tls = match tls {
+                    Some(tls) => {
+                        self.tls_info.insert(url, tls.clone());
+                        Some(tls)
+                    }
+                    // Connection was reused
+                    None => self.tls_info.get(url),
+                };
+                let mut response = match &self.cached_response {
+                    Some(cached)
+                        if response.status == StatusCode::NOT_MODIFIED =>
+                    {
+                        info!("Not modified, using body from cached response");
+                        response.fill_from_cache(cached)
+                    }
+                    _ => response,
+                };
+                if let Some(hook) = &self.post_response_hook {
+                    if let Err(error) = hook
+                        .post_response(&self.record, &mut response)
+                        .await
+                        .context("Error in `post_response` hook")
+                    {
+                        return Err(RequestError {
+                            request: self.record,
+                            retries,
+                            start_time,
+                            end_time,
+                            error,
+                            partial_response: None,
+                            resume_path: None,
+                            options: self.options,
+                        })
+                        .traced();
+                    }
+                }
                 let exchange = Exchange {
                     id,
                     request: self.record,
                     response: Arc::new(response),
                     start_time,
                     end_time,
+                    retries,
+                    timing,
                 };
 
                 // Error here should *not* kill the request
-                let _ = database.insert_exchange(&exchange);
+                if self.persist {
+                    let _ = database.insert_exchange_with_fingerprint(
+                        &exchange,
+                        self.fingerprint.as_deref(),
+                    );
+                }
+                if let Some(cookie_store) = &self.cookie_store {
+                    let _ = database.set_cookies(
+                        &cookie_store
+                            .lock()
+                            .expect("Cookie store lock poisoned"),
+                    );
+                }
                 Ok(exchange)
             }
 
@@ -307,108 +1141,676 @@ impl RequestTicket {
             // because we need to conditionally move the request
             Err(error) => Err(RequestError {
                 request: self.record,
+                retries,
                 start_time,
                 end_time,
-                error: error.into(),
+                error: error.error,
+                partial_response: error.partial_response.map(Arc::new),
+                resume_path: None,
+                options: self.options,
             })
             .traced(),
         }
     }
 }
 
-impl ResponseRecord {
-    /// Convert [reqwest::Response] type into [ResponseRecord]. This is async
-    /// because the response content is not necessarily loaded when we first get
-    /// the response. Only fails if the response content fails to load.
-    async fn from_response(
-        response: Response,
-    ) -> reqwest::Result<ResponseRecord> {
-        // Copy response metadata out first, because we need to move the
-        // response to resolve content (not sure why...)
-        let status = response.status();
-        let headers = response.headers().clone();
+impl RequestTicket {
+    /// If the recipe has a [cache TTL](Recipe::cache_ttl), look for a recent
+    /// response to an identical request. If there is one, return a new
+    /// exchange with that response, so the request doesn't need to be sent.
+    /// The new exchange isn't stored in history, because it didn't actually
+    /// hit the server.
+    fn get_cached(&self, database: &CollectionDatabase) -> Option<Exchange> {
+        let ttl = self.cache_ttl?;
+        let fingerprint = self.fingerprint.as_deref()?;
+        let request = &self.record;
+        // The recipe, profile, or a sensitive value may have changed since
+        // the cached response, so only match an identical request
+        let previous = database
+            .get_cached_exchange(
+                request.profile_id.as_ref(),
+                &request.recipe_id,
+                fingerprint,
+            )
+            .context("Error loading cached response")
+            .traced()
+            .ok()??;
 
-        // Pre-resolve the content, so we get all the async work done
-        let body = response.bytes().await?.into();
+        if !(previous.end_time + ttl >= Utc::now()
+            && previous.response.is_reusable())
+        {
+            return None;
+        }
 
-        Ok(ResponseRecord {
-            status,
-            headers,
-            body,
+        info!(cached_id = %previous.id, "Using cached response");
+        // We're the only owner of the loaded response
+        let mut response = Arc::into_inner(previous.response)?;
+        response.from_cache = true;
+        let now = Utc::now();
+        Some(Exchange {
+            id: request.id,
+            request: Arc::clone(request),
+            response: response.into(),
+            start_time: now,
+            end_time: now,
+            retries: Vec::new(),
+            timing: None,
         })
     }
 }
 
-/// Render steps for individual pieces of a recipe
-impl Recipe {
-    /// Render base URL, *excluding* query params
-    async fn render_url(
+impl Exchange {
+    /// If the recipe that generated this exchange has `save_response_to` set,
+    /// and the response was successful, render the path and write the
+    /// response body to that file. In addition to the usual template sources,
+    /// the path can use the `status`, `date`, and `timestamp` fields.
+    ///
+    /// Returns the path that the body was written to, or `None` if the recipe
+    /// doesn't want its response saved.
+    pub async fn save_response(
         &self,
         template_context: &TemplateContext,
-    ) -> anyhow::Result<Url> {
-        let url = self
-            .url
-            .render_string(template_context)
-            .await
-            .context("Error rendering URL")?;
-        url.parse::<Url>()
-            .with_context(|| format!("Invalid URL: `{url}`"))
+    ) -> anyhow::Result<Option<PathBuf>> {
+        save_response_body(
+            template_context,
+            &self.request,
+            &self.response,
+            self.start_time,
+        )
+        .await
     }
+}
 
-    /// Render query key=value params
-    async fn render_query(
-        &self,
-        options: &BuildOptions,
+impl RequestError {
+    /// If the response body failed partway through loading, write whatever
+    /// was received to the recipe's `save_response_to` path, so the download
+    /// can be resumed later. The path is stored in [Self::resume_path], and
+    /// also returned.
+    pub async fn save_partial_response(
+        &mut self,
         template_context: &TemplateContext,
-    ) -> anyhow::Result<IndexMap<String, String>> {
-        let iter = self
-            .query
-            .iter()
-            // Filter out disabled params
-            .filter(|(param, _)| {
-                !options.disabled_query_parameters.contains(*param)
-            })
-            .map(|(k, v)| async move {
-                Ok::<_, anyhow::Error>((
-                    k.clone(),
-                    v.render_string(template_context).await.context(
-                        format!("Error rendering query parameter `{k}`"),
-                    )?,
-                ))
-            });
-        Ok(future::try_join_all(iter)
-            .await?
-            .into_iter()
-            .collect::<IndexMap<String, String>>())
+    ) -> anyhow::Result<Option<&Path>> {
+        let Some(response) = &self.partial_response else {
+            return Ok(None);
+        };
+        // Nothing worth resuming
+        if response.body.size().as_u64() == 0 {
+            return Ok(None);
+        }
+
+        self.resume_path = save_response_body(
+            template_context,
+            &self.request,
+            response,
+            self.start_time,
+        )
+        .await?;
+        Ok(self.resume_path.as_deref())
     }
+}
 
-    /// Render all headers specified by the user. This will *not* include
-    /// authentication and other implicit headers
-    async fn render_headers(
-        &self,
-        options: &BuildOptions,
-        template_context: &TemplateContext,
-    ) -> anyhow::Result<HeaderMap> {
-        let iter = self
-            .headers
-            .iter()
-            // Filter out disabled headers
-            .filter(|(header, _)| !options.disabled_headers.contains(*header))
-            .map(move |(header, value_template)| {
-                self.render_header(template_context, header, value_template)
+/// Render the `save_response_to` path for the request's recipe, and write a
+/// response body to it. Error responses are never written, so an error page
+/// can't clobber a good download. See [Exchange::save_response]
+async fn save_response_body(
+    template_context: &TemplateContext,
+    request: &RequestRecord,
+    response: &ResponseRecord,
+    start_time: DateTime<Utc>,
+) -> anyhow::Result<Option<PathBuf>> {
+    let Some(template) = template_context
+        .collection
+        .recipes
+        .get_recipe(&request.recipe_id)
+        .and_then(|recipe| recipe.save_response_to.as_ref())
+    else {
+        return Ok(None);
+    };
+    if !response.status.is_success() {
+        info!(status = %response.status, "Not saving error response body");
+        return Ok(None);
+    }
+
+    let start_time = start_time.with_timezone(&Local);
+    let extra_fields: IndexMap<&str, Template> = [
+        ("status", response.status.as_u16().to_string()),
+        ("date", start_time.format("%Y-%m-%d").to_string()),
+        // No colons, because they aren't allowed in Windows file names
+        (
+            "timestamp",
+            start_time.format("%Y-%m-%dT%H-%M-%S").to_string(),
+        ),
+    ]
+    .into_iter()
+    .map(|(field, value)| (field, Template::raw(value)))
+    .collect();
+    // The extra fields only apply to this path, so substitute them into the
+    // template instead of adding them to the context. User overrides and
+    // profile fields with the same name take precedence.
+    let profile =
+        template_context
+            .selected_profile
+            .as_ref()
+            .and_then(|profile_id| {
+                template_context.collection.profiles.get(profile_id)
             });
-        let headers = future::try_join_all(iter)
-            .await?
-            .into_iter()
-            .collect::<HeaderMap>();
-        Ok(headers)
+    let template = template.substitute(|field| {
+        let is_defined = template_context.overrides.contains_key(field)
+            || profile.is_some_and(|profile| profile.data.contains_key(field));
+        if is_defined {
+            None
+        } else {
+            extra_fields.get(field)
+        }
+    });
+    let path: PathBuf = template
+        .render_string(template_context)
+        .await
+        .context("Error rendering `save_response_to` path")?
+        .into();
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("Error creating directory {parent:?}"))?;
+    }
+    async {
+        let mut file = fs::File::create(&path).await?;
+        response.body.write_to(&mut file).await
     }
+    .await
+    .with_context(|| format!("Error writing response body to {path:?}"))?;
+    info!(?path, "Saved response body");
+    Ok(Some(path))
+}
 
-    /// Render a single key/value header
-    async fn render_header(
-        &self,
-        template_context: &TemplateContext,
-        header: &str,
+/// An error that occurred while sending a request or loading its response. If
+/// the response body failed partway through, we hang onto whatever we got so
+/// the download can be resumed.
+struct ResponseLoadError {
+    error: anyhow::Error,
+    partial_response: Option<ResponseRecord>,
+}
+
+impl From<reqwest::Error> for ResponseLoadError {
+    fn from(error: reqwest::Error) -> Self {
+        Self {
+            error: error.into(),
+            partial_response: None,
+        }
+    }
+}
+
+impl From<anyhow::Error> for ResponseLoadError {
+    fn from(error: anyhow::Error) -> Self {
+        Self {
+            error,
+            partial_response: None,
+        }
+    }
+}
+
+/// Accumulates a response body chunk-by-chunk. Once the body grows past the
+/// size threshold, everything received so far is moved to a temp file, and
+/// the rest of the body is written straight to disk.
+struct BodyLoader {
+    large_body_size: u64,
+    buffer: BytesMut,
+    spool: Option<(SpoolFile, fs::File)>,
+}
+
+impl BodyLoader {
+    fn new(large_body_size: ByteSize) -> Self {
+        Self {
+            large_body_size: large_body_size.as_u64(),
+            buffer: BytesMut::new(),
+            spool: None,
+        }
+    }
+
+    async fn push(&mut self, chunk: &[u8]) -> anyhow::Result<()> {
+        async {
+            if let Some((spool, file)) = &mut self.spool {
+                file.write_all(chunk).await?;
+                spool.size += chunk.len() as u64;
+                return Ok(());
+            }
+
+            self.buffer.extend_from_slice(chunk);
+            if self.buffer.len() as u64 > self.large_body_size {
+                let (mut spool, mut file) = SpoolFile::create().await?;
+                info!(path = ?spool.path, "Spooling large response body");
+                file.write_all(&self.buffer).await?;
+                spool.size = self.buffer.len() as u64;
+                // Hang onto just enough for a preview
+                self.buffer.truncate(
+                    ResponseBody::PREVIEW_SIZE
+                        .as_u64()
+                        .min(self.large_body_size) as usize,
+                );
+                self.spool = Some((spool, file));
+            }
+            Ok::<_, io::Error>(())
+        }
+        .await
+        .context("Error writing response body to temp file")
+    }
+
+    async fn finish(self) -> anyhow::Result<ResponseBody> {
+        let preview = self.buffer.freeze();
+        match self.spool {
+            Some((spool, mut file)) => {
+                file.flush()
+                    .await
+                    .context("Error writing response body to temp file")?;
+                Ok(ResponseBody::spooled(preview, spool))
+            }
+            None => Ok(preview.into()),
+        }
+    }
+}
+
+impl ResponseRecord {
+    /// Convert [reqwest::Response] type into [ResponseRecord]. This is async
+    /// because the response content is not necessarily loaded when we first get
+    /// the response. Only fails if the response content fails to load.
+    ///
+    /// If this is the response to a resumed download, the content from the
+    /// previous attempt is prepended. We only do this on `206 Partial
+    /// Content`, because any other status means the server ignored our `Range`
+    /// header and sent the whole thing.
+    ///
+    /// If the response is an event stream and a listener is given, events are
+    /// parsed and reported to the listener as they arrive. The body is still
+    /// accumulated as normal, so the final record contains the raw stream.
+    ///
+    /// If `decompress` is enabled, the body is decoded according to its
+    /// `Content-Encoding` as it arrives. Partial content is never decoded,
+    /// because a range of an encoded body can't be decoded on its own.
+    async fn from_response(
+        mut response: Response,
+        start_time: DateTime<Utc>,
+        resume_download: Option<&ResumeDownload>,
+        download_limit: Option<ByteSize>,
+        large_body_size: ByteSize,
+        decompress: bool,
+        event_stream_listener: Option<&EventStreamListener>,
+    ) -> Result<ResponseRecord, ResponseLoadError> {
+        // Copy response metadata out first, because we need to move the
+        // response to resolve content (not sure why...)
+        let version = response.version();
+        let status = response.status();
+        let headers = response.headers().clone();
+
+        // Appending content that doesn't pick up where the partial download
+        // left off would corrupt the body
+        let resume_download =
+            resume_download.filter(|_| status == StatusCode::PARTIAL_CONTENT);
+        if let Some(resume_download) = resume_download {
+            resume_download.check_content_range(&headers)?;
+        }
+
+        let mut decoder = if decompress && status != StatusCode::PARTIAL_CONTENT
+        {
+            Decoder::from_headers(&headers)
+        } else {
+            None
+        };
+        let is_encoded = decoder.is_some();
+        let mut encoded_size = 0u64;
+
+        let mut event_stream = event_stream_listener
+            .filter(|_| is_event_stream(&headers))
+            .map(|listener| {
+                let stream = Arc::new(EventStream::new(
+                    start_time,
+                    version,
+                    status,
+                    headers.clone(),
+                ));
+                info!("Receiving event stream");
+                listener(&stream);
+                (listener, stream, EventStreamParser::default())
+            });
+
+        // Pre-resolve the content, so we get all the async work done. Load
+        // chunk-by-chunk so we can hang onto the partial body if it fails, so
+        // we can throttle the download if requested, and so large bodies can
+        // be spooled to disk
+        let mut body = BodyLoader::new(large_body_size);
+        let mut limiter = download_limit.map(RateLimiter::new);
+        let result = async {
+            if let Some(resume_download) = resume_download {
+                resume_download.load(&mut body).await?;
+            }
+            while let Some(chunk) = response.chunk().await? {
+                encoded_size += chunk.len() as u64;
+                if let Some(limiter) = &mut limiter {
+                    limiter.consume(chunk.len()).await;
+                }
+                let chunk = match &mut decoder {
+                    Some(decoder) => decoder
+                        .decode(&chunk)
+                        .context("Error decompressing response body")?
+                        .into(),
+                    None => chunk,
+                };
+                body.push(&chunk).await?;
+                if let Some((listener, stream, parser)) = &mut event_stream {
+                    stream.push(chunk.len(), parser.push(&chunk));
+                    listener(stream);
+                }
+            }
+            if let Some(decoder) = decoder.take() {
+                let rest = decoder
+                    .finish()
+                    .context("Error decompressing response body")?;
+                body.push(&rest).await?;
+            }
+            Ok::<_, anyhow::Error>(())
+        }
+        .await;
+        let encoded_size = is_encoded.then(|| ByteSize::b(encoded_size));
+
+        match result {
+            Ok(()) => Ok(ResponseRecord {
+                version,
+                status,
+                headers,
+                encoded_size,
+                from_cache: false,
+                tls: None,
+                body: body.finish().await.map_err(|error| {
+                    ResponseLoadError {
+                        error,
+                        partial_response: None,
+                    }
+                })?,
+            }),
+            // A decoded body can't be resumed, because the Range header
+            // refers to the encoded content
+            Err(error) => Err(ResponseLoadError {
+                error,
+                partial_response: body
+                    .finish()
+                    .await
+                    .ok()
+                    .filter(|_| !is_encoded)
+                    .map(|body| ResponseRecord {
+                        version,
+                        status,
+                        headers,
+                        body,
+                        encoded_size: None,
+                        from_cache: false,
+                        tls: None,
+                    }),
+            }),
+        }
+    }
+}
+
+impl ResumeDownload {
+    /// Make sure a `206 Partial Content` response starts at the offset we
+    /// requested
+    fn check_content_range(&self, headers: &HeaderMap) -> anyhow::Result<()> {
+        let content_range = headers
+            .get(header::CONTENT_RANGE)
+            .ok_or_else(|| anyhow!("Partial response has no Content-Range"))?;
+        // Format is `bytes <start>-<end>/<size>`
+        let start = content_range
+            .to_str()
+            .ok()
+            .and_then(|value| value.strip_prefix("bytes "))
+            .and_then(|range| range.split_once('-'))
+            .and_then(|(start, _)| start.trim().parse::<u64>().ok())
+            .ok_or_else(|| {
+                anyhow!("Invalid Content-Range {content_range:?}")
+            })?;
+        if start != self.offset {
+            bail!(
+                "Partial response starts at byte {start}, but {} bytes were \
+                already downloaded",
+                self.offset
+            );
+        }
+        Ok(())
+    }
+
+    /// Stream the partial download into the body, chunk-by-chunk
+    async fn load(&self, body: &mut BodyLoader) -> anyhow::Result<()> {
+        let context =
+            || format!("Error reading partial download {:?}", self.path);
+        let mut file = fs::File::open(&self.path)
+            .await
+            .with_context(context)?
+            .take(self.offset);
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = file.read(&mut buffer).await.with_context(context)?;
+            if read == 0 {
+                return Ok(());
+            }
+            body.push(&buffer[..read]).await?;
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Should a request attempt be retried? If so, return why
+    fn check(
+        &self,
+        result: &Result<ResponseRecord, ResponseLoadError>,
+    ) -> Option<RetryOutcome> {
+        match result {
+            Ok(response) => self
+                .on_status
+                .contains(&response.status.as_u16())
+                .then_some(RetryOutcome::Status(response.status)),
+            // Only retry if we never got a response. If the body failed
+            // partway through, the user may want to resume it instead.
+            Err(error)
+                if self.on_connection_error
+                    && error.partial_response.is_none()
+                    && error.error.is::<reqwest::Error>() =>
+            {
+                Some(RetryOutcome::Error(format!("{:#}", error.error)))
+            }
+            Err(_) => None,
+        }
+    }
+}
+
+impl DigestCredentials {
+    /// Send a request. If the server responds with a digest challenge, answer
+    /// it and send the request again. Requests with streamed bodies can't be
+    /// copied, so for those the challenge response is returned as-is.
+    async fn execute(
+        &self,
+        client: &Client,
+        request: Request,
+    ) -> anyhow::Result<Response> {
+        let retry_request = request.try_clone();
+        let response = client.execute(request).await?;
+        let Some(mut request) = retry_request
+            .filter(|_| response.status() == StatusCode::UNAUTHORIZED)
+        else {
+            return Ok(response);
+        };
+        // There may be multiple challenges for different schemes
+        let Some(challenge) = response
+            .headers()
+            .get_all(header::WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .find(|value| {
+                value
+                    .trim_start()
+                    .get(..6)
+                    .is_some_and(|scheme| scheme.eq_ignore_ascii_case("digest"))
+            })
+        else {
+            return Ok(response);
+        };
+
+        let mut challenge = digest_auth::parse(challenge)
+            .context("Error parsing digest authentication challenge")?;
+        let context = AuthContext::new_with_method(
+            self.username.as_str(),
+            self.password.as_deref().unwrap_or_default(),
+            &request.url()[Position::BeforePath..],
+            request.body().and_then(Body::as_bytes),
+            HttpMethod(request.method().as_str().into()),
+        );
+        let authorization = challenge
+            .respond(&context)
+            .context("Error answering digest authentication challenge")?
+            .to_header_string();
+        request.headers_mut().insert(
+            header::AUTHORIZATION,
+            authorization
+                .try_into()
+                .context("Error encoding digest authorization header")?,
+        );
+        Ok(client.execute(request).await?)
+    }
+}
+
+/// Join a base URL and a relative URL. This uses plain concatenation rather
+/// than [Url::join], so a path prefix in the base URL (e.g. `/api`) is
+/// retained
+fn join_url(base_url: &str, url: &str) -> String {
+    format!(
+        "{}/{}",
+        base_url.trim_end_matches('/'),
+        url.trim_start_matches('/')
+    )
+}
+
+/// Render steps for individual pieces of a recipe
+impl Recipe {
+    /// Render base URL, *excluding* query params. If the recipe URL is
+    /// relative (e.g. `/users`), it's appended to the `base_url` inherited
+    /// from the collection defaults. If it's *still* relative, it's appended
+    /// to the selected profile's `base_url`.
+    async fn render_url(
+        &self,
+        template_context: &TemplateContext,
+    ) -> anyhow::Result<Url> {
+        let mut url = self
+            .url
+            .render_string(template_context)
+            .await
+            .context("Error rendering URL")?;
+        if let Some(base_url) = self.base_url.as_ref().filter(|_| {
+            matches!(
+                url.parse::<Url>(),
+                Err(url::ParseError::RelativeUrlWithoutBase)
+            )
+        }) {
+            let base_url = base_url
+                .render_string(template_context)
+                .await
+                .context("Error rendering default `base_url`")?;
+            url = join_url(&base_url, &url);
+        }
+        match url.parse::<Url>() {
+            Ok(url) => Ok(url),
+            Err(url::ParseError::RelativeUrlWithoutBase) => {
+                let profile = template_context
+                    .selected_profile
+                    .as_ref()
+                    .and_then(|profile_id| {
+                        template_context.collection.profiles.get(profile_id)
+                    });
+                let Some((profile, base_url)) = profile.and_then(|profile| {
+                    Some((profile, profile.base_url.as_ref()?))
+                }) else {
+                    bail!(
+                        "Relative URL `{url}` requires a `base_url` in the \
+                        selected profile"
+                    );
+                };
+                let base_url = base_url
+                    .render_string(template_context)
+                    .await
+                    .with_context(|| {
+                    format!(
+                        "Error rendering `base_url` for profile `{}`",
+                        profile.id
+                    )
+                })?;
+                // Validate the base on its own first, so the error points at
+                // the profile rather than the recipe
+                base_url.parse::<Url>().with_context(|| {
+                    format!(
+                        "Invalid `base_url` for profile `{}`: `{base_url}`",
+                        profile.id
+                    )
+                })?;
+                let url = join_url(&base_url, &url);
+                url.parse::<Url>()
+                    .with_context(|| format!("Invalid URL: `{url}`"))
+            }
+            Err(error) => Err(anyhow::Error::from(error)
+                .context(format!("Invalid URL: `{url}`"))),
+        }
+    }
+
+    /// Render query key=value params
+    async fn render_query(
+        &self,
+        options: &BuildOptions,
+        template_context: &TemplateContext,
+    ) -> anyhow::Result<IndexMap<String, String>> {
+        let iter = self
+            .query
+            .iter()
+            // Filter out disabled params
+            .filter(|(param, _)| {
+                !options.disabled_query_parameters.contains(*param)
+            })
+            .map(|(k, v)| async move {
+                Ok::<_, anyhow::Error>((
+                    k.clone(),
+                    v.render_string(template_context).await.context(
+                        format!("Error rendering query parameter `{k}`"),
+                    )?,
+                ))
+            });
+        Ok(future::try_join_all(iter)
+            .await?
+            .into_iter()
+            .collect::<IndexMap<String, String>>())
+    }
+
+    /// Render all headers specified by the user. This will *not* include
+    /// authentication and other implicit headers
+    async fn render_headers(
+        &self,
+        options: &BuildOptions,
+        template_context: &TemplateContext,
+    ) -> anyhow::Result<HeaderMap> {
+        let iter = self
+            .headers
+            .iter()
+            // Filter out disabled headers
+            .filter(|(header, _)| !options.disabled_headers.contains(*header))
+            .map(move |(header, value_template)| {
+                self.render_header(template_context, header, value_template)
+            });
+        let headers = future::try_join_all(iter)
+            .await?
+            .into_iter()
+            .collect::<HeaderMap>();
+        Ok(headers)
+    }
+
+    /// Render a single key/value header
+    async fn render_header(
+        &self,
+        template_context: &TemplateContext,
+        header: &str,
         value_template: &Template,
     ) -> anyhow::Result<(HeaderName, HeaderValue)> {
         let mut value = value_template
@@ -443,22 +1845,12 @@ impl Recipe {
     ) -> anyhow::Result<Option<Authentication<String>>> {
         match &self.authentication {
             Some(Authentication::Basic { username, password }) => {
-                let (username, password) = try_join!(
-                    async {
-                        username
-                            .render_string(template_context)
-                            .await
-                            .context("Error rendering username")
-                    },
-                    async {
-                        OptionFuture::from(password.as_ref().map(|password| {
-                            password.render_string(template_context)
-                        }))
-                        .await
-                        .transpose()
-                        .context("Error rendering password")
-                    },
-                )?;
+                let (username, password) = render_credentials(
+                    username,
+                    password.as_ref(),
+                    template_context,
+                )
+                .await?;
                 Ok(Some(Authentication::Basic { username, password }))
             }
 
@@ -469,27 +1861,272 @@ impl Recipe {
                     .context("Error rendering bearer token")?;
                 Ok(Some(Authentication::Bearer(token)))
             }
+
+            Some(Authentication::Digest { username, password }) => {
+                let (username, password) = render_credentials(
+                    username,
+                    password.as_ref(),
+                    template_context,
+                )
+                .await?;
+                Ok(Some(Authentication::Digest { username, password }))
+            }
+
+            Some(Authentication::OAuth2(oauth2)) => Ok(Some(
+                Authentication::OAuth2(oauth2.render(template_context).await?),
+            )),
+
+            Some(Authentication::AwsSigV4 {
+                access_key_id,
+                secret_access_key,
+                session_token,
+                region,
+                service,
+            }) => {
+                let render = |template, field| {
+                    render_field(template, field, template_context)
+                };
+                let (
+                    access_key_id,
+                    secret_access_key,
+                    session_token,
+                    region,
+                    service,
+                ) = try_join!(
+                    render(access_key_id, "access_key_id"),
+                    render(secret_access_key, "secret_access_key"),
+                    async {
+                        OptionFuture::from(
+                            session_token
+                                .as_ref()
+                                .map(|token| render(token, "session_token")),
+                        )
+                        .await
+                        .transpose()
+                    },
+                    render(region, "region"),
+                    render(service, "service"),
+                )?;
+                Ok(Some(Authentication::AwsSigV4 {
+                    access_key_id,
+                    secret_access_key,
+                    session_token,
+                    region,
+                    service,
+                }))
+            }
             None => Ok(None),
         }
     }
 
-    /// Render request body
+    /// Render request body. A body override from the build options takes
+    /// precedence over the recipe's body
     async fn render_body(
+        &self,
+        options: &BuildOptions,
+        template_context: &TemplateContext,
+    ) -> anyhow::Result<Option<RenderedBody>> {
+        let body = match &options.body {
+            Some(body) => Some(RecipeBody::Raw(body.clone())),
+            None => self.body.clone(),
+        };
+        match &body {
+            Some(RecipeBody::Raw(body)) => {
+                let mut rendered = body
+                    .render(template_context)
+                    .await
+                    .context("Error rendering body")?;
+                if self.graphql {
+                    rendered = graphql::request_body(&rendered);
+                }
+                Ok(Some(RenderedBody::Raw(rendered.into())))
+            }
+            Some(RecipeBody::FormMultipart(fields)) => {
+                let parts = future::try_join_all(fields.iter().map(
+                    |(name, field)| async move {
+                        let part = field
+                            .render(template_context)
+                            .await
+                            .with_context(|| {
+                                format!("Error rendering form field `{name}`")
+                            })?;
+                        Ok::<_, anyhow::Error>((name.clone(), part))
+                    },
+                ))
+                .await?;
+                let form = parts
+                    .into_iter()
+                    .fold(Form::new(), |form, (name, part)| {
+                        form.part(name, part)
+                    });
+                Ok(Some(RenderedBody::FormMultipart(form)))
+            }
+            Some(RecipeBody::File(path)) => {
+                // Relative to the collection file that defined the recipe
+                let path = self.dir.join(
+                    path.render_string(template_context)
+                        .await
+                        .context("Error rendering body file path")?,
+                );
+                // Open the file now so a bad path fails the build, but don't
+                // read it until the request is sent
+                let file = fs::File::open(&path)
+                    .await
+                    .with_context(|| format!("Error opening file {path:?}"))?;
+                let size = file
+                    .metadata()
+                    .await
+                    .with_context(|| format!("Error reading file {path:?}"))?
+                    .len();
+                Ok(Some(RenderedBody::File { file, size }))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl OAuth2 {
+    /// Render all templates in the settings
+    async fn render(
         &self,
         template_context: &TemplateContext,
-    ) -> anyhow::Result<Option<Bytes>> {
-        if let Some(body) = &self.body {
-            let rendered = body
-                .render(template_context)
+    ) -> anyhow::Result<OAuth2<String>> {
+        let render =
+            |template, field| render_field(template, field, template_context);
+        let (token_url, authorization_url, client_id, client_secret) = try_join!(
+            render(&self.token_url, "token_url"),
+            async {
+                OptionFuture::from(
+                    self.authorization_url
+                        .as_ref()
+                        .map(|url| render(url, "authorization_url")),
+                )
                 .await
-                .context("Error rendering body")?;
-            Ok(Some(rendered.into()))
-        } else {
-            Ok(None)
+                .transpose()
+            },
+            render(&self.client_id, "client_id"),
+            async {
+                OptionFuture::from(
+                    self.client_secret
+                        .as_ref()
+                        .map(|secret| render(secret, "client_secret")),
+                )
+                .await
+                .transpose()
+            },
+        )?;
+        Ok(OAuth2 {
+            grant_type: self.grant_type,
+            token_url,
+            authorization_url,
+            client_id,
+            client_secret,
+            scopes: self.scopes.clone(),
+            redirect_port: self.redirect_port,
+        })
+    }
+
+    /// Get the cached token for these settings, without fetching or
+    /// refreshing anything. Return `None` if no token has been fetched yet.
+    pub async fn cached_token(
+        &self,
+        template_context: &TemplateContext,
+    ) -> anyhow::Result<Option<OAuth2Token>> {
+        let key = self.render(template_context).await?.cache_key();
+        template_context.database.get_oauth2_token(&key)
+    }
+}
+
+/// Render a single authentication field, with the field name in the error
+async fn render_field(
+    template: &Template,
+    field: &str,
+    template_context: &TemplateContext,
+) -> anyhow::Result<String> {
+    template
+        .render_string(template_context)
+        .await
+        .with_context(|| format!("Error rendering `{field}`"))
+}
+
+/// Render the username and password for basic or digest authentication
+async fn render_credentials(
+    username: &Template,
+    password: Option<&Template>,
+    template_context: &TemplateContext,
+) -> anyhow::Result<(String, Option<String>)> {
+    try_join!(
+        async {
+            username
+                .render_string(template_context)
+                .await
+                .context("Error rendering username")
+        },
+        async {
+            OptionFuture::from(
+                password
+                    .map(|password| password.render_string(template_context)),
+            )
+            .await
+            .transpose()
+            .context("Error rendering password")
+        },
+    )
+}
+
+impl MultipartField {
+    /// Render a form field into a multipart part. For files, this loads the
+    /// file content into memory
+    async fn render(
+        &self,
+        template_context: &TemplateContext,
+    ) -> anyhow::Result<Part> {
+        match self {
+            Self::Text(template) => {
+                Ok(Part::bytes(template.render(template_context).await?))
+            }
+            Self::File(file) => {
+                let path = PathBuf::from(
+                    file.path
+                        .render_string(template_context)
+                        .await
+                        .context("Error rendering path")?,
+                );
+                let content = fs::read(&path)
+                    .await
+                    .with_context(|| format!("Error reading file {path:?}"))?;
+                let file_name = file.filename.clone().or_else(|| {
+                    Some(path.file_name()?.to_string_lossy().into_owned())
+                });
+                let content_type = file
+                    .content_type
+                    .as_deref()
+                    .unwrap_or(mime::APPLICATION_OCTET_STREAM.as_ref());
+
+                let mut part =
+                    Part::bytes(content).mime_str(content_type).with_context(
+                        || format!("Invalid content type `{content_type}`"),
+                    )?;
+                if let Some(file_name) = file_name {
+                    part = part.file_name(file_name);
+                }
+                Ok(part)
+            }
         }
     }
 }
 
+/// A request body that has been rendered, but not yet attached to a request
+enum RenderedBody {
+    Raw(Bytes),
+    FormMultipart(Form),
+    /// A file that's been opened, but not read yet
+    File {
+        file: fs::File,
+        size: u64,
+    },
+}
+
 impl From<Method> for reqwest::Method {
     fn from(method: Method) -> Self {
         match method {
@@ -531,14 +2168,17 @@ fn trim_bytes(bytes: &mut Vec<u8>, f: impl Fn(u8) -> bool) {
 mod tests {
     use super::*;
     use crate::{
-        collection::{self, Authentication, Collection, Profile},
-        test_util::{header_map, Factory},
+        collection::{
+            self, Authentication, Collection, Compression, ConnectionOptions,
+            ContentEncoding, Hooks, MultipartFile, Profile, Throttle,
+        },
+        test_util::{assert_err, header_map, temp_dir, Factory, TempDir},
     };
     use indexmap::indexmap;
     use pretty_assertions::assert_eq;
-    use reqwest::{Method, StatusCode};
+    use reqwest::{Method, StatusCode, Version};
     use rstest::{fixture, rstest};
-    use std::collections::HashMap;
+    use std::{collections::HashMap, num::NonZeroUsize, time::Duration};
 
     #[fixture]
     fn http_engine() -> HttpEngine {
@@ -618,13 +2258,85 @@ mod tests {
         );
     }
 
-    /// Test building just a URL. Should include query params, but headers/body
-    /// should *not* be built
+    /// Values from sensitive profile fields are redacted from the stored
+    /// record, but the sent request still gets the real value
     #[rstest]
     #[tokio::test]
-    async fn test_build_url(
+    async fn test_build_request_sensitive(
         http_engine: HttpEngine,
-        template_context: TemplateContext,
+        mut template_context: TemplateContext,
+    ) {
+        for profile in template_context.collection.profiles.values_mut() {
+            profile.sensitive = vec!["token".into()];
+        }
+        let recipe = Recipe {
+            url: "{{host}}/users?token={{token}}".into(),
+            headers: indexmap! {
+                "Authorization".into() => "Bearer {{token}}".into(),
+            },
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+
+        assert_eq!(
+            ticket.record().url.as_str(),
+            "http://localhost/users?token=%3Csensitive%3E"
+        );
+        assert_eq!(
+            ticket.record().headers,
+            header_map(indexmap! {"authorization" => "Bearer <sensitive>"})
+        );
+        assert_eq!(
+            ticket.unredacted_record().headers,
+            header_map(indexmap! {"authorization" => "Bearer hunter2"})
+        );
+        assert_eq!(
+            ticket.request.url().as_str(),
+            "http://localhost/users?token=hunter2"
+        );
+    }
+
+    /// Basic auth credentials are base64-encoded, so a sensitive password
+    /// can't be matched literally. The whole credential gets redacted instead.
+    #[rstest]
+    #[tokio::test]
+    async fn test_build_request_sensitive_basic_auth(
+        http_engine: HttpEngine,
+        mut template_context: TemplateContext,
+    ) {
+        for profile in template_context.collection.profiles.values_mut() {
+            profile.sensitive = vec!["token".into()];
+        }
+        let recipe = Recipe {
+            authentication: Some(Authentication::Basic {
+                username: "user".into(),
+                password: Some("{{token}}".into()),
+            }),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+
+        assert_eq!(
+            ticket.record().headers,
+            header_map(indexmap! {"authorization" => "Basic <sensitive>"})
+        );
+        assert_eq!(
+            ticket.unredacted_record().headers,
+            header_map(indexmap! {"authorization" => "Basic dXNlcjpodW50ZXIy"})
+        );
+    }
+
+    /// Test building just a URL. Should include query params, but headers/body
+    /// should *not* be built
+    #[rstest]
+    #[tokio::test]
+    async fn test_build_url(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
     ) {
         let recipe = Recipe {
             url: "{{host}}/users/{{user_id}}".into(),
@@ -647,6 +2359,104 @@ mod tests {
         );
     }
 
+    /// Relative recipe URLs are resolved against the profile's base URL
+    #[rstest]
+    #[case::absolute(
+        Some("http://base"),
+        "http://localhost/users",
+        Ok("http://localhost/users")
+    )]
+    #[case::relative(Some("http://base"), "/users", Ok("http://base/users"))]
+    #[case::path_prefix(
+        Some("http://base:3000/api/"),
+        "/users",
+        Ok("http://base:3000/api/users")
+    )]
+    #[case::no_leading_slash(
+        Some("http://base"),
+        "users",
+        Ok("http://base/users")
+    )]
+    #[case::template(
+        Some("{{host}}/api"),
+        "/users/{{user_id}}",
+        Ok("http://localhost/api/users/1")
+    )]
+    #[case::no_base_url(
+        None,
+        "/users",
+        Err("Relative URL `/users` requires a `base_url` in the selected profile")
+    )]
+    #[case::invalid_base_url(
+        Some("base"),
+        "/users",
+        Err("Invalid `base_url` for profile `profile1`: `base`")
+    )]
+    #[tokio::test]
+    async fn test_build_url_base_url(
+        http_engine: HttpEngine,
+        mut template_context: TemplateContext,
+        #[case] base_url: Option<&str>,
+        #[case] url: &str,
+        #[case] expected: Result<&str, &str>,
+    ) {
+        let profile_id = template_context.selected_profile.clone().unwrap();
+        template_context.collection.profiles[&profile_id].base_url =
+            base_url.map(|base_url| base_url.parse().unwrap());
+        let recipe = Recipe {
+            url: url.parse().unwrap(),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let result = http_engine.build_url(seed, &template_context).await;
+        match expected {
+            Ok(expected) => assert_eq!(result.unwrap().as_str(), expected),
+            Err(expected) => {
+                assert_err!(result, expected)
+            }
+        }
+    }
+
+    /// Test a `base_url` inherited from collection defaults, which is applied
+    /// before the profile's `base_url`
+    #[rstest]
+    #[case::absolute_url(
+        "http://default",
+        "http://other/users",
+        "http://other/users"
+    )]
+    #[case::absolute_default(
+        "http://default/",
+        "/users",
+        "http://default/users"
+    )]
+    #[case::relative_default("/v2", "/users", "http://profile/v2/users")]
+    #[tokio::test]
+    async fn test_build_url_default_base_url(
+        http_engine: HttpEngine,
+        mut template_context: TemplateContext,
+        #[case] default_base_url: &str,
+        #[case] url: &str,
+        #[case] expected: &str,
+    ) {
+        let profile_id = template_context.selected_profile.clone().unwrap();
+        template_context.collection.profiles[&profile_id].base_url =
+            Some("http://profile".into());
+        let recipe = Recipe {
+            url: url.parse().unwrap(),
+            base_url: Some(default_base_url.parse().unwrap()),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let url = http_engine
+            .build_url(seed, &template_context)
+            .await
+            .unwrap();
+        assert_eq!(url.as_str(), expected);
+    }
+
     /// Test building just a body. URL/query/headers should *not* be built.
     #[rstest]
     #[tokio::test]
@@ -668,6 +2478,120 @@ mod tests {
         assert_eq!(body.as_deref(), Some(br#"{"group_id":"3"}"#.as_slice()));
     }
 
+    /// A body override in the build options should replace the recipe's
+    /// body, and still be rendered as a template
+    #[rstest]
+    #[case::no_body(None)]
+    #[case::raw_body(Some(r#"{"group_id":"{{group_id}}"}"#))]
+    #[tokio::test]
+    async fn test_build_body_override(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+        #[case] recipe_body: Option<&str>,
+    ) {
+        let recipe = Recipe {
+            body: recipe_body.map(RecipeBody::from),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(
+            recipe,
+            BuildOptions {
+                body: Some("edited {{group_id}}".into()),
+                ..Default::default()
+            },
+        );
+        let body = http_engine
+            .build_body(seed, &template_context)
+            .await
+            .unwrap();
+
+        assert_eq!(body.as_deref(), Some(b"edited 3".as_slice()));
+    }
+
+    /// A GraphQL recipe's body is rendered, then wrapped in a JSON request.
+    /// The JSON content type is added unless the recipe sets its own
+    #[rstest]
+    #[case::default_content_type(None, "application/json")]
+    #[case::custom_content_type(
+        Some("application/graphql+json"),
+        "application/graphql+json"
+    )]
+    #[tokio::test]
+    async fn test_build_graphql(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+        #[case] content_type: Option<&str>,
+        #[case] expected_content_type: &str,
+    ) {
+        let recipe = Recipe {
+            method: collection::Method::Post,
+            body: Some("{ group(id: \"{{group_id}}\") { name } }".into()),
+            headers: content_type
+                .map(|content_type| {
+                    ("Content-Type".into(), content_type.into())
+                })
+                .into_iter()
+                .collect(),
+            graphql: true,
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let record = ticket.record();
+        assert_eq!(
+            record.body.as_ref().and_then(ResponseBody::text),
+            Some(r#"{"query":"{ group(id: \"3\") { name } }"}"#)
+        );
+        assert_eq!(
+            record.headers.get(header::CONTENT_TYPE).unwrap(),
+            expected_content_type
+        );
+    }
+
+    /// With a concurrency limit, a request waits for a free slot. The queue
+    /// listener is told when it starts and stops waiting
+    #[rstest]
+    #[tokio::test]
+    async fn test_concurrency_limit(template_context: TemplateContext) {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        server.mock("GET", "/get").create_async().await;
+
+        let http_engine = HttpEngine::new(&Config {
+            max_concurrent_requests: NonZeroUsize::new(1),
+            ..Config::default()
+        });
+        let recipe = Recipe {
+            url: format!("{url}/get").as_str().into(),
+            ..Recipe::factory(())
+        };
+        let queued = Arc::new(Mutex::new(Vec::new()));
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine
+            .build(seed, &template_context)
+            .await
+            .unwrap()
+            .on_queued({
+                let queued = Arc::clone(&queued);
+                move |is_queued| queued.lock().unwrap().push(is_queued)
+            });
+
+        // Take the only slot, so the request has to wait
+        let slots = http_engine.request_slots.clone().unwrap();
+        let permit = slots.acquire_owned().await.unwrap();
+        let database = template_context.database.clone();
+        let handle = tokio::spawn(async move { ticket.send(&database).await });
+        time::sleep(Duration::from_millis(50)).await;
+        assert!(!handle.is_finished());
+        assert_eq!(*queued.lock().unwrap(), vec![true]);
+
+        drop(permit);
+        handle.await.unwrap().unwrap();
+        assert_eq!(*queued.lock().unwrap(), vec![true, false]);
+    }
+
     /// Test launching a built request
     #[rstest]
     #[tokio::test]
@@ -707,163 +2631,1972 @@ mod tests {
         assert_eq!(
             *exchange.response,
             ResponseRecord {
+                version: Version::HTTP_11,
                 status: StatusCode::OK,
                 headers: header_map([
                     ("connection", "close"),
                     ("content-length", "6"),
                     ("date", date_header),
                 ]),
-                body: ResponseBody::new(b"hello!".as_slice().into())
+                body: ResponseBody::new(b"hello!".as_slice().into()),
+                encoded_size: None,
+                from_cache: false,
+                tls: None,
             }
         );
 
         mock.assert();
     }
 
-    /// Test building requests with various authentication methods
+    /// A request sent without history isn't stored in the DB
     #[rstest]
-    #[case::basic(
-        Authentication::Basic {
-            username: "{{username}}".into(),
-            password: Some("{{password}}".into()),
-        },
-        "Basic dXNlcjpodW50ZXIy"
-    )]
-    #[case::basic_no_password(
-        Authentication::Basic {
-            username: "{{username}}".into(),
-            password: None,
-        },
-        "Basic dXNlcjo="
-    )]
-    #[case::bearer(Authentication::Bearer("{{token}}".into()), "Bearer token!")]
     #[tokio::test]
-    async fn test_authentication(
+    async fn test_send_without_history(
         http_engine: HttpEngine,
-        #[case] authentication: Authentication,
-        #[case] expected_header: &str,
+        template_context: TemplateContext,
     ) {
-        let profile_data = indexmap! {
-            "username".into() => "user".into(),
-            "password".into() => "hunter2".into(),
-            "token".into() => "token!".into(),
-        };
-        let profile = Profile {
-            data: profile_data,
-            ..Profile::factory(())
-        };
-        let profile_id = profile.id.clone();
-        let template_context = TemplateContext {
-            collection: Collection {
-                profiles: indexmap! {profile_id.clone() => profile},
-                ..Collection::factory(())
-            },
-            selected_profile: Some(profile_id.clone()),
-            ..TemplateContext::factory(())
-        };
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        server.mock("GET", "/get").create_async().await;
+
         let recipe = Recipe {
-            authentication: Some(authentication),
+            url: format!("{url}/get").as_str().into(),
             ..Recipe::factory(())
         };
         let recipe_id = recipe.id.clone();
-
         let seed = RequestSeed::new(recipe, BuildOptions::default());
         let ticket = http_engine.build(seed, &template_context).await.unwrap();
-
-        let expected_headers: HashMap<String, String> =
-            [("authorization", expected_header)]
-                .into_iter()
-                .map(|(k, v)| (k.to_string(), v.to_string()))
-                .collect();
-
-        assert_eq!(
-            *ticket.record,
-            RequestRecord {
-                id: ticket.record.id,
-                profile_id: Some(profile_id),
-                recipe_id,
-                method: Method::GET,
-                url: "http://localhost/url".parse().unwrap(),
-                headers: (&expected_headers).try_into().unwrap(),
-                body: None,
-            }
-        );
+        ticket
+            .without_history()
+            .send(&template_context.database)
+            .await
+            .unwrap();
+        assert!(template_context
+            .database
+            .get_latest_request(None, &recipe_id)
+            .unwrap()
+            .is_none());
     }
 
+    /// Failed attempts should be retried according to the policy, and each
+    /// failure recorded on the exchange
     #[rstest]
+    #[case::no_policy(None, 503, &[])]
+    #[case::success(Some(vec![503]), 200, &[503])]
+    #[case::exhausted(Some(vec![503]), 503, &[503, 503])]
+    #[case::status_not_retried(Some(vec![502]), 503, &[])]
     #[tokio::test]
-    async fn test_disable_headers_and_query_params(
+    async fn test_retry(
         http_engine: HttpEngine,
         template_context: TemplateContext,
+        #[case] on_status: Option<Vec<u16>>,
+        #[case] expected_status: u16,
+        #[case] expected_retries: &[u16],
     ) {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        // The first attempt always fails. Later attempts hit the second mock
+        let fail_mock = server
+            .mock("GET", "/flaky")
+            .with_status(503)
+            .expect(1)
+            .create_async()
+            .await;
+        let second_mock = server
+            .mock("GET", "/flaky")
+            .with_status(if expected_status == 200 { 200 } else { 503 })
+            .expect(expected_retries.len())
+            .create_async()
+            .await;
         let recipe = Recipe {
-            query: indexmap! {
-                "mode".into() => "sudo".into(),
-                "fast".into() => "true".into(),
-            },
-            headers: indexmap! {
-                "Accept".into() => "application/json".into(),
-                "Content-Type".into() => "application/json".into(),
-            },
+            url: format!("{url}/flaky").as_str().into(),
+            retry: on_status.map(|on_status| RetryPolicy {
+                max_attempts: 3,
+                backoff: Duration::ZERO,
+                on_status,
+                on_connection_error: false,
+            }),
             ..Recipe::factory(())
         };
-        let recipe_id = recipe.id.clone();
 
-        let seed = RequestSeed::new(
-            recipe,
-            BuildOptions {
-                disabled_headers: ["Content-Type".to_owned()].into(),
-                disabled_query_parameters: ["fast".to_owned()].into(),
-            },
-        );
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
         let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let exchange = ticket.send(&template_context.database).await.unwrap();
+        fail_mock.assert();
+        second_mock.assert();
 
-        let expected_headers: HashMap<String, String> =
-            [("accept", "application/json")]
-                .into_iter()
-                .map(|(k, v)| (k.to_string(), v.to_string()))
-                .collect();
-
-        assert_eq!(
-            *ticket.record,
-            RequestRecord {
-                id: ticket.record.id,
-                profile_id: template_context.selected_profile.clone(),
-                recipe_id,
-                method: Method::GET,
-                url: "http://localhost/url?mode=sudo".parse().unwrap(),
-                headers: (&expected_headers).try_into().unwrap(),
-                body: None,
-            }
-        );
+        assert_eq!(exchange.response.status.as_u16(), expected_status);
+        let retries = exchange
+            .retries
+            .iter()
+            .map(|attempt| match &attempt.outcome {
+                RetryOutcome::Status(status) => status.as_u16(),
+                RetryOutcome::Error(error) => {
+                    panic!("Unexpected error {error}")
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(retries, expected_retries);
     }
 
-    /// Leading/trailing newlines should be stripped from rendered header
-    /// values. These characters are invalid and trigger an error, so we assume
-    /// they're unintentional and the user won't miss them.
+    /// Requests that fail without a response should be retried if enabled,
+    /// and the retries attached to the final error
     #[rstest]
+    #[case::disabled(false, 0)]
+    #[case::enabled(true, 1)]
     #[tokio::test]
-    async fn test_render_headers_strip(template_context: TemplateContext) {
+    async fn test_retry_connection_error(
+        template_context: TemplateContext,
+        #[case] on_connection_error: bool,
+        #[case] expected_retries: usize,
+    ) {
+        // Grab a free port, then close it so the connection is refused
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+        let http_engine = HttpEngine::new(&Config {
+            retry: Some(RetryPolicy {
+                max_attempts: 2,
+                backoff: Duration::ZERO,
+                on_status: vec![],
+                on_connection_error,
+            }),
+            ..Config::default()
+        });
         let recipe = Recipe {
-            // Leading/trailing newlines should be stripped
-            headers: indexmap! {
-                "Accept".into() => "application/json".into(),
-                "Host".into() => "\n{{host}}\n".into(),
+            url: format!("http://127.0.0.1:{port}/").as_str().into(),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let error = ticket
+            .send(&template_context.database)
+            .await
+            .expect_err("Expected connection error");
+        assert_eq!(error.retries.len(), expected_retries);
+        assert!(error
+            .retries
+            .iter()
+            .all(|attempt| matches!(attempt.outcome, RetryOutcome::Error(_))));
+    }
+
+    /// A forced HTTP version should be used for the connection, and the
+    /// negotiated version recorded in the response. The mock server only
+    /// speaks HTTP/1.1, so forcing HTTP/2 fails.
+    #[rstest]
+    #[case::default(None, None, Some(Version::HTTP_11))]
+    #[case::config(Some(HttpVersion::Http1_1), None, Some(Version::HTTP_11))]
+    #[case::recipe(None, Some(HttpVersion::Http1_1), Some(Version::HTTP_11))]
+    #[case::http2(None, Some(HttpVersion::Http2), None)]
+    #[case::recipe_overrides_config(
+        Some(HttpVersion::Http2),
+        Some(HttpVersion::Http1_1),
+        Some(Version::HTTP_11)
+    )]
+    #[tokio::test]
+    async fn test_http_version(
+        template_context: TemplateContext,
+        #[case] config_version: Option<HttpVersion>,
+        #[case] recipe_version: Option<HttpVersion>,
+        #[case] expected: Option<Version>,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        server
+            .mock("GET", "/get")
+            .with_body("hello!")
+            .create_async()
+            .await;
+        let http_engine = HttpEngine::new(&Config {
+            http_version: config_version,
+            ..Config::default()
+        });
+        let recipe = Recipe {
+            url: format!("{url}/get").as_str().into(),
+            http_version: recipe_version,
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let result = ticket.send(&template_context.database).await;
+        assert_eq!(
+            result.ok().map(|exchange| exchange.response.version),
+            expected
+        );
+    }
+
+    /// Connection options from the recipe should override the config, field
+    /// by field. Binding to an address that isn't local fails to connect.
+    #[rstest]
+    #[case::default(None, None, true)]
+    #[case::config_loopback(Some("127.0.0.1"), None, true)]
+    #[case::config_not_local(Some("192.0.2.1"), None, false)]
+    #[case::recipe_not_local(None, Some("192.0.2.1"), false)]
+    #[case::recipe_overrides_config(Some("192.0.2.1"), Some("127.0.0.1"), true)]
+    #[tokio::test]
+    async fn test_connection_options(
+        template_context: TemplateContext,
+        #[case] config_address: Option<&str>,
+        #[case] recipe_address: Option<&str>,
+        #[case] expect_success: bool,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        server
+            .mock("GET", "/get")
+            .with_body("hello!")
+            .create_async()
+            .await;
+        let http_engine = HttpEngine::new(&Config {
+            connection: ConnectionOptions {
+                local_address: config_address.map(|a| a.parse().unwrap()),
+                ..ConnectionOptions::default()
             },
+            ..Config::default()
+        });
+        let recipe = Recipe {
+            url: format!("{url}/get").as_str().into(),
+            connection: Some(ConnectionOptions {
+                // Unrelated field shouldn't prevent inheriting the address
+                keep_alive: Some(false),
+                local_address: recipe_address.map(|a| a.parse().unwrap()),
+                ..ConnectionOptions::default()
+            }),
             ..Recipe::factory(())
         };
-        let rendered = recipe
-            .render_headers(&BuildOptions::default(), &template_context)
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let result = ticket.send(&template_context.database).await;
+        assert_eq!(result.is_ok(), expect_success);
+    }
+
+    /// Recipe timeout should be applied to the built request
+    #[rstest]
+    #[case::none(None)]
+    #[case::timeout(Some(Duration::from_secs(3)))]
+    #[tokio::test]
+    async fn test_timeout(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+        #[case] timeout: Option<Duration>,
+    ) {
+        let recipe = Recipe {
+            timeout,
+            ..Recipe::factory(())
+        };
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        assert_eq!(ticket.request.timeout(), timeout.as_ref());
+    }
+
+    /// Multipart forms should include text fields and file content, and the
+    /// encoded body should be captured in the request record
+    #[rstest]
+    #[tokio::test]
+    async fn test_form_multipart(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+        temp_dir: TempDir,
+    ) {
+        let path = temp_dir.join("ted.txt");
+        std::fs::write(&path, "hello!").unwrap();
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        let mock = server
+            .mock("POST", "/upload")
+            .match_header(
+                "content-type",
+                mockito::Matcher::Regex(
+                    "^multipart/form-data; boundary=".into(),
+                ),
+            )
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::Regex("name=\"user_id\"\r\n\r\n1\r\n".into()),
+                mockito::Matcher::Regex(
+                    "name=\"file\"; filename=\"ted.txt\"\r\n\
+                    Content-Type: text/plain\r\n\r\nhello!\r\n"
+                        .into(),
+                ),
+            ]))
+            .create_async()
+            .await;
+        let recipe = Recipe {
+            method: collection::Method::Post,
+            url: format!("{url}/upload").as_str().into(),
+            body: Some(RecipeBody::FormMultipart(indexmap! {
+                "user_id".into() => MultipartField::Text("{{user_id}}".into()),
+                "file".into() => MultipartField::File(MultipartFile {
+                    path: path.to_str().unwrap().into(),
+                    content_type: Some("text/plain".into()),
+                    filename: None,
+                }),
+            })),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let body = ticket.record.body.as_ref().unwrap().text().unwrap();
+        assert!(body.contains("hello!"), "{body:?}");
+        ticket.send(&template_context.database).await.unwrap();
+        mock.assert();
+    }
+
+    /// File bodies should be streamed with an explicit length, and left out of
+    /// the request record
+    #[rstest]
+    #[tokio::test]
+    async fn test_file_body(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+        temp_dir: TempDir,
+    ) {
+        let path = temp_dir.join("body.txt");
+        std::fs::write(&path, "hello world!").unwrap();
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        let mock = server
+            .mock("POST", "/upload")
+            .match_header("content-length", "12")
+            .match_body("hello world!")
+            .create_async()
+            .await;
+        let recipe = Recipe {
+            method: collection::Method::Post,
+            url: format!("{url}/upload").as_str().into(),
+            body: Some(RecipeBody::File(path.to_str().unwrap().into())),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe.clone(), BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        assert_eq!(ticket.record.body, None);
+        ticket.send(&template_context.database).await.unwrap();
+        mock.assert();
+
+        // Rendering just the body should load the file
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let body = http_engine
+            .build_body(seed, &template_context)
+            .await
+            .unwrap();
+        assert_eq!(body.as_deref(), Some(b"hello world!".as_slice()));
+    }
+
+    /// Relative body file paths are resolved against the recipe's directory
+    #[rstest]
+    #[tokio::test]
+    async fn test_file_body_relative(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+        temp_dir: TempDir,
+    ) {
+        std::fs::write(temp_dir.join("body.json"), "{}").unwrap();
+        let recipe = Recipe {
+            dir: temp_dir.to_path_buf(),
+            body: Some(RecipeBody::File("./body.json".into())),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let body = http_engine
+            .build_body(seed, &template_context)
             .await
             .unwrap();
+        assert_eq!(body.as_deref(), Some(b"{}".as_slice()));
+    }
+
+    /// Missing files should fail the build
+    #[rstest]
+    #[tokio::test]
+    async fn test_form_multipart_missing_file(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+    ) {
+        let recipe = Recipe {
+            body: Some(RecipeBody::FormMultipart(indexmap! {
+                "file".into() => MultipartFile {
+                    path: "./not-a-file".into(),
+                    content_type: None,
+                    filename: None,
+                }.into(),
+            })),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        assert_err!(
+            http_engine
+                .build(seed, &template_context)
+                .await
+                .map(|ticket| ticket.record),
+            "Error reading file \"./not-a-file\""
+        );
+    }
+
+    /// Cookies set by a response should be sent with later requests, and
+    /// restored from the DB in a new session if persistence is enabled
+    #[rstest]
+    #[tokio::test]
+    async fn test_cookies(template_context: TemplateContext) {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        let login_mock = server
+            .mock("POST", "/login")
+            .with_header("set-cookie", "session=abc123; Max-Age=3600")
+            .create_async()
+            .await;
+        let config = Config {
+            persist_cookies: true,
+            ..Config::default()
+        };
+        let login_recipe = Recipe {
+            method: collection::Method::Post,
+            url: format!("{url}/login").as_str().into(),
+            ..Recipe::factory(())
+        };
+        let recipe = Recipe {
+            url: format!("{url}/me").as_str().into(),
+            ..Recipe::factory(())
+        };
+
+        let http_engine = HttpEngine::new(&config);
+        let seed = RequestSeed::new(login_recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        ticket.send(&template_context.database).await.unwrap();
+        login_mock.assert();
 
+        // Same engine replays the cookie
+        let seed = RequestSeed::new(recipe.clone(), BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
         assert_eq!(
-            rendered,
-            header_map([
-                ("Accept", "application/json"),
-                // This is a non-sensical value, but it's good enough
-                ("Host", "http://localhost"),
-            ])
+            ticket.record.headers.get(header::COOKIE),
+            Some(&HeaderValue::from_static("session=abc123"))
+        );
+
+        // New engine has no cookies until they're loaded from the DB
+        for load in [false, true] {
+            let http_engine = HttpEngine::new(&config);
+            if load {
+                http_engine
+                    .load_cookies(&template_context.database)
+                    .unwrap();
+            }
+            let seed =
+                RequestSeed::new(recipe.clone(), BuildOptions::default());
+            let ticket =
+                http_engine.build(seed, &template_context).await.unwrap();
+            let expected = load.then_some(b"session=abc123".as_slice());
+            assert_eq!(
+                ticket
+                    .record
+                    .headers
+                    .get(header::COOKIE)
+                    .map(HeaderValue::as_bytes),
+                expected
+            );
+        }
+    }
+
+    /// Requests should go through the proxy from the profile or config,
+    /// unless the host is excluded by `no_proxy`, or the profile wants a
+    /// direct connection
+    #[rstest]
+    #[case::none(false, None, None, None, false)]
+    #[case::config(true, None, None, None, true)]
+    #[case::profile(false, Some(false), None, None, true)]
+    #[case::profile_direct(true, Some(true), None, None, false)]
+    #[case::config_no_proxy(true, None, Some("slumber.invalid"), None, false)]
+    #[case::profile_no_proxy(true, None, None, Some("*"), false)]
+    #[case::profile_overrides_no_proxy(
+        true,
+        None,
+        Some("slumber.invalid"),
+        Some("other.invalid"),
+        true
+    )]
+    #[tokio::test]
+    async fn test_proxy(
+        mut template_context: TemplateContext,
+        #[case] config_proxy: bool,
+        // `Some(true)` for a direct connection, `Some(false)` for the proxy
+        #[case] profile_direct: Option<bool>,
+        #[case] config_no_proxy: Option<&str>,
+        #[case] profile_no_proxy: Option<&str>,
+        #[case] expect_proxied: bool,
+    ) {
+        // The mock server plays the role of the proxy. The target host
+        // doesn't exist, so the request only succeeds if it's proxied
+        let mut server = mockito::Server::new_async().await;
+        let proxy_url = server.url();
+        let mock = server
+            .mock("GET", mockito::Matcher::Any)
+            .with_body("proxied")
+            .expect(if expect_proxied { 1 } else { 0 })
+            .create_async()
+            .await;
+
+        let http_engine = HttpEngine::new(&Config {
+            proxy: config_proxy.then(|| proxy_url.clone()),
+            no_proxy: config_no_proxy.map(String::from),
+            ..Config::default()
+        });
+        let profile_id = template_context.selected_profile.clone().unwrap();
+        let profile = &mut template_context.collection.profiles[&profile_id];
+        profile.proxy = profile_direct.map(|direct| {
+            if direct {
+                ProfileProxy::Direct
+            } else {
+                ProfileProxy::Url(proxy_url.clone())
+            }
+        });
+        profile.no_proxy = profile_no_proxy.map(String::from);
+        let recipe = Recipe {
+            url: "http://slumber.invalid/get".into(),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let result = ticket.send(&template_context.database).await;
+        assert_eq!(result.is_ok(), expect_proxied);
+        mock.assert();
+    }
+
+    /// A direct connection skips every proxy, even one from the environment.
+    /// reqwest only reads proxy variables once per process, so this tests the
+    /// decision rather than sending a request.
+    #[rstest]
+    #[case::environment(None, None, false, ProxyMode::Environment)]
+    #[case::direct(None, None, true, ProxyMode::Direct)]
+    #[case::direct_overrides_url(
+        Some("http://proxy"),
+        None,
+        true,
+        ProxyMode::Direct
+    )]
+    #[case::url(
+        Some("http://proxy"),
+        Some("localhost"),
+        false,
+        ProxyMode::Url {
+            url: "http://proxy",
+            no_proxy: Some("localhost"),
+        }
+    )]
+    fn test_proxy_mode(
+        #[case] proxy: Option<&str>,
+        #[case] no_proxy: Option<&str>,
+        #[case] direct: bool,
+        #[case] expected: ProxyMode<'static>,
+    ) {
+        let settings = ProxySettings {
+            proxy: proxy.map(String::from),
+            no_proxy: no_proxy.map(String::from),
+            direct,
+        };
+        assert_eq!(settings.mode(), expected);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_proxy_invalid(template_context: TemplateContext) {
+        let http_engine = HttpEngine::new(&Config {
+            proxy: Some("not a url".into()),
+            ..Config::default()
+        });
+        let seed =
+            RequestSeed::new(Recipe::factory(()), BuildOptions::default());
+        let result = http_engine
+            .build(seed, &template_context)
+            .await
+            .map(|ticket| ticket.record);
+        assert_err!(result, "Invalid proxy URL `not a url`");
+    }
+
+    /// Netrc credentials should be used only when the recipe doesn't provide
+    /// its own authorization
+    #[rstest]
+    #[case::netrc(None, None, Some("Basic dXNlcjpodW50ZXIy"))]
+    #[case::authentication(
+        Some(Authentication::Bearer("token!".into())),
+        None,
+        Some("Bearer token!")
+    )]
+    #[case::header(None, Some("Custom"), Some("Custom"))]
+    #[tokio::test]
+    async fn test_netrc(
+        template_context: TemplateContext,
+        #[case] authentication: Option<Authentication>,
+        #[case] header: Option<&str>,
+        #[case] expected_header: Option<&str>,
+    ) {
+        let http_engine = HttpEngine {
+            netrc: Some(
+                Netrc::parse("machine localhost login user password hunter2")
+                    .unwrap()
+                    .into(),
+            ),
+            ..HttpEngine::new(&Config::default())
+        };
+        let recipe = Recipe {
+            authentication,
+            headers: header
+                .map(|header| {
+                    indexmap! {"Authorization".into() => header.into()}
+                })
+                .unwrap_or_default(),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+
+        assert_eq!(
+            ticket
+                .record
+                .headers
+                .get(header::AUTHORIZATION)
+                .map(|value| value.to_str().unwrap()),
+            expected_header
+        );
+    }
+
+    /// Test building requests with various authentication methods
+    #[rstest]
+    #[case::basic(
+        Authentication::Basic {
+            username: "{{username}}".into(),
+            password: Some("{{password}}".into()),
+        },
+        "Basic dXNlcjpodW50ZXIy"
+    )]
+    #[case::basic_no_password(
+        Authentication::Basic {
+            username: "{{username}}".into(),
+            password: None,
+        },
+        "Basic dXNlcjo="
+    )]
+    #[case::bearer(Authentication::Bearer("{{token}}".into()), "Bearer token!")]
+    #[tokio::test]
+    async fn test_authentication(
+        http_engine: HttpEngine,
+        #[case] authentication: Authentication,
+        #[case] expected_header: &str,
+    ) {
+        let profile_data = indexmap! {
+            "username".into() => "user".into(),
+            "password".into() => "hunter2".into(),
+            "token".into() => "token!".into(),
+        };
+        let profile = Profile {
+            data: profile_data,
+            ..Profile::factory(())
+        };
+        let profile_id = profile.id.clone();
+        let template_context = TemplateContext {
+            collection: Collection {
+                profiles: indexmap! {profile_id.clone() => profile},
+                ..Collection::factory(())
+            },
+            selected_profile: Some(profile_id.clone()),
+            ..TemplateContext::factory(())
+        };
+        let recipe = Recipe {
+            authentication: Some(authentication),
+            ..Recipe::factory(())
+        };
+        let recipe_id = recipe.id.clone();
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+
+        let expected_headers: HashMap<String, String> =
+            [("authorization", expected_header)]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+
+        assert_eq!(
+            *ticket.record,
+            RequestRecord {
+                id: ticket.record.id,
+                profile_id: Some(profile_id),
+                recipe_id,
+                method: Method::GET,
+                url: "http://localhost/url".parse().unwrap(),
+                headers: (&expected_headers).try_into().unwrap(),
+                body: None,
+            }
+        );
+    }
+
+    /// OAuth 2.0 token should be fetched before building the request, and
+    /// attached as a bearer token
+    #[rstest]
+    #[tokio::test]
+    async fn test_oauth2_authentication(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        let token_mock = server
+            .mock("POST", "/token")
+            .match_body(mockito::Matcher::UrlEncoded(
+                "client_id".into(),
+                "client".into(),
+            ))
+            .with_body(r#"{"access_token":"token1","expires_in":3600}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let authentication: Authentication = serde_yaml::from_str(&format!(
+            "!oauth2
+            grant_type: client_credentials
+            token_url: {url}/token
+            client_id: client
+            client_secret: secret"
+        ))
+        .unwrap();
+        let recipe = Recipe {
+            authentication: Some(authentication),
+            ..Recipe::factory(())
+        };
+
+        // Build twice; the second time should use the cached token
+        for _ in 0..2 {
+            let seed =
+                RequestSeed::new(recipe.clone(), BuildOptions::default());
+            let ticket =
+                http_engine.build(seed, &template_context).await.unwrap();
+            assert_eq!(
+                ticket.record.headers.get(header::AUTHORIZATION),
+                Some(&HeaderValue::from_static("Bearer token1"))
+            );
+        }
+        token_mock.assert_async().await;
+    }
+
+    /// AWS signature should be computed from the rendered credentials, and the
+    /// signed headers should be included in the request record
+    #[rstest]
+    #[tokio::test]
+    async fn test_aws_sigv4_authentication(http_engine: HttpEngine) {
+        let profile = Profile {
+            data: indexmap! {"region".into() => "us-west-2".into()},
+            ..Profile::factory(())
+        };
+        let profile_id = profile.id.clone();
+        let template_context = TemplateContext {
+            collection: Collection {
+                profiles: indexmap! {profile_id.clone() => profile},
+                ..Collection::factory(())
+            },
+            selected_profile: Some(profile_id),
+            ..TemplateContext::factory(())
+        };
+        let recipe = Recipe {
+            authentication: Some(Authentication::AwsSigV4 {
+                access_key_id: "AKIDEXAMPLE".into(),
+                secret_access_key: "secret".into(),
+                session_token: Some("token".into()),
+                region: "{{region}}".into(),
+                service: "execute-api".into(),
+            }),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+
+        let headers = &ticket.record.headers;
+        let authorization = headers
+            .get(header::AUTHORIZATION)
+            .unwrap()
+            .to_str()
+            .unwrap();
+        assert!(
+            authorization
+                .starts_with("AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/"),
+            "{authorization}"
+        );
+        assert!(
+            authorization.contains(
+                "/us-west-2/execute-api/aws4_request, \
+                SignedHeaders=host;x-amz-date;x-amz-security-token, "
+            ),
+            "{authorization}"
+        );
+        assert!(headers.contains_key("x-amz-date"));
+        assert_eq!(headers["x-amz-security-token"], "token");
+    }
+
+    /// Digest auth should answer the server's challenge and re-send the
+    /// request. If the server doesn't offer digest auth, the first response is
+    /// returned as-is.
+    #[rstest]
+    #[case::digest(r#"Digest realm="test", nonce="abc123", qop="auth""#, 200)]
+    #[case::other_scheme(r#"Basic realm="test""#, 401)]
+    #[tokio::test]
+    async fn test_digest_authentication(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+        #[case] challenge: &str,
+        #[case] expected_status: u16,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        let challenge_mock = server
+            .mock("POST", "/digest?q=1")
+            .match_header("authorization", mockito::Matcher::Missing)
+            .with_status(401)
+            .with_header("www-authenticate", challenge)
+            .expect(1)
+            .create_async()
+            .await;
+        let authorized_mock = server
+            .mock("POST", "/digest?q=1")
+            .match_header(
+                "authorization",
+                mockito::Matcher::AllOf(vec![
+                    mockito::Matcher::Regex(
+                        r#"^Digest username="user""#.into(),
+                    ),
+                    mockito::Matcher::Regex(r#"realm="test""#.into()),
+                    mockito::Matcher::Regex(r#"uri="/digest\?q=1""#.into()),
+                ]),
+            )
+            .match_body("hello")
+            .expect(usize::from(expected_status == 200))
+            .create_async()
+            .await;
+        let recipe = Recipe {
+            method: collection::Method::Post,
+            url: format!("{url}/digest?q=1").as_str().into(),
+            body: Some("hello".into()),
+            authentication: Some(Authentication::Digest {
+                username: "user".into(),
+                password: Some("hunter2".into()),
+            }),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let exchange = ticket.send(&template_context.database).await.unwrap();
+        assert_eq!(exchange.response.status.as_u16(), expected_status);
+        challenge_mock.assert_async().await;
+        authorized_mock.assert_async().await;
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_disable_headers_and_query_params(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+    ) {
+        let recipe = Recipe {
+            query: indexmap! {
+                "mode".into() => "sudo".into(),
+                "fast".into() => "true".into(),
+            },
+            headers: indexmap! {
+                "Accept".into() => "application/json".into(),
+                "Content-Type".into() => "application/json".into(),
+            },
+            ..Recipe::factory(())
+        };
+        let recipe_id = recipe.id.clone();
+
+        let seed = RequestSeed::new(
+            recipe,
+            BuildOptions {
+                disabled_headers: ["Content-Type".to_owned()].into(),
+                disabled_query_parameters: ["fast".to_owned()].into(),
+                ..Default::default()
+            },
+        );
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+
+        let expected_headers: HashMap<String, String> =
+            [("accept", "application/json")]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+
+        assert_eq!(
+            *ticket.record,
+            RequestRecord {
+                id: ticket.record.id,
+                profile_id: template_context.selected_profile.clone(),
+                recipe_id,
+                method: Method::GET,
+                url: "http://localhost/url?mode=sudo".parse().unwrap(),
+                headers: (&expected_headers).try_into().unwrap(),
+                body: None,
+            }
+        );
+    }
+
+    /// Leading/trailing newlines should be stripped from rendered header
+    /// values. These characters are invalid and trigger an error, so we assume
+    /// they're unintentional and the user won't miss them.
+    #[rstest]
+    #[tokio::test]
+    async fn test_render_headers_strip(template_context: TemplateContext) {
+        let recipe = Recipe {
+            // Leading/trailing newlines should be stripped
+            headers: indexmap! {
+                "Accept".into() => "application/json".into(),
+                "Host".into() => "\n{{host}}\n".into(),
+            },
+            ..Recipe::factory(())
+        };
+        let rendered = recipe
+            .render_headers(&BuildOptions::default(), &template_context)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            rendered,
+            header_map([
+                ("Accept", "application/json"),
+                // This is a non-sensical value, but it's good enough
+                ("Host", "http://localhost"),
+            ])
+        );
+    }
+
+    /// Resuming a download should request just the missing content, and
+    /// stitch it onto the existing content. If the server ignores the `Range`
+    /// header, we should use the full response instead.
+    #[rstest]
+    #[case::partial(206, Some("bytes 6-11/12"), "world!", "hello world!")]
+    #[case::range_ignored(200, None, "hello world!", "hello world!")]
+    #[tokio::test]
+    async fn test_resume_download(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+        temp_dir: TempDir,
+        #[case] status: usize,
+        #[case] content_range: Option<&str>,
+        #[case] response_body: &str,
+        #[case] expected_body: &str,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        let mut mock = server
+            .mock("GET", "/download")
+            .match_header("range", "bytes=6-")
+            .with_status(status)
+            .with_body(response_body);
+        if let Some(content_range) = content_range {
+            mock = mock.with_header("content-range", content_range);
+        }
+        let mock = mock.create_async().await;
+
+        let partial_path = temp_dir.join("download.txt");
+        std::fs::write(&partial_path, "hello ").unwrap();
+        let recipe = Recipe {
+            url: format!("{url}/download").as_str().into(),
+            ..Recipe::factory(())
+        };
+        let seed = RequestSeed::new(
+            recipe,
+            BuildOptions {
+                resume_download: Some(partial_path),
+                ..Default::default()
+            },
+        );
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let exchange = ticket.send(&template_context.database).await.unwrap();
+
+        mock.assert();
+        assert_eq!(exchange.response.body.bytes(), expected_body.as_bytes());
+    }
+
+    /// Partial content that doesn't start where the partial download left off
+    /// can't be appended to it
+    #[rstest]
+    #[case::missing(None, "Partial response has no Content-Range")]
+    #[case::invalid(Some("6-11/12"), "Invalid Content-Range")]
+    #[case::wrong_start(
+        Some("bytes 0-11/12"),
+        "Partial response starts at byte 0, but 6 bytes were already downloaded"
+    )]
+    #[tokio::test]
+    async fn test_resume_download_content_range(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+        temp_dir: TempDir,
+        #[case] content_range: Option<&str>,
+        #[case] expected_error: &str,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        let mut mock = server
+            .mock("GET", "/download")
+            .with_status(206)
+            .with_body("world!");
+        if let Some(content_range) = content_range {
+            mock = mock.with_header("content-range", content_range);
+        }
+        let _mock = mock.create_async().await;
+
+        let partial_path = temp_dir.join("download.txt");
+        std::fs::write(&partial_path, "hello ").unwrap();
+        let recipe = Recipe {
+            url: format!("{url}/download").as_str().into(),
+            ..Recipe::factory(())
+        };
+        let seed = RequestSeed::new(
+            recipe,
+            BuildOptions {
+                resume_download: Some(partial_path),
+                ..Default::default()
+            },
+        );
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        assert_err!(
+            ticket.send(&template_context.database).await,
+            expected_error
+        );
+    }
+
+    /// The partial download is streamed into the new body, so a large body is
+    /// still spooled to disk. Only the requested offset is read from the file,
+    /// even if it changes after the request is built.
+    #[rstest]
+    #[tokio::test]
+    async fn test_resume_download_large_body(
+        template_context: TemplateContext,
+        temp_dir: TempDir,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        let mock = server
+            .mock("GET", "/download")
+            .match_header("range", "bytes=6-")
+            .with_status(206)
+            .with_header("content-range", "bytes 6-11/12")
+            .with_body("world!")
+            .create_async()
+            .await;
+        let http_engine = HttpEngine::new(&Config {
+            large_body_size: ByteSize::b(5),
+            ..Config::default()
+        });
+
+        let partial_path = temp_dir.join("download.txt");
+        std::fs::write(&partial_path, "hello ").unwrap();
+        let recipe = Recipe {
+            url: format!("{url}/download").as_str().into(),
+            ..Recipe::factory(())
+        };
+        let seed = RequestSeed::new(
+            recipe,
+            BuildOptions {
+                resume_download: Some(partial_path.clone()),
+                ..Default::default()
+            },
+        );
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        std::fs::write(&partial_path, "hello there").unwrap();
+        let exchange = ticket.send(&template_context.database).await.unwrap();
+        mock.assert();
+
+        let body = &exchange.response.body;
+        assert!(body.is_spooled());
+        assert_eq!(body.size(), ByteSize::b(12));
+        let spool_path = body.spool_path().unwrap();
+        assert_eq!(std::fs::read(spool_path).unwrap(), b"hello world!");
+    }
+
+    /// Conditional recipes should revalidate the previous response, and reuse
+    /// its body if the server says it hasn't changed
+    #[rstest]
+    #[tokio::test]
+    async fn test_conditional(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        let mock = server
+            .mock("GET", "/data")
+            .match_header("if-none-match", "\"v1\"")
+            .match_header("if-modified-since", "Wed, 21 Oct 2015 07:28:00 GMT")
+            .with_status(304)
+            .with_header("etag", "\"v1\"")
+            .with_header("x-new", "new")
+            .create_async()
+            .await;
+        let recipe = Recipe {
+            url: format!("{url}/data").as_str().into(),
+            conditional: true,
+            ..Recipe::factory(())
+        };
+        let previous = Exchange {
+            response: ResponseRecord {
+                headers: header_map([
+                    ("content-type", "application/json"),
+                    ("etag", "\"v1\""),
+                    ("last-modified", "Wed, 21 Oct 2015 07:28:00 GMT"),
+                    ("x-new", "old"),
+                ]),
+                body: br#"{"id":1}"#.to_vec().into(),
+                ..ResponseRecord::factory(())
+            }
+            .into(),
+            ..Exchange::factory((
+                template_context.selected_profile.clone(),
+                recipe.id.clone(),
+            ))
+        };
+        template_context
+            .database
+            .insert_exchange(&previous)
+            .unwrap();
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let exchange = ticket.send(&template_context.database).await.unwrap();
+        mock.assert();
+
+        let response = &exchange.response;
+        assert_eq!(response.status, StatusCode::NOT_MODIFIED);
+        assert!(response.from_cache);
+        assert_eq!(response.body.bytes(), br#"{"id":1}"#);
+        // Headers from the new response take precedence
+        assert_eq!(response.headers.get("x-new").unwrap(), "new");
+        assert_eq!(
+            response.headers.get("content-type").unwrap(),
+            "application/json"
+        );
+    }
+
+    /// Conditional headers should only be attached if the previous response
+    /// can be revalidated, and not if the recipe sets its own
+    #[rstest]
+    #[case::no_validator(&[], StatusCode::OK, &[])]
+    #[case::error_status(&[("etag", "\"v1\"")], StatusCode::NOT_FOUND, &[])]
+    #[case::truncated(
+        &[("etag", "\"v1\""), ("content-length", "100")],
+        StatusCode::OK,
+        &[],
+    )]
+    #[case::recipe_header(
+        &[("etag", "\"v1\"")],
+        StatusCode::OK,
+        &[("If-None-Match", "\"mine\"")],
+    )]
+    #[tokio::test]
+    async fn test_conditional_skipped(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+        #[case] previous_headers: &[(&str, &str)],
+        #[case] previous_status: StatusCode,
+        #[case] recipe_headers: &[(&str, &str)],
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        let expected_if_none_match = match recipe_headers {
+            [(_, value)] => mockito::Matcher::Exact(value.to_string()),
+            _ => mockito::Matcher::Missing,
+        };
+        let mock = server
+            .mock("GET", "/data")
+            .match_header("if-none-match", expected_if_none_match)
+            .match_header("if-modified-since", mockito::Matcher::Missing)
+            .with_status(304)
+            .create_async()
+            .await;
+        let recipe = Recipe {
+            url: format!("{url}/data").as_str().into(),
+            headers: recipe_headers
+                .iter()
+                .map(|(name, value)| ((*name).into(), (*value).into()))
+                .collect(),
+            conditional: true,
+            ..Recipe::factory(())
+        };
+        let previous = Exchange {
+            response: ResponseRecord {
+                status: previous_status,
+                headers: header_map(previous_headers.iter().copied()),
+                body: b"{}".to_vec().into(),
+                ..ResponseRecord::factory(())
+            }
+            .into(),
+            ..Exchange::factory((
+                template_context.selected_profile.clone(),
+                recipe.id.clone(),
+            ))
+        };
+        template_context
+            .database
+            .insert_exchange(&previous)
+            .unwrap();
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let exchange = ticket.send(&template_context.database).await.unwrap();
+        mock.assert();
+
+        // The 304 is shown as-is, with no cached body
+        assert!(!exchange.response.from_cache);
+        assert_eq!(exchange.response.body.bytes(), b"");
+    }
+
+    /// A recent response to an identical request should be reused instead of
+    /// hitting the network, if the recipe has a cache TTL
+    #[rstest]
+    #[case::fresh(Duration::from_secs(10), "/data", true)]
+    #[case::expired(Duration::from_secs(70), "/data", false)]
+    #[case::different_url(Duration::from_secs(10), "/other", false)]
+    #[tokio::test]
+    async fn test_cache_ttl(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+        #[case] age: Duration,
+        #[case] previous_path: &str,
+        #[case] expect_cached: bool,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        let mock = server
+            .mock("GET", "/data")
+            .with_body("new")
+            .expect(if expect_cached { 0 } else { 1 })
+            .create_async()
+            .await;
+        let recipe = Recipe {
+            url: format!("{url}/data").as_str().into(),
+            cache_ttl: Some(Duration::from_secs(60)),
+            ..Recipe::factory(())
+        };
+        let previous_request = RequestRecord {
+            url: format!("{url}{previous_path}").parse().unwrap(),
+            ..RequestRecord::factory((
+                template_context.selected_profile.clone(),
+                recipe.id.clone(),
+            ))
+        };
+        let fingerprint = previous_request.fingerprint();
+        let previous = Exchange {
+            id: previous_request.id,
+            request: previous_request.into(),
+            response: ResponseRecord {
+                body: b"old".to_vec().into(),
+                ..ResponseRecord::factory(())
+            }
+            .into(),
+            start_time: Utc::now() - age,
+            end_time: Utc::now() - age,
+            retries: Vec::new(),
+            timing: None,
+        };
+        template_context
+            .database
+            .insert_exchange_with_fingerprint(&previous, Some(&fingerprint))
+            .unwrap();
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let id = ticket.record().id;
+        let exchange = ticket.send(&template_context.database).await.unwrap();
+        mock.assert();
+
+        // The exchange should always belong to the new request
+        assert_eq!(exchange.id, id);
+        assert_eq!(exchange.response.from_cache, expect_cached);
+        let expected_body: &[u8] = if expect_cached { b"old" } else { b"new" };
+        assert_eq!(exchange.response.body.bytes(), expected_body);
+    }
+
+    /// The cache should compare the request as it was sent, not the redacted
+    /// record, so a changed sensitive value or header isn't a cache hit
+    #[rstest]
+    #[case::unchanged(None, None, true)]
+    #[case::sensitive(Some("hunter3"), None, false)]
+    #[case::header(None, Some("2"), false)]
+    #[tokio::test]
+    async fn test_cache_ttl_changed_request(
+        http_engine: HttpEngine,
+        mut template_context: TemplateContext,
+        #[case] new_token: Option<&str>,
+        #[case] new_header: Option<&str>,
+        #[case] expect_cached: bool,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        let mock = server
+            .mock("GET", "/data")
+            .with_body("hello")
+            .expect(if expect_cached { 1 } else { 2 })
+            .create_async()
+            .await;
+        for profile in template_context.collection.profiles.values_mut() {
+            profile.sensitive = vec!["token".into()];
+        }
+        let mut recipe = Recipe {
+            url: format!("{url}/data").as_str().into(),
+            headers: indexmap! {
+                "Authorization".into() => "Bearer {{token}}".into(),
+                "X-Version".into() => "1".into(),
+            },
+            cache_ttl: Some(Duration::from_secs(60)),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe.clone(), BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        ticket.send(&template_context.database).await.unwrap();
+
+        if let Some(token) = new_token {
+            for profile in template_context.collection.profiles.values_mut() {
+                profile.data.insert("token".into(), token.into());
+            }
+        }
+        if let Some(header) = new_header {
+            recipe.headers.insert("X-Version".into(), header.into());
+        }
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        // The redacted records are identical either way
+        assert_eq!(
+            ticket.record().headers.get("authorization").unwrap(),
+            "Bearer <sensitive>"
+        );
+        let exchange = ticket.send(&template_context.database).await.unwrap();
+        mock.assert();
+        assert_eq!(exchange.response.from_cache, expect_cached);
+    }
+
+    /// Requests to a rate-limited host should be spaced out. Recipes can
+    /// override the limit from the config
+    #[rstest]
+    #[case::config(Some("127.0.0.1"), None, true)]
+    #[case::other_host(Some("example.com"), None, false)]
+    #[case::recipe(None, Some(Duration::from_millis(300)), true)]
+    #[case::recipe_override(
+        Some("127.0.0.1"),
+        Some(Duration::from_millis(1)),
+        false
+    )]
+    #[tokio::test]
+    async fn test_rate_limit(
+        template_context: TemplateContext,
+        #[case] config_host: Option<&str>,
+        #[case] recipe_period: Option<Duration>,
+        #[case] expect_wait: bool,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        let mock = server.mock("GET", "/data").expect(2).create_async().await;
+        let http_engine = HttpEngine::new(&Config {
+            rate_limits: config_host
+                .map(|host| {
+                    (
+                        host.to_owned(),
+                        RateLimit {
+                            requests: 1,
+                            per: Duration::from_millis(300),
+                        },
+                    )
+                })
+                .into_iter()
+                .collect(),
+            ..Config::default()
+        });
+        let recipe = Recipe {
+            url: format!("{url}/data").as_str().into(),
+            rate_limit: recipe_period.map(|per| RateLimit { requests: 1, per }),
+            ..Recipe::factory(())
+        };
+
+        let start = std::time::Instant::now();
+        for _ in 0..2 {
+            let seed =
+                RequestSeed::new(recipe.clone(), BuildOptions::default());
+            let ticket =
+                http_engine.build(seed, &template_context).await.unwrap();
+            ticket.send(&template_context.database).await.unwrap();
+        }
+        mock.assert();
+        assert_eq!(
+            start.elapsed() >= Duration::from_millis(250),
+            expect_wait,
+            "Elapsed: {:?}",
+            start.elapsed()
+        );
+    }
+
+    /// Event stream responses should be reported to the listener as they
+    /// arrive, and still be stored in full in the final exchange
+    #[rstest]
+    #[tokio::test]
+    async fn test_event_stream(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+    ) {
+        let body = "event: greeting\ndata: hello\n\n: comment\ndata: world\n\n";
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        let mock = server
+            .mock("GET", "/events")
+            .with_header("content-type", "text/event-stream")
+            .with_body(body)
+            .create_async()
+            .await;
+        let recipe = Recipe {
+            url: format!("{url}/events").as_str().into(),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let streams = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let ticket = http_engine
+            .build(seed, &template_context)
+            .await
+            .unwrap()
+            .on_event_stream({
+                let streams = Arc::clone(&streams);
+                move |stream| streams.lock().unwrap().push(Arc::clone(stream))
+            });
+        let exchange = ticket.send(&template_context.database).await.unwrap();
+        mock.assert();
+
+        let streams = streams.lock().unwrap();
+        // Called once for the headers, then for each chunk
+        assert!(streams.len() >= 2);
+        let stream = &streams[0];
+        assert_eq!(stream.status, StatusCode::OK);
+        let content = stream.content();
+        let events = content
+            .events
+            .iter()
+            .map(|event| (event.event.as_str(), event.data.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(events, [("greeting", "hello"), ("message", "world")]);
+        assert_eq!(content.size, body.len() as u64);
+        assert_eq!(exchange.response.body.bytes(), body.as_bytes());
+    }
+
+    /// The listener shouldn't be called for regular responses
+    #[rstest]
+    #[tokio::test]
+    async fn test_event_stream_not_stream(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        let mock = server
+            .mock("GET", "/json")
+            .with_header("content-type", "application/json")
+            .with_body("{}")
+            .create_async()
+            .await;
+        let recipe = Recipe {
+            url: format!("{url}/json").as_str().into(),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine
+            .build(seed, &template_context)
+            .await
+            .unwrap()
+            .on_event_stream(|_| panic!("Not an event stream"));
+        ticket.send(&template_context.database).await.unwrap();
+        mock.assert();
+    }
+
+    /// Bodies over the size threshold should be spooled to a temp file, with
+    /// just a preview held in memory. The temp file is deleted once the
+    /// response is dropped.
+    #[rstest]
+    #[tokio::test]
+    async fn test_large_body(
+        template_context: TemplateContext,
+        temp_dir: TempDir,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        let mock = server
+            .mock("GET", "/download")
+            .with_body("hello world!")
+            .create_async()
+            .await;
+        let http_engine = HttpEngine::new(&Config {
+            large_body_size: ByteSize::b(5),
+            ..Config::default()
+        });
+        let recipe = Recipe {
+            url: format!("{url}/download").as_str().into(),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let exchange = ticket.send(&template_context.database).await.unwrap();
+        mock.assert();
+
+        let body = &exchange.response.body;
+        assert!(body.is_spooled());
+        assert_eq!(body.bytes(), b"hello");
+        assert_eq!(body.size(), ByteSize::b(12));
+        let spool_path = body.spool_path().unwrap().to_owned();
+        assert_eq!(std::fs::read(&spool_path).unwrap(), b"hello world!");
+
+        // Saving should copy the full body
+        let path = temp_dir.join("download.txt");
+        let mut file = fs::File::create(&path).await.unwrap();
+        body.write_to(&mut file).await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"hello world!");
+
+        drop(exchange);
+        assert!(!spool_path.exists(), "Temp file should be deleted");
+    }
+
+    /// A partially received body should be written to the save path, so it can
+    /// be resumed later
+    #[rstest]
+    #[tokio::test]
+    async fn test_save_partial_response(
+        mut template_context: TemplateContext,
+        temp_dir: TempDir,
+    ) {
+        let template: Template = format!("{}/download.txt", temp_dir.display())
+            .as_str()
+            .into();
+        let recipe = Recipe {
+            save_response_to: Some(template),
+            ..Recipe::factory(())
+        };
+        template_context.collection.recipes =
+            indexmap! {recipe.id.clone() => recipe}.into();
+        let mut error = RequestError {
+            error: anyhow::anyhow!("connection reset"),
+            request: RequestRecord::factory(()).into(),
+            retries: Vec::new(),
+            start_time: Utc::now(),
+            end_time: Utc::now(),
+            partial_response: Some(
+                ResponseRecord {
+                    body: b"hello ".to_vec().into(),
+                    ..ResponseRecord::factory(())
+                }
+                .into(),
+            ),
+            resume_path: None,
+            options: Default::default(),
+        };
+
+        let expected_path = temp_dir.join("download.txt");
+        assert_eq!(
+            error
+                .save_partial_response(&template_context)
+                .await
+                .unwrap(),
+            Some(expected_path.as_path())
+        );
+        assert_eq!(error.resume_path.as_ref(), Some(&expected_path));
+        assert_eq!(std::fs::read(&expected_path).unwrap(), b"hello ");
+    }
+
+    /// Throttled requests should still send/receive the full bodies, just
+    /// slower
+    #[rstest]
+    #[tokio::test]
+    async fn test_throttle(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        let mock = server
+            .mock("POST", "/upload")
+            .match_header("content-length", "5")
+            .match_body("hello")
+            .with_body("0123456789abcdefghij")
+            .create_async()
+            .await;
+
+        let recipe = Recipe {
+            method: collection::Method::Post,
+            url: format!("{url}/upload").as_str().into(),
+            body: Some("hello".into()),
+            throttle: Some(Throttle {
+                upload: Some(ByteSize::b(100)),
+                download: Some(ByteSize::b(100)),
+            }),
+            ..Recipe::factory(())
+        };
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let exchange = ticket.send(&template_context.database).await.unwrap();
+
+        mock.assert();
+        // Record should still have the body, even though it was streamed
+        assert_eq!(
+            exchange.request.body.as_ref().map(ResponseBody::bytes),
+            Some(b"hello".as_slice())
+        );
+        assert_eq!(exchange.response.body.bytes(), b"0123456789abcdefghij");
+        // 20 bytes at 100 B/s
+        assert!(
+            exchange.duration() >= chrono::Duration::milliseconds(200),
+            "Request was not throttled"
+        );
+    }
+
+    /// Compressed responses should be decoded unless disabled, and request
+    /// bodies should be compressed if enabled
+    #[rstest]
+    #[case::decompress(true, b"hello world!".as_slice(), true)]
+    #[case::no_decompress(false, &*ContentEncoding::Gzip.encode(b"hello world!"), false)]
+    #[tokio::test]
+    async fn test_compression(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+        #[case] decompress: bool,
+        #[case] expected_body: &[u8],
+        #[case] expect_decoded: bool,
+    ) {
+        let request_body = ContentEncoding::Gzip.encode(b"hello");
+        let response_body = ContentEncoding::Gzip.encode(b"hello world!");
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        let mock = server
+            .mock("POST", "/data")
+            .match_header("accept-encoding", "br, gzip")
+            .match_header("content-encoding", "gzip")
+            .match_body(request_body.clone())
+            .with_header("content-encoding", "gzip")
+            .with_body(&response_body)
+            .create_async()
+            .await;
+
+        let recipe = Recipe {
+            method: collection::Method::Post,
+            url: format!("{url}/data").as_str().into(),
+            body: Some("hello".into()),
+            compression: Some(Compression {
+                decompress,
+                accept: vec![ContentEncoding::Brotli, ContentEncoding::Gzip],
+                request_body: Some(ContentEncoding::Gzip),
+            }),
+            ..Recipe::factory(())
+        };
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let exchange = ticket.send(&template_context.database).await.unwrap();
+
+        mock.assert();
+        // Record contains the body that was actually sent
+        assert_eq!(
+            exchange.request.body.as_ref().map(ResponseBody::bytes),
+            Some(request_body.as_slice())
+        );
+        assert_eq!(exchange.response.body.bytes(), expected_body);
+        assert_eq!(
+            exchange.response.encoded_size,
+            expect_decoded.then(|| ByteSize::b(response_body.len() as u64))
+        );
+    }
+
+    /// DNS lookup should only be measured if there was one
+    #[rstest]
+    #[case::hostname("localhost", true)]
+    #[case::ip("127.0.0.1", false)]
+    #[tokio::test]
+    async fn test_timing(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+        #[case] host: &str,
+        #[case] expect_dns: bool,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let port = server.socket_address().port();
+        server
+            .mock("GET", "/get")
+            .with_body("hello!")
+            .create_async()
+            .await;
+
+        let recipe = Recipe {
+            url: format!("http://{host}:{port}/get").as_str().into(),
+            ..Recipe::factory(())
+        };
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let exchange = ticket.send(&template_context.database).await.unwrap();
+
+        let timing = exchange.timing.unwrap();
+        assert_eq!(timing.dns.is_some(), expect_dns);
+        // Plain HTTP, so connect and TLS can't be measured
+        assert_eq!(timing.connect, None);
+        assert_eq!(timing.tls, None);
+        assert_eq!(exchange.response.tls, None);
+    }
+
+    /// The `pre_request` hook can modify the request before it's sent, and the
+    /// `post_response` hook can modify the response before it's stored
+    #[rstest]
+    #[tokio::test]
+    async fn test_hooks(
+        http_engine: HttpEngine,
+        mut template_context: TemplateContext,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        let mock = server
+            .mock("GET", "/get")
+            .match_header("X-Signature", "1")
+            .with_body("hello!")
+            .create_async()
+            .await;
+        template_context.collection.hooks = Hooks {
+            // Args are templates, so they can use profile data
+            pre_request: Some(vec![
+                "sh".into(),
+                "-c".into(),
+                "echo '{\"headers\": {\"X-Signature\": \"'$0'\"}}'".into(),
+                "{{user_id}}".into(),
+            ]),
+            post_response: Some(vec![
+                "sh".into(),
+                "-c".into(),
+                "grep -q '\"body\":\"hello!\"' && echo '{\"body\": \"goodbye!\"}'"
+                    .into(),
+            ]),
+        };
+
+        let recipe = Recipe {
+            url: format!("{url}/get").as_str().into(),
+            ..Recipe::factory(())
+        };
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        // Record includes the header added by the hook
+        assert_eq!(
+            ticket.record().headers.get("X-Signature"),
+            Some(&HeaderValue::from_static("1"))
+        );
+        let exchange = ticket.send(&template_context.database).await.unwrap();
+
+        mock.assert();
+        assert_eq!(exchange.response.body.bytes(), b"goodbye!");
+    }
+
+    /// AWS signing happens after the `pre_request` hook, so headers added by
+    /// the hook are covered by the signature
+    #[rstest]
+    #[tokio::test]
+    async fn test_pre_request_hook_aws_sigv4(
+        http_engine: HttpEngine,
+        mut template_context: TemplateContext,
+    ) {
+        template_context.collection.hooks = Hooks {
+            pre_request: Some(vec![
+                "sh".into(),
+                "-c".into(),
+                "echo '{\"headers\": {\"X-Custom\": \"1\"}}'".into(),
+            ]),
+            post_response: None,
+        };
+        let recipe = Recipe {
+            authentication: Some(Authentication::AwsSigV4 {
+                access_key_id: "AKIDEXAMPLE".into(),
+                secret_access_key: "secret".into(),
+                session_token: None,
+                region: "us-east-1".into(),
+                service: "execute-api".into(),
+            }),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+
+        let headers = &ticket.record.headers;
+        assert_eq!(headers["x-custom"], "1");
+        let authorization = headers[header::AUTHORIZATION].to_str().unwrap();
+        assert!(
+            authorization.contains("SignedHeaders=host;x-amz-date;x-custom, "),
+            "{authorization}"
+        );
+    }
+
+    /// A failing hook should prevent the request from being built
+    #[rstest]
+    #[tokio::test]
+    async fn test_pre_request_hook_error(
+        http_engine: HttpEngine,
+        mut template_context: TemplateContext,
+    ) {
+        template_context.collection.hooks = Hooks {
+            pre_request: Some(vec![
+                "sh".into(),
+                "-c".into(),
+                "echo denied >&2; exit 1".into(),
+            ]),
+            post_response: None,
+        };
+        let seed =
+            RequestSeed::new(Recipe::factory(()), BuildOptions::default());
+        assert_err!(
+            http_engine
+                .build(seed, &template_context)
+                .await
+                .map(|ticket| ticket.record),
+            "Error in `pre_request` hook: Hook `sh` failed with exit status: 1: denied"
+        );
+    }
+
+    /// Response body should be written to the rendered `save_response_to`
+    /// path, with `status`, `date`, and `timestamp` fields available
+    #[rstest]
+    #[tokio::test]
+    async fn test_save_response(
+        mut template_context: TemplateContext,
+        temp_dir: TempDir,
+    ) {
+        let template: Template = format!(
+            "{}/{{{{user_id}}}}/{{{{date}}}}/{{{{status}}}}-{{{{timestamp}}}}.json",
+            temp_dir.display()
+        )
+        .as_str()
+        .into();
+        let recipe = Recipe {
+            save_response_to: Some(template),
+            ..Recipe::factory(())
+        };
+        template_context.collection.recipes =
+            indexmap! {recipe.id.clone() => recipe}.into();
+        let exchange = Exchange {
+            response: ResponseRecord {
+                status: StatusCode::CREATED,
+                body: br#"{"id":3}"#.to_vec().into(),
+                ..ResponseRecord::factory(())
+            }
+            .into(),
+            ..Exchange::factory(())
+        };
+
+        let path = exchange
+            .save_response(&template_context)
+            .await
+            .unwrap()
+            .expect("Response should be saved");
+        let start_time = exchange.start_time.with_timezone(&Local);
+        let expected_path = temp_dir
+            .join("1")
+            .join(start_time.format("%Y-%m-%d").to_string())
+            .join(format!(
+                "201-{}.json",
+                start_time.format("%Y-%m-%dT%H-%M-%S")
+            ));
+        assert_eq!(path, expected_path);
+        assert_eq!(std::fs::read(&path).unwrap(), br#"{"id":3}"#);
+    }
+
+    /// Nothing should be written if the recipe doesn't ask for it
+    #[rstest]
+    #[tokio::test]
+    async fn test_save_response_disabled(
+        mut template_context: TemplateContext,
+    ) {
+        let recipe = Recipe::factory(());
+        template_context.collection.recipes =
+            indexmap! {recipe.id.clone() => recipe}.into();
+        let exchange = Exchange::factory(());
+
+        assert_eq!(
+            exchange.save_response(&template_context).await.unwrap(),
+            None
+        );
+    }
+
+    /// Error responses shouldn't overwrite a previously saved body
+    #[rstest]
+    #[tokio::test]
+    async fn test_save_response_error_status(
+        mut template_context: TemplateContext,
+        temp_dir: TempDir,
+    ) {
+        let path = temp_dir.join("download.txt");
+        std::fs::write(&path, "good").unwrap();
+        let recipe = Recipe {
+            save_response_to: Some(path.to_str().unwrap().into()),
+            ..Recipe::factory(())
+        };
+        template_context.collection.recipes =
+            indexmap! {recipe.id.clone() => recipe}.into();
+        let exchange = Exchange {
+            response: ResponseRecord {
+                status: StatusCode::RANGE_NOT_SATISFIABLE,
+                body: b"bad".to_vec().into(),
+                ..ResponseRecord::factory(())
+            }
+            .into(),
+            ..Exchange::factory(())
+        };
+
+        assert_eq!(
+            exchange.save_response(&template_context).await.unwrap(),
+            None
+        );
+        assert_eq!(std::fs::read(&path).unwrap(), b"good");
+    }
+
+    /// The extra path fields don't override profile fields of the same name,
+    /// and don't leak into the context for later renders
+    #[rstest]
+    #[tokio::test]
+    async fn test_save_response_extra_fields(
+        mut template_context: TemplateContext,
+        temp_dir: TempDir,
+    ) {
+        let profile_id = template_context.selected_profile.clone().unwrap();
+        template_context
+            .collection
+            .profiles
+            .get_mut(&profile_id)
+            .unwrap()
+            .data
+            .insert("date".into(), "today".into());
+        let template: Template =
+            format!("{}/{{{{date}}}}-{{{{status}}}}.txt", temp_dir.display())
+                .as_str()
+                .into();
+        let recipe = Recipe {
+            save_response_to: Some(template),
+            ..Recipe::factory(())
+        };
+        template_context.collection.recipes =
+            indexmap! {recipe.id.clone() => recipe}.into();
+        let exchange = Exchange::factory(());
+
+        let path = exchange
+            .save_response(&template_context)
+            .await
+            .unwrap()
+            .expect("Response should be saved");
+        assert_eq!(path, temp_dir.join("today-200.txt"));
+        assert!(template_context.overrides.is_empty());
+        assert_err!(
+            Template::from("{{status}}")
+                .render_string(&template_context)
+                .await,
+            "Unknown field `status`"
         );
     }
 
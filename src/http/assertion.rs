@@ -0,0 +1,278 @@
+//! Check responses against the assertions defined on their recipe
+
+use crate::{
+    collection::Assertion,
+    http::{ContentType, Exchange, QueryError},
+};
+use reqwest::header::HeaderValue;
+use thiserror::Error;
+
+/// Why an assertion failed
+#[derive(Debug, Error)]
+pub enum AssertionError {
+    #[error("Expected status {expected}, got {actual}")]
+    Status { expected: u16, actual: u16 },
+
+    #[error("Expected header `{name}`, but it's missing")]
+    HeaderMissing { name: String },
+
+    #[error("Expected header `{name}` to equal `{expected}`, got `{actual}`")]
+    HeaderEquals {
+        name: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error(
+        "Expected header `{name}` to contain `{expected}`, got `{actual}`"
+    )]
+    HeaderContains {
+        name: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("Error parsing response body")]
+    Parse {
+        #[source]
+        error: anyhow::Error,
+    },
+
+    #[error("Error querying response body with `{query}`")]
+    Query {
+        query: String,
+        #[source]
+        error: QueryError,
+    },
+
+    #[error("Expected `{query}` to equal {expected}, got {actual}")]
+    JsonpathEquals {
+        query: String,
+        expected: serde_json::Value,
+        actual: serde_json::Value,
+    },
+
+    #[error("Expected latency of at most {max}ms, got {actual}ms")]
+    Latency { max: u128, actual: i64 },
+}
+
+impl Assertion {
+    /// Check this assertion against a completed exchange
+    pub fn check(&self, exchange: &Exchange) -> Result<(), AssertionError> {
+        let response = &exchange.response;
+        match self {
+            Self::Status(expected) => {
+                let actual = response.status.as_u16();
+                if actual == *expected {
+                    Ok(())
+                } else {
+                    Err(AssertionError::Status {
+                        expected: *expected,
+                        actual,
+                    })
+                }
+            }
+            Self::Header {
+                name,
+                equals,
+                contains,
+            } => {
+                let actual = response
+                    .headers
+                    .get(name.as_str())
+                    .map(header_to_string)
+                    .ok_or_else(|| AssertionError::HeaderMissing {
+                        name: name.clone(),
+                    })?;
+                if let Some(expected) =
+                    equals.as_ref().filter(|expected| **expected != actual)
+                {
+                    Err(AssertionError::HeaderEquals {
+                        name: name.clone(),
+                        expected: expected.clone(),
+                        actual,
+                    })
+                } else if let Some(expected) = contains
+                    .as_ref()
+                    .filter(|expected| !actual.contains(expected.as_str()))
+                {
+                    Err(AssertionError::HeaderContains {
+                        name: name.clone(),
+                        expected: expected.clone(),
+                        actual,
+                    })
+                } else {
+                    Ok(())
+                }
+            }
+            Self::Jsonpath { query, equals } => {
+                let content = ContentType::parse_response(response)
+                    .map_err(|error| AssertionError::Parse { error })?;
+                let actual = query.query_value(&*content).map_err(|error| {
+                    AssertionError::Query {
+                        query: query.to_string(),
+                        error,
+                    }
+                })?;
+                if &actual == equals {
+                    Ok(())
+                } else {
+                    Err(AssertionError::JsonpathEquals {
+                        query: query.to_string(),
+                        expected: equals.clone(),
+                        actual,
+                    })
+                }
+            }
+            Self::MaxLatency(max) => {
+                let actual = exchange.duration();
+                // A negative duration (clock skew) is always under the limit
+                if actual.to_std().map_or(true, |actual| actual <= *max) {
+                    Ok(())
+                } else {
+                    Err(AssertionError::Latency {
+                        max: max.as_millis(),
+                        actual: actual.num_milliseconds(),
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// Check every assertion against an exchange, and return the failures
+pub fn check_assertions(
+    assertions: &[Assertion],
+    exchange: &Exchange,
+) -> Vec<AssertionError> {
+    assertions
+        .iter()
+        .filter_map(|assertion| assertion.check(exchange).err())
+        .collect()
+}
+
+/// Header values aren't necessarily UTF-8, so convert them lossily
+fn header_to_string(value: &HeaderValue) -> String {
+    String::from_utf8_lossy(value.as_bytes()).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::ResponseRecord,
+        test_util::{assert_err, header_map, Factory},
+    };
+    use chrono::Duration;
+    use indexmap::indexmap;
+    use reqwest::StatusCode;
+    use rstest::{fixture, rstest};
+    use serde_json::json;
+
+    /// A 200 response with a JSON body, which took 100ms
+    #[fixture]
+    fn exchange() -> Exchange {
+        let exchange = Exchange::factory(());
+        Exchange {
+            response: ResponseRecord {
+                status: StatusCode::OK,
+                headers: header_map(indexmap! {
+                    "content-type" => "application/json",
+                }),
+                body: r#"{"id": 3, "name": "Barry"}"#.into(),
+                ..ResponseRecord::factory(())
+            }
+            .into(),
+            end_time: exchange.start_time + Duration::milliseconds(100),
+            ..exchange
+        }
+    }
+
+    fn header(
+        name: &str,
+        equals: Option<&str>,
+        contains: Option<&str>,
+    ) -> Assertion {
+        Assertion::Header {
+            name: name.into(),
+            equals: equals.map(String::from),
+            contains: contains.map(String::from),
+        }
+    }
+
+    fn jsonpath(query: &str, equals: serde_json::Value) -> Assertion {
+        Assertion::Jsonpath {
+            query: query.parse().unwrap(),
+            equals,
+        }
+    }
+
+    #[rstest]
+    #[case::status(Assertion::Status(200))]
+    #[case::header_present(header("content-type", None, None))]
+    #[case::header_equals(header(
+        "content-type",
+        Some("application/json"),
+        None
+    ))]
+    #[case::header_contains(header("Content-Type", None, Some("json")))]
+    #[case::jsonpath_number(jsonpath("$.id", json!(3)))]
+    #[case::jsonpath_string(jsonpath("$.name", json!("Barry")))]
+    #[case::latency(Assertion::MaxLatency(std::time::Duration::from_millis(
+        100
+    )))]
+    fn test_check_pass(exchange: Exchange, #[case] assertion: Assertion) {
+        assertion.check(&exchange).unwrap();
+    }
+
+    #[rstest]
+    #[case::status(Assertion::Status(201), "Expected status 201, got 200")]
+    #[case::header_missing(
+        header("x-fish", None, None),
+        "Expected header `x-fish`, but it's missing"
+    )]
+    #[case::header_equals(
+        header("content-type", Some("text/plain"), None),
+        "Expected header `content-type` to equal `text/plain`, \
+        got `application/json`"
+    )]
+    #[case::header_contains(
+        header("content-type", None, Some("xml")),
+        "Expected header `content-type` to contain `xml`, \
+        got `application/json`"
+    )]
+    #[case::jsonpath_type(
+        jsonpath("$.id", json!("3")),
+        r#"Expected `$.id` to equal "3", got 3"#
+    )]
+    #[case::jsonpath_no_results(
+        jsonpath("$.bogus", json!(3)),
+        "Expected exactly one result from query"
+    )]
+    #[case::latency(
+        Assertion::MaxLatency(std::time::Duration::from_millis(99)),
+        "Expected latency of at most 99ms, got 100ms"
+    )]
+    fn test_check_fail(
+        exchange: Exchange,
+        #[case] assertion: Assertion,
+        #[case] expected_error: &str,
+    ) {
+        assert_err!(assertion.check(&exchange), expected_error);
+    }
+
+    /// Only failures are returned
+    #[rstest]
+    fn test_check_assertions(exchange: Exchange) {
+        let assertions = [
+            Assertion::Status(200),
+            Assertion::Status(404),
+            jsonpath("$.id", json!(3)),
+        ];
+        let failures = check_assertions(&assertions, &exchange);
+        assert_eq!(
+            failures.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec!["Expected status 404, got 200"]
+        );
+    }
+}
@@ -0,0 +1,212 @@
+//! Compression for request bodies, and decompression for response bodies,
+//! according to the `Content-Encoding` header
+
+use crate::collection::ContentEncoding;
+use bytes::Bytes;
+use flate2::{
+    write::{GzDecoder, GzEncoder, ZlibDecoder, ZlibEncoder},
+    Compression,
+};
+use reqwest::{
+    header::{self, HeaderMap, HeaderValue},
+    Body, Request,
+};
+use std::{
+    io::{self, Write},
+    mem,
+};
+
+/// Buffer size for brotli streams, in bytes. 4KB is what the brotli crate
+/// uses by default
+const BROTLI_BUFFER_SIZE: usize = 4096;
+/// Brotli compression level, from 0-11. This is the level used by most
+/// servers for dynamic content, to balance speed and size
+const BROTLI_QUALITY: u32 = 5;
+/// Brotli window size, as a power of 2. This is the recommended default
+const BROTLI_WINDOW: u32 = 22;
+
+impl ContentEncoding {
+    /// Parse a single encoding from a header value. Return `None` for
+    /// unsupported encodings
+    fn from_header(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Some(Self::Gzip),
+            "deflate" => Some(Self::Deflate),
+            "br" => Some(Self::Brotli),
+            _ => None,
+        }
+    }
+
+    /// Compress a complete body with this encoding
+    pub(super) fn encode(self, data: &[u8]) -> Vec<u8> {
+        // Writing to a Vec can't fail
+        let result = match self {
+            Self::Gzip => {
+                let mut encoder =
+                    GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data).and_then(|()| encoder.finish())
+            }
+            Self::Deflate => {
+                let mut encoder =
+                    ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data).and_then(|()| encoder.finish())
+            }
+            Self::Brotli => {
+                let mut encoder = brotli::CompressorWriter::new(
+                    Vec::new(),
+                    BROTLI_BUFFER_SIZE,
+                    BROTLI_QUALITY,
+                    BROTLI_WINDOW,
+                );
+                encoder.write_all(data).map(|()| encoder.into_inner())
+            }
+        };
+        result.expect("Compressing into a Vec should not fail")
+    }
+}
+
+/// Decompresses a response body chunk-by-chunk, so it can be decoded while
+/// it's still being received
+pub(super) enum Decoder {
+    Gzip(GzDecoder<Vec<u8>>),
+    Deflate(ZlibDecoder<Vec<u8>>),
+    Brotli(Box<brotli::DecompressorWriter<Vec<u8>>>),
+}
+
+impl Decoder {
+    /// Get a decoder for a response's `Content-Encoding`. Return `None` if the
+    /// body isn't encoded, or uses an encoding we don't support. In the
+    /// latter case, the body is left as-is. Multiple layers of encoding are
+    /// exceedingly rare, so they're treated as unsupported.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let encoding = ContentEncoding::from_header(
+            headers.get(header::CONTENT_ENCODING)?.to_str().ok()?,
+        )?;
+        Some(match encoding {
+            ContentEncoding::Gzip => Self::Gzip(GzDecoder::new(Vec::new())),
+            ContentEncoding::Deflate => {
+                Self::Deflate(ZlibDecoder::new(Vec::new()))
+            }
+            ContentEncoding::Brotli => Self::Brotli(Box::new(
+                brotli::DecompressorWriter::new(Vec::new(), BROTLI_BUFFER_SIZE),
+            )),
+        })
+    }
+
+    /// Decode a chunk of the body. Returns whatever decoded content is
+    /// available so far, which may be empty
+    pub fn decode(&mut self, chunk: &[u8]) -> io::Result<Vec<u8>> {
+        let output = match self {
+            Self::Gzip(decoder) => {
+                decoder.write_all(chunk)?;
+                decoder.flush()?;
+                decoder.get_mut()
+            }
+            Self::Deflate(decoder) => {
+                decoder.write_all(chunk)?;
+                decoder.flush()?;
+                decoder.get_mut()
+            }
+            Self::Brotli(decoder) => {
+                decoder.write_all(chunk)?;
+                decoder.flush()?;
+                decoder.get_mut()
+            }
+        };
+        Ok(mem::take(output))
+    }
+
+    /// Finish decoding, returning any remaining content. Fails if the
+    /// encoded stream was incomplete
+    pub fn finish(self) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip(decoder) => decoder.finish(),
+            Self::Deflate(decoder) => decoder.finish(),
+            Self::Brotli(decoder) => decoder.into_inner().map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Incomplete brotli stream",
+                )
+            }),
+        }
+    }
+}
+
+/// Compress a request's body in place, and set the `Content-Encoding` header
+/// to match. Streamed bodies can't be compressed, and are left alone.
+pub(super) fn compress_request_body(
+    request: &mut Request,
+    encoding: ContentEncoding,
+) {
+    let Some(body) = request.body().and_then(Body::as_bytes) else {
+        return;
+    };
+    let compressed = Bytes::from(encoding.encode(body));
+    let headers = request.headers_mut();
+    headers.insert(
+        header::CONTENT_ENCODING,
+        HeaderValue::from_str(&encoding.to_string())
+            .expect("Encoding names are valid header values"),
+    );
+    if headers.contains_key(header::CONTENT_LENGTH) {
+        headers.insert(
+            header::CONTENT_LENGTH,
+            HeaderValue::from(compressed.len()),
+        );
+    }
+    *request.body_mut() = Some(compressed.into());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::header_map;
+    use rstest::rstest;
+
+    /// Encoded content should decode back to the original, even when fed to
+    /// the decoder in small chunks
+    #[rstest]
+    #[case::gzip(ContentEncoding::Gzip, "gzip")]
+    #[case::x_gzip(ContentEncoding::Gzip, "x-gzip")]
+    #[case::deflate(ContentEncoding::Deflate, "deflate")]
+    #[case::brotli(ContentEncoding::Brotli, "br")]
+    fn test_round_trip(
+        #[case] encoding: ContentEncoding,
+        #[case] header: &str,
+    ) {
+        let content = "hello world! ".repeat(100);
+        let encoded = encoding.encode(content.as_bytes());
+        assert!(encoded.len() < content.len());
+
+        let mut decoder =
+            Decoder::from_headers(&header_map([("content-encoding", header)]))
+                .unwrap();
+        let mut decoded = Vec::new();
+        for chunk in encoded.chunks(7) {
+            decoded.extend(decoder.decode(chunk).unwrap());
+        }
+        decoded.extend(decoder.finish().unwrap());
+        assert_eq!(std::str::from_utf8(&decoded).unwrap(), content);
+    }
+
+    #[rstest]
+    #[case::none(&[])]
+    #[case::identity(&[("content-encoding", "identity")])]
+    #[case::unknown(&[("content-encoding", "zstd")])]
+    #[case::multiple(&[("content-encoding", "gzip, br")])]
+    fn test_no_decoder(#[case] headers: &[(&str, &str)]) {
+        assert!(Decoder::from_headers(&header_map(headers.iter().copied()))
+            .is_none());
+    }
+
+    /// A truncated stream should fail when finished
+    #[test]
+    fn test_incomplete() {
+        let encoded = ContentEncoding::Gzip.encode(b"hello world");
+        let mut decoder =
+            Decoder::from_headers(&header_map([("content-encoding", "gzip")]))
+                .unwrap();
+        decoder.decode(&encoded[..encoded.len() - 4]).unwrap();
+        assert!(decoder.finish().is_err());
+    }
+}
@@ -5,11 +5,15 @@
 //! not a value, use [ContentType]. If you want to parse dynamically based on
 //! the response's metadata, use [ContentType::parse_response].
 
-use crate::{http::ResponseRecord, util::Mapping};
+use crate::{
+    http::{markup, ResponseRecord, SelectorType},
+    util::Mapping,
+};
 use anyhow::{anyhow, Context};
 use derive_more::{Deref, Display, From};
-use mime::{Mime, APPLICATION, JSON};
+use mime::{Mime, APPLICATION, HTML, JSON, TEXT, XML};
 use reqwest::header::{self, HeaderValue};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::{borrow::Cow, ffi::OsStr, fmt::Debug, path::Path};
 
@@ -24,10 +28,12 @@ use std::{borrow::Cow, ffi::OsStr, fmt::Debug, path::Path};
 ///
 /// For the serialization string, obviously use serde. For the others, use
 /// the corresponding methods/associated functions.
-#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ContentType {
     Json,
+    Xml,
+    Html,
 }
 
 /// A response content type that we know how to parse. This is defined as a
@@ -48,6 +54,8 @@ pub trait ResponseContent: Debug + Display + Send + Sync {
 
     /// Convert the content to JSON. JSON is the common language used for
     /// querying intenally, so everything needs to be convertible to/from JSON.
+    /// Markup formats don't map onto JSON, so they're converted to a single
+    /// string. They should be queried with XPath or CSS instead.
     fn to_json(&self) -> Cow<'_, serde_json::Value>;
 
     /// Facilitate downcasting generic parsed bodies to concrete types for tests
@@ -82,10 +90,71 @@ impl ResponseContent for Json {
     }
 }
 
+/// An XML document. The source text is stored as-is; it's only parsed to
+/// validate, prettify, or query it.
+#[derive(Debug, Display, PartialEq)]
+pub struct Xml(String);
+
+impl ResponseContent for Xml {
+    fn content_type(&self) -> ContentType {
+        ContentType::Xml
+    }
+
+    fn parse(body: &[u8]) -> anyhow::Result<Self> {
+        let text = std::str::from_utf8(body)?;
+        sxd_document::parser::parse(text)?;
+        Ok(Self(text.to_owned()))
+    }
+
+    fn prettify(&self) -> String {
+        // We validated the document during parsing, so this can't fail
+        markup::prettify_xml(&self.0).unwrap_or_else(|_| self.0.clone())
+    }
+
+    fn to_json(&self) -> Cow<'_, serde_json::Value> {
+        Cow::Owned(serde_json::Value::String(self.0.clone()))
+    }
+
+    #[cfg(test)]
+    fn as_any(&self) -> &dyn std::any::Any {
+        self as &dyn std::any::Any
+    }
+}
+
+/// An HTML document. HTML parsing never fails, so any UTF-8 text is accepted
+#[derive(Debug, Display, PartialEq)]
+pub struct Html(String);
+
+impl ResponseContent for Html {
+    fn content_type(&self) -> ContentType {
+        ContentType::Html
+    }
+
+    fn parse(body: &[u8]) -> anyhow::Result<Self> {
+        Ok(Self(std::str::from_utf8(body)?.to_owned()))
+    }
+
+    fn prettify(&self) -> String {
+        markup::prettify_html(&self.0)
+    }
+
+    fn to_json(&self) -> Cow<'_, serde_json::Value> {
+        Cow::Owned(serde_json::Value::String(self.0.clone()))
+    }
+
+    #[cfg(test)]
+    fn as_any(&self) -> &dyn std::any::Any {
+        self as &dyn std::any::Any
+    }
+}
+
 impl ContentType {
     /// File extensions for each content type
-    const EXTENSIONS: Mapping<'static, ContentType> =
-        Mapping::new(&[(Self::Json, &["json"])]);
+    const EXTENSIONS: Mapping<'static, ContentType> = Mapping::new(&[
+        (Self::Json, &["json"]),
+        (Self::Xml, &["xml"]),
+        (Self::Html, &["html", "htm"]),
+    ]);
 
     /// Get the file extension associated with this content type. For content
     /// types that have multiple common extensions (e.g. `image/jpeg` has `jpeg`
@@ -94,6 +163,15 @@ impl ContentType {
         Self::EXTENSIONS.get_label(*self)
     }
 
+    /// Get the query language used to filter content of this type in the TUI
+    pub fn selector_type(self) -> SelectorType {
+        match self {
+            Self::Json => SelectorType::Jsonpath,
+            Self::Xml => SelectorType::Xpath,
+            Self::Html => SelectorType::Css,
+        }
+    }
+
     /// Guess content type from a file path based on its extension
     pub fn from_path(path: &Path) -> anyhow::Result<Self> {
         let extension = path
@@ -125,6 +203,8 @@ impl ContentType {
     ) -> anyhow::Result<Box<dyn ResponseContent>> {
         match self {
             Self::Json => Ok(Box::new(Json::parse(content)?)),
+            Self::Xml => Ok(Box::new(Xml::parse(content)?)),
+            Self::Html => Ok(Box::new(Html::parse(content)?)),
         }
     }
 
@@ -132,12 +212,20 @@ impl ContentType {
     /// in any other format too, so this is infallible. This takes a `Cow`
     /// because some formats may need an owned JSON value while others may not.
     /// You should pass an owned value if you have it, but it's not necessary.
+    /// For markup formats, strings are used as the document text and anything
+    /// else is stringified as JSON.
     pub fn parse_json(
         self,
         content: Cow<'_, serde_json::Value>,
     ) -> Box<dyn ResponseContent> {
+        let text = || match content.as_ref() {
+            serde_json::Value::String(text) => text.clone(),
+            value => value.to_string(),
+        };
         match self {
             Self::Json => Box::new(Json(content.into_owned())),
+            Self::Xml => Box::new(Xml(text())),
+            Self::Html => Box::new(Html(text())),
         }
     }
 
@@ -152,7 +240,7 @@ impl ContentType {
 
     /// Parse the value of the content-type header and map it to a known content
     /// type
-    fn from_mime(mime_type: &str) -> anyhow::Result<Self> {
+    pub fn from_mime(mime_type: &str) -> anyhow::Result<Self> {
         let mime_type: Mime = mime_type
             .parse()
             .with_context(|| format!("Invalid content type `{mime_type}`"))?;
@@ -164,6 +252,9 @@ impl ContentType {
             (APPLICATION, JSON, _) | (APPLICATION, _, Some("json")) => {
                 Ok(Self::Json)
             }
+            // Same for XML, e.g. "application/soap+xml"
+            (APPLICATION | TEXT, XML, _) | (_, _, Some("xml")) => Ok(Self::Xml),
+            (TEXT, HTML, _) => Ok(Self::Html),
             _ => Err(anyhow!("Unknown content type `{mime_type}`")),
         }
     }
@@ -190,6 +281,10 @@ mod tests {
     )]
     // Test extended MIME type
     #[case::json_extended("application/geo+json", ContentType::Json)]
+    #[case::xml("application/xml", ContentType::Xml)]
+    #[case::xml_text("text/xml; charset=utf-8", ContentType::Xml)]
+    #[case::xml_extended("application/soap+xml", ContentType::Xml)]
+    #[case::html("text/html; charset=utf-8", ContentType::Html)]
     fn test_try_from_mime(
         #[case] mime_type: &str,
         #[case] expected: ContentType,
@@ -202,7 +297,7 @@ mod tests {
     #[case::invalid("json", "Invalid content type")]
     #[case::json_empty_extension("application/+json", "Unknown content type")]
     #[case::whitespace("application/ +json", "Invalid content type")]
-    #[case::unknown("text/html", "Unknown content type")]
+    #[case::unknown("text/plain", "Unknown content type")]
     fn test_try_from_mime_error(
         #[case] mime_type: &str,
         #[case] expected_error: &str,
@@ -216,6 +311,14 @@ mod tests {
             ContentType::from_path(Path::new("turbo.json")).unwrap(),
             ContentType::Json
         );
+        assert_eq!(
+            ContentType::from_path(Path::new("turbo.xml")).unwrap(),
+            ContentType::Xml
+        );
+        assert_eq!(
+            ContentType::from_path(Path::new("turbo.htm")).unwrap(),
+            ContentType::Html
+        );
 
         // Errors
         assert_err!(
@@ -235,6 +338,8 @@ mod tests {
         "{\"hello\": \"goodbye\"}",
         Json(json!({"hello": "goodbye"}))
     )]
+    #[case::xml("text/xml", "<a>hi</a>", Xml("<a>hi</a>".into()))]
+    #[case::html("text/html", "<p>hi", Html("<p>hi".into()))]
     fn test_parse_body<T: ResponseContent + PartialEq + 'static>(
         #[case] content_type: &str,
         #[case] body: &str,
@@ -271,6 +376,7 @@ mod tests {
         "not json!",
         "expected ident"
     )]
+    #[case::invalid_xml(Some("application/xml"), "<a>", "XML parsing error")]
     fn test_parse_body_error<
         T: TryInto<HeaderValue, Error = InvalidHeaderValue>,
     >(
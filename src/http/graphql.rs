@@ -0,0 +1,566 @@
+//! GraphQL support. A recipe marked with `graphql: true` has a GraphQL query
+//! as its body, which is wrapped in a JSON request when sent. The server's
+//! schema can be fetched with an introspection query, then used to complete
+//! and validate queries while they're edited.
+//!
+//! This doesn't include a full GraphQL parser. Queries are tokenized just
+//! enough to track which type each selection set belongs to, which is all
+//! that completion and field validation need.
+
+use anyhow::{anyhow, Context};
+use indexmap::IndexMap;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// Query to fetch a schema from a server. This only asks for what completion
+/// needs: the root types, and the fields of each type.
+pub const INTROSPECTION_QUERY: &str = "query IntrospectionQuery {
+  __schema {
+    queryType { name }
+    mutationType { name }
+    subscriptionType { name }
+    types {
+      name
+      fields(includeDeprecated: true) {
+        name
+        type { ...TypeRef }
+      }
+    }
+  }
+}
+
+fragment TypeRef on __Type {
+  name
+  ofType { name ofType { name ofType { name } } }
+}";
+
+/// Wrap a rendered query in the JSON body that GraphQL servers expect
+pub fn request_body(query: &[u8]) -> Vec<u8> {
+    let body = json!({"query": String::from_utf8_lossy(query)});
+    // Serializing a JSON value can't fail
+    serde_json::to_vec(&body).unwrap_or_default()
+}
+
+/// The parts of a GraphQL schema needed for completion. This is stored in the
+/// database, so it can be used without fetching it again.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct GraphQlSchema {
+    query_type: Option<String>,
+    mutation_type: Option<String>,
+    subscription_type: Option<String>,
+    /// Fields of every type that has them (objects and interfaces), mapped to
+    /// the name of the field's type. Lists and non-null wrappers are removed.
+    types: IndexMap<String, IndexMap<String, String>>,
+}
+
+impl GraphQlSchema {
+    /// Parse a schema from the response to [INTROSPECTION_QUERY]
+    pub fn from_introspection(body: &[u8]) -> anyhow::Result<Self> {
+        let response: IntrospectionResponse = serde_json::from_slice(body)
+            .context("Invalid introspection response")?;
+        let schema = match response.data {
+            Some(data) => data.schema,
+            None if response.errors.is_empty() => {
+                return Err(anyhow!("Introspection response has no data"))
+            }
+            None => {
+                return Err(anyhow!(
+                    "Introspection failed: {}",
+                    response
+                        .errors
+                        .iter()
+                        .map(|error| &error.message)
+                        .join("; ")
+                ))
+            }
+        };
+
+        let types = schema
+            .types
+            .into_iter()
+            // Skip built-in introspection types like `__Schema`
+            .filter(|typ| !typ.name.starts_with("__"))
+            .filter_map(|typ| {
+                let fields = typ
+                    .fields?
+                    .into_iter()
+                    .filter_map(|field| {
+                        let type_name = field.type_ref.name()?.to_owned();
+                        Some((field.name, type_name))
+                    })
+                    .collect();
+                Some((typ.name, fields))
+            })
+            .collect();
+        Ok(Self {
+            query_type: schema.query_type.map(|typ| typ.name),
+            mutation_type: schema.mutation_type.map(|typ| typ.name),
+            subscription_type: schema.subscription_type.map(|typ| typ.name),
+            types,
+        })
+    }
+
+    /// Number of types with fields
+    pub fn type_count(&self) -> usize {
+        self.types.len()
+    }
+
+    /// Get every field that could complete the name being typed at the end of
+    /// a partial query, e.g. `{ us` could complete to `users`. Only the text
+    /// before the cursor should be given.
+    pub fn complete(&self, query: &str) -> Vec<String> {
+        let mut tokens = tokenize(query);
+        // The name being typed, if the query ends in the middle of one
+        let partial = match tokens.last() {
+            Some(Token::Name(name)) if query.ends_with(name) => {
+                let name = *name;
+                tokens.pop();
+                name
+            }
+            _ => "",
+        };
+        // Names after these aren't fields
+        if let Some(
+            Token::Spread | Token::Name("on") | Token::Punct('@' | '$'),
+        ) = tokens.last()
+        {
+            return Vec::new();
+        }
+
+        let walk = self.walk(&tokens);
+        let Some(Some(parent)) = walk.stack.last().filter(|_| walk.depth == 0)
+        else {
+            return Vec::new();
+        };
+        let Some(fields) = self.types.get(parent) else {
+            return Vec::new();
+        };
+        fields
+            .keys()
+            .filter(|field| field.starts_with(partial) && *field != partial)
+            .cloned()
+            .collect()
+    }
+
+    /// Check that every field in a query exists on its parent type, and that
+    /// braces are balanced. Return the first problem found. Fields of types
+    /// that aren't in the schema aren't checked.
+    pub fn validate(&self, query: &str) -> Result<(), String> {
+        let walk = self.walk(&tokenize(query));
+        if let Some(error) = walk.errors.into_iter().next() {
+            Err(error)
+        } else if !walk.stack.is_empty() {
+            Err("Unclosed `{`".into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Walk through a query, tracking which type each selection set belongs to
+    fn walk(&self, tokens: &[Token]) -> Walk {
+        let mut walk = Walk::default();
+        let mut tokens = tokens.iter().copied().peekable();
+        while let Some(token) = tokens.next() {
+            // Arguments and variable definitions can't contain fields
+            if walk.depth > 0 {
+                match token {
+                    Token::Punct('(') => walk.depth += 1,
+                    Token::Punct(')') => walk.depth -= 1,
+                    _ => {}
+                }
+                continue;
+            }
+
+            match token {
+                Token::Punct('(') => walk.depth += 1,
+                Token::Punct('{') => {
+                    // A bare selection set at the top level is a query
+                    let typ = walk.next_type.take().or_else(|| {
+                        walk.stack
+                            .is_empty()
+                            .then(|| self.query_type.clone())
+                            .flatten()
+                    });
+                    walk.stack.push(typ);
+                }
+                Token::Punct('}') => {
+                    if walk.stack.pop().is_none() {
+                        walk.errors.push("Unexpected `}`".into());
+                    }
+                    walk.next_type = None;
+                }
+                // Skip directive and variable names
+                Token::Punct('@' | '$') => {
+                    tokens.next_if(|token| matches!(token, Token::Name(_)));
+                }
+                Token::Punct(_) => {}
+                Token::Spread => match tokens.peek() {
+                    // Inline fragment: `... on User { name }`
+                    Some(Token::Name("on")) => {
+                        tokens.next();
+                        walk.next_type = type_name(tokens.next());
+                    }
+                    // Fragment spread: `...userFields`
+                    Some(Token::Name(_)) => {
+                        tokens.next();
+                    }
+                    // Inline fragment without a type: `... @include { name }`
+                    _ => walk.next_type = walk.stack.last().cloned().flatten(),
+                },
+                Token::Name(name) if walk.stack.is_empty() => match name {
+                    "query" => walk.next_type = self.query_type.clone(),
+                    "mutation" => walk.next_type = self.mutation_type.clone(),
+                    "subscription" => {
+                        walk.next_type = self.subscription_type.clone()
+                    }
+                    "fragment" => {
+                        // `fragment userFields on User`
+                        tokens.next();
+                        if tokens.next_if_eq(&Token::Name("on")).is_some() {
+                            walk.next_type = type_name(tokens.next());
+                        }
+                    }
+                    // Operation name
+                    _ => {}
+                },
+                Token::Name(name) => {
+                    // `alias: field`
+                    let field =
+                        if tokens.next_if_eq(&Token::Punct(':')).is_some() {
+                            match tokens.next() {
+                                Some(Token::Name(field)) => field,
+                                _ => continue,
+                            }
+                        } else {
+                            name
+                        };
+                    let parent = walk.stack.last().cloned().flatten();
+                    walk.next_type =
+                        self.field_type(parent.as_deref(), field, &mut walk);
+                }
+            }
+        }
+        walk
+    }
+
+    /// Get the type of a field. If the parent type is in the schema but
+    /// doesn't have the field, record an error
+    fn field_type(
+        &self,
+        parent: Option<&str>,
+        field: &str,
+        walk: &mut Walk,
+    ) -> Option<String> {
+        let parent = parent?;
+        // Every type has this implicitly
+        if field == "__typename" {
+            return Some("String".into());
+        }
+        let fields = self.types.get(parent)?;
+        match fields.get(field) {
+            Some(typ) => Some(typ.clone()),
+            None => {
+                walk.errors.push(format!(
+                    "Unknown field `{field}` on type `{parent}`"
+                ));
+                None
+            }
+        }
+    }
+}
+
+/// State of a walk through the tokens of a query
+#[derive(Debug, Default)]
+struct Walk {
+    /// Type of each open selection set, innermost last. `None` if the type
+    /// isn't known
+    stack: Vec<Option<String>>,
+    /// Type of the next selection set to be opened, i.e. the type of the
+    /// previous field
+    next_type: Option<String>,
+    /// Depth of nested parentheses
+    depth: usize,
+    errors: Vec<String>,
+}
+
+fn type_name(token: Option<Token>) -> Option<String> {
+    match token {
+        Some(Token::Name(name)) => Some(name.to_owned()),
+        _ => None,
+    }
+}
+
+/// The tokens of a query that matter for tracking selection sets. Strings,
+/// comments, numbers, and template keys are dropped.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Token<'a> {
+    Name(&'a str),
+    Punct(char),
+    Spread,
+}
+
+fn tokenize(query: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < query.len() {
+        let rest = &query[i..];
+        let bytes = rest.as_bytes();
+        i += match bytes[0] {
+            b'#' => rest.find('\n').unwrap_or(rest.len()),
+            // Template keys, e.g. `{{user_id}}`. Two braces in a row are never
+            // valid GraphQL, so this can't be a selection set
+            b'{' if rest.starts_with("{{") => {
+                rest.find("}}").map_or(rest.len(), |end| end + 2)
+            }
+            b'"' if rest.starts_with(r#"""""#) => {
+                rest[3..].find(r#"""""#).map_or(rest.len(), |end| end + 6)
+            }
+            b'"' => {
+                // Strings end at an unescaped quote, or the end of the line
+                let mut end = 1;
+                while end < bytes.len()
+                    && bytes[end] != b'"'
+                    && bytes[end] != b'\n'
+                {
+                    if bytes[end] == b'\\' {
+                        end += 1;
+                    }
+                    end += 1;
+                }
+                (end + 1).min(rest.len())
+            }
+            b'.' if rest.starts_with("...") => {
+                tokens.push(Token::Spread);
+                3
+            }
+            c if c == b'_' || c.is_ascii_alphabetic() => {
+                let len = rest
+                    .find(|c: char| !(c == '_' || c.is_ascii_alphanumeric()))
+                    .unwrap_or(rest.len());
+                tokens.push(Token::Name(&rest[..len]));
+                len
+            }
+            c @ (b'{' | b'}' | b'(' | b')' | b':' | b'@' | b'$') => {
+                tokens.push(Token::Punct(c.into()));
+                1
+            }
+            // Whitespace, commas, numbers, etc.
+            _ => rest.chars().next().map_or(1, char::len_utf8),
+        };
+    }
+    tokens
+}
+
+#[derive(Deserialize)]
+struct IntrospectionResponse {
+    data: Option<IntrospectionData>,
+    #[serde(default)]
+    errors: Vec<ResponseError>,
+}
+
+#[derive(Deserialize)]
+struct ResponseError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct IntrospectionData {
+    #[serde(rename = "__schema")]
+    schema: SchemaData,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SchemaData {
+    query_type: Option<NamedType>,
+    mutation_type: Option<NamedType>,
+    subscription_type: Option<NamedType>,
+    types: Vec<TypeData>,
+}
+
+#[derive(Deserialize)]
+struct NamedType {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct TypeData {
+    name: String,
+    /// `null` for anything other than objects and interfaces
+    fields: Option<Vec<FieldData>>,
+}
+
+#[derive(Deserialize)]
+struct FieldData {
+    name: String,
+    #[serde(rename = "type")]
+    type_ref: TypeRef,
+}
+
+/// A possibly wrapped type. Lists and non-null types have no name, just the
+/// type they wrap
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TypeRef {
+    name: Option<String>,
+    of_type: Option<Box<TypeRef>>,
+}
+
+impl TypeRef {
+    /// Get the name of the innermost type
+    fn name(&self) -> Option<&str> {
+        match &self.name {
+            Some(name) => Some(name),
+            None => self.of_type.as_deref()?.name(),
+        }
+    }
+}
+
+/// A schema with users, and a mutation to delete them
+#[cfg(test)]
+impl crate::test_util::Factory for GraphQlSchema {
+    fn factory(_: ()) -> Self {
+        let body = json!({
+            "data": {
+                "__schema": {
+                    "queryType": {"name": "Query"},
+                    "mutationType": {"name": "Mutation"},
+                    "subscriptionType": null,
+                    "types": [
+                        {
+                            "name": "Query",
+                            "fields": [
+                                {"name": "user", "type": {"name": "User"}},
+                                {
+                                    "name": "users",
+                                    "type": {
+                                        "name": null,
+                                        "ofType": {
+                                            "name": null,
+                                            "ofType": {"name": "User"}
+                                        }
+                                    }
+                                },
+                            ]
+                        },
+                        {
+                            "name": "Mutation",
+                            "fields": [
+                                {"name": "deleteUser", "type": {"name": "Boolean"}},
+                            ]
+                        },
+                        {
+                            "name": "User",
+                            "fields": [
+                                {"name": "id", "type": {"name": "ID"}},
+                                {"name": "name", "type": {"name": "String"}},
+                                {"name": "friends", "type": {"name": "User"}},
+                            ]
+                        },
+                        {"name": "String", "fields": null},
+                        {"name": "__Schema", "fields": []},
+                    ]
+                }
+            }
+        });
+        GraphQlSchema::from_introspection(&serde_json::to_vec(&body).unwrap())
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_err, Factory};
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn schema() -> GraphQlSchema {
+        GraphQlSchema::factory(())
+    }
+
+    /// Wrapped types are unwrapped, and types without fields are dropped
+    #[rstest]
+    fn test_from_introspection(schema: GraphQlSchema) {
+        assert_eq!(schema.type_count(), 3);
+        assert_eq!(schema.types["Query"]["users"], "User");
+    }
+
+    /// Errors from the server are reported when there's no data
+    #[rstest]
+    #[case::errors(
+        json!({"errors": [{"message": "Introspection is disabled"}]}),
+        "Introspection failed: Introspection is disabled"
+    )]
+    #[case::no_data(json!({}), "Introspection response has no data")]
+    #[case::invalid(json!([]), "Invalid introspection response")]
+    fn test_from_introspection_error(
+        #[case] body: serde_json::Value,
+        #[case] expected_error: &str,
+    ) {
+        assert_err!(
+            GraphQlSchema::from_introspection(
+                &serde_json::to_vec(&body).unwrap()
+            ),
+            expected_error
+        );
+    }
+
+    #[rstest]
+    #[case::shorthand("{ us", &["user", "users"])]
+    #[case::empty("{ ", &["user", "users"])]
+    #[case::nested("query { users { id na", &["name"])]
+    #[case::after_sibling("query { users { id friends { id } ", &["id", "name", "friends"])]
+    #[case::mutation("mutation { del", &["deleteUser"])]
+    #[case::alias("{ u: use", &["user", "users"])]
+    #[case::fragment("fragment f on User { fr", &["friends"])]
+    #[case::inline_fragment("{ user { ... on User { i", &["id"])]
+    #[case::exact_match("{ user", &["users"])]
+    #[case::arguments("{ user(id: \"{{id}}\") { i", &["id"])]
+    #[case::in_arguments("{ user(i", &[])]
+    #[case::spread("{ user { ...f", &[])]
+    #[case::top_level("que", &[])]
+    #[case::unknown_parent("{ user { name { ", &[])]
+    fn test_complete(
+        schema: GraphQlSchema,
+        #[case] query: &str,
+        #[case] expected: &[&str],
+    ) {
+        assert_eq!(schema.complete(query), expected);
+    }
+
+    #[rstest]
+    #[case::valid("query Users { users { id name friends { id } } }", Ok(()))]
+    #[case::comments_strings(
+        "# { bogus\n{ user(id: \"}\") { id } }",
+        Ok(())
+    )]
+    #[case::typename("{ __typename user { __typename } }", Ok(()))]
+    #[case::unknown_field(
+        "{ users { id email } }",
+        Err("Unknown field `email` on type `User`")
+    )]
+    #[case::unknown_root(
+        "mutation { users }",
+        Err("Unknown field `users` on type `Mutation`")
+    )]
+    #[case::unclosed("{ users { id }", Err("Unclosed `{`"))]
+    #[case::extra_close("{ users } }", Err("Unexpected `}`"))]
+    fn test_validate(
+        schema: GraphQlSchema,
+        #[case] query: &str,
+        #[case] expected: Result<(), &str>,
+    ) {
+        assert_eq!(schema.validate(query), expected.map_err(String::from));
+    }
+
+    #[test]
+    fn test_request_body() {
+        assert_eq!(
+            request_body(b"{ users { id } }"),
+            br#"{"query":"{ users { id } }"}"#
+        );
+    }
+}
@@ -0,0 +1,404 @@
+//! Request hooks: external commands that can inspect and modify requests
+//! before they're sent, and responses after they're received. Hooks talk JSON
+//! over stdin/stdout, so they can be written in any language.
+
+use crate::{
+    collection::RecipeId,
+    http::{RequestRecord, ResponseBody, ResponseRecord},
+    template::{Template, TemplateContext},
+};
+use anyhow::{anyhow, bail, Context};
+use bytes::Bytes;
+use futures::future;
+use indexmap::IndexMap;
+use reqwest::{
+    header::{self, HeaderMap, HeaderName, HeaderValue},
+    Body, Method, Request, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use std::{io, process::Stdio};
+use tokio::{io::AsyncWriteExt, process::Command};
+use tracing::{debug, debug_span};
+use url::Url;
+
+/// A hook command, with each argument rendered
+#[derive(Clone, Debug)]
+pub(super) struct HookCommand(Vec<String>);
+
+impl HookCommand {
+    /// Render each argument of a hook command
+    pub async fn render(
+        command: &[Template],
+        context: &TemplateContext,
+    ) -> anyhow::Result<Self> {
+        let command = future::try_join_all(command.iter().enumerate().map(
+            |(i, template)| async move {
+                template
+                    .render_string(context)
+                    .await
+                    .with_context(|| format!("Error rendering `command[{i}]`"))
+            },
+        ))
+        .await?;
+        Ok(Self(command))
+    }
+
+    /// Run the `pre_request` hook on a request, and apply any changes it
+    /// returns
+    pub async fn pre_request(
+        &self,
+        recipe_id: &RecipeId,
+        request: &mut Request,
+    ) -> anyhow::Result<()> {
+        let input = PreRequestInput {
+            request: HookRequest {
+                recipe_id,
+                method: request.method(),
+                url: request.url(),
+                headers: headers_to_map(request.headers()),
+                body: request
+                    .body()
+                    .and_then(Body::as_bytes)
+                    .and_then(|body| std::str::from_utf8(body).ok()),
+            },
+        };
+        let Some(patch) = self.run::<_, RequestPatch>(&input).await? else {
+            return Ok(());
+        };
+
+        if let Some(method) = patch.method {
+            *request.method_mut() = method
+                .parse()
+                .with_context(|| format!("Invalid method `{method}`"))?;
+        }
+        if let Some(url) = patch.url {
+            *request.url_mut() = url
+                .parse()
+                .with_context(|| format!("Invalid URL `{url}`"))?;
+        }
+        apply_headers(request.headers_mut(), patch.headers)?;
+        if let Some(body) = patch.body {
+            let headers = request.headers_mut();
+            if headers.contains_key(header::CONTENT_LENGTH) {
+                headers.insert(
+                    header::CONTENT_LENGTH,
+                    HeaderValue::from(body.len()),
+                );
+            }
+            *request.body_mut() = Some(body.into());
+        }
+        Ok(())
+    }
+
+    /// Run the `post_response` hook on a response, and apply any changes it
+    /// returns
+    pub async fn post_response(
+        &self,
+        request: &RequestRecord,
+        response: &mut ResponseRecord,
+    ) -> anyhow::Result<()> {
+        let input = PostResponseInput {
+            request: HookRequest {
+                recipe_id: &request.recipe_id,
+                method: &request.method,
+                url: &request.url,
+                headers: headers_to_map(&request.headers),
+                body: request.body.as_ref().and_then(ResponseBody::text),
+            },
+            response: HookResponse {
+                status: response.status.as_u16(),
+                headers: headers_to_map(&response.headers),
+                // Don't pass a preview off as the full body
+                body: Some(&response.body)
+                    .filter(|body| !body.is_truncated())
+                    .and_then(ResponseBody::text),
+            },
+        };
+        let Some(patch) = self.run::<_, ResponsePatch>(&input).await? else {
+            return Ok(());
+        };
+
+        if let Some(status) = patch.status {
+            response.status = StatusCode::from_u16(status)
+                .with_context(|| format!("Invalid status `{status}`"))?;
+        }
+        apply_headers(&mut response.headers, patch.headers)?;
+        if let Some(body) = patch.body {
+            response.body = ResponseBody::new(Bytes::from(body));
+        }
+        Ok(())
+    }
+
+    /// Run the command, passing the input as JSON on stdin. Return `None` if
+    /// the command printed nothing, meaning no changes
+    async fn run<I: Serialize, O: for<'de> Deserialize<'de>>(
+        &self,
+        input: &I,
+    ) -> anyhow::Result<Option<O>> {
+        let [program, args @ ..] = self.0.as_slice() else {
+            bail!("Hook command must have at least one element");
+        };
+        let _ = debug_span!("Executing hook", command = ?self.0).entered();
+        let error_context = || format!("Error executing hook `{program}`");
+
+        let mut process = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(error_context)?;
+        let input = serde_json::to_vec(input)?;
+        let write_result = process
+            .stdin
+            .take()
+            .expect("Process missing stdin")
+            .write_all(&input)
+            .await;
+        // It's fine if the hook doesn't bother reading its input
+        match write_result {
+            Err(error) if error.kind() != io::ErrorKind::BrokenPipe => {
+                return Err(error).with_context(error_context);
+            }
+            _ => {}
+        }
+        let output = process
+            .wait_with_output()
+            .await
+            .with_context(error_context)?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        debug!(%stdout, %stderr, status = %output.status, "Hook finished");
+        if !output.status.success() {
+            return Err(anyhow!("{}", stderr.trim()).context(format!(
+                "Hook `{program}` failed with {}",
+                output.status
+            )));
+        }
+
+        if stdout.trim().is_empty() {
+            Ok(None)
+        } else {
+            serde_json::from_str(&stdout).map(Some).with_context(|| {
+                format!("Invalid output from hook `{program}`")
+            })
+        }
+    }
+}
+
+/// Input to the `pre_request` hook
+#[derive(Debug, Serialize)]
+struct PreRequestInput<'a> {
+    request: HookRequest<'a>,
+}
+
+/// Input to the `post_response` hook
+#[derive(Debug, Serialize)]
+struct PostResponseInput<'a> {
+    request: HookRequest<'a>,
+    response: HookResponse<'a>,
+}
+
+#[derive(Debug, Serialize)]
+struct HookRequest<'a> {
+    recipe_id: &'a RecipeId,
+    #[serde(serialize_with = "serialize_method")]
+    method: &'a Method,
+    url: &'a Url,
+    headers: IndexMap<&'a str, String>,
+    /// `None` if there's no body, or it isn't UTF-8, or it's streamed
+    body: Option<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+struct HookResponse<'a> {
+    status: u16,
+    headers: IndexMap<&'a str, String>,
+    /// `None` if the body isn't UTF-8, or is too large to hold in memory
+    body: Option<&'a str>,
+}
+
+/// Changes to a request, returned by the `pre_request` hook. Any omitted field
+/// is left unchanged.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RequestPatch {
+    method: Option<String>,
+    url: Option<String>,
+    headers: IndexMap<String, Option<String>>,
+    body: Option<String>,
+}
+
+/// Changes to a response, returned by the `post_response` hook. Any omitted
+/// field is left unchanged.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ResponsePatch {
+    status: Option<u16>,
+    headers: IndexMap<String, Option<String>>,
+    body: Option<String>,
+}
+
+fn serialize_method<S: serde::Serializer>(
+    method: &Method,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(method.as_str())
+}
+
+/// Convert headers to a map for hook input. Repeated headers are joined into
+/// a single value, and non-UTF-8 values are converted lossily.
+fn headers_to_map(headers: &HeaderMap) -> IndexMap<&str, String> {
+    let mut map: IndexMap<&str, String> = IndexMap::new();
+    for (name, value) in headers {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        map.entry(name.as_str())
+            .and_modify(|existing| {
+                existing.push_str(", ");
+                existing.push_str(&value);
+            })
+            .or_insert_with(|| value.into_owned());
+    }
+    map
+}
+
+/// Apply header changes from a hook. A value replaces the header, and `null`
+/// removes it.
+fn apply_headers(
+    headers: &mut HeaderMap,
+    changes: IndexMap<String, Option<String>>,
+) -> anyhow::Result<()> {
+    for (name, value) in changes {
+        let name: HeaderName = name
+            .parse()
+            .with_context(|| format!("Invalid header name `{name}`"))?;
+        match value {
+            Some(value) => {
+                let value: HeaderValue = value.parse().with_context(|| {
+                    format!("Invalid value for header `{name}`")
+                })?;
+                headers.insert(name, value);
+            }
+            None => {
+                headers.remove(name);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_err, header_map, Factory};
+    use rstest::rstest;
+
+    /// Build a hook that runs a shell script
+    fn script(script: &str) -> HookCommand {
+        HookCommand(vec!["sh".into(), "-c".into(), script.into()])
+    }
+
+    /// Changes from the hook should be applied to the request
+    #[tokio::test]
+    async fn test_pre_request() {
+        let mut request =
+            Request::new(Method::GET, "http://localhost/get".parse().unwrap());
+        request
+            .headers_mut()
+            .extend(header_map([("x-remove", "gone"), ("x-keep", "kept")]));
+        let hook = script(
+            r#"grep -q '"url":"http://localhost/get"' && echo '{
+                "method": "POST",
+                "url": "http://localhost/post",
+                "headers": {"x-signature": "abc", "x-remove": null},
+                "body": "signed"
+            }'"#,
+        );
+        hook.pre_request(&"recipe1".into(), &mut request)
+            .await
+            .unwrap();
+
+        assert_eq!(request.method(), Method::POST);
+        assert_eq!(request.url().as_str(), "http://localhost/post");
+        assert_eq!(
+            request.headers(),
+            &header_map([("x-keep", "kept"), ("x-signature", "abc")])
+        );
+        assert_eq!(
+            request.body().and_then(Body::as_bytes),
+            Some(b"signed".as_slice())
+        );
+    }
+
+    /// Changes from the hook should be applied to the response, and the hook
+    /// should see both the request and response
+    #[tokio::test]
+    async fn test_post_response() {
+        let request = RequestRecord::factory(());
+        let mut response = ResponseRecord {
+            headers: header_map([("content-type", "text/plain")]),
+            body: ResponseBody::new("hello".into()),
+            ..ResponseRecord::factory(())
+        };
+        let hook = script(
+            r#"input=$(cat)
+            echo "$input" | grep -q '"recipe_id":"' || exit 1
+            echo "$input" | grep -q '"body":"hello"' || exit 1
+            echo '{"status": 201, "headers": {"x-audited": "yes"}}'"#,
+        );
+        hook.post_response(&request, &mut response).await.unwrap();
+
+        assert_eq!(response.status, StatusCode::CREATED);
+        assert_eq!(
+            response.headers,
+            header_map([("content-type", "text/plain"), ("x-audited", "yes")])
+        );
+        // Body wasn't included in the patch, so it's unchanged
+        assert_eq!(response.body.bytes(), b"hello");
+    }
+
+    #[rstest]
+    #[case::no_output("true", None)]
+    #[case::whitespace_output("echo", None)]
+    #[case::failure("echo oh no >&2; exit 3", Some("oh no"))]
+    #[case::invalid_output("echo nope", Some("Invalid output from hook"))]
+    #[case::unknown_field(
+        r#"echo '{"bogus": 1}'"#,
+        Some("Invalid output from hook")
+    )]
+    #[case::invalid_header(
+        r#"echo '{"headers": {"bad header": "x"}}'"#,
+        Some("Invalid header name `bad header`")
+    )]
+    #[tokio::test]
+    async fn test_pre_request_errors(
+        #[case] script_text: &str,
+        #[case] expected_error: Option<&str>,
+    ) {
+        let mut request =
+            Request::new(Method::GET, "http://localhost/".parse().unwrap());
+        let result = script(script_text)
+            .pre_request(&"recipe1".into(), &mut request)
+            .await;
+        match expected_error {
+            Some(expected) => assert_err!(result, expected),
+            None => {
+                result.unwrap();
+                assert_eq!(request.url().as_str(), "http://localhost/");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_empty_command() {
+        let mut request =
+            Request::new(Method::GET, "http://localhost/".parse().unwrap());
+        assert_err!(
+            HookCommand(vec![])
+                .pre_request(&"recipe1".into(), &mut request)
+                .await,
+            "Hook command must have at least one element"
+        );
+    }
+}
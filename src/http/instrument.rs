@@ -0,0 +1,182 @@
+//! Instrumentation for new connections. reqwest doesn't expose any details
+//! about its connections, so we hook into the pieces that we can customize:
+//! the DNS resolver and the TLS config (see [tls](super::tls)). Each hook
+//! records an event for whichever request is currently opening the connection,
+//! which is tracked with a task-local.
+
+use crate::http::{tls, Timing, TlsInfo};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use rustls::{pki_types::CertificateDer, CipherSuite, ProtocolVersion};
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+};
+use tokio::time::Instant;
+
+tokio::task_local! {
+    /// Connection events for the request being polled. Not set while polling
+    /// anything else, e.g. a connection that's opened in the background
+    static CONNECTION_EVENTS: Arc<Mutex<ConnectionEvents>>;
+}
+
+/// Everything we learned while opening a connection. Everything is empty if
+/// the request reused a pooled connection
+#[derive(Debug, Default)]
+pub(super) struct ConnectionEvents {
+    pub dns_start: Option<Instant>,
+    pub dns_end: Option<Instant>,
+    /// When the TLS ClientHello was built, which happens as soon as the TCP
+    /// connection is open
+    pub tls_start: Option<Instant>,
+    pub tls_end: Option<Instant>,
+    /// Certificate chain sent by the server
+    pub certificates: Vec<CertificateDer<'static>>,
+    /// Negotiated TLS version and cipher suite
+    pub cipher_suite: Option<(ProtocolVersion, CipherSuite)>,
+}
+
+/// Record a connection event for the current request, if any
+pub(super) fn record(f: impl FnOnce(&mut ConnectionEvents)) {
+    let _ = CONNECTION_EVENTS.try_with(|events| {
+        f(&mut events.lock().expect("Connection events lock poisoned"))
+    });
+}
+
+/// Measures a single attempt at a request, and captures details of the
+/// connection it opened
+#[derive(Debug)]
+pub(super) struct AttemptRecorder {
+    start: Instant,
+    headers_received: Option<Instant>,
+    connection: Arc<Mutex<ConnectionEvents>>,
+}
+
+impl AttemptRecorder {
+    /// Start the clock
+    pub fn start() -> Self {
+        Self {
+            start: Instant::now(),
+            headers_received: None,
+            connection: Default::default(),
+        }
+    }
+
+    /// Run a future that sends the request and waits for the response
+    /// headers. Any connection opened while it runs is attributed to this
+    /// request.
+    pub async fn send<F: Future>(&mut self, future: F) -> F::Output {
+        let output = CONNECTION_EVENTS
+            .scope(Arc::clone(&self.connection), future)
+            .await;
+        self.headers_received = Some(Instant::now());
+        output
+    }
+
+    /// Get details of the TLS connection, if the request opened one
+    pub fn tls_info(&self) -> Option<TlsInfo> {
+        let connection = self
+            .connection
+            .lock()
+            .expect("Connection events lock poisoned");
+        let (protocol, cipher_suite) = connection.cipher_suite?;
+        Some(tls::tls_info(
+            protocol,
+            cipher_suite,
+            &connection.certificates,
+        ))
+    }
+
+    /// Stop the clock, after the response body has been received. Return
+    /// `None` if the request never got a response
+    pub fn finish(self, succeeded: bool) -> Option<Timing> {
+        let headers_received = self.headers_received.filter(|_| succeeded)?;
+        let end = Instant::now();
+        let connection = self.connection.lock().expect("Timing lock poisoned");
+
+        let dns = connection
+            .dns_start
+            .zip(connection.dns_end)
+            .map(|(start, end)| end.saturating_duration_since(start));
+        let connect = connection.tls_start.map(|tls_start| {
+            tls_start.saturating_duration_since(
+                connection.dns_end.unwrap_or(self.start),
+            )
+        });
+        let tls = connection
+            .tls_start
+            .zip(connection.tls_end)
+            .map(|(start, end)| end.saturating_duration_since(start));
+        // Anything we couldn't measure gets lumped in with the wait
+        let ready = connection
+            .tls_end
+            .or(connection.dns_end)
+            .unwrap_or(self.start);
+        Some(Timing {
+            dns,
+            connect,
+            tls,
+            wait: headers_received.saturating_duration_since(ready),
+            download: end.saturating_duration_since(headers_received),
+        })
+    }
+}
+
+/// DNS resolver that records how long lookups take. Lookups are done with the
+/// system resolver, same as reqwest's default resolver
+#[derive(Debug, Default)]
+pub(super) struct TimingResolver;
+
+impl Resolve for TimingResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        record(|events| events.dns_start = Some(Instant::now()));
+        Box::pin(async move {
+            // Port is replaced by the connector
+            let addrs =
+                tokio::net::lookup_host((name.as_str().to_owned(), 0)).await?;
+            record(|events| events.dns_end = Some(Instant::now()));
+            Ok(Box::new(addrs) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Phases that weren't measured are left out, and their time is counted
+    /// as part of the wait
+    #[tokio::test(start_paused = true)]
+    async fn test_recorder() {
+        let mut recorder = AttemptRecorder::start();
+        recorder
+            .send(async {
+                record(|events| events.dns_start = Some(Instant::now()));
+                tokio::time::advance(Duration::from_millis(10)).await;
+                record(|events| events.dns_end = Some(Instant::now()));
+                tokio::time::advance(Duration::from_millis(20)).await;
+            })
+            .await;
+        tokio::time::advance(Duration::from_millis(30)).await;
+
+        assert_eq!(
+            recorder.finish(true),
+            Some(Timing {
+                dns: Some(Duration::from_millis(10)),
+                connect: None,
+                tls: None,
+                wait: Duration::from_millis(20),
+                download: Duration::from_millis(30),
+            })
+        );
+    }
+
+    /// Events outside the recorder's scope aren't recorded
+    #[tokio::test(start_paused = true)]
+    async fn test_recorder_out_of_scope() {
+        let mut recorder = AttemptRecorder::start();
+        record(|events| events.dns_start = Some(Instant::now()));
+        recorder.send(async {}).await;
+        assert_eq!(recorder.finish(true).unwrap().dns, None);
+    }
+}
@@ -0,0 +1,411 @@
+//! Pretty printing for XML and HTML. Documents (or query results) are
+//! converted to a simplified tree of [Node]s, which is then printed with one
+//! element per line.
+
+use scraper::ElementRef;
+use sxd_document::dom::{self, ChildOfElement, ChildOfRoot, ParentOfChild};
+use sxd_xpath::nodeset;
+
+/// HTML elements that never have children or a closing tag
+const HTML_VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta",
+    "param", "source", "track", "wbr",
+];
+
+/// HTML elements whose text content is printed as-is, without escaping
+const HTML_RAW_TEXT_ELEMENTS: &[&str] = &["script", "style"];
+
+/// The flavor of markup being printed. This only affects how empty elements
+/// are written.
+#[derive(Copy, Clone, Debug)]
+pub enum Syntax {
+    Xml,
+    Html,
+}
+
+/// One node in a markup tree
+#[derive(Debug)]
+pub enum Node {
+    Element {
+        /// Tag name, including namespace prefix
+        name: String,
+        /// Attribute names and *unescaped* values
+        attributes: Vec<(String, String)>,
+        children: Vec<Node>,
+    },
+    /// Unescaped text content. Will be escaped when printed
+    Text(String),
+    /// Content to be printed as-is, such as comments or `<script>` contents
+    Verbatim(String),
+}
+
+impl Node {
+    /// Is this a text node with nothing but whitespace?
+    fn is_blank(&self) -> bool {
+        match self {
+            Self::Element { .. } => false,
+            Self::Text(text) | Self::Verbatim(text) => text.trim().is_empty(),
+        }
+    }
+}
+
+/// Prettify an XML document. Fails if the document isn't valid XML
+pub fn prettify_xml(xml: &str) -> anyhow::Result<String> {
+    let package = sxd_document::parser::parse(xml)?;
+    let document = package.as_document();
+    // The parser doesn't retain the declaration, so grab it from the source
+    let declaration = xml
+        .trim_start()
+        .strip_prefix("<?xml ")
+        .and_then(|rest| rest.split_once("?>"))
+        .map(|(declaration, _)| {
+            Node::Verbatim(format!("<?xml {}?>", declaration.trim()))
+        });
+    let nodes: Vec<Node> = declaration
+        .into_iter()
+        .chain(document.root().children().into_iter().map(
+            |child| match child {
+                ChildOfRoot::Element(element) => xml_element(element),
+                ChildOfRoot::Comment(comment) => xml_comment(comment),
+                ChildOfRoot::ProcessingInstruction(instruction) => {
+                    xml_instruction(instruction)
+                }
+            },
+        ))
+        .collect();
+    Ok(print(&nodes, Syntax::Xml))
+}
+
+/// Prettify an HTML document. HTML parsing is lenient, so this can't fail
+pub fn prettify_html(html: &str) -> String {
+    let document = scraper::Html::parse_document(html);
+    let nodes: Vec<Node> = document
+        .tree
+        .root()
+        .children()
+        .filter_map(|child| match ElementRef::wrap(child) {
+            Some(element) => Some(html_element(element)),
+            None => html_leaf(child.value(), false),
+        })
+        .collect();
+    print(&nodes, Syntax::Html)
+}
+
+/// Convert a node from an XPath result set
+pub fn xpath_node(node: nodeset::Node) -> Node {
+    match node {
+        nodeset::Node::Root(root) => Node::Element {
+            // The root isn't a real element, but this is a rare case. Just
+            // give it a name so its children can be printed
+            name: "root".into(),
+            attributes: Vec::new(),
+            children: root
+                .children()
+                .into_iter()
+                .filter_map(ChildOfRoot::element)
+                .map(xml_element)
+                .collect(),
+        },
+        nodeset::Node::Element(element) => xml_element(element),
+        nodeset::Node::Attribute(attribute) => {
+            Node::Text(attribute.value().to_owned())
+        }
+        nodeset::Node::Text(text) => Node::Text(text.text().to_owned()),
+        nodeset::Node::Comment(comment) => xml_comment(comment),
+        nodeset::Node::Namespace(namespace) => {
+            Node::Text(namespace.uri().to_owned())
+        }
+        nodeset::Node::ProcessingInstruction(instruction) => {
+            xml_instruction(instruction)
+        }
+    }
+}
+
+/// Convert an HTML element and all its descendants
+pub fn html_element(element: ElementRef) -> Node {
+    let value = element.value();
+    let raw = HTML_RAW_TEXT_ELEMENTS.contains(&value.name());
+    Node::Element {
+        name: value.name().to_owned(),
+        attributes: value
+            .attrs()
+            .map(|(name, value)| (name.to_owned(), value.to_owned()))
+            .collect(),
+        children: element
+            .children()
+            .filter_map(|child| match ElementRef::wrap(child) {
+                Some(element) => Some(html_element(element)),
+                None => html_leaf(child.value(), raw),
+            })
+            .collect(),
+    }
+}
+
+/// Convert a non-element HTML node. Returns `None` for nodes that can't be
+/// printed, which shouldn't appear in a parsed tree anyway
+fn html_leaf(node: &scraper::Node, raw: bool) -> Option<Node> {
+    match node {
+        scraper::Node::Text(text) if raw => {
+            Some(Node::Verbatim(text.to_string()))
+        }
+        scraper::Node::Text(text) => Some(Node::Text(text.to_string())),
+        scraper::Node::Comment(comment) => {
+            Some(Node::Verbatim(format!("<!--{}-->", &**comment)))
+        }
+        scraper::Node::Doctype(doctype) => {
+            Some(Node::Verbatim(format!("<!DOCTYPE {}>", doctype.name())))
+        }
+        scraper::Node::ProcessingInstruction(instruction) => {
+            Some(Node::Verbatim(format!(
+                "<?{} {}?>",
+                instruction.target, instruction.data
+            )))
+        }
+        scraper::Node::Document
+        | scraper::Node::Fragment
+        | scraper::Node::Element(_) => None,
+    }
+}
+
+/// Convert an XML element and all its descendants
+fn xml_element(element: dom::Element) -> Node {
+    let mut attributes = namespace_declarations(element);
+    attributes.extend(element.attributes().into_iter().map(|attribute| {
+        (
+            qualified_name(
+                attribute.preferred_prefix(),
+                attribute.name().local_part(),
+            ),
+            attribute.value().to_owned(),
+        )
+    }));
+    let mut children: Vec<Node> = Vec::new();
+    for child in element.children() {
+        match (child, children.last_mut()) {
+            // The parser splits text at entity references (e.g. `&amp;`), so
+            // stitch it back together
+            (ChildOfElement::Text(text), Some(Node::Text(previous))) => {
+                previous.push_str(text.text());
+            }
+            (ChildOfElement::Text(text), _) => {
+                children.push(Node::Text(text.text().to_owned()))
+            }
+            (ChildOfElement::Element(element), _) => {
+                children.push(xml_element(element))
+            }
+            (ChildOfElement::Comment(comment), _) => {
+                children.push(xml_comment(comment))
+            }
+            (ChildOfElement::ProcessingInstruction(instruction), _) => {
+                children.push(xml_instruction(instruction))
+            }
+        }
+    }
+    Node::Element {
+        name: qualified_name(
+            element.preferred_prefix(),
+            element.name().local_part(),
+        ),
+        attributes,
+        children,
+    }
+}
+
+/// Get the `xmlns` attributes for the namespaces first declared on an
+/// element. The parser consumes these, so we have to rebuild them by
+/// comparing against the parent's namespaces.
+fn namespace_declarations(element: dom::Element) -> Vec<(String, String)> {
+    let parent = match element.parent() {
+        Some(ParentOfChild::Element(parent)) => Some(parent),
+        _ => None,
+    };
+    let mut declarations = Vec::new();
+
+    if let Some(default_uri) = element.default_namespace_uri() {
+        let inherited_uri =
+            parent.and_then(|parent| parent.recursive_default_namespace_uri());
+        if inherited_uri != Some(default_uri) {
+            declarations.push(("xmlns".into(), default_uri.to_owned()));
+        }
+    }
+
+    let parent_namespaces = parent
+        .map(|parent| parent.namespaces_in_scope())
+        .unwrap_or_default();
+    for namespace in element.namespaces_in_scope() {
+        let inherited = parent_namespaces.iter().any(|parent_namespace| {
+            parent_namespace.prefix() == namespace.prefix()
+                && parent_namespace.uri() == namespace.uri()
+        });
+        // The `xml` prefix is implicitly declared everywhere
+        if !inherited && namespace.prefix() != "xml" {
+            declarations.push((
+                format!("xmlns:{}", namespace.prefix()),
+                namespace.uri().to_owned(),
+            ));
+        }
+    }
+    declarations
+}
+
+fn xml_comment(comment: dom::Comment) -> Node {
+    Node::Verbatim(format!("<!--{}-->", comment.text()))
+}
+
+fn xml_instruction(instruction: dom::ProcessingInstruction) -> Node {
+    Node::Verbatim(match instruction.value() {
+        Some(value) => format!("<?{} {value}?>", instruction.target()),
+        None => format!("<?{}?>", instruction.target()),
+    })
+}
+
+/// Join a namespace prefix and local name, e.g. `soap:Body`
+fn qualified_name(prefix: Option<&str>, local_part: &str) -> String {
+    match prefix {
+        Some(prefix) => format!("{prefix}:{local_part}"),
+        None => local_part.to_owned(),
+    }
+}
+
+/// Print a list of nodes, one element per line with nested elements indented.
+/// Elements containing only text are kept on a single line.
+pub fn print(nodes: &[Node], syntax: Syntax) -> String {
+    let mut lines = Vec::new();
+    for node in nodes {
+        print_node(&mut lines, node, syntax, 0);
+    }
+    lines.join("\n")
+}
+
+fn print_node(
+    lines: &mut Vec<String>,
+    node: &Node,
+    syntax: Syntax,
+    depth: usize,
+) {
+    let indent = "  ".repeat(depth);
+    match node {
+        Node::Text(text) => {
+            let text = text.trim();
+            if !text.is_empty() {
+                lines.push(format!("{indent}{}", escape(text, false)));
+            }
+        }
+        Node::Verbatim(text) => {
+            let text = text.trim();
+            if !text.is_empty() {
+                lines.push(format!("{indent}{text}"));
+            }
+        }
+        Node::Element {
+            name,
+            attributes,
+            children,
+        } => {
+            let mut open = format!("{indent}<{name}");
+            for (name, value) in attributes {
+                open.push_str(&format!(" {name}=\"{}\"", escape(value, true)));
+            }
+            let children: Vec<&Node> =
+                children.iter().filter(|child| !child.is_blank()).collect();
+            match (children.as_slice(), syntax) {
+                ([], Syntax::Xml) => lines.push(format!("{open}/>")),
+                ([], Syntax::Html)
+                    if HTML_VOID_ELEMENTS.contains(&name.as_str()) =>
+                {
+                    lines.push(format!("{open}>"))
+                }
+                ([], Syntax::Html) => lines.push(format!("{open}></{name}>")),
+                // Keep short text content on the same line as its tags
+                ([Node::Text(text)], _) if !text.trim().contains('\n') => lines
+                    .push(format!(
+                        "{open}>{}</{name}>",
+                        escape(text.trim(), false)
+                    )),
+                ([Node::Verbatim(text)], _) if !text.trim().contains('\n') => {
+                    lines.push(format!("{open}>{}</{name}>", text.trim()))
+                }
+                (children, _) => {
+                    lines.push(format!("{open}>"));
+                    for child in children {
+                        print_node(lines, child, syntax, depth + 1);
+                    }
+                    lines.push(format!("{indent}</{name}>"));
+                }
+            }
+        }
+    }
+}
+
+/// Escape special characters in text or an attribute value
+fn escape(text: &str, attribute: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' if !attribute => escaped.push_str("&gt;"),
+            '"' if attribute => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_prettify_xml() {
+        let xml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+            <soap:Envelope xmlns:soap=\"urn:soap\">\
+            <soap:Body><!-- hi --><User xmlns=\"urn:users\" id=\"3\">\
+            <Name>Fish &amp; Chips</Name><Roles/></User></soap:Body>\
+            </soap:Envelope>";
+        let expected = [
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            r#"<soap:Envelope xmlns:soap="urn:soap">"#,
+            r#"  <soap:Body>"#,
+            r#"    <!-- hi -->"#,
+            r#"    <User xmlns="urn:users" id="3">"#,
+            r#"      <Name>Fish &amp; Chips</Name>"#,
+            r#"      <Roles/>"#,
+            r#"    </User>"#,
+            r#"  </soap:Body>"#,
+            r#"</soap:Envelope>"#,
+        ];
+        assert_eq!(prettify_xml(xml).unwrap(), expected.join("\n"));
+    }
+
+    #[test]
+    fn test_prettify_xml_invalid() {
+        assert!(prettify_xml("<a>").is_err());
+    }
+
+    #[test]
+    fn test_prettify_html() {
+        let html = "<!DOCTYPE html><html><head><title>Hi</title>\
+            <script>if (a < b) {}</script></head><body>\
+            <p class=\"a&quot;b\">Hello<br>World</p><div></div></body></html>";
+        let expected = [
+            r#"<!DOCTYPE html>"#,
+            r#"<html>"#,
+            r#"  <head>"#,
+            r#"    <title>Hi</title>"#,
+            r#"    <script>if (a < b) {}</script>"#,
+            r#"  </head>"#,
+            r#"  <body>"#,
+            r#"    <p class="a&quot;b">"#,
+            r#"      Hello"#,
+            r#"      <br>"#,
+            r#"      World"#,
+            r#"    </p>"#,
+            r#"    <div></div>"#,
+            r#"  </body>"#,
+            r#"</html>"#,
+        ];
+        assert_eq!(prettify_html(html), expected.join("\n"));
+    }
+}
@@ -4,28 +4,43 @@
 //! exchange is incomplete or failed.
 
 use crate::{
-    collection::{ProfileId, Recipe, RecipeId},
-    http::{cereal, ContentType, ResponseContent},
-    util::ResultExt,
+    collection::{ProfileId, Recipe, RecipeId, RetryPolicy},
+    http::{
+        cereal, hook::HookCommand, rate_limit::TokenBucket, tls::TlsInfoCache,
+        ContentType, EventStream, EventStreamListener, ResponseContent,
+        SelectorType,
+    },
+    template::Template,
+    util::{format_duration, MaybeStr, ResultExt},
 };
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 use bytes::Bytes;
 use bytesize::ByteSize;
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
 use derive_more::{Display, From};
 use mime::Mime;
 use reqwest::{
-    header::{self, HeaderMap},
-    Client, Method, Request, StatusCode, Url,
+    header::{self, HeaderMap, HeaderValue},
+    Client, Method, Request, StatusCode, Url, Version,
 };
+use reqwest_cookie_store::CookieStoreMutex;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::{
+    cmp::Reverse,
     collections::HashSet,
-    fmt::{Debug, Write},
+    env,
+    fmt::{self, Debug, Write},
+    fs, io,
+    path::{Path, PathBuf},
     sync::{Arc, OnceLock},
+    time,
 };
 use thiserror::Error;
-use tracing::error;
+use tokio::{
+    io::{AsyncWrite, AsyncWriteExt},
+    sync::Semaphore,
+};
 use uuid::Uuid;
 
 /// Unique ID for a single request. Can also be used to refer to the
@@ -86,27 +101,160 @@ pub struct BuildOptions {
     /// Which query parameters should be excluded?  A blacklist allows the
     /// default to be "include all".
     pub disabled_query_parameters: HashSet<String>,
+    /// Path to a partially downloaded response body from a previous attempt.
+    /// If given, a `Range` header will be added to request just the remainder
+    /// of the body, which will then be appended to the existing content.
+    pub resume_download: Option<PathBuf>,
+    /// Replace the recipe's body with this template, e.g. after the user
+    /// edits it in the TUI. Always sent as a raw body.
+    pub body: Option<Template>,
 }
 
+/// Placeholder for sensitive values that have been redacted from a request
+/// record. Matches the placeholder used in template previews
+pub const REDACTED: &str = "<sensitive>";
+
 /// A request ready to be launched into through the stratosphere. This is
 /// basically a two-part ticket: the request is the part we'll hand to the HTTP
 /// engine to be launched, and the record is the ticket stub we'll keep for
 /// ourselves (to display to the user).
 pub struct RequestTicket {
-    /// A record of the request that we can hang onto and persist
+    /// A record of the request that we can hang onto and persist. Sensitive
+    /// values are redacted
     pub(super) record: Arc<RequestRecord>,
+    /// The record *before* redaction, if there was anything to redact
+    pub(super) unredacted_record: Option<RequestRecord>,
     /// reqwest client that should be used to launch the request
     pub(super) client: Client,
     /// Our brave little astronaut, ready to be launched...
     pub(super) request: Request,
+    /// Options the request was built with, so it can be rebuilt the same way
+    /// if it fails. Boxed to keep [RequestError] small.
+    pub(super) options: Box<BuildOptions>,
+    /// Body content received by a previous attempt at this request, from
+    /// [BuildOptions::resume_download]. If the server responds with `206
+    /// Partial Content`, the new content will be appended to this.
+    pub(super) resume_download: Option<ResumeDownload>,
+    /// Max rate to receive the response body at, in bytes per second
+    pub(super) download_limit: Option<ByteSize>,
+    /// Response bodies larger than this are spooled to a temp file instead of
+    /// being held in memory
+    pub(super) large_body_size: ByteSize,
+    /// Decode the response body according to its `Content-Encoding`?
+    pub(super) decompress: bool,
+    /// Policy for retrying failed attempts. `None` to never retry
+    pub(super) retry: Option<RetryPolicy>,
+    /// Credentials for digest authentication. These can't be attached until
+    /// the server sends a challenge, so they're held until the request is sent
+    pub(super) digest: Option<DigestCredentials>,
+    /// Cookie store to save to the DB once the response is received. `None`
+    /// if cookie persistence is disabled
+    pub(super) cookie_store: Option<Arc<CookieStoreMutex>>,
+    /// Notified as events arrive if the response is an event stream. See
+    /// [Self::on_event_stream]
+    pub(super) event_stream_listener: Option<EventStreamListener>,
+    /// Previous response that the request is conditional upon, if the recipe
+    /// is [conditional](Recipe::conditional). If the server responds `304 Not
+    /// Modified`, this response's body is used.
+    pub(super) cached_response: Option<Arc<ResponseRecord>>,
+    /// Reuse a previous response to the same request if it was received
+    /// within this long ago. See [Recipe::cache_ttl]
+    pub(super) cache_ttl: Option<std::time::Duration>,
+    /// [Fingerprint](RequestRecord::fingerprint) of the unredacted request,
+    /// used to find a cached response. Only computed if there's a cache TTL
+    pub(super) fingerprint: Option<String>,
+    /// Bucket to take a token from before each attempt, if the host is rate
+    /// limited
+    pub(super) rate_limiter: Option<Arc<TokenBucket>>,
+    /// Slots for requests in flight, shared with the engine, if concurrent
+    /// requests are limited
+    pub(super) request_slots: Option<Arc<Semaphore>>,
+    /// Notified when the request starts and stops waiting for a slot. See
+    /// [Self::on_queued]
+    pub(super) queue_listener: Option<QueueListener>,
+    /// TLS details for each server, shared with the engine
+    pub(super) tls_info: Arc<TlsInfoCache>,
+    /// Collection's `post_response` hook, if any
+    pub(super) post_response_hook: Option<HookCommand>,
+    /// Store the exchange in request history once it's complete? See
+    /// [Self::without_history]
+    pub(super) persist: bool,
+}
+
+/// A partially downloaded response body, to be prepended to the rest of the
+/// body. The file is streamed into the new response rather than loaded up
+/// front, so large downloads can still be spooled to disk.
+#[derive(Debug)]
+pub(super) struct ResumeDownload {
+    pub path: PathBuf,
+    /// Number of bytes requested to be skipped. Only this much of the file is
+    /// used, in case it grew after the request was built.
+    pub offset: u64,
+}
+
+/// Username and password for HTTP Digest authentication
+#[derive(Debug)]
+pub(super) struct DigestCredentials {
+    pub username: String,
+    pub password: Option<String>,
 }
 
 impl RequestTicket {
     pub fn record(&self) -> &Arc<RequestRecord> {
         &self.record
     }
+
+    /// Get the request record *without* sensitive values redacted. Only use
+    /// this for output that has to be usable as-is, e.g. a curl command
+    /// printed by the CLI. Anything that's displayed or stored should use
+    /// [Self::record].
+    pub fn unredacted_record(&self) -> &RequestRecord {
+        self.unredacted_record.as_ref().unwrap_or(&self.record)
+    }
+
+    /// Take the built request out of the ticket, without sending it. This is
+    /// for protocols that start with an HTTP request but don't use reqwest to
+    /// send it, i.e. the WebSocket handshake.
+    pub fn into_request(self) -> (Arc<RequestRecord>, Request) {
+        (self.record, self.request)
+    }
+
+    /// Watch the response as it arrives, if it turns out to be an event stream
+    /// (`text/event-stream`). The listener is called when the response headers
+    /// are received, and again as each chunk of the body arrives. Without a
+    /// listener, events are only visible once the server closes the stream.
+    pub fn on_event_stream(
+        mut self,
+        listener: impl Fn(&Arc<EventStream>) + Send + Sync + 'static,
+    ) -> Self {
+        self.event_stream_listener = Some(Box::new(listener));
+        self
+    }
+
+    /// Watch for the request waiting on the engine's concurrency limit. If no
+    /// slot is free when the request is sent, the listener is called with
+    /// `true`, then with `false` once the request gets a slot. If a slot is
+    /// free right away, the listener isn't called at all.
+    pub fn on_queued(
+        mut self,
+        listener: impl Fn(bool) + Send + Sync + 'static,
+    ) -> Self {
+        self.queue_listener = Some(Box::new(listener));
+        self
+    }
+
+    /// Don't store the exchange in request history. This is for requests
+    /// that are sent on the recipe's behalf but aren't really *its* requests,
+    /// e.g. GraphQL introspection, so they don't shadow its latest response.
+    pub fn without_history(mut self) -> Self {
+        self.persist = false;
+        self
+    }
 }
 
+/// Callback for [RequestTicket::on_queued]
+pub type QueueListener = Box<dyn Fn(bool) + Send + Sync>;
+
 /// A complete request+response pairing. This is generated by
 /// [RequestTicket::send] when a response is received successfully for a sent
 /// request.
@@ -123,6 +271,12 @@ pub struct Exchange {
     pub start_time: DateTime<Utc>,
     /// When did we finish receiving the *entire* response?
     pub end_time: DateTime<Utc>,
+    /// Earlier attempts at this request that failed and were retried. The
+    /// timestamps above are for the final attempt.
+    pub retries: Vec<RetryAttempt>,
+    /// Breakdown of where the time went for the final attempt. `None` for
+    /// cached responses, and exchanges from before timing was tracked
+    pub timing: Option<Timing>,
 }
 
 impl Exchange {
@@ -130,22 +284,116 @@ impl Exchange {
     pub fn duration(&self) -> Duration {
         self.end_time - self.start_time
     }
+
+    /// Render the exchange as a Markdown document, for sharing (e.g. in a bug
+    /// report). Includes the request as a curl command, the response status
+    /// and headers, the response body (prettified if possible), and timing.
+    pub fn to_markdown(&self) -> anyhow::Result<String> {
+        let request = &self.request;
+        let response = &self.response;
+        let mut buf = String::new();
+
+        // These writes are all infallible because we're writing to a string,
+        // but use ? because it's shorter than unwrap()
+        writeln!(
+            &mut buf,
+            "## `{} {}` → {}\n",
+            request.method, request.url, response.status
+        )?;
+        writeln!(&mut buf, "- Recipe: `{}`", request.recipe_id)?;
+        if let Some(profile_id) = &request.profile_id {
+            writeln!(&mut buf, "- Profile: `{profile_id}`")?;
+        }
+        writeln!(
+            &mut buf,
+            "- Sent: {}",
+            self.start_time.to_rfc3339_opts(SecondsFormat::Secs, true)
+        )?;
+        writeln!(
+            &mut buf,
+            "- Duration: {}",
+            format_duration(&self.duration())
+        )?;
+
+        writeln!(&mut buf, "\n### Request\n")?;
+        write_code_block(&mut buf, "sh", &request.to_curl()?)?;
+
+        writeln!(&mut buf, "\n### Response\n")?;
+        if response.headers.is_empty() {
+            writeln!(&mut buf, "No headers")?;
+        } else {
+            writeln!(&mut buf, "| Header | Value |")?;
+            writeln!(&mut buf, "| ------ | ----- |")?;
+            for (name, value) in &response.headers {
+                let value = String::from_utf8_lossy(value.as_bytes());
+                // Pipes would end the table cell early
+                writeln!(
+                    &mut buf,
+                    "| {name} | {} |",
+                    value.replace('|', "\\|")
+                )?;
+            }
+        }
+        writeln!(&mut buf)?;
+
+        let body = &response.body;
+        if let Some(parsed) = body.parsed() {
+            let language = parsed.content_type().extension();
+            write_code_block(&mut buf, language, &parsed.prettify())?;
+        } else if body.bytes().is_empty() {
+            writeln!(&mut buf, "*Empty body*")?;
+        } else if body.is_truncated() {
+            writeln!(&mut buf, "*Large body ({}) omitted*", body.size())?;
+        } else if let Some(text) = body.text() {
+            write_code_block(&mut buf, "", text)?;
+        } else {
+            writeln!(&mut buf, "*Binary body ({}) omitted*", body.size())?;
+        }
+
+        Ok(buf)
+    }
+}
+
+/// Write a fenced Markdown code block. The fence is made longer than any
+/// run of backticks in the content, so the content can't close it early.
+fn write_code_block(
+    buf: &mut String,
+    language: &str,
+    content: &str,
+) -> fmt::Result {
+    let longest_run =
+        content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat((longest_run + 1).max(3));
+    writeln!(buf, "{fence}{language}")?;
+    writeln!(buf, "{}", content.trim_end_matches('\n'))?;
+    writeln!(buf, "{fence}")
 }
 
 /// Metadata about an exchange. Useful in lists where request/response content
 /// isn't needed.
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub struct ExchangeSummary {
     pub id: RequestId,
+    pub profile_id: Option<ProfileId>,
+    pub recipe_id: RecipeId,
     pub start_time: DateTime<Utc>,
     pub end_time: DateTime<Utc>,
     pub status: StatusCode,
 }
 
+impl ExchangeSummary {
+    /// Get the elapsed time for this request
+    pub fn duration(&self) -> Duration {
+        self.end_time - self.start_time
+    }
+}
+
 impl From<&Exchange> for ExchangeSummary {
     fn from(exchange: &Exchange) -> Self {
         Self {
             id: exchange.id,
+            profile_id: exchange.request.profile_id.clone(),
+            recipe_id: exchange.request.recipe_id.clone(),
             start_time: exchange.start_time,
             end_time: exchange.end_time,
             status: exchange.response.status,
@@ -153,6 +401,169 @@ impl From<&Exchange> for ExchangeSummary {
     }
 }
 
+/// Outcome of one recipe in a folder run, where each recipe in a folder is
+/// sent in order
+#[derive(Clone, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct RunResult {
+    pub recipe_id: RecipeId,
+    pub outcome: RunOutcome,
+    /// Elapsed time for the request. `None` if it was never sent
+    pub duration: Option<Duration>,
+}
+
+/// What happened to a recipe in a folder run
+#[derive(Clone, Debug, Display)]
+#[cfg_attr(test, derive(PartialEq))]
+pub enum RunOutcome {
+    /// A response was received, although it may have an error status
+    Response(StatusCode),
+    /// The request failed to build or send
+    #[display("error: {_0}")]
+    Error(String),
+    /// An earlier request failed, so this one was never sent
+    #[display("skipped")]
+    Skipped,
+}
+
+impl RunResult {
+    /// A recipe that wasn't sent because the run stopped early
+    pub fn skipped(recipe_id: RecipeId) -> Self {
+        Self {
+            recipe_id,
+            outcome: RunOutcome::Skipped,
+            duration: None,
+        }
+    }
+
+    /// Did the request get a non-error response? Skipped recipes count as
+    /// failures, because they weren't verified
+    pub fn is_success(&self) -> bool {
+        matches!(
+            self.outcome,
+            RunOutcome::Response(status)
+                if !status.is_client_error() && !status.is_server_error()
+        )
+    }
+}
+
+impl From<&Exchange> for RunResult {
+    fn from(exchange: &Exchange) -> Self {
+        Self {
+            recipe_id: exchange.request.recipe_id.clone(),
+            outcome: RunOutcome::Response(exchange.response.status),
+            duration: Some(exchange.duration()),
+        }
+    }
+}
+
+impl From<&RequestError> for RunResult {
+    fn from(error: &RequestError) -> Self {
+        Self {
+            recipe_id: error.request.recipe_id.clone(),
+            outcome: RunOutcome::Error(format!("{:#}", error.error)),
+            duration: Some(error.end_time - error.start_time),
+        }
+    }
+}
+
+impl From<&RequestBuildError> for RunResult {
+    fn from(error: &RequestBuildError) -> Self {
+        Self {
+            recipe_id: error.recipe_id.clone(),
+            outcome: RunOutcome::Error(format!("{:#}", error.error)),
+            duration: None,
+        }
+    }
+}
+
+/// Time spent in each phase of a request. Connection phases are only measured
+/// if a new connection was opened for the request. They're `None` if a pooled
+/// connection was reused, or if the phase doesn't apply (e.g. DNS for an IP
+/// address, or TLS for plain HTTP).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct Timing {
+    /// Resolving the hostname
+    pub dns: Option<time::Duration>,
+    /// Opening the TCP connection. Only measured for HTTPS, because the start
+    /// of the TLS handshake is how we know the connection is open
+    pub connect: Option<time::Duration>,
+    /// TLS handshake
+    pub tls: Option<time::Duration>,
+    /// Time from the connection being ready to receiving the response headers,
+    /// a.k.a. time to first byte. This includes sending the request body, and
+    /// any connection phases that couldn't be measured
+    pub wait: time::Duration,
+    /// Receiving the response body
+    pub download: time::Duration,
+}
+
+/// Details of the TLS connection a response was received over
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct TlsInfo {
+    /// Negotiated protocol version, e.g. `TLS 1.3`
+    pub protocol: String,
+    /// Negotiated cipher suite, e.g. `TLS13_AES_256_GCM_SHA384`
+    pub cipher_suite: String,
+    /// Certificate chain sent by the server, starting with the server's own
+    /// certificate. Certificates that couldn't be parsed are omitted.
+    pub certificates: Vec<Certificate>,
+}
+
+/// An X.509 certificate from a server's certificate chain. This only includes
+/// the fields that are useful for debugging.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct Certificate {
+    /// Distinguished name, e.g. `C=US, O=Example, CN=example.com`
+    pub subject: String,
+    pub issuer: String,
+    /// DNS names and IP addresses the certificate is valid for
+    pub subject_alt_names: Vec<String>,
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+}
+
+impl Certificate {
+    /// Is the certificate past its expiration date?
+    pub fn is_expired(&self) -> bool {
+        self.not_after < Utc::now()
+    }
+}
+
+/// A failed attempt at a request, which was then retried according to the
+/// recipe's [RetryPolicy](crate::collection::RetryPolicy)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct RetryAttempt {
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    /// What went wrong: either the response status code, or the error that
+    /// prevented a response
+    pub outcome: RetryOutcome,
+}
+
+/// Why a request attempt was retried
+#[derive(Clone, Debug, Display, Serialize, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+pub enum RetryOutcome {
+    /// Got a response with a retryable status code
+    #[display("{_0}")]
+    Status(#[serde(with = "cereal::serde_status_code")] StatusCode),
+    /// The request failed without a response
+    #[display("{_0}")]
+    Error(String),
+}
+
+impl RetryAttempt {
+    /// Get the elapsed time for this attempt
+    pub fn duration(&self) -> Duration {
+        self.end_time - self.start_time
+    }
+}
+
 /// Data for an HTTP request. This is similar to [reqwest::Request], but differs
 /// in some key ways:
 /// - Each [reqwest::Request] can only exist once (from creation to sending),
@@ -180,7 +591,8 @@ pub struct RequestRecord {
     pub url: Url,
     #[serde(with = "cereal::serde_header_map")]
     pub headers: HeaderMap,
-    /// Body content as bytes. This should be decoded as needed
+    /// Body content as bytes. This should be decoded as needed. `None` if
+    /// there is no body, or if the body was streamed from a file
     pub body: Option<ResponseBody>,
 }
 
@@ -207,14 +619,122 @@ impl RequestRecord {
             method: request.method().clone(),
             url: request.url().clone(),
             headers: request.headers().clone(),
-            body: request.body().map(|body| {
-                ResponseBody::new(
-                    body.as_bytes()
-                        .expect("Streaming bodies not supported")
-                        .to_owned()
-                        .into(),
-                )
-            }),
+            // Streamed bodies (i.e. files) aren't captured, because we'd
+            // have to load the whole thing into memory
+            body: request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map(|body| ResponseBody::new(body.to_owned().into())),
+        }
+    }
+
+    /// Hash the method, URL, headers, and body of this request, to check if
+    /// two requests are identical. This should be called on the record
+    /// *before* redaction, so that changes to sensitive values change the
+    /// fingerprint.
+    pub fn fingerprint(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.method.as_str());
+        hasher.update(b"\n");
+        hasher.update(self.url.as_str());
+        hasher.update(b"\n");
+        // Header names and values can't contain newlines, so they can't bleed
+        // into each other
+        for (name, value) in &self.headers {
+            hasher.update(name.as_str());
+            hasher.update(b":");
+            hasher.update(value.as_bytes());
+            hasher.update(b"\n");
+        }
+        hasher.update(b"\n");
+        if let Some(body) = &self.body {
+            hasher.update(body.bytes());
+        }
+        hex::encode(hasher.finalize())
+    }
+
+    /// Copy this record, replacing each of the given values with a
+    /// placeholder wherever it appears in the URL, a header value, or the
+    /// body. Values are matched literally, except for query parameters, which
+    /// are matched after being decoded. Encoded credentials (e.g. base64 for
+    /// basic auth) can't be matched, so if `redact_authorization` is set, the
+    /// credentials in the `Authorization` header are replaced entirely. Binary
+    /// header values and bodies are left as-is.
+    pub fn redact(
+        &self,
+        values: &HashSet<String>,
+        redact_authorization: bool,
+    ) -> Self {
+        // Replace longer values first, in case one contains another
+        let mut values: Vec<&String> = values.iter().collect();
+        values.sort_by_key(|value| Reverse(value.len()));
+        let redact = |text: &str| {
+            values.iter().fold(text.to_owned(), |text, value| {
+                text.replace(value.as_str(), REDACTED)
+            })
+        };
+
+        let mut url = Url::parse(&redact(self.url.as_str()))
+            .unwrap_or_else(|_| self.url.clone());
+        // Query parameters are percent-encoded in the URL, so a value with
+        // reserved characters won't match literally. Check the decoded
+        // parameters too. Only rebuild the query if something changed,
+        // because rebuilding can change how the rest of it is encoded.
+        let mut query_changed = false;
+        let query: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(name, value)| {
+                let redacted = (redact(&name), redact(&value));
+                query_changed |= redacted.0 != name || redacted.1 != value;
+                redacted
+            })
+            .collect();
+        if query_changed {
+            url.query_pairs_mut().clear().extend_pairs(query);
+        }
+        let headers = self
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                if redact_authorization && name == header::AUTHORIZATION {
+                    // Keep the scheme, so it's clear what kind of auth it was
+                    let value = match value.to_str().ok().and_then(|value| {
+                        value.split_once(' ').map(|(scheme, _)| scheme)
+                    }) {
+                        Some(scheme) => format!("{scheme} {REDACTED}"),
+                        None => REDACTED.to_owned(),
+                    };
+                    return (
+                        name.clone(),
+                        HeaderValue::from_str(&value)
+                            .expect("Redacted value is a valid header value"),
+                    );
+                }
+                let value = value
+                    .to_str()
+                    .ok()
+                    .and_then(|value| {
+                        HeaderValue::from_str(&redact(value)).ok()
+                    })
+                    .unwrap_or_else(|| value.clone());
+                (name.clone(), value)
+            })
+            .collect();
+        let body = self.body.as_ref().map(|body| {
+            let data = match std::str::from_utf8(&body.data) {
+                Ok(text) => redact(text).into(),
+                Err(_) => body.data.clone(),
+            };
+            ResponseBody::new(data)
+        });
+        Self {
+            id: self.id,
+            profile_id: self.profile_id.clone(),
+            recipe_id: self.recipe_id.clone(),
+            method: self.method.clone(),
+            url,
+            headers,
+            body,
         }
     }
 
@@ -295,9 +815,13 @@ impl crate::test_util::Factory<(Option<ProfileId>, RecipeId)>
 impl crate::test_util::Factory for ResponseRecord {
     fn factory(_: ()) -> Self {
         Self {
+            version: Version::HTTP_11,
             status: StatusCode::OK,
             headers: HeaderMap::new(),
             body: ResponseBody::default(),
+            encoded_size: None,
+            from_cache: false,
+            tls: None,
         }
     }
 }
@@ -313,6 +837,8 @@ impl crate::test_util::Factory for Exchange {
             response: response.into(),
             start_time: Utc::now(),
             end_time: Utc::now(),
+            retries: Vec::new(),
+            timing: None,
         }
     }
 }
@@ -329,6 +855,8 @@ impl crate::test_util::Factory<(Option<ProfileId>, RecipeId)> for Exchange {
             response: response.into(),
             start_time: Utc::now(),
             end_time: Utc::now(),
+            retries: Vec::new(),
+            timing: None,
         }
     }
 }
@@ -344,27 +872,53 @@ impl crate::test_util::Factory<(Option<ProfileId>, RecipeId)> for Exchange {
 #[derive(Debug, Serialize, Deserialize)]
 #[cfg_attr(test, derive(PartialEq))]
 pub struct ResponseRecord {
+    /// HTTP version negotiated with the server. Responses recorded before
+    /// this was tracked default to HTTP/1.1
+    #[serde(default, with = "cereal::serde_http_version")]
+    pub version: Version,
     #[serde(with = "cereal::serde_status_code")]
     pub status: StatusCode,
     #[serde(with = "cereal::serde_header_map")]
     pub headers: HeaderMap,
     pub body: ResponseBody,
+    /// Size of the body as it was transferred, if it was decompressed. `None`
+    /// if the body wasn't encoded, or was left as-is
+    #[serde(default)]
+    pub encoded_size: Option<ByteSize>,
+    /// The body wasn't sent by the server, but copied from a previous
+    /// response. This happens when the server responds `304 Not Modified` to
+    /// a conditional request, or when a recent response is reused because of
+    /// the recipe's [cache TTL](Recipe::cache_ttl).
+    #[serde(default)]
+    pub from_cache: bool,
+    /// Details of the TLS connection. `None` for plain HTTP, or if the
+    /// response was received before this was tracked
+    #[serde(default)]
+    pub tls: Option<TlsInfo>,
 }
 
 impl ResponseRecord {
     /// Attempt to parse the body of this response, and store it in the body
-    /// struct. If parsing fails, we'll store `None` instead.
+    /// struct. If parsing fails, we'll store `None` instead. If the body has
+    /// already been parsed, this does nothing. If another thread is parsing it
+    /// right now, this will block until it's done. Parsing a large body can be
+    /// slow, so avoid calling this from the main TUI thread.
     pub fn parse_body(&self) {
-        let body = ContentType::parse_response(self)
-            .context("Error parsing response body")
-            .traced()
-            .ok();
         // Store whether we succeeded or not, so we know not to try again
-        if self.body.parsed.set(body).is_err() {
-            // Unfortunately we don't have any helpful context to include here.
-            // The body could potentially be huge so don't log it.
-            error!("Response body parsed twice");
-        }
+        self.body.parsed.get_or_init(|| {
+            // A truncated body is only a preview. Even if it happens to parse,
+            // the result would be missing data
+            let result = if self.body.is_truncated() {
+                Err(anyhow!(
+                    "Body is truncated to the first {} of {}",
+                    ByteSize(self.body.bytes().len() as u64),
+                    self.body.size()
+                ))
+            } else {
+                ContentType::parse_response(self)
+            };
+            result.context("Error parsing response body").traced().ok()
+        });
     }
 
     /// Get a suggested file name for the content of this response. First we'll
@@ -401,15 +955,76 @@ impl ResponseRecord {
     pub fn content_type(&self) -> Option<ContentType> {
         ContentType::from_response(self).ok()
     }
+
+    /// Can this response be revalidated with a conditional request? It needs
+    /// a validator (`ETag` or `Last-Modified`) for the server to compare
+    /// against, and must be [reusable](Self::is_reusable), because the body
+    /// will be reused if the server says nothing has changed.
+    pub(super) fn is_revalidatable(&self) -> bool {
+        let has_validator = self.headers.contains_key(header::ETAG)
+            || self.headers.contains_key(header::LAST_MODIFIED);
+        has_validator && self.is_reusable()
+    }
+
+    /// Can this response stand in for a later response to the same request?
+    /// It must be successful, and have its full body. Bodies that were too
+    /// large to store in full are only a preview, so they're disqualified.
+    pub(super) fn is_reusable(&self) -> bool {
+        let is_complete = !self.body.is_truncated()
+            && self
+                .headers
+                .get(header::CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok()?.parse::<u64>().ok())
+                .map_or(true, |length| {
+                    // Length refers to the encoded body
+                    let size = self.encoded_size.unwrap_or(self.body.size());
+                    length == size.as_u64()
+                });
+        (self.status.is_success() || self.from_cache) && is_complete
+    }
+
+    /// Fill in a `304 Not Modified` response with the content of the cached
+    /// response that it refers to. Headers from the new response take
+    /// precedence, because the server may have updated them.
+    pub(super) fn fill_from_cache(mut self, cached: &ResponseRecord) -> Self {
+        // Length describes the (empty) 304 body, not the content we're using
+        self.headers.remove(header::CONTENT_LENGTH);
+        let mut headers = cached.headers.clone();
+        // Extending with a HeaderMap replaces existing values
+        headers.extend(self.headers);
+        Self {
+            version: self.version,
+            status: self.status,
+            headers,
+            body: ResponseBody::new(cached.body.data.clone()),
+            encoded_size: cached.encoded_size,
+            from_cache: true,
+            tls: self.tls,
+        }
+    }
 }
 
 /// HTTP response body. Content is stored as bytes because it may not
 /// necessarily be valid UTF-8. Converted to text only as needed.
+///
+/// Bodies that are too large to hold in memory are *spooled* to a temp file.
+/// In that case, only a preview from the start of the body is held in memory.
+/// Only the in-memory content is serialized, so spooled bodies are stored in
+/// history as just their preview, along with the size of the full body. A
+/// body loaded from history in this state is *truncated*: the preview is all
+/// that's left.
 #[derive(Default, Deserialize)]
-#[serde(from = "Bytes")] // Can't use into=Bytes because that requires cloning
+#[serde(from = "ResponseBodyCereal")]
 pub struct ResponseBody {
-    /// Raw body
+    /// Raw body, or the preview of a truncated body
     data: Bytes,
+    /// Size of the full body, if `data` is only a preview of it
+    #[serde(skip)]
+    full_size: Option<ByteSize>,
+    /// Temp file holding the full body, if it was too large to keep in memory.
+    /// This doesn't survive being stored in history
+    #[serde(skip)]
+    spool: Option<SpoolFile>,
     /// For responses of a known content type, we can parse the body into a
     /// real data structure. This is populated *eagerly*. Call
     /// [ResponseRecord::parse_body] to set the parsed body.
@@ -418,31 +1033,105 @@ pub struct ResponseBody {
 }
 
 impl ResponseBody {
+    /// Max amount of a spooled body to keep in memory for display
+    pub const PREVIEW_SIZE: ByteSize = ByteSize::kib(64);
+
     pub fn new(data: Bytes) -> Self {
         Self {
             data,
+            full_size: None,
+            spool: None,
+            parsed: Default::default(),
+        }
+    }
+
+    /// Create a body whose full content is in a temp file
+    pub(super) fn spooled(preview: Bytes, spool: SpoolFile) -> Self {
+        Self {
+            data: preview,
+            full_size: Some(ByteSize(spool.size)),
+            spool: Some(spool),
+            parsed: Default::default(),
+        }
+    }
+
+    /// Create a body that's just a preview of a larger body, whose full
+    /// content is no longer available
+    pub fn truncated(preview: Bytes, full_size: ByteSize) -> Self {
+        Self {
+            data: preview,
+            full_size: Some(full_size),
+            spool: None,
             parsed: Default::default(),
         }
     }
 
-    /// Raw content bytes
+    /// Raw content bytes. For a truncated body, this is just the preview
     pub fn bytes(&self) -> &[u8] {
         &self.data
     }
 
-    /// Owned raw content bytes
+    /// Owned raw content bytes. For a truncated body, this is just the
+    /// preview
     pub fn into_bytes(self) -> Bytes {
         self.data
     }
 
-    /// Get bytes as text, if valid UTF-8
+    /// Get bytes as text, if valid UTF-8. For a truncated body, this is just
+    /// the preview
     pub fn text(&self) -> Option<&str> {
         std::str::from_utf8(&self.data).ok()
     }
 
-    /// Get body size, in bytes
+    /// Get body size, in bytes. For a truncated body, this is the size of the
+    /// full body, not just the preview
     pub fn size(&self) -> ByteSize {
-        ByteSize(self.bytes().len() as u64)
+        self.full_size
+            .unwrap_or_else(|| ByteSize(self.bytes().len() as u64))
+    }
+
+    /// Was this body too large to hold in memory? If so, [Self::bytes] is
+    /// just a preview. The full body may still be available on disk; see
+    /// [Self::is_spooled].
+    pub fn is_truncated(&self) -> bool {
+        self.full_size.is_some()
+    }
+
+    /// Is the full content of a truncated body available in a temp file? This
+    /// is only true for the lifetime of the response it came from; bodies
+    /// loaded from history never have their full content.
+    pub fn is_spooled(&self) -> bool {
+        self.spool.is_some()
+    }
+
+    /// Path to the temp file holding the full body, if it was spooled
+    pub fn spool_path(&self) -> Option<&Path> {
+        self.spool.as_ref().map(|spool| spool.path.as_path())
+    }
+
+    /// Write the full body to a writer. For a spooled body, this streams from
+    /// the temp file rather than loading it into memory. Fails if the body is
+    /// truncated and the full content is gone, rather than writing just the
+    /// preview.
+    pub async fn write_to(
+        &self,
+        writer: &mut (impl AsyncWrite + Unpin),
+    ) -> io::Result<()> {
+        match &self.spool {
+            Some(spool) => {
+                let mut file = tokio::fs::File::open(&spool.path).await?;
+                tokio::io::copy(&mut file, writer).await?;
+            }
+            None if self.is_truncated() => {
+                return Err(io::Error::other(format!(
+                    "Only the first {} of the body ({}) was stored in history",
+                    ByteSize(self.data.len() as u64),
+                    self.size(),
+                )));
+            }
+            None => writer.write_all(&self.data).await?,
+        }
+        writer.flush().await
     }
 
     /// Get the parsed version of this body. Must haved call
@@ -454,17 +1143,72 @@ impl ResponseBody {
     pub fn parsed(&self) -> Option<&dyn ResponseContent> {
         self.parsed.get().and_then(Option::as_deref)
     }
+
+    /// Get the text to show the user for this body. If the body was parsed,
+    /// apply the query (if any) and prettify the result. If the query fails,
+    /// it's logged and the whole body is shown. Unparsed bodies are shown as
+    /// raw text, or a placeholder if they aren't UTF-8. This can be slow for
+    /// large bodies.
+    pub fn display_text(
+        &self,
+        selector_type: SelectorType,
+        query: Option<&str>,
+    ) -> String {
+        // This involves a lot of cloning because it makes stuff easier
+        self.parsed()
+            .map(|parsed_body| {
+                query
+                    .and_then(|query| {
+                        selector_type
+                            .query_content(query, parsed_body)
+                            .with_context(|| {
+                                format!("Error applying query {query:?}")
+                            })
+                            .traced()
+                            .ok()
+                    })
+                    .unwrap_or_else(|| parsed_body.prettify())
+            })
+            .unwrap_or_else(|| format!("{:#}", MaybeStr(self.bytes())))
+    }
 }
 
 impl Debug for ResponseBody {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         // Don't print the actual body because it could be huge
         f.debug_tuple("Body")
-            .field(&format!("<{} bytes>", self.data.len()))
+            .field(&format!("<{} bytes>", self.size().as_u64()))
             .finish()
     }
 }
 
+/// A temp file holding the full content of a large response body. The file is
+/// deleted when this is dropped.
+#[derive(Debug)]
+pub(super) struct SpoolFile {
+    pub(super) path: PathBuf,
+    /// Number of bytes written so far
+    pub(super) size: u64,
+}
+
+impl SpoolFile {
+    /// Create a new empty temp file, returning a handle to write to it
+    pub(super) async fn create() -> io::Result<(Self, tokio::fs::File)> {
+        let path =
+            env::temp_dir().join(format!("slumber-{}.body", Uuid::new_v4()));
+        let file = tokio::fs::File::create(&path).await?;
+        Ok((Self { path, size: 0 }, file))
+    }
+}
+
+impl Drop for SpoolFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path)
+            .context("Error deleting spooled response body")
+            .traced();
+    }
+}
+
 impl From<Bytes> for ResponseBody {
     fn from(bytes: Bytes) -> Self {
         Self::new(bytes)
@@ -476,8 +1220,38 @@ impl Serialize for ResponseBody {
     where
         S: serde::Serializer,
     {
-        // Serialize just the bytes, everything else is derived
-        self.data.serialize(serializer)
+        // Serialize just the bytes, everything else is derived. A truncated
+        // body needs its full size too, so it's clear that it's incomplete
+        // when loaded again
+        match self.full_size {
+            None => self.data.serialize(serializer),
+            Some(full_size) => ResponseBodyCereal::Truncated {
+                preview: self.data.clone(),
+                full_size,
+            }
+            .serialize(serializer),
+        }
+    }
+}
+
+/// Serialized form of [ResponseBody]. Complete bodies are stored as just their
+/// bytes, which is also how every body was stored before truncated bodies were
+/// tracked.
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum ResponseBodyCereal {
+    Complete(Bytes),
+    Truncated { preview: Bytes, full_size: ByteSize },
+}
+
+impl From<ResponseBodyCereal> for ResponseBody {
+    fn from(value: ResponseBodyCereal) -> Self {
+        match value {
+            ResponseBodyCereal::Complete(data) => Self::new(data),
+            ResponseBodyCereal::Truncated { preview, full_size } => {
+                Self::truncated(preview, full_size)
+            }
+        }
     }
 }
 
@@ -558,17 +1332,31 @@ impl PartialEq for RequestBuildError {
     .request.id,
 )]
 pub struct RequestError {
-    /// Underlying error. This will always be a `reqwest::Error`, but wrapping
+    /// Underlying error. This is typically a `reqwest::Error`, but wrapping
     /// it in anyhow makes it easier to render
     #[source]
     pub error: anyhow::Error,
 
     /// The request that caused all this ruckus
     pub request: Arc<RequestRecord>,
+    /// Earlier attempts at this request, which also failed. The timestamps
+    /// below are for the final attempt.
+    pub retries: Vec<RetryAttempt>,
     /// When was the request launched?
     pub start_time: DateTime<Utc>,
     /// When did the error occur?
     pub end_time: DateTime<Utc>,
+    /// If the error occurred while loading the response body, this holds
+    /// whatever was received before the failure
+    pub partial_response: Option<Arc<ResponseRecord>>,
+    /// Where the partial response body was written to disk, if anywhere. This
+    /// is populated by [RequestError::save_partial_response], and is needed to
+    /// resume the download.
+    pub resume_path: Option<PathBuf>,
+    /// Options the request was built with. Needed to re-send the same
+    /// request, e.g. to resume the download. Boxed because this error ends up
+    /// in other error types, which shouldn't all be this big.
+    pub options: Box<BuildOptions>,
 }
 
 #[cfg(test)]
@@ -578,6 +1366,9 @@ impl PartialEq for RequestError {
             && self.request == other.request
             && self.start_time == other.start_time
             && self.end_time == other.end_time
+            && self.partial_response == other.partial_response
+            && self.resume_path == other.resume_path
+            && self.options == other.options
     }
 }
 
@@ -650,4 +1441,138 @@ mod tests {
             --data '{\"data\":\"value\"}'"
         );
     }
+
+    /// Query parameters are redacted after decoding, so values with reserved
+    /// characters are masked no matter how they were encoded. Other
+    /// parameters are left alone.
+    #[rstest]
+    #[case::form_encoded(
+        "http://localhost/users?token=a%2Bb%2Fc%3D%3D+d&page=1",
+        "http://localhost/users?token=%3Csensitive%3E&page=1"
+    )]
+    #[case::percent_encoded(
+        "http://localhost/users?token=a%2Bb%2Fc%3D%3D%20d&page=1",
+        "http://localhost/users?token=%3Csensitive%3E&page=1"
+    )]
+    #[case::not_present(
+        "http://localhost/users?q=a%20b",
+        "http://localhost/users?q=a%20b"
+    )]
+    fn test_redact_encoded_query(#[case] url: &str, #[case] expected: &str) {
+        let request = RequestRecord {
+            url: url.parse().unwrap(),
+            ..RequestRecord::factory(())
+        };
+        let values = HashSet::from(["a+b/c== d".to_owned()]);
+        let redacted = request.redact(&values, false);
+        assert_eq!(redacted.url.as_str(), expected);
+    }
+
+    /// Sensitive values are replaced everywhere they appear. Longer values
+    /// are replaced first, so overlapping values are fully masked
+    #[test]
+    fn test_redact() {
+        let request = RequestRecord {
+            url: "http://localhost/users?token=hunter2".parse().unwrap(),
+            headers: header_map(indexmap! {
+                "authorization" => "Bearer hunter2",
+                "accept" => "application/json",
+            }),
+            body: Some(r#"{"password":"hunter22"}"#.into()),
+            ..RequestRecord::factory(())
+        };
+        let values = HashSet::from(["hunter2".to_owned(), "hunter22".into()]);
+        let redacted = request.redact(&values, false);
+
+        assert_eq!(
+            redacted.url.as_str(),
+            "http://localhost/users?token=%3Csensitive%3E"
+        );
+        assert_eq!(
+            redacted.headers,
+            header_map(indexmap! {
+                "authorization" => "Bearer <sensitive>",
+                "accept" => "application/json",
+            })
+        );
+        assert_eq!(
+            redacted.body.unwrap().bytes(),
+            br#"{"password":"<sensitive>"}"#
+        );
+        // Original is untouched
+        assert_eq!(
+            request.url.as_str(),
+            "http://localhost/users?token=hunter2"
+        );
+    }
+
+    /// Only non-error responses count as a success
+    #[rstest]
+    #[case::ok(RunOutcome::Response(StatusCode::OK), true)]
+    #[case::redirect(RunOutcome::Response(StatusCode::FOUND), true)]
+    #[case::client_error(RunOutcome::Response(StatusCode::NOT_FOUND), false)]
+    #[case::server_error(
+        RunOutcome::Response(StatusCode::INTERNAL_SERVER_ERROR),
+        false
+    )]
+    #[case::error(RunOutcome::Error("connection refused".into()), false)]
+    #[case::skipped(RunOutcome::Skipped, false)]
+    fn test_run_result_is_success(
+        #[case] outcome: RunOutcome,
+        #[case] expected: bool,
+    ) {
+        let result = RunResult {
+            recipe_id: "recipe1".into(),
+            outcome,
+            duration: None,
+        };
+        assert_eq!(result.is_success(), expected);
+    }
+
+    #[rstest]
+    #[case::plain("{}", "```json\n{}\n```\n")]
+    #[case::trailing_newline("{}\n", "```json\n{}\n```\n")]
+    #[case::backticks("a ```` b", "`````json\na ```` b\n`````\n")]
+    fn test_write_code_block(#[case] content: &str, #[case] expected: &str) {
+        let mut buf = String::new();
+        write_code_block(&mut buf, "json", content).unwrap();
+        assert_eq!(buf, expected);
+    }
+
+    /// Complete bodies are serialized as just their bytes, like they've
+    /// always been. Truncated bodies keep their full size, so they're still
+    /// truncated when deserialized.
+    #[tokio::test]
+    async fn test_truncated_body_serialization() {
+        let body = ResponseBody::from(b"data".to_vec());
+        let bytes = rmp_serde::to_vec_named(&body).unwrap();
+        assert_eq!(
+            bytes,
+            rmp_serde::to_vec_named(&Bytes::from("data")).unwrap()
+        );
+        let body: ResponseBody = rmp_serde::from_slice(&bytes).unwrap();
+        assert!(!body.is_truncated());
+        assert_eq!(body.bytes(), b"data");
+
+        let body =
+            ResponseBody::truncated("{\"a\": ".into(), ByteSize::kib(100));
+        let bytes = rmp_serde::to_vec_named(&body).unwrap();
+        let body: ResponseBody = rmp_serde::from_slice(&bytes).unwrap();
+        assert!(body.is_truncated());
+        assert!(!body.is_spooled());
+        assert_eq!(body.bytes(), b"{\"a\": ");
+        assert_eq!(body.size(), ByteSize::kib(100));
+
+        // The preview can't be passed off as the full body
+        let response = ResponseRecord {
+            headers: header_map([("content-type", "application/json")]),
+            body,
+            ..ResponseRecord::factory(())
+        };
+        response.parse_body();
+        assert!(response.body.parsed().is_none());
+        let mut buf = Vec::new();
+        assert!(response.body.write_to(&mut buf).await.is_err());
+        assert!(buf.is_empty());
+    }
 }
@@ -0,0 +1,218 @@
+//! Support for `.netrc` files, which store login credentials by hostname. This
+//! follows the same format as curl and other tools:
+//! <https://everything.curl.dev/usingcurl/netrc>
+
+use anyhow::{anyhow, bail, Context};
+use std::{collections::HashMap, env, fs, io, path::PathBuf};
+use tracing::info;
+
+/// Environment variable to override the location of the netrc file
+const ENV_VAR: &str = "NETRC";
+
+/// Parsed contents of a netrc file
+#[derive(Debug, Default)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct Netrc {
+    /// Credentials keyed by hostname
+    machines: HashMap<String, NetrcEntry>,
+    /// Credentials to use for any host not in `machines`
+    default: Option<NetrcEntry>,
+}
+
+/// Credentials for a single machine
+#[derive(Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct NetrcEntry {
+    pub login: String,
+    pub password: Option<String>,
+}
+
+impl Netrc {
+    /// Load the netrc file from `$NETRC`, or `~/.netrc` if that isn't set
+    /// (`~/_netrc` on Windows). Returns `None` if the file doesn't exist.
+    pub fn load() -> anyhow::Result<Option<Self>> {
+        let Some(path) = Self::path() else {
+            return Ok(None);
+        };
+        info!(?path, "Loading netrc file");
+        match fs::read_to_string(&path) {
+            Ok(content) => Self::parse(&content)
+                .map(Some)
+                .with_context(|| format!("Error parsing netrc file {path:?}")),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(anyhow!(error))
+                .with_context(|| format!("Error reading netrc file {path:?}")),
+        }
+    }
+
+    /// Get the expected location of the netrc file
+    fn path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os(ENV_VAR) {
+            return Some(path.into());
+        }
+        let file_name = if cfg!(windows) { "_netrc" } else { ".netrc" };
+        dirs::home_dir().map(|home| home.join(file_name))
+    }
+
+    /// Parse the contents of a netrc file. Comments (`#`) and macro
+    /// definitions (`macdef`) are ignored. If a machine appears multiple times,
+    /// the first entry wins, to match curl.
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
+        let mut netrc = Self::default();
+        // Machine name for the entry currently being parsed. `None` means
+        // we're in the default entry
+        let mut current: Option<(Option<String>, Builder)> = None;
+        let mut in_macro = false;
+
+        let mut finish = |current: Option<(Option<String>, Builder)>| {
+            let Some((machine, builder)) = current else {
+                return;
+            };
+            // Entries without a login are useless
+            let Some(login) = builder.login else {
+                return;
+            };
+            let entry = NetrcEntry {
+                login,
+                password: builder.password,
+            };
+            match machine {
+                Some(machine) => {
+                    netrc.machines.entry(machine).or_insert(entry);
+                }
+                None => {
+                    netrc.default.get_or_insert(entry);
+                }
+            }
+        };
+
+        for line in content.lines() {
+            // Macros run until the next blank line
+            if in_macro {
+                in_macro = !line.trim().is_empty();
+                continue;
+            }
+
+            let mut tokens = line.split_whitespace();
+            while let Some(token) = tokens.next() {
+                if token.starts_with('#') {
+                    break; // Comment runs to the end of the line
+                }
+                let mut value = || {
+                    tokens.next().ok_or_else(|| {
+                        anyhow!("Expected value after `{token}`")
+                    })
+                };
+                match token {
+                    "machine" => {
+                        finish(current.take());
+                        current = Some((
+                            Some(value()?.to_owned()),
+                            Builder::default(),
+                        ));
+                    }
+                    "default" => {
+                        finish(current.take());
+                        current = Some((None, Builder::default()));
+                    }
+                    "login" | "password" | "account" => {
+                        let value = value()?.to_owned();
+                        let Some((_, builder)) = &mut current else {
+                            bail!("`{token}` must come after `machine`");
+                        };
+                        match token {
+                            "login" => builder.login = Some(value),
+                            "password" => builder.password = Some(value),
+                            _ => {} // We don't use account
+                        }
+                    }
+                    "macdef" => {
+                        in_macro = true;
+                        break; // Rest of the line is the macro name
+                    }
+                    _ => bail!("Unexpected token `{token}`"),
+                }
+            }
+        }
+        finish(current);
+
+        Ok(netrc)
+    }
+
+    /// Get credentials for a host, falling back to the `default` entry if
+    /// the host isn't listed
+    pub fn get(&self, host: &str) -> Option<&NetrcEntry> {
+        self.machines.get(host).or(self.default.as_ref())
+    }
+}
+
+/// Intermediate state for an entry that's being parsed
+#[derive(Default)]
+struct Builder {
+    login: Option<String>,
+    password: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::assert_err;
+    use rstest::rstest;
+
+    #[test]
+    fn test_parse() {
+        let netrc = Netrc::parse(
+            "# My credentials
+machine example.com login user1 password hunter2
+machine other.com
+    login user2 # No password
+    account ignored
+
+macdef init
+machine fake.com login nope
+
+machine example.com login duplicate password ignored
+machine nologin.com password useless
+default login anonymous password guest
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            netrc,
+            Netrc {
+                machines: [
+                    (
+                        "example.com".into(),
+                        NetrcEntry {
+                            login: "user1".into(),
+                            password: Some("hunter2".into())
+                        }
+                    ),
+                    (
+                        "other.com".into(),
+                        NetrcEntry {
+                            login: "user2".into(),
+                            password: None
+                        }
+                    ),
+                ]
+                .into(),
+                default: Some(NetrcEntry {
+                    login: "anonymous".into(),
+                    password: Some("guest".into())
+                }),
+            }
+        );
+        assert_eq!(netrc.get("example.com").unwrap().login, "user1");
+        assert_eq!(netrc.get("unknown.com").unwrap().login, "anonymous");
+    }
+
+    #[rstest]
+    #[case::missing_value("machine", "Expected value after `machine`")]
+    #[case::no_machine("login user", "`login` must come after `machine`")]
+    #[case::unknown_token("machine a.com fish", "Unexpected token `fish`")]
+    fn test_parse_error(#[case] content: &str, #[case] expected_error: &str) {
+        assert_err!(Netrc::parse(content), expected_error);
+    }
+}
@@ -0,0 +1,554 @@
+//! OAuth 2.0 access tokens, for [Authentication::OAuth2]. Tokens are cached in
+//! the database, keyed by the settings used to fetch them, so every recipe
+//! that uses the same client shares a token. Expired tokens are refreshed
+//! automatically if the server gave us a refresh token.
+//!
+//! [Authentication::OAuth2]: crate::collection::Authentication::OAuth2
+
+use crate::{
+    collection::{OAuth2, OAuth2GrantType},
+    db::CollectionDatabase,
+    util::ResultExt,
+};
+use anyhow::{anyhow, bail, Context};
+use chrono::{DateTime, Duration, Utc};
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use std::time::Duration as StdDuration;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    time,
+};
+use tracing::{info, info_span, warn};
+use uuid::Uuid;
+
+/// Default local port for the authorization code redirect
+pub const DEFAULT_REDIRECT_PORT: u16 = 7890;
+
+/// Treat tokens as expired this long before they actually expire, so they
+/// don't expire while the request is in flight
+const EXPIRY_MARGIN: Duration = Duration::seconds(30);
+
+/// How long to wait for the user to log in during the authorization code flow
+const AUTHORIZATION_TIMEOUT: StdDuration = StdDuration::from_secs(300);
+
+/// An access token, as cached in the database
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct OAuth2Token {
+    pub access_token: String,
+    /// Used to get a new access token once this one expires
+    pub refresh_token: Option<String>,
+    /// `None` if the server didn't say when the token expires
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl OAuth2Token {
+    /// Has the token expired, or is it about to?
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at - EXPIRY_MARGIN <= Utc::now())
+    }
+}
+
+/// Body of a successful response from the token endpoint
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    /// Lifetime of the token, in seconds
+    expires_in: Option<i64>,
+    refresh_token: Option<String>,
+}
+
+impl OAuth2<String> {
+    /// Key to cache the token under. Any setting that could change the token
+    /// we get back is included.
+    pub fn cache_key(&self) -> String {
+        format!(
+            "{} {} {} {}",
+            self.grant_type,
+            self.token_url,
+            self.client_id,
+            self.scopes.join(" ")
+        )
+    }
+
+    /// Get an access token. Use the cached token if it's still valid,
+    /// otherwise refresh it or fetch a new one. New tokens are cached.
+    pub async fn get_token(
+        &self,
+        client: &Client,
+        database: &CollectionDatabase,
+    ) -> anyhow::Result<String> {
+        let _ =
+            info_span!("OAuth 2.0 token", token_url = self.token_url).entered();
+        let key = self.cache_key();
+        let cached = database.get_oauth2_token(&key)?;
+
+        let token = match cached {
+            Some(token) if !token.is_expired() => return Ok(token.access_token),
+            // Refreshing can fail if the refresh token expired or was
+            // revoked. In that case, fall back to getting a new one
+            Some(OAuth2Token {
+                refresh_token: Some(refresh_token),
+                ..
+            }) => match self.refresh_token(client, &refresh_token).await {
+                Ok(token) => token,
+                Err(error) => {
+                    warn!(
+                        error = format!("{error:#}"),
+                        "Error refreshing token"
+                    );
+                    self.fetch_token(client).await?
+                }
+            },
+            _ => self.fetch_token(client).await?,
+        };
+
+        database.set_oauth2_token(&key, &token)?;
+        Ok(token.access_token)
+    }
+
+    /// Get a brand new token, according to the grant type
+    async fn fetch_token(
+        &self,
+        client: &Client,
+    ) -> anyhow::Result<OAuth2Token> {
+        match self.grant_type {
+            OAuth2GrantType::ClientCredentials => {
+                info!("Fetching token with client credentials");
+                self.request_token(
+                    client,
+                    &[("grant_type", "client_credentials")],
+                )
+                .await
+            }
+            OAuth2GrantType::AuthorizationCode => {
+                let (code, redirect_uri) = self.authorize().await?;
+                info!("Exchanging authorization code for token");
+                self.request_token(
+                    client,
+                    &[
+                        ("grant_type", "authorization_code"),
+                        ("code", &code),
+                        ("redirect_uri", &redirect_uri),
+                    ],
+                )
+                .await
+            }
+        }
+    }
+
+    /// Exchange a refresh token for a new access token. If the server doesn't
+    /// send a new refresh token, the old one is kept.
+    async fn refresh_token(
+        &self,
+        client: &Client,
+        refresh_token: &str,
+    ) -> anyhow::Result<OAuth2Token> {
+        info!("Refreshing token");
+        let mut token = self
+            .request_token(
+                client,
+                &[
+                    ("grant_type", "refresh_token"),
+                    ("refresh_token", refresh_token),
+                ],
+            )
+            .await?;
+        token
+            .refresh_token
+            .get_or_insert_with(|| refresh_token.to_owned());
+        Ok(token)
+    }
+
+    /// Send a request to the token endpoint. Client credentials and scopes are
+    /// added to the given form parameters.
+    async fn request_token(
+        &self,
+        client: &Client,
+        params: &[(&str, &str)],
+    ) -> anyhow::Result<OAuth2Token> {
+        let scope = self.scopes.join(" ");
+        let mut form = params.to_vec();
+        form.push(("client_id", &self.client_id));
+        if let Some(client_secret) = &self.client_secret {
+            form.push(("client_secret", client_secret));
+        }
+        if !scope.is_empty() {
+            form.push(("scope", &scope));
+        }
+
+        let response = client
+            .post(&self.token_url)
+            .form(&form)
+            .send()
+            .await
+            .with_context(|| {
+                format!("Error requesting token from `{}`", self.token_url)
+            })?;
+        let status = response.status();
+        let body = response.bytes().await?;
+        if !status.is_success() {
+            bail!(
+                "Token request to `{}` failed with status {status}: {}",
+                self.token_url,
+                String::from_utf8_lossy(&body)
+            );
+        }
+        let response: TokenResponse = serde_json::from_slice(&body)
+            .context("Error parsing token response")?;
+        Ok(OAuth2Token {
+            access_token: response.access_token,
+            refresh_token: response.refresh_token,
+            expires_at: response
+                .expires_in
+                .map(|expires_in| Utc::now() + Duration::seconds(expires_in)),
+        })
+    }
+
+    /// Run the browser portion of the authorization code flow. Open the
+    /// authorization page in the browser, then wait for the server to redirect
+    /// the user back to us with a code. Returns the code and the redirect URI,
+    /// which has to be included in the token request.
+    async fn authorize(&self) -> anyhow::Result<(String, String)> {
+        let Some(authorization_url) = &self.authorization_url else {
+            bail!(
+                "`authorization_url` is required for the \
+                `authorization_code` grant type"
+            );
+        };
+        let port = self.redirect_port.unwrap_or(DEFAULT_REDIRECT_PORT);
+        let redirect_uri = format!("http://localhost:{port}/callback");
+        // Random value to make sure the redirect came from our request
+        let state = Uuid::new_v4().to_string();
+
+        let mut url: Url = authorization_url.parse().with_context(|| {
+            format!("Invalid authorization URL `{authorization_url}`")
+        })?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.client_id)
+            .append_pair("redirect_uri", &redirect_uri)
+            .append_pair("state", &state);
+        if !self.scopes.is_empty() {
+            url.query_pairs_mut()
+                .append_pair("scope", &self.scopes.join(" "));
+        }
+
+        // Start listening *before* opening the browser, so we can't miss the
+        // redirect
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .await
+            .with_context(|| {
+                format!("Error listening for OAuth 2.0 redirect on port {port}")
+            })?;
+        info!(%url, "Opening browser for authorization");
+        open::that_detached(url.as_str())
+            .with_context(|| format!("Error opening browser to `{url}`"))?;
+
+        let code = time::timeout(
+            AUTHORIZATION_TIMEOUT,
+            receive_code(&listener, &state),
+        )
+        .await
+        .map_err(|_| anyhow!("Timed out waiting for authorization"))??;
+        Ok((code, redirect_uri))
+    }
+}
+
+/// Wait for the authorization server to redirect the browser back to us, and
+/// pull the authorization code out of the redirect URL. Any other requests
+/// (e.g. the browser asking for a favicon) are ignored.
+async fn receive_code(
+    listener: &TcpListener,
+    state: &str,
+) -> anyhow::Result<String> {
+    loop {
+        let (mut stream, _) = listener
+            .accept()
+            .await
+            .context("Error accepting OAuth 2.0 redirect")?;
+        let Some(target) = read_request_target(&mut stream).await.traced().ok()
+        else {
+            continue;
+        };
+        // The URL only needs to be valid so we can pull the query out
+        let url = Url::parse("http://localhost")?.join(&target)?;
+        if url.path() != "/callback" {
+            respond(&mut stream, "404 Not Found", "Not found").await;
+            continue;
+        }
+
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        };
+        let result = if let Some(error) = param("error") {
+            let description = param("error_description").unwrap_or_default();
+            Err(anyhow!("Authorization failed: {error} {description}"))
+        } else if param("state").as_deref() != Some(state) {
+            Err(anyhow!("Authorization redirect has incorrect `state`"))
+        } else {
+            param("code")
+                .ok_or_else(|| anyhow!("Authorization redirect has no `code`"))
+        };
+
+        match &result {
+            Ok(_) => {
+                respond(
+                    &mut stream,
+                    "200 OK",
+                    "Authorization complete. You can close this window.",
+                )
+                .await
+            }
+            Err(error) => {
+                respond(&mut stream, "400 Bad Request", &error.to_string())
+                    .await
+            }
+        }
+        return result;
+    }
+}
+
+/// Read the request line of an HTTP request, and return the target (path and
+/// query). We don't care about anything else in the request.
+async fn read_request_target(stream: &mut TcpStream) -> anyhow::Result<String> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 1024];
+    // Read until we have the full first line
+    while !buffer.contains(&b'\n') {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            bail!("Connection closed before request was received");
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+    let request = String::from_utf8_lossy(&buffer);
+    let line = request.lines().next().unwrap_or_default();
+    // Request line is `<method> <target> <version>`
+    line.split_whitespace()
+        .nth(1)
+        .map(String::from)
+        .ok_or_else(|| anyhow!("Invalid request line `{line}`"))
+}
+
+/// Send a plain text response to the browser. Errors are ignored, because
+/// it's just a courtesy to the user.
+async fn respond(stream: &mut TcpStream, status: &str, body: &str) {
+    let response = format!(
+        "HTTP/1.1 {status}\r\ncontent-type: text/plain\r\n\
+        content-length: {}\r\nconnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_err, Factory};
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn oauth2(token_url: String) -> OAuth2<String> {
+        OAuth2 {
+            grant_type: OAuth2GrantType::ClientCredentials,
+            token_url,
+            authorization_url: None,
+            client_id: "client".into(),
+            client_secret: Some("secret".into()),
+            scopes: vec!["read".into(), "write".into()],
+            redirect_port: None,
+        }
+    }
+
+    /// A new token should be fetched and cached. Subsequent calls should use
+    /// the cached token until it expires.
+    #[tokio::test]
+    async fn test_client_credentials() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/token")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded(
+                    "grant_type".into(),
+                    "client_credentials".into(),
+                ),
+                mockito::Matcher::UrlEncoded(
+                    "client_id".into(),
+                    "client".into(),
+                ),
+                mockito::Matcher::UrlEncoded(
+                    "client_secret".into(),
+                    "secret".into(),
+                ),
+                mockito::Matcher::UrlEncoded(
+                    "scope".into(),
+                    "read write".into(),
+                ),
+            ]))
+            .with_body(r#"{"access_token":"token1","expires_in":3600}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let database = CollectionDatabase::factory(());
+        let oauth2 = oauth2(format!("{}/token", server.url()));
+        let client = Client::new();
+
+        assert_eq!(
+            oauth2.get_token(&client, &database).await.unwrap(),
+            "token1"
+        );
+        // Second call hits the cache
+        assert_eq!(
+            oauth2.get_token(&client, &database).await.unwrap(),
+            "token1"
+        );
+        mock.assert_async().await;
+
+        let token = database
+            .get_oauth2_token(&oauth2.cache_key())
+            .unwrap()
+            .unwrap();
+        assert!(!token.is_expired());
+        assert_eq!(token.refresh_token, None);
+    }
+
+    /// An expired token should be refreshed if possible. If refreshing fails,
+    /// fall back to fetching a new token.
+    #[rstest]
+    #[case::refresh(200, "refreshed", 0)]
+    #[case::refresh_failed(400, "new", 1)]
+    #[tokio::test]
+    async fn test_expired(
+        #[case] refresh_status: usize,
+        #[case] expected_token: &str,
+        #[case] expected_fetches: usize,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let refresh_mock = server
+            .mock("POST", "/token")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded(
+                    "grant_type".into(),
+                    "refresh_token".into(),
+                ),
+                mockito::Matcher::UrlEncoded(
+                    "refresh_token".into(),
+                    "refresh1".into(),
+                ),
+            ]))
+            .with_status(refresh_status)
+            .with_body(r#"{"access_token":"refreshed","expires_in":3600}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let fetch_mock = server
+            .mock("POST", "/token")
+            .match_body(mockito::Matcher::UrlEncoded(
+                "grant_type".into(),
+                "client_credentials".into(),
+            ))
+            .with_body(r#"{"access_token":"new"}"#)
+            .expect(expected_fetches)
+            .create_async()
+            .await;
+        let database = CollectionDatabase::factory(());
+        let oauth2 = oauth2(format!("{}/token", server.url()));
+        database
+            .set_oauth2_token(
+                &oauth2.cache_key(),
+                &OAuth2Token {
+                    access_token: "expired".into(),
+                    refresh_token: Some("refresh1".into()),
+                    expires_at: Some(Utc::now() - Duration::minutes(1)),
+                },
+            )
+            .unwrap();
+
+        let token = oauth2.get_token(&Client::new(), &database).await.unwrap();
+        assert_eq!(token, expected_token);
+        refresh_mock.assert_async().await;
+        fetch_mock.assert_async().await;
+
+        // The refresh token should be retained if the server doesn't send a
+        // new one
+        let cached = database
+            .get_oauth2_token(&oauth2.cache_key())
+            .unwrap()
+            .unwrap();
+        assert_eq!(cached.access_token, expected_token);
+        let expected_refresh_token =
+            (refresh_status == 200).then(|| "refresh1".to_owned());
+        assert_eq!(cached.refresh_token, expected_refresh_token);
+    }
+
+    /// Error from the token endpoint should be reported
+    #[tokio::test]
+    async fn test_token_error() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/token")
+            .with_status(401)
+            .with_body(r#"{"error":"invalid_client"}"#)
+            .create_async()
+            .await;
+        let database = CollectionDatabase::factory(());
+        let oauth2 = oauth2(format!("{}/token", server.url()));
+
+        assert_err!(
+            oauth2.get_token(&Client::new(), &database).await,
+            "failed with status 401 Unauthorized: {\"error\":\"invalid_client\"}"
+        );
+        assert_eq!(
+            database.get_oauth2_token(&oauth2.cache_key()).unwrap(),
+            None
+        );
+    }
+
+    /// Test receiving the authorization code from the browser redirect
+    #[rstest]
+    #[case::success("/callback?code=abc&state=state1", None)]
+    #[case::wrong_state(
+        "/callback?code=abc&state=state2",
+        Some("incorrect `state`")
+    )]
+    #[case::error(
+        "/callback?error=access_denied&state=state1",
+        Some("Authorization failed: access_denied")
+    )]
+    #[tokio::test]
+    async fn test_receive_code(
+        #[case] target: &str,
+        #[case] expected_error: Option<&str>,
+    ) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let target = target.to_owned();
+        let browser = tokio::spawn(async move {
+            // Unrelated requests should be ignored
+            for target in ["/favicon.ico", &target] {
+                let mut stream = TcpStream::connect(address).await.unwrap();
+                stream
+                    .write_all(
+                        format!("GET {target} HTTP/1.1\r\n\r\n").as_bytes(),
+                    )
+                    .await
+                    .unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).await.unwrap();
+            }
+        });
+
+        let result = receive_code(&listener, "state1").await;
+        browser.await.unwrap();
+        match expected_error {
+            None => assert_eq!(result.unwrap(), "abc"),
+            Some(expected_error) => assert_err!(result, expected_error),
+        }
+    }
+}
@@ -1,17 +1,66 @@
 //! Utilities for querying HTTP response data
 
-use crate::http::ResponseContent;
+use crate::http::{
+    markup::{self, Syntax},
+    ResponseContent,
+};
+use anyhow::anyhow;
 use derive_more::{Display, FromStr};
+use itertools::Itertools;
+use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json_path::{ExactlyOneError, JsonPath};
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::HashMap};
+use sxd_document::dom::{ChildOfElement, ChildOfRoot, Element};
+use sxd_xpath::{Context, XPath};
 use thiserror::Error;
 
 /// A wrapper around a JSONPath. This combines some common behavior, and will
 /// make it easy to swap out the query language in the future if necessary.
-#[derive(Clone, Debug, Display, FromStr, PartialEq, Serialize, Deserialize)]
+#[derive(
+    Clone,
+    Debug,
+    Display,
+    FromStr,
+    PartialEq,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
 #[serde(transparent)]
-pub struct Query(JsonPath);
+#[schemars(description = "JSONPath query, e.g. `$.data[0].id`")]
+pub struct Query(#[schemars(with = "String")] JsonPath);
+
+/// The query language of a chain's `selector`
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    Display,
+    PartialEq,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectorType {
+    /// JSONPath, which can be applied to any supported content type
+    #[default]
+    #[display("JSONPath")]
+    Jsonpath,
+    /// XPath 1.0, for XML
+    #[display("XPath")]
+    Xpath,
+    /// CSS selector, for HTML
+    #[display("CSS")]
+    Css,
+    /// Regular expression, for unstructured text. If the regex has a capture
+    /// group, the first group is the output. Otherwise it's the whole match.
+    #[display("regex")]
+    Regex,
+}
 
 #[derive(Debug, Error)]
 pub enum QueryError {
@@ -22,6 +71,129 @@ pub enum QueryError {
         #[source]
         error: ExactlyOneError,
     },
+
+    /// Got either 0 or 2+ results for an XPath or CSS query
+    #[error("Expected exactly one result from query, but got {count}")]
+    ResultCount { count: usize },
+
+    /// Regex selector didn't match the content
+    #[error("Regex `{regex}` did not match")]
+    NoMatch { regex: String },
+
+    /// The selector isn't valid for its query language
+    #[error("Invalid {selector_type} selector `{selector}`")]
+    InvalidSelector {
+        selector_type: SelectorType,
+        selector: String,
+        #[source]
+        error: anyhow::Error,
+    },
+
+    /// The content couldn't be parsed as text/XML/HTML
+    #[error("Parsing response")]
+    Parse {
+        #[source]
+        error: anyhow::Error,
+    },
+}
+
+impl SelectorType {
+    /// Check if a selector is valid for this query language
+    pub fn validate(self, selector: &str) -> Result<(), QueryError> {
+        match self {
+            Self::Jsonpath => {
+                Query::from_str(selector).map(|_| ()).map_err(|error| {
+                    QueryError::InvalidSelector {
+                        selector_type: self,
+                        selector: selector.to_owned(),
+                        error: error.into(),
+                    }
+                })
+            }
+            Self::Xpath => parse_xpath(selector).map(|_| ()),
+            Self::Css => parse_css(selector).map(|_| ()),
+            Self::Regex => parse_regex(selector).map(|_| ()),
+        }
+    }
+
+    /// Apply an XPath, CSS, or regex selector to some text content, returning
+    /// a string. JSONPath can't be applied here, because it requires the
+    /// parsed content; use [Query] instead.
+    pub fn query_text(
+        self,
+        selector: &str,
+        content: &[u8],
+    ) -> Result<String, QueryError> {
+        let content = std::str::from_utf8(content).map_err(|error| {
+            QueryError::Parse {
+                error: error.into(),
+            }
+        })?;
+        match self {
+            Self::Jsonpath => Err(QueryError::InvalidSelector {
+                selector_type: self,
+                selector: selector.to_owned(),
+                error: anyhow!("JSONPath requires parsed content"),
+            }),
+            Self::Xpath => query_xpath(selector, content),
+            Self::Css => query_css(selector, content),
+            Self::Regex => query_regex(selector, content),
+        }
+    }
+
+    /// Apply a selector to parsed content for display, returning every match
+    /// prettified. Unlike [query_text](Self::query_text), any number of
+    /// matches is allowed. This is used to filter response bodies in the TUI.
+    pub fn query_content(
+        self,
+        selector: &str,
+        content: &dyn ResponseContent,
+    ) -> Result<String, QueryError> {
+        match self {
+            Self::Jsonpath => {
+                let query = Query::from_str(selector).map_err(|error| {
+                    QueryError::InvalidSelector {
+                        selector_type: self,
+                        selector: selector.to_owned(),
+                        error: error.into(),
+                    }
+                })?;
+                Ok(query.query(content).prettify())
+            }
+            Self::Xpath => {
+                evaluate_xpath(selector, &content.to_string(), |value| {
+                    match value {
+                        sxd_xpath::Value::Nodeset(nodes) => {
+                            let nodes = nodes
+                                .document_order()
+                                .into_iter()
+                                .map(markup::xpath_node)
+                                .collect_vec();
+                            markup::print(&nodes, Syntax::Xml)
+                        }
+                        value => value.into_string(),
+                    }
+                })
+            }
+            Self::Css => {
+                let (css, attribute) = parse_css(selector)?;
+                let document =
+                    scraper::Html::parse_document(&content.to_string());
+                let elements = document.select(&css);
+                Ok(match attribute {
+                    Some(attribute) => elements
+                        .filter_map(|element| element.value().attr(attribute))
+                        .join("\n"),
+                    None => {
+                        let nodes =
+                            elements.map(markup::html_element).collect_vec();
+                        markup::print(&nodes, Syntax::Html)
+                    }
+                })
+            }
+            Self::Regex => query_regex(selector, &content.to_string()),
+        }
+    }
 }
 
 impl Query {
@@ -41,6 +213,16 @@ impl Query {
         content_type.parse_json(Cow::Owned(queried))
     }
 
+    /// Apply a query to some content, returning the single result as JSON.
+    /// Error if the query doesn't return exactly one result
+    pub fn query_value(
+        &self,
+        value: &dyn ResponseContent,
+    ) -> Result<serde_json::Value, QueryError> {
+        let json_value = value.to_json();
+        Ok(self.0.query(&json_value).exactly_one()?.clone())
+    }
+
     /// Apply a query to some content, returning a string. The query should
     /// return a single result. If it's a scalar, that will be stringified. If
     /// it's an array/object, it'll be converted back into its input format,
@@ -72,10 +254,219 @@ impl Query {
     }
 }
 
+/// Compile an XPath query
+fn parse_xpath(selector: &str) -> Result<XPath, QueryError> {
+    let invalid = |error: anyhow::Error| QueryError::InvalidSelector {
+        selector_type: SelectorType::Xpath,
+        selector: selector.to_owned(),
+        error,
+    };
+    sxd_xpath::Factory::new()
+        .build(selector)
+        .map_err(|error| invalid(anyhow!("{error}")))?
+        .ok_or_else(|| invalid(anyhow!("Selector is empty")))
+}
+
+/// Apply an XPath query to an XML document. If the query returns a node set,
+/// it must contain exactly one node, and the output is that node's text.
+fn query_xpath(selector: &str, xml: &str) -> Result<String, QueryError> {
+    evaluate_xpath(selector, xml, |value| match value {
+        sxd_xpath::Value::Nodeset(nodes) => {
+            let nodes = nodes.document_order();
+            match nodes.as_slice() {
+                [node] => Ok(node.string_value()),
+                _ => Err(QueryError::ResultCount { count: nodes.len() }),
+            }
+        }
+        // Functions like count() can return scalars
+        value => Ok(value.into_string()),
+    })?
+}
+
+/// Evaluate an XPath query against an XML document, and pass the result to a
+/// function. The result borrows from the parsed document, so it can't be
+/// returned directly. Every namespace prefix declared in the document can be
+/// used in the query.
+fn evaluate_xpath<T>(
+    selector: &str,
+    xml: &str,
+    f: impl FnOnce(sxd_xpath::Value<'_>) -> T,
+) -> Result<T, QueryError> {
+    let xpath = parse_xpath(selector)?;
+    let package = sxd_document::parser::parse(xml).map_err(|error| {
+        QueryError::Parse {
+            error: error.into(),
+        }
+    })?;
+    let document = package.as_document();
+
+    let mut namespaces = HashMap::new();
+    for child in document.root().children() {
+        if let ChildOfRoot::Element(element) = child {
+            collect_namespaces(&mut namespaces, element);
+        }
+    }
+    // The XPath library panics on undeclared prefixes, so catch them first
+    if let Some(prefix) = xpath_prefixes(selector)
+        .into_iter()
+        .find(|prefix| !namespaces.contains_key(prefix))
+    {
+        return Err(QueryError::InvalidSelector {
+            selector_type: SelectorType::Xpath,
+            selector: selector.to_owned(),
+            error: anyhow!(
+                "Namespace prefix `{prefix}` is not declared in the document"
+            ),
+        });
+    }
+    let mut context = Context::new();
+    for (prefix, uri) in namespaces {
+        context.set_namespace(prefix, uri);
+    }
+
+    let value = xpath.evaluate(&context, document.root()).map_err(|error| {
+        QueryError::InvalidSelector {
+            selector_type: SelectorType::Xpath,
+            selector: selector.to_owned(),
+            error: anyhow!("{error}"),
+        }
+    })?;
+    Ok(f(value))
+}
+
+/// Get the namespaces declared on an element and all of its descendants, as
+/// a map of prefix to URI
+fn collect_namespaces<'d>(
+    namespaces: &mut HashMap<&'d str, &'d str>,
+    element: Element<'d>,
+) {
+    for namespace in element.namespaces_in_scope() {
+        namespaces.insert(namespace.prefix(), namespace.uri());
+    }
+    for child in element.children() {
+        if let ChildOfElement::Element(child) = child {
+            collect_namespaces(namespaces, child);
+        }
+    }
+}
+
+/// Get the namespace prefix of every qualified name in an XPath query, e.g.
+/// `soap` in `//soap:Body`. Axes (`child::`) and string literals are skipped.
+fn xpath_prefixes(selector: &str) -> Vec<&str> {
+    let is_name_char =
+        |c: char| c.is_alphanumeric() || matches!(c, '_' | '-' | '.');
+    let mut prefixes = Vec::new();
+    let mut quote = None;
+    let chars: Vec<(usize, char)> = selector.char_indices().collect();
+    for (i, &(index, c)) in chars.iter().enumerate() {
+        match (quote, c) {
+            (Some(q), _) if q == c => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, ':') => {
+                // `::` is an axis separator, not a prefix
+                let is_colon =
+                    |j: usize| chars.get(j).map(|(_, c)| *c) == Some(':');
+                if is_colon(i + 1) || (i > 0 && is_colon(i - 1)) {
+                    continue;
+                }
+                let start = selector[..index]
+                    .char_indices()
+                    .rev()
+                    .take_while(|(_, c)| is_name_char(*c))
+                    .last()
+                    .map(|(start, _)| start);
+                if let Some(start) = start {
+                    prefixes.push(&selector[start..index]);
+                }
+            }
+            _ => {}
+        }
+    }
+    prefixes
+}
+
+/// A CSS selector, optionally followed by `::attr(name)` to select an
+/// attribute of the matched element instead of its text
+fn parse_css(
+    selector: &str,
+) -> Result<(scraper::Selector, Option<&str>), QueryError> {
+    let (css, attribute) = match selector
+        .strip_suffix(')')
+        .and_then(|selector| selector.rsplit_once("::attr("))
+    {
+        Some((css, attribute)) => (css, Some(attribute.trim())),
+        None => (selector, None),
+    };
+    let css = scraper::Selector::parse(css).map_err(|error| {
+        QueryError::InvalidSelector {
+            selector_type: SelectorType::Css,
+            selector: selector.to_owned(),
+            error: anyhow!("{error}"),
+        }
+    })?;
+    Ok((css, attribute))
+}
+
+/// Apply a CSS selector to an HTML document. It must match exactly one
+/// element, and the output is that element's text, or the requested
+/// attribute. A missing attribute is an empty string.
+fn query_css(selector: &str, html: &str) -> Result<String, QueryError> {
+    let (css, attribute) = parse_css(selector)?;
+    let document = scraper::Html::parse_document(html);
+    let elements: Vec<_> = document.select(&css).collect();
+    let [element] = elements.as_slice() else {
+        return Err(QueryError::ResultCount {
+            count: elements.len(),
+        });
+    };
+    Ok(match attribute {
+        Some(attribute) => element
+            .value()
+            .attr(attribute)
+            .unwrap_or_default()
+            .to_owned(),
+        None => element.text().collect(),
+    })
+}
+
+/// Compile a regex selector
+fn parse_regex(selector: &str) -> Result<Regex, QueryError> {
+    Regex::new(selector).map_err(|error| QueryError::InvalidSelector {
+        selector_type: SelectorType::Regex,
+        selector: selector.to_owned(),
+        error: error.into(),
+    })
+}
+
+/// Apply a regex to some text. Unlike the other selectors, multiple matches
+/// are allowed; the first one is used. If the regex has any capture groups,
+/// the output is the first group. Otherwise it's the entire match.
+fn query_regex(selector: &str, text: &str) -> Result<String, QueryError> {
+    let regex = parse_regex(selector)?;
+    let captures = regex.captures(text).ok_or_else(|| QueryError::NoMatch {
+        regex: selector.to_owned(),
+    })?;
+    // Group 0 is the whole match. If group 1 exists but didn't participate in
+    // the match (e.g. `(a)?`), it's an empty string
+    let value = if captures.len() > 1 {
+        captures
+            .get(1)
+            .map(|group| group.as_str())
+            .unwrap_or_default()
+    } else {
+        &captures[0]
+    };
+    Ok(value.to_owned())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{http::Json, test_util::assert_err};
+    use crate::{
+        http::{ContentType, Json},
+        test_util::assert_err,
+    };
     use rstest::rstest;
     use serde_json::json;
 
@@ -106,6 +497,178 @@ mod tests {
         assert_err!(query.query_to_string(&*content), expected_err);
     }
 
+    const XML: &str = r#"<?xml version="1.0"?>
+<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope">
+  <soap:Body>
+    <user id="3"><name>Fish</name><role>admin</role><role>dev</role></user>
+  </soap:Body>
+</soap:Envelope>"#;
+
+    const HTML: &str = r#"<html><body>
+  <form><input name="csrf" value="abc123"></form>
+  <ul><li class="item">one</li><li class="item">two</li></ul>
+  <p id="greeting">Hello, <b>World</b>!</p>
+</body></html>"#;
+
+    #[rstest]
+    #[case::xpath_text(SelectorType::Xpath, "//user/name", XML, "Fish")]
+    #[case::xpath_attribute(SelectorType::Xpath, "//user/@id", XML, "3")]
+    #[case::xpath_namespace(
+        SelectorType::Xpath,
+        "/soap:Envelope/soap:Body/user/name",
+        XML,
+        "Fish"
+    )]
+    #[case::xpath_axis(SelectorType::Xpath, "//user/child::name", XML, "Fish")]
+    #[case::xpath_scalar(SelectorType::Xpath, "count(//role)", XML, "2")]
+    #[case::css_text(SelectorType::Css, "#greeting", HTML, "Hello, World!")]
+    #[case::css_attribute(
+        SelectorType::Css,
+        "input[name=csrf]::attr(value)",
+        HTML,
+        "abc123"
+    )]
+    #[case::css_missing_attribute(
+        SelectorType::Css,
+        "input::attr(bogus)",
+        HTML,
+        ""
+    )]
+    #[case::regex_match(SelectorType::Regex, r"\d+", "id: 42, 43", "42")]
+    #[case::regex_group(
+        SelectorType::Regex,
+        r"token=(\w+)",
+        "status=ok token=abc123",
+        "abc123"
+    )]
+    #[case::regex_optional_group(SelectorType::Regex, "a(b)?", "ac", "")]
+    fn test_query_text(
+        #[case] selector_type: SelectorType,
+        #[case] selector: &str,
+        #[case] content: &str,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(
+            selector_type
+                .query_text(selector, content.as_bytes())
+                .unwrap(),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::xpath_invalid(SelectorType::Xpath, "//[", XML, "Invalid XPath")]
+    #[case::xpath_too_many(
+        SelectorType::Xpath,
+        "//role",
+        XML,
+        "Expected exactly one result from query, but got 2"
+    )]
+    #[case::xpath_none(
+        SelectorType::Xpath,
+        "//bogus",
+        XML,
+        "Expected exactly one result from query, but got 0"
+    )]
+    #[case::xpath_unknown_prefix(
+        SelectorType::Xpath,
+        "//bogus:user[@a='x:y']",
+        XML,
+        "Namespace prefix `bogus` is not declared in the document"
+    )]
+    #[case::xpath_invalid_xml(SelectorType::Xpath, "/a", "<a>", "Parsing")]
+    #[case::css_invalid(SelectorType::Css, "li[", HTML, "Invalid CSS")]
+    #[case::css_too_many(
+        SelectorType::Css,
+        "li.item",
+        HTML,
+        "Expected exactly one result from query, but got 2"
+    )]
+    #[case::regex_invalid(SelectorType::Regex, "(", "", "Invalid regex")]
+    #[case::regex_no_match(
+        SelectorType::Regex,
+        r"\d+",
+        "none",
+        "Regex `\\d+` did not match"
+    )]
+    fn test_query_text_error(
+        #[case] selector_type: SelectorType,
+        #[case] selector: &str,
+        #[case] content: &str,
+        #[case] expected_error: &str,
+    ) {
+        assert_err!(
+            selector_type.query_text(selector, content.as_bytes()),
+            expected_error
+        );
+    }
+
+    /// Display queries allow any number of results, and prettify them
+    #[rstest]
+    #[case::jsonpath(
+        SelectorType::Jsonpath,
+        "$[*].id",
+        ContentType::Json,
+        r#"[{"id": 1}, {"id": 2}]"#,
+        "[\n  1,\n  2\n]"
+    )]
+    #[case::xpath_elements(
+        SelectorType::Xpath,
+        "//role",
+        ContentType::Xml,
+        XML,
+        "<role>admin</role>\n<role>dev</role>"
+    )]
+    #[case::xpath_nested(
+        SelectorType::Xpath,
+        "//soap:Body",
+        ContentType::Xml,
+        XML,
+        "<soap:Body>\n  <user id=\"3\">\n    <name>Fish</name>\n    \
+        <role>admin</role>\n    <role>dev</role>\n  </user>\n</soap:Body>"
+    )]
+    #[case::xpath_none(
+        SelectorType::Xpath,
+        "//bogus",
+        ContentType::Xml,
+        XML,
+        ""
+    )]
+    #[case::xpath_scalar(
+        SelectorType::Xpath,
+        "count(//role)",
+        ContentType::Xml,
+        XML,
+        "2"
+    )]
+    #[case::css_elements(
+        SelectorType::Css,
+        "li.item",
+        ContentType::Html,
+        HTML,
+        "<li class=\"item\">one</li>\n<li class=\"item\">two</li>"
+    )]
+    #[case::css_attribute(
+        SelectorType::Css,
+        "li::attr(class)",
+        ContentType::Html,
+        HTML,
+        "item\nitem"
+    )]
+    fn test_query_content(
+        #[case] selector_type: SelectorType,
+        #[case] selector: &str,
+        #[case] content_type: ContentType,
+        #[case] content: &str,
+        #[case] expected: &str,
+    ) {
+        let content = content_type.parse_content(content.as_bytes()).unwrap();
+        assert_eq!(
+            selector_type.query_content(selector, &*content).unwrap(),
+            expected
+        );
+    }
+
     /// Helper to create JSON content
     fn json(value: serde_json::Value) -> Box<dyn ResponseContent> {
         Box::new(Json::from(value))
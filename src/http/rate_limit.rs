@@ -0,0 +1,135 @@
+//! Per-host request rate limits, to avoid tripping quotas on strict APIs
+
+use crate::collection::RateLimit;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::{self, Instant};
+use tracing::info;
+
+/// Token buckets for rate-limited hosts, created on demand. Recipes that
+/// override the limit get their own bucket, so buckets are keyed by the limit
+/// as well as the host.
+#[derive(Debug, Default)]
+pub struct RateLimiters(Mutex<HashMap<(String, RateLimit), Arc<TokenBucket>>>);
+
+impl RateLimiters {
+    /// Get the bucket for a host+limit, creating a full one if needed
+    pub fn get(&self, host: &str, limit: &RateLimit) -> Arc<TokenBucket> {
+        let mut buckets = self.0.lock().expect("Rate limiter lock poisoned");
+        let bucket = buckets
+            .entry((host.to_owned(), limit.clone()))
+            .or_insert_with(|| Arc::new(TokenBucket::new(limit)));
+        Arc::clone(bucket)
+    }
+}
+
+/// A token bucket, shared by every request to a host. Each request takes a
+/// token, and tokens are refilled at a steady rate up to the bucket's capacity.
+/// If the bucket is empty, the request waits for the next token.
+#[derive(Debug)]
+pub struct TokenBucket {
+    /// Max number of tokens, i.e. the largest burst of requests allowed
+    capacity: f64,
+    /// Tokens added per second
+    refill_rate: f64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    /// This can go negative, which means requests are queued up waiting for
+    /// tokens that haven't been refilled yet
+    tokens: f64,
+    /// When tokens were last counted
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// Create a full bucket
+    pub fn new(limit: &RateLimit) -> Self {
+        // Avoid dividing by zero on a nonsensical limit
+        let capacity = f64::from(limit.requests.max(1));
+        let period = limit.per.max(Duration::from_millis(1));
+        Self {
+            capacity,
+            refill_rate: capacity / period.as_secs_f64(),
+            state: Mutex::new(BucketState {
+                tokens: capacity,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Take a token, waiting until one is available if necessary
+    pub async fn acquire(&self) {
+        let wait = self.reserve();
+        if !wait.is_zero() {
+            info!(?wait, "Rate limit reached, waiting");
+            time::sleep(wait).await;
+        }
+    }
+
+    /// Take a token, even if it hasn't been refilled yet, and return how long
+    /// to wait until it is. Reserving up front means concurrent requests queue
+    /// up in order, without holding the lock while waiting.
+    fn reserve(&self) -> Duration {
+        let mut state = self.state.lock().expect("Rate limiter lock poisoned");
+        let now = Instant::now();
+        let elapsed = now.duration_since(state.updated).as_secs_f64();
+        state.tokens =
+            (state.tokens + elapsed * self.refill_rate).min(self.capacity);
+        state.updated = now;
+
+        state.tokens -= 1.0;
+        if state.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-state.tokens / self.refill_rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A full bucket allows a burst, then requests are spaced out
+    #[test]
+    fn test_reserve() {
+        let bucket = TokenBucket::new(&RateLimit {
+            requests: 2,
+            per: Duration::from_secs(10),
+        });
+        assert_eq!(bucket.reserve(), Duration::ZERO);
+        assert_eq!(bucket.reserve(), Duration::ZERO);
+        // Each subsequent request waits another 5s
+        let wait = bucket.reserve();
+        assert!(
+            wait > Duration::from_millis(4900)
+                && wait <= Duration::from_secs(5)
+        );
+        let wait = bucket.reserve();
+        assert!(
+            wait > Duration::from_millis(9900)
+                && wait <= Duration::from_secs(10)
+        );
+    }
+
+    /// Requests over the limit should wait for a token to be refilled
+    #[tokio::test]
+    async fn test_acquire() {
+        let bucket = TokenBucket::new(&RateLimit {
+            requests: 1,
+            per: Duration::from_millis(100),
+        });
+        let start = Instant::now();
+        bucket.acquire().await;
+        assert!(start.elapsed() < Duration::from_millis(50));
+        bucket.acquire().await;
+        bucket.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+}
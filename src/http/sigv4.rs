@@ -0,0 +1,313 @@
+//! AWS Signature Version 4 request signing, for
+//! [Authentication::AwsSigV4]. This follows the spec at
+//! <https://docs.aws.amazon.com/IAM/latest/UserGuide/create-signed-request.html>
+//!
+//! [Authentication::AwsSigV4]: crate::collection::Authentication::AwsSigV4
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use itertools::Itertools;
+use reqwest::{
+    header::{self, HeaderValue},
+    Request,
+};
+use sha2::{Digest, Sha256};
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+/// Payload hash to use when the body is streamed, and therefore can't be
+/// hashed up front
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// Rendered credentials and scope for signing a request
+#[derive(Debug)]
+pub(super) struct SigningParams {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+    pub region: String,
+    pub service: String,
+}
+
+impl SigningParams {
+    /// Sign a fully built request. This adds the `Authorization` and
+    /// `X-Amz-*` headers. Every header already on the request is signed, so
+    /// the request shouldn't be modified after this.
+    pub fn sign(
+        &self,
+        request: &mut Request,
+        time: DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        let amz_date = time.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = time.format("%Y%m%d").to_string();
+        let is_s3 = self.service == "s3";
+
+        let payload_hash = match request.body() {
+            Some(body) => match body.as_bytes() {
+                Some(bytes) => hex_sha256(bytes),
+                None => UNSIGNED_PAYLOAD.to_owned(),
+            },
+            None => hex_sha256(b""),
+        };
+
+        let headers = request.headers_mut();
+        headers.insert("x-amz-date", HeaderValue::from_str(&amz_date)?);
+        // S3 requires the payload hash as a header. Other services don't
+        // accept it.
+        if is_s3 {
+            headers.insert(
+                "x-amz-content-sha256",
+                HeaderValue::from_str(&payload_hash)?,
+            );
+        }
+        if let Some(session_token) = &self.session_token {
+            headers.insert(
+                "x-amz-security-token",
+                HeaderValue::from_str(session_token)
+                    .context("Invalid session token")?,
+            );
+        }
+
+        // The Host header isn't added until the request is sent, but it
+        // still has to be signed
+        let url = request.url();
+        let host = match url.port() {
+            Some(port) => {
+                format!("{}:{port}", url.host_str().unwrap_or_default())
+            }
+            None => url.host_str().unwrap_or_default().to_owned(),
+        };
+        let mut canonical_headers: Vec<(String, String)> = request
+            .headers()
+            .keys()
+            .filter(|name| *name != header::HOST)
+            .map(|name| {
+                // Multiple values for the same header are joined with commas
+                let value = request
+                    .headers()
+                    .get_all(name)
+                    .iter()
+                    .map(|value| {
+                        normalize_whitespace(&String::from_utf8_lossy(
+                            value.as_bytes(),
+                        ))
+                    })
+                    .join(",");
+                (name.as_str().to_owned(), value)
+            })
+            .collect();
+        canonical_headers.push(("host".into(), host));
+        canonical_headers.sort();
+        let signed_headers =
+            canonical_headers.iter().map(|(name, _)| name).join(";");
+
+        let canonical_request = [
+            request.method().as_str(),
+            // S3 paths are only encoded once, everything else is encoded
+            // twice
+            &canonical_path(url.path(), !is_s3),
+            &canonical_query(url),
+            &canonical_headers
+                .iter()
+                .map(|(name, value)| format!("{name}:{value}\n"))
+                .join(""),
+            &signed_headers,
+            &payload_hash,
+        ]
+        .join("\n");
+
+        let scope = format!(
+            "{date}/{region}/{service}/aws4_request",
+            region = self.region,
+            service = self.service
+        );
+        let string_to_sign = [
+            ALGORITHM,
+            &amz_date,
+            &scope,
+            &hex_sha256(canonical_request.as_bytes()),
+        ]
+        .join("\n");
+
+        let signing_key =
+            [date.as_str(), &self.region, &self.service, "aws4_request"]
+                .into_iter()
+                .fold(
+                    format!("AWS4{}", self.secret_access_key).into_bytes(),
+                    |key, data| hmac_sha256(&key, data.as_bytes()),
+                );
+        let signature =
+            hex::encode(hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+
+        let authorization = format!(
+            "{ALGORITHM} Credential={access_key_id}/{scope}, \
+            SignedHeaders={signed_headers}, Signature={signature}",
+            access_key_id = self.access_key_id,
+        );
+        request.headers_mut().insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(&authorization)
+                .context("Invalid access key ID")?,
+        );
+        Ok(())
+    }
+}
+
+/// URI-encode each segment of an (already encoded) URL path. For everything
+/// besides S3, existing escapes are encoded again.
+fn canonical_path(path: &str, double_encode: bool) -> String {
+    if path.is_empty() {
+        return "/".into();
+    }
+    path.split('/')
+        .map(|segment| uri_encode(segment, double_encode))
+        .join("/")
+}
+
+/// Decode and re-encode query params, then sort them by name and value
+fn canonical_query(url: &reqwest::Url) -> String {
+    url.query_pairs()
+        .map(|(name, value)| {
+            (uri_encode(&name, true), uri_encode(&value, true))
+        })
+        .sorted()
+        .map(|(name, value)| format!("{name}={value}"))
+        .join("&")
+}
+
+/// Percent-encode everything except unreserved characters, per AWS's rules.
+/// If `encode_percent` is false, `%` is left as-is so existing escapes aren't
+/// encoded a second time.
+fn uri_encode(value: &str, encode_percent: bool) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'_'
+            | b'.'
+            | b'~' => (byte as char).to_string(),
+            b'%' if !encode_percent => "%".into(),
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// Trim a header value and collapse any runs of spaces into one
+fn normalize_whitespace(value: &str) -> String {
+    value.split_whitespace().join(" ")
+}
+
+fn hex_sha256(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key)
+        .expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use reqwest::{Client, Method};
+    use rstest::rstest;
+
+    /// Test against cases from the AWS signature test suite
+    #[rstest]
+    #[case::vanilla(
+        Method::GET,
+        "https://example.amazonaws.com/",
+        "SignedHeaders=host;x-amz-date, \
+        Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+    )]
+    #[case::query_order(
+        Method::GET,
+        "https://example.amazonaws.com/?Param2=value2&Param1=value1",
+        "SignedHeaders=host;x-amz-date, \
+        Signature=b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500"
+    )]
+    #[case::post(
+        Method::POST,
+        "https://example.amazonaws.com/",
+        "SignedHeaders=host;x-amz-date, \
+        Signature=5da7c1a2acd57cee7505fc6676e4e544621c30862966e37dddb68e92efbe5d6b"
+    )]
+    fn test_sign(
+        #[case] method: Method,
+        #[case] url: &str,
+        #[case] expected: &str,
+    ) {
+        let params = SigningParams {
+            access_key_id: "AKIDEXAMPLE".into(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY"
+                .into(),
+            session_token: None,
+            region: "us-east-1".into(),
+            service: "service".into(),
+        };
+        let mut request = Client::new().request(method, url).build().unwrap();
+        let time = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+        params.sign(&mut request, time).unwrap();
+
+        let headers = request.headers();
+        assert_eq!(headers["x-amz-date"], "20150830T123600Z");
+        assert_eq!(
+            headers[header::AUTHORIZATION],
+            format!(
+                "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/\
+                service/aws4_request, {expected}"
+            )
+        );
+    }
+
+    /// S3 requests should include the payload hash header, and signed
+    /// requests should include the session token
+    #[test]
+    fn test_sign_s3() {
+        let params = SigningParams {
+            access_key_id: "AKIDEXAMPLE".into(),
+            secret_access_key: "secret".into(),
+            session_token: Some("token".into()),
+            region: "us-east-1".into(),
+            service: "s3".into(),
+        };
+        let mut request = Client::new()
+            .put("https://bucket.s3.amazonaws.com/my%20file.txt")
+            .body("hello")
+            .build()
+            .unwrap();
+        params.sign(&mut request, Utc::now()).unwrap();
+
+        let headers = request.headers();
+        assert_eq!(
+            headers["x-amz-content-sha256"],
+            // sha256("hello")
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(headers["x-amz-security-token"], "token");
+        assert!(headers[header::AUTHORIZATION].to_str().unwrap().contains(
+            "SignedHeaders=host;x-amz-content-sha256;x-amz-date;\
+                x-amz-security-token"
+        ));
+    }
+
+    #[rstest]
+    #[case::empty("", true, "/")]
+    #[case::plain("/documents/report", true, "/documents/report")]
+    #[case::double_encode("/my%20file", true, "/my%2520file")]
+    #[case::single_encode("/my%20file", false, "/my%20file")]
+    #[case::reserved("/a:b", false, "/a%3Ab")]
+    fn test_canonical_path(
+        #[case] path: &str,
+        #[case] double_encode: bool,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(canonical_path(path, double_encode), expected);
+    }
+}
@@ -0,0 +1,295 @@
+//! Server-Sent Events, i.e. `text/event-stream` responses. These responses
+//! are long-lived, so rather than waiting for the body to finish, we parse
+//! events as they arrive and expose them to the consumer incrementally.
+//!
+//! Spec: <https://html.spec.whatwg.org/multipage/server-sent-events.html>
+
+use chrono::{DateTime, Utc};
+use reqwest::{
+    header::{self, HeaderMap},
+    StatusCode, Version,
+};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Callback to notify a consumer of new data on an event stream. Called once
+/// when the response headers are received, then again for every chunk of body
+/// that arrives.
+pub type EventStreamListener = Box<dyn Fn(&Arc<EventStream>) + Send + Sync>;
+
+/// An event stream response that is still being received. Metadata is
+/// available immediately, and events are appended as they're parsed. Once the
+/// stream ends, the full body is available in the regular
+/// [Exchange](super::Exchange) like any other response.
+#[derive(Debug)]
+pub struct EventStream {
+    /// When the request for this stream was launched
+    pub start_time: DateTime<Utc>,
+    pub version: Version,
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    content: Mutex<EventStreamContent>,
+}
+
+/// Everything received on an event stream so far
+#[derive(Debug, Default)]
+pub struct EventStreamContent {
+    pub events: Vec<ServerSentEvent>,
+    /// Total bytes of body received, including partial events
+    pub size: u64,
+}
+
+/// A single event dispatched by the server
+#[derive(Clone, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct ServerSentEvent {
+    /// When the event was received
+    pub time: DateTime<Utc>,
+    /// Event type. Defaults to `message` if the server doesn't specify one
+    pub event: String,
+    /// Most recent event ID sent by the server. IDs carry over to subsequent
+    /// events until changed
+    pub id: Option<String>,
+    /// All `data` lines for the event, joined by newlines
+    pub data: String,
+}
+
+impl EventStream {
+    pub fn new(
+        start_time: DateTime<Utc>,
+        version: Version,
+        status: StatusCode,
+        headers: HeaderMap,
+    ) -> Self {
+        Self {
+            start_time,
+            version,
+            status,
+            headers,
+            content: Default::default(),
+        }
+    }
+
+    /// Get everything received so far. This locks the stream, so don't hold
+    /// onto the guard any longer than needed.
+    pub fn content(&self) -> MutexGuard<'_, EventStreamContent> {
+        self.content.lock().expect("Event stream lock poisoned")
+    }
+
+    /// Record a received chunk of body, along with the events parsed from it
+    pub(super) fn push(&self, size: usize, events: Vec<ServerSentEvent>) {
+        let mut content = self.content();
+        content.size += size as u64;
+        content.events.extend(events);
+    }
+}
+
+/// Streams are only equal if they're the same stream. Comparing the contents
+/// would require locking both
+#[cfg(test)]
+impl PartialEq for EventStream {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+/// Does this response contain an event stream?
+pub(super) fn is_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<mime::Mime>().ok())
+        .is_some_and(|mime| {
+            mime.type_() == mime::TEXT && mime.subtype() == mime::EVENT_STREAM
+        })
+}
+
+/// Incremental parser for an event stream body. Chunks can split lines (or
+/// even UTF-8 characters) anywhere, so incomplete lines are buffered until
+/// the rest arrives.
+///
+/// Lines can end in `\n` or `\r\n`. The spec also allows a lone `\r`, but
+/// that's vanishingly rare in practice and would make handling chunk
+/// boundaries a lot more annoying, so it isn't supported.
+#[derive(Debug, Default)]
+pub(super) struct EventStreamParser {
+    /// Bytes of a line that hasn't been terminated yet
+    line: Vec<u8>,
+    /// Type of the event being built
+    event: Option<String>,
+    /// Data of the event being built, with a trailing newline after each line
+    data: String,
+    /// ID of the most recent event. Persists between events
+    last_id: Option<String>,
+}
+
+impl EventStreamParser {
+    /// Parse a chunk of the body, returning every event that it completed
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<ServerSentEvent> {
+        let mut events = Vec::new();
+        let mut rest = chunk;
+        while let Some(index) = rest.iter().position(|&byte| byte == b'\n') {
+            self.line.extend_from_slice(&rest[..index]);
+            rest = &rest[index + 1..];
+
+            let mut line = std::mem::take(&mut self.line);
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            events.extend(self.process_line(&String::from_utf8_lossy(&line)));
+        }
+        self.line.extend_from_slice(rest);
+        events
+    }
+
+    /// Handle a single complete line. If it's a blank line, the pending event
+    /// (if any) is dispatched
+    fn process_line(&mut self, line: &str) -> Option<ServerSentEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        // Lines starting with a colon are comments
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => {
+                (field, value.strip_prefix(' ').unwrap_or(value))
+            }
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_owned()),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            // IDs containing null are ignored, per the spec
+            "id" if !value.contains('\0') => {
+                self.last_id = Some(value.to_owned())
+            }
+            // Reconnection isn't supported, so `retry` is meaningless to us.
+            // Unknown fields are ignored, per the spec
+            _ => {}
+        }
+        None
+    }
+
+    /// Build the pending event and reset for the next one. Events with no
+    /// data aren't dispatched
+    fn dispatch(&mut self) -> Option<ServerSentEvent> {
+        let event = self.event.take();
+        let mut data = std::mem::take(&mut self.data);
+        if data.is_empty() {
+            return None;
+        }
+        data.pop(); // Trailing newline
+        Some(ServerSentEvent {
+            time: Utc::now(),
+            event: event.unwrap_or_else(|| "message".into()),
+            id: self.last_id.clone(),
+            data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    use rstest::rstest;
+
+    /// Strip timestamps from events so they can be compared
+    fn simplify(
+        events: Vec<ServerSentEvent>,
+    ) -> Vec<(String, Option<String>, String)> {
+        events
+            .into_iter()
+            .map(|event| (event.event, event.id, event.data))
+            .collect()
+    }
+
+    fn event(
+        event: &str,
+        id: Option<&str>,
+        data: &str,
+    ) -> (String, Option<String>, String) {
+        (event.into(), id.map(String::from), data.into())
+    }
+
+    #[rstest]
+    #[case::event_stream("text/event-stream", true)]
+    #[case::charset("text/event-stream; charset=utf-8", true)]
+    #[case::json("application/json", false)]
+    #[case::invalid("not a mime", false)]
+    fn test_is_event_stream(
+        #[case] content_type: &str,
+        #[case] expected: bool,
+    ) {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_str(content_type).unwrap(),
+        );
+        assert_eq!(is_event_stream(&headers), expected);
+        assert!(!is_event_stream(&HeaderMap::new()));
+    }
+
+    #[rstest]
+    #[case::simple("data: hello\n\n", vec![event("message", None, "hello")])]
+    #[case::crlf("data: hello\r\n\r\n", vec![event("message", None, "hello")])]
+    #[case::multiline_data(
+        "data: line 1\ndata: line 2\n\n",
+        vec![event("message", None, "line 1\nline 2")],
+    )]
+    #[case::no_space("data:hello\n\n", vec![event("message", None, "hello")])]
+    #[case::event_type_and_id(
+        "event: update\nid: 1\ndata: {}\n\ndata: next\n\n",
+        vec![
+            event("update", Some("1"), "{}"),
+            // ID carries over, event type doesn't
+            event("message", Some("1"), "next"),
+        ],
+    )]
+    #[case::comments_and_unknown_fields(
+        ": keepalive\nretry: 1000\nfoo: bar\ndata: hello\n\n",
+        vec![event("message", None, "hello")],
+    )]
+    #[case::no_data(
+        "event: empty\n\ndata: hello\n\n",
+        vec![event("message", None, "hello")],
+    )]
+    #[case::field_without_colon("data\n\n", vec![event("message", None, "")])]
+    #[case::unterminated("data: hello\n", vec![])]
+    fn test_parse(
+        #[case] body: &str,
+        #[case] expected: Vec<(String, Option<String>, String)>,
+    ) {
+        let mut parser = EventStreamParser::default();
+        assert_eq!(simplify(parser.push(body.as_bytes())), expected);
+    }
+
+    /// Events split across chunks are buffered until complete
+    #[test]
+    fn test_parse_chunked() {
+        let mut parser = EventStreamParser::default();
+        assert_eq!(parser.push(b"da"), vec![]);
+        assert_eq!(parser.push(b"ta: hel"), vec![]);
+        assert_eq!(parser.push(b"lo\r"), vec![]);
+        assert_eq!(parser.push(b"\n"), vec![]);
+        assert_eq!(
+            simplify(parser.push(b"\ndata: world\n")),
+            vec![event("message", None, "hello")]
+        );
+        // Split in the middle of a multi-byte character
+        let bytes = "\ndata: ☃\n\n".as_bytes();
+        assert_eq!(
+            simplify(parser.push(&bytes[..9])),
+            vec![event("message", None, "world")]
+        );
+        assert_eq!(
+            simplify(parser.push(&bytes[9..])),
+            vec![event("message", None, "☃")]
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! Artificial bandwidth limits, to simulate slow clients
+
+use bytes::Bytes;
+use bytesize::ByteSize;
+use futures::{stream, Stream, StreamExt};
+use reqwest::{
+    header::{self, HeaderValue},
+    Body, Request,
+};
+use std::{convert::Infallible, time::Duration};
+use tokio::time::{self, Instant};
+
+/// How many chunks to split each second's worth of upload into. Smaller chunks
+/// make for a smoother transfer rate.
+const UPLOAD_CHUNKS_PER_SECOND: u64 = 10;
+
+/// Track how many bytes have been transferred, and wait as needed to keep the
+/// average rate under a limit. The clock starts when the limiter is created.
+#[derive(Debug)]
+pub struct RateLimiter {
+    bytes_per_second: u64,
+    start: Instant,
+    transferred: u64,
+}
+
+impl RateLimiter {
+    pub fn new(limit: ByteSize) -> Self {
+        Self {
+            // Avoid dividing by zero on a nonsensical limit
+            bytes_per_second: limit.as_u64().max(1),
+            start: Instant::now(),
+            transferred: 0,
+        }
+    }
+
+    /// Record that some bytes were transferred, then wait until the average
+    /// rate since the start is back under the limit
+    pub async fn consume(&mut self, bytes: usize) {
+        self.transferred += bytes as u64;
+        let target = Duration::from_secs_f64(
+            self.transferred as f64 / self.bytes_per_second as f64,
+        );
+        time::sleep_until(self.start + target).await;
+    }
+}
+
+/// Replace a request's body with a stream that will upload no faster than the
+/// given limit. If the request has no body, do nothing.
+pub fn throttle_request_body(request: &mut Request, limit: ByteSize) {
+    let Some(body) = request
+        .body()
+        .and_then(Body::as_bytes)
+        .map(Bytes::copy_from_slice)
+    else {
+        return;
+    };
+    // Streamed bodies are sent with chunked encoding by default. Set the length
+    // explicitly so the request looks the same as it would unthrottled
+    request
+        .headers_mut()
+        .entry(header::CONTENT_LENGTH)
+        .or_insert_with(|| HeaderValue::from(body.len()));
+    *request.body_mut() = Some(Body::wrap_stream(throttle_body(body, limit)));
+}
+
+/// Split a request body into a stream of chunks that will be emitted no faster
+/// than the given limit
+fn throttle_body(
+    body: Bytes,
+    limit: ByteSize,
+) -> impl Stream<Item = Result<Bytes, Infallible>> {
+    let chunk_size =
+        (limit.as_u64() / UPLOAD_CHUNKS_PER_SECOND).max(1) as usize;
+    // Don't start the clock until the first chunk is requested, which is when
+    // the request is actually sent
+    stream::unfold((body, None), move |(mut body, limiter)| async move {
+        if body.is_empty() {
+            return None;
+        }
+        let mut limiter: RateLimiter =
+            limiter.unwrap_or_else(|| RateLimiter::new(limit));
+        let chunk = body.split_to(chunk_size.min(body.len()));
+        limiter.consume(chunk.len()).await;
+        Some((Ok(chunk), (body, Some(limiter))))
+    })
+}
+
+/// Throttle an existing stream of chunks, so it's emitted no faster than the
+/// given limit. Unlike [throttle_body], chunks aren't re-split, so the stream
+/// should produce reasonably small chunks for a smooth rate.
+pub fn throttle_stream<E>(
+    stream: impl Stream<Item = Result<Bytes, E>> + Send + 'static,
+    limit: ByteSize,
+) -> impl Stream<Item = Result<Bytes, E>> {
+    stream::unfold(
+        (Box::pin(stream), None),
+        move |(mut stream, limiter)| async move {
+            let chunk = stream.next().await?;
+            let mut limiter: RateLimiter =
+                limiter.unwrap_or_else(|| RateLimiter::new(limit));
+            if let Ok(chunk) = &chunk {
+                limiter.consume(chunk.len()).await;
+            }
+            Some((chunk, (stream, Some(limiter))))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Chunks should be emitted no faster than the given rate
+    #[tokio::test]
+    async fn test_throttle_body() {
+        let start = Instant::now();
+        let chunks: Vec<Bytes> = throttle_body(
+            Bytes::from_static(b"0123456789abcdefghij"),
+            ByteSize::b(100),
+        )
+        .map(Result::unwrap)
+        .collect()
+        .await;
+
+        // 100 B/s => 10 byte chunks, 20 bytes total => 0.2 seconds
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks.concat(), b"0123456789abcdefghij");
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    /// Existing chunks should be passed through as-is, no faster than the
+    /// given rate
+    #[tokio::test]
+    async fn test_throttle_stream() {
+        let start = Instant::now();
+        let chunks = stream::iter([
+            Ok::<_, Infallible>(Bytes::from_static(b"0123456789")),
+            Ok(Bytes::from_static(b"abcdefghij")),
+        ]);
+        let chunks: Vec<Bytes> = throttle_stream(chunks, ByteSize::b(100))
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        assert_eq!(chunks.concat(), b"0123456789abcdefghij");
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+}
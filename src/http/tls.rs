@@ -0,0 +1,469 @@
+//! TLS configuration for HTTP clients. Since reqwest won't touch a
+//! preconfigured TLS config, this replicates everything reqwest would normally
+//! configure, plus hooks to [instrument](super::instrument) the handshake.
+
+use crate::{
+    collection::HttpVersion,
+    http::{instrument::record, x509, TlsInfo},
+};
+use rustls::{
+    client::{
+        danger::{
+            HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+        },
+        ClientSessionMemoryCache, ClientSessionStore, Resumption,
+        Tls12ClientSessionValue, Tls13ClientSessionValue, WebPkiServerVerifier,
+    },
+    crypto::{
+        cipher::{
+            AeadKey, Iv, KeyBlockShape, MessageDecrypter, MessageEncrypter,
+            Tls12AeadAlgorithm, Tls13AeadAlgorithm, UnsupportedOperationError,
+        },
+        ring, CipherSuiteCommon, CryptoProvider,
+    },
+    pki_types::{CertificateDer, ServerName, UnixTime},
+    CipherSuite, ClientConfig, ConnectionTrafficSecrets, DigitallySignedStruct,
+    KeyLog, NamedGroup, ProtocolVersion, RootCertStore, SignatureScheme,
+    SupportedCipherSuite, Tls12CipherSuite, Tls13CipherSuite,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+};
+use tokio::time::Instant;
+use url::Url;
+
+/// Max number of servers to remember key exchange hints for. This is
+/// rustls's default session cache size
+const KX_HINT_CACHE_SIZE: usize = 256;
+
+/// Build the TLS config for a client
+pub(super) fn tls_config(
+    danger: bool,
+    http_version: Option<HttpVersion>,
+) -> ClientConfig {
+    let provider = provider();
+    let inner: Arc<dyn ServerCertVerifier> = if danger {
+        Arc::new(NoVerifier(Arc::clone(&provider)))
+    } else {
+        let mut roots = RootCertStore::empty();
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        WebPkiServerVerifier::builder_with_provider(
+            Arc::new(roots),
+            Arc::clone(&provider),
+        )
+        .build()
+        .expect("Built-in root certificates should be valid")
+    };
+    let mut config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .expect("Default cipher suites should support default TLS versions")
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(CapturingVerifier(inner)))
+        .with_no_client_auth();
+    config.alpn_protocols = match http_version {
+        Some(HttpVersion::Http1_1) => vec![b"http/1.1".to_vec()],
+        Some(HttpVersion::Http2) => vec![b"h2".to_vec()],
+        None => vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+    };
+    config.resumption = Resumption::store(Arc::new(NoResumptionStore(
+        ClientSessionMemoryCache::new(KX_HINT_CACHE_SIZE),
+    )));
+    config.key_log = Arc::new(TimingKeyLog);
+    config
+}
+
+/// Convert captured connection details into a displayable form. Certificates
+/// that can't be parsed are skipped.
+pub(super) fn tls_info(
+    protocol: ProtocolVersion,
+    cipher_suite: CipherSuite,
+    certificates: &[CertificateDer<'_>],
+) -> TlsInfo {
+    let protocol = match protocol {
+        ProtocolVersion::TLSv1_2 => "TLS 1.2".into(),
+        ProtocolVersion::TLSv1_3 => "TLS 1.3".into(),
+        other => format!("{other:?}"),
+    };
+    TlsInfo {
+        protocol,
+        cipher_suite: format!("{cipher_suite:?}"),
+        certificates: certificates
+            .iter()
+            .filter_map(|certificate| x509::parse(certificate))
+            .collect(),
+    }
+}
+
+/// The most recent TLS details for each server, keyed by host and port. A
+/// request that reuses a pooled connection doesn't see a handshake, so it
+/// gets the details from whichever request opened the connection.
+#[derive(Debug, Default)]
+pub(super) struct TlsInfoCache(Mutex<HashMap<String, TlsInfo>>);
+
+impl TlsInfoCache {
+    /// Store details of a new connection to the URL's server
+    pub fn insert(&self, url: &Url, info: TlsInfo) {
+        if let Some(key) = Self::key(url) {
+            self.0
+                .lock()
+                .expect("TLS info cache lock poisoned")
+                .insert(key, info);
+        }
+    }
+
+    /// Get details of the last connection to the URL's server. Always `None`
+    /// for plain HTTP
+    pub fn get(&self, url: &Url) -> Option<TlsInfo> {
+        let key = Self::key(url)?;
+        self.0
+            .lock()
+            .expect("TLS info cache lock poisoned")
+            .get(&key)
+            .cloned()
+    }
+
+    fn key(url: &Url) -> Option<String> {
+        if url.scheme() != "https" {
+            return None;
+        }
+        Some(format!(
+            "{}:{}",
+            url.host_str()?,
+            url.port_or_known_default()?
+        ))
+    }
+}
+
+/// Get the crypto provider for all clients. This is *ring*'s default provider,
+/// with each cipher suite wrapped to record when it's used. The wrapped suites
+/// are leaked to get the static lifetime rustls requires, so they're only
+/// built once.
+fn provider() -> Arc<CryptoProvider> {
+    static PROVIDER: OnceLock<Arc<CryptoProvider>> = OnceLock::new();
+    let provider = PROVIDER.get_or_init(|| {
+        let mut provider = ring::default_provider();
+        for suite in &mut provider.cipher_suites {
+            *suite = match *suite {
+                SupportedCipherSuite::Tls12(inner) => {
+                    SupportedCipherSuite::Tls12(Box::leak(Box::new(
+                        Tls12CipherSuite {
+                            common: copy_common(&inner.common),
+                            aead_alg: Box::leak(Box::new(RecordingAead {
+                                inner: inner.aead_alg,
+                                protocol: ProtocolVersion::TLSv1_2,
+                                suite: inner.common.suite,
+                            })),
+                            ..*inner
+                        },
+                    )))
+                }
+                SupportedCipherSuite::Tls13(inner) => {
+                    SupportedCipherSuite::Tls13(Box::leak(Box::new(
+                        Tls13CipherSuite {
+                            common: copy_common(&inner.common),
+                            aead_alg: Box::leak(Box::new(RecordingAead {
+                                inner: inner.aead_alg,
+                                protocol: ProtocolVersion::TLSv1_3,
+                                suite: inner.common.suite,
+                            })),
+                            ..*inner
+                        },
+                    )))
+                }
+            };
+        }
+        Arc::new(provider)
+    });
+    Arc::clone(provider)
+}
+
+/// [CipherSuiteCommon] isn't `Clone`, but all its fields are
+fn copy_common(common: &CipherSuiteCommon) -> CipherSuiteCommon {
+    CipherSuiteCommon {
+        suite: common.suite,
+        hash_provider: common.hash_provider,
+        confidentiality_limit: common.confidentiality_limit,
+        integrity_limit: common.integrity_limit,
+    }
+}
+
+/// Wraps a cipher suite's encryption algorithm to record when it's used. Keys
+/// are only ever set up for the negotiated suite, so this tells us which suite
+/// that was.
+struct RecordingAead<T: ?Sized + 'static> {
+    inner: &'static T,
+    protocol: ProtocolVersion,
+    suite: CipherSuite,
+}
+
+impl<T: ?Sized> RecordingAead<T> {
+    fn record(&self) {
+        record(|events| {
+            events.cipher_suite = Some((self.protocol, self.suite));
+        });
+    }
+}
+
+impl Tls12AeadAlgorithm for RecordingAead<dyn Tls12AeadAlgorithm> {
+    fn encrypter(
+        &self,
+        key: AeadKey,
+        iv: &[u8],
+        extra: &[u8],
+    ) -> Box<dyn MessageEncrypter> {
+        self.record();
+        self.inner.encrypter(key, iv, extra)
+    }
+
+    fn decrypter(&self, key: AeadKey, iv: &[u8]) -> Box<dyn MessageDecrypter> {
+        self.inner.decrypter(key, iv)
+    }
+
+    fn key_block_shape(&self) -> KeyBlockShape {
+        self.inner.key_block_shape()
+    }
+
+    fn extract_keys(
+        &self,
+        key: AeadKey,
+        iv: &[u8],
+        explicit: &[u8],
+    ) -> Result<ConnectionTrafficSecrets, UnsupportedOperationError> {
+        self.inner.extract_keys(key, iv, explicit)
+    }
+}
+
+impl Tls13AeadAlgorithm for RecordingAead<dyn Tls13AeadAlgorithm> {
+    fn encrypter(&self, key: AeadKey, iv: Iv) -> Box<dyn MessageEncrypter> {
+        self.record();
+        self.inner.encrypter(key, iv)
+    }
+
+    fn decrypter(&self, key: AeadKey, iv: Iv) -> Box<dyn MessageDecrypter> {
+        self.inner.decrypter(key, iv)
+    }
+
+    fn key_len(&self) -> usize {
+        self.inner.key_len()
+    }
+
+    fn extract_keys(
+        &self,
+        key: AeadKey,
+        iv: Iv,
+    ) -> Result<ConnectionTrafficSecrets, UnsupportedOperationError> {
+        self.inner.extract_keys(key, iv)
+    }
+}
+
+/// Records the certificate chain sent by the server, then passes it along to
+/// the real verifier
+#[derive(Debug)]
+struct CapturingVerifier(Arc<dyn ServerCertVerifier>);
+
+impl ServerCertVerifier for CapturingVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        record(|events| {
+            events.certificates = [end_entity]
+                .into_iter()
+                .chain(intermediates)
+                .map(|certificate| certificate.clone().into_owned())
+                .collect();
+        });
+        self.0.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.0.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.0.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.supported_verify_schemes()
+    }
+}
+
+/// Verifier for clients that ignore certificate errors
+#[derive(Debug)]
+struct NoVerifier(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for NoVerifier {
+    fn verify_server_cert(
+        &self,
+        _: &CertificateDer<'_>,
+        _: &[CertificateDer<'_>],
+        _: &ServerName<'_>,
+        _: &[u8],
+        _: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _: &[u8],
+        _: &CertificateDer<'_>,
+        _: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        _: &[u8],
+        _: &CertificateDer<'_>,
+        _: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Session store that never resumes sessions. A resumed session skips the
+/// server's certificate, and we want to capture it for every connection. Key
+/// exchange hints are still remembered, to avoid an extra round trip.
+///
+/// This also records when the handshake starts. rustls looks for a session to
+/// resume while building the ClientHello, which is the first thing it does
+/// once the TCP connection is open.
+#[derive(Debug)]
+struct NoResumptionStore(ClientSessionMemoryCache);
+
+impl ClientSessionStore for NoResumptionStore {
+    fn set_kx_hint(&self, server_name: ServerName<'static>, group: NamedGroup) {
+        self.0.set_kx_hint(server_name, group);
+    }
+
+    fn kx_hint(&self, server_name: &ServerName<'_>) -> Option<NamedGroup> {
+        self.0.kx_hint(server_name)
+    }
+
+    fn set_tls12_session(
+        &self,
+        _: ServerName<'static>,
+        _: Tls12ClientSessionValue,
+    ) {
+    }
+
+    fn tls12_session(
+        &self,
+        _: &ServerName<'_>,
+    ) -> Option<Tls12ClientSessionValue> {
+        None
+    }
+
+    fn remove_tls12_session(&self, _: &ServerName<'static>) {}
+
+    fn insert_tls13_ticket(
+        &self,
+        _: ServerName<'static>,
+        _: Tls13ClientSessionValue,
+    ) {
+    }
+
+    fn take_tls13_ticket(
+        &self,
+        _: &ServerName<'static>,
+    ) -> Option<Tls13ClientSessionValue> {
+        record(|events| events.tls_start = Some(Instant::now()));
+        None
+    }
+}
+
+/// Records when the handshake finishes. rustls "logs" secrets as soon as
+/// they're derived, and the client's traffic secret is derived at the end of
+/// the handshake. For TLS 1.2, this is one round trip before the end, because
+/// the secret is derived before the client's final message. Nothing is
+/// actually logged.
+#[derive(Debug)]
+struct TimingKeyLog;
+
+impl TimingKeyLog {
+    const TLS12_LABEL: &'static str = "CLIENT_RANDOM";
+    const TLS13_LABEL: &'static str = "CLIENT_TRAFFIC_SECRET_0";
+}
+
+impl KeyLog for TimingKeyLog {
+    fn log(&self, _: &str, _: &[u8], _: &[u8]) {
+        record(|events| events.tls_end = Some(Instant::now()));
+    }
+
+    fn will_log(&self, label: &str) -> bool {
+        label == Self::TLS12_LABEL || label == Self::TLS13_LABEL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_data_dir;
+    use rstest::rstest;
+
+    #[test]
+    fn test_tls_info() {
+        let der =
+            std::fs::read(test_data_dir().join("certificate.der")).unwrap();
+        let info = tls_info(
+            ProtocolVersion::TLSv1_3,
+            CipherSuite::TLS13_AES_128_GCM_SHA256,
+            // Unparseable certificates are skipped
+            &[CertificateDer::from(der), CertificateDer::from(vec![0, 1])],
+        );
+        assert_eq!(info.protocol, "TLS 1.3");
+        assert_eq!(info.cipher_suite, "TLS13_AES_128_GCM_SHA256");
+        assert_eq!(info.certificates.len(), 1);
+        assert_eq!(
+            info.certificates[0].subject,
+            "C=US, O=Slumber Test, CN=example.com"
+        );
+    }
+
+    /// Cache is keyed by host and port, and never applies to plain HTTP
+    #[rstest]
+    #[case::same_origin("https://example.com/other", true)]
+    #[case::explicit_port("https://example.com:443/", true)]
+    #[case::different_port("https://example.com:8443/", false)]
+    #[case::different_host("https://other.com/", false)]
+    #[case::http("http://example.com/", false)]
+    fn test_tls_info_cache(#[case] url: &str, #[case] expected: bool) {
+        let cache = TlsInfoCache::default();
+        let info = TlsInfo {
+            protocol: "TLS 1.3".into(),
+            cipher_suite: "TLS13_AES_128_GCM_SHA256".into(),
+            certificates: vec![],
+        };
+        cache.insert(&"https://example.com/get".parse().unwrap(), info.clone());
+        cache.insert(&"http://example.com/get".parse().unwrap(), info.clone());
+        assert_eq!(cache.get(&url.parse().unwrap()), expected.then_some(info));
+    }
+}
@@ -0,0 +1,306 @@
+//! Minimal X.509 certificate parsing, to display certificate details to the
+//! user. This only extracts the fields we show, and does no validation of any
+//! kind. Actual verification is done by rustls.
+//!
+//! Spec: <https://www.rfc-editor.org/rfc/rfc5280#section-4.1>
+
+use crate::http::Certificate;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use itertools::Itertools;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+const TAG_BOOLEAN: u8 = 0x01;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_UTC_TIME: u8 = 0x17;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_BMP_STRING: u8 = 0x1e;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+/// `[0]`, wrapping the certificate version
+const TAG_VERSION: u8 = 0xa0;
+/// `[3]`, wrapping the extension list
+const TAG_EXTENSIONS: u8 = 0xa3;
+/// `dNSName` in a `GeneralName`
+const TAG_DNS_NAME: u8 = 0x82;
+/// `iPAddress` in a `GeneralName`
+const TAG_IP_ADDRESS: u8 = 0x87;
+
+/// OID for the subject alternative name extension (2.5.29.17)
+const OID_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+
+/// Parse a DER-encoded certificate. Return `None` if it's malformed
+pub fn parse(der: &[u8]) -> Option<Certificate> {
+    let mut tbs = Reader(der).expect(TAG_SEQUENCE)?.expect(TAG_SEQUENCE)?;
+    if tbs.peek() == Some(TAG_VERSION) {
+        tbs.read()?;
+    }
+    tbs.read()?; // Serial number
+    tbs.expect(TAG_SEQUENCE)?; // Signature algorithm
+    let issuer = format_name(tbs.expect(TAG_SEQUENCE)?)?;
+    let mut validity = tbs.expect(TAG_SEQUENCE)?;
+    let not_before = parse_time(validity.read()?)?;
+    let not_after = parse_time(validity.read()?)?;
+    let subject = format_name(tbs.expect(TAG_SEQUENCE)?)?;
+    tbs.expect(TAG_SEQUENCE)?; // Public key
+
+    // Skip the optional unique IDs to get to the extensions
+    let mut subject_alt_names = Vec::new();
+    while let Some((tag, content)) = tbs.read() {
+        if tag == TAG_EXTENSIONS {
+            subject_alt_names = parse_subject_alt_names(Reader(content))?;
+        }
+    }
+
+    Some(Certificate {
+        subject,
+        issuer,
+        subject_alt_names,
+        not_before,
+        not_after,
+    })
+}
+
+/// Format a distinguished name like `CN=example.com, O=Example`
+fn format_name(mut name: Reader<'_>) -> Option<String> {
+    let mut parts = Vec::new();
+    while !name.is_empty() {
+        let mut set = name.expect(TAG_SET)?;
+        while !set.is_empty() {
+            let mut attribute = set.expect(TAG_SEQUENCE)?;
+            let oid = attribute.expect_raw(TAG_OID)?;
+            let (tag, value) = attribute.read()?;
+            let key = match oid {
+                [0x55, 0x04, 0x03] => "CN".into(),
+                [0x55, 0x04, 0x06] => "C".into(),
+                [0x55, 0x04, 0x07] => "L".into(),
+                [0x55, 0x04, 0x08] => "ST".into(),
+                [0x55, 0x04, 0x0a] => "O".into(),
+                [0x55, 0x04, 0x0b] => "OU".into(),
+                _ => format_oid(oid),
+            };
+            let value = if tag == TAG_BMP_STRING {
+                // UTF-16BE
+                let units = value
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                    .collect_vec();
+                String::from_utf16_lossy(&units)
+            } else {
+                // Everything else we'd reasonably see is ASCII-compatible
+                String::from_utf8_lossy(value).into_owned()
+            };
+            parts.push(format!("{key}={value}"));
+        }
+    }
+    Some(parts.join(", "))
+}
+
+/// Format an OID in dotted notation, e.g. `2.5.4.3`
+fn format_oid(oid: &[u8]) -> String {
+    let mut components = Vec::new();
+    let mut value: u64 = 0;
+    for &byte in oid {
+        value = (value << 7) | u64::from(byte & 0x7f);
+        if byte & 0x80 == 0 {
+            if components.is_empty() {
+                // First byte packs the first two components together
+                let first = (value / 40).min(2);
+                components.push(first);
+                components.push(value - first * 40);
+            } else {
+                components.push(value);
+            }
+            value = 0;
+        }
+    }
+    components.iter().join(".")
+}
+
+/// Parse a `UTCTime` or `GeneralizedTime`. Certificates are required to use
+/// UTC with seconds, so we don't bother supporting other formats
+fn parse_time((tag, value): (u8, &[u8])) -> Option<DateTime<Utc>> {
+    let value = std::str::from_utf8(value).ok()?;
+    let value = match tag {
+        // Two-digit years: 50-99 are 19xx, 00-49 are 20xx
+        TAG_UTC_TIME => {
+            let century = if value.get(..2)? >= "50" { "19" } else { "20" };
+            format!("{century}{value}")
+        }
+        TAG_GENERALIZED_TIME => value.to_owned(),
+        _ => return None,
+    };
+    NaiveDateTime::parse_from_str(&value, "%Y%m%d%H%M%SZ")
+        .ok()
+        .map(|time| time.and_utc())
+}
+
+/// Get DNS names and IP addresses from the subject alternative name extension,
+/// if present
+fn parse_subject_alt_names(mut extensions: Reader<'_>) -> Option<Vec<String>> {
+    let mut extensions = extensions.expect(TAG_SEQUENCE)?;
+    while !extensions.is_empty() {
+        let mut extension = extensions.expect(TAG_SEQUENCE)?;
+        let oid = extension.expect_raw(TAG_OID)?;
+        if extension.peek() == Some(TAG_BOOLEAN) {
+            extension.read()?; // Critical flag
+        }
+        if oid != OID_SUBJECT_ALT_NAME {
+            continue;
+        }
+
+        let mut names =
+            extension.expect(TAG_OCTET_STRING)?.expect(TAG_SEQUENCE)?;
+        let mut parsed = Vec::new();
+        while let Some((tag, value)) = names.read() {
+            match tag {
+                TAG_DNS_NAME => {
+                    parsed.push(String::from_utf8_lossy(value).into_owned())
+                }
+                TAG_IP_ADDRESS => {
+                    let ip = if let Ok(octets) = <[u8; 4]>::try_from(value) {
+                        IpAddr::from(Ipv4Addr::from(octets))
+                    } else {
+                        IpAddr::from(Ipv6Addr::from(
+                            <[u8; 16]>::try_from(value).ok()?,
+                        ))
+                    };
+                    parsed.push(ip.to_string());
+                }
+                // Emails, URIs, etc. aren't relevant to TLS
+                _ => {}
+            }
+        }
+        return Some(parsed);
+    }
+    Some(Vec::new())
+}
+
+/// Reads DER elements from a buffer, one at a time
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Get the tag of the next element without consuming it
+    fn peek(&self) -> Option<u8> {
+        self.0.first().copied()
+    }
+
+    /// Read the next element, returning its tag and content
+    fn read(&mut self) -> Option<(u8, &'a [u8])> {
+        let (&tag, rest) = self.0.split_first()?;
+        let (&first, mut rest) = rest.split_first()?;
+        let length = if first & 0x80 == 0 {
+            usize::from(first)
+        } else {
+            // Long form: the low bits are the number of length bytes
+            let count = usize::from(first & 0x7f);
+            if count == 0 || count > 4 || rest.len() < count {
+                return None;
+            }
+            let (bytes, remaining) = rest.split_at(count);
+            rest = remaining;
+            bytes
+                .iter()
+                .fold(0, |length, &byte| (length << 8) | usize::from(byte))
+        };
+        if rest.len() < length {
+            return None;
+        }
+        let (content, rest) = rest.split_at(length);
+        self.0 = rest;
+        Some((tag, content))
+    }
+
+    /// Read the next element, which must have the given tag, and return its
+    /// content
+    fn expect_raw(&mut self, expected: u8) -> Option<&'a [u8]> {
+        let (tag, content) = self.read()?;
+        (tag == expected).then_some(content)
+    }
+
+    /// Read the next element, which must have the given tag, and return a
+    /// reader for its content
+    fn expect(&mut self, expected: u8) -> Option<Reader<'a>> {
+        self.expect_raw(expected).map(Reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_data_dir;
+    use chrono::TimeZone;
+    use rstest::rstest;
+
+    /// Parse a self-signed certificate generated with:
+    ///
+    /// ```sh
+    /// openssl req -x509 -newkey ec -pkeyopt ec_paramgen_curve:prime256v1 \
+    ///   -nodes -subj "/C=US/O=Slumber Test/CN=example.com" \
+    ///   -addext "subjectAltName=DNS:example.com,DNS:*.example.com,IP:127.0.0.1" \
+    ///   -days 3650 -outform DER -out test_data/certificate.der
+    /// ```
+    #[test]
+    fn test_parse() {
+        let der =
+            std::fs::read(test_data_dir().join("certificate.der")).unwrap();
+        let certificate = parse(&der).unwrap();
+        assert_eq!(
+            certificate,
+            Certificate {
+                subject: "C=US, O=Slumber Test, CN=example.com".into(),
+                issuer: "C=US, O=Slumber Test, CN=example.com".into(),
+                subject_alt_names: vec![
+                    "example.com".into(),
+                    "*.example.com".into(),
+                    "127.0.0.1".into()
+                ],
+                not_before: Utc
+                    .with_ymd_and_hms(2026, 10, 16, 13, 40, 10)
+                    .unwrap(),
+                not_after: Utc
+                    .with_ymd_and_hms(2036, 10, 13, 13, 40, 10)
+                    .unwrap(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(parse(b""), None);
+        assert_eq!(parse(b"\x30\x05\x30\x03"), None);
+    }
+
+    #[rstest]
+    #[case::common_name(&[0x55, 0x04, 0x03], "2.5.4.3")]
+    #[case::multi_byte(&[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d], "1.2.840.113549")]
+    fn test_format_oid(#[case] oid: &[u8], #[case] expected: &str) {
+        assert_eq!(format_oid(oid), expected);
+    }
+
+    #[rstest]
+    #[case::utc_time(TAG_UTC_TIME, "360101000000Z", Some((2036, 1, 1)))]
+    #[case::utc_time_1900s(TAG_UTC_TIME, "991231000000Z", Some((1999, 12, 31)))]
+    #[case::generalized_time(
+        TAG_GENERALIZED_TIME,
+        "20600101000000Z",
+        Some((2060, 1, 1))
+    )]
+    #[case::invalid(TAG_UTC_TIME, "garbage", None)]
+    fn test_parse_time(
+        #[case] tag: u8,
+        #[case] value: &str,
+        #[case] expected: Option<(i32, u32, u32)>,
+    ) {
+        assert_eq!(
+            parse_time((tag, value.as_bytes())),
+            expected.map(|(year, month, day)| Utc
+                .with_ymd_and_hms(year, month, day, 0, 0, 0)
+                .unwrap())
+        );
+    }
+}
@@ -5,12 +5,15 @@ mod cli;
 mod collection;
 mod config;
 mod db;
+mod export;
 mod http;
+mod secrets;
 mod template;
 #[cfg(test)]
 mod test_util;
 mod tui;
 mod util;
+mod ws;
 
 use crate::{cli::CliCommand, tui::Tui, util::paths::DataDirectory};
 use clap::Parser;
@@ -34,7 +37,7 @@ struct Args {
 }
 
 /// Arguments that are available to all subcommands and the TUI
-#[derive(Debug, Parser)]
+#[derive(Clone, Debug, Parser)]
 struct GlobalArgs {
     /// Collection file, which defines profiles, recipes, etc. If omitted,
     /// check the current and all parent directories for the following files
@@ -0,0 +1,190 @@
+//! Secrets stored in the OS keyring (Keychain on macOS, Credential Manager on
+//! Windows, Secret Service on Linux). Secrets are global, meaning they're
+//! shared between all collections. They're referenced in templates as
+//! `{{secret.<name>}}`, and managed with `slumber secrets`.
+
+use anyhow::{anyhow, bail};
+use itertools::Itertools;
+use thiserror::Error;
+use tokio::task;
+
+/// The keyring doesn't support listing entries, so we store a list of secret
+/// names under this key. It's not a valid secret name, so it can't collide.
+const INDEX_KEY: &str = ".index";
+
+/// Error loading a secret. The secret name is provided as context by the
+/// caller, so it's not included here.
+#[derive(Debug, Error)]
+pub enum SecretError {
+    #[error("Secret does not exist. Set it with `slumber secrets set <name>`")]
+    NotFound,
+    /// Error from the OS keyring
+    #[error(transparent)]
+    Store(anyhow::Error),
+}
+
+/// `anyhow::Error` doesn't implement `PartialEq`, so compare messages
+#[cfg(test)]
+impl PartialEq for SecretError {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_string() == other.to_string()
+    }
+}
+
+/// Get the value of a secret
+pub async fn get(name: &str) -> Result<String, SecretError> {
+    let name = name.to_owned();
+    let result = task::spawn_blocking(move || store::get(&name))
+        .await
+        .map_err(|error| SecretError::Store(error.into()))?;
+    match result {
+        Ok(Some(value)) => Ok(value),
+        Ok(None) => Err(SecretError::NotFound),
+        Err(error) => Err(SecretError::Store(error)),
+    }
+}
+
+/// Set the value of a secret, creating it if necessary. This is blocking.
+pub fn set(name: &str, value: &str) -> anyhow::Result<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || "-_".contains(c))
+    {
+        bail!(
+            "Invalid secret name `{name}`; names can only contain letters, \
+            numbers, `-`, and `_`"
+        );
+    }
+    store::set(name, value)?;
+    let mut names = list()?;
+    if !names.iter().any(|other| other == name) {
+        names.push(name.to_owned());
+        names.sort();
+        store::set(INDEX_KEY, &names.join("\n"))?;
+    }
+    Ok(())
+}
+
+/// Delete a secret. This is blocking.
+pub fn delete(name: &str) -> anyhow::Result<()> {
+    if !store::delete(name)? {
+        return Err(anyhow!("Secret `{name}` does not exist"));
+    }
+    let names = list()?;
+    store::set(
+        INDEX_KEY,
+        &names.iter().filter(|other| *other != name).join("\n"),
+    )
+}
+
+/// Get the names of all stored secrets, in alphabetical order. This is
+/// blocking.
+pub fn list() -> anyhow::Result<Vec<String>> {
+    let index = store::get(INDEX_KEY)?.unwrap_or_default();
+    Ok(index
+        .lines()
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Storage backend, backed by the OS keyring
+#[cfg(not(test))]
+mod store {
+    use anyhow::Context;
+    use keyring::{Entry, Error};
+
+    /// Service name that all secrets are stored under in the keyring
+    const SERVICE: &str = "slumber";
+
+    pub fn get(key: &str) -> anyhow::Result<Option<String>> {
+        match Entry::new(SERVICE, key)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(Error::NoEntry) => Ok(None),
+            Err(error) => Err(error)
+                .with_context(|| format!("Error reading `{key}` from keyring")),
+        }
+    }
+
+    pub fn set(key: &str, value: &str) -> anyhow::Result<()> {
+        Entry::new(SERVICE, key)?
+            .set_password(value)
+            .with_context(|| format!("Error writing `{key}` to keyring"))
+    }
+
+    /// Delete an entry. Return `false` if it didn't exist
+    pub fn delete(key: &str) -> anyhow::Result<bool> {
+        match Entry::new(SERVICE, key)?.delete_credential() {
+            Ok(()) => Ok(true),
+            Err(Error::NoEntry) => Ok(false),
+            Err(error) => Err(error).with_context(|| {
+                format!("Error deleting `{key}` from keyring")
+            }),
+        }
+    }
+}
+
+/// In-memory storage backend, so tests don't touch the real keyring. This is
+/// shared by all tests, so each test should use unique secret names. Tests in
+/// other modules should use [store::set] directly, because the index isn't
+/// safe to modify concurrently.
+#[cfg(test)]
+pub mod store {
+    use std::{collections::HashMap, sync::Mutex};
+
+    static STORE: Mutex<Option<HashMap<String, String>>> = Mutex::new(None);
+
+    pub fn get(key: &str) -> anyhow::Result<Option<String>> {
+        let store = STORE.lock().unwrap();
+        Ok(store.as_ref().and_then(|store| store.get(key).cloned()))
+    }
+
+    pub fn set(key: &str, value: &str) -> anyhow::Result<()> {
+        let mut store = STORE.lock().unwrap();
+        store
+            .get_or_insert_with(HashMap::new)
+            .insert(key.to_owned(), value.to_owned());
+        Ok(())
+    }
+
+    pub fn delete(key: &str) -> anyhow::Result<bool> {
+        let mut store = STORE.lock().unwrap();
+        Ok(store.as_mut().and_then(|store| store.remove(key)).is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::assert_err;
+
+    #[tokio::test]
+    async fn test_secrets() {
+        assert_eq!(get("test_secrets").await, Err(SecretError::NotFound));
+
+        set("test_secrets", "hunter2").unwrap();
+        set("test_secrets_2", "hunter3").unwrap();
+        assert_eq!(get("test_secrets").await.unwrap(), "hunter2");
+        let names = list().unwrap();
+        assert!(names.contains(&"test_secrets".to_owned()));
+        assert!(names.contains(&"test_secrets_2".to_owned()));
+
+        // Overwrite
+        set("test_secrets", "hunter4").unwrap();
+        assert_eq!(get("test_secrets").await.unwrap(), "hunter4");
+
+        delete("test_secrets").unwrap();
+        assert_eq!(get("test_secrets").await, Err(SecretError::NotFound));
+        assert!(!list().unwrap().contains(&"test_secrets".to_owned()));
+        assert_err!(
+            delete("test_secrets"),
+            "Secret `test_secrets` does not exist"
+        );
+    }
+
+    #[test]
+    fn test_set_invalid_name() {
+        assert_err!(set("my.secret", "hunter2"), "Invalid secret name");
+    }
+}
@@ -1,25 +1,45 @@
+mod chain_log;
 mod error;
+mod faker;
+mod function;
 mod parse;
 mod prompt;
 mod render;
+mod script;
+mod trace;
 
+pub use chain_log::{
+    cache_state, clear_chain, last_resolution, ChainCacheState, ChainResolution,
+};
 pub use error::{ChainError, TemplateError};
+pub use faker::Faker;
+pub use function::{Function, FunctionArgument, FunctionCall};
 pub use parse::Span;
-pub use prompt::{Prompt, PromptChannel, Prompter};
+pub use prompt::{FilePrompt, Prompt, PromptChannel, Prompter};
+pub use trace::{trace, Trace};
 
 use crate::{
-    collection::{Collection, ProfileId},
+    collection::{ChainId, Collection, ProfileId},
     db::CollectionDatabase,
     http::HttpEngine,
     template::{
         error::TemplateParseError,
-        parse::{TemplateInputChunk, CHAIN_PREFIX, ENV_PREFIX},
+        parse::{
+            TemplateInputChunk, CHAIN_PREFIX, ENV_PREFIX, FAKE_PREFIX,
+            SECRET_PREFIX,
+        },
     },
 };
 use derive_more::Display;
 use indexmap::IndexMap;
+use itertools::Itertools;
 use serde::Serialize;
-use std::{fmt::Debug, sync::atomic::AtomicU8};
+use std::{
+    collections::HashSet,
+    fmt::Debug,
+    sync::{atomic::AtomicU8, Mutex},
+};
+use tracing::warn;
 
 /// Maximum number of layers of nested templates
 const RECURSION_LIMIT: u8 = 10;
@@ -56,6 +76,22 @@ pub struct TemplateContext {
     /// templates is the same as a template that renders a single child 5
     /// times.
     pub recursion_count: AtomicU8,
+    /// Every value rendered from a sensitive key (a sensitive chain or
+    /// profile field, or a secret) with this context. These are redacted from
+    /// request records, so they aren't shown in the UI or stored in history.
+    pub sensitive_values: Mutex<HashSet<String>>,
+}
+
+impl TemplateContext {
+    /// Track a value rendered from a sensitive key
+    fn add_sensitive_value(&self, value: &[u8]) {
+        if !value.is_empty() {
+            self.sensitive_values
+                .lock()
+                .expect("Sensitive values lock poisoned")
+                .insert(String::from_utf8_lossy(value).into_owned());
+        }
+    }
 }
 
 /// An immutable string that can contain templated content. The string is parsed
@@ -83,6 +119,208 @@ impl Template {
         &self.template[span.start()..span.end()]
     }
 
+    /// Get the ID of every chain referenced by this template, in the order
+    /// they first appear. This includes chains used as conditions or function
+    /// arguments.
+    pub fn chain_ids(&self) -> Vec<ChainId> {
+        fn add(
+            template: &Template,
+            key: &TemplateKey<Span>,
+            ids: &mut Vec<ChainId>,
+        ) {
+            if let TemplateKey::Chain(span) = key {
+                let id = ChainId::from(template.substring(*span));
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+        }
+
+        fn visit(
+            template: &Template,
+            chunks: &[TemplateInputChunk<Span>],
+            ids: &mut Vec<ChainId>,
+        ) {
+            for chunk in chunks {
+                match chunk {
+                    TemplateInputChunk::Raw(_) => {}
+                    TemplateInputChunk::Key { key, functions } => {
+                        add(template, key, ids);
+                        for argument in
+                            functions.iter().flat_map(|call| &call.arguments)
+                        {
+                            if let FunctionArgument::Key(key) = argument {
+                                add(template, key, ids);
+                            }
+                        }
+                    }
+                    TemplateInputChunk::Conditional {
+                        condition,
+                        then,
+                        otherwise,
+                    } => {
+                        add(template, condition, ids);
+                        visit(template, then, ids);
+                        visit(template, otherwise, ids);
+                    }
+                }
+            }
+        }
+
+        let mut ids = Vec::new();
+        visit(self, &self.chunks, &mut ids);
+        ids
+    }
+
+    /// Rebuild the template text, replacing each key (including the functions
+    /// it's piped through) with the output of the given function. Raw text is
+    /// kept as-is. Used to translate templates to the variable syntax of other
+    /// tools. Conditional blocks are kept, but keys within them are replaced.
+    /// The condition key itself is never replaced.
+    pub fn map_keys(
+        &self,
+        mut f: impl FnMut(TemplateKey<&str>, &[FunctionCall<&str>]) -> String,
+    ) -> String {
+        let mut output = String::new();
+        self.map_keys_inner(&self.chunks, &mut f, &mut output);
+        output
+    }
+
+    fn map_keys_inner(
+        &self,
+        chunks: &[TemplateInputChunk<Span>],
+        f: &mut impl FnMut(TemplateKey<&str>, &[FunctionCall<&str>]) -> String,
+        output: &mut String,
+    ) {
+        for chunk in chunks {
+            match chunk {
+                TemplateInputChunk::Raw(span) => {
+                    output.push_str(self.substring(*span))
+                }
+                TemplateInputChunk::Key { key, functions } => {
+                    let functions = functions
+                        .iter()
+                        .map(|call| {
+                            call.clone().map(|span| self.substring(span))
+                        })
+                        .collect_vec();
+                    output.push_str(&f(
+                        key.map(|span| self.substring(span)),
+                        &functions,
+                    ));
+                }
+                TemplateInputChunk::Conditional {
+                    condition,
+                    then,
+                    otherwise,
+                } => {
+                    let condition = condition.map(|span| self.substring(span));
+                    output.push_str(&format!("{{{{#if {condition}}}}}"));
+                    self.map_keys_inner(then, f, output);
+                    if !otherwise.is_empty() {
+                        output.push_str("{{else}}");
+                        self.map_keys_inner(otherwise, f, output);
+                    }
+                    output.push_str("{{/if}}");
+                }
+            }
+        }
+    }
+
+    /// Replace each field key (e.g. `{{host}}`) with the raw text of the
+    /// template returned by `get_field`, then re-parse. Keys for which
+    /// `get_field` returns `None`, and all other key types, are kept as-is. If
+    /// the result can't be parsed, log a warning and keep the original.
+    ///
+    /// A key piped through functions (e.g. `{{host | upper}}`) can only be
+    /// substituted if the value is a single key, in which case the pipelines
+    /// are combined. Otherwise it's kept as-is, with a warning.
+    pub fn substitute<'a>(
+        &self,
+        get_field: impl Fn(&str) -> Option<&'a Template>,
+    ) -> Self {
+        fn has_match(
+            template: &Template,
+            chunks: &[TemplateInputChunk<Span>],
+            get_field: &dyn Fn(&str) -> bool,
+        ) -> bool {
+            chunks.iter().any(|chunk| match chunk {
+                TemplateInputChunk::Raw(_) => false,
+                TemplateInputChunk::Key { key, .. } => matches!(
+                    key,
+                    TemplateKey::Field(span)
+                        if get_field(template.substring(*span))
+                ),
+                TemplateInputChunk::Conditional {
+                    then, otherwise, ..
+                } => {
+                    has_match(template, then, get_field)
+                        || has_match(template, otherwise, get_field)
+                }
+            })
+        }
+
+        if !has_match(self, &self.chunks, &|field| get_field(field).is_some()) {
+            return self.clone();
+        }
+
+        let template = self.map_keys(|key, functions| {
+            let value = match key {
+                TemplateKey::Field(field) => get_field(field),
+                _ => None,
+            };
+            match value {
+                None => format_key(key, functions),
+                Some(value) if functions.is_empty() => {
+                    value.as_str().to_owned()
+                }
+                Some(value) => match value.chunks.as_slice() {
+                    [TemplateInputChunk::Key {
+                        key: inner_key,
+                        functions: inner_functions,
+                    }] => {
+                        let inner_functions = inner_functions
+                            .iter()
+                            .map(|call| {
+                                call.clone().map(|span| value.substring(span))
+                            })
+                            .chain(functions.iter().cloned())
+                            .collect_vec();
+                        format_key(
+                            inner_key.map(|span| value.substring(span)),
+                            &inner_functions,
+                        )
+                    }
+                    _ => {
+                        warn!(
+                            template = %self,
+                            %key,
+                            "Cannot substitute a field that's piped through \
+                            functions, unless the value is a single key"
+                        );
+                        format_key(key, functions)
+                    }
+                },
+            }
+        });
+        Self::parse(template).unwrap_or_else(|error| {
+            warn!(template = %self, %error, "Error substituting fields");
+            self.clone()
+        })
+    }
+
+    /// Create a template that renders to exactly the given text. The text is
+    /// *not* parsed, so any `{{` in it is kept as-is. Serializing and
+    /// re-parsing this template may give a different template.
+    pub fn raw(template: String) -> Self {
+        // Create one raw chunk for everything
+        let chunk = TemplateInputChunk::Raw(Span::new(0, template.len()));
+        Self {
+            template,
+            chunks: vec![chunk],
+        }
+    }
+
     /// Create a new template **without parsing**. The created template should
     /// *never* be rendered. This is only useful when creating templates purely
     /// for the purpose of being serialized, e.g. when importing an external
@@ -94,12 +332,7 @@ impl Template {
     /// the extra code for something that is very unlikely to happen. It says
     /// "dangerous", don't be stupid.
     pub(crate) fn dangerous(template: String) -> Self {
-        // Create one raw chunk for everything
-        let chunk = TemplateInputChunk::Raw(Span::new(0, template.len()));
-        Self {
-            template,
-            chunks: vec![chunk],
-        }
+        Self::raw(template)
     }
 }
 
@@ -162,7 +395,7 @@ pub enum TemplateChunk {
 /// This is important for matching override keys during rendering.
 #[derive(Copy, Clone, Debug, Display)]
 #[cfg_attr(test, derive(PartialEq))]
-enum TemplateKey<T> {
+pub enum TemplateKey<T> {
     /// A plain field, which can come from the profile or an override
     Field(T),
     /// A value from a predefined chain of another recipe
@@ -171,6 +404,25 @@ enum TemplateKey<T> {
     /// A value pulled from the process environment
     #[display("{ENV_PREFIX}{_0}")]
     Environment(T),
+    /// A secret from the OS keyring
+    #[display("{SECRET_PREFIX}{_0}")]
+    Secret(T),
+    /// Randomly generated test data, e.g. a name or email
+    #[display("{FAKE_PREFIX}{_0}")]
+    Fake(T),
+}
+
+/// Format a key and the functions it's piped through as template source, e.g.
+/// `{{chains.body | jsonpath('$.id') | urlencode}}`
+fn format_key(
+    key: TemplateKey<&str>,
+    functions: &[FunctionCall<&str>],
+) -> String {
+    let functions = functions
+        .iter()
+        .map(|call| format!(" | {call}"))
+        .collect::<String>();
+    format!("{{{{{key}{functions}}}}}")
 }
 
 impl<T> TemplateKey<T> {
@@ -181,6 +433,8 @@ impl<T> TemplateKey<T> {
             Self::Field(value) => TemplateKey::Field(f(value)),
             Self::Chain(value) => TemplateKey::Chain(f(value)),
             Self::Environment(value) => TemplateKey::Environment(f(value)),
+            Self::Secret(value) => TemplateKey::Secret(f(value)),
+            Self::Fake(value) => TemplateKey::Fake(f(value)),
         }
     }
 }
@@ -197,6 +451,7 @@ impl crate::test_util::Factory for TemplateContext {
             overrides: IndexMap::new(),
             prompter: Box::<TestPrompter>::default(),
             recursion_count: 0.into(),
+            sensitive_values: Default::default(),
         }
     }
 }
@@ -207,14 +462,20 @@ mod tests {
     use crate::{
         collection::{
             Chain, ChainOutputTrim, ChainRequestSection, ChainRequestTrigger,
-            ChainSource, Profile, Recipe, RecipeId,
+            ChainSource, FilePickerOutput, Profile, Recipe, RecipeId,
         },
         config::Config,
-        http::{ContentType, Exchange, RequestRecord, ResponseRecord},
+        http::{
+            ContentType, Exchange, RequestRecord, ResponseBody, ResponseRecord,
+            SelectorType,
+        },
+        secrets,
         test_util::{
-            assert_err, header_map, temp_dir, Factory, TempDir, TestPrompter,
+            assert_err, assert_matches, header_map, temp_dir, Factory, TempDir,
+            TestPrompter,
         },
     };
+    use bytesize::ByteSize;
     use chrono::Utc;
     use indexmap::indexmap;
     use rstest::rstest;
@@ -222,6 +483,62 @@ mod tests {
     use std::{env, time::Duration};
     use tokio::fs;
 
+    #[test]
+    fn test_map_keys() {
+        let template: Template =
+            "{{host}}/{{chains.id}}?user={{env.USER}}".parse().unwrap();
+        let mapped = template.map_keys(|key, _| match key {
+            TemplateKey::Field(field) => format!("<{field}>"),
+            key => format!("[{key}]"),
+        });
+        assert_eq!(mapped, "<host>/[chains.id]?user=[env.USER]");
+    }
+
+    /// Chains are found in keys, conditions, and function arguments, in order
+    /// of first appearance
+    #[test]
+    fn test_chain_ids() {
+        let template: Template = "{{chains.a}}/{{host}}/{{chains.b}}\
+            {{#if chains.c}}{{chains.a}}{{/if}}\
+            {{user | hmac_sha256(chains.d)}}"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            template.chain_ids(),
+            ["a", "b", "c", "d"].map(ChainId::from)
+        );
+        assert_eq!(Template::from("{{host}}").chain_ids(), []);
+    }
+
+    #[rstest]
+    #[case::no_match("{{host}}/{{chains.id}}", "{{host}}/{{chains.id}}")]
+    #[case::field("{{user}}/{{chains.id}}", "fish/{{chains.id}}")]
+    #[case::nested("{{url}}?user={{user}}", "{{host}}/api?user=fish")]
+    #[case::function(
+        "{{url | urlencode}}/{{user | upper}}",
+        "{{url | urlencode}}/{{user | upper}}"
+    )]
+    #[case::function_single_key(
+        "{{alias | base64}}",
+        "{{chains.user | trim | base64}}"
+    )]
+    // Keys in conditionals are substituted, but conditions aren't
+    #[case::conditional(
+        "{{#if user}}{{user}}{{else}}{{host}}{{/if}}",
+        "{{#if user}}fish{{else}}{{host}}{{/if}}"
+    )]
+    fn test_substitute(#[case] template: Template, #[case] expected: &str) {
+        let data: IndexMap<String, Template> = indexmap! {
+            "user".into() => "fish".into(),
+            "url".into() => "{{host}}/api".into(),
+            "alias".into() => "{{chains.user|trim}}".into(),
+        };
+        assert_eq!(
+            template.substitute(|field| data.get(field)).as_str(),
+            expected
+        );
+    }
+
     /// Test overriding all key types, as well as missing keys
     #[tokio::test]
     async fn test_override() {
@@ -270,6 +587,172 @@ mod tests {
         );
     }
 
+    /// Values are piped through functions in order, and overridden values are
+    /// piped too. Key arguments are rendered too
+    #[rstest]
+    #[case::single("{{user | upper}}", "FISH")]
+    #[case::chain(r#"{{body | jsonpath('$.id') | base64}}"#, "YWJj")]
+    #[case::override_(
+        "{{token | trim | sha256}}",
+        "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+    )]
+    #[case::key_argument(
+        "{{user | hmac_sha256(key)}}",
+        "0a353e1e2a78e6fe6eb8b2bc4c697c229a1e9fcd1bbb30f3207274189ef89a0d"
+    )]
+    #[tokio::test]
+    async fn test_functions(#[case] template: &str, #[case] expected: &str) {
+        let profile = Profile {
+            data: indexmap! {
+                "user".into() => "fish".into(),
+                "body".into() => Template::raw(r#"{"id": "abc"}"#.into()),
+                "key".into() => "key".into(),
+            },
+            ..Profile::factory(())
+        };
+        let profile_id = profile.id.clone();
+        let context = TemplateContext {
+            collection: Collection {
+                profiles: indexmap! {profile_id.clone() => profile},
+                ..Collection::factory(())
+            },
+            selected_profile: Some(profile_id),
+            overrides: indexmap! {"token".into() => " test ".into()},
+            ..TemplateContext::factory(())
+        };
+        assert_eq!(render!(template, context).unwrap(), expected);
+    }
+
+    /// `default()` replaces empty and missing values. Values that fail for
+    /// any other reason are still errors
+    #[rstest]
+    #[case::present("{{user | default('x')}}", "fish")]
+    #[case::empty("{{empty | default('x')}}", "x")]
+    #[case::missing("{{bogus | default('x')}}", "x")]
+    #[case::missing_env("{{env.SLUMBER_BOGUS | default('x')}}", "x")]
+    // Functions before the default are skipped for missing values, but
+    // functions after it are still applied
+    #[case::pipeline("{{bogus | base64 | default('x') | upper}}", "X")]
+    #[case::key_argument("{{bogus | default(user)}}", "fish")]
+    #[tokio::test]
+    async fn test_default(#[case] template: &str, #[case] expected: &str) {
+        let profile = Profile {
+            data: indexmap! {
+                "user".into() => "fish".into(),
+                "empty".into() => "".into(),
+            },
+            ..Profile::factory(())
+        };
+        let profile_id = profile.id.clone();
+        let context = TemplateContext {
+            collection: Collection {
+                profiles: indexmap! {profile_id.clone() => profile},
+                ..Collection::factory(())
+            },
+            selected_profile: Some(profile_id),
+            ..TemplateContext::factory(())
+        };
+        assert_eq!(render!(template, context).unwrap(), expected);
+    }
+
+    /// `default()` doesn't hide errors for values that exist
+    #[tokio::test]
+    async fn test_default_error() {
+        let profile = Profile {
+            data: indexmap! {"nested".into() => "{{bogus}}".into()},
+            ..Profile::factory(())
+        };
+        let profile_id = profile.id.clone();
+        let context = TemplateContext {
+            collection: Collection {
+                profiles: indexmap! {profile_id.clone() => profile},
+                ..Collection::factory(())
+            },
+            selected_profile: Some(profile_id),
+            ..TemplateContext::factory(())
+        };
+        assert_err!(
+            render!("{{nested | default('x')}}", context),
+            "Rendering nested template for field `nested`"
+        );
+        assert_err!(
+            render!("{{chains.bogus | default('x')}}", context),
+            "Unknown chain: bogus"
+        );
+    }
+
+    /// Only the branch matching the condition is rendered
+    #[rstest]
+    #[case::true_("{{#if staging}}stage{{/if}}", "stage")]
+    #[case::string("{{#if user}}{{user}}{{/if}}", "fish")]
+    #[case::false_("{{#if prod}}prod{{/if}}", "")]
+    #[case::empty("{{#if empty}}empty{{/if}}", "")]
+    #[case::missing("{{#if bogus}}bogus{{/if}}", "")]
+    #[case::else_("{{#if prod}}prod{{else}}{{user}}{{/if}}", "fish")]
+    #[case::nested(
+        "a{{#if staging}}b{{#if prod}}c{{else}}d{{/if}}{{/if}}e",
+        "abde"
+    )]
+    // The untaken branch isn't rendered, so errors in it are ignored
+    #[case::untaken_error("{{#if prod}}{{bogus}}{{/if}}", "")]
+    #[tokio::test]
+    async fn test_conditional(#[case] template: &str, #[case] expected: &str) {
+        let profile = Profile {
+            data: indexmap! {
+                "user".into() => "fish".into(),
+                "staging".into() => "true".into(),
+                "prod".into() => "false".into(),
+                "empty".into() => "".into(),
+            },
+            ..Profile::factory(())
+        };
+        let profile_id = profile.id.clone();
+        let context = TemplateContext {
+            collection: Collection {
+                profiles: indexmap! {profile_id.clone() => profile},
+                ..Collection::factory(())
+            },
+            selected_profile: Some(profile_id),
+            ..TemplateContext::factory(())
+        };
+        assert_eq!(render!(template, context).unwrap(), expected);
+    }
+
+    /// A condition that fails to render is an error
+    #[tokio::test]
+    async fn test_conditional_error() {
+        let context = TemplateContext::factory(());
+        assert_err!(
+            render!("{{#if chains.bogus}}a{{/if}}", context),
+            "Unknown chain: bogus"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_function_error() {
+        let profile = Profile {
+            data: indexmap! {"user".into() => "fish".into()},
+            ..Profile::factory(())
+        };
+        let profile_id = profile.id.clone();
+        let context = TemplateContext {
+            collection: Collection {
+                profiles: indexmap! {profile_id.clone() => profile},
+                ..Collection::factory(())
+            },
+            selected_profile: Some(profile_id),
+            ..TemplateContext::factory(())
+        };
+        assert_err!(
+            render!("{{user | jsonpath('$.id')}}", context),
+            "Calling function `jsonpath`"
+        );
+        assert_err!(
+            render!("{{user | hmac_sha256(unknown)}}", context),
+            "Rendering argument: Unknown field `unknown`"
+        );
+    }
+
     /// Test that a field key renders correctly
     #[tokio::test]
     async fn test_field() {
@@ -353,6 +836,17 @@ mod tests {
     #[case::array(Some("$.array"), ChainRequestSection::Body, "[1,2]")]
     #[case::object(Some("$.object"), ChainRequestSection::Body, "{\"a\":1}")]
     #[case::header(None, ChainRequestSection::Header("Token".into()), "Secret Value")]
+    #[case::header_case_insensitive(
+        None,
+        ChainRequestSection::Header("token".into()),
+        "Secret Value"
+    )]
+    #[case::header_multiple(
+        None,
+        ChainRequestSection::Header("Link".into()),
+        r#"</users?page=1>; rel="prev", </users?page=3>; rel="next""#
+    )]
+    #[case::status(None, ChainRequestSection::Status, "200")]
     #[tokio::test]
     async fn test_chain_request(
         #[case] selector: Option<&str>,
@@ -368,8 +862,14 @@ mod tests {
             "array": [1,2],
             "object": {"a": 1},
         });
-        let response_headers =
+        let mut response_headers =
             header_map(indexmap! {"Token" => "Secret Value"});
+        for link in [
+            r#"</users?page=1>; rel="prev""#,
+            r#"</users?page=3>; rel="next""#,
+        ] {
+            response_headers.append("Link", link.parse().unwrap());
+        }
         let request = RequestRecord {
             recipe_id: recipe_id.clone(),
             ..RequestRecord::factory(())
@@ -417,6 +917,46 @@ mod tests {
         );
     }
 
+    /// XPath, CSS, and regex selectors are applied to the raw content,
+    /// regardless of content type
+    #[rstest]
+    #[case::xpath(
+        SelectorType::Xpath,
+        "<user><id>3</id></user>",
+        "/user/id",
+        "3"
+    )]
+    #[case::css(
+        SelectorType::Css,
+        "<html><a href='/next'>Next</a></html>",
+        "a::attr(href)",
+        "/next"
+    )]
+    #[case::regex(SelectorType::Regex, "count: 3", r"count: (\d+)", "3")]
+    #[tokio::test]
+    async fn test_chain_selector_text(
+        #[case] selector_type: SelectorType,
+        #[case] content: &str,
+        #[case] selector: &str,
+        #[case] expected: &str,
+    ) {
+        let chain = Chain {
+            source: ChainSource::command(["echo", "-n", content]),
+            selector: Some(selector.into()),
+            selector_type,
+            ..Chain::factory(())
+        };
+        let context = TemplateContext {
+            collection: Collection {
+                chains: indexmap! {chain.id.clone() => chain},
+                ..Collection::factory(())
+            },
+            ..TemplateContext::factory(())
+        };
+
+        assert_eq!(render!("{{chains.chain1}}", context).unwrap(), expected);
+    }
+
     /// Test all possible error cases for chained requests. This covers all
     /// chain-specific error variants
     #[rstest]
@@ -518,6 +1058,45 @@ mod tests {
         }),
         "Parsing response: expected ident at line 1 column 2",
     )]
+    // Header isn't in the response
+    #[case::missing_header(
+        "chain1",
+        Chain {
+            source: ChainSource::Request {
+                recipe: "recipe1".into(),
+                trigger: Default::default(),
+                section: ChainRequestSection::Header("Location".into()),
+            },
+            ..Chain::factory(())
+        },
+        Some("recipe1"),
+        Some(Exchange::factory(())),
+        "Header `Location` not in response",
+    )]
+    // Only a preview of the body was stored in history
+    #[case::truncated_body(
+        "chain1",
+        Chain {
+            source: ChainSource::Request {
+                recipe: "recipe1".into(),
+                trigger: Default::default(),
+                section: Default::default(),
+            },
+            ..Chain::factory(())
+        },
+        Some("recipe1"),
+        Some(Exchange {
+            response: ResponseRecord {
+                body: ResponseBody::truncated(
+                    "{\"a\": ".into(),
+                    ByteSize::mb(10),
+                ),
+                ..ResponseRecord::factory(())
+            }.into(),
+            ..Exchange::factory(())
+        }),
+        "Response body (10.0 MB) was too large to store in history",
+    )]
     // Query returned multiple results
     #[case::query_multiple_results(
         "chain1",
@@ -683,6 +1262,118 @@ mod tests {
         assert_eq!(render!("{{chains.chain1}}", context).unwrap(), expected);
     }
 
+    /// The latest resolution of each chain is recorded, including errors
+    #[rstest]
+    #[tokio::test]
+    async fn test_chain_log() {
+        // The log is global, so use IDs that no other test renders
+        let ok = Chain {
+            id: "log_ok".into(),
+            source: ChainSource::command(["echo", "-n", "hello!"]),
+            sensitive: true,
+            ..Chain::factory(())
+        };
+        let error = Chain {
+            id: "log_error".into(),
+            source: ChainSource::command(["totally not a program"]),
+            ..Chain::factory(())
+        };
+        let context = TemplateContext {
+            collection: Collection {
+                chains: indexmap! {
+                    ok.id.clone() => ok,
+                    error.id.clone() => error,
+                },
+                ..Collection::factory(())
+            },
+            selected_profile: Some("profile1".into()),
+            ..TemplateContext::factory(())
+        };
+        assert!(last_resolution(&"log_ok".into()).is_none());
+
+        render!("{{chains.log_ok}}", context).unwrap();
+        let resolution = last_resolution(&"log_ok".into()).unwrap();
+        assert_eq!(resolution.outcome, Ok(b"hello!".to_vec()));
+        assert!(resolution.sensitive);
+        assert_eq!(resolution.profile_id, Some("profile1".into()));
+
+        render!("{{chains.log_error}}", context).unwrap_err();
+        let resolution = last_resolution(&"log_error".into()).unwrap();
+        let error = resolution.outcome.unwrap_err();
+        assert!(
+            error.contains("No such file or directory"),
+            "Unexpected error: {error}"
+        );
+
+        // Unknown chains aren't recorded
+        render!("{{chains.log_unknown}}", context).unwrap_err();
+        assert!(last_resolution(&"log_unknown".into()).is_none());
+    }
+
+    /// Secrets report whether they're cached, and clearing a chain drops its
+    /// cached secret along with its log entry
+    #[tokio::test]
+    async fn test_chain_cache_state() {
+        let ttl = Duration::from_secs(3600);
+        let chain = Chain {
+            // The log is global, so use an ID that no other test renders
+            id: "cache_vault".into(),
+            source: ChainSource::Vault {
+                path: "secret/app".into(),
+                field: "token".into(),
+                cache_ttl: Some(ttl),
+            },
+            ..Chain::factory(())
+        };
+        assert_eq!(cache_state(&chain), ChainCacheState::Empty);
+
+        // Vault isn't available here, so fill the cache by hand
+        let command = vec![
+            "sh".to_owned(),
+            "-c".to_owned(),
+            "echo cache_vault".to_owned(),
+        ];
+        render::fetch_secret(command.clone(), Some(ttl))
+            .await
+            .unwrap();
+        chain_log::record(
+            chain.id.clone(),
+            None,
+            Ok((b"cache_vault", false)),
+            Some(command.clone()),
+        );
+        assert_matches!(cache_state(&chain), ChainCacheState::Cached { .. });
+
+        assert!(clear_chain(&chain.id));
+        assert!(last_resolution(&chain.id).is_none());
+        assert!(render::secret_fetched_at(&command).is_none());
+        assert_eq!(cache_state(&chain), ChainCacheState::Empty);
+        assert!(!clear_chain(&chain.id));
+
+        // Other sources have fixed states
+        let request = |trigger| Chain {
+            source: ChainSource::Request {
+                recipe: "recipe1".into(),
+                trigger,
+                section: Default::default(),
+            },
+            ..Chain::factory(())
+        };
+        assert_eq!(
+            cache_state(&request(ChainRequestTrigger::Always)),
+            ChainCacheState::Uncached
+        );
+        assert_eq!(
+            cache_state(&request(ChainRequestTrigger::NoHistory)),
+            ChainCacheState::History(ChainRequestTrigger::NoHistory)
+        );
+        let command = Chain {
+            source: ChainSource::command(["echo"]),
+            ..Chain::factory(())
+        };
+        assert_eq!(cache_state(&command), ChainCacheState::Uncached);
+    }
+
     /// Test trimmed chained command
     #[rstest]
     #[case::no_trim(ChainOutputTrim::None, "   hello!   ")]
@@ -750,6 +1441,78 @@ mod tests {
         assert_err!(render!("{{chains.chain1}}", context), expected_error);
     }
 
+    /// Script inputs can use profile data, and the output can be JSON
+    #[rstest]
+    #[tokio::test]
+    async fn test_chain_script() {
+        let profile = Profile {
+            data: indexmap! {"ids".into() => "1,2,3".into()},
+            ..Profile::factory(())
+        };
+        let profile_id = profile.id.clone();
+        let chain = Chain {
+            source: ChainSource::Script {
+                script: r#"
+                    let total = 0;
+                    for id in ids.split(",") { total += parse_int(id); }
+                    #{prefix: prefix, total: total}
+                "#
+                .into(),
+                inputs: indexmap! {
+                    "ids".into() => "{{ids}}".into(),
+                    "prefix".into() => "user".into(),
+                },
+            },
+            ..Chain::factory(())
+        };
+        let context = TemplateContext {
+            collection: Collection {
+                profiles: indexmap! {profile_id.clone() => profile},
+                chains: indexmap! {chain.id.clone() => chain},
+                ..Collection::factory(())
+            },
+            selected_profile: Some(profile_id),
+            ..TemplateContext::factory(())
+        };
+
+        assert_eq!(
+            render!("{{chains.chain1}}", context).unwrap(),
+            r#"{"prefix":"user","total":6}"#
+        );
+    }
+
+    #[rstest]
+    #[case::input(
+        "x",
+        "{{chains.unknown}}",
+        "Rendering nested template for field `inputs.x`: Resolving chain \
+        `unknown`: Unknown chain: unknown"
+    )]
+    #[case::script("x + y", "", "Executing script: Variable not found: y")]
+    #[tokio::test]
+    async fn test_chain_script_error(
+        #[case] script: &str,
+        #[case] input: &str,
+        #[case] expected_error: &str,
+    ) {
+        let chain = Chain {
+            source: ChainSource::Script {
+                script: script.into(),
+                inputs: indexmap! {"x".into() => input.into()},
+            },
+            ..Chain::factory(())
+        };
+        let context = TemplateContext {
+            collection: Collection {
+                chains: indexmap! {chain.id.clone() => chain},
+                ..Collection::factory(())
+            },
+            ..TemplateContext::factory(())
+        };
+
+        assert_err!(render!("{{chains.chain1}}", context), expected_error);
+    }
+
     /// Test success with chained file
     #[rstest]
     #[tokio::test]
@@ -809,6 +1572,8 @@ mod tests {
             source: ChainSource::Prompt {
                 message: Some("password".into()),
                 default: Some("default".into()),
+                options: Vec::new(),
+                multiline: false,
             },
             ..Chain::factory(())
         };
@@ -830,6 +1595,93 @@ mod tests {
         assert_eq!(render!("{{chains.chain1}}", context).unwrap(), "default");
     }
 
+    /// Options and the default are rendered and passed to the prompter
+    #[tokio::test]
+    async fn test_chain_prompt_options() {
+        /// Pick the option after the default
+        #[derive(Debug)]
+        struct NextPrompter;
+
+        impl Prompter for NextPrompter {
+            fn prompt(&self, prompt: Prompt) {
+                assert!(prompt.multiline);
+                let index = prompt
+                    .options
+                    .iter()
+                    .position(|option| Some(option) == prompt.default.as_ref())
+                    .unwrap();
+                prompt.channel.respond(prompt.options[index + 1].clone());
+            }
+        }
+
+        let profile = Profile {
+            data: indexmap! {"region".into() => "eu".into()},
+            ..Profile::factory(())
+        };
+        let profile_id = profile.id.clone();
+        let chain = Chain {
+            source: ChainSource::Prompt {
+                message: None,
+                default: Some("{{region}}-west".into()),
+                options: vec![
+                    "us-west".into(),
+                    "{{region}}-west".into(),
+                    "{{region}}-east".into(),
+                ],
+                multiline: true,
+            },
+            ..Chain::factory(())
+        };
+        let context = TemplateContext {
+            collection: Collection {
+                profiles: indexmap! {profile_id.clone() => profile},
+                chains: indexmap! {chain.id.clone() => chain},
+                ..Collection::factory(())
+            },
+            selected_profile: Some(profile_id),
+            prompter: Box::new(NextPrompter),
+            ..TemplateContext::factory(())
+        };
+        assert_eq!(render!("{{chains.chain1}}", context).unwrap(), "eu-east");
+    }
+
+    /// The picked file's contents or path are used, depending on `output`
+    #[rstest]
+    #[case::contents(FilePickerOutput::Contents, "hello!")]
+    #[case::path(FilePickerOutput::Path, "{dir}/fish.txt")]
+    #[tokio::test]
+    async fn test_chain_file_picker(
+        temp_dir: TempDir,
+        #[case] output: FilePickerOutput,
+        #[case] expected: &str,
+    ) {
+        fs::write(temp_dir.join("fish.txt"), "hello!")
+            .await
+            .unwrap();
+        let dir = temp_dir.to_str().unwrap();
+        let chain = Chain {
+            source: ChainSource::FilePicker {
+                message: Some("Pick a fish".into()),
+                directory: Some(dir.into()),
+                output,
+            },
+            ..Chain::factory(())
+        };
+        let context = TemplateContext {
+            collection: Collection {
+                chains: indexmap! {chain.id.clone() => chain},
+                ..Collection::factory(())
+            },
+            // Relative to the chain's directory
+            prompter: Box::new(TestPrompter::new(Some("fish.txt"))),
+            ..TemplateContext::factory(())
+        };
+        assert_eq!(
+            render!("{{chains.chain1}}", context).unwrap(),
+            expected.replace("{dir}", dir)
+        );
+    }
+
     /// Prompting gone wrong
     #[tokio::test]
     async fn test_chain_prompt_error() {
@@ -837,6 +1689,8 @@ mod tests {
             source: ChainSource::Prompt {
                 message: Some("password".into()),
                 default: None,
+                options: Vec::new(),
+                multiline: false,
             },
             ..Chain::factory(())
         };
@@ -856,6 +1710,30 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_chain_prompt_options_error() {
+        let chain = Chain {
+            source: ChainSource::Prompt {
+                message: None,
+                default: None,
+                options: vec!["{{unknown}}".into()],
+                multiline: false,
+            },
+            ..Chain::factory(())
+        };
+        let context = TemplateContext {
+            collection: Collection {
+                chains: indexmap! {chain.id.clone() => chain},
+                ..Collection::factory(())
+            },
+            ..TemplateContext::factory(())
+        };
+        assert_err!(
+            render!("{{chains.chain1}}", context),
+            "Rendering nested template for field `options`"
+        );
+    }
+
     /// Values marked sensitive should have that flag set in the rendered output
     #[tokio::test]
     async fn test_chain_sensitive() {
@@ -863,6 +1741,8 @@ mod tests {
             source: ChainSource::Prompt {
                 message: Some("password".into()),
                 default: None,
+                options: Vec::new(),
+                multiline: false,
             },
             sensitive: true,
             ..Chain::factory(())
@@ -887,6 +1767,131 @@ mod tests {
         );
     }
 
+    /// Profile fields listed as sensitive are flagged in the rendered output,
+    /// and their values are tracked for redaction
+    #[tokio::test]
+    async fn test_profile_sensitive() {
+        let profile = Profile {
+            data: indexmap! {
+                "password".into() => "hunter2".into(),
+                "user".into() => "fish".into(),
+            },
+            sensitive: vec!["password".into()],
+            ..Profile::factory(())
+        };
+        let context = TemplateContext {
+            selected_profile: Some(profile.id.clone()),
+            collection: Collection {
+                profiles: indexmap! {profile.id.clone() => profile},
+                ..Collection::factory(())
+            },
+            ..TemplateContext::factory(())
+        };
+        assert_eq!(
+            Template::from("{{user}}:{{password}}")
+                .render_chunks(&context)
+                .await,
+            vec![
+                TemplateChunk::Rendered {
+                    value: "fish".into(),
+                    sensitive: false
+                },
+                TemplateChunk::Raw(Span::new(8, 1)),
+                TemplateChunk::Rendered {
+                    value: "hunter2".into(),
+                    sensitive: true
+                },
+            ]
+        );
+        assert_eq!(
+            *context.sensitive_values.lock().unwrap(),
+            HashSet::from(["hunter2".to_owned()])
+        );
+    }
+
+    /// A sensitive value passed as a function argument makes the output
+    /// sensitive too, since the argument can end up in the output
+    #[tokio::test]
+    async fn test_profile_sensitive_argument() {
+        let profile = Profile {
+            data: indexmap! {"password".into() => "hunter2".into()},
+            sensitive: vec!["password".into()],
+            ..Profile::factory(())
+        };
+        let context = TemplateContext {
+            selected_profile: Some(profile.id.clone()),
+            collection: Collection {
+                profiles: indexmap! {profile.id.clone() => profile},
+                ..Collection::factory(())
+            },
+            ..TemplateContext::factory(())
+        };
+        assert_eq!(
+            Template::from("{{bogus | default(password)}}")
+                .render_chunks(&context)
+                .await,
+            vec![TemplateChunk::Rendered {
+                value: "hunter2".into(),
+                sensitive: true
+            }]
+        );
+        assert_eq!(
+            *context.sensitive_values.lock().unwrap(),
+            HashSet::from(["hunter2".to_owned()])
+        );
+    }
+
+    /// Secret manager values are cached for the TTL, if given
+    #[rstest]
+    #[tokio::test]
+    async fn test_fetch_secret_cache(temp_dir: TempDir) {
+        // Each execution appends a line, and outputs the line count
+        let path = temp_dir.join("count.txt");
+        let command = vec![
+            "sh".to_owned(),
+            "-c".to_owned(),
+            format!("echo x >> {path:?}; wc -l < {path:?}"),
+        ];
+        let fetch = |cache_ttl| {
+            let command = command.clone();
+            async move {
+                let value =
+                    render::fetch_secret(command, cache_ttl).await.unwrap();
+                String::from_utf8(value).unwrap().trim().to_owned()
+            }
+        };
+
+        assert_eq!(fetch(Some(Duration::from_secs(3600))).await, "1");
+        assert_eq!(fetch(Some(Duration::from_secs(3600))).await, "1");
+        // No TTL bypasses the cache
+        assert_eq!(fetch(None).await, "2");
+        // Cached value is expired
+        assert_eq!(fetch(Some(Duration::ZERO)).await, "3");
+    }
+
+    /// A failed secret manager command is an error, and trailing newlines are
+    /// stripped from successful output
+    #[tokio::test]
+    async fn test_fetch_secret() {
+        let command = |script: &str| {
+            vec!["sh".to_owned(), "-c".to_owned(), script.to_owned()]
+        };
+        assert_eq!(
+            render::fetch_secret(command("echo hunter2"), None)
+                .await
+                .unwrap(),
+            b"hunter2"
+        );
+        assert_err!(
+            render::fetch_secret(
+                command("echo 'not signed in' >&2; exit 3"),
+                None
+            )
+            .await,
+            "`sh` failed (exit status: 3): not signed in"
+        );
+    }
+
     /// Test linking two chains together. This example is contribed because the
     /// command could just read the file itself, but don't worry about it it's
     /// just a test.
@@ -981,6 +1986,41 @@ mod tests {
         assert_eq!(render!("{{env.UNKNOWN}}", context).unwrap(), "");
     }
 
+    /// Secrets are loaded from the keyring, and always marked sensitive
+    #[tokio::test]
+    async fn test_secret() {
+        let context = TemplateContext::factory(());
+        secrets::store::set("test_template_secret", "hunter2").unwrap();
+        assert_eq!(
+            Template::from("{{secret.test_template_secret}}")
+                .render_chunks(&context)
+                .await,
+            vec![TemplateChunk::Rendered {
+                value: "hunter2".into(),
+                sensitive: true
+            }]
+        );
+        assert_err!(
+            render!("{{secret.test_template_unknown}}", context),
+            "Loading secret `test_template_unknown`: Secret does not exist"
+        );
+    }
+
+    /// Fake data is generated fresh for every key
+    #[tokio::test]
+    async fn test_fake() {
+        let context = TemplateContext::factory(());
+        let rendered = render!("{{fake.uuid}} {{fake.uuid}}", context).unwrap();
+        let (first, second) = rendered.split_once(' ').unwrap();
+        assert_eq!(first.len(), 36);
+        assert_ne!(first, second);
+        assert!(render!("{{fake.email}}", context).unwrap().contains('@'));
+        assert_err!(
+            render!("{{fake.bogus}}", context),
+            "Unknown fake data `bogus`; must be one of: name, first_name"
+        );
+    }
+
     /// Test rendering non-UTF-8 data
     #[tokio::test]
     async fn test_render_binary() {
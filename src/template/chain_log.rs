@@ -0,0 +1,142 @@
+//! A record of the most recent resolution of each chain. Chains are otherwise
+//! a black box: their values are only visible in the requests they feed. This
+//! lets the UI show what a chain last produced (or why it failed), without
+//! having to resolve it again.
+
+use crate::{
+    collection::{Chain, ChainId, ChainRequestTrigger, ChainSource, ProfileId},
+    template::{render, trace, ChainError},
+};
+use chrono::{DateTime, Utc};
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+/// Latest resolution of each chain. This is global so it captures every
+/// render, whether it's a preview or a request being built
+static CHAIN_LOG: Mutex<Option<HashMap<ChainId, ChainResolution>>> =
+    Mutex::new(None);
+
+/// The outcome of one resolution of a chain
+#[derive(Clone, Debug)]
+pub struct ChainResolution {
+    pub resolved_at: DateTime<Utc>,
+    /// Profile that was selected for the render
+    pub profile_id: Option<ProfileId>,
+    /// Final value, after the selector and trimming are applied, or the error
+    /// message (including its sources)
+    pub outcome: Result<Vec<u8>, String>,
+    /// Was the chain marked sensitive? If so, the UI should mask the value
+    pub sensitive: bool,
+    /// For secret manager chains, the command the value was fetched with.
+    /// This is the key to its entry in the secret cache
+    pub(super) secret_command: Option<Vec<String>>,
+}
+
+/// Where the next render of a chain will get its value from
+#[derive(Clone, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub enum ChainCacheState {
+    /// The value is computed from scratch on every render
+    Uncached,
+    /// The value is taken from the most recent response in request history,
+    /// until the trigger decides to send the request again
+    History(ChainRequestTrigger),
+    /// A secret is cached, and will be reused for this much longer
+    Cached { expires_in: Duration },
+    /// A secret was cached, but its TTL has passed
+    Expired,
+    /// Nothing is cached yet
+    Empty,
+}
+
+/// Get the most recent resolution of a chain. `None` if it hasn't been
+/// resolved since the process started
+pub fn last_resolution(chain_id: &ChainId) -> Option<ChainResolution> {
+    CHAIN_LOG
+        .lock()
+        .expect("Chain log lock poisoned")
+        .as_ref()
+        .and_then(|log| log.get(chain_id))
+        .cloned()
+}
+
+/// Get the cache state of a chain
+pub fn cache_state(chain: &Chain) -> ChainCacheState {
+    let cache_ttl = match &chain.source {
+        ChainSource::Request {
+            trigger: ChainRequestTrigger::Always,
+            ..
+        } => return ChainCacheState::Uncached,
+        ChainSource::Request { trigger, .. } => {
+            return ChainCacheState::History(*trigger)
+        }
+        ChainSource::Vault { cache_ttl, .. }
+        | ChainSource::OnePassword { cache_ttl, .. }
+        | ChainSource::AwsSecret { cache_ttl, .. } => *cache_ttl,
+        _ => None,
+    };
+    let Some(cache_ttl) = cache_ttl else {
+        return ChainCacheState::Uncached;
+    };
+
+    // The secret cache is keyed by the rendered command, which we only know
+    // from the last resolution
+    let fetched_at = last_resolution(&chain.id)
+        .and_then(|resolution| resolution.secret_command)
+        .and_then(|command| render::secret_fetched_at(&command));
+    match fetched_at
+        .map(|fetched_at| cache_ttl.checked_sub(fetched_at.elapsed()))
+    {
+        None => ChainCacheState::Empty,
+        Some(Some(expires_in)) if !expires_in.is_zero() => {
+            ChainCacheState::Cached { expires_in }
+        }
+        Some(_) => ChainCacheState::Expired,
+    }
+}
+
+/// Forget the latest resolution of a chain. If its value came from a secret
+/// manager, the cached secret is dropped too, so the next render fetches it
+/// again. Return `false` if there was nothing to clear.
+pub fn clear_chain(chain_id: &ChainId) -> bool {
+    let resolution = CHAIN_LOG
+        .lock()
+        .expect("Chain log lock poisoned")
+        .as_mut()
+        .and_then(|log| log.remove(chain_id));
+    match resolution {
+        Some(resolution) => {
+            if let Some(command) = resolution.secret_command {
+                render::clear_secret(&command);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Record the outcome of a chain resolution, replacing the previous one
+pub(super) fn record(
+    chain_id: ChainId,
+    profile_id: Option<ProfileId>,
+    outcome: Result<(&[u8], bool), &ChainError>,
+    secret_command: Option<Vec<String>>,
+) {
+    let (outcome, sensitive) = match outcome {
+        Ok((value, sensitive)) => (Ok(value.to_owned()), sensitive),
+        Err(error) => (Err(trace::format_error(error)), false),
+    };
+    CHAIN_LOG
+        .lock()
+        .expect("Chain log lock poisoned")
+        .get_or_insert_with(HashMap::new)
+        .insert(
+            chain_id,
+            ChainResolution {
+                resolved_at: Utc::now(),
+                profile_id,
+                outcome,
+                sensitive,
+                secret_command,
+            },
+        );
+}
@@ -1,11 +1,15 @@
 use crate::{
     collection::{ChainId, ProfileId, RecipeId},
     http::{QueryError, RequestBuildError, RequestError},
-    template::RECURSION_LIMIT,
+    secrets::SecretError,
+    template::{Faker, Function, RECURSION_LIMIT},
     util::doc_link,
 };
+use bytesize::ByteSize;
+use itertools::Itertools;
 use nom::error::VerboseError;
-use std::{io, path::PathBuf, string::FromUtf8Error};
+use std::{io, path::PathBuf, process::ExitStatus, string::FromUtf8Error};
+use strum::IntoEnumIterator;
 use thiserror::Error;
 
 /// An error while parsing a template. This is derived from a nom error
@@ -74,6 +78,29 @@ pub enum TemplateError {
         #[source]
         error: ChainError,
     },
+
+    /// Unknown kind in a `{{fake.<kind>}}` key
+    #[error(
+        "Unknown fake data `{kind}`; must be one of: {}",
+        Faker::iter().map(<&str>::from).join(", ")
+    )]
+    FakeUnknown { kind: String },
+
+    /// Error loading a secret from the OS keyring
+    #[error("Loading secret `{name}`")]
+    Secret {
+        name: String,
+        #[source]
+        error: SecretError,
+    },
+
+    /// Error applying a function from a template pipeline
+    #[error("Calling function `{function}`")]
+    Function {
+        function: Function,
+        #[source]
+        error: Box<FunctionError>,
+    },
 }
 
 /// An error sub-type, for any error that occurs while resolving a chained
@@ -99,6 +126,14 @@ pub enum ChainError {
     #[error("No response available")]
     NoResponse,
 
+    /// The response body was too large to store in history, so only a
+    /// preview of it is left
+    #[error(
+        "Response body ({size}) was too large to store in history; send the \
+        request again to use its body"
+    )]
+    TruncatedBody { size: ByteSize },
+
     /// Couldn't guess content type from request/file/etc. metadata
     #[error(
         "Selector cannot be applied; content type not provided and could not \
@@ -139,6 +174,15 @@ pub enum ChainError {
         error: io::Error,
     },
 
+    /// A secret manager CLI ran but failed. Its stderr usually explains why
+    /// (e.g. not logged in)
+    #[error("`{program}` failed ({status}): {stderr}")]
+    SecretManager {
+        program: String,
+        status: ExitStatus,
+        stderr: String,
+    },
+
     /// Error opening/reading a file
     #[error("Reading file `{path}`")]
     File {
@@ -147,6 +191,11 @@ pub enum ChainError {
         error: io::Error,
     },
 
+    /// A `!script` chain failed to compile or run. Rhai errors include the
+    /// position in the script, so we don't need any extra context
+    #[error("Executing script")]
+    Script(#[source] Box<rhai::EvalAltResult>),
+
     /// Never got a response from the prompt channel. Do *not* store the
     /// `RecvError` here, because it provides useless extra output to the user.
     #[error("No response from prompt")]
@@ -166,6 +215,49 @@ pub enum ChainError {
     MissingHeader { header: String },
 }
 
+/// An error sub-type, for any error that occurs while applying a template
+/// function to a value
+#[derive(Debug, Error)]
+pub enum FunctionError {
+    /// The function needs a string, but got bytes that aren't valid UTF-8
+    #[error(transparent)]
+    InvalidUtf8(FromUtf8Error),
+
+    /// Arguments should be validated during parsing, so this shouldn't happen
+    #[error("Invalid arguments")]
+    InvalidArguments,
+
+    #[error(transparent)]
+    Base64Decode(#[from] base64::DecodeError),
+
+    #[error("Invalid percent-encoding")]
+    InvalidPercentEncoding,
+
+    /// Failed to parse the value before applying a JSONPath query
+    #[error("Parsing JSON")]
+    ParseJson(#[source] anyhow::Error),
+
+    /// Got either 0 or 2+ results for JSON path query
+    #[error(transparent)]
+    Query(#[from] QueryError),
+
+    /// Error rendering a key passed as an argument
+    #[error("Rendering argument")]
+    Argument(#[source] Box<TemplateError>),
+
+    /// Error reading a file, e.g. a private key
+    #[error("Reading file `{path}`")]
+    File {
+        path: PathBuf,
+        #[source]
+        error: io::Error,
+    },
+
+    /// Couldn't load a private key, or it can't be used for the algorithm
+    #[error("Invalid or unsupported private key")]
+    InvalidKey,
+}
+
 /// Error occurred while trying to build/execute a triggered request
 #[derive(Debug, Error)]
 pub enum TriggeredRequestError {
@@ -185,6 +277,13 @@ pub enum TriggeredRequestError {
 }
 
 impl TemplateError {
+    /// Did the key fail because it has no value at all, rather than because
+    /// its value failed to render? Missing values can be replaced with
+    /// `default()`, and are falsy in conditionals.
+    pub(super) fn is_missing(&self) -> bool {
+        matches!(self, Self::NoProfileSelected | Self::FieldUnknown { .. })
+    }
+
     /// Does the given error have *any* error in its chain that contains
     /// [TriggeredRequestError::NotAllowed]? This makes it easy to attach
     /// additional error context.
@@ -213,3 +312,11 @@ impl PartialEq for ChainError {
         unimplemented!("PartialEq for ChainError is hard to implement")
     }
 }
+
+/// Placeholder implementation, for the same reason as [ChainError]
+#[cfg(test)]
+impl PartialEq for FunctionError {
+    fn eq(&self, _: &Self) -> bool {
+        unimplemented!("PartialEq for FunctionError is hard to implement")
+    }
+}
@@ -0,0 +1,112 @@
+//! Random test data for `{{fake.<kind>}}` template keys. A new value is
+//! generated every time a key is rendered, so each request gets fresh data.
+
+use fake::{
+    faker::{
+        address::en::{CityName, CountryName, StreetName, ZipCode},
+        company::en::CompanyName,
+        internet::en::{IPv4, IPv6, Password, SafeEmail, UserAgent, Username},
+        lorem::en::{Paragraph, Sentence, Word},
+        name::en::{FirstName, LastName, Name},
+        phone_number::en::PhoneNumber,
+    },
+    Fake,
+};
+use strum::{EnumIter, EnumString, IntoStaticStr};
+use uuid::Uuid;
+
+/// A kind of fake data that can be generated. The variant name (in
+/// snake_case) is the key used in templates, e.g. `{{fake.first_name}}`
+#[derive(
+    Copy, Clone, Debug, PartialEq, EnumIter, EnumString, IntoStaticStr,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum Faker {
+    /// Full name, e.g. `Lorenzo Schultz`
+    Name,
+    FirstName,
+    LastName,
+    Username,
+    /// Email address on a reserved domain, e.g. `jo@example.net`
+    Email,
+    /// 8-16 alphanumeric characters
+    Password,
+    Phone,
+    Ipv4,
+    Ipv6,
+    UserAgent,
+    /// Random (v4) UUID
+    Uuid,
+    Company,
+    Street,
+    City,
+    Country,
+    ZipCode,
+    /// A single lorem ipsum word
+    Word,
+    /// A few lorem ipsum words, ending with a period
+    Sentence,
+    /// A few lorem ipsum sentences
+    Paragraph,
+}
+
+impl Faker {
+    /// Generate a new random value
+    pub fn generate(self) -> String {
+        match self {
+            Self::Name => Name().fake(),
+            Self::FirstName => FirstName().fake(),
+            Self::LastName => LastName().fake(),
+            Self::Username => Username().fake(),
+            Self::Email => SafeEmail().fake(),
+            Self::Password => Password(8..17).fake(),
+            Self::Phone => PhoneNumber().fake(),
+            Self::Ipv4 => IPv4().fake(),
+            Self::Ipv6 => IPv6().fake(),
+            Self::UserAgent => UserAgent().fake(),
+            Self::Uuid => Uuid::new_v4().to_string(),
+            Self::Company => CompanyName().fake(),
+            Self::Street => StreetName().fake(),
+            Self::City => CityName().fake(),
+            Self::Country => CountryName().fake(),
+            Self::ZipCode => ZipCode().fake(),
+            Self::Word => Word().fake(),
+            Self::Sentence => Sentence(3..8).fake(),
+            Self::Paragraph => Paragraph(2..5).fake(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use strum::IntoEnumIterator;
+
+    /// Every kind should generate a non-empty value
+    #[test]
+    fn test_generate_all() {
+        for faker in Faker::iter() {
+            assert!(!faker.generate().is_empty(), "{faker:?} generated empty");
+        }
+    }
+
+    #[rstest]
+    #[case::email(Faker::Email, |value: &str| value.contains('@'))]
+    #[case::ipv4(Faker::Ipv4, |value: &str| value.parse::<Ipv4Addr>().is_ok())]
+    #[case::ipv6(Faker::Ipv6, |value: &str| value.parse::<Ipv6Addr>().is_ok())]
+    #[case::uuid(Faker::Uuid, |value: &str| value.parse::<Uuid>().is_ok())]
+    #[case::password(Faker::Password, |value: &str| (8..=16).contains(&value.len()))]
+    fn test_generate(#[case] faker: Faker, #[case] check: fn(&str) -> bool) {
+        let value = faker.generate();
+        assert!(check(&value), "Invalid value {value:?} for {faker:?}");
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("first_name".parse::<Faker>().unwrap(), Faker::FirstName);
+        assert_eq!("ipv4".parse::<Faker>().unwrap(), Faker::Ipv4);
+        assert!("bogus".parse::<Faker>().is_err());
+    }
+}
@@ -0,0 +1,549 @@
+//! Built-in functions that a template key's value can be piped through, e.g.
+//! `{{token | base64}}` or `{{chains.body | jsonpath('$.id') | urlencode}}`
+
+use crate::{
+    http::{ContentType, Query},
+    template::{error::FunctionError, TemplateKey},
+};
+use base64::{
+    prelude::{BASE64_STANDARD, BASE64_URL_SAFE_NO_PAD},
+    Engine,
+};
+use hmac::{Hmac, Mac};
+use itertools::Itertools;
+use rustls::{
+    crypto::ring::sign,
+    pki_types::{PrivateKeyDer, PrivatePkcs1KeyDer, PrivatePkcs8KeyDer},
+    SignatureScheme,
+};
+use sha2::{Digest, Sha256};
+use std::{fmt, fs, path::PathBuf, str::FromStr};
+use strum::{Display, EnumString};
+
+/// A built-in template function. In a template, each function is referred to
+/// by its snake_case name. Functions take the piped value as input, plus
+/// any number of arguments.
+#[derive(Copy, Clone, Debug, Display, EnumString, PartialEq)]
+#[strum(serialize_all = "snake_case")]
+pub enum Function {
+    /// Encode as standard base64
+    Base64,
+    /// Decode standard base64
+    Base64Decode,
+    /// Percent-encode everything except unreserved characters, so the value
+    /// can be used in a URL path or query parameter
+    Urlencode,
+    /// Decode percent-encoded characters
+    Urldecode,
+    /// Hex-encoded SHA-256 hash
+    Sha256,
+    /// Convert to uppercase
+    Upper,
+    /// Convert to lowercase
+    Lower,
+    /// Remove leading and trailing whitespace
+    Trim,
+    /// Parse the value as JSON and apply a JSONPath query to it. The query
+    /// must return exactly one result.
+    Jsonpath,
+    /// Hex- or base64-encoded HMAC-SHA256 signature of the value. The first
+    /// argument is the key, and the optional second argument is the output
+    /// encoding: `hex` (default) or `base64`
+    HmacSha256,
+    /// Sign the value, which must be a JSON object of claims, as a JWT. The
+    /// first argument is the algorithm. For `HS256`, the second argument is
+    /// the secret. For `RS256`, it's the path to a PEM-encoded private key.
+    Jwt,
+    /// Use the argument instead if the value is empty. If the key has no value
+    /// at all (e.g. the field isn't in the selected profile), the render skips
+    /// straight to this function, so it also acts as a fallback for missing
+    /// values.
+    Default,
+}
+
+/// Signing algorithms supported by [Function::Jwt]
+#[derive(Copy, Clone, Debug, Display, EnumString)]
+enum JwtAlgorithm {
+    #[strum(serialize = "HS256")]
+    Hs256,
+    #[strum(serialize = "RS256")]
+    Rs256,
+}
+
+impl Function {
+    /// Check if the arguments are valid for this function. This is called
+    /// while parsing the template, so mistakes are caught immediately. Keys
+    /// aren't rendered until the function is called, so only literal
+    /// arguments can be checked here. Return a static message because that's
+    /// all nom errors can hold.
+    pub fn check_arguments(
+        self,
+        arguments: &[FunctionArgument<&str>],
+    ) -> Result<(), &'static str> {
+        let literal = |index: usize| match arguments.get(index) {
+            Some(FunctionArgument::Literal(value)) => Some(*value),
+            _ => None,
+        };
+        match self {
+            Self::Jsonpath if arguments.len() != 1 => {
+                Err("jsonpath() takes 1 argument")
+            }
+            Self::Jsonpath => match literal(0).map(Query::from_str) {
+                Some(Err(_)) => Err("invalid JSONPath query"),
+                _ => Ok(()),
+            },
+            Self::HmacSha256 if !(1..=2).contains(&arguments.len()) => {
+                Err("hmac_sha256() takes 1 or 2 arguments")
+            }
+            Self::HmacSha256 => match literal(1) {
+                Some("hex" | "base64") | None => Ok(()),
+                Some(_) => Err("encoding must be 'hex' or 'base64'"),
+            },
+            Self::Default if arguments.len() != 1 => {
+                Err("default() takes 1 argument")
+            }
+            Self::Default => Ok(()),
+            Self::Jwt if arguments.len() != 2 => Err("jwt() takes 2 arguments"),
+            Self::Jwt => match literal(0).map(JwtAlgorithm::from_str) {
+                Some(Err(_)) => Err("algorithm must be 'HS256' or 'RS256'"),
+                _ => Ok(()),
+            },
+            _ if arguments.is_empty() => Ok(()),
+            _ => Err("function takes no arguments"),
+        }
+    }
+
+    /// Apply this function to a value, with rendered arguments. Arguments
+    /// should have already been validated with [Self::check_arguments].
+    pub fn apply(
+        self,
+        value: Vec<u8>,
+        arguments: &[String],
+    ) -> Result<Vec<u8>, FunctionError> {
+        let to_string = |value| {
+            String::from_utf8(value).map_err(FunctionError::InvalidUtf8)
+        };
+        Ok(match self {
+            Self::Base64 => BASE64_STANDARD.encode(value).into_bytes(),
+            Self::Base64Decode => BASE64_STANDARD.decode(value)?,
+            Self::Urlencode => urlencode(&value).into_bytes(),
+            Self::Urldecode => urldecode(&value)?,
+            Self::Sha256 => hex::encode(Sha256::digest(value)).into_bytes(),
+            Self::Upper => to_string(value)?.to_uppercase().into_bytes(),
+            Self::Lower => to_string(value)?.to_lowercase().into_bytes(),
+            Self::Trim => to_string(value)?.trim().as_bytes().to_owned(),
+            Self::Jsonpath => {
+                let query = arguments
+                    .first()
+                    .and_then(|query| Query::from_str(query).ok())
+                    .ok_or(FunctionError::InvalidArguments)?;
+                let content = ContentType::Json
+                    .parse_content(&value)
+                    .map_err(FunctionError::ParseJson)?;
+                query.query_to_string(&*content)?.into_bytes()
+            }
+            Self::HmacSha256 => {
+                let [key, encoding @ ..] = arguments else {
+                    return Err(FunctionError::InvalidArguments);
+                };
+                let signature = hmac_sha256(key.as_bytes(), &value);
+                match encoding.first().map(String::as_str) {
+                    None | Some("hex") => hex::encode(signature),
+                    Some("base64") => BASE64_STANDARD.encode(signature),
+                    Some(_) => return Err(FunctionError::InvalidArguments),
+                }
+                .into_bytes()
+            }
+            Self::Jwt => {
+                let [algorithm, key] = arguments else {
+                    return Err(FunctionError::InvalidArguments);
+                };
+                let algorithm = JwtAlgorithm::from_str(algorithm)
+                    .map_err(|_| FunctionError::InvalidArguments)?;
+                jwt(algorithm, key, &value)?.into_bytes()
+            }
+            Self::Default => {
+                let [fallback] = arguments else {
+                    return Err(FunctionError::InvalidArguments);
+                };
+                if value.is_empty() {
+                    fallback.clone().into_bytes()
+                } else {
+                    value
+                }
+            }
+        })
+    }
+}
+
+/// An argument to a function call
+#[derive(Clone, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub enum FunctionArgument<T> {
+    /// A quoted string, *without* its surrounding quotes
+    Literal(T),
+    /// An unquoted template key, e.g. `secret.signing_key`. It's rendered
+    /// before the function is called.
+    Key(TemplateKey<T>),
+}
+
+impl<T> FunctionArgument<T> {
+    /// Map the internal data using the given function
+    fn map<U>(self, f: impl Fn(T) -> U) -> FunctionArgument<U> {
+        match self {
+            Self::Literal(value) => FunctionArgument::Literal(f(value)),
+            Self::Key(key) => FunctionArgument::Key(key.map(f)),
+        }
+    }
+}
+
+/// Format as the argument would appear in a template. Literals are quoted
+/// with `'`, unless they contain one. Literals can't contain both quote types,
+/// so this always re-parses to the same argument.
+impl fmt::Display for FunctionArgument<&str> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Literal(value) => {
+                let quote = if value.contains('\'') { '"' } else { '\'' };
+                write!(f, "{quote}{value}{quote}")
+            }
+            Self::Key(key) => write!(f, "{key}"),
+        }
+    }
+}
+
+/// A call to a function in a template pipeline, e.g. `jsonpath('$.id')`
+#[derive(Clone, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct FunctionCall<T> {
+    pub function: Function,
+    pub arguments: Vec<FunctionArgument<T>>,
+}
+
+impl<T> FunctionCall<T> {
+    /// Map the internal data using the given function. Useful for mapping
+    /// string slices to spans and vice versa.
+    pub(super) fn map<U>(self, f: impl Fn(T) -> U) -> FunctionCall<U> {
+        FunctionCall {
+            function: self.function,
+            arguments: self
+                .arguments
+                .into_iter()
+                .map(|argument| argument.map(&f))
+                .collect(),
+        }
+    }
+}
+
+/// Format as the function would appear in a template
+impl fmt::Display for FunctionCall<&str> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.function)?;
+        if !self.arguments.is_empty() {
+            write!(f, "({})", self.arguments.iter().join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key)
+        .expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Create a signed JWT from a JSON object of claims. For RS256, `key` is the
+/// path to a private key file.
+fn jwt(
+    algorithm: JwtAlgorithm,
+    key: &str,
+    claims: &[u8],
+) -> Result<String, FunctionError> {
+    // Round trip the claims to make sure they're an object, and to strip
+    // whitespace so the token is as short as possible
+    let claims: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_slice(claims)
+            .map_err(|error| FunctionError::ParseJson(error.into()))?;
+    let claims = serde_json::to_string(&claims)
+        .map_err(|error| FunctionError::ParseJson(error.into()))?;
+    let header = format!(r#"{{"alg":"{algorithm}","typ":"JWT"}}"#);
+    let message = format!(
+        "{}.{}",
+        BASE64_URL_SAFE_NO_PAD.encode(header),
+        BASE64_URL_SAFE_NO_PAD.encode(claims)
+    );
+
+    let signature = match algorithm {
+        JwtAlgorithm::Hs256 => hmac_sha256(key.as_bytes(), message.as_bytes()),
+        JwtAlgorithm::Rs256 => {
+            let path = PathBuf::from(key);
+            let pem = fs::read_to_string(&path)
+                .map_err(|error| FunctionError::File { path, error })?;
+            sign::any_supported_type(&parse_private_key(&pem)?)
+                .ok()
+                .and_then(|key| {
+                    key.choose_scheme(&[SignatureScheme::RSA_PKCS1_SHA256])
+                })
+                .ok_or(FunctionError::InvalidKey)?
+                .sign(message.as_bytes())
+                .map_err(|_| FunctionError::InvalidKey)?
+        }
+    };
+    Ok(format!(
+        "{message}.{}",
+        BASE64_URL_SAFE_NO_PAD.encode(signature)
+    ))
+}
+
+/// Parse the first private key in a PEM file. Supports PKCS#8
+/// (`BEGIN PRIVATE KEY`) and PKCS#1 (`BEGIN RSA PRIVATE KEY`)
+fn parse_private_key(
+    pem: &str,
+) -> Result<PrivateKeyDer<'static>, FunctionError> {
+    let mut lines = pem.lines().map(str::trim);
+    let label = lines
+        .find_map(|line| {
+            line.strip_prefix("-----BEGIN ")?.strip_suffix("-----")
+        })
+        .ok_or(FunctionError::InvalidKey)?;
+    let end = format!("-----END {label}-----");
+    let content: String = lines.take_while(|line| *line != end).collect();
+    let der = BASE64_STANDARD
+        .decode(content)
+        .map_err(|_| FunctionError::InvalidKey)?;
+    match label {
+        "PRIVATE KEY" => Ok(PrivatePkcs8KeyDer::from(der).into()),
+        "RSA PRIVATE KEY" => Ok(PrivatePkcs1KeyDer::from(der).into()),
+        _ => Err(FunctionError::InvalidKey),
+    }
+}
+
+/// Percent-encode all bytes except the unreserved characters from RFC 3986
+fn urlencode(value: &[u8]) -> String {
+    value
+        .iter()
+        .map(|&byte| {
+            if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                char::from(byte).to_string()
+            } else {
+                format!("%{byte:02X}")
+            }
+        })
+        .collect()
+}
+
+/// Decode `%XX` sequences. Anything else is passed through as-is
+fn urldecode(value: &[u8]) -> Result<Vec<u8>, FunctionError> {
+    let mut decoded = Vec::with_capacity(value.len());
+    let mut bytes = value.iter();
+    while let Some(&byte) = bytes.next() {
+        if byte == b'%' {
+            let hex = [bytes.next(), bytes.next()];
+            let [Some(&high), Some(&low)] = hex else {
+                return Err(FunctionError::InvalidPercentEncoding);
+            };
+            let byte = std::str::from_utf8(&[high, low])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or(FunctionError::InvalidPercentEncoding)?;
+            decoded.push(byte);
+        } else {
+            decoded.push(byte);
+        }
+    }
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_err, test_data_dir};
+    use rstest::rstest;
+
+    /// Convert string slices to owned arguments
+    fn arguments(arguments: &[&str]) -> Vec<String> {
+        arguments.iter().copied().map(String::from).collect()
+    }
+
+    #[rstest]
+    #[case::base64(Function::Base64, "hello?", &[], "aGVsbG8/")]
+    #[case::base64_decode(Function::Base64Decode, "aGVsbG8/", &[], "hello?")]
+    #[case::urlencode(Function::Urlencode, "a b&c=d/é~", &[], "a%20b%26c%3Dd%2F%C3%A9~")]
+    #[case::urldecode(Function::Urldecode, "a%20b%26c+%C3%A9", &[], "a b&c+é")]
+    #[case::sha256(
+        Function::Sha256,
+        "hello",
+        &[],
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+    )]
+    #[case::upper(Function::Upper, "Hello", &[], "HELLO")]
+    #[case::lower(Function::Lower, "Hello", &[], "hello")]
+    #[case::trim(Function::Trim, "  hello\n", &[], "hello")]
+    #[case::jsonpath_string(Function::Jsonpath, r#"{"id": "abc"}"#, &["$.id"], "abc")]
+    #[case::jsonpath_object(
+        Function::Jsonpath,
+        r#"{"user": {"id": 3}}"#,
+        &["$.user"],
+        r#"{"id":3}"#
+    )]
+    #[case::hmac_sha256(
+        Function::HmacSha256,
+        "hello",
+        &["key"],
+        "9307b3b915efb5171ff14d8cb55fbcc798c6c0ef1456d66ded1a6aa723a58b7b"
+    )]
+    #[case::hmac_sha256_base64(
+        Function::HmacSha256,
+        "hello",
+        &["key", "base64"],
+        "kwezuRXvtRcf8U2MtV+8x5jGwO8UVtZt7RpqpyOli3s="
+    )]
+    #[case::default_empty(Function::Default, "", &["fallback"], "fallback")]
+    #[case::default_present(Function::Default, "value", &["fallback"], "value")]
+    #[case::jwt_hs256(
+        Function::Jwt,
+        // Whitespace is removed
+        r#"{ "sub": "fish" }"#,
+        &["HS256", "secret"],
+        "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.eyJzdWIiOiJmaXNoIn0.\
+        c5zOHetH1ZSGy1wiaL3zDnH8fYXjp5Q-ktjVrZ4jVx4"
+    )]
+    fn test_apply(
+        #[case] function: Function,
+        #[case] value: &str,
+        #[case] arguments: &[&str],
+        #[case] expected: &str,
+    ) {
+        let output = function
+            .apply(value.into(), &self::arguments(arguments))
+            .unwrap();
+        assert_eq!(std::str::from_utf8(&output).unwrap(), expected);
+    }
+
+    /// RS256 signatures are deterministic, so we can compare against a token
+    /// generated by `openssl dgst -sha256 -sign`
+    #[test]
+    fn test_jwt_rs256() {
+        let path = test_data_dir().join("jwt_rs256.pem");
+        let output = Function::Jwt
+            .apply(
+                r#"{"sub":"fish"}"#.into(),
+                &["RS256".into(), path.to_str().unwrap().into()],
+            )
+            .unwrap();
+        let expected = "eyJhbGciOiJSUzI1NiIsInR5cCI6IkpXVCJ9.\
+            eyJzdWIiOiJmaXNoIn0.Z5bpQYJ-JTBdg_pdnJm7U4EArO3UySt55GINs2zsG02j4-\
+            ay1ypDE7wuM5peor023WWDOjD07j4r1WBUD4eSTk4v6hAeBNaoCFdYWshBsbnexrJO\
+            pysRSmqSmqZ2m_UwpRKw11RtF0hCPWxH_-jDItKu7uGxWE6WIw5bsWsjDIdbGGRaEq\
+            AkDwYQf4KVY-wwjBi299_83IIxI1iSbtI40enBSbI40E2CuULnfp5JmfnLrpPLXs-h\
+            _YMG8aEusehO-wPScuag5CTlab9IKqhe6AzxFNM0KlX3UFR3eu1HhRh-GM6K740QES\
+            A9XveWCuqhm7OcK9iBgw0sOasVkzBI9A";
+        assert_eq!(std::str::from_utf8(&output).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::base64_decode(Function::Base64Decode, b"!!!", &[], "Invalid")]
+    #[case::urldecode(
+        Function::Urldecode,
+        b"%2",
+        &[],
+        "Invalid percent-encoding"
+    )]
+    #[case::upper(Function::Upper, b"\xc3\x28", &[], "invalid utf-8")]
+    #[case::jsonpath_parse(Function::Jsonpath, b"{", &["$"], "Parsing JSON")]
+    #[case::jsonpath_query(
+        Function::Jsonpath,
+        b"[1, 2]",
+        &["$[*]"],
+        "Expected exactly one result"
+    )]
+    #[case::jwt_not_object(Function::Jwt, b"[]", &["HS256", "k"], "Parsing JSON")]
+    #[case::jwt_missing_key(
+        Function::Jwt,
+        b"{}",
+        &["RS256", "./does_not_exist.pem"],
+        "Reading file `./does_not_exist.pem`"
+    )]
+    #[case::jwt_invalid_key(
+        Function::Jwt,
+        b"{}",
+        &["RS256", "Cargo.toml"],
+        "Invalid or unsupported private key"
+    )]
+    fn test_apply_error(
+        #[case] function: Function,
+        #[case] value: &[u8],
+        #[case] arguments: &[&str],
+        #[case] expected_error: &str,
+    ) {
+        assert_err!(
+            function.apply(value.into(), &self::arguments(arguments)),
+            expected_error
+        );
+    }
+
+    #[rstest]
+    #[case::no_arguments(Function::Base64, &[], None)]
+    #[case::unexpected_argument(
+        Function::Base64,
+        &["a"],
+        Some("function takes no arguments")
+    )]
+    #[case::jsonpath(Function::Jsonpath, &["$.id"], None)]
+    #[case::jsonpath_missing(
+        Function::Jsonpath,
+        &[],
+        Some("jsonpath() takes 1 argument")
+    )]
+    #[case::jsonpath_invalid(
+        Function::Jsonpath,
+        &["$."],
+        Some("invalid JSONPath query")
+    )]
+    #[case::hmac_sha256(Function::HmacSha256, &["key", "hex"], None)]
+    #[case::hmac_sha256_encoding(
+        Function::HmacSha256,
+        &["key", "base32"],
+        Some("encoding must be 'hex' or 'base64'")
+    )]
+    #[case::jwt(Function::Jwt, &["RS256", "key.pem"], None)]
+    #[case::jwt_algorithm(
+        Function::Jwt,
+        &["none", "key"],
+        Some("algorithm must be 'HS256' or 'RS256'")
+    )]
+    #[case::jwt_missing_key(
+        Function::Jwt,
+        &["HS256"],
+        Some("jwt() takes 2 arguments")
+    )]
+    #[case::default(Function::Default, &["fallback"], None)]
+    #[case::default_missing(
+        Function::Default,
+        &[],
+        Some("default() takes 1 argument")
+    )]
+    fn test_check_arguments(
+        #[case] function: Function,
+        #[case] arguments: &[&str],
+        #[case] expected_error: Option<&str>,
+    ) {
+        let arguments = arguments
+            .iter()
+            .copied()
+            .map(FunctionArgument::Literal)
+            .collect_vec();
+        assert_eq!(function.check_arguments(&arguments).err(), expected_error);
+    }
+
+    /// Keys can't be validated until they're rendered
+    #[test]
+    fn test_check_arguments_key() {
+        let arguments = [
+            FunctionArgument::Key(TemplateKey::Field("algorithm")),
+            FunctionArgument::Key(TemplateKey::Secret("key")),
+        ];
+        assert_eq!(Function::Jwt.check_arguments(&arguments), Ok(()));
+    }
+}
@@ -1,21 +1,31 @@
 //! Template string parser
 
-use crate::template::{error::TemplateParseError, Template, TemplateKey};
+use crate::template::{
+    error::TemplateParseError, Function, FunctionArgument, FunctionCall,
+    Template, TemplateKey,
+};
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_while1},
-    combinator::{all_consuming, cut},
-    error::{context, ErrorKind, ParseError, VerboseError},
-    multi::many0,
-    sequence::{preceded, terminated},
+    bytes::complete::{tag, take_while, take_while1},
+    character::complete::{space0, space1},
+    combinator::{all_consuming, cut, not, opt},
+    error::{context, ErrorKind, ParseError, VerboseError, VerboseErrorKind},
+    multi::{many0, separated_list0},
+    sequence::{delimited, pair, preceded, terminated, tuple},
     FindSubstring, Finish, IResult, InputLength, InputTake, Offset, Parser,
 };
 
 const KEY_OPEN: &str = "{{";
 const KEY_CLOSE: &str = "}}";
+const PIPE: &str = "|";
+const IF_OPEN: &str = "{{#if";
+const ELSE: &str = "{{else}}";
+const IF_CLOSE: &str = "{{/if}}";
 // Export these so they can be used in TemplateKey's Display impl
 pub const CHAIN_PREFIX: &str = "chains.";
 pub const ENV_PREFIX: &str = "env.";
+pub const SECRET_PREFIX: &str = "secret.";
+pub const FAKE_PREFIX: &str = "fake.";
 
 type ParseResult<'a, T> = IResult<&'a str, T, VerboseError<&'a str>>;
 
@@ -31,28 +41,58 @@ impl Template {
         // be better to track the spans as we go, but that's a bit harder
         let mapper = |s: &str| Span::new(template.offset(s), s.len());
         let chunks =
-            chunks.into_iter().map(|chunk| chunk.map(mapper)).collect();
+            chunks.into_iter().map(|chunk| chunk.map(&mapper)).collect();
 
         Ok(Self { template, chunks })
     }
 }
 
-/// A parsed piece of a template. After parsing, each chunk is either raw text
-/// or a parsed key, ready to be rendered.
-#[derive(Copy, Clone, Debug)]
+/// A parsed piece of a template. After parsing, each chunk is either raw text,
+/// a parsed key, or a conditional block, ready to be rendered.
+#[derive(Clone, Debug)]
 #[cfg_attr(test, derive(PartialEq))]
 pub enum TemplateInputChunk<T> {
     Raw(T),
-    Key(TemplateKey<T>),
+    Key {
+        key: TemplateKey<T>,
+        /// Functions to pipe the key's value through, in order
+        functions: Vec<FunctionCall<T>>,
+    },
+    /// `{{#if condition}}...{{else}}...{{/if}}`. Only one branch is rendered,
+    /// depending on whether the condition key's value is truthy
+    Conditional {
+        condition: TemplateKey<T>,
+        then: Vec<TemplateInputChunk<T>>,
+        /// Chunks from the `{{else}}` branch. Empty if there isn't one
+        otherwise: Vec<TemplateInputChunk<T>>,
+    },
 }
 
 impl<T> TemplateInputChunk<T> {
     /// Map the internal data using the given function. Useful for mapping
     /// string slices to spans and vice versa.
-    fn map<U>(self, f: impl Fn(T) -> U) -> TemplateInputChunk<U> {
+    fn map<U>(self, f: &impl Fn(T) -> U) -> TemplateInputChunk<U> {
         match self {
             Self::Raw(value) => TemplateInputChunk::Raw(f(value)),
-            Self::Key(key) => TemplateInputChunk::Key(key.map(f)),
+            Self::Key { key, functions } => TemplateInputChunk::Key {
+                key: key.map(f),
+                functions: functions
+                    .into_iter()
+                    .map(|call| call.map(f))
+                    .collect(),
+            },
+            Self::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => TemplateInputChunk::Conditional {
+                condition: condition.map(f),
+                then: then.into_iter().map(|chunk| chunk.map(f)).collect(),
+                otherwise: otherwise
+                    .into_iter()
+                    .map(|chunk| chunk.map(f))
+                    .collect(),
+            },
         }
     }
 }
@@ -83,12 +123,50 @@ impl Span {
     }
 }
 
-/// Parse a template into keys and raw text
+/// Parse a template into keys, conditionals, and raw text
 fn all_chunks(input: &str) -> ParseResult<Vec<TemplateInputChunk<&str>>> {
-    all_consuming(many0(alt((
-        key.map(TemplateInputChunk::Key),
+    all_consuming(many0(chunk))(input)
+}
+
+/// Parse a single chunk of any type
+fn chunk(input: &str) -> ParseResult<'_, TemplateInputChunk<&str>> {
+    alt((
+        conditional,
+        key.map(|(key, functions)| TemplateInputChunk::Key { key, functions }),
         raw.map(TemplateInputChunk::Raw),
-    ))))(input)
+    ))(input)
+}
+
+/// Parse a conditional block: `{{#if condition}}...{{/if}}`, with an optional
+/// `{{else}}` branch. Blocks can be nested.
+fn conditional(input: &str) -> ParseResult<'_, TemplateInputChunk<&str>> {
+    // Chunks within a branch, up to the `{{else}}` or `{{/if}}`
+    fn branch(input: &str) -> ParseResult<'_, Vec<TemplateInputChunk<&str>>> {
+        many0(preceded(not(alt((tag(ELSE), tag(IF_CLOSE)))), chunk))(input)
+    }
+
+    context(
+        "conditional",
+        preceded(
+            tag(IF_OPEN),
+            // Once we've seen the opening tag, any error is fatal, including
+            // a missing `{{/if}}`
+            cut(tuple((
+                delimited(space1, key_contents, pair(space0, tag(KEY_CLOSE))),
+                branch,
+                opt(preceded(tag(ELSE), branch)),
+                context("if close", tag(IF_CLOSE)),
+            ))),
+        ),
+    )
+    .map(
+        |(condition, then, otherwise, _)| TemplateInputChunk::Conditional {
+            condition,
+            then,
+            otherwise: otherwise.unwrap_or_default(),
+        },
+    )
+    .parse(input)
 }
 
 /// Parse raw text, until we hit a key or end of input
@@ -96,15 +174,26 @@ fn raw(input: &str) -> ParseResult<&str> {
     context("raw", take_until_or_eof(KEY_OPEN))(input)
 }
 
-/// Parse a template key
-fn key(input: &str) -> ParseResult<TemplateKey<&str>> {
+/// Parse a template key, and the functions it's piped through
+fn key(
+    input: &str,
+) -> ParseResult<'_, (TemplateKey<&str>, Vec<FunctionCall<&str>>)> {
     context(
         "key",
         preceded(
             tag(KEY_OPEN),
             // Any error inside a template key is fatal, including an unclosed
             // key
-            cut(terminated(key_contents, tag(KEY_CLOSE))),
+            cut(terminated(
+                pair(
+                    key_contents,
+                    many0(preceded(
+                        delimited(space0, tag(PIPE), space0),
+                        function_call,
+                    )),
+                ),
+                tag(KEY_CLOSE),
+            )),
         ),
     )(input)
 }
@@ -120,10 +209,71 @@ fn key_contents(input: &str) -> ParseResult<TemplateKey<&str>> {
             "environment",
             preceded(tag(ENV_PREFIX), identifier).map(TemplateKey::Environment),
         ),
+        context(
+            "secret",
+            preceded(tag(SECRET_PREFIX), identifier).map(TemplateKey::Secret),
+        ),
+        context(
+            "fake",
+            preceded(tag(FAKE_PREFIX), identifier).map(TemplateKey::Fake),
+        ),
         context("field", identifier.map(TemplateKey::Field)),
     ))(input)
 }
 
+/// Parse a function call in a pipeline, e.g. `base64` or `jsonpath('$.id')`.
+/// Parentheses are optional if there are no arguments.
+fn function_call(input: &str) -> ParseResult<'_, FunctionCall<&str>> {
+    let (rest, name) = context(
+        "function",
+        take_while1(|c: char| c.is_alphanumeric() || c == '_'),
+    )(input)?;
+    let (rest, arguments) = opt(delimited(
+        pair(tag("("), space0),
+        separated_list0(delimited(space0, tag(","), space0), function_argument),
+        pair(space0, tag(")")),
+    ))(rest)?;
+    let arguments = arguments.unwrap_or_default();
+
+    // Errors are attached to the function name, so the user can see which one
+    // is bad
+    let error = |message| {
+        nom::Err::Failure(VerboseError {
+            errors: vec![(input, VerboseErrorKind::Context(message))],
+        })
+    };
+    let function: Function =
+        name.parse().map_err(|_| error("unknown function"))?;
+    function.check_arguments(&arguments).map_err(error)?;
+    Ok((
+        rest,
+        FunctionCall {
+            function,
+            arguments,
+        },
+    ))
+}
+
+/// Parse a function argument, which is either a string literal or a key
+fn function_argument(input: &str) -> ParseResult<'_, FunctionArgument<&str>> {
+    alt((
+        string_literal.map(FunctionArgument::Literal),
+        key_contents.map(FunctionArgument::Key),
+    ))(input)
+}
+
+/// Parse a single- or double-quoted string. There are no escape sequences, so
+/// the string can't contain its own quote character
+fn string_literal(input: &str) -> ParseResult<'_, &str> {
+    context(
+        "string",
+        alt((
+            delimited(tag("'"), take_while(|c| c != '\''), tag("'")),
+            delimited(tag("\""), take_while(|c| c != '"'), tag("\"")),
+        )),
+    )(input)
+}
+
 /// Parse a field name/chain ID/env variable etc, inside a key
 fn identifier(input: &str) -> ParseResult<&str> {
     context(
@@ -155,6 +305,29 @@ mod tests {
     use itertools::Itertools;
     use rstest::rstest;
 
+    /// Shorthand for a key chunk
+    fn key_chunk<'a>(
+        key: TemplateKey<&'a str>,
+        functions: Vec<FunctionCall<&'a str>>,
+    ) -> TemplateInputChunk<&'a str> {
+        TemplateInputChunk::Key { key, functions }
+    }
+
+    /// Shorthand for a function call with literal arguments
+    fn call<'a>(
+        function: Function,
+        arguments: &[&'a str],
+    ) -> FunctionCall<&'a str> {
+        FunctionCall {
+            function,
+            arguments: arguments
+                .iter()
+                .copied()
+                .map(FunctionArgument::Literal)
+                .collect(),
+        }
+    }
+
     /// Test parsing success cases
     #[rstest]
     #[case::empty("", vec![])]
@@ -162,27 +335,72 @@ mod tests {
     #[case::unopened_key("unopened}}", vec![TemplateInputChunk::Raw("unopened}}")])]
     #[case::field(
         "{{field1}}",
-        vec![TemplateInputChunk::Key(TemplateKey::Field("field1"))]
+        vec![key_chunk(TemplateKey::Field("field1"), vec![])]
     )]
-    #[case::field_number_id("{{1}}", vec![TemplateInputChunk::Key(TemplateKey::Field("1"))])]
+    #[case::field_number_id("{{1}}", vec![key_chunk(TemplateKey::Field("1"), vec![])])]
     #[case::chain(
         "{{chains.chain1}}",
-        vec![TemplateInputChunk::Key(TemplateKey::Chain("chain1"))]
+        vec![key_chunk(TemplateKey::Chain("chain1"), vec![])]
     )]
     #[case::env(
         "{{env.ENV}}",
-        vec![TemplateInputChunk::Key(TemplateKey::Environment("ENV"))]
+        vec![key_chunk(TemplateKey::Environment("ENV"), vec![])]
+    )]
+    #[case::secret(
+        "{{secret.api-key}}",
+        vec![key_chunk(TemplateKey::Secret("api-key"), vec![])]
+    )]
+    #[case::fake(
+        "{{fake.first_name}}",
+        vec![key_chunk(TemplateKey::Fake("first_name"), vec![])]
     )]
     #[case::utf8(
         "intro\n{{user_id}} 💚💙💜 {{chains.chain}}\noutro\r\nmore outro",
         vec![
             TemplateInputChunk::Raw("intro\n"),
-            TemplateInputChunk::Key(TemplateKey::Field("user_id")),
+            key_chunk(TemplateKey::Field("user_id"), vec![]),
             TemplateInputChunk::Raw(" 💚💙💜 "),
-            TemplateInputChunk::Key(TemplateKey::Chain("chain")),
+            key_chunk(TemplateKey::Chain("chain"), vec![]),
             TemplateInputChunk::Raw("\noutro\r\nmore outro"),
         ]
     )]
+    #[case::function(
+        "{{token | base64}}",
+        vec![key_chunk(
+            TemplateKey::Field("token"),
+            vec![call(Function::Base64, &[])],
+        )]
+    )]
+    #[case::pipeline(
+        "{{chains.body|jsonpath('$.id') |  urlencode()}}",
+        vec![key_chunk(
+            TemplateKey::Chain("body"),
+            vec![
+                call(Function::Jsonpath, &["$.id"]),
+                call(Function::Urlencode, &[]),
+            ],
+        )]
+    )]
+    #[case::double_quotes(
+        r#"{{body | jsonpath( "$['id']" )}}"#,
+        vec![key_chunk(
+            TemplateKey::Field("body"),
+            vec![call(Function::Jsonpath, &["$['id']"])],
+        )]
+    )]
+    #[case::key_arguments(
+        "{{body | hmac_sha256(secret.key, encoding)}}",
+        vec![key_chunk(
+            TemplateKey::Field("body"),
+            vec![FunctionCall {
+                function: Function::HmacSha256,
+                arguments: vec![
+                    FunctionArgument::Key(TemplateKey::Secret("key")),
+                    FunctionArgument::Key(TemplateKey::Field("encoding")),
+                ],
+            }],
+        )]
+    )]
     fn test_parse(
         #[case] template: &str,
         #[case] expected_chunks: Vec<TemplateInputChunk<&str>>,
@@ -193,7 +411,68 @@ mod tests {
         let chunks = parsed
             .chunks
             .iter()
-            .map(|chunk| chunk.map(|span| parsed.substring(span)))
+            .map(|chunk| chunk.clone().map(&|span| parsed.substring(span)))
+            .collect_vec();
+        assert_eq!(chunks, expected_chunks);
+    }
+
+    /// Test parsing conditional blocks
+    #[rstest]
+    #[case::if_only(
+        "a{{#if staging}}b{{host}}{{/if}}c",
+        vec![
+            TemplateInputChunk::Raw("a"),
+            TemplateInputChunk::Conditional {
+                condition: TemplateKey::Field("staging"),
+                then: vec![
+                    TemplateInputChunk::Raw("b"),
+                    key_chunk(TemplateKey::Field("host"), vec![]),
+                ],
+                otherwise: vec![],
+            },
+            TemplateInputChunk::Raw("c"),
+        ]
+    )]
+    #[case::else_branch(
+        "{{#if env.CI  }}ci{{else}}local{{/if}}",
+        vec![TemplateInputChunk::Conditional {
+            condition: TemplateKey::Environment("CI"),
+            then: vec![TemplateInputChunk::Raw("ci")],
+            otherwise: vec![TemplateInputChunk::Raw("local")],
+        }]
+    )]
+    #[case::empty("{{#if a}}{{/if}}", vec![TemplateInputChunk::Conditional {
+        condition: TemplateKey::Field("a"),
+        then: vec![],
+        otherwise: vec![],
+    }])]
+    #[case::nested(
+        "{{#if a}}{{#if chains.b}}1{{else}}2{{/if}}{{else}}3{{/if}}",
+        vec![TemplateInputChunk::Conditional {
+            condition: TemplateKey::Field("a"),
+            then: vec![TemplateInputChunk::Conditional {
+                condition: TemplateKey::Chain("b"),
+                then: vec![TemplateInputChunk::Raw("1")],
+                otherwise: vec![TemplateInputChunk::Raw("2")],
+            }],
+            otherwise: vec![TemplateInputChunk::Raw("3")],
+        }]
+    )]
+    // Outside a conditional, `else` is just a field
+    #[case::else_field(
+        "{{else}}",
+        vec![key_chunk(TemplateKey::Field("else"), vec![])]
+    )]
+    fn test_parse_conditional(
+        #[case] template: &str,
+        #[case] expected_chunks: Vec<TemplateInputChunk<&str>>,
+    ) {
+        let parsed =
+            Template::parse(template.to_owned()).expect("Parsing failed");
+        let chunks = parsed
+            .chunks
+            .iter()
+            .map(|chunk| chunk.clone().map(&|span| parsed.substring(span)))
             .collect_vec();
         assert_eq!(chunks, expected_chunks);
     }
@@ -209,7 +488,38 @@ mod tests {
     #[case::invalid_chain("{{chains.one.two}}")]
     #[case::invalid_env("{{env.one.two}}")]
     #[case::whitespace("{{ field }}")]
+    #[case::pipe_no_function("{{field |}}")]
+    #[case::unclosed_arguments("{{field | jsonpath('$'}}")]
+    #[case::unquoted_argument("{{field | jsonpath($)}}")]
+    #[case::if_unclosed("{{#if a}}b")]
+    #[case::if_no_condition("{{#if}}b{{/if}}")]
+    #[case::if_double_else("{{#if a}}b{{else}}c{{else}}d{{/if}}")]
+    #[case::close_without_if("a{{/if}}")]
     fn test_parse_error(#[case] template: &str) {
         assert_err!(Template::parse(template.into()), "at line 1");
     }
+
+    /// Invalid function calls give a specific error
+    #[rstest]
+    #[case::unknown_function("{{field | bogus}}", "unknown function")]
+    #[case::unexpected_argument(
+        "{{field | base64('a')}}",
+        "function takes no arguments"
+    )]
+    #[case::missing_argument("{{field | jsonpath}}", "takes 1 argument")]
+    #[case::invalid_query("{{field | jsonpath('$.')}}", "invalid JSONPath")]
+    #[case::invalid_encoding(
+        "{{field | hmac_sha256('key', 'hax')}}",
+        "encoding must be 'hex' or 'base64'"
+    )]
+    #[case::invalid_algorithm(
+        "{{field | jwt('ES256', 'key')}}",
+        "algorithm must be 'HS256' or 'RS256'"
+    )]
+    fn test_parse_function_error(
+        #[case] template: &str,
+        #[case] expected_error: &str,
+    ) {
+        assert_err!(Template::parse(template.into()), expected_error);
+    }
 }
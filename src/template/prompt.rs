@@ -1,7 +1,7 @@
 use crate::util::ResultExt;
 use anyhow::anyhow;
 use derive_more::From;
-use std::fmt::Debug;
+use std::{fmt::Debug, path::PathBuf};
 use tokio::sync::oneshot;
 
 /// A prompter is a bridge between the user and the template engine. It enables
@@ -19,6 +19,13 @@ pub trait Prompter: Debug + Send + Sync {
     /// If an error occurs while prompting the user, just drop the returner.
     /// The implementor is responsible for logging the error as appropriate.
     fn prompt(&self, prompt: Prompt);
+
+    /// Ask the user to pick a file, and use the given channel to return its
+    /// path. Same as [Self::prompt], drop the returner to indicate "no
+    /// response". By default, there's never a response.
+    fn prompt_file(&self, prompt: FilePrompt) {
+        drop(prompt);
+    }
 }
 
 /// Data defining a prompt which should be presented to the user
@@ -26,14 +33,32 @@ pub trait Prompter: Debug + Send + Sync {
 pub struct Prompt {
     /// Tell the user what we're asking for
     pub message: String,
-    /// Value used to pre-populate the text box
+    /// Value used to pre-populate the text box. If there are options, this is
+    /// the initially selected option
     pub default: Option<String>,
+    /// Fixed list of values to choose from. If empty, the user can enter any
+    /// text
+    pub options: Vec<String>,
+    /// Can the entered value contain newlines? Ignored if there are options
+    pub multiline: bool,
     /// Should the value the user is typing be masked? E.g. password input
     pub sensitive: bool,
     /// How the prompter will pass the answer back
     pub channel: PromptChannel<String>,
 }
 
+/// Data defining a file selection prompt
+#[derive(Debug)]
+pub struct FilePrompt {
+    /// Tell the user what we're asking for
+    pub message: String,
+    /// Directory to start in. Relative paths entered by the user are relative
+    /// to this
+    pub directory: PathBuf,
+    /// How the prompter will pass the selected path back
+    pub channel: PromptChannel<PathBuf>,
+}
+
 /// Channel used to return a prompt response. This is its own type so we can
 /// provide wrapping functionality while letting the user decompose the `Prompt`
 /// type.
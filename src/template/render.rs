@@ -3,25 +3,39 @@
 use crate::{
     collection::{
         ChainId, ChainOutputTrim, ChainRequestSection, ChainRequestTrigger,
-        ChainSource, RecipeId,
+        ChainSource, FilePickerOutput, RecipeId,
     },
-    http::{ContentType, Exchange, RequestSeed, ResponseRecord},
+    http::{
+        ContentType, Exchange, Query, QueryError, RequestSeed, ResponseRecord,
+        SelectorType,
+    },
+    secrets,
     template::{
-        error::TriggeredRequestError, parse::TemplateInputChunk, ChainError,
-        Prompt, Template, TemplateChunk, TemplateContext, TemplateError,
-        TemplateKey, RECURSION_LIMIT,
+        chain_log,
+        error::{FunctionError, TriggeredRequestError},
+        parse::TemplateInputChunk,
+        script,
+        trace::{self, TraceKind},
+        ChainError, Faker, FilePrompt, Function, FunctionArgument,
+        FunctionCall, Prompt, Span, Template, TemplateChunk, TemplateContext,
+        TemplateError, TemplateKey, RECURSION_LIMIT,
     },
     util::ResultExt,
 };
 use async_trait::async_trait;
 use chrono::Utc;
-use futures::future;
-use itertools::Itertools;
+use futures::{
+    future::{self, BoxFuture},
+    FutureExt,
+};
+use indexmap::IndexMap;
 use std::{
+    collections::HashMap,
     env,
     path::PathBuf,
     process::Stdio,
-    sync::{atomic::Ordering, Arc},
+    sync::{atomic::Ordering, Arc, Mutex},
+    time::{Duration, Instant},
 };
 use tokio::{fs, io::AsyncWriteExt, process::Command, sync::oneshot};
 use tracing::{debug, debug_span, instrument, trace};
@@ -44,6 +58,15 @@ impl Template {
         &self,
         context: &TemplateContext,
     ) -> Result<Vec<u8>, TemplateError> {
+        self.render_stitched(context).await.map(|chunk| chunk.value)
+    }
+
+    /// Render the template and stitch the chunks together. The output is
+    /// sensitive if any of its rendered chunks are
+    async fn render_stitched(
+        &self,
+        context: &TemplateContext,
+    ) -> TemplateResult {
         debug!(template = self.template, "Rendering template");
 
         if context.recursion_count.load(Ordering::Relaxed) >= RECURSION_LIMIT {
@@ -53,22 +76,26 @@ impl Template {
         // Render each individual template chunk in the string
         let chunks = self.render_chunks(context).await;
 
-        let unwrap_chunk = |chunk| match chunk {
-            TemplateChunk::Raw(span) => {
-                Ok(self.substring(span).as_bytes().to_owned())
-            }
-            TemplateChunk::Rendered { value, .. } => Ok(value),
-            TemplateChunk::Error(error) => Err(error),
-        };
-
         // Stitch the rendered chunks together into one string. It's possible
         // this is suboptimal for single-chunk templates, but until that's
         // a demonstrated bottleneck this is good enough.
-        chunks
-            .into_iter()
-            .map(unwrap_chunk)
-            .flatten_ok()
-            .try_collect()
+        let mut output = RenderedChunk {
+            value: Vec::new(),
+            sensitive: false,
+        };
+        for chunk in chunks {
+            match chunk {
+                TemplateChunk::Raw(span) => output
+                    .value
+                    .extend_from_slice(self.substring(span).as_bytes()),
+                TemplateChunk::Rendered { value, sensitive } => {
+                    output.value.extend(value);
+                    output.sensitive |= sensitive;
+                }
+                TemplateChunk::Error(error) => return Err(error),
+            }
+        }
+        Ok(output)
     }
 
     /// Render the template using values from the given context. If any chunk
@@ -92,57 +119,285 @@ impl Template {
         &self,
         context: &TemplateContext,
     ) -> Vec<TemplateChunk> {
-        // Map over each parsed chunk, and render the keys into strings. The
-        // raw text chunks will be mapped 1:1
-        let futures = self.chunks.iter().copied().map(|chunk| async move {
+        let render = self.render_input_chunks(&self.chunks, context);
+        // Templates without any keys aren't interesting enough to trace
+        if self
+            .chunks
+            .iter()
+            .all(|chunk| matches!(chunk, TemplateInputChunk::Raw(_)))
+        {
+            return render.await;
+        }
+        trace::record(
+            || TraceKind::Template(self.template.clone()),
+            render,
+            |chunks| self.trace_outcome(chunks),
+        )
+        .await
+    }
+
+    /// Render a list of parsed chunks. Raw text and keys are mapped 1:1.
+    /// Conditionals are replaced by the chunks of whichever branch is taken,
+    /// so the output never contains any conditional tags.
+    fn render_input_chunks<'a>(
+        &'a self,
+        chunks: &'a [TemplateInputChunk<Span>],
+        context: &'a TemplateContext,
+    ) -> BoxFuture<'a, Vec<TemplateChunk>> {
+        let futures = chunks.iter().map(|chunk| async move {
             match chunk {
-                TemplateInputChunk::Raw(span) => TemplateChunk::Raw(span),
-                TemplateInputChunk::Key(key) => {
+                TemplateInputChunk::Raw(span) => {
+                    vec![TemplateChunk::Raw(*span)]
+                }
+                TemplateInputChunk::Key { key, functions } => {
                     // Grab the string corresponding to the span
                     let key = key.map(|span| self.substring(span));
-
-                    // The formatted key should match the source that it was
-                    // parsed from, therefore we can use it to match the
-                    // override key
-                    let raw = key.to_string();
-                    // If the key is in the overrides, use the given value
-                    // without parsing it
-                    let result = match context.overrides.get(&raw) {
-                        Some(value) => {
-                            trace!(
-                                key = raw,
-                                value,
-                                "Rendered template key from override"
-                            );
-                            Ok(RenderedChunk {
-                                value: value.clone().into_bytes(),
-                                // The overriden value *could* be marked
-                                // sensitive, but we're taking a shortcut and
-                                // assuming it isn't
-                                sensitive: false,
-                            })
+                    let result = render_key(key, context).await;
+                    let chunk =
+                        self.apply_functions(result, functions, context).await;
+                    // Remember the final value, so it can be redacted from
+                    // the request record
+                    if let TemplateChunk::Rendered {
+                        value,
+                        sensitive: true,
+                    } = &chunk
+                    {
+                        context.add_sensitive_value(value);
+                    }
+                    vec![chunk]
+                }
+                TemplateInputChunk::Conditional {
+                    condition,
+                    then,
+                    otherwise,
+                } => {
+                    let condition = condition.map(|span| self.substring(span));
+                    match render_condition(condition, context).await {
+                        Ok(true) => {
+                            self.render_input_chunks(then, context).await
                         }
-                        None => {
-                            // Standard case - parse the key and render it
-                            let result =
-                                key.into_source().render(context).await;
-                            if let Ok(value) = &result {
-                                trace!(
-                                    key = raw,
-                                    ?value,
-                                    "Rendered template key"
-                                );
-                            }
-                            result
+                        Ok(false) => {
+                            self.render_input_chunks(otherwise, context).await
                         }
-                    };
-                    result.into()
+                        Err(error) => vec![TemplateChunk::Error(error)],
+                    }
                 }
             }
         });
 
         // Parallelization!
-        future::join_all(futures).await
+        future::join_all(futures)
+            .map(|chunks| chunks.into_iter().flatten().collect())
+            .boxed()
+    }
+
+    /// Stitch rendered chunks into a preview for a render trace. If any chunk
+    /// failed, use the first error instead
+    fn trace_outcome(
+        &self,
+        chunks: &[TemplateChunk],
+    ) -> Result<String, String> {
+        let mut value = String::new();
+        for chunk in chunks {
+            match chunk {
+                TemplateChunk::Raw(span) => {
+                    value.push_str(self.substring(*span))
+                }
+                TemplateChunk::Rendered {
+                    sensitive: true, ..
+                } => value.push_str("<sensitive>"),
+                TemplateChunk::Rendered { value: bytes, .. } => {
+                    value.push_str(&String::from_utf8_lossy(bytes))
+                }
+                TemplateChunk::Error(error) => {
+                    return Err(trace::format_error(error))
+                }
+            }
+        }
+        Ok(trace::format_value(value.as_bytes(), false))
+    }
+
+    /// Pipe a rendered key value through each function, in order. Key
+    /// arguments are rendered just before their function is called. If the key
+    /// has no value, skip to the first `default()` in the pipeline, which will
+    /// replace the missing value with its fallback.
+    async fn apply_functions(
+        &self,
+        result: TemplateResult,
+        mut functions: &[FunctionCall<Span>],
+        context: &TemplateContext,
+    ) -> TemplateChunk {
+        let chunk = match result {
+            Ok(chunk) => chunk,
+            Err(error) if error.is_missing() => {
+                let Some(index) = functions
+                    .iter()
+                    .position(|call| call.function == Function::Default)
+                else {
+                    return TemplateChunk::Error(error);
+                };
+                functions = &functions[index..];
+                RenderedChunk {
+                    value: Vec::new(),
+                    sensitive: false,
+                }
+            }
+            Err(error) => return TemplateChunk::Error(error),
+        };
+        let mut value = chunk.value;
+        let mut sensitive = chunk.sensitive;
+        for call in functions {
+            let function_error = |error: FunctionError| {
+                TemplateChunk::Error(TemplateError::Function {
+                    function: call.function,
+                    error: error.into(),
+                })
+            };
+            let mut arguments = Vec::with_capacity(call.arguments.len());
+            for argument in &call.arguments {
+                let argument = match argument {
+                    FunctionArgument::Literal(span) => {
+                        self.substring(*span).to_owned()
+                    }
+                    FunctionArgument::Key(key) => {
+                        let key = key.map(|span| self.substring(span));
+                        let rendered = match render_key(key, context).await {
+                            Ok(rendered) => rendered,
+                            Err(error) => {
+                                return function_error(FunctionError::Argument(
+                                    error.into(),
+                                ))
+                            }
+                        };
+                        // A sensitive argument can end up in the output, e.g.
+                        // via default()
+                        sensitive |= rendered.sensitive;
+                        match String::from_utf8(rendered.value) {
+                            Ok(argument) => argument,
+                            Err(error) => {
+                                return function_error(
+                                    FunctionError::InvalidUtf8(error),
+                                )
+                            }
+                        }
+                    }
+                };
+                arguments.push(argument);
+            }
+            trace!(function = %call.function, "Applying template function");
+            value = match call.function.apply(value, &arguments) {
+                Ok(value) => value,
+                Err(error) => return function_error(error),
+            };
+        }
+        TemplateChunk::Rendered { value, sensitive }
+    }
+}
+
+/// Render a single key. If the key is in the overrides, use the given value
+/// without parsing it.
+async fn render_key(
+    key: TemplateKey<&str>,
+    context: &TemplateContext,
+) -> TemplateResult {
+    // The formatted key should match the source that it was parsed from,
+    // therefore we can use it to match the override key
+    let raw = key.to_string();
+    trace::record(
+        || TraceKind::Key {
+            source: key_source(key, context),
+            key: raw.clone(),
+        },
+        render_key_inner(key, &raw, context),
+        |result| match result {
+            Ok(chunk) => Ok(trace::format_value(&chunk.value, chunk.sensitive)),
+            Err(error) => Err(trace::format_error(error)),
+        },
+    )
+    .await
+}
+
+async fn render_key_inner(
+    key: TemplateKey<&str>,
+    raw: &str,
+    context: &TemplateContext,
+) -> TemplateResult {
+    match context.overrides.get(raw) {
+        Some(value) => {
+            trace!(key = raw, value, "Rendered template key from override");
+            // Overriding a sensitive value doesn't make it any less sensitive
+            let sensitive = match key {
+                TemplateKey::Field(field) => context
+                    .selected_profile
+                    .as_ref()
+                    .and_then(|id| context.collection.profiles.get(id))
+                    .is_some_and(|profile| profile.is_sensitive(field)),
+                TemplateKey::Chain(chain_id) => context
+                    .collection
+                    .chains
+                    .get(&ChainId::<&str>::from(chain_id))
+                    .is_some_and(|chain| chain.sensitive),
+                TemplateKey::Environment(_) => false,
+                TemplateKey::Secret(_) => true,
+                TemplateKey::Fake(_) => false,
+            };
+            Ok(RenderedChunk {
+                value: value.clone().into_bytes(),
+                sensitive,
+            })
+        }
+        None => {
+            // Standard case - parse the key and render it
+            let result = key.into_source().render(context).await;
+            if let Ok(value) = &result {
+                trace!(key = raw, ?value, "Rendered template key");
+            }
+            result
+        }
+    }
+}
+
+/// Render the condition of a conditional block, and check if it's truthy.
+/// Anything other than an empty string or `false` is truthy. A key with no
+/// value (e.g. a field that isn't in the selected profile) is falsy, so
+/// profiles don't need to define every field used in a condition.
+async fn render_condition(
+    key: TemplateKey<&str>,
+    context: &TemplateContext,
+) -> Result<bool, TemplateError> {
+    match render_key(key, context).await {
+        Ok(chunk) => Ok(!chunk.value.is_empty() && chunk.value != b"false"),
+        Err(error) if error.is_missing() => Ok(false),
+        Err(error) => Err(error),
+    }
+}
+
+/// Describe where a key's value comes from, for a render trace
+fn key_source(key: TemplateKey<&str>, context: &TemplateContext) -> String {
+    if context.overrides.contains_key(&key.to_string()) {
+        return "override".into();
+    }
+    match key {
+        TemplateKey::Field(_) => match &context.selected_profile {
+            Some(profile_id) => format!("profile `{profile_id}`"),
+            None => "profile".into(),
+        },
+        TemplateKey::Chain(chain_id) => {
+            match context
+                .collection
+                .chains
+                .get(&ChainId::<&str>::from(chain_id))
+            {
+                Some(chain) => {
+                    let tag: &str = (&chain.source).into();
+                    format!("chain `!{tag}`")
+                }
+                None => "chain".into(),
+            }
+        }
+        TemplateKey::Environment(_) => "environment variable".into(),
+        TemplateKey::Secret(_) => "keyring secret".into(),
+        TemplateKey::Fake(_) => "fake data".into(),
     }
 }
 
@@ -169,6 +424,8 @@ impl<'a> TemplateKey<&'a str> {
             Self::Environment(variable) => {
                 Box::new(EnvironmentTemplateSource { variable })
             }
+            Self::Secret(name) => Box::new(SecretTemplateSource { name }),
+            Self::Fake(kind) => Box::new(FakeTemplateSource { kind }),
         }
     }
 }
@@ -220,15 +477,16 @@ impl<'a> TemplateSource<'a> for FieldTemplateSource<'a> {
         // recursion!
         trace!(%field, %template, "Rendering recursive template");
         context.recursion_count.fetch_add(1, Ordering::Relaxed);
-        let rendered = template.render(context).await.map_err(|error| {
-            TemplateError::FieldNested {
-                field: field.to_owned(),
-                error: Box::new(error),
-            }
-        })?;
+        let rendered =
+            template.render_stitched(context).await.map_err(|error| {
+                TemplateError::FieldNested {
+                    field: field.to_owned(),
+                    error: Box::new(error),
+                }
+            })?;
         Ok(RenderedChunk {
-            value: rendered,
-            sensitive: false,
+            value: rendered.value,
+            sensitive: rendered.sensitive || profile.is_sensitive(field),
         })
     }
 }
@@ -241,6 +499,9 @@ struct ChainTemplateSource<'a> {
 #[async_trait]
 impl<'a> TemplateSource<'a> for ChainTemplateSource<'a> {
     async fn render(&self, context: &'a TemplateContext) -> TemplateResult {
+        // Secret manager commands are recorded so the chain can be linked to
+        // its entry in the secret cache
+        let mut secret_command = None;
         // Any error in here is the chain error subtype
         let result: Result<_, ChainError> = async {
             // Resolve chained value
@@ -268,12 +529,41 @@ impl<'a> TemplateSource<'a> for ChainTemplateSource<'a> {
                     let content_type =
                         ContentType::from_response(&response).ok();
                     let value =
-                        self.extract_response_value(response, section)?;
+                        self.extract_response_value(response, section).await?;
                     (value, content_type)
                 }
                 ChainSource::File { path } => {
                     self.render_file(context, path).await?
                 }
+                ChainSource::FilePicker {
+                    message,
+                    directory,
+                    output,
+                } => {
+                    let path = self
+                        .render_file_picker(
+                            context,
+                            message.as_ref(),
+                            directory.as_ref(),
+                        )
+                        .await?;
+                    match output {
+                        FilePickerOutput::Contents => read_file(path).await?,
+                        FilePickerOutput::Path => (
+                            path.to_string_lossy().into_owned().into_bytes(),
+                            None,
+                        ),
+                    }
+                }
+                ChainSource::Script { script, inputs } => {
+                    // No way to guess content type on this
+                    (
+                        self.render_script(context, script, inputs)
+                            .await?
+                            .into_bytes(),
+                        None,
+                    )
+                }
                 ChainSource::Command { command, stdin } => {
                     // No way to guess content type on this
                     (
@@ -282,11 +572,18 @@ impl<'a> TemplateSource<'a> for ChainTemplateSource<'a> {
                         None,
                     )
                 }
-                ChainSource::Prompt { message, default } => (
+                ChainSource::Prompt {
+                    message,
+                    default,
+                    options,
+                    multiline,
+                } => (
                     self.render_prompt(
                         context,
                         message.as_ref(),
                         default.as_ref(),
+                        options,
+                        *multiline,
                         chain.sensitive,
                     )
                     .await?
@@ -294,22 +591,94 @@ impl<'a> TemplateSource<'a> for ChainTemplateSource<'a> {
                     // No way to guess content type on this
                     None,
                 ),
+                ChainSource::Vault {
+                    path,
+                    field,
+                    cache_ttl,
+                } => {
+                    let path =
+                        self.render_nested(context, "path", path).await?;
+                    let field =
+                        self.render_nested(context, "field", field).await?;
+                    let command = vec![
+                        "vault".into(),
+                        "kv".into(),
+                        "get".into(),
+                        format!("-field={field}"),
+                        path,
+                    ];
+                    secret_command = Some(command.clone());
+                    (fetch_secret(command, *cache_ttl).await?, None)
+                }
+                ChainSource::OnePassword {
+                    reference,
+                    cache_ttl,
+                } => {
+                    let reference = self
+                        .render_nested(context, "reference", reference)
+                        .await?;
+                    let command = vec!["op".into(), "read".into(), reference];
+                    secret_command = Some(command.clone());
+                    (fetch_secret(command, *cache_ttl).await?, None)
+                }
+                ChainSource::AwsSecret {
+                    secret_id,
+                    region,
+                    cache_ttl,
+                } => {
+                    let secret_id = self
+                        .render_nested(context, "secret_id", secret_id)
+                        .await?;
+                    let mut command = vec![
+                        "aws".into(),
+                        "secretsmanager".into(),
+                        "get-secret-value".into(),
+                        "--secret-id".into(),
+                        secret_id,
+                        "--query".into(),
+                        "SecretString".into(),
+                        "--output".into(),
+                        "text".into(),
+                    ];
+                    if let Some(region) = region {
+                        let region = self
+                            .render_nested(context, "region", region)
+                            .await?;
+                        command.extend(["--region".into(), region]);
+                    }
+                    secret_command = Some(command.clone());
+                    (fetch_secret(command, *cache_ttl).await?, None)
+                }
             };
             // If the user provided a content type, prefer that over the
             // detected one
             let content_type = chain.content_type.or(content_type);
 
             // If a selector path is present, filter down the value
-            let value = if let Some(selector) = &chain.selector {
-                let content_type =
-                    content_type.ok_or(ChainError::UnknownContentType)?;
-                // Parse according to detected content type
-                let value = content_type
-                    .parse_content(&value)
-                    .map_err(|err| ChainError::ParseResponse { error: err })?;
-                selector.query_to_string(&*value)?.into_bytes()
-            } else {
-                value
+            let value = match (&chain.selector, chain.selector_type) {
+                (None, _) => value,
+                (Some(selector), SelectorType::Jsonpath) => {
+                    let query = selector.parse::<Query>().map_err(|error| {
+                        QueryError::InvalidSelector {
+                            selector_type: SelectorType::Jsonpath,
+                            selector: selector.clone(),
+                            error: error.into(),
+                        }
+                    })?;
+                    let content_type =
+                        content_type.ok_or(ChainError::UnknownContentType)?;
+                    // Parse according to detected content type
+                    let value =
+                        content_type.parse_content(&value).map_err(|err| {
+                            ChainError::ParseResponse { error: err }
+                        })?;
+                    query.query_to_string(&*value)?.into_bytes()
+                }
+                // Other selectors parse the content themselves, so the
+                // content type doesn't matter
+                (Some(selector), selector_type) => {
+                    selector_type.query_text(selector, &value)?.into_bytes()
+                }
             };
 
             Ok(RenderedChunk {
@@ -319,6 +688,19 @@ impl<'a> TemplateSource<'a> for ChainTemplateSource<'a> {
         }
         .await;
 
+        match &result {
+            // Unknown chains have nothing to inspect
+            Err(ChainError::ChainUnknown(_)) => {}
+            _ => chain_log::record(
+                (&self.chain_id).into(),
+                context.selected_profile.clone(),
+                result
+                    .as_ref()
+                    .map(|chunk| (chunk.value.as_slice(), chunk.sensitive)),
+                secret_command,
+            ),
+        }
+
         // Wrap the chain error into a TemplateError
         result.map_err(|error| TemplateError::Chain {
             chain_id: (&self.chain_id).into(),
@@ -429,7 +811,7 @@ impl<'a> ChainTemplateSource<'a> {
 
     /// Extract the specified component bytes from the response.
     /// Returns an error with the missing header if not found.
-    fn extract_response_value(
+    async fn extract_response_value(
         &self,
         response: ResponseRecord,
         component: &ChainRequestSection,
@@ -437,17 +819,42 @@ impl<'a> ChainTemplateSource<'a> {
         Ok(match component {
             // This will clone the bytes, which is necessary for the subsequent
             // string conversion anyway
-            ChainRequestSection::Body => response.body.into_bytes().into(),
+            ChainRequestSection::Body => match response.body.spool_path() {
+                // Large bodies are only previewed in memory. We need the
+                // whole value, so load it from disk
+                Some(path) => {
+                    fs::read(path).await.map_err(|error| ChainError::File {
+                        path: path.to_owned(),
+                        error,
+                    })?
+                }
+                // The full body is gone, and using just the preview would
+                // silently give the wrong value
+                None if response.body.is_truncated() => {
+                    return Err(ChainError::TruncatedBody {
+                        size: response.body.size(),
+                    });
+                }
+                None => response.body.into_bytes().into(),
+            },
             ChainRequestSection::Header(target_header) => {
-                response
-                    .headers
-                    // If header has multiple values, only grab the first
-                    .get(target_header)
-                    .ok_or_else(|| ChainError::MissingHeader {
+                // If the header has multiple values, combine them into one
+                // list, the same as if they'd been sent in a single header.
+                // This is common for `Link`, which is used for pagination
+                let mut values =
+                    response.headers.get_all(target_header).into_iter();
+                let first =
+                    values.next().ok_or_else(|| ChainError::MissingHeader {
                         header: target_header.clone(),
-                    })?
-                    .as_bytes()
-                    .to_vec()
+                    })?;
+                values.fold(first.as_bytes().to_vec(), |mut acc, value| {
+                    acc.extend_from_slice(b", ");
+                    acc.extend_from_slice(value.as_bytes());
+                    acc
+                })
+            }
+            ChainRequestSection::Status => {
+                response.status.as_u16().to_string().into_bytes()
             }
         })
     }
@@ -467,12 +874,46 @@ impl<'a> ChainTemplateSource<'a> {
                 error: error.into(),
             })?
             .into();
-        // Guess content type based on file extension
-        let content_type = ContentType::from_path(&path).ok();
-        let content = fs::read(&path)
-            .await
-            .map_err(|error| ChainError::File { path, error })?;
-        Ok((content, content_type))
+        read_file(path).await
+    }
+
+    /// Ask the user to pick a file, and return its path
+    async fn render_file_picker(
+        &self,
+        context: &'a TemplateContext,
+        message: Option<&Template>,
+        directory: Option<&Template>,
+    ) -> Result<PathBuf, ChainError> {
+        let (tx, rx) = oneshot::channel();
+        let message = if let Some(template) = message {
+            template.render_string(context).await.map_err(|error| {
+                ChainError::Nested {
+                    field: "message".into(),
+                    error: error.into(),
+                }
+            })?
+        } else {
+            self.chain_id.to_string()
+        };
+        let directory = if let Some(template) = directory {
+            template
+                .render_string(context)
+                .await
+                .map_err(|error| ChainError::Nested {
+                    field: "directory".into(),
+                    error: error.into(),
+                })?
+                .into()
+        } else {
+            PathBuf::from(".")
+        };
+
+        context.prompter.prompt_file(FilePrompt {
+            message,
+            directory,
+            channel: tx.into(),
+        });
+        rx.await.map_err(|_| ChainError::PromptNoResponse)
     }
 
     /// Render a chained value from an external command
@@ -563,12 +1004,53 @@ impl<'a> ChainTemplateSource<'a> {
         Ok(output.stdout)
     }
 
+    /// Render a chained value by executing a script, with its rendered inputs
+    async fn render_script(
+        &self,
+        context: &TemplateContext,
+        script: &str,
+        inputs: &IndexMap<String, Template>,
+    ) -> Result<String, ChainError> {
+        let inputs = future::try_join_all(inputs.iter().map(
+            |(name, template)| async move {
+                let field = format!("inputs.{name}");
+                let value =
+                    self.render_nested(context, &field, template).await?;
+                Ok::<_, ChainError>((name.clone(), value))
+            },
+        ))
+        .await?
+        .into_iter()
+        .collect();
+        script::run_script(script.to_owned(), inputs)
+            .await
+            .map_err(ChainError::Script)
+            .traced()
+    }
+
+    /// Render a template field of the chain source to a string
+    async fn render_nested(
+        &self,
+        context: &TemplateContext,
+        field: &str,
+        template: &Template,
+    ) -> Result<String, ChainError> {
+        template.render_string(context).await.map_err(|error| {
+            ChainError::Nested {
+                field: field.to_owned(),
+                error: error.into(),
+            }
+        })
+    }
+
     /// Render a value by asking the user to provide it
     async fn render_prompt(
         &self,
         context: &'a TemplateContext,
         message: Option<&Template>,
         default: Option<&Template>,
+        options: &[Template],
+        multiline: bool,
         sensitive: bool,
     ) -> Result<String, ChainError> {
         // Use the prompter to ask the user a question, and wait for a response
@@ -594,10 +1076,20 @@ impl<'a> ChainTemplateSource<'a> {
         } else {
             None
         };
+        let options = future::try_join_all(
+            options.iter().map(|option| option.render_string(context)),
+        )
+        .await
+        .map_err(|error| ChainError::Nested {
+            field: "options".into(),
+            error: error.into(),
+        })?;
 
         context.prompter.prompt(Prompt {
             message,
             default,
+            options,
+            multiline,
             sensitive,
             channel: tx.into(),
         });
@@ -605,6 +1097,17 @@ impl<'a> ChainTemplateSource<'a> {
     }
 }
 
+/// Read a file, and guess its content type from its extension
+async fn read_file(
+    path: PathBuf,
+) -> Result<(Vec<u8>, Option<ContentType>), ChainError> {
+    let content_type = ContentType::from_path(&path).ok();
+    let content = fs::read(&path)
+        .await
+        .map_err(|error| ChainError::File { path, error })?;
+    Ok((content, content_type))
+}
+
 /// A value sourced from the process's environment
 struct EnvironmentTemplateSource<'a> {
     pub variable: &'a str,
@@ -626,6 +1129,136 @@ impl<'a> TemplateSource<'a> for EnvironmentTemplateSource<'a> {
     }
 }
 
+/// Randomly generated test data
+struct FakeTemplateSource<'a> {
+    pub kind: &'a str,
+}
+
+#[async_trait]
+impl<'a> TemplateSource<'a> for FakeTemplateSource<'a> {
+    async fn render(&self, _: &'a TemplateContext) -> TemplateResult {
+        let faker: Faker =
+            self.kind.parse().map_err(|_| TemplateError::FakeUnknown {
+                kind: self.kind.to_owned(),
+            })?;
+        Ok(RenderedChunk {
+            value: faker.generate().into_bytes(),
+            sensitive: false,
+        })
+    }
+}
+
+/// Values fetched from external secret managers, keyed by the full command
+/// used to fetch them. This is global so values are reused across requests.
+static SECRET_CACHE: Mutex<Option<SecretCache>> = Mutex::new(None);
+
+/// Cached secret values and when they were fetched, keyed by command
+type SecretCache = HashMap<Vec<String>, (Instant, Vec<u8>)>;
+
+/// Fetch a value from an external secret manager by executing its CLI. If a
+/// TTL is given, the value is cached and reused until it expires. Unlike
+/// generic commands, a non-zero exit status is an error, and the trailing
+/// newline that every CLI prints is stripped.
+pub(super) async fn fetch_secret(
+    command: Vec<String>,
+    cache_ttl: Option<Duration>,
+) -> Result<Vec<u8>, ChainError> {
+    if let Some(cache_ttl) = cache_ttl {
+        let cache = SECRET_CACHE.lock().expect("Secret cache lock poisoned");
+        if let Some((fetched_at, value)) =
+            cache.as_ref().and_then(|cache| cache.get(&command))
+        {
+            if fetched_at.elapsed() < cache_ttl {
+                trace!(?command, "Using cached secret");
+                return Ok(value.clone());
+            }
+        }
+    }
+
+    let [program, args @ ..] = command.as_slice() else {
+        return Err(ChainError::CommandMissing);
+    };
+    let _ = debug_span!("Fetching secret", ?command).entered();
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|error| ChainError::Command {
+            command: command.clone(),
+            error,
+        })
+        .traced()?;
+    if !output.status.success() {
+        return Err(ChainError::SecretManager {
+            program: program.clone(),
+            status: output.status,
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        })
+        .traced();
+    }
+
+    let mut value = output.stdout;
+    if value.ends_with(b"\n") {
+        value.pop();
+        if value.ends_with(b"\r") {
+            value.pop();
+        }
+    }
+
+    if cache_ttl.is_some() {
+        SECRET_CACHE
+            .lock()
+            .expect("Secret cache lock poisoned")
+            .get_or_insert_with(HashMap::new)
+            .insert(command, (Instant::now(), value.clone()));
+    }
+    Ok(value)
+}
+
+/// Get when a secret was fetched, if it's in the cache. Expired values are
+/// included
+pub(super) fn secret_fetched_at(command: &[String]) -> Option<Instant> {
+    SECRET_CACHE
+        .lock()
+        .expect("Secret cache lock poisoned")
+        .as_ref()
+        .and_then(|cache| cache.get(command))
+        .map(|(fetched_at, _)| *fetched_at)
+}
+
+/// Drop a secret from the cache, so the next fetch runs the command again
+pub(super) fn clear_secret(command: &[String]) {
+    if let Some(cache) = SECRET_CACHE
+        .lock()
+        .expect("Secret cache lock poisoned")
+        .as_mut()
+    {
+        cache.remove(command);
+    }
+}
+
+/// A value from the OS keyring
+struct SecretTemplateSource<'a> {
+    pub name: &'a str,
+}
+
+#[async_trait]
+impl<'a> TemplateSource<'a> for SecretTemplateSource<'a> {
+    async fn render(&self, _: &'a TemplateContext) -> TemplateResult {
+        let value = secrets::get(self.name).await.map_err(|error| {
+            TemplateError::Secret {
+                name: self.name.to_owned(),
+                error,
+            }
+        })?;
+        Ok(RenderedChunk {
+            value: value.into_bytes(),
+            sensitive: true,
+        })
+    }
+}
+
 impl ChainOutputTrim {
     /// Apply whitespace trimming to string values. If the value is not a valid
     /// string, no trimming is applied
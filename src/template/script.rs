@@ -0,0 +1,148 @@
+//! Execution of user scripts for `!script` chains. Scripts are written in
+//! [Rhai](https://rhai.rs), a small embedded language with Rust-like syntax.
+
+use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
+use rhai::{Dynamic, Engine, EvalAltResult, Map, Scope};
+use std::fmt::Write;
+use tokio::task;
+use tracing::debug_span;
+
+/// Maximum number of operations a script can perform before it's killed. This
+/// prevents an infinite loop from hanging the render forever. It's high enough
+/// that any reasonable script won't come anywhere near it.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Execute a script, with each input available as a string variable. The
+/// script runs on a blocking thread, because Rhai is synchronous. The value of
+/// the last expression is the output:
+/// - Strings are used as-is
+/// - Maps and arrays are serialized as JSON
+/// - `()` is an empty string
+/// - Anything else is converted with `to_string()`
+pub(super) async fn run_script(
+    script: String,
+    inputs: IndexMap<String, String>,
+) -> Result<String, Box<EvalAltResult>> {
+    task::spawn_blocking(move || {
+        let _ = debug_span!("Executing script").entered();
+        let engine = engine();
+        let mut scope = Scope::new();
+        for (name, value) in inputs {
+            scope.push(name, value);
+        }
+        let output: Dynamic = engine.eval_with_scope(&mut scope, &script)?;
+        to_output(output)
+    })
+    .await
+    // The task only fails if the script panicked, which Rhai shouldn't do
+    .map_err(|error| {
+        Box::new(EvalAltResult::ErrorSystem(
+            "Script task failed".into(),
+            error.into(),
+        ))
+    })?
+}
+
+/// Build a Rhai engine, with our extra functions registered
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    // Scripts can't do anything with print output, so send it to the log
+    engine.on_print(|message| tracing::info!(message, "Script output"));
+    engine.on_debug(|message, _, _| tracing::debug!(message, "Script output"));
+    // Current time, as a Unix timestamp in seconds
+    engine.register_fn("now", || Utc::now().timestamp());
+    // Format a Unix timestamp (in seconds) with a strftime format, in UTC
+    engine.register_fn(
+        "format_timestamp",
+        |timestamp: i64, format: &str| -> Result<String, Box<EvalAltResult>> {
+            let time = DateTime::from_timestamp(timestamp, 0)
+                .ok_or_else(|| format!("Invalid timestamp: {timestamp}"))?;
+            // to_string() would panic on an invalid format
+            let mut output = String::new();
+            write!(output, "{}", time.format(format))
+                .map_err(|_| format!("Invalid format: {format}"))?;
+            Ok(output)
+        },
+    );
+    engine
+}
+
+/// Convert a script's output value to a string
+fn to_output(output: Dynamic) -> Result<String, Box<EvalAltResult>> {
+    if output.is_unit() {
+        Ok(String::new())
+    } else if output.is_string() {
+        Ok(output.into_string()?)
+    } else if output.is_map() || output.is_array() {
+        // Rhai only serializes maps to JSON natively, so wrap the value
+        let mut map = Map::new();
+        map.insert("value".into(), output);
+        let json = rhai::format_map_as_json(&map);
+        let json = json
+            .strip_prefix(r#"{"value":"#)
+            .and_then(|json| json.strip_suffix('}'))
+            .expect("JSON object has known prefix and suffix");
+        Ok(json.to_owned())
+    } else {
+        Ok(output.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::assert_err;
+    use indexmap::indexmap;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::string(r#""hello""#, "hello")]
+    #[case::int("40 + 2", "42")]
+    #[case::unit("let x = 3;", "")]
+    #[case::map(r#"#{a: 1, b: "two"}"#, r#"{"a":1,"b":"two"}"#)]
+    #[case::array("[1, [2], #{c: ()}]", r#"[1,[2],{"c":null}]"#)]
+    #[case::input(r#"name + "!""#, "fish!")]
+    #[case::json(
+        r#"
+        let users = parse_json(json).users;
+        let ids = [];
+        for user in users { ids.push(user.id); }
+        ids
+        "#,
+        "[1,2]"
+    )]
+    #[case::format_timestamp(
+        r#"format_timestamp(86400, "%Y-%m-%d")"#,
+        "1970-01-02"
+    )]
+    #[tokio::test]
+    async fn test_run_script(#[case] script: &str, #[case] expected: &str) {
+        let inputs = indexmap! {
+            "name".into() => "fish".into(),
+            "json".into() => r#"{"users": [{"id": 1}, {"id": 2}]}"#.into(),
+        };
+        assert_eq!(run_script(script.into(), inputs).await.unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::syntax("let x = ", "Script is incomplete")]
+    #[case::undefined("y", "Variable not found: y")]
+    #[case::infinite_loop("loop {}", "Too many operations")]
+    #[case::throw(r#"throw "bad";"#, "bad")]
+    #[case::invalid_format(
+        r#"format_timestamp(0, "%Q")"#,
+        "Invalid format: %Q"
+    )]
+    #[tokio::test]
+    async fn test_run_script_error(
+        #[case] script: &str,
+        #[case] expected_error: &str,
+    ) {
+        assert_err!(
+            run_script(script.into(), IndexMap::new()).await,
+            expected_error
+        );
+    }
+}
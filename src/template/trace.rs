@@ -0,0 +1,395 @@
+//! Tracing of template renders, to explain where each piece of a rendered
+//! value came from. Tracing is opt-in: wrap a render in [trace] and every
+//! template and key rendered within it is recorded in a tree, along with its
+//! timing and outcome.
+
+use crate::util::format_duration;
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    future::Future,
+    iter, mem,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+tokio::task_local! {
+    /// Nodes recorded at the current level of the tree. Each traced render
+    /// swaps in a fresh list to collect its own children
+    static NODES: Nodes;
+}
+
+/// Recorded nodes for one level of the tree. Each slot is reserved before its
+/// render starts, so nodes stay in template order even though keys are
+/// rendered concurrently. A slot is only empty if its render was cancelled.
+type Nodes = Arc<Mutex<Vec<Option<TraceNode>>>>;
+
+/// Maximum number of characters of a rendered value to include in the trace.
+/// We just want enough to identify the value, not dump entire bodies.
+const MAX_VALUE_LENGTH: usize = 50;
+
+/// Placeholder for values that shouldn't be shown
+const SENSITIVE_VALUE: &str = "<sensitive>";
+
+/// The resolution tree of every template rendered within a [trace] call. Each
+/// root node is one top-level template.
+#[derive(Debug, Default)]
+pub struct Trace(pub Vec<TraceNode>);
+
+/// One template or key in a render tree
+#[derive(Debug)]
+pub struct TraceNode {
+    pub kind: TraceKind,
+    /// Total time spent rendering, *including* children
+    pub duration: Duration,
+    /// Preview of the rendered value (truncated, with sensitive values
+    /// masked), or the full error message
+    pub outcome: Result<String, String>,
+    /// Templates and keys rendered to produce this value
+    pub children: Vec<TraceNode>,
+}
+
+/// What was rendered for a [TraceNode]
+#[derive(Debug, PartialEq)]
+pub enum TraceKind {
+    /// A template containing at least one key. Raw templates aren't traced
+    Template(String),
+    /// A single key within a template
+    Key {
+        key: String,
+        /// Where the value came from, e.g. a profile or chain
+        source: String,
+    },
+}
+
+/// Run a future, recording every template rendered within it
+pub async fn trace<T>(future: impl Future<Output = T>) -> (T, Trace) {
+    let nodes = Nodes::default();
+    let output = NODES.scope(Arc::clone(&nodes), future).await;
+    (output, Trace(take_nodes(&nodes)))
+}
+
+/// Record a render as a node in the current trace. Anything rendered within
+/// the future is recorded as a child of this node. If there's no active trace,
+/// the future is just executed.
+pub(super) async fn record<T>(
+    kind: impl FnOnce() -> TraceKind,
+    future: impl Future<Output = T>,
+    outcome: impl FnOnce(&T) -> Result<String, String>,
+) -> T {
+    let Ok(parent) = NODES.try_with(Arc::clone) else {
+        return future.await;
+    };
+    let index = {
+        let mut parent = parent.lock().expect("Trace lock poisoned");
+        parent.push(None);
+        parent.len() - 1
+    };
+
+    let children = Nodes::default();
+    let start = Instant::now();
+    let output = NODES.scope(Arc::clone(&children), future).await;
+    let node = TraceNode {
+        kind: kind(),
+        duration: start.elapsed(),
+        outcome: outcome(&output),
+        children: take_nodes(&children),
+    };
+    parent.lock().expect("Trace lock poisoned")[index] = Some(node);
+    output
+}
+
+fn take_nodes(nodes: &Nodes) -> Vec<TraceNode> {
+    mem::take(&mut *nodes.lock().expect("Trace lock poisoned"))
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Format a rendered value for a trace. The value is truncated and quoted, so
+/// whitespace and control characters are visible
+pub(super) fn format_value(value: &[u8], sensitive: bool) -> String {
+    if sensitive {
+        return SENSITIVE_VALUE.into();
+    }
+    let value = String::from_utf8_lossy(value);
+    let mut truncated: String = value.chars().take(MAX_VALUE_LENGTH).collect();
+    if truncated.len() < value.len() {
+        truncated.push('…');
+    }
+    format!("{truncated:?}")
+}
+
+/// Format an error for a trace, including its entire chain of sources
+pub(super) fn format_error(error: &(dyn Error + 'static)) -> String {
+    iter::successors(Some(error), |&error| error.source())
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(": ")
+}
+
+impl Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_children(
+            f: &mut fmt::Formatter<'_>,
+            nodes: &[TraceNode],
+            prefix: &str,
+        ) -> fmt::Result {
+            for (i, node) in nodes.iter().enumerate() {
+                let (branch, indent) = if i == nodes.len() - 1 {
+                    ("└─ ", "   ")
+                } else {
+                    ("├─ ", "│  ")
+                };
+                writeln!(f, "{prefix}{branch}{node}")?;
+                write_children(
+                    f,
+                    &node.children,
+                    &format!("{prefix}{indent}"),
+                )?;
+            }
+            Ok(())
+        }
+
+        if self.0.is_empty() {
+            return writeln!(f, "No templates rendered");
+        }
+        for node in &self.0 {
+            writeln!(f, "{node}")?;
+            write_children(f, &node.children, "")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for TraceNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let duration = chrono::Duration::from_std(self.duration)
+            .unwrap_or(chrono::Duration::max_value());
+        match &self.outcome {
+            Ok(value) => write!(f, "{} => {value}", self.kind)?,
+            Err(error) => write!(f, "{} => Error: {error}", self.kind)?,
+        }
+        write!(f, " ({})", format_duration(&duration))
+    }
+}
+
+impl Display for TraceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Template(template) => write!(f, "{template:?}"),
+            Self::Key { key, source } => {
+                write!(f, "{{{{{key}}}}} from {source}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collection::{Chain, ChainSource, Collection, Profile},
+        template::{Template, TemplateContext},
+        test_util::Factory,
+    };
+    use indexmap::indexmap;
+    use pretty_assertions::assert_eq;
+
+    /// Simplified version of a node, without durations
+    #[derive(Debug, PartialEq)]
+    struct Node {
+        kind: TraceKind,
+        outcome: Result<String, String>,
+        children: Vec<Node>,
+    }
+
+    impl From<TraceNode> for Node {
+        fn from(node: TraceNode) -> Self {
+            Self {
+                kind: node.kind,
+                outcome: node.outcome,
+                children: node.children.into_iter().map(Node::from).collect(),
+            }
+        }
+    }
+
+    fn template(template: &str, value: &str, children: Vec<Node>) -> Node {
+        Node {
+            kind: TraceKind::Template(template.into()),
+            outcome: Ok(value.into()),
+            children,
+        }
+    }
+
+    fn key(
+        key: &str,
+        source: &str,
+        outcome: Result<&str, &str>,
+        children: Vec<Node>,
+    ) -> Node {
+        Node {
+            kind: TraceKind::Key {
+                key: key.into(),
+                source: source.into(),
+            },
+            outcome: outcome.map(String::from).map_err(String::from),
+            children,
+        }
+    }
+
+    /// Trace a render with nested profile fields, a chain, an override, and
+    /// an error
+    #[tokio::test]
+    async fn test_trace() {
+        let profile = Profile {
+            id: "local".into(),
+            data: indexmap! {
+                "host".into() => "{{protocol}}://localhost".into(),
+                "protocol".into() => "http".into(),
+            },
+            ..Profile::factory(())
+        };
+        let chain = Chain {
+            id: "token".into(),
+            source: ChainSource::command(["echo", "-n", "abc"]),
+            sensitive: true,
+            ..Chain::factory(())
+        };
+        let context = TemplateContext {
+            collection: Collection {
+                profiles: indexmap! {profile.id.clone() => profile},
+                chains: indexmap! {chain.id.clone() => chain},
+                ..Collection::factory(())
+            },
+            selected_profile: Some("local".into()),
+            overrides: indexmap! {"user".into() => "fish".into()},
+            ..TemplateContext::factory(())
+        };
+
+        let url: Template = "{{host}}/{{user}}?t={{chains.token}}".into();
+        let bad: Template = "{{unknown}}".into();
+        let raw: Template = "raw".into();
+        let ((url_result, bad_result, raw_result), trace) = trace(async {
+            (
+                url.render_string(&context).await,
+                bad.render_string(&context).await,
+                raw.render_string(&context).await,
+            )
+        })
+        .await;
+        assert_eq!(url_result.unwrap(), "http://localhost/fish?t=abc");
+        assert!(bad_result.is_err());
+        assert_eq!(raw_result.unwrap(), "raw");
+
+        let nodes: Vec<Node> = trace.0.into_iter().map(Node::from).collect();
+        assert_eq!(
+            nodes,
+            vec![
+                template(
+                    "{{host}}/{{user}}?t={{chains.token}}",
+                    r#""http://localhost/fish?t=<sensitive>""#,
+                    vec![
+                        key(
+                            "host",
+                            "profile `local`",
+                            Ok(r#""http://localhost""#),
+                            vec![template(
+                                "{{protocol}}://localhost",
+                                r#""http://localhost""#,
+                                vec![key(
+                                    "protocol",
+                                    "profile `local`",
+                                    Ok(r#""http""#),
+                                    vec![]
+                                )]
+                            )]
+                        ),
+                        key("user", "override", Ok(r#""fish""#), vec![]),
+                        key(
+                            "chains.token",
+                            "chain `!command`",
+                            Ok("<sensitive>"),
+                            vec![]
+                        ),
+                    ]
+                ),
+                Node {
+                    kind: TraceKind::Template("{{unknown}}".into()),
+                    outcome: Err("Unknown field `unknown`".into()),
+                    children: vec![key(
+                        "unknown",
+                        "profile `local`",
+                        Err("Unknown field `unknown`"),
+                        vec![]
+                    )],
+                },
+            ]
+        );
+    }
+
+    /// Outside of a trace, recording just executes the future
+    #[tokio::test]
+    async fn test_no_trace() {
+        let output = record(
+            || TraceKind::Template("".into()),
+            async { 3 },
+            |_| Ok("".into()),
+        )
+        .await;
+        assert_eq!(output, 3);
+
+        let ((), trace) = trace(async {}).await;
+        assert!(trace.0.is_empty());
+        assert_eq!(trace.to_string(), "No templates rendered\n");
+    }
+
+    #[test]
+    fn test_format_value() {
+        assert_eq!(format_value(b"a\nb", false), r#""a\nb""#);
+        assert_eq!(format_value(b"secret", true), "<sensitive>");
+        let long = "a".repeat(60);
+        assert_eq!(
+            format_value(long.as_bytes(), false),
+            format!("{:?}", format!("{}…", "a".repeat(50)))
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let node = |kind, outcome, children| TraceNode {
+            kind,
+            duration: Duration::from_millis(2),
+            outcome,
+            children,
+        };
+        let trace = Trace(vec![node(
+            TraceKind::Template("{{a}}{{b}}".into()),
+            Ok(r#""xy""#.into()),
+            vec![
+                node(
+                    TraceKind::Key {
+                        key: "a".into(),
+                        source: "override".into(),
+                    },
+                    Ok(r#""x""#.into()),
+                    vec![],
+                ),
+                node(
+                    TraceKind::Key {
+                        key: "b".into(),
+                        source: "environment variable".into(),
+                    },
+                    Err("Unknown".into()),
+                    vec![],
+                ),
+            ],
+        )]);
+        assert_eq!(
+            trace.to_string(),
+            "\"{{a}}{{b}}\" => \"xy\" (2ms)\n\
+            ├─ {{a}} from override => \"x\" (2ms)\n\
+            └─ {{b}} from environment variable => Error: Unknown (2ms)\n"
+        );
+    }
+}
@@ -2,7 +2,7 @@
 
 use crate::{
     collection::{ProfileId, Recipe, RecipeId, RecipeNode, RecipeTree},
-    template::{Prompt, Prompter, Template},
+    template::{FilePrompt, Prompt, Prompter, Template},
     util::ResultExt,
 };
 use anyhow::Context;
@@ -89,6 +89,13 @@ impl Prompter for TestPrompter {
             prompt.channel.respond(default);
         }
     }
+
+    /// Respond with the static value as a path, if given
+    fn prompt_file(&self, prompt: FilePrompt) {
+        if let Some(value) = self.value.as_ref() {
+            prompt.channel.respond(prompt.directory.join(value));
+        }
+    }
 }
 
 // Some helpful conversion implementations
@@ -7,41 +7,76 @@ mod util;
 pub mod view;
 
 use crate::{
-    collection::{Collection, CollectionFile, ProfileId, Recipe, RecipeId},
+    collection::{
+        ChainId, Collection, CollectionFile, ProfileId, ProfileOverrides,
+        Recipe, RecipeBody, RecipeId, RecipeKind, RecipeNode,
+    },
     config::Config,
     db::{CollectionDatabase, Database},
-    http::RequestSeed,
-    template::{Prompter, Template, TemplateChunk, TemplateContext},
+    export::Har,
+    http::{
+        check_assertions, BuildOptions, ContentType, Exchange, GraphQlSchema,
+        RequestBuildError, RequestError, RequestId, RequestSeed, RunResult,
+        INTROSPECTION_QUERY,
+    },
+    template::{self, Prompter, Template, TemplateChunk, TemplateContext},
     tui::{
         context::TuiContext,
         input::Action,
-        message::{Message, MessageSender, RequestConfig},
-        util::{save_file, signals},
-        view::{ModalPriority, PreviewPrompter, RequestState, View},
+        message::{
+            Message, MessageSender, RequestConfig, RequestPreviewResult,
+            ScheduleId, ScheduledRequest,
+        },
+        util::{
+            compose_scratch_request, create_folder, create_recipe,
+            delete_recipe, editor_command, find_replace, mark_golden,
+            move_recipe, paste_curl_request, rename_recipe, save_file,
+            save_response_body, save_scratch_request, schedule_request,
+            signals, SCRATCH_RECIPE_ID,
+        },
+        view::{
+            ModalPriority, PrettyBody, PreviewPrompter, RequestState, View,
+        },
     },
-    util::{Replaceable, ResultExt},
+    util::{format_duration, Replaceable, ResultExt},
+    ws::WebSocketConnection,
 };
-use anyhow::{anyhow, Context};
-use chrono::Utc;
+use anyhow::{anyhow, bail, Context};
+use chrono::{DateTime, Local, Utc};
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
     terminal::{EnterAlternateScreen, LeaveAlternateScreen},
 };
 use futures::Future;
-use notify::{event::ModifyKind, RecursiveMode, Watcher};
+use itertools::Itertools;
+use notify::{
+    event::ModifyKind, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+};
 use ratatui::{prelude::CrosstermBackend, Terminal};
+use reqwest::header;
 use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    env, fs,
     io::{self, Stdout},
+    mem,
     ops::Deref,
-    path::PathBuf,
-    sync::{Arc, OnceLock},
-    time::Duration,
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 use tokio::{
     sync::mpsc::{self, UnboundedReceiver},
+    task::{self, JoinHandle},
     time,
 };
-use tracing::{debug, error, info, trace};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, trace, warn};
+use uuid::Uuid;
 
 /// Main controller struct for the TUI. The app uses a React-ish architecture
 /// for the view, with a wrapping controller (this struct)
@@ -62,14 +97,87 @@ pub struct Tui {
     /// before the new one is created.
     view: Replaceable<View>,
     collection_file: CollectionFile,
+    /// Watches all of the collection's files for changes. This stops when
+    /// dropped, and is replaced whenever the set of files changes
+    watcher: Option<RecommendedWatcher>,
+    /// Temporary profile field overrides, set by the user. These are applied
+    /// on top of the collection, and persist across collection reloads
+    profile_overrides: ProfileOverrides,
+    /// Ad hoc request built in the TUI. This isn't in the collection file,
+    /// but is shown at the top of the recipe list until it's saved
+    scratch_recipe: Option<Recipe>,
+    /// WebSocket connections, one per profile+recipe. These are held here
+    /// rather than in the view so they stay open when the view is rebuilt
+    websockets: HashMap<(Option<ProfileId>, RecipeId), WebSocketConnection>,
+    /// Cancellation handles for HTTP requests that are being built or sent.
+    /// Entries are removed when the request completes
+    active_requests: HashMap<RequestId, CancellationToken>,
+    /// Folder being run, one recipe at a time. Only one can run at once
+    folder_run: Option<FolderRun>,
+    /// Request being re-sent on an interval. Only one can be watched at once
+    watch: Option<Watch>,
+    /// Requests waiting to be sent at a later time
+    scheduled: Vec<ScheduledRequest>,
+    /// ID for the next scheduled request
+    next_schedule_id: ScheduleId,
+    /// Background task that forwards terminal input to the message queue.
+    /// This is stopped while an external editor has the terminal
+    input_task: Option<JoinHandle<()>>,
     should_run: bool,
 }
 
 type Term = Terminal<CrosstermBackend<Stdout>>;
 
+/// State of a folder run, where each recipe in a folder is sent in order
+#[derive(Debug)]
+struct FolderRun {
+    folder_id: RecipeId,
+    profile_id: Option<ProfileId>,
+    /// Recipes that haven't been sent yet, in order
+    remaining: VecDeque<RecipeId>,
+    /// Request in progress. We wait for this to finish before sending the
+    /// next one
+    current: Option<RequestId>,
+    results: Vec<RunResult>,
+    /// Keep sending after a request fails? Otherwise the rest are skipped
+    continue_on_failure: bool,
+}
+
+impl FolderRun {
+    /// Get the next recipe to send. If the run has failed and shouldn't
+    /// continue, the remaining recipes are marked as skipped
+    fn next_recipe(&mut self) -> Option<RecipeId> {
+        let failed = self
+            .results
+            .last()
+            .is_some_and(|result| !result.is_success());
+        if failed && !self.continue_on_failure {
+            self.results
+                .extend(self.remaining.drain(..).map(RunResult::skipped));
+            None
+        } else {
+            self.remaining.pop_front()
+        }
+    }
+}
+
+/// A request that's re-sent on an interval
+#[derive(Debug)]
+struct Watch {
+    request_config: RequestConfig,
+    /// Request in progress. We wait for this to finish before scheduling the
+    /// next one, so slow requests don't pile up
+    current: Option<RequestId>,
+    /// When to send the next request
+    next_send: Instant,
+}
+
 impl Tui {
     /// Rough **maximum** time for each iteration of the main loop
     const TICK_TIME: Duration = Duration::from_millis(250);
+    /// Time to wait after a collection file changes before reloading, so that
+    /// all the events from a single save are handled with one reload
+    const RELOAD_DEBOUNCE: Duration = Duration::from_millis(100);
 
     /// Start the TUI. Any errors that occur during startup will be panics,
     /// because they prevent TUI execution.
@@ -86,8 +194,18 @@ impl Tui {
         let messages_tx = MessageSender::new(messages_tx);
         // Load a database for this particular collection
         let database = Database::load()?.into_collection(&collection_path)?;
+        // Fall back to the default theme if the configured one is broken
+        let theme = config
+            .theme
+            .load()
+            .reported(&messages_tx)
+            .unwrap_or_default();
         // Initialize global view context
-        TuiContext::init(config);
+        TuiContext::init(config, &theme);
+        TuiContext::get()
+            .http_engine
+            .load_cookies(&database)
+            .reported(&messages_tx);
 
         // ===== Initialize collection & view =====
 
@@ -97,8 +215,14 @@ impl Tui {
             .await
             .reported(&messages_tx)
             .unwrap_or_else(|| CollectionFile::with_path(collection_path));
-        let view =
-            View::new(&collection_file, database.clone(), messages_tx.clone());
+        let profile_overrides = ProfileOverrides::default();
+        let view = View::new(
+            &collection_file.collection,
+            collection_file.path(),
+            &profile_overrides,
+            database.clone(),
+            messages_tx.clone(),
+        );
 
         // The code to revert the terminal takeover is in `Tui::drop`, so we
         // shouldn't take over the terminal until right before creating the
@@ -112,6 +236,16 @@ impl Tui {
             messages_tx,
 
             collection_file,
+            watcher: None,
+            profile_overrides,
+            scratch_recipe: None,
+            websockets: HashMap::new(),
+            active_requests: HashMap::new(),
+            folder_run: None,
+            watch: None,
+            scheduled: Vec::new(),
+            next_schedule_id: 0,
+            input_task: None,
             should_run: true,
 
             view: Replaceable::new(view),
@@ -126,13 +260,8 @@ impl Tui {
     async fn run(mut self) -> anyhow::Result<()> {
         // Spawn background tasks
         self.listen_for_signals();
-        tokio::spawn(
-            TuiContext::get()
-                .input_engine
-                .input_loop(self.messages_tx.clone()),
-        );
-        // Hang onto this because it stops running when dropped
-        let _watcher = self.watch_collection()?;
+        self.start_input_loop();
+        self.watcher = Some(self.watch_collection()?);
 
         // This loop is limited by the rate that messages come in, with a
         // minimum rate enforced by a timeout
@@ -156,10 +285,19 @@ impl Tui {
                 trace!(?message, "Handling message");
                 // If an error occurs, store it so we can show the user
                 if let Err(error) = self.handle_message(message) {
-                    self.view.open_modal(error, ModalPriority::High);
+                    self.view.error(error);
                 }
             }
 
+            // Re-send the watched request, if it's due
+            if let Err(error) = self.poll_watch() {
+                self.view.error(error);
+            }
+            // Send any scheduled requests that are due
+            if let Err(error) = self.poll_scheduled() {
+                self.view.error(error);
+            }
+
             // ===== Event Phase =====
             // Let the view handle all queued events
             self.view.handle_events();
@@ -174,20 +312,60 @@ impl Tui {
             Message::CollectionStartReload => {
                 let future = self.collection_file.reload();
                 let messages_tx = self.messages_tx();
-                self.spawn(async move {
-                    let collection = future.await?;
-                    messages_tx.send(Message::CollectionEndReload(collection));
-                    Ok(())
+                tokio::spawn(async move {
+                    match future.await {
+                        Ok(collection_file) => {
+                            messages_tx.send(Message::CollectionEndReload(
+                                collection_file.into(),
+                            ))
+                        }
+                        // Keep the old collection, and show the error in a
+                        // notification so it doesn't interrupt the user
+                        // mid-edit. They'll fix it and the file will reload.
+                        // The error is already logged by the loader
+                        Err(error) => messages_tx
+                            .send(Message::Notify(format!("{error:#}"))),
+                    }
                 });
             }
-            Message::CollectionEndReload(collection) => {
-                self.reload_collection(collection);
+            Message::CollectionEndReload(collection_file) => {
+                self.reload_collection(*collection_file);
             }
             Message::CollectionEdit => {
                 let path = self.collection_file.path();
                 open::that_detached(path).context("Error opening {path:?}")?;
             }
+            Message::CollectionFindReplace => {
+                self.spawn(find_replace(
+                    self.messages_tx(),
+                    self.collection_file.path().to_owned(),
+                ));
+            }
 
+            Message::CopyExchangeReport(request_id) => {
+                let report = self.exchange_report(request_id)?;
+                self.view.copy_text(report);
+            }
+            Message::SaveExchangeReport(request_id) => {
+                let report = self.exchange_report(request_id)?;
+                self.spawn(save_file(
+                    self.messages_tx(),
+                    Some(format!("report-{request_id}.md")),
+                    report.into_bytes(),
+                ));
+            }
+            Message::ExportExchangeHar(request_id) => {
+                let exchange =
+                    self.database.get_request(request_id)?.ok_or_else(
+                        || anyhow!("Request `{request_id}` not in history"),
+                    )?;
+                let har = serde_json::to_vec_pretty(&Har::new([&exchange]))?;
+                self.spawn(save_file(
+                    self.messages_tx(),
+                    Some(format!("{request_id}.har")),
+                    har,
+                ));
+            }
             Message::CopyRequestUrl(request_config) => {
                 self.copy_request_url(request_config)?;
             }
@@ -198,32 +376,231 @@ impl Tui {
                 self.copy_request_curl(request_config)?;
             }
             Message::CopyText(text) => self.view.copy_text(text),
+            Message::EditRequestBody(request_config) => {
+                self.edit_request_body(request_config)?;
+            }
+            Message::EditRecipeNote(recipe_id) => {
+                let note = self.database.get_recipe_note(&recipe_id)?;
+                let note = self.edit_note(note.as_deref())?;
+                self.database.set_recipe_note(&recipe_id, note.as_deref())?;
+                self.view.set_recipe_note(&recipe_id, note);
+            }
+            Message::EditExchangeNote(request_id) => {
+                let note = self.database.get_exchange_note(request_id)?;
+                let note = self.edit_note(note.as_deref())?;
+                self.database
+                    .set_exchange_note(request_id, note.as_deref())?;
+                self.view.set_exchange_note(request_id, note);
+            }
+            Message::HttpPrettifyBody {
+                response,
+                selector_type,
+                query,
+                destination,
+            } => {
+                // This is CPU-bound, so keep it off the async executor
+                task::spawn_blocking(move || {
+                    let body = PrettyBody::new(
+                        response,
+                        selector_type,
+                        query.as_deref(),
+                    );
+                    let _ = destination.set(body);
+                });
+            }
+            Message::RequestPreview {
+                request_config,
+                destination,
+            } => {
+                self.preview_request(request_config, destination)?;
+            }
+            Message::ExplainRequest(request_config) => {
+                self.explain_request(request_config)?;
+            }
+            Message::ExplainShow { recipe_id, trace } => {
+                self.view.open_modal((recipe_id, trace), ModalPriority::Low);
+            }
+            Message::ChainInspect {
+                chain_ids,
+                profile_id,
+            } => {
+                let chains = &self.collection_file.collection.chains;
+                let chains = match chain_ids {
+                    Some(chain_ids) => chain_ids
+                        .iter()
+                        .map(|chain_id| {
+                            chains.get(chain_id).cloned().ok_or_else(|| {
+                                anyhow!("Unknown chain `{chain_id}`")
+                            })
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?,
+                    None => chains.values().cloned().collect(),
+                };
+                self.view
+                    .open_modal((chains, profile_id), ModalPriority::Low);
+            }
+            Message::ChainResolve {
+                chain_id,
+                profile_id,
+            } => self.resolve_chain(chain_id, profile_id)?,
+            Message::GoldenMarkStart(request_id) => {
+                let exchange =
+                    self.database.get_request(request_id)?.ok_or_else(
+                        || anyhow!("Request `{request_id}` not in history"),
+                    )?;
+                // Keep the current name by default, so re-marking is quick
+                let default_name = self
+                    .database
+                    .get_golden_exchange(&exchange.request.recipe_id)?
+                    .map_or_else(
+                        || exchange.request.recipe_id.to_string(),
+                        |golden| golden.name,
+                    );
+                self.spawn(mark_golden(
+                    self.messages_tx(),
+                    request_id,
+                    default_name,
+                ));
+            }
+            Message::GoldenMark { request_id, name } => {
+                let exchange =
+                    self.database.get_request(request_id)?.ok_or_else(
+                        || anyhow!("Request `{request_id}` not in history"),
+                    )?;
+                let recipe_id = &exchange.request.recipe_id;
+                self.database
+                    .set_golden_exchange(recipe_id, &name, request_id)?;
+                self.view.notify(format!(
+                    "Marked golden response `{name}` for `{recipe_id}`"
+                ));
+            }
+            Message::GoldenDiff {
+                profile_id,
+                recipe_id,
+            } => {
+                let golden =
+                    self.database.get_golden_exchange(&recipe_id)?.ok_or_else(
+                        || anyhow!("No golden response for `{recipe_id}`"),
+                    )?;
+                let latest = self
+                    .database
+                    .get_latest_request(profile_id.as_ref(), &recipe_id)?
+                    .ok_or_else(|| {
+                        anyhow!("No response to compare for `{recipe_id}`")
+                    })?;
+                self.view.open_modal((golden, latest), ModalPriority::Low);
+            }
+            Message::GraphqlIntrospect(request_config) => {
+                self.graphql_introspect(request_config)?;
+            }
+            Message::GraphqlEditStart(request_config) => {
+                let recipe = self.get_recipe(&request_config.recipe_id)?;
+                if !recipe.graphql {
+                    bail!("Recipe `{}` is not a GraphQL recipe", recipe.id);
+                }
+                let query = match &recipe.body {
+                    None => String::new(),
+                    Some(RecipeBody::Raw(body)) => body.as_str().to_owned(),
+                    Some(_) => bail!("GraphQL queries must be raw bodies"),
+                };
+                let schema = self.database.get_graphql_schema(&recipe.id)?;
+                self.view.open_modal(
+                    (request_config, query, schema),
+                    ModalPriority::Low,
+                );
+            }
             Message::SaveFile { default_path, data } => {
                 self.spawn(save_file(self.messages_tx(), default_path, data));
             }
-
-            Message::Error { error } => {
-                self.view.open_modal(error, ModalPriority::High)
+            Message::SaveResponseBody {
+                default_path,
+                response,
+            } => {
+                self.spawn(save_response_body(
+                    self.messages_tx(),
+                    default_path,
+                    response,
+                ));
             }
 
+            Message::Error { error } => self.view.error(error),
+
             // Manage HTTP life cycle
             Message::HttpBeginRequest(request_config) => {
-                self.send_request(request_config)?
+                self.send_request(request_config)?;
             }
             Message::HttpBuildError { error } => {
+                self.active_requests.remove(&error.id);
+                let (id, result) = (error.id, RunResult::from(&error));
                 self.view
                     .set_request_state(RequestState::BuildError { error });
+                self.advance_watch(id);
+                self.advance_folder_run(id, result)?;
             }
-            Message::HttpLoading { request } => {
-                self.view.set_request_state(RequestState::loading(request))
+            Message::HttpLoading { request, queued } => {
+                self.view.set_request_state(if queued {
+                    RequestState::queued(request)
+                } else {
+                    RequestState::loading(request)
+                })
             }
+            Message::HttpStreaming { request, stream } => self
+                .view
+                .set_request_state(RequestState::streaming(request, stream)),
             Message::HttpComplete(result) => {
+                let id = match &result {
+                    Ok(exchange) => exchange.id,
+                    Err(error) => error.request.id,
+                };
+                self.active_requests.remove(&id);
+                let run_result = match &result {
+                    Ok(exchange) => {
+                        self.check_assertions(exchange);
+                        RunResult::from(exchange)
+                    }
+                    Err(error) => RunResult::from(error),
+                };
                 let state = match result {
                     Ok(exchange) => RequestState::response(exchange),
                     Err(error) => RequestState::RequestError { error },
                 };
                 self.view.set_request_state(state);
+                self.advance_watch(id);
+                self.advance_folder_run(id, run_result)?;
+            }
+            Message::ToggleWatch(request_config) => {
+                self.toggle_watch(request_config)
+            }
+            Message::ScheduleStart(request_config) => {
+                self.spawn(schedule_request(self.messages_tx(), request_config))
+            }
+            Message::ScheduleAdd {
+                request_config,
+                send_at,
+            } => self.schedule(request_config, send_at),
+            Message::ScheduleOpen => self
+                .view
+                .open_modal(self.scheduled.clone(), ModalPriority::Low),
+            Message::ScheduleCancel(id) => {
+                self.scheduled.retain(|scheduled| scheduled.id != id)
+            }
+            Message::RunFolder {
+                folder_id,
+                profile_id,
+            } => self.run_folder(folder_id, profile_id)?,
+            Message::HttpCancel(request_id) => {
+                // The request task reports the cancellation itself, since it
+                // knows how far the request got
+                if let Some(token) = self.active_requests.remove(&request_id) {
+                    token.cancel();
+                }
+            }
+
+            Message::WebSocketConnect(request_config) => {
+                self.connect_websocket(request_config)?
             }
+            // State lives in the connection; we just need to redraw
+            Message::WebSocketUpdated => {}
 
             // Force quit short-circuits the view/message cycle, to make sure
             // it doesn't get ate by text boxes
@@ -239,6 +616,9 @@ impl Tui {
             Message::PromptStart(prompt) => {
                 self.view.open_modal(prompt, ModalPriority::Low);
             }
+            Message::FilePromptStart(prompt) => {
+                self.view.open_modal(prompt, ModalPriority::Low);
+            }
             Message::ConfirmStart(confirm) => {
                 self.view.open_modal(confirm, ModalPriority::Low);
             }
@@ -254,8 +634,146 @@ impl Tui {
                     destination,
                 )?;
             }
+            Message::OAuth2TokenPreview {
+                oauth2,
+                profile_id,
+                destination,
+            } => {
+                let context = self.template_context(profile_id, false)?;
+                self.spawn(async move {
+                    // This is just a preview, so don't bother the user with
+                    // errors. They'll see them when they send the request
+                    let token = oauth2
+                        .cached_token(&context)
+                        .await
+                        .traced()
+                        .ok()
+                        .flatten();
+                    destination.set(token).map_err(|_| {
+                        anyhow!("Multiple writes to OAuth 2.0 token lock")
+                    })
+                });
+            }
+
+            Message::ProfileOverrideStart(profile_id) => {
+                self.start_profile_override(&profile_id)?
+            }
+            Message::ProfileOverride {
+                profile_id,
+                field,
+                value,
+            } => {
+                self.profile_overrides.set(profile_id, field.clone(), value);
+                self.update_profile_overrides();
+                self.view.notify(format!(
+                    "Overrode `{field}` until exit; the collection file is \
+                    unchanged"
+                ));
+            }
+            Message::ProfileRemoveOverride { profile_id, field } => {
+                self.profile_overrides.remove(&profile_id, &field);
+                self.update_profile_overrides();
+                self.view.notify(format!("Reset `{field}`"));
+            }
+            Message::ProfileClearOverrides => {
+                if self.profile_overrides.is_empty() {
+                    self.view.notify("No profile overrides to clear");
+                } else {
+                    self.profile_overrides.clear();
+                    self.update_profile_overrides();
+                    self.view.notify("Cleared profile overrides");
+                }
+            }
+
+            Message::ScratchStart => {
+                self.spawn(compose_scratch_request(
+                    self.messages_tx(),
+                    self.scratch_recipe.clone(),
+                ));
+            }
+            Message::ScratchCurlStart => {
+                self.spawn(paste_curl_request(self.messages_tx()));
+            }
+            Message::ScratchUpdate(recipe) => self.set_scratch_recipe(*recipe),
+            Message::ScratchSaveStart(recipe_id) => {
+                if recipe_id.as_str() != SCRATCH_RECIPE_ID {
+                    bail!("Only the scratch request can be saved as a recipe");
+                }
+                self.spawn(save_scratch_request(self.messages_tx()));
+            }
+            Message::ScratchSave(recipe_id) => {
+                self.save_scratch_recipe(recipe_id)?
+            }
 
             Message::Quit => self.quit(),
+
+            Message::RecipeCreateStart => {
+                self.spawn(create_recipe(self.messages_tx()))
+            }
+            Message::RecipeCreate(recipe) => {
+                self.collection_file.add_recipe(&recipe)?;
+                self.reload_after_edit(
+                    format!("Created recipe `{}`", recipe.id),
+                    Some(recipe.id),
+                );
+            }
+            Message::RecipeDuplicate(recipe_id) => {
+                self.duplicate_recipe(&recipe_id)?
+            }
+            Message::RecipeRenameStart(recipe_id) => {
+                self.spawn(rename_recipe(self.messages_tx(), recipe_id))
+            }
+            Message::RecipeRename { recipe_id, new_id } => {
+                self.collection_file.rename_recipe(&recipe_id, &new_id)?;
+                self.reload_after_edit(
+                    format!("Renamed `{recipe_id}` to `{new_id}`"),
+                    Some(new_id),
+                );
+            }
+            Message::RecipeDeleteStart(recipe_id) => {
+                self.spawn(delete_recipe(self.messages_tx(), recipe_id))
+            }
+            Message::RecipeDelete(recipe_id) => {
+                self.collection_file.delete_recipe(&recipe_id)?;
+                self.reload_after_edit(
+                    format!("Deleted recipe `{recipe_id}`"),
+                    None,
+                );
+            }
+            Message::RecipeMoveStart(recipe_id, parent_id) => self
+                .spawn(move_recipe(self.messages_tx(), recipe_id, parent_id)),
+            Message::RecipeMove {
+                recipe_id,
+                folder_id,
+            } => {
+                self.collection_file
+                    .move_recipe(&recipe_id, folder_id.as_ref())?;
+                let destination = match &folder_id {
+                    Some(folder_id) => format!("`{folder_id}`"),
+                    None => "top level".into(),
+                };
+                self.reload_after_edit(
+                    format!("Moved `{recipe_id}` to {destination}"),
+                    Some(recipe_id),
+                );
+            }
+            Message::FolderCreateStart(parent_id) => {
+                self.spawn(create_folder(self.messages_tx(), parent_id))
+            }
+            Message::FolderCreate {
+                folder_id,
+                parent_id,
+            } => {
+                self.collection_file
+                    .add_folder(&folder_id, parent_id.as_ref())?;
+                self.reload_after_edit(
+                    format!("Created folder `{folder_id}`"),
+                    Some(folder_id),
+                );
+            }
+            Message::RecipeSelect(recipe_id) => {
+                self.view.select_recipe(&recipe_id)
+            }
         }
         Ok(())
     }
@@ -275,24 +793,56 @@ impl Tui {
         });
     }
 
-    /// Spawn a watcher to automatically reload the collection when the file
-    /// changes. Return the watcher because it stops when dropped.
-    fn watch_collection(&self) -> anyhow::Result<impl Watcher> {
-        // Spawn a watcher for the collection file
+    /// Spawn a watcher to automatically reload the collection when any of its
+    /// files (including imports and `.env` files) change. Return the watcher
+    /// because it stops when dropped.
+    ///
+    /// We watch each file's *directory* rather than the file itself, because
+    /// many editors save by writing a new file and renaming it over the old
+    /// one, which would break a watch on the file.
+    fn watch_collection(&self) -> anyhow::Result<RecommendedWatcher> {
+        let files: HashSet<PathBuf> =
+            self.collection_file.files().map(normalize_path).collect();
+        let directories: HashSet<PathBuf> = files
+            .iter()
+            .filter_map(|path| path.parent())
+            .map(PathBuf::from)
+            .collect();
+
         let messages_tx = self.messages_tx();
-        let f = move |result: notify::Result<_>| {
+        // A single save can generate several events, so wait for them to
+        // settle and reload once
+        let reload_pending = Arc::new(AtomicBool::new(false));
+        let f = move |result: notify::Result<notify::Event>| {
             match result {
-                // Only reload if the file *content* changes
-                Ok(
-                    event @ notify::Event {
-                        kind: notify::EventKind::Modify(ModifyKind::Data(_)),
-                        ..
-                    },
-                ) => {
-                    info!(?event, "Collection file changed, reloading");
-                    messages_tx.send(Message::CollectionStartReload);
+                // Only reload if the *content* of a collection file changes
+                Ok(event)
+                    if matches!(
+                        event.kind,
+                        EventKind::Create(_)
+                            | EventKind::Remove(_)
+                            | EventKind::Modify(
+                                ModifyKind::Any
+                                    | ModifyKind::Data(_)
+                                    | ModifyKind::Name(_)
+                            )
+                    ) && event
+                        .paths
+                        .iter()
+                        .any(|path| files.contains(&normalize_path(path))) =>
+                {
+                    if !reload_pending.swap(true, Ordering::Relaxed) {
+                        info!(?event, "Collection file changed, reloading");
+                        let reload_pending = Arc::clone(&reload_pending);
+                        let messages_tx = messages_tx.clone();
+                        thread::spawn(move || {
+                            thread::sleep(Self::RELOAD_DEBOUNCE);
+                            reload_pending.store(false, Ordering::Relaxed);
+                            messages_tx.send(Message::CollectionStartReload);
+                        });
+                    }
                 }
-                // Do nothing for other event kinds
+                // Do nothing for other events
                 Ok(_) => {}
                 Err(err) => {
                     error!(error = %err, "Error watching collection file");
@@ -300,28 +850,184 @@ impl Tui {
             }
         };
         let mut watcher = notify::recommended_watcher(f)?;
-        watcher
-            .watch(self.collection_file.path(), RecursiveMode::NonRecursive)?;
+        for directory in &directories {
+            // A missing directory (e.g. for a `.env` file) shouldn't prevent
+            // watching the other files
+            if let Err(error) =
+                watcher.watch(directory, RecursiveMode::NonRecursive)
+            {
+                warn!(?directory, %error, "Error watching directory");
+            }
+        }
         info!(
-            path = ?self.collection_file.path(), ?watcher,
-            "Watching collection file for changes"
+            files = ?self.collection_file.files().collect_vec(), ?watcher,
+            "Watching collection files for changes"
         );
         Ok(watcher)
     }
 
     /// Reload state with a new collection
-    fn reload_collection(&mut self, collection: Collection) {
-        self.collection_file.collection = collection;
+    fn reload_collection(&mut self, collection_file: CollectionFile) {
+        // Imports may have changed, in which case we need to watch a different
+        // set of files
+        let rewatch = !self.collection_file.files().eq(collection_file.files());
+        self.collection_file = collection_file;
+        if rewatch {
+            if let Some(watcher) =
+                self.watch_collection().reported(&self.messages_tx)
+            {
+                self.watcher = Some(watcher);
+            }
+        }
+        self.rebuild_view();
+    }
 
-        // Rebuild the whole view, because tons of things can change. Drop the
-        // old one *first* to make sure UI state is saved before being restored
+    /// Rebuild the whole view, because tons of things can change. Drop the
+    /// old one *first* to make sure UI state is saved before being restored
+    fn rebuild_view(&mut self) {
+        let collection = self.collection();
+        let collection_path = self.collection_file.path();
         let database = self.database.clone();
         let messages_tx = self.messages_tx();
-        let collection_file = &self.collection_file;
+        let profile_overrides = &self.profile_overrides;
         self.view.replace(move |old| {
             drop(old);
-            View::new(collection_file, database, messages_tx)
+            View::new(
+                &collection,
+                collection_path,
+                profile_overrides,
+                database,
+                messages_tx,
+            )
+        });
+        // The new view doesn't know about any open connections
+        for connection in self.websockets.values() {
+            self.view.set_websocket(connection.clone());
+        }
+    }
+
+    /// Get the collection as the user sees it: the collection file, plus any
+    /// temporary state from this session (profile overrides and the scratch
+    /// request)
+    fn collection(&self) -> Collection {
+        let mut collection = self.collection_file.collection.clone();
+        self.profile_overrides.apply(&mut collection);
+        if let Some(recipe) = &self.scratch_recipe {
+            if let Err(recipe_id) =
+                collection.recipes.insert_first(recipe.clone())
+            {
+                warn!(
+                    %recipe_id,
+                    "Scratch request ID is already used in the collection"
+                );
+            }
+        }
+        collection
+    }
+
+    /// Create or replace the scratch request, then select it
+    fn set_scratch_recipe(&mut self, recipe: Recipe) {
+        let recipe_id = recipe.id.clone();
+        self.scratch_recipe = Some(recipe);
+        self.rebuild_view();
+        self.view.select_recipe(&recipe_id);
+    }
+
+    /// Write the scratch request to the collection file under a new ID. Once
+    /// the collection is reloaded, select the new recipe
+    fn save_scratch_recipe(
+        &mut self,
+        recipe_id: RecipeId,
+    ) -> anyhow::Result<()> {
+        let mut recipe = self
+            .scratch_recipe
+            .clone()
+            .ok_or_else(|| anyhow!("No scratch request to save"))?;
+        recipe.id = recipe_id.clone();
+        recipe.name = None;
+        self.collection_file.add_recipe(&recipe)?;
+        // The scratch request now lives in the collection file
+        self.scratch_recipe = None;
+        self.reload_after_edit(
+            format!("Saved scratch request as `{recipe_id}`"),
+            Some(recipe_id),
+        );
+        Ok(())
+    }
+
+    /// Reload the collection after the TUI modified the file, then show a
+    /// notification and optionally select a recipe. The file watcher will
+    /// trigger a reload too, but we need to know when the reload is done so we
+    /// can select the recipe.
+    fn reload_after_edit(
+        &self,
+        notification: String,
+        select_recipe: Option<RecipeId>,
+    ) {
+        let future = self.collection_file.reload();
+        let messages_tx = self.messages_tx();
+        self.spawn(async move {
+            let collection_file = future.await?;
+            messages_tx
+                .send(Message::CollectionEndReload(collection_file.into()));
+            messages_tx.send(Message::Notify(notification));
+            if let Some(recipe_id) = select_recipe {
+                messages_tx.send(Message::RecipeSelect(recipe_id));
+            }
+            Ok(())
+        });
+    }
+
+    /// Prompt the user to override a field in a profile
+    fn start_profile_override(
+        &mut self,
+        profile_id: &ProfileId,
+    ) -> anyhow::Result<()> {
+        // The editor applies the overrides itself, so it can reset them
+        let profile = self
+            .collection_file
+            .collection
+            .profiles
+            .get(profile_id)
+            .ok_or_else(|| anyhow!("No profile with ID `{profile_id}`"))?
+            .clone();
+        self.view.open_modal(
+            (profile, self.profile_overrides.clone()),
+            ModalPriority::Low,
+        );
+        Ok(())
+    }
+
+    /// Pass changed profile overrides to the view. Unlike a full rebuild, this
+    /// keeps the view state (including open modals) intact, and just
+    /// re-renders everything that depends on profile values
+    fn update_profile_overrides(&mut self) {
+        let profiles = self.collection().profiles.into_values().collect();
+        self.view
+            .set_profile_overrides(profiles, self.profile_overrides.clone());
+    }
+
+    /// Duplicate a recipe in the collection file. Once the collection is
+    /// reloaded, select the new recipe and open the file so the user can edit
+    /// it
+    fn duplicate_recipe(&self, recipe_id: &RecipeId) -> anyhow::Result<()> {
+        let new_id = self.collection_file.duplicate_recipe(recipe_id)?;
+        // The file watcher will trigger a reload too, but we need to know when
+        // the reload is done so we can select the new recipe
+        let future = self.collection_file.reload();
+        let messages_tx = self.messages_tx();
+        self.spawn(async move {
+            let collection_file = future.await?;
+            messages_tx
+                .send(Message::CollectionEndReload(collection_file.into()));
+            messages_tx.send(Message::Notify(format!(
+                "Duplicated recipe as `{new_id}`"
+            )));
+            messages_tx.send(Message::RecipeSelect(new_id));
+            messages_tx.send(Message::CollectionEdit);
+            Ok(())
         });
+        Ok(())
     }
 
     /// GOODBYE
@@ -407,6 +1113,189 @@ impl Tui {
         Ok(())
     }
 
+    /// Build a request without sending it, for the request preview. This uses
+    /// the preview prompter, so the user isn't prompted for a preview
+    fn preview_request(
+        &self,
+        request_config: RequestConfig,
+        destination: Arc<OnceLock<RequestPreviewResult>>,
+    ) -> anyhow::Result<()> {
+        let seed = RequestSeed::new(
+            self.get_recipe(&request_config.recipe_id)?,
+            request_config.options,
+        );
+        let template_context =
+            self.template_context(request_config.profile_id, false)?;
+        self.spawn(async move {
+            // Errors are shown in the preview, so don't bother the user
+            let result = TuiContext::get()
+                .http_engine
+                .build(seed, &template_context)
+                .await
+                .map(|ticket| Arc::clone(ticket.record()))
+                .map_err(|error| format!("{:#}", anyhow::Error::from(error)));
+            let _ = destination.set(result);
+            Ok(())
+        });
+        Ok(())
+    }
+
+    /// Render a request with tracing enabled, then show the trace. The trace
+    /// is shown even if the build fails, because that's when it's most useful
+    fn explain_request(
+        &self,
+        request_config: RequestConfig,
+    ) -> anyhow::Result<()> {
+        let recipe_id = request_config.recipe_id;
+        let seed = RequestSeed::new(
+            self.get_recipe(&recipe_id)?,
+            request_config.options,
+        );
+        let template_context =
+            self.template_context(request_config.profile_id, true)?;
+        let messages_tx = self.messages_tx();
+        self.spawn(async move {
+            let (result, trace) = template::trace(
+                TuiContext::get().http_engine.build(seed, &template_context),
+            )
+            .await;
+            messages_tx.send(Message::ExplainShow { recipe_id, trace });
+            result?;
+            Ok(())
+        });
+        Ok(())
+    }
+
+    /// Load a completed exchange from history and render it as Markdown
+    fn exchange_report(&self, request_id: RequestId) -> anyhow::Result<String> {
+        let exchange = self
+            .database
+            .get_request(request_id)?
+            .ok_or_else(|| anyhow!("Request `{request_id}` not in history"))?;
+        // Parse the body so it can be prettified
+        exchange.response.parse_body();
+        exchange.to_markdown()
+    }
+
+    /// Open a recipe's body in the user's editor, then send the request with
+    /// the edited body. The edit only applies to this request; the recipe is
+    /// not modified.
+    fn edit_request_body(
+        &mut self,
+        request_config: RequestConfig,
+    ) -> anyhow::Result<()> {
+        let recipe = self.get_recipe(&request_config.recipe_id)?;
+        let body = match &recipe.body {
+            None => "",
+            Some(RecipeBody::Raw(body)) => body.as_str(),
+            Some(_) => bail!("Only raw request bodies can be edited"),
+        };
+        // Use an extension matching the content type, so the editor can
+        // highlight the body. GraphQL queries are sent as JSON, but the body
+        // itself is just the query
+        let extension = if recipe.graphql {
+            "graphql"
+        } else {
+            recipe
+                .headers
+                .iter()
+                .find(|(name, _)| {
+                    name.eq_ignore_ascii_case(header::CONTENT_TYPE.as_str())
+                })
+                .and_then(|(_, value)| {
+                    ContentType::from_mime(value.as_str()).ok()
+                })
+                .map_or("txt", |content_type| content_type.extension())
+        };
+
+        let body = self.edit_text(body, extension)?;
+        let body = Template::try_from(body)
+            .context("Edited body is not a valid template")?;
+        self.send_request(RequestConfig {
+            options: BuildOptions {
+                body: Some(body),
+                ..request_config.options
+            },
+            ..request_config
+        })?;
+        Ok(())
+    }
+
+    /// Open a note in the user's editor, and return the edited note.
+    /// Surrounding whitespace is trimmed, and an empty note is `None`.
+    fn edit_note(
+        &mut self,
+        note: Option<&str>,
+    ) -> anyhow::Result<Option<String>> {
+        let note = self.edit_text(note.unwrap_or_default(), "md")?;
+        let note = note.trim();
+        Ok((!note.is_empty()).then(|| note.to_owned()))
+    }
+
+    /// Open some text in the user's editor, and return the edited text once
+    /// the editor exits. The editor needs the terminal, so the TUI is
+    /// suspended until then. This blocks the main loop, but background tasks
+    /// keep running.
+    fn edit_text(
+        &mut self,
+        text: &str,
+        extension: &str,
+    ) -> anyhow::Result<String> {
+        let command = editor_command()?;
+        let path = env::temp_dir()
+            .join(format!("slumber-{}.{extension}", Uuid::new_v4()));
+        fs::write(&path, text)
+            .with_context(|| format!("Error writing {path:?}"))?;
+
+        // Hand the terminal over to the editor
+        self.stop_input_loop();
+        restore_terminal()?;
+        info!(?command, ?path, "Opening editor");
+        let status = process::Command::new(&command[0])
+            .args(&command[1..])
+            .arg(&path)
+            .status();
+        // Take the terminal back, even if the editor failed
+        take_terminal()?;
+        self.terminal.clear()?;
+        self.start_input_loop();
+
+        let result = status
+            .with_context(|| format!("Error running editor `{}`", command[0]))
+            .and_then(|status| {
+                if status.success() {
+                    fs::read_to_string(&path)
+                        .with_context(|| format!("Error reading {path:?}"))
+                } else {
+                    Err(anyhow!("Editor exited with {status}"))
+                }
+            });
+        let _ = fs::remove_file(&path)
+            .with_context(|| format!("Error deleting {path:?}"))
+            .traced();
+        result
+    }
+
+    /// Spawn the background task that forwards terminal input to the message
+    /// queue
+    fn start_input_loop(&mut self) {
+        self.input_task = Some(tokio::spawn(
+            TuiContext::get()
+                .input_engine
+                .input_loop(self.messages_tx.clone()),
+        ));
+    }
+
+    /// Stop reading terminal input, so another process can read it. This
+    /// waits until the input task is dropped, so no more input is consumed
+    fn stop_input_loop(&mut self) {
+        if let Some(task) = self.input_task.take() {
+            task.abort();
+            // Result is always a cancellation error, nothing to report
+            let _ = futures::executor::block_on(task);
+        }
+    }
+
     /// Launch an HTTP request in a separate task
     fn send_request(
         &mut self,
@@ -415,7 +1304,7 @@ impl Tui {
             recipe_id,
             options,
         }: RequestConfig,
-    ) -> anyhow::Result<()> {
+    ) -> anyhow::Result<RequestId> {
         // Launch the request in a separate task so it doesn't block.
         // These clones are all cheap.
 
@@ -424,36 +1313,120 @@ impl Tui {
         let messages_tx = self.messages_tx();
 
         // Mark request state as building
+        // Keep the options in case the request is cancelled, so it can be
+        // re-sent the same way
         let initialized =
-            RequestSeed::new(self.get_recipe(&recipe_id)?, options);
+            RequestSeed::new(self.get_recipe(&recipe_id)?, options.clone());
+        let id = initialized.id;
         self.view.set_request_state(RequestState::Building {
-            id: initialized.id,
+            id,
             start_time: Utc::now(),
-            profile_id,
-            recipe_id,
+            profile_id: profile_id.clone(),
+            recipe_id: recipe_id.clone(),
         });
+        let cancel = CancellationToken::new();
+        self.active_requests.insert(id, cancel.clone());
 
         // We can't use self.spawn here because HTTP errors are handled
         // differently from all other error types
         let database = self.database.clone();
         tokio::spawn(async move {
             // Build the request
-            let ticket = TuiContext::get()
+            let build = TuiContext::get()
                 .http_engine
-                .build(initialized, &template_context)
-                .await
-                .map_err(|error| {
-                    // Report the error, but don't actually return anything
-                    messages_tx.send(Message::HttpBuildError { error });
-                })?;
+                .build(initialized, &template_context);
+            let ticket = tokio::select! {
+                result = build => result,
+                () = cancel.cancelled() => Err(RequestBuildError {
+                    error: anyhow!("Request cancelled"),
+                    profile_id,
+                    recipe_id,
+                    id,
+                    time: Utc::now(),
+                }),
+            }
+            .map_err(|error| {
+                // Report the error, but don't actually return anything
+                messages_tx.send(Message::HttpBuildError { error });
+            })?;
 
             // Report liftoff
+            let request = Arc::clone(ticket.record());
+            let start_time = Utc::now();
             messages_tx.send(Message::HttpLoading {
-                request: Arc::clone(ticket.record()),
+                request: Arc::clone(&request),
+                queued: false,
             });
 
-            // Send the request and report the result to the main thread
-            let result = ticket.send(&database).await;
+            // If we have to wait for a free slot, show that in the view. Once
+            // we get the slot, the request is loading again
+            let ticket = ticket.on_queued({
+                let messages_tx = messages_tx.clone();
+                let request = Arc::clone(&request);
+                move |queued| {
+                    messages_tx.send(Message::HttpLoading {
+                        request: Arc::clone(&request),
+                        queued,
+                    })
+                }
+            });
+
+            // If the response is an event stream, show events as they arrive
+            let ticket = ticket.on_event_stream({
+                let messages_tx = messages_tx.clone();
+                let request = Arc::clone(&request);
+                move |stream| {
+                    messages_tx.send(Message::HttpStreaming {
+                        request: Arc::clone(&request),
+                        stream: Arc::clone(stream),
+                    })
+                }
+            });
+
+            // Send the request and report the result to the main thread.
+            // Dropping the send future aborts the request
+            let result = tokio::select! {
+                result = ticket.send(&database) => result,
+                () = cancel.cancelled() => Err(RequestError {
+                    error: anyhow!("Request cancelled"),
+                    request,
+                    retries: Vec::new(),
+                    start_time,
+                    end_time: Utc::now(),
+                    partial_response: None,
+                    resume_path: None,
+                    options: options.into(),
+                }),
+            };
+
+            // If the recipe wants its response body saved, do that now. If the
+            // body failed partway through, save what we got so it can be
+            // resumed.
+            let result = match result {
+                Ok(exchange) => {
+                    match exchange.save_response(&template_context).await {
+                        Ok(Some(path)) => {
+                            messages_tx.send(Message::Notify(format!(
+                                "Saved response body to {}",
+                                path.display()
+                            )))
+                        }
+                        Ok(None) => {}
+                        Err(error) => {
+                            messages_tx.send(Message::Error { error })
+                        }
+                    }
+                    Ok(exchange)
+                }
+                Err(mut error) => {
+                    if let Err(error) =
+                        error.save_partial_response(&template_context).await
+                    {
+                        messages_tx.send(Message::Error { error });
+                    }
+                    Err(error)
+                }
+            };
             messages_tx.send(Message::HttpComplete(result));
 
             // By returning an empty result, we can use `?` to break out early.
@@ -461,6 +1434,258 @@ impl Tui {
             Ok::<(), ()>(())
         });
 
+        Ok(id)
+    }
+
+    /// Check a response against its recipe's assertions, and notify the user
+    /// of the outcome. If the recipe has no assertions, do nothing
+    fn check_assertions(&mut self, exchange: &Exchange) {
+        let Some(recipe) = self
+            .collection_file
+            .collection
+            .recipes
+            .get_recipe(&exchange.request.recipe_id)
+            .filter(|recipe| !recipe.assertions.is_empty())
+        else {
+            return;
+        };
+        let failures = check_assertions(&recipe.assertions, exchange);
+        let message = match failures.as_slice() {
+            [] => format!("{} assertion(s) passed", recipe.assertions.len()),
+            [error, rest @ ..] => {
+                let mut message = format!("Assertion failed: {error}");
+                if !rest.is_empty() {
+                    message.push_str(&format!(" (+{} more)", rest.len()));
+                }
+                message
+            }
+        };
+        self.view.notify(message);
+    }
+
+    /// Start watching a request, so it's re-sent on an interval. If the same
+    /// recipe and profile are already being watched, stop watching instead
+    fn toggle_watch(&mut self, request_config: RequestConfig) {
+        let recipe_id = request_config.recipe_id.clone();
+        match self.watch.take() {
+            Some(watch)
+                if watch.request_config.recipe_id == recipe_id
+                    && watch.request_config.profile_id
+                        == request_config.profile_id =>
+            {
+                self.view.notify(format!("Stopped watching `{recipe_id}`"));
+            }
+            _ => {
+                let interval = TuiContext::get().config.watch_interval;
+                self.watch = Some(Watch {
+                    request_config,
+                    current: None,
+                    next_send: Instant::now(),
+                });
+                self.view.notify(format!(
+                    "Watching `{recipe_id}` every {}",
+                    // Only fails on overflow, which isn't a realistic interval
+                    format_duration(
+                        &chrono::Duration::from_std(interval)
+                            .unwrap_or(chrono::Duration::zero())
+                    )
+                ));
+            }
+        }
+    }
+
+    /// Send the watched request, if there is one and it's due
+    fn poll_watch(&mut self) -> anyhow::Result<()> {
+        let Some(watch) = &self.watch else {
+            return Ok(());
+        };
+        if watch.current.is_some() || Instant::now() < watch.next_send {
+            return Ok(());
+        }
+        match self.send_request(watch.request_config.clone()) {
+            Ok(request_id) => {
+                if let Some(watch) = &mut self.watch {
+                    watch.current = Some(request_id);
+                }
+                Ok(())
+            }
+            Err(error) => {
+                // The recipe may have been removed from the collection, so
+                // don't keep trying
+                self.watch = None;
+                Err(error)
+            }
+        }
+    }
+
+    /// If a request from the watch finished, schedule the next one
+    fn advance_watch(&mut self, request_id: RequestId) {
+        if let Some(watch) = &mut self.watch {
+            if watch.current == Some(request_id) {
+                watch.current = None;
+                watch.next_send =
+                    Instant::now() + TuiContext::get().config.watch_interval;
+            }
+        }
+    }
+
+    /// Add a request to be sent later. See [Self::poll_scheduled]
+    fn schedule(
+        &mut self,
+        request_config: RequestConfig,
+        send_at: DateTime<Local>,
+    ) {
+        self.view.notify(format!(
+            "Scheduled `{}` for {}",
+            request_config.recipe_id,
+            send_at.format("%H:%M:%S")
+        ));
+        self.scheduled.push(ScheduledRequest {
+            id: self.next_schedule_id,
+            request_config,
+            send_at,
+        });
+        self.next_schedule_id += 1;
+    }
+
+    /// Send every scheduled request that's due. Each schedule is removed once
+    /// it's sent, even if the send fails
+    fn poll_scheduled(&mut self) -> anyhow::Result<()> {
+        let now = Local::now();
+        let (due, pending) = mem::take(&mut self.scheduled)
+            .into_iter()
+            .partition::<Vec<_>, _>(|scheduled| scheduled.send_at <= now);
+        self.scheduled = pending;
+        // Send everything before reporting errors, so one bad request
+        // doesn't hold up the rest
+        let mut result = Ok(());
+        for scheduled in due {
+            if let Err(error) = self.send_request(scheduled.request_config) {
+                result = Err(error);
+            }
+        }
+        result
+    }
+
+    /// Start sending every recipe in a folder, in order. Each request is sent
+    /// once the previous one completes, so chains can use earlier responses.
+    /// See [Self::advance_folder_run]
+    fn run_folder(
+        &mut self,
+        folder_id: RecipeId,
+        profile_id: Option<ProfileId>,
+    ) -> anyhow::Result<()> {
+        if self.folder_run.is_some() {
+            self.view.notify("A folder is already running");
+            return Ok(());
+        }
+        let folder = self
+            .collection_file
+            .collection
+            .recipes
+            .get(&folder_id)
+            .and_then(RecipeNode::folder)
+            .ok_or_else(|| anyhow!("No folder with ID `{folder_id}`"))?;
+        let remaining = folder
+            .recipes()
+            .into_iter()
+            .filter(|recipe| recipe.kind != RecipeKind::WebSocket)
+            .map(|recipe| recipe.id.clone())
+            .collect();
+        self.folder_run = Some(FolderRun {
+            folder_id,
+            profile_id,
+            remaining,
+            current: None,
+            results: Vec::new(),
+            continue_on_failure: TuiContext::get().config.continue_on_failure,
+        });
+        self.send_next_in_folder()
+    }
+
+    /// Record the outcome of a request. If it's part of a folder run, move on
+    /// to the next recipe
+    fn advance_folder_run(
+        &mut self,
+        request_id: RequestId,
+        result: RunResult,
+    ) -> anyhow::Result<()> {
+        match &mut self.folder_run {
+            // Only the request we sent counts, not others the user sends while
+            // the run is going
+            Some(run) if run.current == Some(request_id) => {
+                run.current = None;
+                run.results.push(result);
+                self.send_next_in_folder()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Send the next recipe in the folder run. If there are none left, show
+    /// the report
+    fn send_next_in_folder(&mut self) -> anyhow::Result<()> {
+        let Some(run) = &mut self.folder_run else {
+            return Ok(());
+        };
+        if let Some(recipe_id) = run.next_recipe() {
+            let config = RequestConfig {
+                profile_id: run.profile_id.clone(),
+                recipe_id,
+                options: BuildOptions::default(),
+            };
+            match self.send_request(config) {
+                Ok(request_id) => {
+                    if let Some(run) = &mut self.folder_run {
+                        run.current = Some(request_id);
+                    }
+                }
+                Err(error) => {
+                    // Don't leave the run waiting on a request that never
+                    // started
+                    self.folder_run = None;
+                    return Err(error);
+                }
+            }
+        } else if let Some(run) = self.folder_run.take() {
+            self.view
+                .open_modal((run.folder_id, run.results), ModalPriority::Low);
+        }
+        Ok(())
+    }
+
+    /// Open a WebSocket connection in a background task. If there's already a
+    /// live connection for the profile+recipe, do nothing. A closed or failed
+    /// connection is replaced.
+    fn connect_websocket(
+        &mut self,
+        RequestConfig {
+            profile_id,
+            recipe_id,
+            options,
+        }: RequestConfig,
+    ) -> anyhow::Result<()> {
+        let key = (profile_id.clone(), recipe_id.clone());
+        if self
+            .websockets
+            .get(&key)
+            .is_some_and(|connection| connection.state().status.is_live())
+        {
+            return Ok(());
+        }
+
+        let template_context = self.template_context(profile_id, true)?;
+        let seed = RequestSeed::new(self.get_recipe(&recipe_id)?, options);
+        let messages_tx = self.messages_tx();
+        let connection = WebSocketConnection::open(
+            TuiContext::get().http_engine.clone(),
+            seed,
+            template_context,
+            self.database.clone(),
+            move || messages_tx.send(Message::WebSocketUpdated),
+        );
+        self.view.set_websocket(connection.clone());
+        self.websockets.insert(key, connection);
         Ok(())
     }
 
@@ -469,13 +1694,17 @@ impl Tui {
     /// with an unknown ID that indicates a logic error elsewhere, but it
     /// shouldn't be considered fatal.
     fn get_recipe(&self, recipe_id: &RecipeId) -> anyhow::Result<Recipe> {
-        let recipe = self
-            .collection_file
-            .collection
-            .recipes
-            .get_recipe(recipe_id)
-            .ok_or_else(|| anyhow!("No recipe with ID `{recipe_id}`"))?;
-        Ok(recipe.clone())
+        self.scratch_recipe
+            .as_ref()
+            .filter(|recipe| &recipe.id == recipe_id)
+            .or_else(|| {
+                self.collection_file
+                    .collection
+                    .recipes
+                    .get_recipe(recipe_id)
+            })
+            .cloned()
+            .ok_or_else(|| anyhow!("No recipe with ID `{recipe_id}`"))
     }
 
     /// Spawn a task to render a template, storing the result in a pre-defined
@@ -500,6 +1729,77 @@ impl Tui {
         Ok(())
     }
 
+    /// Resolve a chain from scratch in the background. Any cached value is
+    /// dropped first. The outcome lands in the chain log, so the user is just
+    /// notified when it's done.
+    fn resolve_chain(
+        &self,
+        chain_id: ChainId,
+        profile_id: Option<ProfileId>,
+    ) -> anyhow::Result<()> {
+        template::clear_chain(&chain_id);
+        let template = Template::try_from(format!("{{{{chains.{chain_id}}}}}"))
+            .with_context(|| format!("Invalid chain ID `{chain_id}`"))?;
+        // Use real prompts, since the user asked for this explicitly
+        let context = self.template_context(profile_id, true)?;
+        let messages_tx = self.messages_tx();
+        self.spawn(async move {
+            let message = match template.render(&context).await {
+                Ok(_) => format!("Resolved chain `{chain_id}`"),
+                Err(_) => format!("Resolving chain `{chain_id}` failed"),
+            };
+            messages_tx.send(Message::Notify(message));
+            Ok(())
+        });
+        Ok(())
+    }
+
+    /// Send the introspection query to a GraphQL recipe's endpoint, then
+    /// store the schema in the DB. The exchange isn't stored in history, so it
+    /// doesn't replace the recipe's latest response.
+    fn graphql_introspect(
+        &self,
+        request_config: RequestConfig,
+    ) -> anyhow::Result<()> {
+        let recipe = self.get_recipe(&request_config.recipe_id)?;
+        if !recipe.graphql {
+            bail!("Recipe `{}` is not a GraphQL recipe", recipe.id);
+        }
+        let recipe_id = recipe.id.clone();
+        let seed = RequestSeed::new(
+            recipe,
+            BuildOptions {
+                body: Some(Template::raw(INTROSPECTION_QUERY.into())),
+                ..request_config.options
+            },
+        );
+        let template_context =
+            self.template_context(request_config.profile_id, true)?;
+        let messages_tx = self.messages_tx();
+        self.spawn(async move {
+            let database = &template_context.database;
+            let ticket = TuiContext::get()
+                .http_engine
+                .build(seed, &template_context)
+                .await?;
+            let exchange = ticket.without_history().send(database).await?;
+            let status = exchange.response.status;
+            if !status.is_success() {
+                bail!("Introspection request failed with status {status}");
+            }
+            let schema = GraphQlSchema::from_introspection(
+                exchange.response.body.bytes(),
+            )?;
+            database.set_graphql_schema(&recipe_id, &schema)?;
+            messages_tx.send(Message::Notify(format!(
+                "Fetched GraphQL schema for `{recipe_id}` ({} types)",
+                schema.type_count()
+            )));
+            Ok(())
+        });
+        Ok(())
+    }
+
     /// Helper for spawning a fallible task. Any error in the resolved future
     /// will be shown to the user in a modal.
     fn spawn(
@@ -524,16 +1824,15 @@ impl Tui {
         } else {
             Box::new(PreviewPrompter)
         };
-        let collection = &self.collection_file.collection;
-
         Ok(TemplateContext {
             selected_profile: profile_id,
-            collection: collection.clone(),
+            collection: self.collection(),
             http_engine: Some(context.http_engine.clone()),
             database: self.database.clone(),
             overrides: Default::default(),
             prompter,
             recursion_count: Default::default(),
+            sensitive_values: Default::default(),
         })
     }
 }
@@ -558,13 +1857,23 @@ fn initialize_panic_handler() {
 
 /// Set up terminal for TUI
 fn initialize_terminal() -> anyhow::Result<Term> {
-    crossterm::terminal::enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    crossterm::execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
+    take_terminal()?;
+    let backend = CrosstermBackend::new(io::stdout());
     Ok(Terminal::new(backend)?)
 }
 
+/// Put the terminal in raw mode and switch to the alternate screen. Inverse of
+/// [restore_terminal]
+fn take_terminal() -> anyhow::Result<()> {
+    crossterm::terminal::enable_raw_mode()?;
+    crossterm::execute!(
+        io::stdout(),
+        EnterAlternateScreen,
+        EnableMouseCapture
+    )?;
+    Ok(())
+}
+
 /// Return terminal to initial state
 fn restore_terminal() -> anyhow::Result<()> {
     debug!("Restoring terminal");
@@ -576,3 +1885,17 @@ fn restore_terminal() -> anyhow::Result<()> {
     )?;
     Ok(())
 }
+
+/// Canonicalize the parent directory of a path, so paths from the file watcher
+/// can be compared to collection paths. The file itself isn't canonicalized
+/// because it may not exist (e.g. a missing `.env` file, or mid-save)
+fn normalize_path(path: impl AsRef<Path>) -> PathBuf {
+    let path = path.as_ref();
+    match (
+        path.parent().and_then(|parent| parent.canonicalize().ok()),
+        path.file_name(),
+    ) {
+        (Some(parent), Some(file_name)) => parent.join(file_name),
+        _ => path.to_owned(),
+    }
+}
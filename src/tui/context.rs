@@ -1,7 +1,10 @@
 use crate::{
     config::Config,
     http::HttpEngine,
-    tui::{input::InputEngine, view::Styles},
+    tui::{
+        input::InputEngine,
+        view::{Styles, Theme},
+    },
 };
 use std::sync::OnceLock;
 
@@ -31,9 +34,11 @@ pub struct TuiContext {
 
 impl TuiContext {
     /// Initialize global context. Should be called only once, during startup.
-    pub fn init(config: Config) {
+    /// The theme is loaded separately from the config, so that errors loading
+    /// it can be shown in the TUI.
+    pub fn init(config: Config, theme: &Theme) {
         INSTANCE
-            .set(Self::new(config))
+            .set(Self::new(config, theme))
             .expect("Global context is already initialized");
     }
 
@@ -41,11 +46,12 @@ impl TuiContext {
     /// and if the context is already initialized, do nothing.
     #[cfg(test)]
     pub fn init_test() {
-        INSTANCE.get_or_init(|| Self::new(Config::default()));
+        INSTANCE
+            .get_or_init(|| Self::new(Config::default(), &Theme::default()));
     }
 
-    fn new(config: Config) -> Self {
-        let styles = Styles::new(&config.theme);
+    fn new(config: Config, theme: &Theme) -> Self {
+        let styles = Styles::new(theme);
         let input_engine = InputEngine::new(config.input_bindings.clone());
         let http_engine = HttpEngine::new(&config);
         Self {
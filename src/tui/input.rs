@@ -17,11 +17,21 @@ use serde::{Deserialize, Serialize};
 use std::{
     borrow::Cow,
     fmt::{self, Debug},
-    iter,
+    future, iter,
     str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Duration,
 };
+use tokio::time;
 use tracing::trace;
 
+/// If a multi-key sequence isn't continued within this long, the held keys are
+/// handled on their own
+const SEQUENCE_TIMEOUT: Duration = Duration::from_secs(1);
+
 /// Top-level input manager. This handles things like bindings and mapping
 /// events to actions, but then the actions are actually processed by the view.
 #[derive(Debug)]
@@ -33,6 +43,12 @@ pub struct InputEngine {
     /// but keying by action at least allows us to look up action=>binding for
     /// help text.
     bindings: IndexMap<Action, InputBinding>,
+    /// Keys pressed so far in a multi-key sequence that hasn't been completed
+    /// yet. Empty when we're not in the middle of a sequence.
+    pending: Mutex<Vec<KeyEvent>>,
+    /// Is a text box focused? While typing, keys are handled one at a time so
+    /// multi-key sequences can't swallow text. Set by the view on each draw.
+    text_input: AtomicBool,
 }
 
 impl InputEngine {
@@ -118,13 +134,40 @@ impl InputEngine {
     /// spawned in its own task.
     pub async fn input_loop(&self, messages_tx: MessageSender) {
         let mut stream = EventStream::new();
-        while let Some(result) = stream.next().await {
-            // Failure to read input is both weird and fatal, so panic
-            let event = result.expect("Error reading terminal input");
-            self.handle_event(&messages_tx, event);
+        loop {
+            // If we're partway through a sequence, give up on it if the next
+            // key doesn't come soon, so the held keys aren't stuck
+            let has_pending =
+                !self.pending.lock().expect("Lock poisoned").is_empty();
+            let timeout = async {
+                if has_pending {
+                    time::sleep(SEQUENCE_TIMEOUT).await
+                } else {
+                    future::pending().await
+                }
+            };
+            tokio::select! {
+                result = stream.next() => {
+                    let Some(result) = result else { break };
+                    // Failure to read input is both weird and fatal, so panic
+                    let event = result.expect("Error reading terminal input");
+                    self.handle_event(&messages_tx, event);
+                }
+                () = timeout => {
+                    let mut pending =
+                        self.pending.lock().expect("Lock poisoned");
+                    self.flush_pending(&messages_tx, &mut pending);
+                }
+            }
         }
     }
 
+    /// Tell the engine whether a text box is focused. This should be reset
+    /// before each draw, then set by whichever text box has focus.
+    pub fn set_text_input(&self, text_input: bool) {
+        self.text_input.store(text_input, Ordering::Relaxed);
+    }
+
     /// Get a map of all available bindings
     pub fn bindings(&self) -> &IndexMap<Action, InputBinding> {
         &self.bindings
@@ -136,6 +179,14 @@ impl InputEngine {
         self.bindings.get(&action)
     }
 
+    /// Build a key event that triggers the given action, for simulating
+    /// input. Return `None` if the action is unbound
+    pub fn key_event(&self, action: Action) -> Option<Event> {
+        // For a sequence, the final key is the one that carries the action
+        let combo = self.binding(action)?.0.first()?.0.last()?;
+        Some(Event::Key(KeyEvent::new(combo.code, combo.modifiers)))
+    }
+
     /// Append a hotkey hint to a label. If the given action is bound, adding
     /// a hint to the end of the given label. If unbound, return the label
     /// alone.
@@ -168,10 +219,7 @@ impl InputEngine {
 
             Event::Key(key) => {
                 // Scan all bindings for a match
-                self.bindings
-                    .iter()
-                    .find(|(_, binding)| binding.matches(key))
-                    .map(|(action, _)| *action)
+                self.sequence_match(&[*key]).action()
             }
             _ => None,
         };
@@ -183,6 +231,31 @@ impl InputEngine {
         action
     }
 
+    /// Check a series of key presses against every binding. If the keys
+    /// complete one sequence but are also the start of a longer one, the
+    /// match is partial, so the longer sequence isn't shadowed. The completed
+    /// action is kept as a fallback in case the longer sequence is broken.
+    fn sequence_match(&self, keys: &[KeyEvent]) -> SequenceMatch<Action> {
+        let mut complete = None;
+        let mut partial = false;
+        for (action, binding) in &self.bindings {
+            for sequence in &binding.0 {
+                match sequence.matches(keys) {
+                    SequenceMatch::Complete(()) => {
+                        complete.get_or_insert(*action);
+                    }
+                    SequenceMatch::Partial(_) => partial = true,
+                    SequenceMatch::None => {}
+                }
+            }
+        }
+        match (complete, partial) {
+            (complete, true) => SequenceMatch::Partial(complete),
+            (Some(action), false) => SequenceMatch::Complete(action),
+            (None, false) => SequenceMatch::None,
+        }
+    }
+
     /// Given an input event, generate and queue a corresponding message. Some
     /// events will *not* generate a message, because they shouldn't get
     /// handled by components. This could be because they're just useless and
@@ -199,15 +272,119 @@ impl InputEngine {
                     kind: KeyEventKind::Release,
                     ..
                 })
+                // Left button down/drag are kept for resizing panes
                 | Event::Mouse(MouseEvent {
-                    kind: MouseEventKind::Down(_)
-                    | MouseEventKind::Drag(_)
+                    kind: MouseEventKind::Down(
+                        MouseButton::Right | MouseButton::Middle
+                    )
+                    | MouseEventKind::Drag(
+                        MouseButton::Right | MouseButton::Middle
+                    )
                     | MouseEventKind::Moved,
                     ..
                 })
         ) {
+            let mut pending = self.pending.lock().expect("Lock poisoned");
+            if self.text_input.load(Ordering::Relaxed) {
+                // Typing text, so every key stands on its own
+                self.flush_pending(messages_tx, &mut pending);
+                let action = self.action(&event);
+                messages_tx.send(Message::Input { event, action });
+            } else {
+                self.handle_key_sequence(messages_tx, &mut pending, event);
+            }
+        }
+    }
+
+    /// Track key presses that make up multi-key sequences. Keys that start a
+    /// sequence are held back until the sequence is either completed, in
+    /// which case only its action is sent, or broken, in which case the held
+    /// keys are sent individually so nothing typed is lost.
+    fn handle_key_sequence(
+        &self,
+        messages_tx: &MessageSender,
+        pending: &mut Vec<KeyEvent>,
+        event: Event,
+    ) {
+        let Event::Key(key) = event else {
+            // Anything other than a key press breaks the sequence
+            self.flush_pending(messages_tx, pending);
             let action = self.action(&event);
             messages_tx.send(Message::Input { event, action });
+            return;
+        };
+
+        pending.push(key);
+        match self.sequence_match(pending) {
+            SequenceMatch::Complete(action) => {
+                trace!(?action, "Input action");
+                pending.clear();
+                messages_tx.send(Message::Input {
+                    event,
+                    action: Some(action),
+                });
+            }
+            // Wait for the next key
+            SequenceMatch::Partial(_) => {}
+            SequenceMatch::None if pending.len() == 1 => {
+                pending.clear();
+                messages_tx.send(Message::Input {
+                    event,
+                    action: None,
+                });
+            }
+            SequenceMatch::None => {
+                // The held keys didn't go anywhere. Send them on their own,
+                // then start over with this key, which may begin a new sequence
+                pending.pop();
+                self.flush_pending(messages_tx, pending);
+                self.handle_key_sequence(messages_tx, pending, event);
+            }
+        }
+    }
+
+    /// Send the held keys of an incomplete sequence. If some of the held keys
+    /// complete a shorter sequence, they're sent as that sequence's action.
+    /// Any other keys are sent as their own events.
+    fn flush_pending(
+        &self,
+        messages_tx: &MessageSender,
+        pending: &mut Vec<KeyEvent>,
+    ) {
+        while !pending.is_empty() {
+            // Prefer the longest sequence that the held keys start with
+            let completed = (1..=pending.len()).rev().find_map(|len| {
+                let action = self.sequence_match(&pending[..len]).action()?;
+                Some((len, Some(action)))
+            });
+            let (len, action) = completed.unwrap_or((1, None));
+            let event = Event::Key(pending[len - 1]);
+            pending.drain(..len);
+            messages_tx.send(Message::Input { event, action });
+        }
+    }
+}
+
+/// Result of checking key presses against a key sequence
+#[derive(Debug)]
+enum SequenceMatch<T> {
+    /// The keys make up the entire sequence
+    Complete(T),
+    /// The keys are the start of the sequence, but more are needed. If the
+    /// keys also complete a shorter sequence, its value is included.
+    Partial(Option<T>),
+    None,
+}
+
+impl SequenceMatch<Action> {
+    /// Get the action for a completed sequence, even if a longer sequence
+    /// could still be completed
+    fn action(self) -> Option<Action> {
+        match self {
+            Self::Complete(action) | Self::Partial(Some(action)) => {
+                Some(action)
+            }
+            Self::Partial(None) | Self::None => None,
         }
     }
 }
@@ -230,11 +407,57 @@ impl Default for InputEngine {
                     code: KeyCode::Right,
                     modifiers: KeyModifiers::SHIFT,
                 }.into(),
+                Action::MoveUp => KeyCombination {
+                    code: KeyCode::Up,
+                    modifiers: KeyModifiers::SHIFT,
+                }.into(),
+                Action::MoveDown => KeyCombination {
+                    code: KeyCode::Down,
+                    modifiers: KeyModifiers::SHIFT,
+                }.into(),
+                // Modified arrow keys have to come before the plain ones,
+                // because a binding matches even with extra modifiers
+                Action::ShrinkSidebar => KeyCombination {
+                    code: KeyCode::Left,
+                    modifiers: KeyModifiers::CONTROL,
+                }.into(),
+                Action::GrowSidebar => KeyCombination {
+                    code: KeyCode::Right,
+                    modifiers: KeyModifiers::CONTROL,
+                }.into(),
+                Action::ShrinkRecipePane => KeyCombination {
+                    code: KeyCode::Up,
+                    modifiers: KeyModifiers::CONTROL,
+                }.into(),
+                Action::GrowRecipePane => KeyCombination {
+                    code: KeyCode::Down,
+                    modifiers: KeyModifiers::CONTROL,
+                }.into(),
                 Action::OpenActions => KeyCode::Char('x').into(),
+                Action::CommandPalette => KeyCombination {
+                    code: KeyCode::Char('p'),
+                    modifiers: KeyModifiers::CONTROL,
+                }.into(),
                 Action::OpenHelp => KeyCode::Char('?').into(),
+                Action::MessageLog => KeyCode::Char('m').into(),
                 Action::Fullscreen => KeyCode::Char('f').into(),
+                Action::CycleLayout => KeyCombination {
+                    code: KeyCode::Char('l'),
+                    modifiers: KeyModifiers::CONTROL,
+                }.into(),
                 Action::ReloadCollection => KeyCode::F(5).into(),
                 Action::History => KeyCode::Char('h').into(),
+                Action::EditBody => KeyCode::Char('e').into(),
+                Action::EditNote => KeyCode::Char('n').into(),
+                Action::InspectChain => KeyCode::Char('i').into(),
+                Action::TogglePin => KeyCode::Char('b').into(),
+                Action::DiffGolden => KeyCode::Char('d').into(),
+                Action::PreviousPin => KeyCode::Char('[').into(),
+                Action::NextPin => KeyCode::Char(']').into(),
+                Action::ToggleWrap => KeyCode::Char('w').into(),
+                Action::ToggleLineNumbers => KeyCode::Char('#').into(),
+                Action::CancelRequest => KeyCode::Delete.into(),
+                Action::Toggle => KeyCode::Char(' ').into(),
                 Action::Search => KeyCode::Char('/').into(),
                 Action::PreviousPane => KeyCode::BackTab.into(),
                 Action::NextPane => KeyCode::Tab.into(),
@@ -254,6 +477,8 @@ impl Default for InputEngine {
                 Action::SelectResponse => KeyCode::Char('r').into(),
                 // ^^^^^ If making changes, make sure to update the docs ^^^^^
             },
+            pending: Mutex::default(),
+            text_input: AtomicBool::default(),
         }
     }
 }
@@ -316,6 +541,47 @@ pub enum Action {
     Cancel,
     /// Browse request history
     History,
+    /// Edit the request body in an external editor, then send the request
+    #[display("Edit Body & Send")]
+    EditBody,
+    /// Edit the note for the selected recipe or exchange in an external
+    /// editor
+    #[display("Edit Note")]
+    EditNote,
+    /// Show the definition and latest value of the chains referenced by the
+    /// focused template
+    #[display("Inspect Chain")]
+    InspectChain,
+    /// Pin the displayed exchange as a tab, or unpin it if already pinned
+    #[display("Pin/Unpin Response")]
+    TogglePin,
+    /// Show the previous pinned exchange
+    #[display("Prev Pin")]
+    PreviousPin,
+    /// Show the next pinned exchange
+    #[display("Next Pin")]
+    NextPin,
+    /// Compare the latest response for the selected recipe to its golden
+    /// response
+    #[display("Diff Against Golden")]
+    DiffGolden,
+    /// Move the selected recipe/folder up within its folder
+    #[display("Move Up")]
+    MoveUp,
+    /// Move the selected recipe/folder down within its folder
+    #[display("Move Down")]
+    MoveDown,
+    /// Soft wrap long lines in the focused body view
+    #[display("Toggle Word Wrap")]
+    ToggleWrap,
+    /// Show/hide line numbers in the focused body view
+    #[display("Toggle Line Numbers")]
+    ToggleLineNumbers,
+    /// Cancel an in-progress request
+    #[display("Cancel Request")]
+    CancelRequest,
+    /// Check/uncheck the highlighted item in a list
+    Toggle,
     /// Start a search/filter operation
     #[display("Search/Filter")]
     Search,
@@ -324,12 +590,33 @@ pub enum Action {
     ReloadCollection,
     /// Embiggen a pane
     Fullscreen,
+    /// Switch to the next pane layout preset
+    #[display("Cycle Layout")]
+    CycleLayout,
+    /// Make the recipe list column narrower
+    #[display("Shrink Sidebar")]
+    ShrinkSidebar,
+    /// Make the recipe list column wider
+    #[display("Grow Sidebar")]
+    GrowSidebar,
+    /// Give less space to the recipe pane, and more to the exchange pane
+    #[display("Shrink Recipe Pane")]
+    ShrinkRecipePane,
+    /// Give more space to the recipe pane, and less to the exchange pane
+    #[display("Grow Recipe Pane")]
+    GrowRecipePane,
     /// Open the actions modal
     #[display("Actions")]
     OpenActions,
+    /// Open the command palette, to search all actions and recipes
+    #[display("Command Palette")]
+    CommandPalette,
     #[display("Help")]
     /// Open the help modal
     OpenHelp,
+    /// Open the log of past notifications and errors
+    #[display("Message Log")]
+    MessageLog,
     /// Select profile list pane
     SelectProfileList,
     /// Select recipe list pane
@@ -366,26 +653,19 @@ impl Action {
     }
 }
 
-/// One or more key combinations, which should correspond to a single action
+/// One or more key sequences, which should correspond to a single action
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(transparent)]
-pub struct InputBinding(Vec<KeyCombination>);
-
-impl InputBinding {
-    /// Does a key event contain this key combo?
-    fn matches(&self, event: &KeyEvent) -> bool {
-        self.0.iter().any(|combo| combo.matches(event))
-    }
-}
+pub struct InputBinding(Vec<KeySequence>);
 
 impl Display for InputBinding {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for (i, combo) in self.0.iter().enumerate() {
+        for (i, sequence) in self.0.iter().enumerate() {
             if i > 0 {
                 write!(f, ",")?;
             }
-            write!(f, "{}", combo)?;
+            write!(f, "{}", sequence)?;
         }
         Ok(())
     }
@@ -393,7 +673,7 @@ impl Display for InputBinding {
 
 impl From<KeyCombination> for InputBinding {
     fn from(combo: KeyCombination) -> Self {
-        Self(vec![combo])
+        Self(vec![combo.into()])
     }
 }
 
@@ -403,10 +683,96 @@ impl From<KeyCode> for InputBinding {
     }
 }
 
-/// Key input sequence, which can trigger an action
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+/// One or more key combinations that must be pressed in order to trigger an
+/// action, e.g. `g g`. Most sequences are just a single combination.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(into = "String", try_from = "String")]
+pub struct KeySequence(Vec<KeyCombination>);
+
+impl KeySequence {
+    /// Check if a series of key presses completes this sequence, or at least
+    /// starts it
+    fn matches(&self, keys: &[KeyEvent]) -> SequenceMatch<()> {
+        if keys.len() > self.0.len()
+            || !iter::zip(&self.0, keys).all(|(combo, key)| combo.matches(key))
+        {
+            SequenceMatch::None
+        } else if keys.len() == self.0.len() {
+            SequenceMatch::Complete(())
+        } else {
+            SequenceMatch::Partial(None)
+        }
+    }
+}
+
+impl Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.iter().format(" "))
+    }
+}
+
+impl From<KeyCombination> for KeySequence {
+    fn from(combo: KeyCombination) -> Self {
+        Self(vec![combo])
+    }
+}
+
+impl From<KeyCode> for KeySequence {
+    fn from(key_code: KeyCode) -> Self {
+        KeyCombination::from(key_code).into()
+    }
+}
+
+impl FromStr for KeySequence {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Modifiers and key codes are both space-separated, so group tokens
+        // into combinations by ending each group at a key code. Invalid tokens
+        // stay in the current group, so the error message reflects their
+        // position (e.g. a typo'd modifier)
+        let mut tokens = s
+            .split(KeyCombination::SEPARATOR)
+            .filter(|s| !s.is_empty())
+            .peekable();
+        let mut groups: Vec<Vec<&str>> = vec![Vec::new()];
+        while let Some(token) = tokens.next() {
+            groups.last_mut().unwrap().push(token);
+            if tokens.peek().is_some()
+                && parse_key_modifier(token).is_err()
+                && parse_key_code(token).is_ok()
+            {
+                groups.push(Vec::new());
+            }
+        }
+        let combos = groups
+            .into_iter()
+            .map(|group| group.join(" ").parse())
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self(combos))
+    }
+}
+
+/// For serialization
+impl From<KeySequence> for String {
+    fn from(sequence: KeySequence) -> Self {
+        sequence.0.into_iter().map(String::from).join(" ")
+    }
+}
+
+/// For deserialization
+impl TryFrom<String> for KeySequence {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// A single key press, with modifiers
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
 pub struct KeyCombination {
     code: KeyCode,
     modifiers: KeyModifiers,
@@ -443,8 +809,8 @@ impl Display for KeyCombination {
             KeyCode::F(num) => write!(f, "F{}", num),
             KeyCode::Char(' ') => write!(f, "<space>"),
             KeyCode::Char(c) => write!(f, "{c}"),
-            // Punting on everything else until we need it
-            _ => write!(f, "???"),
+            // Everything else is rare enough that the config name is fine
+            code => write!(f, "<{}>", stringify_key_code(code)),
         }
     }
 }
@@ -506,15 +872,6 @@ impl From<KeyCombination> for String {
     }
 }
 
-/// For deserialization
-impl TryFrom<String> for KeyCombination {
-    type Error = anyhow::Error;
-
-    fn try_from(value: String) -> Result<Self, Self::Error> {
-        value.parse()
-    }
-}
-
 /// Parse a plain key code
 fn parse_key_code(s: &str) -> anyhow::Result<KeyCode> {
     // Check for plain char code
@@ -628,6 +985,14 @@ mod tests {
         mouse_event(MouseEventKind::ScrollRight),
         Some(Action::ScrollRight)
     )]
+    #[case::mouse_down_left(
+        mouse_event(MouseEventKind::Down(MouseButton::Left)),
+        None
+    )]
+    #[case::mouse_drag_left(
+        mouse_event(MouseEventKind::Drag(MouseButton::Left)),
+        None
+    )]
     #[case::paste(Event::Paste("hello!".into()), None)]
     fn test_handle_event_queued(
         mut harness: TestHarness,
@@ -650,8 +1015,8 @@ mod tests {
     #[case::focus_lost(Event::FocusLost)]
     #[case::resize(Event::Resize(10, 10))]
     #[case::key_release(key_event(KeyEventKind::Release, KeyCode::Enter))]
-    #[case::mouse_down(mouse_event(MouseEventKind::Down(MouseButton::Left)))]
-    #[case::mouse_drag(mouse_event(MouseEventKind::Drag(MouseButton::Left)))]
+    #[case::mouse_down(mouse_event(MouseEventKind::Down(MouseButton::Right)))]
+    #[case::mouse_drag(mouse_event(MouseEventKind::Drag(MouseButton::Right)))]
     #[case::mouse_move(mouse_event(MouseEventKind::Moved))]
     fn test_handle_event_killed(
         mut harness: TestHarness,
@@ -716,6 +1081,140 @@ mod tests {
         assert_err!(input.parse::<KeyCombination>(), expected_error);
     }
 
+    #[rstest]
+    #[case::single("ctrl s", vec![KeyCombination {
+        code: KeyCode::Char('s'),
+        modifiers: KeyModifiers::CONTROL,
+    }])]
+    #[case::repeated("g g", vec![KeyCode::Char('g').into(), KeyCode::Char('g').into()])]
+    #[case::modifiers("ctrl x ctrl s", vec![
+        KeyCombination {
+            code: KeyCode::Char('x'),
+            modifiers: KeyModifiers::CONTROL,
+        },
+        KeyCombination {
+            code: KeyCode::Char('s'),
+            modifiers: KeyModifiers::CONTROL,
+        },
+    ])]
+    #[case::shift_tab("shift tab  f2", vec![KeyCode::BackTab.into(), KeyCode::F(2).into()])]
+    fn test_parse_key_sequence(
+        #[case] input: &str,
+        #[case] expected: Vec<KeyCombination>,
+    ) {
+        assert_eq!(
+            input.parse::<KeySequence>().unwrap(),
+            KeySequence(expected)
+        );
+    }
+
+    #[rstest]
+    #[case::empty("", "Empty key combination")]
+    #[case::invalid_modifier("g shart w", "Invalid key modifier \"shart\"")]
+    #[case::invalid_code("g nope", "Invalid key code \"nope\"")]
+    #[case::modifier_last("g shift", "Invalid key code \"shift\"")]
+    fn test_parse_key_sequence_error(
+        #[case] input: &str,
+        #[case] expected_error: &str,
+    ) {
+        assert_err!(input.parse::<KeySequence>(), expected_error);
+    }
+
+    /// Keys that start a sequence are held until the sequence is complete. If
+    /// the sequence is broken, the held keys are sent individually
+    #[rstest]
+    fn test_handle_event_sequence(mut harness: TestHarness) {
+        let engine = InputEngine::new(indexmap! {
+            Action::Home => InputBinding(vec!["g g".parse().unwrap()]),
+        });
+        let press = |code| key_event(KeyEventKind::Press, code);
+        let assert_message =
+            |harness: &mut TestHarness, code, expected_action| {
+                let (event, action) = assert_matches!(
+                    harness.pop_message_now(),
+                    Message::Input { event, action } => (event, action),
+                );
+                assert_eq!(event, press(code));
+                assert_eq!(action, expected_action);
+            };
+
+        engine.handle_event(harness.messages_tx(), press(KeyCode::Char('g')));
+        harness.assert_messages_empty();
+        engine.handle_event(harness.messages_tx(), press(KeyCode::Char('g')));
+        assert_message(&mut harness, KeyCode::Char('g'), Some(Action::Home));
+
+        // Broken sequence
+        engine.handle_event(harness.messages_tx(), press(KeyCode::Char('g')));
+        engine.handle_event(harness.messages_tx(), press(KeyCode::Enter));
+        assert_message(&mut harness, KeyCode::Char('g'), None);
+        assert_message(&mut harness, KeyCode::Enter, Some(Action::Submit));
+        harness.assert_messages_empty();
+    }
+
+    /// A key that's bound on its own doesn't shadow longer sequences that
+    /// start with it. It's held until the sequence is completed or broken.
+    #[rstest]
+    fn test_handle_event_sequence_prefix(mut harness: TestHarness) {
+        let engine = InputEngine::new(indexmap! {
+            Action::End => InputBinding(vec!["g".parse().unwrap()]),
+            Action::Home => InputBinding(vec!["g g".parse().unwrap()]),
+        });
+        let press = |code| key_event(KeyEventKind::Press, code);
+        let assert_message =
+            |harness: &mut TestHarness, code, expected_action| {
+                let (event, action) = assert_matches!(
+                    harness.pop_message_now(),
+                    Message::Input { event, action } => (event, action),
+                );
+                assert_eq!(event, press(code));
+                assert_eq!(action, expected_action);
+            };
+
+        // Longer sequence wins
+        engine.handle_event(harness.messages_tx(), press(KeyCode::Char('g')));
+        harness.assert_messages_empty();
+        engine.handle_event(harness.messages_tx(), press(KeyCode::Char('g')));
+        assert_message(&mut harness, KeyCode::Char('g'), Some(Action::Home));
+
+        // Broken sequence falls back to the shorter one
+        engine.handle_event(harness.messages_tx(), press(KeyCode::Char('g')));
+        engine.handle_event(harness.messages_tx(), press(KeyCode::Enter));
+        assert_message(&mut harness, KeyCode::Char('g'), Some(Action::End));
+        assert_message(&mut harness, KeyCode::Enter, Some(Action::Submit));
+
+        // Timing out does too
+        engine.handle_event(harness.messages_tx(), press(KeyCode::Char('g')));
+        harness.assert_messages_empty();
+        engine.flush_pending(
+            harness.messages_tx(),
+            &mut engine.pending.lock().unwrap(),
+        );
+        assert_message(&mut harness, KeyCode::Char('g'), Some(Action::End));
+        harness.assert_messages_empty();
+    }
+
+    /// Keys aren't held for sequences while typing in a text box
+    #[rstest]
+    fn test_handle_event_sequence_text_input(mut harness: TestHarness) {
+        let engine = InputEngine::new(indexmap! {
+            Action::Home => InputBinding(vec!["g g".parse().unwrap()]),
+        });
+        let press = |code| key_event(KeyEventKind::Press, code);
+        engine.set_text_input(true);
+
+        for _ in 0..2 {
+            engine
+                .handle_event(harness.messages_tx(), press(KeyCode::Char('g')));
+            let (event, action) = assert_matches!(
+                harness.pop_message_now(),
+                Message::Input { event, action } => (event, action),
+            );
+            assert_eq!(event, press(KeyCode::Char('g')));
+            assert_eq!(action, None);
+        }
+        harness.assert_messages_empty();
+    }
+
     /// Test stringifying/parsing key codes
     #[test]
     fn test_key_code() {
@@ -804,6 +1303,17 @@ mod tests {
                 Token::SeqEnd,
             ],
         );
+        assert_de_tokens(
+            &InputBinding(vec![KeySequence(vec![
+                KeyCode::Char('g').into(),
+                KeyCode::Char('g').into(),
+            ])]),
+            &[
+                Token::Seq { len: Some(1) },
+                Token::Str("g g"),
+                Token::SeqEnd,
+            ],
+        );
 
         assert_de_tokens_error::<InputBinding>(
             &[Token::Seq { len: Some(1) }, Token::Str("no"), Token::SeqEnd],
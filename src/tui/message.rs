@@ -2,15 +2,22 @@
 //! state updates.
 
 use crate::{
-    collection::{Collection, ProfileId, RecipeId},
+    collection::{
+        ChainId, CollectionFile, OAuth2, ProfileId, Recipe, RecipeId,
+    },
     http::{
-        BuildOptions, Exchange, RequestBuildError, RequestError, RequestRecord,
+        BuildOptions, EventStream, Exchange, OAuth2Token, RequestBuildError,
+        RequestError, RequestId, RequestRecord, ResponseRecord, SelectorType,
+    },
+    template::{FilePrompt, Prompt, Prompter, Template, TemplateChunk, Trace},
+    tui::{
+        input::Action,
+        view::{Confirm, PrettyBody},
     },
-    template::{Prompt, Prompter, Template, TemplateChunk},
-    tui::{input::Action, view::Confirm},
     util::ResultExt,
 };
 use anyhow::Context;
+use chrono::{DateTime, Local};
 use derive_more::From;
 use std::sync::{Arc, OnceLock};
 use tokio::sync::mpsc::UnboundedSender;
@@ -44,6 +51,10 @@ impl Prompter for MessageSender {
     fn prompt(&self, prompt: Prompt) {
         self.send(Message::PromptStart(prompt));
     }
+
+    fn prompt_file(&self, prompt: FilePrompt) {
+        self.send(Message::FilePromptStart(prompt));
+    }
 }
 
 /// A message triggers some *asynchronous* action. Most state modifications can
@@ -55,14 +66,23 @@ pub enum Message {
     /// Trigger collection reload
     CollectionStartReload,
     /// Store a reloaded collection value in state
-    CollectionEndReload(Collection),
+    CollectionEndReload(Box<CollectionFile>),
     /// Open the collection in the user's editor
     CollectionEdit,
+    /// Guide the user through a find/replace in the collection file
+    CollectionFindReplace,
 
     /// Show a yes/no confirmation to the user. Use the included channel to
     /// return the value.
     ConfirmStart(Confirm),
 
+    /// Render a completed exchange as a Markdown report, then copy it
+    CopyExchangeReport(RequestId),
+    /// Render a completed exchange as a Markdown report, then save it to a
+    /// file
+    SaveExchangeReport(RequestId),
+    /// Export a completed exchange as a HAR file, then save it
+    ExportExchangeHar(RequestId),
     /// Render request URL from a recipe, then copy rendered URL
     CopyRequestUrl(RequestConfig),
     /// Render request body from a recipe, then copy rendered text
@@ -72,6 +92,58 @@ pub enum Message {
     /// Copy some text to the clipboard
     CopyText(String),
 
+    /// Open the request body from a recipe in the user's editor, then send
+    /// the request with the edited body
+    EditRequestBody(RequestConfig),
+    /// Open the note attached to a recipe in the user's editor, then save the
+    /// edited note
+    EditRecipeNote(RecipeId),
+    /// Open the note attached to an exchange in the user's editor, then save
+    /// the edited note
+    EditExchangeNote(RequestId),
+
+    /// Build a request without sending it, and store the result for display.
+    /// Errors are stored rather than shown to the user
+    RequestPreview {
+        request_config: RequestConfig,
+        destination: Arc<OnceLock<RequestPreviewResult>>,
+    },
+    /// Render request with tracing enabled, then show how each template was
+    /// rendered
+    ExplainRequest(RequestConfig),
+    /// Show the render trace from [Self::ExplainRequest]
+    ExplainShow { recipe_id: RecipeId, trace: Trace },
+    /// Open the chain inspector for the given chains, in order. `None` to
+    /// show every chain in the collection
+    ChainInspect {
+        chain_ids: Option<Vec<ChainId>>,
+        profile_id: Option<ProfileId>,
+    },
+    /// Clear a chain's cached value, then resolve it again in the background.
+    /// The result is recorded in the chain log
+    ChainResolve {
+        chain_id: ChainId,
+        profile_id: Option<ProfileId>,
+    },
+
+    /// Run the introspection query against a GraphQL recipe's endpoint, then
+    /// store the schema for query completion
+    GraphqlIntrospect(RequestConfig),
+    /// Open the GraphQL query editor for a recipe
+    GraphqlEditStart(RequestConfig),
+
+    /// Ask the user for a name, then mark an exchange as the golden exchange
+    /// for its recipe
+    GoldenMarkStart(RequestId),
+    /// Mark an exchange as the golden exchange for its recipe
+    GoldenMark { request_id: RequestId, name: String },
+    /// Compare the latest exchange for a recipe+profile to the recipe's golden
+    /// exchange
+    GoldenDiff {
+        profile_id: Option<ProfileId>,
+        recipe_id: RecipeId,
+    },
+
     /// An error occurred in some async process and should be shown to the user
     Error { error: anyhow::Error },
 
@@ -79,12 +151,56 @@ pub enum Message {
     HttpBeginRequest(RequestConfig),
     /// Request failed to build
     HttpBuildError { error: RequestBuildError },
-    /// We launched the HTTP request
-    HttpLoading { request: Arc<RequestRecord> },
+    /// We launched the HTTP request. `queued` is set while it waits for a
+    /// free slot under the concurrency limit
+    HttpLoading {
+        request: Arc<RequestRecord>,
+        queued: bool,
+    },
+    /// The response is an event stream, and new data has arrived on it. This
+    /// is sent repeatedly until the stream closes
+    HttpStreaming {
+        request: Arc<RequestRecord>,
+        stream: Arc<EventStream>,
+    },
     /// The HTTP request either succeeded or failed. We don't need to store the
     /// recipe ID here because it's in the inner container already. Combining
     /// these two cases saves a bit of boilerplate.
     HttpComplete(Result<Exchange, RequestError>),
+    /// Cancel an in-progress request. The request is reported as failed
+    HttpCancel(RequestId),
+    /// Parse a response body, then query and prettify it for display. This
+    /// can be slow for large bodies, so it's done on a background thread. The
+    /// result is written to the destination, and a placeholder is shown until
+    /// then.
+    HttpPrettifyBody {
+        response: Arc<ResponseRecord>,
+        selector_type: SelectorType,
+        query: Option<String>,
+        destination: Arc<OnceLock<PrettyBody>>,
+    },
+
+    /// Start re-sending a request on an interval. If the same recipe and
+    /// profile are already being watched, stop instead
+    ToggleWatch(RequestConfig),
+
+    /// Prompt the user for when to send a request, then schedule it
+    ScheduleStart(RequestConfig),
+    /// Send a request at a later time
+    ScheduleAdd {
+        request_config: RequestConfig,
+        send_at: DateTime<Local>,
+    },
+    /// Open a modal listing pending scheduled requests
+    ScheduleOpen,
+    /// Remove a pending scheduled request, so it never gets sent
+    ScheduleCancel(ScheduleId),
+
+    /// Send every recipe in a folder, one at a time, then show a report
+    RunFolder {
+        folder_id: RecipeId,
+        profile_id: Option<ProfileId>,
+    },
 
     /// User input from the terminal
     Input {
@@ -99,10 +215,87 @@ pub enum Message {
     /// Show a prompt to the user, asking for some input. Use the included
     /// channel to return the value.
     PromptStart(Prompt),
+    /// Show a file browser to the user. Use the included channel to return
+    /// the selected path.
+    FilePromptStart(FilePrompt),
+
+    /// Open the editor to override fields in a profile. Overrides only last
+    /// for the current session
+    ProfileOverrideStart(ProfileId),
+    /// Override a profile field for the rest of the session, without
+    /// modifying the collection file
+    ProfileOverride {
+        profile_id: ProfileId,
+        field: String,
+        value: Template,
+    },
+    /// Remove the override for a single profile field, restoring its value
+    /// from the collection file
+    ProfileRemoveOverride {
+        profile_id: ProfileId,
+        field: String,
+    },
+    /// Remove all profile overrides, restoring values from the collection
+    /// file
+    ProfileClearOverrides,
 
     /// Exit the program
     Quit,
 
+    /// Prompt the user to create or edit the scratch request
+    ScratchStart,
+    /// Prompt the user for a curl command, then replace the scratch request
+    /// with it
+    ScratchCurlStart,
+    /// Replace the scratch request, then select it
+    ScratchUpdate(Box<Recipe>),
+    /// Prompt the user for an ID to save the scratch request under. The
+    /// included ID is the currently selected recipe, which must be the
+    /// scratch request
+    ScratchSaveStart(RecipeId),
+    /// Save the scratch request to the collection file under the given ID
+    ScratchSave(RecipeId),
+
+    /// Prompt the user for the fields of a new recipe
+    RecipeCreateStart,
+    /// Add a new recipe to the collection file, then select it
+    RecipeCreate(Box<Recipe>),
+    /// Duplicate a recipe in the collection file, then select the copy and
+    /// open the file for editing
+    RecipeDuplicate(RecipeId),
+    /// Prompt the user for a new ID for a recipe or folder
+    RecipeRenameStart(RecipeId),
+    /// Change the ID of a recipe or folder in the collection file, then
+    /// select it
+    RecipeRename {
+        recipe_id: RecipeId,
+        new_id: RecipeId,
+    },
+    /// Ask the user to confirm deleting a recipe
+    RecipeDeleteStart(RecipeId),
+    /// Remove a recipe from the collection file
+    RecipeDelete(RecipeId),
+    /// Prompt the user for a folder to move a recipe/folder into. The second
+    /// field is the current parent folder, offered as the default
+    RecipeMoveStart(RecipeId, Option<RecipeId>),
+    /// Move a recipe/folder into a folder (or the top level if `None`) in the
+    /// collection file, then select it
+    RecipeMove {
+        recipe_id: RecipeId,
+        folder_id: Option<RecipeId>,
+    },
+    /// Prompt the user for the ID of a new folder, to be created in the given
+    /// parent folder (or the top level if `None`)
+    FolderCreateStart(Option<RecipeId>),
+    /// Add an empty folder to the collection file, then select it
+    FolderCreate {
+        folder_id: RecipeId,
+        parent_id: Option<RecipeId>,
+    },
+    /// Select a recipe in the recipe list. Used to select a recipe after the
+    /// collection is reloaded
+    RecipeSelect(RecipeId),
+
     /// Save data to a file. Could be binary (e.g. image) or encoded text
     SaveFile {
         /// A suggestion for the file name. User will have the opportunity to
@@ -111,6 +304,14 @@ pub enum Message {
         /// Data to save
         data: Vec<u8>,
     },
+    /// Save the full body of a response to a file. Unlike [Self::SaveFile],
+    /// this can handle bodies that are too large to be held in memory
+    SaveResponseBody {
+        /// A suggestion for the file name. User will have the opportunity to
+        /// change this
+        default_path: Option<String>,
+        response: Arc<ResponseRecord>,
+    },
 
     /// Render a template string, to be previewed in the UI. Ideally this could
     /// be launched directly by the component that needs it, but only the
@@ -125,8 +326,28 @@ pub enum Message {
         profile_id: Option<ProfileId>,
         destination: Arc<OnceLock<Vec<TemplateChunk>>>,
     },
+
+    /// Open a WebSocket connection for a recipe. If a connection is already
+    /// open for the profile+recipe, it's left alone.
+    WebSocketConnect(RequestConfig),
+    /// A WebSocket connection changed state, e.g. it received a message. This
+    /// doesn't carry any data, it just triggers a redraw
+    WebSocketUpdated,
+
+    /// Look up the cached OAuth 2.0 token for some settings, to show its
+    /// status in the UI. Works the same as [Self::TemplatePreview]. `None`
+    /// will be written if there is no token yet.
+    OAuth2TokenPreview {
+        oauth2: OAuth2,
+        profile_id: Option<ProfileId>,
+        destination: Arc<OnceLock<Option<OAuth2Token>>>,
+    },
 }
 
+/// Result of building a request for preview. Errors are stringified, because
+/// they're just for display
+pub type RequestPreviewResult = Result<Arc<RequestRecord>, String>;
+
 /// Configuration that defines how to render a request
 #[derive(Clone, Debug)]
 #[cfg_attr(test, derive(PartialEq))]
@@ -135,3 +356,14 @@ pub struct RequestConfig {
     pub recipe_id: RecipeId,
     pub options: BuildOptions,
 }
+
+/// Unique ID for a scheduled request, within a session
+pub type ScheduleId = u32;
+
+/// A request that will be sent at a later time
+#[derive(Clone, Debug)]
+pub struct ScheduledRequest {
+    pub id: ScheduleId,
+    pub request_config: RequestConfig,
+    pub send_at: DateTime<Local>,
+}
@@ -2,11 +2,11 @@
 
 use crate::{
     db::CollectionDatabase,
-    test_util::Factory,
+    test_util::{assert_matches, Factory},
     tui::{
         context::TuiContext,
         message::{Message, MessageSender},
-        view::ViewContext,
+        view::{PrettyBody, ViewContext},
     },
 };
 use ratatui::{backend::TestBackend, Terminal};
@@ -84,6 +84,23 @@ impl TestHarness {
     pub fn clear_messages(&mut self) {
         while self.messages_rx.try_recv().is_ok() {}
     }
+
+    /// Pop the next message off the queue, which must be a request to
+    /// prettify a response body, and do the work immediately. Normally this
+    /// runs in a background task, but tests want it done synchronously.
+    pub fn prettify_body_now(&mut self) {
+        let (response, selector_type, query, destination) = assert_matches!(
+            self.pop_message_now(),
+            Message::HttpPrettifyBody {
+                response,
+                selector_type,
+                query,
+                destination,
+            } => (response, selector_type, query, destination),
+        );
+        let body = PrettyBody::new(response, selector_type, query.as_deref());
+        destination.set(body).unwrap();
+    }
 }
 
 /// Assert that the event queue matches the given list of patterns
@@ -2,17 +2,27 @@
 //! functionality is spun out into this module.
 
 use crate::{
-    template::Prompt,
+    collection::{
+        find_text, replace_text, Method, Recipe, RecipeBody, RecipeId,
+    },
+    http::{RequestId, ResponseRecord},
+    template::{Prompt, Template},
     tui::{
-        message::{Message, MessageSender},
+        message::{Message, MessageSender, RequestConfig},
         view::Confirm,
     },
-    util::ResultExt,
+    util::{parse_send_time, ResultExt},
 };
-use anyhow::Context;
+use anyhow::{anyhow, bail, Context};
+use chrono::Local;
 use futures::{future, FutureExt};
-use std::io;
-use tokio::{fs::OpenOptions, io::AsyncWriteExt, sync::oneshot};
+use indexmap::IndexMap;
+use std::{env, io, path::PathBuf, sync::Arc};
+use tokio::{
+    fs::{self, File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::oneshot,
+};
 use tracing::{debug, info, warn};
 
 /// Listen for any exit signals, and return `Ok(())` when any signal is
@@ -70,57 +80,14 @@ pub async fn save_file(
     default_path: Option<String>,
     data: Vec<u8>,
 ) -> anyhow::Result<()> {
-    // If the user closed the prompt, just exit
-    let Some(path) =
-        prompt(&messages_tx, "Enter a path for the file", default_path).await
+    let Some((path, mut file)) =
+        open_new_file(&messages_tx, default_path).await?
     else {
         return Ok(());
     };
 
-    // If the user input nothing, assume they just want to exit
-    if path.is_empty() {
-        return Ok(());
-    }
-
-    let result = {
-        // Attempt to open the file *if it doesn't exist already*
-        let result = OpenOptions::new()
-            .create_new(true)
-            .write(true)
-            .open(&path)
-            .await;
-
-        match result {
-            Ok(file) => Ok(file),
-            // If the file already exists, ask for confirmation to overwrite
-            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
-                warn!(path, "File already exists, asking to overwrite");
-
-                // Hi, sorry, follow up question. Are you sure?
-                if confirm(
-                    &messages_tx,
-                    format!("`{path}` already exists, overwrite?"),
-                )
-                .await
-                {
-                    // REALLY attempt to open the file
-                    OpenOptions::new()
-                        .create(true)
-                        .write(true)
-                        .truncate(true)
-                        .open(&path)
-                        .await
-                } else {
-                    return Ok(());
-                }
-            }
-            Err(error) => Err(error),
-        }
-    };
-
     debug!(path, bytes = data.len(), "Writing to file");
     async {
-        let mut file = result?;
         file.write_all(&data).await?;
         file.flush().await
     }
@@ -135,6 +102,482 @@ pub async fn save_file(
     Ok(())
 }
 
+/// Save the full body of a response to disk. Same as [save_file], but large
+/// bodies that were spooled to disk are copied from their temp file, rather
+/// than loaded into memory.
+pub async fn save_response_body(
+    messages_tx: MessageSender,
+    default_path: Option<String>,
+    response: Arc<ResponseRecord>,
+) -> anyhow::Result<()> {
+    let Some((path, mut file)) =
+        open_new_file(&messages_tx, default_path).await?
+    else {
+        return Ok(());
+    };
+
+    debug!(path, size = %response.body.size(), "Writing response body to file");
+    response
+        .body
+        .write_to(&mut file)
+        .await
+        .with_context(|| format!("Error writing to file `{path}`"))
+        .traced()?;
+    messages_tx.send(Message::Notify(format!("Saved to {path}")));
+    Ok(())
+}
+
+/// Ask the user for a path, and open a new file there for writing. If the
+/// file already exists, ask for confirmation to overwrite it. Return `None` if
+/// the user backed out at any point.
+async fn open_new_file(
+    messages_tx: &MessageSender,
+    default_path: Option<String>,
+) -> anyhow::Result<Option<(String, File)>> {
+    // If the user closed the prompt, just exit
+    let Some(path) =
+        prompt(messages_tx, "Enter a path for the file", default_path).await
+    else {
+        return Ok(None);
+    };
+
+    // If the user input nothing, assume they just want to exit
+    if path.is_empty() {
+        return Ok(None);
+    }
+
+    // Attempt to open the file *if it doesn't exist already*
+    let result = OpenOptions::new()
+        .create_new(true)
+        .write(true)
+        .open(&path)
+        .await;
+
+    let file = match result {
+        Ok(file) => file,
+        // If the file already exists, ask for confirmation to overwrite
+        Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+            warn!(path, "File already exists, asking to overwrite");
+
+            // Hi, sorry, follow up question. Are you sure?
+            if confirm(
+                messages_tx,
+                format!("`{path}` already exists, overwrite?"),
+            )
+            .await
+            {
+                // REALLY attempt to open the file
+                OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .truncate(true)
+                    .open(&path)
+                    .await
+                    .with_context(|| format!("Error opening file `{path}`"))
+                    .traced()?
+            } else {
+                return Ok(None);
+            }
+        }
+        Err(error) => {
+            return Err(error)
+                .with_context(|| format!("Error opening file `{path}`"))
+                .traced()
+        }
+    };
+    Ok(Some((path, file)))
+}
+
+/// Find and replace text in the collection file. This will:
+/// - Ask the user for the text to find, and its replacement
+/// - Ask if all matches should be replaced at once
+/// - If not, ask for confirmation on each match individually
+/// - Write the file, and trigger a collection reload
+pub async fn find_replace(
+    messages_tx: MessageSender,
+    collection_path: PathBuf,
+) -> anyhow::Result<()> {
+    // If the user closed either prompt or gave no input, just exit
+    let Some(find) = prompt(&messages_tx, "Find", None)
+        .await
+        .filter(|find| !find.is_empty())
+    else {
+        return Ok(());
+    };
+    let Some(replace) =
+        prompt(&messages_tx, format!("Replace `{find}` with"), None).await
+    else {
+        return Ok(());
+    };
+
+    let content = fs::read_to_string(&collection_path)
+        .await
+        .with_context(|| format!("Error reading {collection_path:?}"))?;
+    let matches = find_text(&content, &find);
+    if matches.is_empty() {
+        messages_tx.send(Message::Notify(format!("No matches for `{find}`")));
+        return Ok(());
+    }
+
+    let selected = if confirm(
+        &messages_tx,
+        format!("Replace all {} matches without reviewing?", matches.len()),
+    )
+    .await
+    {
+        matches
+    } else {
+        let mut selected = Vec::with_capacity(matches.len());
+        for (i, text_match) in matches.iter().enumerate() {
+            let message = format!(
+                "({}/{}) Line {}: {}",
+                i + 1,
+                matches.len(),
+                text_match.line_number,
+                text_match.preview(&replace)
+            );
+            if confirm(&messages_tx, message).await {
+                selected.push(text_match.clone());
+            }
+        }
+        selected
+    };
+    if selected.is_empty() {
+        messages_tx.send(Message::Notify("No matches replaced".into()));
+        return Ok(());
+    }
+
+    // Re-read the file, in case it changed while the user was deciding. If
+    // the matched text moved, this will fail instead of clobbering changes
+    let content = fs::read_to_string(&collection_path)
+        .await
+        .with_context(|| format!("Error reading {collection_path:?}"))?;
+    let content = replace_text(&content, &replace, &selected)?;
+    debug!(?collection_path, find, replace, "Writing replacements");
+    fs::write(&collection_path, content)
+        .await
+        .with_context(|| format!("Error writing {collection_path:?}"))
+        .traced()?;
+
+    messages_tx.send(Message::Notify(format!(
+        "Replaced {} occurrence(s) of `{find}`",
+        selected.len()
+    )));
+    // The file watcher should pick this up too, but it isn't always reliable
+    messages_tx.send(Message::CollectionStartReload);
+    Ok(())
+}
+
+/// Ask the user when to send a request, then schedule it. Accepts either a
+/// delay or a time, as parsed by [parse_send_time]
+pub async fn schedule_request(
+    messages_tx: MessageSender,
+    request_config: RequestConfig,
+) -> anyhow::Result<()> {
+    let Some(input) = prompt(
+        &messages_tx,
+        format!(
+            "Send `{}` after (e.g. 30s) or at (e.g. 14:30)",
+            request_config.recipe_id
+        ),
+        None,
+    )
+    .await
+    .filter(|input| !input.trim().is_empty()) else {
+        return Ok(());
+    };
+    let send_at = parse_send_time(input.trim(), Local::now())
+        .map_err(|error| anyhow!(error))?;
+    messages_tx.send(Message::ScheduleAdd {
+        request_config,
+        send_at,
+    });
+    Ok(())
+}
+
+/// Get the command for the user's preferred editor, from `$VISUAL` or
+/// `$EDITOR`. The value is split like a shell would, so it can include
+/// arguments (e.g. `code --wait`). Falls back to a platform default.
+pub fn editor_command() -> anyhow::Result<Vec<String>> {
+    let default = if cfg!(windows) { "notepad" } else { "vi" };
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| default.to_owned());
+    let command = shell_words::split(&editor)
+        .with_context(|| format!("Invalid editor command `{editor}`"))?;
+    if command.is_empty() {
+        bail!("Editor command is empty");
+    }
+    Ok(command)
+}
+
+/// ID of the scratch request. The scratch request is shown in the recipe list
+/// alongside real recipes, so this needs to be unlikely to collide with them
+pub const SCRATCH_RECIPE_ID: &str = "_scratch";
+
+/// Build or edit the scratch request, an ad hoc request that isn't defined in
+/// the collection file. The user is asked for the method, URL, headers, and
+/// body in turn. Existing values are offered as defaults, so this can be used
+/// to edit the request as well. Closing any prompt cancels without changes.
+pub async fn compose_scratch_request(
+    messages_tx: MessageSender,
+    recipe: Option<Recipe>,
+) -> anyhow::Result<()> {
+    let recipe = recipe.unwrap_or_else(|| Recipe {
+        name: Some("Scratch Request".into()),
+        ..Recipe::empty(SCRATCH_RECIPE_ID.to_owned().into())
+    });
+    if let Some(recipe) = prompt_recipe(&messages_tx, recipe).await? {
+        messages_tx.send(Message::ScratchUpdate(recipe.into()));
+    }
+    Ok(())
+}
+
+/// Create a new recipe in the collection file. The user is asked for an ID,
+/// then the same fields as the scratch request. Closing any prompt cancels
+/// without changes.
+pub async fn create_recipe(messages_tx: MessageSender) -> anyhow::Result<()> {
+    let Some(recipe_id) = prompt(&messages_tx, "New recipe ID", None)
+        .await
+        .map(|recipe_id| recipe_id.trim().to_owned())
+        .filter(|recipe_id| !recipe_id.is_empty())
+    else {
+        return Ok(());
+    };
+    let recipe = Recipe::empty(recipe_id.into());
+    if let Some(recipe) = prompt_recipe(&messages_tx, recipe).await? {
+        messages_tx.send(Message::RecipeCreate(recipe.into()));
+    }
+    Ok(())
+}
+
+/// Ask the user for a new ID for a recipe. The controller will update the
+/// collection file.
+pub async fn rename_recipe(
+    messages_tx: MessageSender,
+    recipe_id: RecipeId,
+) -> anyhow::Result<()> {
+    let Some(new_id) = prompt(
+        &messages_tx,
+        format!("Rename `{recipe_id}` to"),
+        Some(recipe_id.to_string()),
+    )
+    .await
+    .map(|new_id| new_id.trim().to_owned())
+    .filter(|new_id| !new_id.is_empty() && new_id != recipe_id.as_str()) else {
+        return Ok(());
+    };
+    messages_tx.send(Message::RecipeRename {
+        recipe_id,
+        new_id: new_id.into(),
+    });
+    Ok(())
+}
+
+/// Ask the user for a name for a golden exchange. The controller will store
+/// it.
+pub async fn mark_golden(
+    messages_tx: MessageSender,
+    request_id: RequestId,
+    default_name: String,
+) -> anyhow::Result<()> {
+    let Some(name) =
+        prompt(&messages_tx, "Golden response name", Some(default_name))
+            .await
+            .map(|name| name.trim().to_owned())
+            .filter(|name| !name.is_empty())
+    else {
+        return Ok(());
+    };
+    messages_tx.send(Message::GoldenMark { request_id, name });
+    Ok(())
+}
+
+/// Ask the user for the ID of a new folder. The controller will update the
+/// collection file.
+pub async fn create_folder(
+    messages_tx: MessageSender,
+    parent_id: Option<RecipeId>,
+) -> anyhow::Result<()> {
+    let message = match &parent_id {
+        Some(parent_id) => format!("New folder ID (in `{parent_id}`)"),
+        None => "New folder ID".into(),
+    };
+    let Some(folder_id) = prompt(&messages_tx, message, None)
+        .await
+        .map(|folder_id| folder_id.trim().to_owned())
+        .filter(|folder_id| !folder_id.is_empty())
+    else {
+        return Ok(());
+    };
+    messages_tx.send(Message::FolderCreate {
+        folder_id: folder_id.into(),
+        parent_id,
+    });
+    Ok(())
+}
+
+/// Ask the user for a folder to move a recipe/folder into. An empty response
+/// moves it to the top level. The controller will update the collection file.
+pub async fn move_recipe(
+    messages_tx: MessageSender,
+    recipe_id: RecipeId,
+    parent_id: Option<RecipeId>,
+) -> anyhow::Result<()> {
+    let Some(folder_id) = prompt(
+        &messages_tx,
+        format!("Move `{recipe_id}` to folder (empty for top level)"),
+        parent_id.as_ref().map(RecipeId::to_string),
+    )
+    .await
+    .map(|folder_id| folder_id.trim().to_owned()) else {
+        return Ok(());
+    };
+    let folder_id = Some(folder_id)
+        .filter(|folder_id| !folder_id.is_empty())
+        .map(RecipeId::from);
+    if folder_id != parent_id {
+        messages_tx.send(Message::RecipeMove {
+            recipe_id,
+            folder_id,
+        });
+    }
+    Ok(())
+}
+
+/// Ask the user to confirm, then delete a recipe from the collection file
+pub async fn delete_recipe(
+    messages_tx: MessageSender,
+    recipe_id: RecipeId,
+) -> anyhow::Result<()> {
+    if confirm(&messages_tx, format!("Delete recipe `{recipe_id}`?")).await {
+        messages_tx.send(Message::RecipeDelete(recipe_id));
+    }
+    Ok(())
+}
+
+/// Ask the user for the method, URL, headers, and body of a recipe, in turn.
+/// The recipe's existing values are offered as defaults. Return `None` if any
+/// prompt is closed.
+async fn prompt_recipe(
+    messages_tx: &MessageSender,
+    recipe: Recipe,
+) -> anyhow::Result<Option<Recipe>> {
+    let Some(method) =
+        prompt(messages_tx, "Method", Some(recipe.method.to_string())).await
+    else {
+        return Ok(None);
+    };
+    let method = method
+        .trim()
+        .parse::<Method>()
+        .map_err(|_| anyhow!("Invalid HTTP method `{method}`"))?;
+    let Some(url) =
+        prompt(messages_tx, "URL", Some(recipe.url.to_string())).await
+    else {
+        return Ok(None);
+    };
+    let url = Template::try_from(url).context("Invalid URL template")?;
+
+    // Offer each existing header for editing, then ask for new ones until
+    // an empty value is given
+    let mut headers = IndexMap::new();
+    let mut existing = recipe.headers.iter();
+    loop {
+        let current = existing.next();
+        let (message, default) = match current {
+            Some((name, value)) => (
+                format!("Header `{name}` (empty to remove)"),
+                Some(format!("{name}: {value}")),
+            ),
+            None => ("Header as `Name: value` (empty to finish)".into(), None),
+        };
+        let Some(header) = prompt(messages_tx, message, default).await else {
+            return Ok(None);
+        };
+        if header.trim().is_empty() {
+            if current.is_some() {
+                continue;
+            }
+            break;
+        }
+        let (name, value) = header
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Invalid header `{header}`"))?;
+        let value = Template::try_from(value.trim().to_owned())
+            .with_context(|| format!("Invalid template for header `{name}`"))?;
+        headers.insert(name.trim().to_owned(), value);
+    }
+
+    let Some(body) = prompt(
+        messages_tx,
+        "Body (empty for none)",
+        // Scratch bodies are always raw, because that's all we prompt for
+        match &recipe.body {
+            Some(RecipeBody::Raw(body)) => Some(body.to_string()),
+            _ => None,
+        },
+    )
+    .await
+    else {
+        return Ok(None);
+    };
+    let body = if body.is_empty() {
+        None
+    } else {
+        Some(RecipeBody::Raw(
+            Template::try_from(body).context("Invalid body template")?,
+        ))
+    };
+
+    Ok(Some(Recipe {
+        method,
+        url,
+        headers,
+        body,
+        ..recipe
+    }))
+}
+
+/// Ask the user for a curl command, and turn it into the scratch request. This
+/// replaces any existing scratch request.
+pub async fn paste_curl_request(
+    messages_tx: MessageSender,
+) -> anyhow::Result<()> {
+    let Some(command) = prompt(&messages_tx, "Paste cURL command", None)
+        .await
+        .filter(|command| !command.trim().is_empty())
+    else {
+        return Ok(());
+    };
+    let recipe = Recipe::from_curl(&command)?;
+    messages_tx.send(Message::ScratchUpdate(
+        Recipe {
+            id: RecipeId::from(SCRATCH_RECIPE_ID.to_owned()),
+            name: Some("Scratch Request".into()),
+            ..recipe
+        }
+        .into(),
+    ));
+    Ok(())
+}
+
+/// Ask the user for an ID to save the scratch request under. The controller
+/// will write it to the collection file.
+pub async fn save_scratch_request(
+    messages_tx: MessageSender,
+) -> anyhow::Result<()> {
+    let Some(recipe_id) = prompt(&messages_tx, "Save as recipe ID", None)
+        .await
+        .map(|recipe_id| recipe_id.trim().to_owned())
+        .filter(|recipe_id| !recipe_id.is_empty())
+    else {
+        return Ok(());
+    };
+    messages_tx.send(Message::ScratchSave(recipe_id.into()));
+    Ok(())
+}
+
 /// Ask the user for some text input and wait for a response. Return `None` if
 /// the prompt is closed with no input.
 async fn prompt(
@@ -146,6 +589,8 @@ async fn prompt(
     messages_tx.send(Message::PromptStart(Prompt {
         message: message.to_string(),
         default,
+        options: Vec::new(),
+        multiline: false,
         sensitive: false,
         channel: tx.into(),
     }));
@@ -172,6 +617,7 @@ mod tests {
         test_util::{assert_matches, temp_dir, TempDir},
         tui::test_util::{harness, TestHarness},
     };
+    use indexmap::indexmap;
     use rstest::rstest;
     use tokio::fs;
 
@@ -242,4 +688,326 @@ mod tests {
             "{expected_path:?}"
         );
     }
+
+    /// Test find/replace with per-match confirmation
+    #[rstest]
+    #[tokio::test]
+    async fn test_find_replace(mut harness: TestHarness, temp_dir: TempDir) {
+        let path = temp_dir.join("slumber.yml");
+        fs::write(&path, "a: /v1/x\nb: /v1/y\n").await.unwrap();
+
+        let handle = tokio::spawn(find_replace(
+            harness.messages_tx().clone(),
+            path.clone(),
+        ));
+
+        for (expected_message, response) in
+            [("Find", "/v1/"), ("Replace `/v1/` with", "/v2/")]
+        {
+            let prompt = assert_matches!(
+                harness.pop_message_wait().await,
+                Message::PromptStart(prompt) => prompt,
+            );
+            assert_eq!(&prompt.message, expected_message);
+            prompt.channel.respond(response.to_owned());
+        }
+
+        // Review each match, and only accept the second
+        for (expected_message, response) in [
+            ("Replace all 2 matches without reviewing?", false),
+            ("(1/2) Line 1: a: [/v1/ → /v2/]x", false),
+            ("(2/2) Line 2: b: [/v1/ → /v2/]y", true),
+        ] {
+            let confirm = assert_matches!(
+                harness.pop_message_wait().await,
+                Message::ConfirmStart(confirm) => confirm,
+            );
+            assert_eq!(confirm.message, expected_message);
+            confirm.channel.respond(response);
+        }
+
+        handle
+            .await
+            .expect("Task dropped")
+            .expect("find_replace failed");
+        assert_eq!(
+            fs::read_to_string(&path).await.unwrap(),
+            "a: /v1/x\nb: /v2/y\n"
+        );
+        let message = assert_matches!(
+            harness.pop_message_wait().await,
+            Message::Notify(message) => message,
+        );
+        assert_eq!(message, "Replaced 1 occurrence(s) of `/v1/`");
+        assert_matches!(
+            harness.pop_message_wait().await,
+            Message::CollectionStartReload,
+        );
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_compose_scratch_request(mut harness: TestHarness) {
+        /// Run the composer, answering each prompt in order
+        async fn compose(
+            harness: &mut TestHarness,
+            recipe: Option<Recipe>,
+            responses: &[(&str, Option<&str>, &str)],
+        ) -> Recipe {
+            let handle = tokio::spawn(compose_scratch_request(
+                harness.messages_tx().clone(),
+                recipe,
+            ));
+            for (expected_message, expected_default, response) in responses {
+                let prompt = assert_matches!(
+                    harness.pop_message_wait().await,
+                    Message::PromptStart(prompt) => prompt,
+                );
+                assert_eq!(&prompt.message, expected_message);
+                assert_eq!(prompt.default.as_deref(), *expected_default);
+                prompt.channel.respond((*response).to_owned());
+            }
+            handle
+                .await
+                .expect("Task dropped")
+                .expect("compose_scratch_request failed");
+            assert_matches!(
+                harness.pop_message_wait().await,
+                Message::ScratchUpdate(recipe) => *recipe,
+            )
+        }
+
+        let recipe = compose(
+            &mut harness,
+            None,
+            &[
+                ("Method", Some("GET"), "post"),
+                ("URL", Some(""), "{{host}}/users"),
+                (
+                    "Header as `Name: value` (empty to finish)",
+                    None,
+                    "Accept: application/json",
+                ),
+                ("Header as `Name: value` (empty to finish)", None, ""),
+                ("Body (empty for none)", None, "{}"),
+            ],
+        )
+        .await;
+        assert_eq!(recipe.id.as_str(), SCRATCH_RECIPE_ID);
+        assert_eq!(recipe.method.to_string(), "POST");
+        assert_eq!(recipe.url.as_str(), "{{host}}/users");
+        assert_eq!(
+            recipe.headers,
+            indexmap! {"Accept".into() => "application/json".parse().unwrap()}
+        );
+        assert_eq!(recipe.body, Some("{}".into()));
+
+        // Edit it: existing values are the defaults, and clearing a header
+        // removes it
+        let recipe = compose(
+            &mut harness,
+            Some(recipe),
+            &[
+                ("Method", Some("POST"), "POST"),
+                ("URL", Some("{{host}}/users"), "{{host}}/users"),
+                (
+                    "Header `Accept` (empty to remove)",
+                    Some("Accept: application/json"),
+                    "",
+                ),
+                ("Header as `Name: value` (empty to finish)", None, ""),
+                ("Body (empty for none)", Some("{}"), ""),
+            ],
+        )
+        .await;
+        assert_eq!(recipe.headers, IndexMap::new());
+        assert_eq!(recipe.body, None);
+    }
+
+    /// Test creating a new recipe. The ID is prompted first, then the same
+    /// fields as the scratch request
+    #[rstest]
+    #[tokio::test]
+    async fn test_create_recipe(mut harness: TestHarness) {
+        let handle = tokio::spawn(create_recipe(harness.messages_tx().clone()));
+        for (expected_message, response) in [
+            ("New recipe ID", " get_user "),
+            ("Method", "GET"),
+            ("URL", "{{host}}/users/1"),
+            ("Header as `Name: value` (empty to finish)", ""),
+            ("Body (empty for none)", ""),
+        ] {
+            let prompt = assert_matches!(
+                harness.pop_message_wait().await,
+                Message::PromptStart(prompt) => prompt,
+            );
+            assert_eq!(prompt.message, expected_message);
+            prompt.channel.respond(response.to_owned());
+        }
+        handle
+            .await
+            .expect("Task dropped")
+            .expect("create_recipe failed");
+        let recipe = assert_matches!(
+            harness.pop_message_wait().await,
+            Message::RecipeCreate(recipe) => *recipe,
+        );
+        assert_eq!(recipe.id.as_str(), "get_user");
+        assert_eq!(recipe.name, None);
+        assert_eq!(recipe.url.as_str(), "{{host}}/users/1");
+    }
+
+    /// Test renaming a recipe. The current ID is offered as the default
+    #[rstest]
+    #[tokio::test]
+    async fn test_rename_recipe(mut harness: TestHarness) {
+        let handle = tokio::spawn(rename_recipe(
+            harness.messages_tx().clone(),
+            "get_user".into(),
+        ));
+        let prompt = assert_matches!(
+            harness.pop_message_wait().await,
+            Message::PromptStart(prompt) => prompt,
+        );
+        assert_eq!(prompt.message, "Rename `get_user` to");
+        assert_eq!(prompt.default.as_deref(), Some("get_user"));
+        prompt.channel.respond("fetch_user".into());
+        handle
+            .await
+            .expect("Task dropped")
+            .expect("rename_recipe failed");
+        let (recipe_id, new_id) = assert_matches!(
+            harness.pop_message_wait().await,
+            Message::RecipeRename { recipe_id, new_id } => (recipe_id, new_id),
+        );
+        assert_eq!(recipe_id.as_str(), "get_user");
+        assert_eq!(new_id.as_str(), "fetch_user");
+    }
+
+    /// Test creating a folder. The parent is passed through untouched
+    #[rstest]
+    #[tokio::test]
+    async fn test_create_folder(mut harness: TestHarness) {
+        let handle = tokio::spawn(create_folder(
+            harness.messages_tx().clone(),
+            Some("users".into()),
+        ));
+        let prompt = assert_matches!(
+            harness.pop_message_wait().await,
+            Message::PromptStart(prompt) => prompt,
+        );
+        assert_eq!(prompt.message, "New folder ID (in `users`)");
+        prompt.channel.respond(" admin ".into());
+        handle
+            .await
+            .expect("Task dropped")
+            .expect("create_folder failed");
+        let (folder_id, parent_id) = assert_matches!(
+            harness.pop_message_wait().await,
+            Message::FolderCreate { folder_id, parent_id } =>
+                (folder_id, parent_id),
+        );
+        assert_eq!(folder_id.as_str(), "admin");
+        assert_eq!(parent_id, Some("users".into()));
+    }
+
+    /// Test moving a recipe. The current folder is offered as the default,
+    /// and an empty response means the top level
+    #[rstest]
+    #[case::into_folder("admin", Some("admin"))]
+    #[case::top_level("  ", None)]
+    #[tokio::test]
+    async fn test_move_recipe(
+        mut harness: TestHarness,
+        #[case] response: &str,
+        #[case] expected: Option<&str>,
+    ) {
+        let handle = tokio::spawn(move_recipe(
+            harness.messages_tx().clone(),
+            "get_user".into(),
+            Some("users".into()),
+        ));
+        let prompt = assert_matches!(
+            harness.pop_message_wait().await,
+            Message::PromptStart(prompt) => prompt,
+        );
+        assert_eq!(
+            prompt.message,
+            "Move `get_user` to folder (empty for top level)"
+        );
+        assert_eq!(prompt.default.as_deref(), Some("users"));
+        prompt.channel.respond(response.into());
+        handle
+            .await
+            .expect("Task dropped")
+            .expect("move_recipe failed");
+        let (recipe_id, folder_id) = assert_matches!(
+            harness.pop_message_wait().await,
+            Message::RecipeMove { recipe_id, folder_id } =>
+                (recipe_id, folder_id),
+        );
+        assert_eq!(recipe_id.as_str(), "get_user");
+        assert_eq!(folder_id, expected.map(RecipeId::from));
+    }
+
+    /// Test deleting a recipe. Nothing happens unless the user confirms
+    #[rstest]
+    #[case::confirm(true)]
+    #[case::cancel(false)]
+    #[tokio::test]
+    async fn test_delete_recipe(
+        mut harness: TestHarness,
+        #[case] response: bool,
+    ) {
+        let handle = tokio::spawn(delete_recipe(
+            harness.messages_tx().clone(),
+            "get_user".into(),
+        ));
+        let confirm = assert_matches!(
+            harness.pop_message_wait().await,
+            Message::ConfirmStart(confirm) => confirm,
+        );
+        assert_eq!(confirm.message, "Delete recipe `get_user`?");
+        confirm.channel.respond(response);
+        handle
+            .await
+            .expect("Task dropped")
+            .expect("delete_recipe failed");
+        if response {
+            let recipe_id = assert_matches!(
+                harness.pop_message_wait().await,
+                Message::RecipeDelete(recipe_id) => recipe_id,
+            );
+            assert_eq!(recipe_id.as_str(), "get_user");
+        }
+    }
+
+    /// Test pasting a curl command as the scratch request
+    #[rstest]
+    #[tokio::test]
+    async fn test_paste_curl_request(mut harness: TestHarness) {
+        let handle =
+            tokio::spawn(paste_curl_request(harness.messages_tx().clone()));
+        let prompt = assert_matches!(
+            harness.pop_message_wait().await,
+            Message::PromptStart(prompt) => prompt,
+        );
+        assert_eq!(prompt.message, "Paste cURL command");
+        prompt
+            .channel
+            .respond("curl -XPOST https://example.com/users -d '{}'".into());
+        handle
+            .await
+            .expect("Task dropped")
+            .expect("paste_curl_request failed");
+
+        let recipe = assert_matches!(
+            harness.pop_message_wait().await,
+            Message::ScratchUpdate(recipe) => *recipe,
+        );
+        assert_eq!(recipe.id.as_str(), SCRATCH_RECIPE_ID);
+        assert_eq!(recipe.method.to_string(), "POST");
+        assert_eq!(recipe.url.as_str(), "https://example.com/users");
+        assert_eq!(recipe.body, Some("{}".into()));
+    }
 }
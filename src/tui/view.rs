@@ -9,16 +9,21 @@ pub mod test_util;
 mod theme;
 mod util;
 
-pub use common::modal::{IntoModal, ModalPriority};
+pub use common::{
+    modal::{IntoModal, ModalPriority},
+    pretty_body::PrettyBody,
+};
 pub use context::ViewContext;
 pub use state::RequestState;
-pub use theme::{Styles, Theme};
+pub use theme::{Styles, Theme, ThemeConfig};
 pub use util::{Confirm, PreviewPrompter};
 
 use crate::{
-    collection::CollectionFile,
+    collection::{Collection, Profile, ProfileOverrides, RecipeId},
     db::CollectionDatabase,
+    http::RequestId,
     tui::{
+        context::TuiContext,
         input::Action,
         message::{Message, MessageSender},
         view::{
@@ -27,10 +32,11 @@ use crate::{
             state::Notification,
         },
     },
+    ws::WebSocketConnection,
 };
 use anyhow::anyhow;
 use ratatui::Frame;
-use std::fmt::Debug;
+use std::{fmt::Debug, path::Path};
 use tracing::{error, trace, trace_span};
 
 /// Primary entrypoint for the view. This contains the main draw functions, as
@@ -50,18 +56,23 @@ pub struct View {
 }
 
 impl View {
+    /// Build the view for a collection. The given profile overrides should
+    /// already be applied to the collection; they're only needed to mark
+    /// overridden fields in the profile list.
     pub fn new(
-        collection_file: &CollectionFile,
+        collection: &Collection,
+        collection_path: &Path,
+        profile_overrides: &ProfileOverrides,
         database: CollectionDatabase,
         messages_tx: MessageSender,
     ) -> Self {
         ViewContext::init(database, messages_tx);
         let mut view = Self {
-            root: Root::new(&collection_file.collection).into(),
+            root: Root::new(collection, profile_overrides).into(),
         };
         view.notify(format!(
             "Loaded collection from {}",
-            collection_file.path().to_string_lossy()
+            collection_path.to_string_lossy()
         ));
         view
     }
@@ -70,6 +81,8 @@ impl View {
     /// to render input bindings as help messages to the user.
     pub fn draw<'a>(&'a self, frame: &'a mut Frame) {
         let chunk = frame.size();
+        // A focused text box will set this again while drawing
+        TuiContext::get().input_engine.set_text_input(false);
         self.root.draw(frame, (), chunk, true);
     }
 
@@ -81,6 +94,37 @@ impl View {
         ViewContext::push_event(Event::HttpSetState(state));
     }
 
+    /// Queue an event to show a WebSocket connection in the view. Connections
+    /// are owned by the controller so they survive view rebuilds; this should
+    /// be called for each connection whenever the view is created.
+    pub fn set_websocket(&mut self, connection: WebSocketConnection) {
+        ViewContext::push_event(Event::WebSocketSetConnection(connection));
+    }
+
+    /// Select a recipe in the recipe list. If the recipe isn't in the list
+    /// (e.g. it's in a collapsed folder), do nothing.
+    pub fn select_recipe(&mut self, recipe_id: &RecipeId) {
+        self.root.data_mut().select_recipe(recipe_id);
+    }
+
+    /// Show an edited recipe note in the recipe pane
+    pub fn set_recipe_note(
+        &mut self,
+        recipe_id: &RecipeId,
+        note: Option<String>,
+    ) {
+        self.root.data_mut().set_recipe_note(recipe_id, note);
+    }
+
+    /// Show an edited exchange note in the exchange pane
+    pub fn set_exchange_note(
+        &mut self,
+        request_id: RequestId,
+        note: Option<String>,
+    ) {
+        self.root.data_mut().set_exchange_note(request_id, note);
+    }
+
     /// Queue an event to open a new modal. The input can be anything that
     /// converts to modal content
     pub fn open_modal(
@@ -94,6 +138,23 @@ impl View {
         });
     }
 
+    /// See [Root::set_profile_overrides]
+    pub fn set_profile_overrides(
+        &mut self,
+        profiles: Vec<Profile>,
+        profile_overrides: ProfileOverrides,
+    ) {
+        self.root
+            .data_mut()
+            .set_profile_overrides(profiles, profile_overrides);
+    }
+
+    /// Show an error to the user in a modal, and record it in the message log
+    pub fn error(&mut self, error: anyhow::Error) {
+        ViewContext::push_event(Event::Notify(Notification::error(&error)));
+        self.open_modal(error, ModalPriority::High);
+    }
+
     /// Queue an event to send an informational notification to the user
     pub fn notify(&mut self, message: impl ToString) {
         let notification = Notification::new(message.to_string());
@@ -156,7 +217,7 @@ impl View {
 mod tests {
     use super::*;
     use crate::{
-        collection::Collection,
+        collection::{Collection, CollectionFile},
         test_util::Factory,
         tui::test_util::{assert_events, harness, TestHarness},
     };
@@ -165,10 +226,11 @@ mod tests {
     /// Test view handling and drawing during initial view setup
     #[rstest]
     fn test_initial_draw(mut harness: TestHarness) {
-        let collection = Collection::factory(());
-        let collection_file = CollectionFile::testing(collection);
+        let collection_file = CollectionFile::testing(Collection::factory(()));
         let mut view = View::new(
-            &collection_file,
+            &collection_file.collection,
+            collection_file.path(),
+            &Default::default(),
             harness.database.clone(),
             harness.messages_tx().clone(),
         );
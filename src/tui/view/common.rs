@@ -6,6 +6,7 @@ pub mod button;
 pub mod header_table;
 pub mod list;
 pub mod modal;
+pub mod pretty_body;
 pub mod scrollbar;
 pub mod table;
 pub mod tabs;
@@ -20,7 +21,7 @@ use crate::{
         context::TuiContext,
         view::{draw::Generate, state::Notification},
     },
-    util::MaybeStr,
+    util::{format_duration, MaybeStr},
 };
 use chrono::{DateTime, Duration, Local, Utc};
 use itertools::Itertools;
@@ -37,7 +38,10 @@ pub struct Pane<'a> {
 }
 
 impl<'a> Generate for Pane<'a> {
-    type Output<'this> = Block<'this> where Self: 'this;
+    type Output<'this>
+        = Block<'this>
+    where
+        Self: 'this;
 
     fn generate<'this>(self) -> Self::Output<'this>
     where
@@ -87,7 +91,10 @@ impl Generate for String {
 
 impl Generate for &String {
     /// Use `Text` because a string can be multiple lines
-    type Output<'this> = Text<'this> where Self: 'this;
+    type Output<'this>
+        = Text<'this>
+    where
+        Self: 'this;
 
     fn generate<'this>(self) -> Self::Output<'this>
     where
@@ -98,7 +105,10 @@ impl Generate for &String {
 }
 
 impl Generate for &Profile {
-    type Output<'this> = Span<'this> where Self: 'this;
+    type Output<'this>
+        = Span<'this>
+    where
+        Self: 'this;
 
     fn generate<'this>(self) -> Self::Output<'this>
     where
@@ -109,7 +119,10 @@ impl Generate for &Profile {
 }
 
 impl Generate for &Notification {
-    type Output<'this> = Span<'this> where Self: 'this;
+    type Output<'this>
+        = Span<'this>
+    where
+        Self: 'this;
 
     fn generate<'this>(self) -> Self::Output<'this>
     where
@@ -126,7 +139,10 @@ impl Generate for &Notification {
 
 /// Format a timestamp in the local timezone
 impl Generate for DateTime<Utc> {
-    type Output<'this> = Span<'this> where Self: 'this;
+    type Output<'this>
+        = Span<'this>
+    where
+        Self: 'this;
 
     fn generate<'this>(self) -> Self::Output<'this>
     where
@@ -147,17 +163,15 @@ impl Generate for Duration {
     where
         Self: 'this,
     {
-        let ms = self.num_milliseconds();
-        if ms < 1000 {
-            format!("{ms}ms").into()
-        } else {
-            format!("{:.2}s", ms as f64 / 1000.0).into()
-        }
+        format_duration(&self).into()
     }
 }
 
 impl Generate for Option<Duration> {
-    type Output<'this> = Span<'this> where Self: 'this;
+    type Output<'this>
+        = Span<'this>
+    where
+        Self: 'this;
 
     fn generate<'this>(self) -> Self::Output<'this>
     where
@@ -172,28 +186,26 @@ impl Generate for Option<Duration> {
 }
 
 impl Generate for StatusCode {
-    type Output<'this> = Span<'this> where Self: 'this;
+    type Output<'this>
+        = Span<'this>
+    where
+        Self: 'this;
 
     fn generate<'this>(self) -> Self::Output<'this>
     where
         Self: 'this,
     {
-        let styles = &TuiContext::get().styles.status_code;
-        let is_error = self.is_client_error() || self.is_server_error();
-        Span::styled(
-            self.to_string(),
-            if is_error {
-                styles.error
-            } else {
-                styles.success
-            },
-        )
+        let style = TuiContext::get().styles.status_code.get(self);
+        Span::styled(self.to_string(), style)
     }
 }
 
 /// Not all header values are UTF-8; use a placeholder if not
 impl Generate for &HeaderValue {
-    type Output<'this> = Span<'this> where Self: 'this;
+    type Output<'this>
+        = Span<'this>
+    where
+        Self: 'this;
 
     fn generate<'this>(self) -> Self::Output<'this>
     where
@@ -205,7 +217,10 @@ impl Generate for &HeaderValue {
 
 impl Generate for &anyhow::Error {
     /// 'static because string is generated
-    type Output<'this> = Text<'static> where Self: 'this;
+    type Output<'this>
+        = Text<'static>
+    where
+        Self: 'this;
 
     fn generate<'this>(self) -> Self::Output<'this>
     where
@@ -236,7 +251,10 @@ impl Generate for &anyhow::Error {
 }
 
 impl Generate for &RequestBuildError {
-    type Output<'this> = Text<'static> where Self: 'this;
+    type Output<'this>
+        = Text<'static>
+    where
+        Self: 'this;
 
     fn generate<'this>(self) -> Self::Output<'this>
     where
@@ -248,7 +266,10 @@ impl Generate for &RequestBuildError {
 }
 
 impl Generate for &RequestError {
-    type Output<'this> = Text<'static> where Self: 'this;
+    type Output<'this>
+        = Text<'static>
+    where
+        Self: 'this;
 
     fn generate<'this>(self) -> Self::Output<'this>
     where
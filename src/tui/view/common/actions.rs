@@ -96,6 +96,26 @@ pub enum GlobalAction {
     #[default]
     #[display("Edit Collection")]
     EditCollection,
+    #[display("Find & Replace")]
+    FindReplace,
+    #[display("New Recipe")]
+    NewRecipe,
+    #[display("Scratch Request")]
+    ScratchRequest,
+    #[display("Paste cURL Command")]
+    PasteCurl,
+    #[display("Compare Profiles")]
+    CompareProfiles,
+    #[display("Override Profile Fields")]
+    OverrideProfileField,
+    #[display("Clear Profile Overrides")]
+    ClearProfileOverrides,
+    #[display("Request Queue")]
+    RequestQueue,
+    #[display("Scheduled Requests")]
+    ScheduledRequests,
+    #[display("Inspect Chains")]
+    InspectChains,
 }
 impl FixedSelect for GlobalAction {}
 impl ToStringGenerate for GlobalAction {}
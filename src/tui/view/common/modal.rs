@@ -9,7 +9,7 @@ use crate::tui::{
     },
 };
 use ratatui::{
-    prelude::Constraint,
+    layout::{Constraint, Rect},
     text::Line,
     widgets::{Block, Borders, Clear},
     Frame,
@@ -145,12 +145,16 @@ impl Draw for ModalQueue {
             let (width, height) = modal.data().dimensions();
 
             // The child gave us the content dimensions, we need to add one cell
-            // of buffer for the border
-            let mut area = centered_rect(width, height, metadata.area());
-            area.x -= 1;
-            area.y -= 1;
-            area.width += 2;
-            area.height += 2;
+            // of buffer for the border. If the modal is too big for the
+            // terminal, it gets cut off at the edges
+            let content_area = centered_rect(width, height, metadata.area());
+            let area = Rect {
+                x: content_area.x.saturating_sub(1),
+                y: content_area.y.saturating_sub(1),
+                width: content_area.width + 2,
+                height: content_area.height + 2,
+            }
+            .intersection(metadata.area());
 
             let block = Block::default()
                 .title(modal.data().title())
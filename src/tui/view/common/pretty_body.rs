@@ -0,0 +1,475 @@
+//! Display text for response bodies. Bodies can be huge, so JSON bodies are
+//! never prettified in full for display. Instead, each line is formatted only
+//! when it's drawn.
+
+use crate::{
+    http::{ContentType, ResponseRecord, SelectorType},
+    tui::view::common::text_window::{IndexedText, TextWindowContent},
+};
+use ratatui::text::{Line, Span};
+use serde_json::Value;
+use std::{
+    borrow::Cow,
+    fmt::{self, Display, Write},
+    ops::Range,
+    sync::Arc,
+};
+
+/// Number of lines between each saved position in a [PrettyJson]. Drawing
+/// starts at the nearest position above the window, so this is the most lines
+/// that have to be walked (but not formatted) to reach the window.
+const CHECKPOINT_INTERVAL: usize = 256;
+
+/// Display text for a body, ready to be shown in a text window
+#[derive(Debug)]
+pub enum PrettyBody {
+    /// Text that's been generated in full, e.g. a queried or unparsed body
+    Text(IndexedText),
+    /// An unqueried JSON body. Lines are formatted as they're drawn
+    Json(PrettyJson),
+}
+
+impl PrettyBody {
+    /// Parse a response body, then query and prettify it for display. This
+    /// can be slow for large bodies, so it should be called on a background
+    /// thread.
+    pub fn new(
+        response: Arc<ResponseRecord>,
+        selector_type: SelectorType,
+        query: Option<&str>,
+    ) -> Self {
+        response.parse_body();
+        let is_json = response
+            .body
+            .parsed()
+            .is_some_and(|parsed| parsed.content_type() == ContentType::Json);
+        if is_json && query.is_none() {
+            Self::Json(PrettyJson::new(response))
+        } else {
+            let text = response.body.display_text(selector_type, query);
+            Self::Text(IndexedText::new(text))
+        }
+    }
+
+    /// Get the full display text. For JSON, this prettifies the whole body
+    pub fn text(&self) -> Cow<'_, str> {
+        match self {
+            Self::Text(text) => text.as_str().into(),
+            Self::Json(json) => json.text().into(),
+        }
+    }
+}
+
+impl TextWindowContent for PrettyBody {
+    fn line_widths(&self) -> Cow<'_, [usize]> {
+        match self {
+            Self::Text(text) => text.line_widths(),
+            Self::Json(json) => json.line_widths(),
+        }
+    }
+
+    fn lines(&self, range: Range<usize>) -> Vec<Line<'_>> {
+        match self {
+            Self::Text(text) => text.lines(range),
+            Self::Json(json) => json.lines(range),
+        }
+    }
+}
+
+/// A JSON body, laid out the same as `serde_json`'s pretty printer. The body
+/// is walked once up front to measure each line, but the formatted text isn't
+/// kept. Drawing formats just the visible lines, starting from a saved walk
+/// position.
+#[derive(Debug)]
+pub struct PrettyJson {
+    /// Owner of the parsed body. Must have been parsed as JSON
+    response: Arc<ResponseRecord>,
+    /// Display width of each line, in columns
+    line_widths: Vec<usize>,
+    /// [JsonLines::position] at every [CHECKPOINT_INTERVAL]th line
+    checkpoints: Vec<Vec<usize>>,
+}
+
+impl PrettyJson {
+    fn new(response: Arc<ResponseRecord>) -> Self {
+        let mut json = Self {
+            response,
+            line_widths: Vec::new(),
+            checkpoints: Vec::new(),
+        };
+        let (line_widths, checkpoints) = measure(&json.value());
+        json.line_widths = line_widths;
+        json.checkpoints = checkpoints;
+        json
+    }
+
+    /// Get the parsed body
+    fn value(&self) -> Cow<'_, Value> {
+        self.response
+            .body
+            .parsed()
+            .expect("Body must be parsed")
+            .to_json()
+    }
+
+    fn text(&self) -> String {
+        // serde_json can't fail serializing its own Value type
+        serde_json::to_string_pretty(&self.value()).unwrap()
+    }
+
+    fn line_widths(&self) -> Cow<'_, [usize]> {
+        Cow::Borrowed(&self.line_widths)
+    }
+
+    fn lines(&self, range: Range<usize>) -> Vec<Line<'_>> {
+        let checkpoint = range.start / CHECKPOINT_INTERVAL;
+        let value = self.value();
+        JsonLines::resume(&value, &self.checkpoints[checkpoint])
+            .skip(range.start - checkpoint * CHECKPOINT_INTERVAL)
+            .take(range.len())
+            .map(|line| line.to_string().into())
+            .collect()
+    }
+}
+
+/// Walk a value to get the display width of each line, and the position at
+/// every [CHECKPOINT_INTERVAL]th line
+fn measure(value: &Value) -> (Vec<usize>, Vec<Vec<usize>>) {
+    let mut lines = JsonLines::new(value);
+    let mut line_widths = Vec::new();
+    let mut checkpoints = Vec::new();
+    let mut buffer = String::new();
+    loop {
+        let position = (line_widths.len() % CHECKPOINT_INTERVAL == 0)
+            .then(|| lines.position());
+        let Some(line) = lines.next() else {
+            return (line_widths, checkpoints);
+        };
+        checkpoints.extend(position);
+        buffer.clear();
+        // Writing to a string can't fail
+        let _ = write!(buffer, "{line}");
+        line_widths.push(Span::raw(buffer.as_str()).width());
+    }
+}
+
+/// Iterator over the lines of a pretty-printed JSON value. Every scalar, empty
+/// collection, and closing bracket gets its own line, and each nested value
+/// starts on the same line as its key.
+struct JsonLines<'a> {
+    /// Collections that we're inside of, outermost first
+    stack: Vec<Frame<'a>>,
+    /// The root value, until its first line has been emitted
+    root: Option<&'a Value>,
+}
+
+/// A non-empty collection being walked by [JsonLines]
+struct Frame<'a> {
+    children: Children<'a>,
+    /// Total number of children
+    len: usize,
+    /// Number of children that haven't been started yet
+    remaining: usize,
+    /// Does the closing bracket need a comma?
+    comma: bool,
+}
+
+enum Children<'a> {
+    Object(serde_json::map::Iter<'a>),
+    Array(std::slice::Iter<'a, Value>),
+}
+
+/// A single line of pretty-printed JSON
+struct JsonLine<'a> {
+    depth: usize,
+    key: Option<&'a str>,
+    token: Token<'a>,
+    comma: bool,
+}
+
+enum Token<'a> {
+    /// A scalar or empty collection
+    Value(&'a Value),
+    Open(char),
+    Close(char),
+}
+
+impl<'a> JsonLines<'a> {
+    fn new(root: &'a Value) -> Self {
+        Self {
+            stack: Vec::new(),
+            root: Some(root),
+        }
+    }
+
+    /// Get the number of children that have been started at each level. This
+    /// can be passed to [Self::resume] to pick up from the same line later
+    fn position(&self) -> Vec<usize> {
+        self.stack
+            .iter()
+            .map(|frame| frame.len - frame.remaining)
+            .collect()
+    }
+
+    /// Pick up walking a value from a [position](Self::position)
+    fn resume(root: &'a Value, position: &[usize]) -> Self {
+        if position.is_empty() {
+            return Self::new(root);
+        }
+        let mut stack: Vec<Frame<'a>> = Vec::with_capacity(position.len());
+        let mut value = root;
+        let mut comma = false;
+        for &started in position {
+            let mut frame =
+                Frame::new(value, comma).expect("Position must match value");
+            // The last child started at this level is the collection we're
+            // in at the next level, if there is one
+            if let Some(child) =
+                started.checked_sub(1).and_then(|i| frame.children.nth(i))
+            {
+                value = child.1;
+            }
+            frame.remaining -= started;
+            comma = frame.remaining > 0;
+            stack.push(frame);
+        }
+        Self { stack, root: None }
+    }
+
+    /// Emit the first line of a value, and start walking it if it's a
+    /// non-empty collection
+    fn start(
+        &mut self,
+        key: Option<&'a str>,
+        value: &'a Value,
+        comma: bool,
+    ) -> JsonLine<'a> {
+        let depth = self.stack.len();
+        let token = match Frame::new(value, comma) {
+            Some(frame) => {
+                self.stack.push(frame);
+                Token::Open(if value.is_object() { '{' } else { '[' })
+            }
+            None => Token::Value(value),
+        };
+        JsonLine {
+            depth,
+            key,
+            // A collection's comma goes after its closing bracket
+            comma: comma && matches!(token, Token::Value(_)),
+            token,
+        }
+    }
+}
+
+impl<'a> Iterator for JsonLines<'a> {
+    type Item = JsonLine<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(root) = self.root.take() {
+            return Some(self.start(None, root, false));
+        }
+
+        let frame = self.stack.last_mut()?;
+        match frame.children.next() {
+            Some((key, child)) => {
+                frame.remaining -= 1;
+                let comma = frame.remaining > 0;
+                Some(self.start(key, child, comma))
+            }
+            None => {
+                let frame = self.stack.pop()?;
+                Some(JsonLine {
+                    depth: self.stack.len(),
+                    key: None,
+                    token: Token::Close(match frame.children {
+                        Children::Object(_) => '}',
+                        Children::Array(_) => ']',
+                    }),
+                    comma: frame.comma,
+                })
+            }
+        }
+    }
+}
+
+impl<'a> Frame<'a> {
+    /// Start walking a collection. `None` if the value isn't a collection or
+    /// is empty, meaning it fits on one line
+    fn new(value: &'a Value, comma: bool) -> Option<Self> {
+        let (children, len) = match value {
+            Value::Object(map) if !map.is_empty() => {
+                (Children::Object(map.iter()), map.len())
+            }
+            Value::Array(values) if !values.is_empty() => {
+                (Children::Array(values.iter()), values.len())
+            }
+            _ => return None,
+        };
+        Some(Self {
+            children,
+            len,
+            remaining: len,
+            comma,
+        })
+    }
+}
+
+impl<'a> Children<'a> {
+    /// Get the next child, with its key if this is an object
+    fn next(&mut self) -> Option<(Option<&'a str>, &'a Value)> {
+        match self {
+            Self::Object(iter) => {
+                iter.next().map(|(key, value)| (Some(key.as_str()), value))
+            }
+            Self::Array(iter) => iter.next().map(|value| (None, value)),
+        }
+    }
+
+    /// Skip `n` children, then get the next one
+    fn nth(&mut self, n: usize) -> Option<(Option<&'a str>, &'a Value)> {
+        match self {
+            Self::Object(iter) => {
+                iter.nth(n).map(|(key, value)| (Some(key.as_str()), value))
+            }
+            Self::Array(iter) => iter.nth(n).map(|value| (None, value)),
+        }
+    }
+}
+
+impl<'a> Display for JsonLine<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:indent$}", "", indent = self.depth * 2)?;
+        if let Some(key) = self.key {
+            // Keys are escaped the same as any other string
+            write!(f, "{}: ", Value::from(key))?;
+        }
+        match self.token {
+            // Compact formatting is the same as pretty for these
+            Token::Value(value) => write!(f, "{value}")?,
+            Token::Open(bracket) | Token::Close(bracket) => {
+                f.write_char(bracket)?
+            }
+        }
+        if self.comma {
+            f.write_char(',')?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::ResponseBody,
+        test_util::{header_map, Factory},
+    };
+    use itertools::Itertools;
+    use rstest::rstest;
+    use serde_json::json;
+
+    fn json_response(value: &Value) -> Arc<ResponseRecord> {
+        ResponseRecord {
+            headers: header_map([("content-type", "application/json")]),
+            body: ResponseBody::new(value.to_string().into_bytes().into()),
+            ..ResponseRecord::factory(())
+        }
+        .into()
+    }
+
+    /// Lines should match serde_json's pretty printer exactly
+    #[rstest]
+    #[case::scalar(json!("hello"))]
+    #[case::empty_object(json!({}))]
+    #[case::empty_array(json!([]))]
+    #[case::nested(json!({
+        "a": 1,
+        "b": [true, null, {"c": "d"}, []],
+        "escape \"me\"": "line\nbreak",
+        "e": {},
+        "f": [[1, 2], [3]],
+    }))]
+    fn test_pretty_json(#[case] value: Value) {
+        let expected = serde_json::to_string_pretty(&value).unwrap();
+        let body = PrettyBody::new(
+            json_response(&value),
+            SelectorType::Jsonpath,
+            None,
+        );
+        let PrettyBody::Json(json) = &body else {
+            panic!("Expected JSON body, got {body:?}");
+        };
+
+        let widths = json.line_widths();
+        assert_eq!(
+            widths.as_ref(),
+            expected.lines().map(str::len).collect_vec()
+        );
+        let lines = json
+            .lines(0..widths.len())
+            .into_iter()
+            .map(|line| line.to_string())
+            .join("\n");
+        assert_eq!(lines, expected);
+        assert_eq!(body.text(), expected);
+    }
+
+    /// Lines can be drawn from anywhere in the body, including from past the
+    /// first checkpoint
+    #[rstest]
+    #[case::start(0..10)]
+    #[case::checkpoint(CHECKPOINT_INTERVAL..CHECKPOINT_INTERVAL + 3)]
+    #[case::between_checkpoints(
+        CHECKPOINT_INTERVAL - 2..CHECKPOINT_INTERVAL + 2
+    )]
+    #[case::end(700..704)]
+    fn test_pretty_json_window(#[case] range: Range<usize>) {
+        let value = json!({
+            "users": (0..100)
+                .map(|i| json!({"id": i, "tags": ["a", "b"]}))
+                .collect_vec(),
+        });
+        let expected = serde_json::to_string_pretty(&value).unwrap();
+        let expected = expected.lines().collect_vec();
+        let body = PrettyBody::new(
+            json_response(&value),
+            SelectorType::Jsonpath,
+            None,
+        );
+
+        assert_eq!(body.line_widths().len(), expected.len());
+        let lines = body
+            .lines(range.clone())
+            .into_iter()
+            .map(|line| line.to_string())
+            .collect_vec();
+        assert_eq!(lines, expected[range]);
+    }
+
+    /// Queried and non-JSON bodies are formatted in full
+    #[rstest]
+    #[case::query(json_response(&json!({"a": 1})), Some("$.a"))]
+    #[case::not_json(
+        ResponseRecord {
+            body: ResponseBody::new(b"not json".as_slice().into()),
+            ..ResponseRecord::factory(())
+        }.into(),
+        None,
+    )]
+    fn test_pretty_body_text(
+        #[case] response: Arc<ResponseRecord>,
+        #[case] query: Option<&str>,
+    ) {
+        let body = PrettyBody::new(
+            Arc::clone(&response),
+            SelectorType::Jsonpath,
+            query,
+        );
+        assert!(matches!(body, PrettyBody::Text(_)));
+        assert_eq!(
+            body.text(),
+            response.body.display_text(SelectorType::Jsonpath, query)
+        );
+    }
+}
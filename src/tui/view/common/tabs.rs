@@ -10,8 +10,13 @@ use crate::tui::{
         },
     },
 };
-use ratatui::Frame;
-use std::fmt::Debug;
+use crossterm::event::MouseEvent;
+use ratatui::{
+    layout::{Position, Rect},
+    text::Line,
+    Frame,
+};
+use std::{cell::Cell, fmt::Debug};
 
 /// Multi-tab display. Generic parameter defines the available tabs.
 #[derive(Debug)]
@@ -20,6 +25,8 @@ where
     T: FixedSelect + Persistable<Persisted = T>,
 {
     tabs: Persistent<FixedSelectState<T, usize>>,
+    /// Area the tabs were last drawn to, for mapping clicks to tabs
+    area: Cell<Rect>,
 }
 
 impl<T> Tabs<T>
@@ -29,12 +36,36 @@ where
     pub fn new(persistent_key: PersistentKey) -> Self {
         Self {
             tabs: Persistent::new(persistent_key, Default::default()),
+            area: Cell::default(),
         }
     }
 
     pub fn selected(&self) -> &T {
         self.tabs.selected()
     }
+
+    /// Get the tab under a mouse click. This mirrors how ratatui lays out
+    /// tabs: each title is padded by a space on both sides, with a one-column
+    /// divider between tabs.
+    fn clicked_tab(&self, mouse_event: &MouseEvent) -> Option<T> {
+        let area = self.area.get();
+        let position = Position {
+            x: mouse_event.column,
+            y: mouse_event.row,
+        };
+        if !area.contains(position) {
+            return None;
+        }
+        let mut x = area.x;
+        for tab in T::iter() {
+            let width = Line::from(tab.to_string()).width() as u16 + 2;
+            if position.x < x + width {
+                return Some(tab);
+            }
+            x += width + 1;
+        }
+        None
+    }
 }
 
 impl<T> EventHandler for Tabs<T>
@@ -48,6 +79,20 @@ where
         match action {
             Action::Left => self.tabs.previous(),
             Action::Right => self.tabs.next(),
+            // Switch to the clicked tab, but let the parent see the click too,
+            // so it can grab focus
+            Action::LeftClick => {
+                if let Event::Input {
+                    event: crossterm::event::Event::Mouse(mouse_event),
+                    ..
+                } = &event
+                {
+                    if let Some(tab) = self.clicked_tab(mouse_event) {
+                        self.tabs.select(&tab);
+                    }
+                }
+                return Update::Propagate(event);
+            }
             _ => return Update::Propagate(event),
         }
         Update::Consumed
@@ -59,6 +104,7 @@ where
     T: FixedSelect + Persistable<Persisted = T>,
 {
     fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+        self.area.set(metadata.area());
         frame.render_widget(
             ratatui::widgets::Tabs::new(T::iter().map(|e| e.to_string()))
                 .select(self.tabs.selected_index())
@@ -67,3 +113,54 @@ where
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::{
+        test_util::{harness, TestHarness},
+        view::test_util::TestComponent,
+    };
+    use derive_more::Display;
+    use rstest::rstest;
+    use serde::{Deserialize, Serialize};
+    use strum::{EnumCount, EnumIter};
+
+    #[derive(
+        Copy,
+        Clone,
+        Debug,
+        Default,
+        Display,
+        EnumCount,
+        EnumIter,
+        PartialEq,
+        Serialize,
+        Deserialize,
+    )]
+    enum Tab {
+        #[default]
+        One,
+        Two,
+        Three,
+    }
+    impl FixedSelect for Tab {}
+
+    /// Clicking a tab selects it. Layout is ` One │ Two │ Three `
+    #[rstest]
+    #[case::first(0, Tab::One)]
+    #[case::title(8, Tab::Two)]
+    #[case::padding(10, Tab::Two)]
+    #[case::last(14, Tab::Three)]
+    // Past the last tab
+    #[case::none(25, Tab::One)]
+    fn test_click(harness: TestHarness, #[case] x: u16, #[case] expected: Tab) {
+        let mut component = TestComponent::new(
+            harness,
+            Tabs::<Tab>::new(PersistentKey::RecipeTab),
+            (),
+        );
+        let _ = component.click(x, 0);
+        assert_eq!(component.data().selected(), &expected);
+    }
+}
@@ -55,10 +55,20 @@ impl TemplatePreview {
             Self::Disabled { template }
         }
     }
+
+    /// Get the template being previewed
+    pub fn template(&self) -> &Template {
+        match self {
+            Self::Disabled { template } | Self::Enabled { template, .. } => {
+                template
+            }
+        }
+    }
 }
 
 impl Generate for &TemplatePreview {
-    type Output<'this> = Text<'this>
+    type Output<'this>
+        = Text<'this>
     where
         Self: 'this;
 
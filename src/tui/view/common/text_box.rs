@@ -1,4 +1,4 @@
-//! A single-line text box with callbacks
+//! A text box with callbacks
 
 use crate::tui::{
     context::TuiContext,
@@ -18,11 +18,14 @@ use ratatui::{
     Frame,
 };
 
-/// Single line text submission component
+/// Text submission component. Single-line unless
+/// [with_multiline](Self::with_multiline) is set.
 #[derive(derive_more::Debug, Default)]
 pub struct TextBox {
     // Parameters
     sensitive: bool,
+    /// Allow newlines in the text
+    multiline: bool,
     placeholder_text: String,
     /// Predicate function to apply visual validation effect
     #[debug(skip)]
@@ -40,6 +43,12 @@ pub struct TextBox {
     /// Called when user exits without saving (e.g. Escape)
     #[debug(skip)]
     on_cancel: Option<Callback>,
+    /// Called when user requests autocompletion (Tab)
+    #[debug(skip)]
+    on_complete: Option<Callback>,
+    /// Called when the user edits the text
+    #[debug(skip)]
+    on_change: Option<Callback>,
 }
 
 type Callback = Box<dyn Fn(&TextBox)>;
@@ -60,6 +69,13 @@ impl TextBox {
         self
     }
 
+    /// Allow multiple lines of text. Enter still submits; Alt+Enter or
+    /// Shift+Enter inserts a newline.
+    pub fn with_multiline(mut self, multiline: bool) -> Self {
+        self.multiline = multiline;
+        self
+    }
+
     /// Set placeholder (text to show when content is empty) on initialization
     pub fn with_placeholder(mut self, placeholder: impl Into<String>) -> Self {
         self.placeholder_text = placeholder.into();
@@ -99,11 +115,37 @@ impl TextBox {
         self
     }
 
+    /// Set the callback to be called when the user hits tab. If set, tab will
+    /// no longer be ignored by the text box. The callback can use
+    /// [insert_text](Self::insert_text) to fill in a completion.
+    pub fn with_on_complete(
+        mut self,
+        on_complete: impl 'static + Fn(&Self),
+    ) -> Self {
+        self.on_complete = Some(Box::new(on_complete));
+        self
+    }
+
+    /// Set the callback to be called whenever the user changes the text, e.g.
+    /// to update a filter as they type
+    pub fn with_on_change(
+        mut self,
+        on_change: impl 'static + Fn(&Self),
+    ) -> Self {
+        self.on_change = Some(Box::new(on_change));
+        self
+    }
+
     /// Get current text
     pub fn text(&self) -> &str {
         &self.state.text
     }
 
+    /// Get the text to the left of the cursor
+    pub fn text_before_cursor(&self) -> &str {
+        &self.state.text[..self.state.cursor]
+    }
+
     /// Move the text out of this text box and return it
     pub fn into_text(self) -> String {
         self.state.text
@@ -116,6 +158,15 @@ impl TextBox {
         self.submit();
     }
 
+    /// Insert text at the cursor, and move the cursor to the end of the
+    /// inserted text. Unlike [set_text](Self::set_text), this does *not*
+    /// trigger submission.
+    pub fn insert_text(&mut self, text: &str) {
+        for c in text.chars() {
+            self.state.insert(c);
+        }
+    }
+
     /// Check if the current input text is valid. Always returns true if there
     /// is no validator
     fn is_valid(&self) -> bool {
@@ -143,6 +194,13 @@ impl TextBox {
         }
     }
 
+    /// Call parent's autocompletion callback
+    fn complete(&mut self) {
+        if let Some(on_complete) = &self.on_complete {
+            on_complete(self);
+        }
+    }
+
     /// Call parent's on_click callback
     fn click(&mut self) {
         if let Some(on_click) = &self.on_click {
@@ -150,8 +208,33 @@ impl TextBox {
         }
     }
 
-    /// Handle input key event to modify text/cursor state
+    /// Should this key event insert a newline, rather than submit?
+    fn is_newline(&self, key_event: &KeyEvent) -> bool {
+        self.multiline
+            && key_event.code == KeyCode::Enter
+            && key_event
+                .modifiers
+                .intersects(KeyModifiers::ALT | KeyModifiers::SHIFT)
+    }
+
+    /// Handle input key event, and notify the parent if the text changed
     fn handle_key_event(&mut self, key_event: KeyEvent) {
+        // Every edit changes the length, so we can use that to detect changes
+        let len = self.state.text.len();
+        self.edit(key_event);
+        if self.state.text.len() != len {
+            if let Some(on_change) = &self.on_change {
+                on_change(self);
+            }
+        }
+    }
+
+    /// Handle input key event to modify text/cursor state
+    fn edit(&mut self, key_event: KeyEvent) {
+        if self.is_newline(&key_event) {
+            self.state.insert('\n');
+            return;
+        }
         match key_event.code {
             KeyCode::Char(c) => self.state.insert(c),
             KeyCode::Backspace => self.state.delete_left(),
@@ -180,6 +263,20 @@ impl TextBox {
 impl EventHandler for TextBox {
     fn update(&mut self, event: Event) -> Update {
         match event {
+            Event::Input {
+                event: crossterm::event::Event::Key(key_event),
+                ..
+            } if self.is_newline(&key_event) => {
+                self.handle_key_event(key_event)
+            }
+            Event::Input {
+                event: crossterm::event::Event::Key(key_event),
+                ..
+            } if key_event.code == KeyCode::Tab
+                && self.on_complete.is_some() =>
+            {
+                self.complete()
+            }
             Event::Input {
                 action: Some(Action::Submit),
                 ..
@@ -226,10 +323,15 @@ impl Draw for TextBox {
         frame.render_widget(Paragraph::new(text).style(style), metadata.area());
 
         if metadata.has_focus() {
+            // Let the input engine know text is being typed, so it doesn't
+            // hold keys back for multi-key sequences
+            TuiContext::get().input_engine.set_text_input(true);
+
             // Apply cursor styling on type
+            let (column, row) = self.state.cursor_position();
             let cursor_area = Rect {
-                x: metadata.area().x + self.state.cursor_offset() as u16,
-                y: metadata.area().y,
+                x: metadata.area().x + column as u16,
+                y: metadata.area().y + row as u16,
                 width: 1,
                 height: 1,
             };
@@ -329,9 +431,15 @@ impl TextState {
         }
     }
 
-    /// Get the **character** offset of the cursor into the text
-    fn cursor_offset(&self) -> usize {
-        self.text[..self.cursor].chars().count()
+    /// Get the `(column, row)` of the cursor, in characters. The row is only
+    /// non-zero for multi-line text.
+    fn cursor_position(&self) -> (usize, usize) {
+        let before = &self.text[..self.cursor];
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+        (
+            before[line_start..].chars().count(),
+            before.matches('\n').count(),
+        )
     }
 }
 
@@ -373,7 +481,7 @@ mod tests {
     fn assert_state(state: &TextState, text: &str, cursor: usize) {
         assert_eq!(state.text, text, "Text does not match");
         assert_eq!(
-            state.cursor_offset(),
+            state.text[..state.cursor].chars().count(),
             cursor,
             "Cursor character offset does not match"
         )
@@ -486,6 +594,84 @@ mod tests {
         component.assert_buffer_lines([vec![text("•••••"), cursor(" ")]]);
     }
 
+    /// Alt+Enter inserts a newline in multi-line mode, and plain Enter still
+    /// submits
+    #[rstest]
+    fn test_multiline(#[with(6, 2)] harness: TestHarness) {
+        let submit_count = Counter::default();
+        let mut component = TestComponent::new(
+            harness,
+            TextBox::default()
+                .with_multiline(true)
+                .with_on_submit(submit_count.callback()),
+            (),
+        );
+
+        component.send_text("ab").assert_empty();
+        component
+            .send_input(crossterm::event::Event::Key(KeyEvent::new(
+                KeyCode::Enter,
+                KeyModifiers::ALT,
+            )))
+            .assert_empty();
+        component.send_text("c").assert_empty();
+        assert_state(&component.data().state, "ab\nc", 4);
+        assert_eq!(component.data().state.cursor_position(), (1, 1));
+        assert_eq!(submit_count, 0);
+        component.assert_buffer_lines([
+            vec![text("ab    ")],
+            vec![text("c"), cursor(" "), text("    ")],
+        ]);
+
+        component.send_key(KeyCode::Enter).assert_empty();
+        assert_eq!(submit_count, 1);
+    }
+
+    /// Alt+Enter is a plain submission in single-line mode
+    #[rstest]
+    fn test_single_line_alt_enter(#[with(6, 1)] harness: TestHarness) {
+        let submit_count = Counter::default();
+        let mut component = TestComponent::new(
+            harness,
+            TextBox::default().with_on_submit(submit_count.callback()),
+            (),
+        );
+        component
+            .send_input(crossterm::event::Event::Key(KeyEvent::new(
+                KeyCode::Enter,
+                KeyModifiers::ALT,
+            )))
+            .assert_empty();
+        assert_state(&component.data().state, "", 0);
+        assert_eq!(submit_count, 1);
+    }
+
+    /// Tab is only handled if there's a completion callback. Completion text
+    /// is inserted at the cursor without submitting.
+    #[rstest]
+    fn test_complete(#[with(10, 1)] harness: TestHarness) {
+        let complete_count = Counter::default();
+        let mut component = TestComponent::new(
+            harness,
+            TextBox::default().with_on_complete(complete_count.callback()),
+            (),
+        );
+
+        component.send_text("helo").assert_empty();
+        component.send_key(KeyCode::Left).assert_empty();
+        assert_eq!(component.data().text_before_cursor(), "hel");
+        component.send_key(KeyCode::Tab).assert_empty();
+        assert_eq!(complete_count, 1);
+
+        let submit_count = Counter::default();
+        let mut text_box = TextBox::default()
+            .with_default("he".into())
+            .with_on_submit(submit_count.callback());
+        text_box.insert_text("llo");
+        assert_state(&text_box.state, "hello", 5);
+        assert_eq!(submit_count, 0);
+    }
+
     #[rstest]
     fn test_placeholder(#[with(6, 1)] harness: TestHarness) {
         let component = TestComponent::new(
@@ -5,38 +5,183 @@ use crate::tui::{
         common::scrollbar::Scrollbar,
         draw::{Draw, DrawMetadata, Generate},
         event::{Event, EventHandler, Update},
+        state::persistence::{impl_persistable, Persistable},
     },
 };
 use ratatui::{
     layout::Layout,
     prelude::{Alignment, Constraint},
-    text::{Line, Text},
+    text::{Line, Span, Text},
     widgets::{Paragraph, ScrollbarOrientation},
     Frame,
 };
-use std::{cell::Cell, cmp, fmt::Debug};
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, cell::Cell, cmp, fmt::Debug, ops::Range};
 
 /// A scrollable (but not editable) block of text. Text is not externally
 /// mutable. If you need to update the text, store this in a `StateCell` and
 /// reconstruct the entire component.
 ///
 /// The generic parameter allows for any type that can be converted to ratatui's
-/// `Text`, e.g. `String` or `TemplatePreview`.
+/// `Text`, e.g. `String` or `TemplatePreview`. For text that could be very
+/// large, use [IndexedText] instead so only the visible lines are rendered.
 #[derive(Debug, Default)]
 pub struct TextWindow<T> {
     text: T,
     offset_x: u16,
-    offset_y: u16,
+    /// Vertical offset, in rows. This can exceed `u16` for large bodies
+    offset_y: usize,
+    /// Index of the line at the top of the window, as of the last draw. This
+    /// differs from `offset_y` when lines are wrapped
+    first_line: Cell<usize>,
     text_width: Cell<u16>,
-    text_height: Cell<u16>,
+    text_height: Cell<usize>,
     window_width: Cell<u16>,
     window_height: Cell<u16>,
 }
 
+#[derive(Clone)]
 pub struct TextWindowProps {
     /// Is there a search box below the content? This tells us if we need to
     /// offset the horizontal scroll box an extra row.
     pub has_search_box: bool,
+    pub toggles: TextWindowToggles,
+}
+
+/// Display settings for a text window that the user can flip on the fly. The
+/// text window gets rebuilt whenever its content changes, so these are stored
+/// by the parent and passed in on each draw.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct TextWindowToggles {
+    /// Wrap lines that are wider than the window, by character. This disables
+    /// horizontal scrolling
+    pub wrap: bool,
+    /// Show line numbers in the gutter. When wrapping, the number is only
+    /// shown on the first row of each line
+    pub line_numbers: bool,
+}
+
+impl TextWindowToggles {
+    /// Flip a setting if the action is one of the toggle actions. Return
+    /// `false` if the action isn't a toggle, so the event can be propagated
+    pub fn handle_action(&mut self, action: Action) -> bool {
+        match action {
+            Action::ToggleWrap => self.wrap = !self.wrap,
+            Action::ToggleLineNumbers => self.line_numbers = !self.line_numbers,
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl Default for TextWindowToggles {
+    fn default() -> Self {
+        Self {
+            wrap: false,
+            line_numbers: true,
+        }
+    }
+}
+
+impl_persistable!(TextWindowToggles);
+
+/// Content that can be displayed in a [TextWindow]. The window only asks for
+/// the lines that are in view, so content that's expensive to generate in full
+/// can still be drawn quickly. Anything that generates [Text] gets this for
+/// free.
+pub trait TextWindowContent {
+    /// Display width of each line, in columns. This also tells the window how
+    /// many lines there are
+    fn line_widths(&self) -> Cow<'_, [usize]>;
+
+    /// Generate the lines in a range. The range is always within the bounds of
+    /// [Self::line_widths]
+    fn lines(&self, range: Range<usize>) -> Vec<Line<'_>>;
+}
+
+impl<T: 'static> TextWindowContent for T
+where
+    for<'a> &'a T: Generate<Output<'a> = Text<'a>>,
+{
+    fn line_widths(&self) -> Cow<'_, [usize]> {
+        self.generate()
+            .lines
+            .iter()
+            .map(Line::width)
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    fn lines(&self, range: Range<usize>) -> Vec<Line<'_>> {
+        let mut lines = self.generate().lines;
+        lines.truncate(range.end);
+        lines.drain(..range.start);
+        lines
+    }
+}
+
+/// A string that's been indexed by line, so a [TextWindow] can pull out just
+/// the visible lines without walking the whole string on every draw. Use this
+/// for text that could be very large, such as a response body. Only drawing is
+/// windowed: the text has to be fully generated (e.g. prettified) before it's
+/// indexed, and the index costs a pass over the text up front, plus a couple
+/// words per line.
+#[derive(Debug, Default)]
+pub struct IndexedText {
+    text: String,
+    /// Byte offset of the start of each line
+    line_starts: Vec<usize>,
+    /// Display width of each line, in columns
+    line_widths: Vec<usize>,
+}
+
+impl IndexedText {
+    pub fn new(text: String) -> Self {
+        // Match the semantics of str::lines, which is how Text splits strings
+        let mut line_starts = Vec::new();
+        let mut line_widths = Vec::new();
+        let mut start = 0;
+        for line in text.split_inclusive('\n') {
+            line_starts.push(start);
+            line_widths.push(Span::raw(trim_line_ending(line)).width());
+            start += line.len();
+        }
+        Self {
+            text,
+            line_starts,
+            line_widths,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+}
+
+impl TextWindowContent for IndexedText {
+    fn line_widths(&self) -> Cow<'_, [usize]> {
+        Cow::Borrowed(&self.line_widths)
+    }
+
+    fn lines(&self, range: Range<usize>) -> Vec<Line<'_>> {
+        range
+            .map(|i| {
+                let start = self.line_starts[i];
+                let end = self
+                    .line_starts
+                    .get(i + 1)
+                    .copied()
+                    .unwrap_or(self.text.len());
+                trim_line_ending(&self.text[start..end]).into()
+            })
+            .collect()
+    }
+}
+
+/// Strip the trailing `\n` or `\r\n` from a line
+fn trim_line_ending(line: &str) -> &str {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    line.strip_suffix('\r').unwrap_or(line)
 }
 
 impl<T> TextWindow<T> {
@@ -45,6 +190,7 @@ impl<T> TextWindow<T> {
             text,
             offset_x: 0,
             offset_y: 0,
+            first_line: Cell::default(),
             text_width: Cell::default(),
             text_height: Cell::default(),
             window_width: Cell::default(),
@@ -56,12 +202,17 @@ impl<T> TextWindow<T> {
         &self.text
     }
 
-    /// Get the final line that we can't scroll past. This will be the first
-    /// line of the last page of text
-    fn max_scroll_line(&self) -> u16 {
+    /// Get the index of the line at the top of the window
+    pub fn first_line(&self) -> usize {
+        self.first_line.get()
+    }
+
+    /// Get the final row that we can't scroll past. This will be the first
+    /// row of the last page of text
+    fn max_scroll_line(&self) -> usize {
         self.text_height
             .get()
-            .saturating_sub(self.window_height.get())
+            .saturating_sub(self.window_height.get() as usize)
     }
 
     /// Get the final column that we can't scroll (horizontally) past. This will
@@ -72,17 +223,20 @@ impl<T> TextWindow<T> {
             .saturating_sub(self.window_width.get())
     }
 
-    fn scroll_up(&mut self, lines: u16) {
+    fn scroll_up(&mut self, lines: usize) {
         self.offset_y = self.offset_y.saturating_sub(lines);
     }
 
-    fn scroll_down(&mut self, lines: u16) {
-        self.offset_y = cmp::min(self.offset_y + lines, self.max_scroll_line());
+    fn scroll_down(&mut self, lines: usize) {
+        self.offset_y = cmp::min(
+            self.offset_y.saturating_add(lines),
+            self.max_scroll_line(),
+        );
     }
 
-    /// Scroll to a specific line number. The target line will end up as close
-    /// to the top of the page as possible
-    fn scroll_to(&mut self, line: u16) {
+    /// Scroll to a specific row. The target row will end up as close to the
+    /// top of the page as possible
+    fn scroll_to(&mut self, line: usize) {
         self.offset_y = cmp::min(line, self.max_scroll_line());
     }
 
@@ -101,15 +255,16 @@ impl<T: Debug> EventHandler for TextWindow<T> {
         let Some(action) = event.action() else {
             return Update::Propagate(event);
         };
+        let page = self.window_height.get() as usize;
         match action {
             Action::Up | Action::ScrollUp => self.scroll_up(1),
             Action::Down | Action::ScrollDown => self.scroll_down(1),
             Action::ScrollLeft => self.scroll_left(1),
             Action::ScrollRight => self.scroll_right(1),
-            Action::PageUp => self.scroll_up(self.window_height.get()),
-            Action::PageDown => self.scroll_down(self.window_height.get()),
+            Action::PageUp => self.scroll_up(page),
+            Action::PageDown => self.scroll_down(page),
             Action::Home => self.scroll_to(0),
-            Action::End => self.scroll_to(u16::MAX),
+            Action::End => self.scroll_to(usize::MAX),
             _ => return Update::Propagate(event),
         }
         Update::Consumed
@@ -118,8 +273,7 @@ impl<T: Debug> EventHandler for TextWindow<T> {
 
 impl<T> Draw<TextWindowProps> for TextWindow<T>
 where
-    T: 'static,
-    for<'a> &'a T: Generate<Output<'a> = Text<'a>>,
+    T: 'static + TextWindowContent,
 {
     fn draw(
         &self,
@@ -128,49 +282,123 @@ where
         metadata: DrawMetadata,
     ) {
         let styles = &TuiContext::get().styles;
-        let text = Paragraph::new(self.text.generate());
-        // Assume no line wrapping when calculating line count
-        let text_height = text.line_count(u16::MAX) as u16;
-
-        let [gutter_area, _, text_area] = Layout::horizontal([
-            // Size gutter based on width of max line number
-            Constraint::Length((text_height as f32).log10().floor() as u16 + 1),
-            Constraint::Length(1), // Spacer
-            Constraint::Min(0),
-        ])
+        let TextWindowToggles { wrap, line_numbers } = props.toggles;
+        let line_widths = self.text.line_widths();
+        let line_count = line_widths.len();
+
+        let [gutter_area, _, text_area] = Layout::horizontal(if line_numbers {
+            [
+                // Size gutter based on width of max line number
+                Constraint::Length(
+                    (line_count as f32).log10().floor() as u16 + 1,
+                ),
+                Constraint::Length(1), // Spacer
+                Constraint::Min(0),
+            ]
+        } else {
+            [
+                Constraint::Length(0),
+                Constraint::Length(0),
+                Constraint::Min(0),
+            ]
+        })
         .areas(metadata.area());
+        let window_width = text_area.width as usize;
+        let window_height = text_area.height as usize;
+
+        // Lines are wrapped by character rather than by word, so we know how
+        // many rows each line takes up from its width alone. This means we
+        // never have to generate lines that are out of view.
+        let row_count = |width: usize| {
+            if wrap {
+                width.div_ceil(window_width.max(1)).max(1)
+            } else {
+                1
+            }
+        };
+        let (text_width, text_height) = if wrap {
+            // Nothing to scroll horizontally
+            (
+                window_width,
+                line_widths.iter().map(|w| row_count(*w)).sum(),
+            )
+        } else {
+            (line_widths.iter().copied().max().unwrap_or(0), line_count)
+        };
 
         // Store text and window sizes for calculations in the update code
-        self.text_width.set(text.line_width() as u16);
+        self.text_width
+            .set(text_width.try_into().unwrap_or(u16::MAX));
         self.text_height.set(text_height);
         self.window_width.set(text_area.width);
         self.window_height.set(text_area.height);
 
+        // Find the first line in view, and how many of its rows are scrolled
+        // off the top. Then find the last line that fits in the window
+        let mut first_line = 0;
+        let mut skip_rows = self.offset_y;
+        while first_line < line_count
+            && row_count(line_widths[first_line]) <= skip_rows
+        {
+            skip_rows -= row_count(line_widths[first_line]);
+            first_line += 1;
+        }
+        self.first_line.set(first_line);
+        let max_rows = skip_rows + window_height;
+        let mut last_line = first_line;
+        let mut rows = 0;
+        while last_line < line_count && rows < max_rows {
+            rows += row_count(line_widths[last_line]);
+            last_line += 1;
+        }
+
+        // Generate just the visible lines, and split them into rows
+        let mut gutter: Vec<Line> = Vec::new();
+        let mut text: Vec<Line> = Vec::new();
+        for (i, line) in
+            (first_line..).zip(self.text.lines(first_line..last_line))
+        {
+            let line_rows = if wrap {
+                wrap_line(line, window_width, max_rows)
+            } else {
+                vec![line]
+            };
+            for (j, row) in line_rows.into_iter().enumerate() {
+                // Number only the first row of each line
+                gutter.push(if j == 0 {
+                    (i + 1).to_string().into()
+                } else {
+                    Line::default()
+                });
+                text.push(row);
+            }
+        }
+        let skip_rows = skip_rows.min(text.len());
+        text.drain(..skip_rows);
+        gutter.drain(..skip_rows);
+
         // Draw line numbers in the gutter
-        let first_line = self.offset_y + 1;
-        let last_line = cmp::min(first_line + text_area.height, text_height);
-        frame.render_widget(
-            Paragraph::new(
-                (first_line..=last_line)
-                    .map(|n| n.to_string().into())
-                    .collect::<Vec<Line>>(),
-            )
-            .alignment(Alignment::Right)
-            .style(styles.text_window.gutter),
-            gutter_area,
-        );
+        if line_numbers {
+            frame.render_widget(
+                Paragraph::new(gutter)
+                    .alignment(Alignment::Right)
+                    .style(styles.text_window.gutter),
+                gutter_area,
+            );
+        }
 
         // Draw the text content
+        let offset_x = if wrap { 0 } else { self.offset_x };
         frame.render_widget(
-            text.scroll((self.offset_y, self.offset_x)),
+            Paragraph::new(text).scroll((0, offset_x)),
             text_area,
         );
 
         // Scrollbars
         frame.render_widget(
             Scrollbar {
-                content_length: self.text_height.get() as usize,
-                offset: self.offset_y as usize,
+                content_length: self.text_height.get(),
+                offset: self.offset_y,
                 ..Default::default()
             },
             text_area,
@@ -186,3 +414,133 @@ where
         );
     }
 }
+
+/// Split a line into rows of at most `width` columns, stopping once there are
+/// `max_rows` rows. This wraps by character rather than by word, so the number
+/// of rows always matches the line's width.
+fn wrap_line(line: Line<'_>, width: usize, max_rows: usize) -> Vec<Line<'_>> {
+    let new_row = || Line {
+        style: line.style,
+        ..Default::default()
+    };
+    let mut rows = vec![new_row()];
+    let mut row_width = 0;
+    for span in &line.spans {
+        let content = span.content.as_ref();
+        let mut start = 0;
+        for (i, c) in content.char_indices() {
+            let char_width = Span::raw(&content[i..i + c.len_utf8()]).width();
+            if row_width > 0 && row_width + char_width > width {
+                rows.last_mut()
+                    .unwrap()
+                    .spans
+                    .push(slice_span(span, start..i));
+                if rows.len() >= max_rows {
+                    return rows;
+                }
+                rows.push(new_row());
+                row_width = 0;
+                start = i;
+            }
+            row_width += char_width;
+        }
+        rows.last_mut()
+            .unwrap()
+            .spans
+            .push(slice_span(span, start..content.len()));
+    }
+    rows
+}
+
+/// Get a piece of a span, without copying the text if it's borrowed
+fn slice_span<'a>(span: &Span<'a>, range: Range<usize>) -> Span<'a> {
+    let content = match &span.content {
+        Cow::Borrowed(content) => Cow::Borrowed(&content[range]),
+        Cow::Owned(content) => Cow::Owned(content[range].to_owned()),
+    };
+    Span::styled(content, span.style)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::{
+        test_util::{harness, TestHarness},
+        view::test_util::TestComponent,
+    };
+    use crossterm::event::KeyCode;
+    use itertools::Itertools;
+    use rstest::rstest;
+
+    /// Lines are split the same way as [str::lines]
+    #[rstest]
+    #[case::empty("", &[])]
+    #[case::single("abc", &["abc"])]
+    #[case::trailing_newline("a\nb\n", &["a", "b"])]
+    #[case::blank_lines("a\n\nb", &["a", "", "b"])]
+    #[case::crlf("a\r\nb", &["a", "b"])]
+    fn test_indexed_text(#[case] text: &str, #[case] expected: &[&str]) {
+        let indexed = IndexedText::new(text.to_owned());
+        let lines = indexed
+            .lines(0..expected.len())
+            .into_iter()
+            .map(|line| line.to_string())
+            .collect_vec();
+        assert_eq!(lines, expected);
+        assert_eq!(
+            indexed.line_widths().as_ref(),
+            expected.iter().map(|line| line.len()).collect_vec()
+        );
+    }
+
+    /// Text longer than `u16::MAX` lines can be scrolled all the way through.
+    /// Only the visible lines are rendered
+    #[rstest]
+    fn test_large_text(#[with(10, 2)] harness: TestHarness) {
+        let text = (1..=100_000).map(|i| i.to_string()).join("\n");
+        let mut component = TestComponent::new(
+            harness,
+            TextWindow::new(IndexedText::new(text)),
+            TextWindowProps {
+                has_search_box: false,
+                toggles: TextWindowToggles {
+                    wrap: false,
+                    line_numbers: false,
+                },
+            },
+        );
+        component.send_key(KeyCode::End).assert_empty();
+        assert_eq!(component.data().offset_y, 99_998);
+        component.assert_buffer_lines(["99999     ", "100000    "]);
+    }
+
+    /// Wrapped lines are split by character, and the scroll offset is in rows
+    /// rather than lines
+    #[rstest]
+    fn test_wrap(#[with(7, 2)] harness: TestHarness) {
+        let mut component = TestComponent::new(
+            harness,
+            TextWindow::new(IndexedText::new("abcdefghij\nxyz".to_owned())),
+            TextWindowProps {
+                has_search_box: false,
+                toggles: TextWindowToggles {
+                    wrap: true,
+                    line_numbers: true,
+                },
+            },
+        );
+        component.send_key(KeyCode::Down).assert_empty();
+        assert_eq!(component.data().offset_y, 1);
+        let styles = &TuiContext::get().styles;
+        component.assert_buffer_lines([
+            vec![
+                Span::styled(" ", styles.text_window.gutter),
+                " fghij".into(),
+            ],
+            vec![
+                Span::styled("2", styles.text_window.gutter),
+                " xyz  ".into(),
+            ],
+        ]);
+    }
+}
@@ -1,16 +1,29 @@
+mod batch_send;
+mod chain_inspector;
+mod command_palette;
 mod exchange_body;
 mod exchange_pane;
+mod explain;
+mod file_picker;
+mod golden_diff;
+mod graphql_editor;
 mod help;
 mod history;
 mod internal;
+mod message_log;
 mod misc;
 mod primary;
 mod profile_select;
 mod recipe_list;
 mod recipe_pane;
+mod request_preview;
+mod request_queue;
 mod request_view;
 mod response_view;
 mod root;
+mod run_report;
+mod scheduled_requests;
+mod websocket_console;
 
 pub use internal::Component;
 pub use root::Root;
@@ -0,0 +1,356 @@
+use crate::{
+    collection::{Profile, ProfileId, RecipeId},
+    http::{BuildOptions, RequestId},
+    tui::{
+        context::TuiContext,
+        input::Action,
+        message::{Message, RequestConfig},
+        view::{
+            common::{
+                modal::Modal,
+                table::{Table, ToggleRow},
+            },
+            component::Component,
+            draw::{Draw, DrawMetadata, Generate},
+            event::{Event, EventHandler, Update},
+            state::{select::SelectState, RequestStateSummary},
+            RequestState, ViewContext,
+        },
+    },
+};
+use bytesize::ByteSize;
+use itertools::Itertools;
+use ratatui::{
+    layout::Constraint,
+    text::{Line, Span},
+    widgets::{Row, TableState},
+    Frame,
+};
+
+/// Send one recipe once per profile, and compare the results side by side.
+/// The user first picks which profiles to include (all by default), then the
+/// modal turns into a table of results that fills in as each request finishes.
+#[derive(Debug)]
+pub struct BatchSendModal {
+    recipe_id: RecipeId,
+    /// Options from the recipe pane, so every profile gets the same request
+    options: BuildOptions,
+    /// Have the requests been sent yet? Before that, the table is a checklist
+    /// of profiles
+    sent: bool,
+    rows: Component<SelectState<BatchRow, TableState>>,
+}
+
+/// One profile in the batch
+#[derive(Debug)]
+struct BatchRow {
+    profile_id: ProfileId,
+    profile_name: String,
+    /// Include this profile in the batch? Only used before sending
+    enabled: bool,
+    /// Latest state of this profile's request. `None` until it starts building
+    request: Option<RequestStateSummary>,
+    /// Size of the response body, once it's received
+    size: Option<ByteSize>,
+}
+
+impl BatchSendModal {
+    pub fn new(
+        recipe_id: RecipeId,
+        options: BuildOptions,
+        profiles: &[Profile],
+    ) -> Self {
+        let rows = profiles
+            .iter()
+            .map(|profile| BatchRow {
+                profile_id: profile.id.clone(),
+                profile_name: profile.name().to_owned(),
+                enabled: true,
+                request: None,
+                size: None,
+            })
+            .collect();
+        Self {
+            recipe_id,
+            options,
+            sent: false,
+            rows: SelectState::builder(rows).build().into(),
+        }
+    }
+
+    /// Send a request for each checked profile. Unchecked profiles are dropped
+    /// from the table, since they won't have any results
+    fn send(&mut self) {
+        let rows = self
+            .rows
+            .data()
+            .items()
+            .iter()
+            .filter(|row| row.enabled)
+            .map(|row| BatchRow {
+                profile_id: row.profile_id.clone(),
+                profile_name: row.profile_name.clone(),
+                enabled: true,
+                request: None,
+                size: None,
+            })
+            .collect_vec();
+        if rows.is_empty() {
+            ViewContext::send_message(Message::Notify(
+                "No profiles selected".into(),
+            ));
+            return;
+        }
+
+        for row in &rows {
+            ViewContext::send_message(Message::HttpBeginRequest(
+                RequestConfig {
+                    profile_id: Some(row.profile_id.clone()),
+                    recipe_id: self.recipe_id.clone(),
+                    options: self.options.clone(),
+                },
+            ));
+        }
+        self.rows = SelectState::builder(rows).build().into();
+        self.sent = true;
+    }
+
+    /// Update the row for a request, if it belongs to this batch. Requests
+    /// are matched by profile, and the first request we see for a profile
+    /// claims its row
+    fn update_request(&mut self, state: &RequestState) {
+        if !self.sent || state.recipe_id() != &self.recipe_id {
+            return;
+        }
+        let Some(profile_id) = state.profile_id() else {
+            return;
+        };
+        let id = state.id();
+        if let Some(row) =
+            self.rows.data_mut().items_mut().iter_mut().find(|row| {
+                &row.profile_id == profile_id
+                    && row.request_id().map_or(true, |row_id| row_id == id)
+            })
+        {
+            row.request = Some(state.into());
+            row.size = state.response_metadata().map(|metadata| metadata.size);
+        }
+    }
+
+    /// Show the highlighted profile's request in the exchange pane
+    fn select_request(&self) {
+        if let Some(request_id) =
+            self.rows.data().selected().and_then(BatchRow::request_id)
+        {
+            ViewContext::push_event(Event::HttpSelectRequest(Some(request_id)));
+            ViewContext::push_event(Event::CloseModal);
+        }
+    }
+}
+
+impl Modal for BatchSendModal {
+    fn title(&self) -> Line<'_> {
+        let input_engine = &TuiContext::get().input_engine;
+        let styles = &TuiContext::get().styles;
+        let mut title = vec![
+            "Send ".into(),
+            Span::styled(self.recipe_id.to_string(), styles.text.primary),
+            " to profiles".into(),
+        ];
+        if !self.sent {
+            title.push(
+                format!(
+                    " | {} | {}",
+                    input_engine.add_hint("Toggle", Action::Toggle),
+                    input_engine.add_hint("Send", Action::Submit)
+                )
+                .into(),
+            );
+        }
+        title.into()
+    }
+
+    fn dimensions(&self) -> (Constraint, Constraint) {
+        (
+            Constraint::Length(80),
+            // Leave room for the header row
+            Constraint::Length(
+                self.rows.data().items().len().min(20) as u16 + 1,
+            ),
+        )
+    }
+}
+
+impl EventHandler for BatchSendModal {
+    fn update(&mut self, event: Event) -> Update {
+        match event {
+            Event::Input {
+                action: Some(Action::Toggle),
+                ..
+            } if !self.sent => {
+                if let Some(row) = self.rows.data_mut().selected_mut() {
+                    row.enabled ^= true;
+                }
+            }
+            Event::Input {
+                action: Some(Action::Submit),
+                ..
+            } => {
+                if self.sent {
+                    self.select_request();
+                } else {
+                    self.send();
+                }
+            }
+            // Fill in results, but let the root store the new state too
+            Event::HttpSetState(ref state) => {
+                self.update_request(state);
+                return Update::Propagate(event);
+            }
+            _ => return Update::Propagate(event),
+        }
+        Update::Consumed
+    }
+
+    fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
+        vec![self.rows.as_child()]
+    }
+}
+
+impl Draw for BatchSendModal {
+    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+        let rows = self.rows.data().items();
+        if self.sent {
+            let table = Table {
+                header: Some(["Profile", "Status", "Duration", "Size"]),
+                rows: rows.iter().map(BatchRow::generate_result).collect_vec(),
+                column_widths: &[
+                    Constraint::Percentage(40),
+                    Constraint::Percentage(30),
+                    Constraint::Percentage(15),
+                    Constraint::Percentage(15),
+                ],
+                ..Default::default()
+            };
+            self.rows
+                .draw(frame, table.generate(), metadata.area(), true);
+        } else {
+            let table = Table {
+                header: Some(["", "Profile"]),
+                rows: rows
+                    .iter()
+                    .map(|row| {
+                        ToggleRow::new([row.profile_name.as_str()], row.enabled)
+                            .generate()
+                    })
+                    .collect_vec(),
+                column_widths: &[Constraint::Min(3), Constraint::Min(0)],
+                ..Default::default()
+            };
+            self.rows
+                .draw(frame, table.generate(), metadata.area(), true);
+        }
+    }
+}
+
+impl BatchRow {
+    fn request_id(&self) -> Option<RequestId> {
+        self.request.as_ref().map(RequestStateSummary::id)
+    }
+
+    /// Generate a row for the results table
+    fn generate_result(&self) -> Row<'_> {
+        let styles = &TuiContext::get().styles;
+        let status: Span = match &self.request {
+            None => "Waiting...".into(),
+            Some(RequestStateSummary::Building { .. }) => {
+                "Initializing...".into()
+            }
+            Some(RequestStateSummary::BuildError { .. }) => {
+                Span::styled("Build error", styles.text.error)
+            }
+            Some(RequestStateSummary::Loading { queued: true, .. }) => {
+                "Queued...".into()
+            }
+            Some(RequestStateSummary::Loading { .. }) => "Loading...".into(),
+            Some(RequestStateSummary::Response(exchange)) => {
+                exchange.status.generate()
+            }
+            Some(RequestStateSummary::RequestError { .. }) => {
+                Span::styled("Request error", styles.text.error)
+            }
+        };
+        let duration = self
+            .request
+            .as_ref()
+            .and_then(RequestStateSummary::duration)
+            .generate();
+        let size = self.size.map(|size| size.to_string()).unwrap_or_default();
+        Row::new([
+            self.profile_name.as_str().into(),
+            status,
+            duration,
+            size.into(),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::Exchange,
+        test_util::{assert_matches, Factory},
+        tui::{
+            test_util::{harness, TestHarness},
+            view::test_util::TestComponent,
+        },
+    };
+    use crossterm::event::KeyCode;
+    use rstest::rstest;
+
+    /// Uncheck a profile, send to the rest, then fill in a result
+    #[rstest]
+    fn test_batch_send(harness: TestHarness) {
+        let recipe_id: RecipeId = "recipe1".into();
+        let profiles = ["dev", "staging", "prod"].map(|id| Profile {
+            id: id.into(),
+            ..Profile::factory(())
+        });
+        let mut component = TestComponent::new(
+            harness,
+            BatchSendModal::new(
+                recipe_id.clone(),
+                BuildOptions::default(),
+                &profiles,
+            ),
+            (),
+        );
+
+        // Skip staging
+        component.send_key(KeyCode::Down).assert_empty();
+        component.send_key(KeyCode::Char(' ')).assert_empty();
+        component.send_key(KeyCode::Enter).assert_empty();
+        for expected in ["dev", "prod"] {
+            let request_config = assert_matches!(
+                component.harness_mut().pop_message_now(),
+                Message::HttpBeginRequest(request_config) => request_config,
+            );
+            assert_eq!(request_config.profile_id, Some(expected.into()));
+            assert_eq!(request_config.recipe_id, recipe_id);
+        }
+        component.harness_mut().assert_messages_empty();
+        assert!(component.data().sent);
+
+        // Request state is always propagated, so the root can store it
+        let exchange = Exchange::factory((Some("prod".into()), recipe_id));
+        let id = exchange.id;
+        let _ = component
+            .update_draw(Event::HttpSetState(RequestState::response(exchange)));
+        let rows = component.data().rows.data().items();
+        assert_eq!(
+            rows.iter().map(BatchRow::request_id).collect_vec(),
+            vec![None, Some(id)]
+        );
+    }
+}
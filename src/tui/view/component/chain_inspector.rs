@@ -0,0 +1,465 @@
+//! Modal showing the definition, cache state, and latest value of chains
+
+use crate::{
+    collection::{
+        Chain, ChainRequestSection, ChainRequestTrigger, ChainSource, ProfileId,
+    },
+    template::{self, ChainCacheState, ChainResolution, Template},
+    tui::{
+        context::TuiContext,
+        input::Action,
+        message::Message,
+        view::{
+            common::{
+                list::List,
+                modal::{IntoModal, Modal},
+                table::Table,
+            },
+            component::Component,
+            draw::{Draw, DrawMetadata, Generate},
+            event::{Event, EventHandler, Update},
+            state::select::SelectState,
+            ViewContext,
+        },
+    },
+    util::format_duration,
+};
+use chrono::Local;
+use itertools::Itertools;
+use ratatui::{
+    layout::{Constraint, Layout},
+    text::{Line, Span, Text},
+    Frame,
+};
+
+/// Placeholder for values of sensitive chains
+const SENSITIVE_VALUE: &str = "<sensitive>";
+
+/// Show where each chain gets its value from, whether that value is cached,
+/// and what it resolved to most recently. If multiple chains are given,
+/// they're listed on the left so the user can pick one. The selected chain
+/// can be resolved again, or have its cached value cleared.
+#[derive(Debug)]
+pub struct ChainInspector {
+    select: Component<SelectState<Chain>>,
+    /// Profile to use when resolving a chain again
+    profile_id: Option<ProfileId>,
+    /// Show the values of sensitive chains? Hidden by default
+    reveal: bool,
+}
+
+impl ChainInspector {
+    pub fn new(chains: Vec<Chain>, profile_id: Option<ProfileId>) -> Self {
+        Self {
+            select: SelectState::builder(chains).build().into(),
+            profile_id,
+            reveal: false,
+        }
+    }
+
+    /// Are there multiple chains to pick from?
+    fn has_list(&self) -> bool {
+        self.select.data().items().len() > 1
+    }
+
+    /// Resolve the selected chain from scratch, in the background. The
+    /// details will update once it's done
+    fn resolve_selected(&self) {
+        if let Some(chain) = self.select.data().selected() {
+            ViewContext::send_message(Message::ChainResolve {
+                chain_id: chain.id.clone(),
+                profile_id: self.profile_id.clone(),
+            });
+        }
+    }
+
+    /// Forget the last value of the selected chain, including its cached
+    /// secret
+    fn clear_selected(&self) {
+        if let Some(chain) = self.select.data().selected() {
+            let message = if template::clear_chain(&chain.id) {
+                format!("Cleared chain `{}`", chain.id)
+            } else {
+                format!("Nothing to clear for chain `{}`", chain.id)
+            };
+            ViewContext::send_message(Message::Notify(message));
+        }
+    }
+}
+
+impl Modal for ChainInspector {
+    fn title(&self) -> Line<'_> {
+        let input_engine = &TuiContext::get().input_engine;
+        let title = match self.select.data().items() {
+            [chain] => format!("Chain `{}`", chain.id),
+            _ => "Chains".into(),
+        };
+        let reveal = if self.reveal { "Hide" } else { "Reveal" };
+        format!(
+            "{title} | {} | {} | {}",
+            input_engine.add_hint("Resolve", Action::Submit),
+            input_engine.add_hint("Clear", Action::CancelRequest),
+            input_engine.add_hint(reveal, Action::Toggle),
+        )
+        .into()
+    }
+
+    fn dimensions(&self) -> (Constraint, Constraint) {
+        (
+            Constraint::Percentage(80),
+            // Fit all the detail rows, and as much of the list as possible
+            Constraint::Length(
+                self.select.data().items().len().clamp(5, 20) as u16
+            ),
+        )
+    }
+}
+
+impl EventHandler for ChainInspector {
+    fn update(&mut self, event: Event) -> Update {
+        match event.action() {
+            Some(Action::Submit) => self.resolve_selected(),
+            Some(Action::CancelRequest) => self.clear_selected(),
+            Some(Action::Toggle) => self.reveal ^= true,
+            _ => return Update::Propagate(event),
+        }
+        Update::Consumed
+    }
+
+    fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
+        vec![self.select.as_child()]
+    }
+}
+
+impl Draw for ChainInspector {
+    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+        let area = metadata.area();
+        let detail_area = if self.has_list() {
+            let [list_area, detail_area] = Layout::horizontal([
+                Constraint::Length(20),
+                Constraint::Min(0),
+            ])
+            .spacing(1)
+            .areas(area);
+            self.select.draw(
+                frame,
+                List::new(self.select.data().items()),
+                list_area,
+                true,
+            );
+            detail_area
+        } else {
+            area
+        };
+
+        if let Some(chain) = self.select.data().selected() {
+            let table = Table {
+                rows: details(chain, self.reveal),
+                column_widths: &[Constraint::Length(11), Constraint::Min(0)],
+                ..Default::default()
+            };
+            frame.render_widget(table.generate(), detail_area);
+        } else {
+            frame.render_widget("No chains in the collection", detail_area);
+        }
+    }
+}
+
+/// Chains whose last resolution failed are highlighted in the list
+impl Generate for &Chain {
+    type Output<'this>
+        = Line<'this>
+    where
+        Self: 'this;
+
+    fn generate<'this>(self) -> Self::Output<'this>
+    where
+        Self: 'this,
+    {
+        let styles = &TuiContext::get().styles;
+        let failed = template::last_resolution(&self.id)
+            .is_some_and(|resolution| resolution.outcome.is_err());
+        if failed {
+            Span::styled(self.id.to_string(), styles.text.error).into()
+        } else {
+            self.id.to_string().into()
+        }
+    }
+}
+
+impl IntoModal for (Vec<Chain>, Option<ProfileId>) {
+    type Target = ChainInspector;
+
+    fn into_modal(self) -> Self::Target {
+        ChainInspector::new(self.0, self.1)
+    }
+}
+
+/// Get a row for each piece of info about a chain. Values of sensitive chains
+/// are masked unless `reveal` is enabled
+fn details(chain: &Chain, reveal: bool) -> Vec<[Text<'static>; 2]> {
+    let styles = &TuiContext::get().styles;
+    let selector = match &chain.selector {
+        Some(selector) => format!("{selector} ({})", chain.selector_type),
+        None => "None".into(),
+    };
+    let (value, resolved) = match template::last_resolution(&chain.id) {
+        None => ("Not resolved yet".into(), Text::default()),
+        Some(ChainResolution {
+            resolved_at,
+            profile_id,
+            outcome,
+            sensitive,
+            ..
+        }) => {
+            let value = match outcome {
+                Ok(_) if sensitive && !reveal => SENSITIVE_VALUE.into(),
+                Ok(value) => preview_value(&value).into(),
+                Err(error) => Span::styled(error, styles.text.error).into(),
+            };
+            let profile = match profile_id {
+                Some(profile_id) => format!("profile `{profile_id}`"),
+                None => "no profile".into(),
+            };
+            let resolved = format!(
+                "{} ({profile})",
+                resolved_at.with_timezone(&Local).format("%H:%M:%S")
+            );
+            (value, resolved.into())
+        }
+    };
+    vec![
+        ["Source".into(), describe_source(&chain.source).into()],
+        ["Selector".into(), selector.into()],
+        [
+            "Cache".into(),
+            describe_cache(&template::cache_state(chain)).into(),
+        ],
+        ["Last Value".into(), value],
+        ["Resolved".into(), resolved],
+    ]
+}
+
+/// Explain where the next render of a chain will get its value from
+fn describe_cache(state: &ChainCacheState) -> String {
+    match state {
+        ChainCacheState::Uncached => "None, resolved on every render".into(),
+        ChainCacheState::History(trigger) => format!(
+            "Latest response from history, trigger {}",
+            describe_trigger(*trigger)
+        ),
+        ChainCacheState::Cached { expires_in } => format!(
+            "Cached, expires in {}",
+            chrono::Duration::from_std(*expires_in)
+                .map(|duration| format_duration(&duration))
+                .unwrap_or_default()
+        ),
+        ChainCacheState::Expired => "Expired".into(),
+        ChainCacheState::Empty => "Empty".into(),
+    }
+}
+
+/// Describe when a chained request is sent
+fn describe_trigger(trigger: ChainRequestTrigger) -> String {
+    match trigger {
+        ChainRequestTrigger::Never => "never".into(),
+        ChainRequestTrigger::NoHistory => "no history".into(),
+        ChainRequestTrigger::Expire(duration) => format!(
+            "expire after {}",
+            chrono::Duration::from_std(duration)
+                .map(|duration| format_duration(&duration))
+                .unwrap_or_default()
+        ),
+        ChainRequestTrigger::Always => "always".into(),
+    }
+}
+
+/// Summarize where a chain gets its value from, in a single line
+fn describe_source(source: &ChainSource) -> String {
+    let tag: &str = source.into();
+    let description = match source {
+        ChainSource::Request {
+            recipe,
+            trigger,
+            section,
+        } => {
+            let section = match section {
+                ChainRequestSection::Body => "body".into(),
+                ChainRequestSection::Header(header) => {
+                    format!("header `{header}`")
+                }
+                ChainRequestSection::Status => "status".into(),
+            };
+            format!(
+                "recipe `{recipe}`, {section}, trigger {}",
+                describe_trigger(*trigger)
+            )
+        }
+        ChainSource::Command { command, .. } => {
+            format!("`{}`", command.iter().map(Template::as_str).join(" "))
+        }
+        ChainSource::File { path } => format!("`{path}`"),
+        ChainSource::Prompt { message, .. }
+        | ChainSource::FilePicker { message, .. } => message
+            .as_ref()
+            .map(|message| format!("\"{message}\""))
+            .unwrap_or_default(),
+        ChainSource::Script { inputs, .. } if inputs.is_empty() => {
+            String::new()
+        }
+        ChainSource::Script { inputs, .. } => {
+            format!("inputs: {}", inputs.keys().join(", "))
+        }
+        ChainSource::Vault { path, field, .. } => {
+            format!("`{path}`, field `{field}`")
+        }
+        ChainSource::OnePassword { reference, .. } => format!("`{reference}`"),
+        ChainSource::AwsSecret { secret_id, .. } => format!("`{secret_id}`"),
+    };
+    format!("!{tag} {description}").trim_end().to_owned()
+}
+
+/// Get the first line of a value, marking it if anything was cut off
+fn preview_value(value: &[u8]) -> String {
+    let value = String::from_utf8_lossy(value);
+    let mut lines = value.lines();
+    let first = lines.next().unwrap_or_default();
+    if lines.next().is_some() {
+        format!("{first}…")
+    } else {
+        first.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_util::{assert_matches, Factory},
+        tui::{
+            test_util::{harness, TestHarness},
+            view::test_util::TestComponent,
+        },
+    };
+    use crossterm::event::KeyCode;
+    use rstest::rstest;
+    use std::time::Duration;
+
+    #[rstest]
+    #[case::request(
+        ChainSource::Request {
+            recipe: "login".into(),
+            trigger: ChainRequestTrigger::Expire(Duration::from_secs(60)),
+            section: ChainRequestSection::Header("Token".into()),
+        },
+        "!request recipe `login`, header `Token`, trigger expire after 60.00s"
+    )]
+    #[case::command(
+        ChainSource::command(["echo", "hi"]),
+        "!command `echo hi`"
+    )]
+    #[case::prompt(
+        ChainSource::Prompt {
+            message: None,
+            default: None,
+            options: vec![],
+            multiline: false,
+        },
+        "!prompt"
+    )]
+    fn test_describe_source(
+        #[case] source: ChainSource,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(describe_source(&source), expected);
+    }
+
+    /// Chains that haven't been resolved are marked as such. With multiple
+    /// chains, the details follow the selection in the list
+    #[rstest]
+    fn test_inspect(harness: TestHarness) {
+        let mut component =
+            TestComponent::new(harness, (chains(), None).into_modal(), ());
+        assert!(component
+            .data()
+            .title()
+            .to_string()
+            .starts_with("Chains | "));
+        assert_eq!(rows(&component)[1].1, "$.id (JSONPath)");
+        assert_eq!(
+            rows(&component)[2].1,
+            "Latest response from history, trigger never"
+        );
+        assert_eq!(rows(&component)[3].1, "Not resolved yet");
+
+        component.send_key(KeyCode::Down).assert_empty();
+        assert_eq!(rows(&component)[1].1, "None");
+    }
+
+    /// The selected chain can be resolved again with the inspector's
+    /// profile, or cleared
+    #[rstest]
+    fn test_actions(harness: TestHarness) {
+        let mut component = TestComponent::new(
+            harness,
+            (chains(), Some("profile1".into())).into_modal(),
+            (),
+        );
+
+        component.send_key(KeyCode::Enter).assert_empty();
+        let (chain_id, profile_id) = assert_matches!(
+            component.harness_mut().pop_message_now(),
+            Message::ChainResolve { chain_id, profile_id } =>
+                (chain_id, profile_id),
+        );
+        assert_eq!(chain_id, "inspect1".into());
+        assert_eq!(profile_id, Some("profile1".into()));
+
+        component.send_key(KeyCode::Delete).assert_empty();
+        let message = assert_matches!(
+            component.harness_mut().pop_message_now(),
+            Message::Notify(message) => message,
+        );
+        assert_eq!(message, "Nothing to clear for chain `inspect1`");
+
+        assert!(!component.data().reveal);
+        component.send_key(KeyCode::Char(' ')).assert_empty();
+        assert!(component.data().reveal);
+    }
+
+    fn chains() -> Vec<Chain> {
+        vec![
+            Chain {
+                id: "inspect1".into(),
+                selector: Some("$.id".into()),
+                ..Chain::factory(())
+            },
+            Chain {
+                id: "inspect2".into(),
+                ..Chain::factory(())
+            },
+        ]
+    }
+
+    /// Get the label and value of each detail row for the selected chain
+    fn rows(
+        component: &TestComponent<ChainInspector, ()>,
+    ) -> Vec<(String, String)> {
+        let inspector = component.data();
+        details(
+            inspector.select.data().selected().unwrap(),
+            inspector.reveal,
+        )
+        .into_iter()
+        .map(|[label, value]| {
+            let value = value
+                .lines
+                .iter()
+                .flat_map(|line| &line.spans)
+                .map(|span| span.content.as_ref())
+                .collect::<String>();
+            (label.lines[0].spans[0].content.to_string(), value)
+        })
+        .collect_vec()
+    }
+}
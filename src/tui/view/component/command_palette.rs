@@ -0,0 +1,379 @@
+//! Modal to search every available action and recipe by name
+
+use crate::{
+    collection::{RecipeId, RecipeTree},
+    tui::{
+        context::TuiContext,
+        input::Action,
+        message::Message,
+        view::{
+            common::{
+                actions::GlobalAction, list::List, modal::Modal,
+                text_box::TextBox,
+            },
+            component::{recipe_pane::RecipeMenuAction, Component},
+            draw::{Draw, DrawMetadata, Generate},
+            event::{Event, EventHandler, Update},
+            state::select::SelectState,
+            ViewContext,
+        },
+    },
+    util::fuzzy_match,
+};
+use itertools::Itertools;
+use ratatui::{
+    layout::{Constraint, Layout},
+    text::{Line, Span},
+    Frame,
+};
+use strum::IntoEnumIterator;
+
+/// A searchable list of everything the user can do: bound input actions,
+/// global and recipe actions from the actions menu, and jumping to a recipe.
+/// Typing filters the list with fuzzy matching. Selecting a command triggers
+/// the same event as its key binding or menu entry would.
+#[derive(Debug)]
+pub struct CommandPalette {
+    /// Every available command, in the default order
+    commands: Vec<Command>,
+    /// Query to filter commands by
+    text_box: Component<TextBox>,
+    /// Commands that match the query, best match first
+    select: Component<SelectState<Command>>,
+}
+
+/// Something that can be triggered from the palette
+#[derive(Clone, Debug)]
+enum Command {
+    /// Simulate a key press for a bound action
+    Input(Action),
+    /// An entry from the global actions menu
+    Global(GlobalAction),
+    /// An entry from the recipe actions menu, applied to the selected recipe
+    Recipe(RecipeMenuAction),
+    /// Select a recipe in the recipe list
+    SelectRecipe { id: RecipeId, name: String },
+}
+
+impl CommandPalette {
+    /// Build a palette with commands for every bound action and every recipe
+    /// in the tree. Recipe actions are only included if a recipe or folder is
+    /// selected for them to apply to.
+    pub fn new(recipes: &RecipeTree, has_selected_recipe: bool) -> Self {
+        let input_engine = &TuiContext::get().input_engine;
+        let input_actions = input_engine
+            .bindings()
+            .keys()
+            .copied()
+            .filter(|action| {
+                action.visible()
+                    && !matches!(
+                        action,
+                        // These only make sense in a specific component, or
+                        // would just close/reopen the palette
+                        Action::ScrollLeft
+                            | Action::ScrollRight
+                            | Action::Cancel
+                            | Action::Toggle
+                            | Action::CommandPalette
+                    )
+            })
+            .map(Command::Input);
+        let global_actions = GlobalAction::iter().map(Command::Global);
+        let recipe_actions = RecipeMenuAction::iter()
+            .filter(|_| has_selected_recipe)
+            .map(Command::Recipe);
+        let recipes = recipes.iter().filter_map(|(_, node)| {
+            let recipe = node.recipe()?;
+            Some(Command::SelectRecipe {
+                id: recipe.id.clone(),
+                name: recipe.name().to_owned(),
+            })
+        });
+        let commands = input_actions
+            .chain(global_actions)
+            .chain(recipe_actions)
+            .chain(recipes)
+            .collect_vec();
+
+        Self {
+            select: build_select(commands.clone()),
+            commands,
+            text_box: TextBox::default()
+                .with_placeholder("Search actions and recipes")
+                .into(),
+        }
+    }
+
+    /// Rebuild the list of commands to match the current query
+    fn filter(&mut self) {
+        let query = self.text_box.data().text().trim();
+        let commands = self
+            .commands
+            .iter()
+            .filter_map(|command| Some((command.score(query)?, command)))
+            // Stable sort, so ties stay in the default order
+            .sorted_by_key(|(score, _)| -score)
+            .map(|(_, command)| command.clone())
+            .collect();
+        self.select = build_select(commands);
+    }
+
+    /// Close the palette and trigger the selected command
+    fn submit(&self) {
+        let Some(command) = self.select.data().selected() else {
+            return;
+        };
+        ViewContext::push_event(Event::CloseModal);
+        match command {
+            Command::Input(action) => {
+                // Send through the message queue, like real input. By the time
+                // it's handled the palette will be closed, so the event goes
+                // to whatever has focus underneath
+                if let Some(event) =
+                    TuiContext::get().input_engine.key_event(*action)
+                {
+                    ViewContext::send_message(Message::Input {
+                        event,
+                        action: Some(*action),
+                    });
+                }
+            }
+            Command::Global(action) => {
+                ViewContext::push_event(Event::new_local(*action))
+            }
+            Command::Recipe(action) => {
+                ViewContext::push_event(Event::new_local(*action))
+            }
+            Command::SelectRecipe { id, .. } => {
+                ViewContext::send_message(Message::RecipeSelect(id.clone()))
+            }
+        }
+    }
+}
+
+fn build_select(commands: Vec<Command>) -> Component<SelectState<Command>> {
+    SelectState::builder(commands).build().into()
+}
+
+impl Modal for CommandPalette {
+    fn title(&self) -> Line<'_> {
+        "Command Palette".into()
+    }
+
+    fn dimensions(&self) -> (Constraint, Constraint) {
+        (
+            Constraint::Length(70),
+            // Leave room for the search box
+            Constraint::Length(self.commands.len().min(20) as u16 + 1),
+        )
+    }
+}
+
+impl EventHandler for CommandPalette {
+    fn update(&mut self, event: Event) -> Update {
+        // The text box would eat every key, so we route input ourselves.
+        // Navigation goes to the list, and everything else goes to the query
+        match event.action() {
+            Some(Action::Submit) => self.submit(),
+            Some(Action::Cancel) => return Update::Propagate(event),
+            Some(
+                Action::Up | Action::Down | Action::PageUp | Action::PageDown,
+            ) => return self.select.update_all(event),
+            _ if matches!(event, Event::Input { .. }) => {
+                let query = self.text_box.data().text().to_owned();
+                let update = self.text_box.update_all(event);
+                if self.text_box.data().text() != query {
+                    self.filter();
+                }
+                return update;
+            }
+            _ => return Update::Propagate(event),
+        }
+        Update::Consumed
+    }
+}
+
+impl Draw for CommandPalette {
+    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+        let [text_box_area, list_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0)])
+                .areas(metadata.area());
+        self.text_box.draw(frame, (), text_box_area, true);
+        if self.select.data().items().is_empty() {
+            frame.render_widget("No matches", list_area);
+        } else {
+            self.select.draw(
+                frame,
+                List::new(self.select.data().items()),
+                list_area,
+                true,
+            );
+        }
+    }
+}
+
+impl Command {
+    /// Name to show in the list, and to match the query against
+    fn label(&self) -> String {
+        match self {
+            Self::Input(action) => action.to_string(),
+            Self::Global(action) => action.to_string(),
+            Self::Recipe(action) => action.to_string(),
+            Self::SelectRecipe { name, .. } => name.clone(),
+        }
+    }
+
+    /// Extra context to show next to the label
+    fn hint(&self) -> String {
+        match self {
+            Self::Input(action) => TuiContext::get()
+                .input_engine
+                .binding(*action)
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+            Self::Global(_) => "Action".into(),
+            Self::Recipe(_) => "Recipe Action".into(),
+            Self::SelectRecipe { id, .. } => format!("Recipe `{id}`"),
+        }
+    }
+
+    /// How well does this command match a query? `None` if it doesn't match.
+    /// Recipes can be matched by name or ID
+    fn score(&self, query: &str) -> Option<i64> {
+        let label_score = fuzzy_match(query, &self.label());
+        match self {
+            Self::SelectRecipe { id, .. } => {
+                label_score.max(fuzzy_match(query, id))
+            }
+            _ => label_score,
+        }
+    }
+}
+
+impl Generate for &Command {
+    type Output<'this>
+        = Line<'this>
+    where
+        Self: 'this;
+
+    fn generate<'this>(self) -> Self::Output<'this>
+    where
+        Self: 'this,
+    {
+        let styles = &TuiContext::get().styles;
+        vec![
+            format!("{:<40}", self.label()).into(),
+            Span::styled(self.hint(), styles.text.highlight),
+        ]
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collection::{Collection, Recipe, RecipeNode},
+        test_util::{assert_matches, Factory},
+        tui::{
+            test_util::{harness, TestHarness},
+            view::test_util::TestComponent,
+        },
+    };
+    use crossterm::event::KeyCode;
+    use indexmap::indexmap;
+    use rstest::rstest;
+
+    fn recipes() -> RecipeTree {
+        let recipe = Recipe {
+            id: "get_fish".into(),
+            name: Some("Get Fish".into()),
+            ..Recipe::factory(())
+        };
+        RecipeTree::new(indexmap! {
+            recipe.id.clone() => RecipeNode::Recipe(recipe),
+        })
+        .unwrap()
+    }
+
+    /// Selecting an input action sends the bound key through the message
+    /// queue, just like real input
+    #[rstest]
+    fn test_input_action(harness: TestHarness) {
+        let mut component = TestComponent::new(
+            harness,
+            CommandPalette::new(&recipes(), true),
+            (),
+        );
+        component.send_text("histor").assert_empty();
+        assert_matches!(
+            component.data().select.data().selected(),
+            Some(Command::Input(Action::History)),
+        );
+        let _ = component.send_key(KeyCode::Enter);
+        assert_matches!(
+            component.harness_mut().pop_message_now(),
+            Message::Input {
+                event: crossterm::event::Event::Key(_),
+                action: Some(Action::History),
+            },
+        );
+    }
+
+    /// Recipes can be found by name or ID
+    #[rstest]
+    #[case::name("gtfsh")]
+    #[case::id("get_f")]
+    fn test_select_recipe(harness: TestHarness, #[case] query: &str) {
+        let mut component = TestComponent::new(
+            harness,
+            CommandPalette::new(&recipes(), true),
+            (),
+        );
+        component.send_text(query).assert_empty();
+        let _ = component.send_key(KeyCode::Enter);
+        let id = assert_matches!(
+            component.harness_mut().pop_message_now(),
+            Message::RecipeSelect(id) => id,
+        );
+        assert_eq!(id, RecipeId::from("get_fish"));
+    }
+
+    /// Navigation keys move through the list instead of the text box
+    #[rstest]
+    fn test_navigation(harness: TestHarness) {
+        let mut component = TestComponent::new(
+            harness,
+            CommandPalette::new(&Collection::factory(()).recipes, false),
+            (),
+        );
+        let first = component.data().select.data().selected_index();
+        component.send_key(KeyCode::Down).assert_empty();
+        assert_eq!(
+            component.data().select.data().selected_index(),
+            first.map(|index| index + 1)
+        );
+        assert_eq!(component.data().text_box.data().text(), "");
+        // Recipe actions are hidden when nothing is selected
+        assert!(!component
+            .data()
+            .commands
+            .iter()
+            .any(|command| matches!(command, Command::Recipe(_))));
+    }
+
+    /// No matches leaves an empty list, and submitting does nothing
+    #[rstest]
+    fn test_no_matches(harness: TestHarness) {
+        let mut component = TestComponent::new(
+            harness,
+            CommandPalette::new(&recipes(), true),
+            (),
+        );
+        component.send_text("zzzzzz").assert_empty();
+        assert!(component.data().select.data().items().is_empty());
+        component.send_key(KeyCode::Enter).assert_empty();
+        component.harness_mut().assert_messages_empty();
+    }
+}
@@ -1,13 +1,22 @@
 //! Request/response body display component
 
 use crate::{
-    http::{Query, ResponseBody},
+    http::{
+        ContentType, QueryError, ResponseBody, ResponseContent, ResponseRecord,
+        SelectorType,
+    },
     tui::{
+        context::TuiContext,
         input::Action,
+        message::Message,
         view::{
             common::{
+                pretty_body::PrettyBody,
                 text_box::TextBox,
-                text_window::{TextWindow, TextWindowProps},
+                text_window::{
+                    IndexedText, TextWindow, TextWindowContent,
+                    TextWindowProps, TextWindowToggles,
+                },
             },
             draw::{Draw, DrawMetadata},
             event::{Event, EventHandler, Update},
@@ -18,55 +27,109 @@ use crate::{
             Component, ViewContext,
         },
     },
-    util::{MaybeStr, ResultExt},
+    util::{common_prefix, ResultExt},
 };
 use anyhow::Context;
+use itertools::Itertools;
 use ratatui::{
     layout::{Constraint, Layout},
+    text::Line,
+    widgets::Paragraph,
     Frame,
 };
+use serde_json::Value;
 use serde_json_path::JsonPath;
-use std::cell::Cell;
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    iter,
+    ops::Range,
+    sync::{Arc, OnceLock},
+};
 use Debug;
 
+/// Shown in place of the body while it's being prettified
+const PLACEHOLDER: &str = "Formatting body...";
+
 /// Display text body of a request OR response
 #[derive(Debug)]
 pub struct ExchangeBody {
     /// Body text content. State cell allows us to reset this whenever the
-    /// request changes
-    text_window: StateCell<Option<Query>, Component<TextWindow<String>>>,
+    /// request changes. The text is indexed by line so only the visible
+    /// portion is rendered, which keeps huge bodies from bogging down the draw
+    text_window: StateCell<Option<String>, Component<TextWindow<BodyText>>>,
     /// Store whether the body can be queried. True only if it's a recognized
     /// and parsed format
     query_available: Cell<bool>,
     /// Are we currently typing in the query box?
     query_focused: bool,
-    /// Expression used to filter the content of the body down
-    query: Option<Query>,
+    /// Query language for the body, based on its content type
+    selector_type: SelectorType,
+    /// Expression used to filter the content of the body down. This is only
+    /// stored after being validated
+    query: Option<String>,
     /// Where the user enters their body query
     query_text_box: Component<Persistent<TextBox>>,
+    /// Object keys that can complete the query being typed, keyed by the
+    /// query text left of the cursor. Calculated during draw because that's
+    /// where we have access to the body.
+    completions: StateCell<String, Vec<String>>,
 }
 
 #[derive(Clone)]
 pub struct ExchangeBodyProps<'a> {
-    pub body: &'a ResponseBody,
+    pub body: BodySource<'a>,
+    /// Display settings, owned by the parent so they outlive the body
+    pub toggles: TextWindowToggles,
+}
+
+/// Where a body came from. This determines how its display text is generated
+#[derive(Clone)]
+pub enum BodySource<'a> {
+    /// Request bodies are never parsed, so their text is ready immediately
+    Request(&'a ResponseBody),
+    /// Response bodies can be huge, so they're parsed and prettified in a
+    /// background task. We need an `Arc` to hand off to the task.
+    Response(&'a Arc<ResponseRecord>),
 }
 
+impl<'a> BodySource<'a> {
+    fn body(&self) -> &'a ResponseBody {
+        match self {
+            Self::Request(body) => body,
+            Self::Response(response) => &response.body,
+        }
+    }
+}
+
+/// Display text for a body. Populated by a background task for responses, so
+/// it may not be available yet.
+#[derive(Debug)]
+struct BodyText(Arc<OnceLock<PrettyBody>>);
+
 /// All callback events from the query text box
 #[derive(Debug)]
 enum QueryCallback {
     Focus,
     Cancel,
     Submit(String),
+    Complete,
 }
 
 impl ExchangeBody {
     /// Create a new body, optionally loading the query text from the
     /// persistence DB. This is optional because not all callers use the query
-    /// box, or want to persist the value.
-    pub fn new(query_persistent_key: Option<PersistentKey>) -> Self {
+    /// box, or want to persist the value. The selector type determines the
+    /// query language, and should match the body's content type.
+    pub fn new(
+        query_persistent_key: Option<PersistentKey>,
+        selector_type: SelectorType,
+    ) -> Self {
         let text_box = TextBox::default()
-            .with_placeholder("'/' to filter body with JSONPath")
-            .with_validator(|text| JsonPath::parse(text).is_ok())
+            .with_placeholder(format!(
+                "'/' to filter body with {selector_type}"
+            ))
+            .with_validator(move |text| selector_type.validate(text).is_ok())
             // Callback trigger an events, so we can modify our own state
             .with_on_click(|_| {
                 ViewContext::push_event(Event::new_local(QueryCallback::Focus))
@@ -78,25 +141,101 @@ impl ExchangeBody {
                 ViewContext::push_event(Event::new_local(
                     QueryCallback::Submit(text_box.text().to_owned()),
                 ))
+            })
+            .with_on_complete(|_| {
+                ViewContext::push_event(Event::new_local(
+                    QueryCallback::Complete,
+                ))
             });
         Self {
             text_window: Default::default(),
             query_available: Cell::new(false),
             query_focused: false,
+            selector_type,
             query: Default::default(),
             query_text_box: Persistent::optional(
                 query_persistent_key,
                 text_box,
             )
             .into(),
+            completions: Default::default(),
         }
     }
 
-    /// Get visible body text
+    /// Get visible body text. `None` if the body is still being prettified
     pub fn text(&self) -> Option<String> {
-        self.text_window
-            .get()
-            .map(|text_window| text_window.data().text().to_owned())
+        let text_window = self.text_window.get()?;
+        let text = text_window.data().text().0.get()?;
+        Some(text.text().into_owned())
+    }
+
+    /// Get the JSONPath of the node on the line at the top of the window.
+    /// `None` if the body isn't JSON, or if it's been filtered by a query,
+    /// because then the lines don't correspond to the body anymore
+    pub fn json_path(&self, body: &ResponseBody) -> Option<String> {
+        if self.query.is_some() {
+            return None;
+        }
+        let parsed = body
+            .parsed()
+            .filter(|parsed| parsed.content_type() == ContentType::Json)?;
+        let line = self.text_window.get()?.data().first_line();
+        Some(json_path_at_line(&parsed.to_json(), line))
+    }
+
+    /// Fill in the longest prefix shared by all available key completions
+    fn complete(&mut self) {
+        let Some(completions) = self.completions.get() else {
+            return;
+        };
+        let text_box = self.query_text_box.data_mut();
+        let partial = text_box
+            .text_before_cursor()
+            .rsplit_once('.')
+            .map(|(_, partial)| partial)
+            .unwrap_or_default();
+        let prefix = common_prefix(&completions);
+        if let Some(suffix) = prefix.strip_prefix(partial) {
+            text_box.insert_text(suffix);
+        }
+    }
+
+    /// Get a line of feedback for the query currently being typed. Show
+    /// available key completions if there are any, otherwise the parse error
+    /// if the query is invalid.
+    fn query_feedback(&self, body: &dyn ResponseContent) -> Option<Line<'_>> {
+        let styles = &TuiContext::get().styles;
+        let text_box = self.query_text_box.data();
+        let text = text_box.text();
+        if text.is_empty() {
+            return None;
+        }
+
+        // Key completion is only supported for JSONPath
+        if self.selector_type == SelectorType::Jsonpath {
+            let input = text_box.text_before_cursor();
+            let completions =
+                self.completions.get_or_update(input.to_owned(), || {
+                    complete_keys(&body.to_json(), input)
+                });
+            if !completions.is_empty() {
+                return Some(
+                    format!("Tab to complete: {}", completions.join(", "))
+                        .into(),
+                );
+            }
+        }
+
+        if let Err(QueryError::InvalidSelector { error, .. }) =
+            self.selector_type.validate(text)
+        {
+            Some(Line::styled(
+                format!("Invalid {}: {error:#}", self.selector_type),
+                styles.text.error,
+            ))
+        } else {
+            None
+        }
     }
 }
 
@@ -111,25 +250,28 @@ impl EventHandler for ExchangeBody {
                 QueryCallback::Focus => self.query_focused = true,
                 QueryCallback::Cancel => {
                     // Reset text to whatever was submitted last
-                    self.query_text_box.data_mut().set_text(
-                        self.query
-                            .as_ref()
-                            .map(Query::to_string)
-                            .unwrap_or_default(),
-                    );
+                    self.query_text_box
+                        .data_mut()
+                        .set_text(self.query.clone().unwrap_or_default());
                     self.query_focused = false;
                 }
                 QueryCallback::Submit(text) => {
-                    self.query = text
-                        .parse()
-                        // Log the error, then throw it away
-                        .with_context(|| {
-                            format!("Error parsing query {text:?}")
-                        })
-                        .traced()
-                        .ok();
+                    self.query = if text.is_empty() {
+                        None
+                    } else {
+                        self.selector_type
+                            .validate(text)
+                            // Log the error, then throw it away
+                            .with_context(|| {
+                                format!("Error parsing query {text:?}")
+                            })
+                            .traced()
+                            .ok()
+                            .map(|()| text.clone())
+                    };
                     self.query_focused = false;
                 }
+                QueryCallback::Complete => self.complete(),
             }
         } else {
             return Update::Propagate(event);
@@ -156,23 +298,49 @@ impl<'a> Draw<ExchangeBodyProps<'a>> for ExchangeBody {
         metadata: DrawMetadata,
     ) {
         // Body can only be queried if it's been parsed
-        let query_available = props.body.parsed().is_some();
+        let parsed_body = props.body.body().parsed();
+        let query_available = parsed_body.is_some();
         self.query_available.set(query_available);
+        let feedback = parsed_body
+            .filter(|_| self.query_focused)
+            .and_then(|body| self.query_feedback(body));
 
-        let [body_area, query_area] = Layout::vertical([
+        let [body_area, query_area, feedback_area] = Layout::vertical([
             Constraint::Min(0),
             Constraint::Length(if query_available { 1 } else { 0 }),
+            Constraint::Length(if feedback.is_some() { 1 } else { 0 }),
         ])
         .areas(metadata.area());
 
         // Draw the body
         let text = self.text_window.get_or_update(self.query.clone(), || {
-            init_text_window(props.body, self.query.as_ref())
+            let destination = match &props.body {
+                BodySource::Request(body) => Arc::new(OnceLock::from(
+                    PrettyBody::Text(IndexedText::new(
+                        body.display_text(self.selector_type, None),
+                    )),
+                )),
+                BodySource::Response(response) => {
+                    // This message will trigger a task to parse, query, and
+                    // prettify the body. Once it's done, the text will show
+                    // up on the next draw
+                    let destination = Arc::new(OnceLock::new());
+                    ViewContext::send_message(Message::HttpPrettifyBody {
+                        response: Arc::clone(response),
+                        selector_type: self.selector_type,
+                        query: self.query.clone(),
+                        destination: Arc::clone(&destination),
+                    });
+                    destination
+                }
+            };
+            TextWindow::new(BodyText(destination)).into()
         });
         text.draw(
             frame,
             TextWindowProps {
                 has_search_box: query_available,
+                toggles: props.toggles,
             },
             body_area,
             true,
@@ -182,30 +350,164 @@ impl<'a> Draw<ExchangeBodyProps<'a>> for ExchangeBody {
             self.query_text_box
                 .draw(frame, (), query_area, self.query_focused);
         }
+        if let Some(feedback) = feedback {
+            frame.render_widget(Paragraph::new(feedback), feedback_area);
+        }
     }
 }
 
-fn init_text_window(
-    body: &ResponseBody,
-    query: Option<&Query>,
-) -> Component<TextWindow<String>> {
-    // Query and prettify text if possible. This involves a lot of cloning
-    // because it makes stuff easier. If it becomes a bottleneck on large
-    // responses it's fixable.
-    let body = body
-        .parsed()
-        .map(|parsed_body| {
-            // Body is a known content type so we parsed it - apply a query if
-            // necessary and prettify the output
-            query
-                .map(|query| query.query(parsed_body).prettify())
-                .unwrap_or_else(|| parsed_body.prettify())
+/// Get all object keys that could complete the last segment of a partial
+/// query, e.g. `$.data.us` could complete to `$.data.users`. The query up to
+/// the last `.` is evaluated against the body to find candidate objects. Only
+/// keys that are valid in dot notation are included.
+fn complete_keys(value: &Value, input: &str) -> Vec<String> {
+    let Some((parent, partial)) = input.rsplit_once('.') else {
+        return Vec::new();
+    };
+    if !partial.is_empty() && !is_shorthand_name(partial) {
+        return Vec::new();
+    }
+    // `$..key` searches the entire tree below the parent
+    let (parent, recursive) = match parent.strip_suffix('.') {
+        Some(parent) => (parent, true),
+        None => (parent, false),
+    };
+    let Ok(path) = JsonPath::parse(parent) else {
+        return Vec::new();
+    };
+
+    let mut keys = Vec::new();
+    for node in path.query(value).all() {
+        collect_keys(node, recursive, &mut keys);
+    }
+    keys.into_iter()
+        .filter(|key| {
+            key.starts_with(partial)
+                && *key != partial
+                && is_shorthand_name(key)
         })
-        // Content couldn't be parsed, fall back to the raw text
-        // If the text isn't UTF-8, we'll show a placeholder instead
-        .unwrap_or_else(|| format!("{:#}", MaybeStr(body.bytes())));
+        .unique()
+        .map(String::from)
+        .collect()
+}
+
+/// Collect the keys of an object, optionally recursing into all descendants
+fn collect_keys<'a>(
+    value: &'a Value,
+    recursive: bool,
+    keys: &mut Vec<&'a str>,
+) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                keys.push(key);
+                if recursive {
+                    collect_keys(value, recursive, keys);
+                }
+            }
+        }
+        Value::Array(values) if recursive => {
+            for value in values {
+                collect_keys(value, recursive, keys);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Get the JSONPath of the innermost node shown on a line of pretty-printed
+/// JSON. This mirrors the layout of `serde_json`'s pretty printer: every
+/// scalar, empty collection, and closing bracket gets its own line, and each
+/// nested value starts on the same line as its key. A closing bracket belongs
+/// to its collection.
+fn json_path_at_line(value: &Value, line: usize) -> String {
+    let mut path = "$".to_owned();
+    path_at_line(value, line, &mut 0, &mut path);
+    path
+}
+
+/// Walk the lines of a value, starting at `current`, until reaching `target`.
+/// Return `true` if the target is within this value, in which case `path`
+/// points to the node on that line. Otherwise `current` is left at the line
+/// after this value.
+fn path_at_line(
+    value: &Value,
+    target: usize,
+    current: &mut usize,
+    path: &mut String,
+) -> bool {
+    if *current == target {
+        return true;
+    }
+    *current += 1;
 
-    TextWindow::new(body).into()
+    let mut visit = |segment: String, child: &Value| {
+        let len = path.len();
+        path.push_str(&segment);
+        if path_at_line(child, target, current, path) {
+            return true;
+        }
+        path.truncate(len);
+        false
+    };
+    let found = match value {
+        Value::Object(map) if !map.is_empty() => {
+            map.iter().any(|(key, child)| {
+                let segment = if is_shorthand_name(key) {
+                    format!(".{key}")
+                } else {
+                    let key = key.replace('\\', "\\\\").replace('\'', "\\'");
+                    format!("['{key}']")
+                };
+                visit(segment, child)
+            })
+        }
+        Value::Array(values) if !values.is_empty() => values
+            .iter()
+            .enumerate()
+            .any(|(i, child)| visit(format!("[{i}]"), child)),
+        // Everything else fits on one line
+        _ => return false,
+    };
+    if found {
+        return true;
+    }
+
+    // Closing bracket
+    if *current == target {
+        return true;
+    }
+    *current += 1;
+    false
+}
+
+/// Can this key be used in dot notation (`$.key`), rather than requiring
+/// bracket notation (`$['key']`)?
+fn is_shorthand_name(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c == '_' || c.is_alphabetic() || !c.is_ascii())
+        && chars.all(|c| c == '_' || c.is_alphanumeric() || !c.is_ascii())
+}
+
+impl TextWindowContent for BodyText {
+    fn line_widths(&self) -> Cow<'_, [usize]> {
+        match self.0.get() {
+            Some(text) => text.line_widths(),
+            None => vec![PLACEHOLDER.len()].into(),
+        }
+    }
+
+    fn lines(&self, range: Range<usize>) -> Vec<Line<'_>> {
+        match self.0.get() {
+            Some(text) => text.lines(range),
+            None => iter::once(PLACEHOLDER.into())
+                .take(range.end)
+                .skip(range.start)
+                .collect(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -223,7 +525,7 @@ mod tests {
     };
     use crossterm::event::KeyCode;
     use ratatui::text::Span;
-    use reqwest::StatusCode;
+    use reqwest::{StatusCode, Version};
     use rstest::{fixture, rstest};
 
     const TEXT: &[u8] = b"{\"greeting\":\"hello\"}";
@@ -235,14 +537,25 @@ mod tests {
     }
 
     #[fixture]
-    fn json_response() -> ResponseRecord {
-        let response = ResponseRecord {
+    fn json_response() -> Arc<ResponseRecord> {
+        ResponseRecord {
+            version: Version::HTTP_11,
             status: StatusCode::OK,
             headers: header_map([("Content-Type", "application/json")]),
             body: ResponseBody::new(TEXT.into()),
-        };
-        response.parse_body();
-        response
+            encoded_size: None,
+            from_cache: false,
+            tls: None,
+        }
+        .into()
+    }
+
+    /// Run the pending background prettification, then redraw to show it
+    fn prettify(
+        component: &mut TestComponent<ExchangeBody, ExchangeBodyProps>,
+    ) {
+        component.harness_mut().prettify_body_now();
+        component.redraw();
     }
 
     /// Render an unparsed body with no query box
@@ -251,8 +564,11 @@ mod tests {
         let body = ResponseBody::new(TEXT.into());
         let component = TestComponent::new(
             harness,
-            ExchangeBody::new(None),
-            ExchangeBodyProps { body: &body },
+            ExchangeBody::new(None, SelectorType::Jsonpath),
+            ExchangeBodyProps {
+                body: BodySource::Request(&body),
+                toggles: Default::default(),
+            },
         );
 
         // Assert state
@@ -271,19 +587,98 @@ mod tests {
         ]);
     }
 
+    /// Long lines can be wrapped, with line numbers only on the first row of
+    /// each line
+    #[rstest]
+    fn test_wrap(#[with(12, 3)] harness: TestHarness) {
+        let body = ResponseBody::new(TEXT.into());
+        let component = TestComponent::new(
+            harness,
+            ExchangeBody::new(None, SelectorType::Jsonpath),
+            ExchangeBodyProps {
+                body: BodySource::Request(&body),
+                toggles: TextWindowToggles {
+                    wrap: true,
+                    line_numbers: true,
+                },
+            },
+        );
+        component.assert_buffer_lines([
+            vec![gutter("1"), " {\"greeting".into()],
+            vec![gutter(" "), " \":\"hello\"}".into()],
+            vec![gutter(" "), "           ".into()],
+        ]);
+    }
+
+    /// Line numbers can be hidden, giving the whole width to the text
+    #[rstest]
+    fn test_no_line_numbers(#[with(12, 3)] harness: TestHarness) {
+        let body = ResponseBody::new(TEXT.into());
+        let component = TestComponent::new(
+            harness,
+            ExchangeBody::new(None, SelectorType::Jsonpath),
+            ExchangeBodyProps {
+                body: BodySource::Request(&body),
+                toggles: TextWindowToggles {
+                    wrap: true,
+                    line_numbers: false,
+                },
+            },
+        );
+        component.assert_buffer_lines([
+            "{\"greeting\":",
+            "\"hello\"}    ",
+            "            ",
+        ]);
+    }
+
+    /// Response bodies show a placeholder until the background task is done.
+    /// The query box is hidden until the body has been parsed.
+    #[rstest]
+    fn test_placeholder(
+        #[with(32, 4)] harness: TestHarness,
+        json_response: Arc<ResponseRecord>,
+    ) {
+        let mut component = TestComponent::new(
+            harness,
+            ExchangeBody::new(None, SelectorType::Jsonpath),
+            ExchangeBodyProps {
+                body: BodySource::Response(&json_response),
+                toggles: Default::default(),
+            },
+        );
+        assert_eq!(component.data().text(), None);
+        assert!(!component.data().query_available.get());
+        component.assert_buffer_lines([
+            vec![gutter("1"), " Formatting body...            ".into()],
+            vec![gutter(" "), "                              ".into()],
+            vec![gutter(" "), "                              ".into()],
+            vec![gutter(" "), "                              ".into()],
+        ]);
+
+        prettify(&mut component);
+        assert_eq!(
+            component.data().text().as_deref(),
+            Some("{\n  \"greeting\": \"hello\"\n}")
+        );
+        assert!(component.data().query_available.get());
+    }
+
     /// Render a parsed body with query text box
     #[rstest]
     fn test_parsed(
         #[with(32, 5)] harness: TestHarness,
-        json_response: ResponseRecord,
+        json_response: Arc<ResponseRecord>,
     ) {
         let mut component = TestComponent::new(
             harness,
-            ExchangeBody::new(None),
+            ExchangeBody::new(None, SelectorType::Jsonpath),
             ExchangeBodyProps {
-                body: &json_response.body,
+                body: BodySource::Response(&json_response),
+                toggles: Default::default(),
             },
         );
+        prettify(&mut component);
 
         // Assert initial state/view
         let data = component.data();
@@ -309,6 +704,8 @@ mod tests {
         component.send_key(KeyCode::Char('/')).assert_empty();
         component.send_text("$.greeting").assert_empty();
         component.send_key(KeyCode::Enter).assert_empty();
+        // Changing the query prettifies again
+        prettify(&mut component);
 
         // Make sure state updated correctly
         let data = component.data();
@@ -340,7 +737,7 @@ mod tests {
     #[rstest]
     fn test_initial_query(
         #[with(30, 4)] harness: TestHarness,
-        json_response: ResponseRecord,
+        json_response: Arc<ResponseRecord>,
     ) {
         let recipe_id = RecipeId::factory(());
 
@@ -357,11 +754,167 @@ mod tests {
         // correctly here
         let component = TestComponent::new(
             harness,
-            ExchangeBody::new(Some(persistent_key)),
+            ExchangeBody::new(Some(persistent_key), SelectorType::Jsonpath),
             ExchangeBodyProps {
-                body: &json_response.body,
+                body: BodySource::Response(&json_response),
+                toggles: Default::default(),
             },
         );
         assert_eq!(component.data().query, Some("$.greeting".parse().unwrap()));
     }
+
+    /// Typing in the query box shows completions, and tab fills them in.
+    /// Invalid queries show the parse error.
+    #[rstest]
+    fn test_query_feedback(
+        #[with(32, 5)] harness: TestHarness,
+        json_response: Arc<ResponseRecord>,
+    ) {
+        let mut component = TestComponent::new(
+            harness,
+            ExchangeBody::new(None, SelectorType::Jsonpath),
+            ExchangeBodyProps {
+                body: BodySource::Response(&json_response),
+                toggles: Default::default(),
+            },
+        );
+        prettify(&mut component);
+        let feedback = |component: &TestComponent<_, _>| {
+            let data: &ExchangeBody = component.data();
+            data.query_feedback(json_response.body.parsed().unwrap())
+                .map(|line| line.to_string())
+        };
+
+        component.send_key(KeyCode::Char('/')).assert_empty();
+        component.send_text("$.gr").assert_empty();
+        assert_eq!(
+            feedback(&component).as_deref(),
+            Some("Tab to complete: greeting")
+        );
+        component.send_key(KeyCode::Tab).assert_empty();
+        assert_eq!(component.data().query_text_box.data().text(), "$.greeting");
+        assert_eq!(feedback(&component), None);
+
+        component.send_text("[").assert_empty();
+        let error = feedback(&component).unwrap();
+        assert!(
+            error.starts_with("Invalid JSONPath: at position"),
+            "Unexpected feedback {error:?}"
+        );
+
+        // Feedback goes away when the box isn't focused
+        component.send_key(KeyCode::Esc).assert_empty();
+        let styles = &TuiContext::get().styles.text_box;
+        component.assert_buffer_lines([
+            vec![gutter("1"), " {                        ".into()],
+            vec![gutter("2"), "   \"greeting\": \"hello\"".into()],
+            vec![gutter("3"), " }                        ".into()],
+            vec![gutter(" "), "                          ".into()],
+            vec![Span::styled(
+                "'/' to filter body with JSONPath",
+                styles.text.patch(styles.placeholder),
+            )],
+        ]);
+    }
+
+    /// XML bodies are prettified and queried with XPath
+    #[rstest]
+    fn test_xml(#[with(40, 4)] harness: TestHarness) {
+        let response: Arc<ResponseRecord> = ResponseRecord {
+            headers: header_map([("Content-Type", "application/xml")]),
+            body: ResponseBody::new(
+                b"<users><user>Ted</user><user>Jo</user></users>"
+                    .as_slice()
+                    .into(),
+            ),
+            ..ResponseRecord::factory(())
+        }
+        .into();
+        let mut component = TestComponent::new(
+            harness,
+            ExchangeBody::new(None, SelectorType::Xpath),
+            ExchangeBodyProps {
+                body: BodySource::Response(&response),
+                toggles: Default::default(),
+            },
+        );
+        prettify(&mut component);
+        assert_eq!(
+            component.data().text().as_deref(),
+            Some("<users>\n  <user>Ted</user>\n  <user>Jo</user>\n</users>")
+        );
+
+        component.send_key(KeyCode::Char('/')).assert_empty();
+        component.send_text("//user").assert_empty();
+        component.send_key(KeyCode::Enter).assert_empty();
+        prettify(&mut component);
+        assert_eq!(component.data().query.as_deref(), Some("//user"));
+        assert_eq!(
+            component.data().text().as_deref(),
+            Some("<user>Ted</user>\n<user>Jo</user>")
+        );
+    }
+
+    #[rstest]
+    #[case::root("$.", &["data", "meta"])]
+    #[case::partial("$.data.us", &["user_count", "users"])]
+    #[case::array("$.data.users[*].n", &["name"])]
+    #[case::recursive("$..de", &["deep"])]
+    #[case::exact("$.data", &[])]
+    #[case::missing("$.nope.x", &[])]
+    #[case::no_dot("$[", &[])]
+    #[case::invalid_parent("$[.x", &[])]
+    fn test_complete_keys(#[case] input: &str, #[case] expected: &[&str]) {
+        let value = serde_json::json!({
+            "data": {"users": [{"name": "Ted", "id": 1}], "user_count": 1},
+            "meta": {"nested": {"deep": true}},
+            "bad-key": 1,
+        });
+        assert_eq!(complete_keys(&value, input), expected);
+    }
+
+    /// Each line of pretty-printed JSON maps to the node it shows. Lines are:
+    /// ```json
+    /// {
+    ///   "a": 1,
+    ///   "empty": [],
+    ///   "list": [
+    ///     {
+    ///       "it's": true
+    ///     },
+    ///     null
+    ///   ],
+    ///   "my key": {}
+    /// }
+    /// ```
+    #[rstest]
+    #[case::root(0, "$")]
+    #[case::scalar(1, "$.a")]
+    #[case::empty(2, "$.empty")]
+    #[case::array(3, "$.list")]
+    #[case::array_element(4, "$.list[0]")]
+    #[case::escaped_key(5, "$.list[0]['it\\'s']")]
+    #[case::closing_bracket(6, "$.list[0]")]
+    #[case::second_element(7, "$.list[1]")]
+    #[case::array_closing_bracket(8, "$.list")]
+    #[case::bracket_key(9, "$['my key']")]
+    #[case::root_closing_bracket(10, "$")]
+    #[case::past_end(11, "$")]
+    fn test_json_path_at_line(#[case] line: usize, #[case] expected: &str) {
+        let value = serde_json::json!({
+            "a": 1,
+            "empty": [],
+            "list": [{"it's": true}, null],
+            "my key": {},
+        });
+        // Make sure the test stays in sync with the prettifier
+        assert_eq!(
+            serde_json::to_string_pretty(&value)
+                .unwrap()
+                .lines()
+                .count(),
+            11
+        );
+        assert_eq!(json_path_at_line(&value, line), expected);
+    }
 }
@@ -1,37 +1,54 @@
 use crate::{
-    collection::RecipeNode,
-    http::RequestRecord,
+    collection::{ProfileId, RecipeKind, RecipeNode},
+    http::{
+        EventStream, RequestId, RequestRecord, RetryAttempt, Timing, TlsInfo,
+    },
     tui::{
         context::TuiContext,
         input::Action,
+        message::Message,
         view::{
-            common::{tabs::Tabs, Pane},
+            common::{
+                header_table::HeaderTable,
+                table::Table,
+                tabs::Tabs,
+                text_window::{TextWindow, TextWindowProps},
+                Pane,
+            },
             component::{
                 primary::PrimaryPane,
                 request_view::{RequestView, RequestViewProps},
                 response_view::{
-                    ResponseBodyView, ResponseBodyViewProps,
-                    ResponseHeadersView, ResponseHeadersViewProps,
+                    RequestErrorView, RequestErrorViewProps, ResponseBodyView,
+                    ResponseBodyViewProps, ResponseHeadersView,
+                    ResponseHeadersViewProps,
                 },
+                websocket_console::{WebSocketConsole, WebSocketConsoleProps},
                 Component,
             },
             draw::{Draw, DrawMetadata, Generate},
             event::{Event, EventHandler, Update},
-            state::{fixed_select::FixedSelect, persistence::PersistentKey},
+            state::{
+                fixed_select::FixedSelect, persistence::PersistentKey,
+                StateCell,
+            },
             RequestState, ViewContext,
         },
     },
-    util::doc_link,
+    util::{doc_link, ResultExt},
+    ws::WebSocketConnection,
 };
+use chrono::Local;
 use derive_more::Display;
+use itertools::Itertools;
 use ratatui::{
-    layout::{Alignment, Constraint, Layout},
-    text::{Line, Text},
-    widgets::block::Title,
+    layout::{Alignment, Constraint, Layout, Rect},
+    text::{Line, Span, Text},
+    widgets::{block::Title, Paragraph, Wrap},
     Frame,
 };
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::{cell::Cell, iter, sync::Arc};
 use strum::{EnumCount, EnumIter};
 
 /// Display for a request/response exchange. This allows the user to switch
@@ -44,12 +61,30 @@ pub struct ExchangePane {
     request: Component<RequestView>,
     response_headers: Component<ResponseHeadersView>,
     response_body: Component<ResponseBodyView>,
+    request_error: Component<RequestErrorView>,
+    /// Shown in place of everything else for WebSocket recipes
+    websocket_console: Component<WebSocketConsole>,
+    /// ID of the displayed exchange, if it's a completed one that can have a
+    /// note. Updated on each draw
+    exchange_id: Cell<Option<RequestId>>,
+    /// User's note for the displayed exchange, loaded from the DB. `None` if
+    /// the exchange has no note
+    note: StateCell<RequestId, Option<Component<TextWindow<String>>>>,
 }
 
 pub struct ExchangePaneProps<'a> {
     /// Selected recipe OR folder. Used to decide what placeholder to show
     pub selected_recipe_node: Option<&'a RecipeNode>,
+    pub selected_profile_id: Option<&'a ProfileId>,
+    /// Request to show. This is either the selected request or a pinned one
     pub request_state: Option<&'a RequestState>,
+    /// Exchanges pinned by the user, shown as a row of tabs
+    pub pinned_requests: &'a [&'a RequestState],
+    /// Index of the pinned exchange being shown, if any
+    pub selected_pin: Option<usize>,
+    /// Connection for the selected recipe, if it's a WebSocket recipe that
+    /// has been connected this session
+    pub websocket: Option<&'a WebSocketConnection>,
 }
 
 impl Default for ExchangePane {
@@ -59,6 +94,27 @@ impl Default for ExchangePane {
             request: Default::default(),
             response_headers: Default::default(),
             response_body: Default::default(),
+            request_error: Default::default(),
+            websocket_console: Default::default(),
+            exchange_id: Cell::default(),
+            note: StateCell::default(),
+        }
+    }
+}
+
+impl ExchangePane {
+    /// Start typing in the WebSocket console. Only has an effect while the
+    /// console is visible
+    pub fn focus_websocket_console(&mut self) {
+        self.websocket_console.data_mut().focus_input();
+    }
+
+    /// Replace the displayed note for an exchange, after it's been edited. If
+    /// a different exchange is displayed, do nothing; the note will be loaded
+    /// from the DB when that exchange is shown.
+    pub fn set_note(&mut self, request_id: RequestId, note: Option<String>) {
+        if let Some(state) = self.note.get_mut_for(&request_id) {
+            *state = note.map(|note| TextWindow::new(note).into());
         }
     }
 }
@@ -80,6 +136,11 @@ enum Tab {
     #[default]
     Body,
     Headers,
+    Retries,
+    Timing,
+    #[display("TLS")]
+    Tls,
+    Notes,
 }
 impl FixedSelect for Tab {}
 
@@ -91,18 +152,33 @@ impl EventHandler for ExchangePane {
                     PrimaryPane::Exchange,
                 ));
             }
+            Some(Action::EditNote) => {
+                let message = match self.exchange_id.get() {
+                    Some(request_id) => Message::EditExchangeNote(request_id),
+                    None => Message::Notify(
+                        "Only completed exchanges can have notes".into(),
+                    ),
+                };
+                ViewContext::send_message(message);
+            }
             _ => return Update::Propagate(event),
         }
         Update::Consumed
     }
 
     fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
-        vec![
+        let mut children = vec![
             self.request.as_child(),
             self.response_body.as_child(),
-            // Tabs last so the children get priority
-            self.tabs.as_child(),
-        ]
+            self.request_error.as_child(),
+            self.websocket_console.as_child(),
+        ];
+        if let Some(Some(note)) = self.note.get_mut() {
+            children.push(note.as_child());
+        }
+        // Tabs last so the children get priority
+        children.push(self.tabs.as_child());
+        children
     }
 }
 
@@ -113,6 +189,10 @@ impl<'a> Draw<ExchangePaneProps<'a>> for ExchangePane {
         props: ExchangePaneProps<'a>,
         metadata: DrawMetadata,
     ) {
+        self.exchange_id.set(match props.request_state {
+            Some(RequestState::Response { exchange }) => Some(exchange.id),
+            _ => None,
+        });
         let input_engine = &TuiContext::get().input_engine;
         let title =
             input_engine.add_hint("Request / Response", Action::SelectResponse);
@@ -121,35 +201,73 @@ impl<'a> Draw<ExchangePaneProps<'a>> for ExchangePane {
             has_focus: metadata.has_focus(),
         }
         .generate();
-        // If a recipe is selected, history is available so show the hint
-        if matches!(props.selected_recipe_node, Some(RecipeNode::Recipe(_))) {
+        // If a recipe is selected, history is available so show the hint.
+        // WebSocket recipes only show their latest conversation
+        if matches!(
+            props.selected_recipe_node,
+            Some(RecipeNode::Recipe(recipe)) if recipe.kind == RecipeKind::Http
+        ) {
             let text = input_engine.add_hint("History", Action::History);
             block = block.title(Title::from(text).alignment(Alignment::Right));
         }
         frame.render_widget(&block, metadata.area());
         let area = block.inner(metadata.area());
 
-        // Empty states
-        match props.selected_recipe_node {
-            None => {
-                frame.render_widget(
-                    Text::from(vec![
-                        "No recipes defined; add one to your collection".into(),
-                        doc_link("api/request_collection/request_recipe")
-                            .into(),
-                    ]),
-                    area,
-                );
-                return;
-            }
-            Some(RecipeNode::Folder { .. }) => {
-                frame.render_widget(
-                    "Select a recipe to see its request history",
-                    area,
-                );
-                return;
+        // Pinned exchanges get a row of tabs above everything else
+        let area = if props.pinned_requests.is_empty() {
+            area
+        } else {
+            let [pins_area, area] =
+                Layout::vertical([Constraint::Length(1), Constraint::Min(0)])
+                    .areas(area);
+            render_pins(
+                frame,
+                props.pinned_requests,
+                props.selected_pin,
+                pins_area,
+            );
+            area
+        };
+
+        // Empty states. A pinned exchange is shown no matter what's selected
+        if props.selected_pin.is_none() {
+            match props.selected_recipe_node {
+                None => {
+                    frame.render_widget(
+                        Text::from(vec![
+                            "No recipes defined; add one to your collection"
+                                .into(),
+                            doc_link("api/request_collection/request_recipe")
+                                .into(),
+                        ]),
+                        area,
+                    );
+                    return;
+                }
+                Some(RecipeNode::Folder { .. }) => {
+                    frame.render_widget(
+                        "Select a recipe to see its request history",
+                        area,
+                    );
+                    return;
+                }
+                Some(RecipeNode::Recipe(recipe))
+                    if recipe.kind == RecipeKind::WebSocket =>
+                {
+                    self.websocket_console.draw(
+                        frame,
+                        WebSocketConsoleProps {
+                            profile_id: props.selected_profile_id,
+                            recipe_id: &recipe.id,
+                            connection: props.websocket,
+                        },
+                        area,
+                        true,
+                    );
+                    return;
+                }
+                Some(RecipeNode::Recipe { .. }) => {}
             }
-            Some(RecipeNode::Recipe { .. }) => {}
         }
 
         // Split out the areas we *may* need
@@ -160,32 +278,8 @@ impl<'a> Draw<ExchangePaneProps<'a>> for ExchangePane {
         ])
         .areas(area);
 
-        // Draw whatever metadata is available
-        if let Some(metadata) =
-            props.request_state.and_then(RequestState::request_metadata)
-        {
-            frame.render_widget(
-                Line::from(vec![
-                    metadata.start_time.generate(),
-                    " / ".into(),
-                    metadata.duration.generate(),
-                ]),
-                metadata_area,
-            );
-        }
-        if let Some(metadata) = props
-            .request_state
-            .and_then(RequestState::response_metadata)
-        {
-            frame.render_widget(
-                Line::from(vec![
-                    metadata.status.generate(),
-                    " ".into(),
-                    metadata.size.to_string_as(false).into(),
-                ])
-                .alignment(Alignment::Right),
-                metadata_area,
-            );
+        if let Some(request_state) = props.request_state {
+            render_summary(frame, request_state, metadata_area);
         }
 
         // Render request/response based on state. Lambas help with code dupe
@@ -212,13 +306,43 @@ impl<'a> Draw<ExchangePaneProps<'a>> for ExchangePane {
             Some(RequestState::BuildError { error, .. }) => {
                 frame.render_widget(error.generate(), area)
             }
-            Some(RequestState::Loading { request, .. }) => {
+            Some(RequestState::Loading {
+                request,
+                queued,
+                stream,
+                ..
+            }) => {
                 render_tabs(frame);
-                match selected_tab {
-                    Tab::Request => render_request(frame, request),
-                    Tab::Body | Tab::Headers => {
-                        frame.render_widget("Loading...", content_area)
+                match (selected_tab, stream) {
+                    (Tab::Request, _) => render_request(frame, request),
+                    // Event streams can stay open indefinitely, so show what
+                    // we have so far
+                    (Tab::Body, Some(stream)) => {
+                        render_events(frame, stream, content_area)
                     }
+                    (Tab::Headers, Some(stream)) => frame.render_widget(
+                        HeaderTable {
+                            headers: &stream.headers,
+                        }
+                        .generate(),
+                        content_area,
+                    ),
+                    (
+                        Tab::Body
+                        | Tab::Headers
+                        | Tab::Retries
+                        | Tab::Timing
+                        | Tab::Tls
+                        | Tab::Notes,
+                        _,
+                    ) => frame.render_widget(
+                        if *queued {
+                            "Waiting for a free request slot..."
+                        } else {
+                            "Loading..."
+                        },
+                        content_area,
+                    ),
                 }
             }
             Some(RequestState::Response { exchange }) => {
@@ -254,17 +378,370 @@ impl<'a> Draw<ExchangePaneProps<'a>> for ExchangePane {
                         content_area,
                         true,
                     ),
+                    Tab::Retries => {
+                        render_retries(frame, &exchange.retries, content_area)
+                    }
+                    Tab::Timing => render_timing(
+                        frame,
+                        exchange.timing.as_ref(),
+                        content_area,
+                    ),
+                    Tab::Tls => render_tls(
+                        frame,
+                        exchange.response.tls.as_ref(),
+                        content_area,
+                    ),
+                    Tab::Notes => {
+                        self.draw_note(frame, exchange.id, content_area)
+                    }
                 }
             }
             Some(RequestState::RequestError { error }) => {
                 render_tabs(frame);
                 match selected_tab {
                     Tab::Request => render_request(frame, &error.request),
-                    Tab::Body | Tab::Headers => {
-                        frame.render_widget(error.generate(), content_area)
+                    Tab::Body | Tab::Headers => self.request_error.draw(
+                        frame,
+                        RequestErrorViewProps { error },
+                        content_area,
+                        true,
+                    ),
+                    Tab::Retries => {
+                        render_retries(frame, &error.retries, content_area)
                     }
+                    Tab::Timing => render_timing(frame, None, content_area),
+                    Tab::Tls => render_tls(frame, None, content_area),
+                    Tab::Notes => frame.render_widget(
+                        "Failed requests aren't saved to history, so they \
+                        can't have notes",
+                        content_area,
+                    ),
                 }
             }
         }
     }
 }
+
+impl ExchangePane {
+    /// Draw the note for a completed exchange, loading it from the DB if the
+    /// exchange has changed
+    fn draw_note(&self, frame: &mut Frame, request_id: RequestId, area: Rect) {
+        let note = self.note.get_or_update(request_id, || {
+            ViewContext::with_database(|database| {
+                database.get_exchange_note(request_id)
+            })
+            .reported(&ViewContext::messages_tx())
+            .flatten()
+            .map(|note| TextWindow::new(note).into())
+        });
+        if let Some(note) = note.as_ref() {
+            note.draw(
+                frame,
+                TextWindowProps {
+                    has_search_box: false,
+                    toggles: Default::default(),
+                },
+                area,
+                true,
+            );
+        } else {
+            let input_engine = &TuiContext::get().input_engine;
+            frame.render_widget(
+                format!(
+                    "No note; {}",
+                    input_engine.add_hint("add one", Action::EditNote)
+                ),
+                area,
+            );
+        }
+    }
+}
+
+/// Render a one-line summary of the exchange. The left side shows the outcome:
+/// status, elapsed time, body size, and content type. The right side shows
+/// where it came from: profile, HTTP version, and start time. Fields are
+/// omitted until they're available.
+fn render_summary(frame: &mut Frame, request_state: &RequestState, area: Rect) {
+    let styles = &TuiContext::get().styles;
+    let request_metadata = request_state.request_metadata();
+    let response_metadata = request_state.response_metadata();
+
+    let mut left: Vec<Span> = Vec::new();
+    if let Some(metadata) = &response_metadata {
+        left.push(metadata.status.generate());
+    }
+    if let Some(metadata) = &request_metadata {
+        left.push(metadata.duration.generate());
+    }
+    if let Some(metadata) = &response_metadata {
+        let mut size = metadata.size.to_string_as(false);
+        if let Some(encoded_size) = metadata.encoded_size {
+            size.push_str(&format!(
+                " ({} compressed)",
+                encoded_size.to_string_as(false)
+            ));
+        }
+        left.push(size.into());
+        if let Some(content_type) = &metadata.content_type {
+            left.push(content_type.clone().into());
+        }
+        if metadata.from_cache {
+            left.push(Span::styled("from cache", styles.text.highlight));
+        }
+    }
+    match request_metadata.as_ref().map(|metadata| metadata.retries) {
+        None | Some(0) => {}
+        Some(1) => left.push("1 retry".into()),
+        Some(n) => left.push(format!("{n} retries").into()),
+    }
+
+    let mut right: Vec<Span> = vec![request_state
+        .profile_id()
+        .map(ToString::to_string)
+        .unwrap_or_else(|| "No profile".into())
+        .into()];
+    if let Some(metadata) = &response_metadata {
+        right.push(format!("{:?}", metadata.version).into());
+    }
+    if let Some(metadata) = &request_metadata {
+        right.push(metadata.start_time.generate());
+    }
+
+    let join = |spans: Vec<Span<'static>>| {
+        Line::from(
+            Itertools::intersperse(spans.into_iter(), " / ".into())
+                .collect_vec(),
+        )
+    };
+    frame.render_widget(join(left), area);
+    frame.render_widget(join(right).alignment(Alignment::Right), area);
+}
+
+/// Render a tab for each pinned exchange. The first tab is the selected
+/// request, which is shown when no pin is selected
+fn render_pins(
+    frame: &mut Frame,
+    pinned_requests: &[&RequestState],
+    selected_pin: Option<usize>,
+    area: Rect,
+) {
+    let titles = iter::once(Line::from("Current")).chain(
+        pinned_requests.iter().map(|state| {
+            let mut line = Line::from(state.recipe_id().to_string());
+            if let Some(profile_id) = state.profile_id() {
+                line.push_span(format!(" ({profile_id})"));
+            }
+            if let Some(metadata) = state.request_metadata() {
+                line.push_span(" ");
+                line.push_span(metadata.start_time.generate());
+            }
+            line
+        }),
+    );
+    frame.render_widget(
+        ratatui::widgets::Tabs::new(titles)
+            .select(selected_pin.map_or(0, |index| index + 1))
+            .highlight_style(TuiContext::get().styles.tab.highlight),
+        area,
+    );
+}
+
+/// Render the tail of an in-progress event stream, one event per line. Newest
+/// events are at the bottom, so the view follows the stream as it grows
+fn render_events(frame: &mut Frame, stream: &EventStream, area: Rect) {
+    let styles = &TuiContext::get().styles;
+    let content = stream.content();
+    if content.events.is_empty() {
+        frame.render_widget("Waiting for events...", area);
+        return;
+    }
+    // Only render what fits, to avoid cloning the whole stream
+    let events = &content.events
+        [content.events.len().saturating_sub(area.height as usize)..];
+    let lines = events
+        .iter()
+        .map(|event| {
+            Line::from(vec![
+                event
+                    .time
+                    .with_timezone(&Local)
+                    .format("%H:%M:%S ")
+                    .to_string()
+                    .into(),
+                Span::styled(event.event.clone(), styles.text.highlight),
+                " ".into(),
+                // Keep each event on one line
+                event.data.lines().join(" ").into(),
+            ])
+        })
+        .collect_vec();
+    frame.render_widget(Paragraph::new(lines), area);
+}
+
+/// Render a table of failed attempts that preceded the final one
+fn render_retries(frame: &mut Frame, retries: &[RetryAttempt], area: Rect) {
+    if retries.is_empty() {
+        frame.render_widget("No retries", area);
+        return;
+    }
+    let table = Table {
+        rows: retries
+            .iter()
+            .enumerate()
+            .map(|(i, attempt)| -> [Text; 4] {
+                [
+                    (i + 1).to_string().into(),
+                    attempt.start_time.generate().into(),
+                    attempt.duration().generate().into(),
+                    attempt.outcome.to_string().into(),
+                ]
+            })
+            .collect_vec(),
+        header: Some(["Attempt", "Started", "Duration", "Result"]),
+        column_widths: &[
+            Constraint::Length(7),
+            Constraint::Length(15),
+            Constraint::Length(8),
+            Constraint::Min(0),
+        ],
+        ..Default::default()
+    };
+    frame.render_widget(table.generate(), area);
+}
+
+/// Render a waterfall chart of each phase of the request. Each phase gets a
+/// bar, offset by the phases before it, so it's clear where the time went
+fn render_timing(frame: &mut Frame, timing: Option<&Timing>, area: Rect) {
+    const LABEL_WIDTH: u16 = 8;
+    const DURATION_WIDTH: u16 = 8;
+
+    let Some(timing) = timing else {
+        frame.render_widget("No timing data", area);
+        return;
+    };
+    let styles = &TuiContext::get().styles;
+    let phases = [
+        ("DNS", timing.dns),
+        ("Connect", timing.connect),
+        ("TLS", timing.tls),
+        ("Waiting", Some(timing.wait)),
+        ("Download", Some(timing.download)),
+    ];
+    let total: std::time::Duration =
+        phases.iter().filter_map(|(_, duration)| *duration).sum();
+    // Leave room for the spacing between columns
+    let bar_width = area.width.saturating_sub(LABEL_WIDTH + DURATION_WIDTH + 2);
+    // Convert a duration to a number of cells. Round the end rather than the
+    // length, so the bars line up with no gaps or overlap
+    let to_cells = |duration: std::time::Duration| -> usize {
+        if total.is_zero() {
+            0
+        } else {
+            (duration.as_secs_f64() / total.as_secs_f64()
+                * f64::from(bar_width))
+            .round() as usize
+        }
+    };
+
+    let mut elapsed = std::time::Duration::ZERO;
+    let table = Table {
+        rows: phases
+            .into_iter()
+            .map(|(label, duration)| -> [Text; 3] {
+                let Some(duration) = duration else {
+                    return [label.into(), "-".into(), "".into()];
+                };
+                let start = to_cells(elapsed);
+                elapsed += duration;
+                let end = to_cells(elapsed);
+                let bar = Line::from(vec![
+                    " ".repeat(start).into(),
+                    Span::styled(
+                        // Every phase gets at least a sliver, so it's visible
+                        "█".repeat((end - start).max(1)),
+                        styles.text.primary,
+                    ),
+                ]);
+                [
+                    label.into(),
+                    chrono::Duration::from_std(duration)
+                        .unwrap_or_default()
+                        .generate()
+                        .into(),
+                    bar.into(),
+                ]
+            })
+            .collect_vec(),
+        header: Some(["Phase", "Duration", ""]),
+        column_widths: &[
+            Constraint::Length(LABEL_WIDTH),
+            Constraint::Length(DURATION_WIDTH),
+            Constraint::Min(0),
+        ],
+        ..Default::default()
+    };
+    frame.render_widget(table.generate(), area);
+}
+
+/// Render the negotiated TLS parameters and the server's certificate chain.
+/// Expired certificates are highlighted, since they're the most common reason
+/// to be looking at this
+fn render_tls(frame: &mut Frame, tls: Option<&TlsInfo>, area: Rect) {
+    let Some(tls) = tls else {
+        frame.render_widget("No TLS connection", area);
+        return;
+    };
+    let styles = &TuiContext::get().styles;
+    let field = |label: &'static str, value: String| {
+        Line::from(vec![
+            Span::styled(format!("{label}: "), styles.text.highlight),
+            value.into(),
+        ])
+    };
+
+    let mut lines = vec![
+        field("Protocol", tls.protocol.clone()),
+        field("Cipher suite", tls.cipher_suite.clone()),
+    ];
+    for (i, certificate) in tls.certificates.iter().enumerate() {
+        // First certificate is the server's, the rest are intermediates
+        let title = if i == 0 { "Server" } else { "Issuer" };
+        // The default date format omits the year, which matters here
+        let validity = format!(
+            "{} to {}",
+            certificate
+                .not_before
+                .with_timezone(&Local)
+                .format("%Y-%m-%d"),
+            certificate
+                .not_after
+                .with_timezone(&Local)
+                .format("%Y-%m-%d")
+        );
+        lines.extend([
+            Line::default(),
+            Line::styled(
+                format!("Certificate {} ({title})", i + 1),
+                styles.text.primary,
+            ),
+            field("Subject", certificate.subject.clone()),
+            field("Issuer", certificate.issuer.clone()),
+            field(
+                "Alternative names",
+                certificate.subject_alt_names.join(", "),
+            ),
+            if certificate.is_expired() {
+                Line::from(vec![
+                    Span::styled("Valid: ", styles.text.highlight),
+                    Span::styled(
+                        format!("{validity} (expired)"),
+                        styles.text.error,
+                    ),
+                ])
+            } else {
+                field("Valid", validity)
+            },
+        ]);
+    }
+    frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), area);
+}
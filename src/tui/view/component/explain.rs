@@ -0,0 +1,70 @@
+//! Modal showing how each template in a request was rendered
+
+use crate::{
+    collection::RecipeId,
+    template::Trace,
+    tui::view::{
+        common::{
+            modal::{IntoModal, Modal},
+            text_window::{TextWindow, TextWindowProps},
+        },
+        draw::{Draw, DrawMetadata},
+        event::EventHandler,
+        Component,
+    },
+};
+use ratatui::{prelude::Constraint, text::Line, Frame};
+
+/// Show the render trace of a request, as a scrollable tree
+#[derive(Debug)]
+pub struct ExplainModal {
+    recipe_id: RecipeId,
+    text_window: Component<TextWindow<String>>,
+}
+
+impl ExplainModal {
+    pub fn new(recipe_id: RecipeId, trace: Trace) -> Self {
+        Self {
+            recipe_id,
+            text_window: TextWindow::new(trace.to_string()).into(),
+        }
+    }
+}
+
+impl Modal for ExplainModal {
+    fn title(&self) -> Line<'_> {
+        format!("Explain `{}`", self.recipe_id).into()
+    }
+
+    fn dimensions(&self) -> (Constraint, Constraint) {
+        (Constraint::Percentage(80), Constraint::Percentage(60))
+    }
+}
+
+impl EventHandler for ExplainModal {
+    fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
+        vec![self.text_window.as_child()]
+    }
+}
+
+impl Draw for ExplainModal {
+    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+        self.text_window.draw(
+            frame,
+            TextWindowProps {
+                has_search_box: false,
+                toggles: Default::default(),
+            },
+            metadata.area(),
+            true,
+        );
+    }
+}
+
+impl IntoModal for (RecipeId, Trace) {
+    type Target = ExplainModal;
+
+    fn into_modal(self) -> Self::Target {
+        ExplainModal::new(self.0, self.1)
+    }
+}
@@ -0,0 +1,254 @@
+//! Modal for picking a file, for `!file_picker` chains
+
+use crate::{
+    template::{FilePrompt, PromptChannel},
+    tui::{
+        input::Action,
+        view::{
+            common::{
+                list::List,
+                modal::{IntoModal, Modal},
+            },
+            draw::{Draw, DrawMetadata, ToStringGenerate},
+            event::{Event, EventHandler, Update},
+            state::select::SelectState,
+            Component, ViewContext,
+        },
+    },
+    util::ResultExt,
+};
+use anyhow::Context;
+use derive_more::Display;
+use ratatui::{prelude::Constraint, text::Line, Frame};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// A simple file browser. The user can navigate into directories (including
+/// `..`), and selecting a file submits its path.
+#[derive(Debug)]
+pub struct FilePickerModal {
+    /// Modal title, from the prompt message
+    message: String,
+    /// Directory whose contents are being shown
+    directory: PathBuf,
+    /// Contents of the current directory. Rebuilt on each navigation
+    select: Component<SelectState<FileEntry>>,
+    /// Error from reading the current directory, if any
+    error: Option<String>,
+    /// Channel used to submit the selected path. This is taken on submission,
+    /// so it can only be used once
+    channel: Option<PromptChannel<PathBuf>>,
+}
+
+impl FilePickerModal {
+    pub fn new(prompt: FilePrompt) -> Self {
+        // Use an absolute path, so `..` can just go to the parent
+        let directory =
+            prompt.directory.canonicalize().unwrap_or(prompt.directory);
+        let mut slf = Self {
+            message: prompt.message,
+            directory: PathBuf::new(),
+            select: Default::default(),
+            error: None,
+            channel: Some(prompt.channel),
+        };
+        slf.open_directory(directory);
+        slf
+    }
+
+    /// Show the contents of a directory
+    fn open_directory(&mut self, directory: PathBuf) {
+        match read_entries(&directory)
+            .with_context(|| format!("Error reading directory {directory:?}"))
+            .traced()
+        {
+            Ok(entries) => {
+                self.select = SelectState::builder(entries).build().into();
+                self.error = None;
+            }
+            Err(error) => {
+                self.select = Default::default();
+                self.error = Some(format!("{error:#}"));
+            }
+        }
+        self.directory = directory;
+    }
+
+    /// Open the selected directory, or submit the selected file
+    fn submit(&mut self) {
+        let Some(entry) = self.select.data().selected() else {
+            return;
+        };
+        if entry.is_parent {
+            let parent = self
+                .directory
+                .parent()
+                .map(Path::to_owned)
+                .unwrap_or_else(|| self.directory.clone());
+            self.open_directory(parent);
+        } else if entry.is_dir {
+            let directory = self.directory.join(&entry.name);
+            self.open_directory(directory);
+        } else {
+            let path = self.directory.join(&entry.name);
+            if let Some(channel) = self.channel.take() {
+                channel.respond(path);
+            }
+            ViewContext::push_event(Event::CloseModal);
+        }
+    }
+}
+
+impl Modal for FilePickerModal {
+    fn title(&self) -> Line<'_> {
+        format!("{} ({})", self.message, self.directory.display()).into()
+    }
+
+    fn dimensions(&self) -> (Constraint, Constraint) {
+        (Constraint::Percentage(60), Constraint::Percentage(50))
+    }
+}
+
+impl EventHandler for FilePickerModal {
+    fn update(&mut self, event: Event) -> Update {
+        match event.action() {
+            Some(Action::Submit) => self.submit(),
+            _ => return Update::Propagate(event),
+        }
+        Update::Consumed
+    }
+
+    fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
+        vec![self.select.as_child()]
+    }
+}
+
+impl Draw for FilePickerModal {
+    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+        if let Some(error) = &self.error {
+            frame.render_widget(error.as_str(), metadata.area());
+            return;
+        }
+        self.select.draw(
+            frame,
+            List::new(self.select.data().items()),
+            metadata.area(),
+            true,
+        );
+    }
+}
+
+impl IntoModal for FilePrompt {
+    type Target = FilePickerModal;
+
+    fn into_modal(self) -> Self::Target {
+        FilePickerModal::new(self)
+    }
+}
+
+/// A file or directory in the picker list
+#[derive(Debug, Display, PartialEq)]
+#[display("{name}{}", if *is_dir { "/" } else { "" })]
+struct FileEntry {
+    name: String,
+    is_dir: bool,
+    /// Is this the `..` entry?
+    is_parent: bool,
+}
+
+impl ToStringGenerate for FileEntry {}
+
+/// Get the contents of a directory, for display in the picker. Directories
+/// come first, then files, each sorted by name. Hidden files are included,
+/// because they're often exactly what the user is looking for (e.g. `.env`).
+fn read_entries(directory: &Path) -> io::Result<Vec<FileEntry>> {
+    let mut entries = fs::read_dir(directory)?
+        .map(|entry| {
+            let entry = entry?;
+            Ok(FileEntry {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                // Follow symlinks
+                is_dir: entry.path().is_dir(),
+                is_parent: false,
+            })
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then(a.name.cmp(&b.name)));
+    if directory.parent().is_some() {
+        entries.insert(
+            0,
+            FileEntry {
+                name: "..".into(),
+                is_dir: true,
+                is_parent: true,
+            },
+        );
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_util::{temp_dir, TempDir},
+        tui::{
+            test_util::{harness, TestHarness},
+            view::test_util::TestComponent,
+        },
+    };
+    use crossterm::event::KeyCode;
+    use rstest::rstest;
+    use tokio::sync::oneshot;
+
+    fn names(entries: &[FileEntry]) -> Vec<String> {
+        entries.iter().map(ToString::to_string).collect()
+    }
+
+    #[rstest]
+    fn test_read_entries(temp_dir: TempDir) {
+        fs::create_dir(temp_dir.join("b_dir")).unwrap();
+        fs::write(temp_dir.join("a.txt"), "").unwrap();
+        fs::write(temp_dir.join(".env"), "").unwrap();
+        assert_eq!(
+            names(&read_entries(&temp_dir).unwrap()),
+            ["../", "b_dir/", ".env", "a.txt"]
+        );
+    }
+
+    /// Navigate into a directory, then back out
+    #[rstest]
+    fn test_navigate(harness: TestHarness, temp_dir: TempDir) {
+        fs::create_dir(temp_dir.join("dir")).unwrap();
+        fs::write(temp_dir.join("dir/file.txt"), "").unwrap();
+        let (tx, _rx) = oneshot::channel();
+        let prompt = FilePrompt {
+            message: "File".into(),
+            directory: temp_dir.to_path_buf(),
+            channel: tx.into(),
+        };
+        let mut component =
+            TestComponent::new(harness, FilePickerModal::new(prompt), ());
+        let directory = temp_dir.canonicalize().unwrap();
+        assert_eq!(component.data().directory, directory);
+
+        // Select `dir/` and open it
+        component.send_key(KeyCode::Down).assert_empty();
+        component.send_key(KeyCode::Enter).assert_empty();
+        assert_eq!(component.data().directory, directory.join("dir"));
+        assert_eq!(
+            names(component.data().select.data().items()),
+            ["../", "file.txt"]
+        );
+
+        // Back up
+        component.send_key(KeyCode::Enter).assert_empty();
+        assert_eq!(component.data().directory, directory);
+        assert_eq!(
+            names(component.data().select.data().items()),
+            ["../", "dir/"]
+        );
+    }
+}
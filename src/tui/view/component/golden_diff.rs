@@ -0,0 +1,321 @@
+//! Modal comparing the latest response for a recipe to its golden response
+
+use crate::{
+    db::GoldenExchange,
+    http::{Exchange, ResponseRecord, SelectorType},
+    tui::{
+        context::TuiContext,
+        view::{
+            common::{
+                modal::{IntoModal, Modal},
+                text_window::{TextWindow, TextWindowProps},
+            },
+            component::Component,
+            draw::{Draw, DrawMetadata, Generate},
+            event::EventHandler,
+        },
+    },
+};
+use itertools::Itertools;
+use ratatui::{
+    layout::{Constraint, Layout},
+    text::{Line, Span, Text},
+    Frame,
+};
+use reqwest::header;
+use similar::{ChangeTag, TextDiff};
+use std::fmt::Write;
+
+/// Number of unchanged lines to show around each change
+const CONTEXT_LINES: usize = 3;
+
+/// Line diff of the latest response against the golden response, so changes
+/// to an API's contract stand out. Status, headers, and the prettified body
+/// are compared. Only changed lines and a bit of context around them are
+/// shown.
+#[derive(Debug)]
+pub struct GoldenDiff {
+    name: String,
+    golden: Exchange,
+    latest: Exchange,
+    /// `None` if the responses are identical
+    text_window: Option<Component<TextWindow<DiffText>>>,
+}
+
+/// Every line in the diff, as one block of text
+#[derive(Debug)]
+struct DiffText(Vec<DiffLine>);
+
+/// One line of a diff
+#[derive(Debug, PartialEq)]
+enum DiffLine {
+    /// Separates groups of changes that are far apart
+    Gap,
+    Equal(String),
+    /// Line is only in the golden response
+    Removed(String),
+    /// Line is only in the latest response
+    Added(String),
+}
+
+impl GoldenDiff {
+    pub fn new(golden: GoldenExchange, latest: Exchange) -> Self {
+        let lines = diff_lines(
+            &snapshot(&golden.exchange.response),
+            &snapshot(&latest.response),
+        );
+        Self {
+            name: golden.name,
+            golden: golden.exchange,
+            latest,
+            text_window: (!lines.is_empty())
+                .then(|| TextWindow::new(DiffText(lines)).into()),
+        }
+    }
+}
+
+impl Modal for GoldenDiff {
+    fn title(&self) -> Line<'_> {
+        format!("Golden `{}` vs Latest", self.name).into()
+    }
+
+    fn dimensions(&self) -> (Constraint, Constraint) {
+        (Constraint::Percentage(80), Constraint::Percentage(80))
+    }
+}
+
+impl EventHandler for GoldenDiff {
+    fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
+        self.text_window
+            .as_mut()
+            .map(Component::as_child)
+            .into_iter()
+            .collect()
+    }
+}
+
+impl Draw for GoldenDiff {
+    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+        let styles = &TuiContext::get().styles;
+        let [golden_area, latest_area, diff_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Min(0),
+        ])
+        .spacing(1)
+        .areas(metadata.area());
+
+        // Explain which side is which
+        frame.render_widget(
+            Line::from(vec![
+                Span::styled("- golden ", styles.text.error),
+                self.golden.start_time.generate(),
+            ]),
+            golden_area,
+        );
+        frame.render_widget(
+            Line::from(vec![
+                Span::styled("+ latest ", styles.status_code.success),
+                self.latest.start_time.generate(),
+            ]),
+            latest_area,
+        );
+
+        match &self.text_window {
+            Some(text_window) => text_window.draw(
+                frame,
+                TextWindowProps {
+                    has_search_box: false,
+                    toggles: Default::default(),
+                },
+                diff_area,
+                true,
+            ),
+            None => frame.render_widget("No differences", diff_area),
+        }
+    }
+}
+
+impl Generate for &DiffText {
+    type Output<'this>
+        = Text<'this>
+    where
+        Self: 'this;
+
+    fn generate<'this>(self) -> Self::Output<'this>
+    where
+        Self: 'this,
+    {
+        let styles = &TuiContext::get().styles;
+        self.0
+            .iter()
+            .map(|line| match line {
+                DiffLine::Gap => Line::styled("...", styles.text.highlight),
+                DiffLine::Equal(text) => format!("  {text}").into(),
+                DiffLine::Removed(text) => {
+                    Line::styled(format!("- {text}"), styles.text.error)
+                }
+                DiffLine::Added(text) => Line::styled(
+                    format!("+ {text}"),
+                    styles.status_code.success,
+                ),
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+impl IntoModal for (GoldenExchange, Exchange) {
+    type Target = GoldenDiff;
+
+    fn into_modal(self) -> Self::Target {
+        GoldenDiff::new(self.0, self.1)
+    }
+}
+
+/// Get the text of a response to be diffed: status, headers, and body. The
+/// `Date` header is left out because it changes on every response. Headers
+/// are sorted so their order doesn't matter, and the body is prettified so
+/// formatting doesn't matter.
+fn snapshot(response: &ResponseRecord) -> String {
+    let mut text = format!("{}\n", response.status);
+    for (name, value) in response
+        .headers
+        .iter()
+        .filter(|(name, _)| **name != header::DATE)
+        .sorted_by_key(|(name, _)| name.as_str())
+    {
+        // Writing to a string can't fail
+        let _ = writeln!(
+            &mut text,
+            "{name}: {}",
+            String::from_utf8_lossy(value.as_bytes())
+        );
+    }
+    text.push('\n');
+
+    response.parse_body();
+    text.push_str(&response.body.display_text(SelectorType::default(), None));
+    text
+}
+
+/// Diff two texts by line. Only changed lines, and a few unchanged lines
+/// around each change, are included. Return an empty list if the texts are
+/// the same.
+fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let diff = TextDiff::from_lines(old, new);
+    diff.grouped_ops(CONTEXT_LINES)
+        .iter()
+        .enumerate()
+        .flat_map(|(i, group)| {
+            let gap = (i > 0).then_some(DiffLine::Gap);
+            let changes = group.iter().flat_map(|op| diff.iter_changes(op));
+            gap.into_iter().chain(changes.map(|change| {
+                let text = change.value().trim_end_matches('\n').to_owned();
+                match change.tag() {
+                    ChangeTag::Equal => DiffLine::Equal(text),
+                    ChangeTag::Delete => DiffLine::Removed(text),
+                    ChangeTag::Insert => DiffLine::Added(text),
+                }
+            }))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_util::{header_map, Factory},
+        tui::{
+            test_util::{harness, TestHarness},
+            view::test_util::TestComponent,
+        },
+    };
+    use reqwest::StatusCode;
+    use rstest::rstest;
+    use std::sync::Arc;
+
+    /// Only changed lines and their context are included, with a gap between
+    /// changes that are far apart
+    #[rstest]
+    #[case::same("a\nb\n", "a\nb\n", &[])]
+    #[case::changed(
+        "a\nb\nc\n",
+        "a\nB\nc\n",
+        &[
+            DiffLine::Equal("a".into()),
+            DiffLine::Removed("b".into()),
+            DiffLine::Added("B".into()),
+            DiffLine::Equal("c".into()),
+        ],
+    )]
+    #[case::gap(
+        "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n",
+        "0\n2\n3\n4\n5\n6\n7\n8\n9\n11\n",
+        &[
+            DiffLine::Removed("1".into()),
+            DiffLine::Added("0".into()),
+            DiffLine::Equal("2".into()),
+            DiffLine::Equal("3".into()),
+            DiffLine::Equal("4".into()),
+            DiffLine::Gap,
+            DiffLine::Equal("7".into()),
+            DiffLine::Equal("8".into()),
+            DiffLine::Equal("9".into()),
+            DiffLine::Removed("10".into()),
+            DiffLine::Added("11".into()),
+        ],
+    )]
+    fn test_diff_lines(
+        #[case] old: &str,
+        #[case] new: &str,
+        #[case] expected: &[DiffLine],
+    ) {
+        assert_eq!(diff_lines(old, new), expected);
+    }
+
+    /// The date header is ignored, and JSON bodies are prettified before
+    /// they're compared
+    #[rstest]
+    fn test_golden_diff(harness: TestHarness) {
+        let exchange = |status: StatusCode, date: &str, body: &[u8]| Exchange {
+            response: Arc::new(ResponseRecord {
+                status,
+                headers: header_map([
+                    ("content-type", "application/json"),
+                    ("date", date),
+                ]),
+                body: body.to_vec().into(),
+                ..ResponseRecord::factory(())
+            }),
+            ..Exchange::factory(())
+        };
+        let golden = GoldenExchange {
+            name: "v1".into(),
+            exchange: exchange(
+                StatusCode::OK,
+                "Mon, 1 Jan 2024 00:00:00 GMT",
+                br#"{"id": 1, "name": "Barry"}"#,
+            ),
+        };
+        let latest = exchange(
+            StatusCode::NOT_FOUND,
+            "Tue, 2 Jan 2024 00:00:00 GMT",
+            br#"{"id":1,"name":"Barry"}"#,
+        );
+        let component =
+            TestComponent::new(harness, (golden, latest).into_modal(), ());
+        let text_window = component.data().text_window.as_ref().unwrap();
+        assert_eq!(
+            text_window.data().text().0,
+            vec![
+                DiffLine::Removed("200 OK".into()),
+                DiffLine::Added("404 Not Found".into()),
+                DiffLine::Equal("content-type: application/json".into()),
+                DiffLine::Equal("".into()),
+                DiffLine::Equal("{".into()),
+            ]
+        );
+    }
+}
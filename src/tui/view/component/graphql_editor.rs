@@ -0,0 +1,280 @@
+//! Modal for editing a GraphQL query, with completion from the schema
+
+use crate::{
+    http::{BuildOptions, GraphQlSchema},
+    template::Template,
+    tui::{
+        context::TuiContext,
+        input::Action,
+        message::{Message, RequestConfig},
+        view::{
+            common::{
+                modal::{IntoModal, Modal},
+                text_box::TextBox,
+            },
+            component::Component,
+            draw::{Draw, DrawMetadata},
+            event::{Event, EventHandler, Update},
+            state::StateCell,
+            ViewContext,
+        },
+    },
+    util::common_prefix,
+};
+use anyhow::Context;
+use ratatui::{
+    layout::{Constraint, Layout},
+    text::Line,
+    Frame,
+};
+
+/// Edit the query of a GraphQL recipe, then send the request with the edited
+/// query. If the recipe's schema has been fetched, field names can be
+/// completed with Tab, and unknown fields are flagged as the query is typed.
+/// Like editing a body in the external editor, the recipe itself isn't
+/// modified.
+#[derive(Debug)]
+pub struct GraphqlEditor {
+    request_config: RequestConfig,
+    /// `None` if the schema hasn't been fetched yet
+    schema: Option<GraphQlSchema>,
+    text_box: Component<TextBox>,
+    /// Fields that can complete the name being typed, keyed by the query text
+    /// left of the cursor
+    completions: StateCell<String, Vec<String>>,
+    /// Result of validating the query against the schema, keyed by the full
+    /// query text
+    validation: StateCell<String, Result<(), String>>,
+}
+
+/// All callback events from the query text box
+#[derive(Debug)]
+enum EditorCallback {
+    Submit,
+    Complete,
+}
+
+impl GraphqlEditor {
+    pub fn new(
+        request_config: RequestConfig,
+        query: String,
+        schema: Option<GraphQlSchema>,
+    ) -> Self {
+        let text_box = TextBox::default()
+            .with_multiline(true)
+            .with_default(query)
+            .with_on_cancel(|_| ViewContext::push_event(Event::CloseModal))
+            .with_on_submit(|_| {
+                ViewContext::push_event(Event::new_local(
+                    EditorCallback::Submit,
+                ))
+            })
+            .with_on_complete(|_| {
+                ViewContext::push_event(Event::new_local(
+                    EditorCallback::Complete,
+                ))
+            });
+        Self {
+            request_config,
+            schema,
+            text_box: text_box.into(),
+            completions: Default::default(),
+            validation: Default::default(),
+        }
+    }
+
+    /// Get the fields that could complete the name before the cursor
+    fn completions(&self) -> Vec<String> {
+        let Some(schema) = &self.schema else {
+            return Vec::new();
+        };
+        let input = self.text_box.data().text_before_cursor();
+        self.completions
+            .get_or_update(input.to_owned(), || schema.complete(input))
+            .clone()
+    }
+
+    /// Fill in the longest prefix shared by all available field completions
+    fn complete(&mut self) {
+        let completions = self.completions();
+        let text_box = self.text_box.data_mut();
+        let before_cursor = text_box.text_before_cursor();
+        let partial = before_cursor
+            .rsplit(|c: char| !(c == '_' || c.is_alphanumeric()))
+            .next()
+            .unwrap_or_default();
+        if let Some(suffix) = common_prefix(&completions).strip_prefix(partial)
+        {
+            text_box.insert_text(suffix);
+        }
+    }
+
+    /// Send the request with the edited query in place of the recipe's body.
+    /// The modal stays open if the query isn't a valid template, so the user
+    /// can fix it.
+    fn submit(&mut self) {
+        let query = self.text_box.data().text().to_owned();
+        match Template::try_from(query).context("Query is not a valid template")
+        {
+            Ok(body) => {
+                let request_config = self.request_config.clone();
+                ViewContext::send_message(Message::HttpBeginRequest(
+                    RequestConfig {
+                        options: BuildOptions {
+                            body: Some(body),
+                            ..request_config.options
+                        },
+                        ..request_config
+                    },
+                ));
+                ViewContext::push_event(Event::CloseModal);
+            }
+            Err(error) => ViewContext::send_message(Message::Error { error }),
+        }
+    }
+
+    /// Get a line of feedback for the query being typed. Show available field
+    /// completions if there are any, otherwise the first validation error
+    fn feedback(&self) -> Line<'_> {
+        let styles = &TuiContext::get().styles;
+        let Some(schema) = &self.schema else {
+            return "No schema fetched; use Fetch GraphQL Schema to enable \
+                completion"
+                .into();
+        };
+
+        let completions = self.completions();
+        if !completions.is_empty() {
+            return format!("Tab to complete: {}", completions.join(", "))
+                .into();
+        }
+
+        let text = self.text_box.data().text();
+        let validation = self
+            .validation
+            .get_or_update(text.to_owned(), || schema.validate(text));
+        match &*validation {
+            Ok(()) => Line::default(),
+            Err(error) => Line::styled(error.clone(), styles.text.error),
+        }
+    }
+}
+
+impl Modal for GraphqlEditor {
+    fn title(&self) -> Line<'_> {
+        let input_engine = &TuiContext::get().input_engine;
+        format!(
+            "Query `{}` | {}",
+            self.request_config.recipe_id,
+            input_engine.add_hint("Send", Action::Submit),
+        )
+        .into()
+    }
+
+    fn dimensions(&self) -> (Constraint, Constraint) {
+        (Constraint::Percentage(80), Constraint::Percentage(60))
+    }
+}
+
+impl EventHandler for GraphqlEditor {
+    fn update(&mut self, event: Event) -> Update {
+        let Some(callback) = event.local::<EditorCallback>() else {
+            return Update::Propagate(event);
+        };
+        match callback {
+            EditorCallback::Submit => self.submit(),
+            EditorCallback::Complete => self.complete(),
+        }
+        Update::Consumed
+    }
+
+    fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
+        vec![self.text_box.as_child()]
+    }
+}
+
+impl Draw for GraphqlEditor {
+    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+        let [text_area, feedback_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)])
+                .areas(metadata.area());
+        self.text_box.draw(frame, (), text_area, true);
+        frame.render_widget(self.feedback(), feedback_area);
+    }
+}
+
+impl IntoModal for (RequestConfig, String, Option<GraphQlSchema>) {
+    type Target = GraphqlEditor;
+
+    fn into_modal(self) -> Self::Target {
+        GraphqlEditor::new(self.0, self.1, self.2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_util::{assert_matches, Factory},
+        tui::{
+            test_util::{harness, TestHarness},
+            view::test_util::TestComponent,
+        },
+    };
+    use crossterm::event::KeyCode;
+    use rstest::rstest;
+
+    fn request_config() -> RequestConfig {
+        RequestConfig {
+            profile_id: None,
+            recipe_id: "recipe1".into(),
+            options: BuildOptions::default(),
+        }
+    }
+
+    /// Tab fills in the prefix shared by all matching fields, and the
+    /// feedback line shows what's left to pick from
+    #[rstest]
+    fn test_complete(harness: TestHarness) {
+        let mut component = TestComponent::new(
+            harness,
+            GraphqlEditor::new(
+                request_config(),
+                "{ us".into(),
+                Some(GraphQlSchema::factory(())),
+            ),
+            (),
+        );
+        component.send_key(KeyCode::Tab).assert_empty();
+        assert_eq!(component.data().text_box.data().text(), "{ user");
+        assert_eq!(
+            component.data().feedback().to_string(),
+            "Tab to complete: users"
+        );
+
+        component.send_text(" { email } }").assert_empty();
+        assert_eq!(
+            component.data().feedback().to_string(),
+            "Unknown field `email` on type `User`"
+        );
+    }
+
+    /// Submitting sends the request with the query as the body override
+    #[rstest]
+    fn test_submit(harness: TestHarness) {
+        let mut component = TestComponent::new(
+            harness,
+            GraphqlEditor::new(request_config(), "{ users }".into(), None),
+            (),
+        );
+        let _ = component.send_key(KeyCode::Enter);
+        let request_config = assert_matches!(
+            component.harness_mut().pop_message_now(),
+            Message::HttpBeginRequest(request_config) => request_config,
+        );
+        assert_eq!(
+            request_config.options.body,
+            Some(Template::raw("{ users }".into()))
+        );
+    }
+}
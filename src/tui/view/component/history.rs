@@ -3,104 +3,320 @@ use crate::{
     http::RequestId,
     tui::{
         context::TuiContext,
+        input::Action,
         view::{
-            common::{list::List, modal::Modal},
+            common::{list::List, modal::Modal, text_box::TextBox},
             component::Component,
             draw::{Draw, DrawMetadata, Generate},
-            event::{Event, EventHandler},
+            event::{Event, EventHandler, Update},
             state::{select::SelectState, RequestStateSummary},
             ViewContext,
         },
     },
 };
 use ratatui::{
-    layout::Constraint,
+    layout::{Constraint, Layout},
     text::{Line, Span},
     Frame,
 };
+use std::collections::HashMap;
 
-/// Browse request/response history for a recipe
+/// Browse request/response history for a recipe. The history action toggles
+/// between the selected recipe and every recipe in the collection
 #[derive(Debug)]
 pub struct History {
     recipe_name: String,
-    select: Component<SelectState<RequestStateSummary>>,
+    /// Requests for the selected recipe+profile
+    recipe_requests: Vec<RequestStateSummary>,
+    /// Requests for every recipe+profile in the collection
+    all_requests: Vec<RequestStateSummary>,
+    /// User's notes on exchanges, keyed by request ID
+    notes: HashMap<RequestId, String>,
+    scope: HistoryScope,
+    select: Component<SelectState<HistoryItem>>,
+    /// Are we currently typing in the filter box?
+    filter_focused: bool,
+    /// Only show requests whose note, recipe, or profile contains this text.
+    /// Empty means no filter
+    filter: String,
+    filter_text_box: Component<TextBox>,
+}
+
+/// All callback events from the filter text box
+#[derive(Debug)]
+enum FilterCallback {
+    /// The filter is applied as the user types
+    Change(String),
+    Cancel,
+    Submit,
+}
+
+/// Which requests are shown in the history list
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum HistoryScope {
+    /// Just the selected recipe+profile
+    Recipe,
+    /// Everything in the collection
+    All,
+}
+
+/// One row in the history list
+#[derive(Debug)]
+struct HistoryItem {
+    summary: RequestStateSummary,
+    /// Include the recipe ID in the row? Only needed when showing multiple
+    /// recipes
+    show_recipe: bool,
+    note: Option<String>,
 }
 
 impl History {
-    /// Construct a new history modal with the given list of requests. Parent
-    /// is responsible for loading the list from the request store.
+    /// Construct a new history modal with the given lists of requests. Parent
+    /// is responsible for loading the lists from the request store.
     pub fn new(
         recipe: &Recipe,
-        requests: Vec<RequestStateSummary>,
+        recipe_requests: Vec<RequestStateSummary>,
+        all_requests: Vec<RequestStateSummary>,
+        notes: HashMap<RequestId, String>,
         selected_request_id: Option<RequestId>,
     ) -> Self {
-        let select = SelectState::builder(requests)
-            .preselect_opt(selected_request_id.as_ref())
-            // When an item is selected, load it up
-            .on_select(|exchange| {
-                ViewContext::push_event(Event::HttpSelectRequest(Some(
-                    exchange.id(),
-                )))
+        let select = build_select(
+            &recipe_requests,
+            &notes,
+            false,
+            "",
+            selected_request_id,
+        );
+        let filter_text_box = TextBox::default()
+            .with_placeholder("Filter by note, recipe, or profile")
+            .with_on_cancel(|_| {
+                ViewContext::push_event(Event::new_local(
+                    FilterCallback::Cancel,
+                ))
             })
-            .build();
-
+            .with_on_change(|text_box| {
+                ViewContext::push_event(Event::new_local(
+                    FilterCallback::Change(text_box.text().to_owned()),
+                ))
+            })
+            .with_on_submit(|_| {
+                ViewContext::push_event(Event::new_local(
+                    FilterCallback::Submit,
+                ))
+            });
         Self {
             recipe_name: recipe.name().to_owned(),
-            select: select.into(),
+            recipe_requests,
+            all_requests,
+            notes,
+            scope: HistoryScope::Recipe,
+            select,
+            filter_focused: false,
+            filter: String::new(),
+            filter_text_box: filter_text_box.into(),
         }
     }
+
+    /// Switch between the selected recipe and all recipes
+    fn toggle_scope(&mut self) {
+        self.scope = match self.scope {
+            HistoryScope::Recipe => HistoryScope::All,
+            HistoryScope::All => HistoryScope::Recipe,
+        };
+        self.rebuild_select();
+    }
+
+    /// Rebuild the list of requests after the scope or filter changes,
+    /// keeping the selection if possible
+    fn rebuild_select(&mut self) {
+        let selected = self.select.data().selected().map(HistoryItem::id);
+        let (requests, show_recipe) = match self.scope {
+            HistoryScope::Recipe => (&self.recipe_requests, false),
+            HistoryScope::All => (&self.all_requests, true),
+        };
+        self.select = build_select(
+            requests,
+            &self.notes,
+            show_recipe,
+            &self.filter,
+            selected,
+        );
+    }
+}
+
+/// Build the list of requests that match the filter
+fn build_select(
+    requests: &[RequestStateSummary],
+    notes: &HashMap<RequestId, String>,
+    show_recipe: bool,
+    filter: &str,
+    selected_request_id: Option<RequestId>,
+) -> Component<SelectState<HistoryItem>> {
+    let filter = filter.to_lowercase();
+    let items = requests
+        .iter()
+        .map(|summary| HistoryItem {
+            summary: summary.clone(),
+            show_recipe,
+            note: notes.get(&summary.id()).cloned(),
+        })
+        .filter(|item| filter.is_empty() || item.matches(&filter))
+        .collect();
+    SelectState::builder(items)
+        .preselect_opt(selected_request_id.as_ref())
+        // When an item is selected, load it up
+        .on_select(|item| {
+            ViewContext::push_event(Event::HttpSelectRequest(Some(item.id())))
+        })
+        .build()
+        .into()
 }
 
 impl Modal for History {
     fn title(&self) -> Line<'_> {
-        vec![
-            "History for ".into(),
-            Span::styled(
-                self.recipe_name.as_str(),
-                TuiContext::get().styles.text.primary,
-            ),
-        ]
+        let input_engine = &TuiContext::get().input_engine;
+        let styles = &TuiContext::get().styles;
+        match self.scope {
+            HistoryScope::Recipe => vec![
+                "History for ".into(),
+                Span::styled(self.recipe_name.as_str(), styles.text.primary),
+                format!(
+                    " | {}",
+                    input_engine.add_hint("All Recipes", Action::History)
+                )
+                .into(),
+            ],
+            HistoryScope::All => vec![
+                "History for all recipes".into(),
+                format!(
+                    " | {}",
+                    input_engine.add_hint("This Recipe", Action::History)
+                )
+                .into(),
+            ],
+        }
         .into()
     }
 
     fn dimensions(&self) -> (Constraint, Constraint) {
+        let width = match self.scope {
+            HistoryScope::Recipe => 60,
+            HistoryScope::All => 90,
+        };
         (
-            Constraint::Length(40),
-            Constraint::Length(self.select.data().items().len().min(20) as u16),
+            Constraint::Length(width),
+            Constraint::Length(
+                self.select.data().items().len().clamp(1, 20) as u16
+                    + self.show_filter() as u16,
+            ),
         )
     }
 }
 
 impl EventHandler for History {
+    fn update(&mut self, event: Event) -> Update {
+        if let Some(callback) = event.local::<FilterCallback>() {
+            match callback {
+                FilterCallback::Change(text) => {
+                    self.filter = text.trim().to_owned();
+                    self.rebuild_select();
+                }
+                // Cancelling clears the filter entirely
+                FilterCallback::Cancel => {
+                    self.filter_text_box.data_mut().set_text(String::new());
+                    self.filter.clear();
+                    self.filter_focused = false;
+                    self.rebuild_select();
+                }
+                FilterCallback::Submit => self.filter_focused = false,
+            }
+            return Update::Consumed;
+        }
+
+        match event.action() {
+            Some(Action::History) => self.toggle_scope(),
+            Some(Action::Search) => self.filter_focused = true,
+            _ => return Update::Propagate(event),
+        }
+        Update::Consumed
+    }
+
     fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
-        vec![self.select.as_child()]
+        vec![self.filter_text_box.as_child(), self.select.as_child()]
+    }
+}
+
+impl History {
+    /// Only show the filter box while it's in use
+    fn show_filter(&self) -> bool {
+        self.filter_focused || !self.filter.is_empty()
     }
 }
 
 impl Draw for History {
     fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
-        self.select.draw(
-            frame,
-            List::new(self.select.data().items()),
-            metadata.area(),
-            true,
-        );
+        let show_filter = self.show_filter();
+        let [list_area, filter_area] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(show_filter as u16),
+        ])
+        .areas(metadata.area());
+
+        if self.select.data().items().is_empty() {
+            frame.render_widget("No matching requests", list_area);
+        } else {
+            self.select.draw(
+                frame,
+                List::new(self.select.data().items()),
+                list_area,
+                true,
+            );
+        }
+        if show_filter {
+            self.filter_text_box.draw(
+                frame,
+                (),
+                filter_area,
+                self.filter_focused,
+            );
+        }
+    }
+}
+
+impl HistoryItem {
+    fn id(&self) -> RequestId {
+        self.summary.id()
+    }
+
+    /// Does the note, recipe, or profile contain the (lowercase) filter text?
+    fn matches(&self, filter: &str) -> bool {
+        let contains = |text: &str| text.to_lowercase().contains(filter);
+        self.note.as_deref().is_some_and(contains)
+            || contains(self.summary.recipe_id())
+            || self.summary.profile_id().is_some_and(|id| contains(id))
     }
 }
 
-impl Generate for &RequestStateSummary {
-    type Output<'this> = Line<'this> where Self: 'this;
+impl Generate for &HistoryItem {
+    type Output<'this>
+        = Line<'this>
+    where
+        Self: 'this;
 
     fn generate<'this>(self) -> Self::Output<'this>
     where
         Self: 'this,
     {
         let styles = &TuiContext::get().styles;
-        let description: Span = match self {
+        let summary = &self.summary;
+        let description: Span = match summary {
             RequestStateSummary::Building { .. } => "Initializing...".into(),
             RequestStateSummary::BuildError { .. } => {
                 Span::styled("Build error", styles.text.error)
             }
+            RequestStateSummary::Loading { queued: true, .. } => {
+                "Queued...".into()
+            }
             RequestStateSummary::Loading { .. } => "Loading...".into(),
             RequestStateSummary::Response(exchange) => {
                 exchange.status.generate()
@@ -109,13 +325,116 @@ impl Generate for &RequestStateSummary {
                 Span::styled("Request error", styles.text.error)
             }
         };
-        vec![self.time().generate(), " ".into(), description].into()
+        // Pad the status so the following columns line up
+        let description = Span::styled(
+            format!("{:<24}", description.content),
+            description.style,
+        );
+        let duration = match summary.duration() {
+            Some(duration) => duration.generate(),
+            None => "".into(),
+        };
+        let profile = summary
+            .profile_id()
+            .map(ToString::to_string)
+            .unwrap_or_else(|| "No profile".into());
+
+        let mut spans = vec![
+            summary.time().generate(),
+            "  ".into(),
+            description,
+            format!("{:>8}  ", duration.content).into(),
+            profile.into(),
+        ];
+        if self.show_recipe {
+            spans.extend([
+                "  ".into(),
+                Span::styled(
+                    summary.recipe_id().to_string(),
+                    styles.text.primary,
+                ),
+            ]);
+        }
+        // Just the first line of the note, so each request fits on one row
+        if let Some(line) =
+            self.note.as_deref().and_then(|note| note.lines().next())
+        {
+            spans.extend([
+                "  ".into(),
+                Span::styled(line.to_owned(), styles.text.highlight),
+            ]);
+        }
+        spans.into()
     }
 }
 
 /// Allow selection by ID
-impl PartialEq<RequestStateSummary> for RequestId {
-    fn eq(&self, other: &RequestStateSummary) -> bool {
+impl PartialEq<HistoryItem> for RequestId {
+    fn eq(&self, other: &HistoryItem) -> bool {
         self == &other.id()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::{Exchange, ExchangeSummary},
+        test_util::Factory,
+        tui::{
+            test_util::{harness, TestHarness},
+            view::test_util::TestComponent,
+        },
+    };
+    use crossterm::event::KeyCode;
+    use itertools::Itertools;
+    use rstest::rstest;
+
+    /// Typing in the filter box narrows the list down to requests whose note
+    /// matches, and cancelling restores the full list
+    #[rstest]
+    fn test_filter(harness: TestHarness) {
+        let recipe = Recipe::factory(());
+        let exchanges = (0..3)
+            .map(|_| Exchange::factory((None, recipe.id.clone())))
+            .collect_vec();
+        let requests = exchanges
+            .iter()
+            .map(|exchange| {
+                RequestStateSummary::Response(ExchangeSummary::from(exchange))
+            })
+            .collect_vec();
+        let notes = HashMap::from([(
+            exchanges[1].id,
+            "Repro for bug #123\nMore details".to_owned(),
+        )]);
+        let mut component = TestComponent::new(
+            harness,
+            History::new(&recipe, requests.clone(), requests, notes, None),
+            (),
+        );
+        let ids = |component: &TestComponent<History, ()>| {
+            component
+                .data()
+                .select
+                .data()
+                .items()
+                .iter()
+                .map(HistoryItem::id)
+                .collect_vec()
+        };
+        assert_eq!(ids(&component).len(), 3);
+
+        component.send_key(KeyCode::Char('/')).assert_empty();
+        // Selecting a request also selects it in the main view, so ignore those
+        let _ = component.send_text("BUG #1");
+        assert_eq!(ids(&component), vec![exchanges[1].id]);
+
+        // Submitting keeps the filter, cancelling clears it
+        component.send_key(KeyCode::Enter).assert_empty();
+        assert_eq!(ids(&component), vec![exchanges[1].id]);
+        component.send_key(KeyCode::Char('/')).assert_empty();
+        let _ = component.send_key(KeyCode::Esc);
+        assert_eq!(ids(&component).len(), 3);
+    }
+}
@@ -0,0 +1,199 @@
+//! Modal showing every notification and error from this session
+
+use crate::tui::{
+    context::TuiContext,
+    view::{
+        common::{
+            modal::Modal,
+            text_window::{TextWindow, TextWindowProps},
+        },
+        component::Component,
+        draw::{Draw, DrawMetadata, Generate},
+        event::EventHandler,
+        state::{Notification, NotificationLevel},
+    },
+};
+use chrono::Local;
+use ratatui::{
+    layout::Constraint,
+    text::{Line, Span, Text},
+    Frame,
+};
+use std::collections::VecDeque;
+
+/// Maximum number of messages to hold onto. Once full, the oldest message is
+/// dropped for each new one
+const MAX_LENGTH: usize = 500;
+
+/// A scrollback of notifications and errors. Notifications disappear from the
+/// footer after a few seconds, and error modals are gone once closed, so this
+/// lets the user go back and read them.
+#[derive(Debug, Default)]
+pub struct MessageLog {
+    /// Oldest first
+    messages: VecDeque<Notification>,
+}
+
+impl MessageLog {
+    /// Add a message to the end of the log
+    pub fn push(&mut self, notification: Notification) {
+        if self.messages.len() >= MAX_LENGTH {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(notification);
+    }
+
+    /// Get a modal to show a snapshot of the log
+    pub fn to_modal(&self) -> MessageLogModal {
+        MessageLogModal {
+            text_window: TextWindow::new(Messages(
+                // Newest first, so the user doesn't have to scroll for it
+                self.messages.iter().rev().cloned().collect(),
+            ))
+            .into(),
+        }
+    }
+}
+
+/// Display every logged message in a scrollable window
+#[derive(Debug)]
+pub struct MessageLogModal {
+    text_window: Component<TextWindow<Messages>>,
+}
+
+/// Wrapper for a list of messages so we can generate them as one block of text
+#[derive(Debug)]
+struct Messages(Vec<Notification>);
+
+impl Modal for MessageLogModal {
+    fn title(&self) -> Line<'_> {
+        "Message Log".into()
+    }
+
+    fn dimensions(&self) -> (Constraint, Constraint) {
+        (Constraint::Percentage(80), Constraint::Percentage(60))
+    }
+}
+
+impl EventHandler for MessageLogModal {
+    fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
+        vec![self.text_window.as_child()]
+    }
+}
+
+impl Draw for MessageLogModal {
+    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+        if self.text_window.data().text().0.is_empty() {
+            frame.render_widget("No messages yet", metadata.area());
+        } else {
+            self.text_window.draw(
+                frame,
+                TextWindowProps {
+                    has_search_box: false,
+                    toggles: Default::default(),
+                },
+                metadata.area(),
+                true,
+            );
+        }
+    }
+}
+
+impl Generate for &Messages {
+    type Output<'this>
+        = Text<'this>
+    where
+        Self: 'this;
+
+    fn generate<'this>(self) -> Self::Output<'this>
+    where
+        Self: 'this,
+    {
+        let styles = &TuiContext::get().styles;
+        self.0
+            .iter()
+            .flat_map(|notification| {
+                let timestamp = notification
+                    .timestamp
+                    .with_timezone(&Local)
+                    .format("[%H:%M:%S] ")
+                    .to_string();
+                let indent = " ".repeat(timestamp.len());
+                let style = match notification.level {
+                    NotificationLevel::Info => Default::default(),
+                    NotificationLevel::Error => styles.text.error,
+                };
+                // Errors have one line per error in the chain. Line the
+                // causes up under the first message
+                notification.message.lines().enumerate().map(
+                    move |(i, line)| {
+                        let prefix = if i == 0 {
+                            timestamp.clone()
+                        } else {
+                            indent.clone()
+                        };
+                        Line::from(vec![
+                            prefix.into(),
+                            Span::styled(line.to_owned(), style),
+                        ])
+                    },
+                )
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::{
+        test_util::{harness, TestHarness},
+        view::test_util::TestComponent,
+    };
+    use anyhow::anyhow;
+    use rstest::rstest;
+
+    /// The log is capped, dropping the oldest messages first
+    #[rstest]
+    fn test_max_length() {
+        let mut log = MessageLog::default();
+        for i in 0..(MAX_LENGTH + 5) {
+            log.push(Notification::new(i.to_string()));
+        }
+        assert_eq!(log.messages.len(), MAX_LENGTH);
+        assert_eq!(log.messages.front().unwrap().message, "5");
+    }
+
+    /// Messages are shown newest first, with each error in a chain on its own
+    /// line
+    #[rstest]
+    fn test_messages(harness: TestHarness) {
+        let mut log = MessageLog::default();
+        log.push(Notification::new("first".into()));
+        log.push(Notification::error(&anyhow!("inner").context("outer")));
+        let component = TestComponent::new(harness, log.to_modal(), ());
+        let lines = component
+            .data()
+            .text_window
+            .data()
+            .text()
+            .generate()
+            .lines
+            .into_iter()
+            .map(|line| {
+                line.spans
+                    .iter()
+                    .map(|span| span.content.as_ref())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        // Strip the timestamps, since they're time-dependent
+        let messages = lines
+            .iter()
+            .map(|line| &line["[00:00:00] ".len()..])
+            .collect::<Vec<_>>();
+        assert_eq!(messages, vec!["outer", "inner", "first"]);
+        assert!(lines[1].starts_with("           inner"));
+    }
+}
@@ -6,13 +6,14 @@ use crate::{
     tui::view::{
         common::{
             button::ButtonGroup,
+            list::List,
             modal::{IntoModal, Modal},
             text_box::TextBox,
         },
         component::Component,
         draw::{Draw, DrawMetadata, Generate},
         event::{Event, EventHandler, Update},
-        state::{fixed_select::FixedSelect, Notification},
+        state::{fixed_select::FixedSelect, select::SelectState, Notification},
         Confirm, ViewContext,
     },
 };
@@ -66,35 +67,65 @@ pub struct PromptModal {
     /// Channel used to submit entered value
     channel: PromptChannel<String>,
     /// Flag set before closing to indicate if we should submit in our own
-    /// `on_close`. This is set from the text box's `on_submit`.
+    /// `on_close`. This is set from the input's `on_submit`.
     submit: Rc<Cell<bool>>,
+    input: PromptInput,
+}
+
+/// How the user provides a value for a prompt
+#[derive(Debug)]
+enum PromptInput {
     /// Little editor fucker
-    text_box: Component<TextBox>,
+    Text {
+        text_box: Component<TextBox>,
+        multiline: bool,
+    },
+    /// Pick one of a fixed list of options
+    Select(Component<SelectState<String>>),
 }
 
+/// Height of the text box for multi-line prompts
+const MULTILINE_HEIGHT: u16 = 5;
+/// Maximum number of options to show at once in a select prompt
+const MAX_SELECT_HEIGHT: usize = 10;
+
 impl PromptModal {
     pub fn new(prompt: Prompt) -> Self {
         let submit = Rc::new(Cell::new(false));
         let submit_cell = Rc::clone(&submit);
-        let text_box = TextBox::default()
-            .with_sensitive(prompt.sensitive)
-            .with_default(prompt.default.unwrap_or_default())
-            // Make sure cancel gets propagated to close the modal
-            .with_on_cancel(|_| ViewContext::push_event(Event::CloseModal))
-            .with_on_submit(move |_| {
-                // We have to defer submission to on_close, because we need the
-                // owned value of `self.prompt`. We could have just put that in
-                // a refcell, but this felt a bit cleaner because we know this
-                // submitter will only be called once.
-                submit_cell.set(true);
-                ViewContext::push_event(Event::CloseModal);
-            })
-            .into();
+        let on_submit = move || {
+            // We have to defer submission to on_close, because we need the
+            // owned value of `self.prompt`. We could have just put that in a
+            // refcell, but this felt a bit cleaner because we know this
+            // submitter will only be called once.
+            submit_cell.set(true);
+            ViewContext::push_event(Event::CloseModal);
+        };
+
+        let input = if prompt.options.is_empty() {
+            let text_box = TextBox::default()
+                .with_sensitive(prompt.sensitive)
+                .with_multiline(prompt.multiline)
+                .with_default(prompt.default.unwrap_or_default())
+                // Make sure cancel gets propagated to close the modal
+                .with_on_cancel(|_| ViewContext::push_event(Event::CloseModal))
+                .with_on_submit(move |_| on_submit());
+            PromptInput::Text {
+                text_box: text_box.into(),
+                multiline: prompt.multiline,
+            }
+        } else {
+            let select = SelectState::builder(prompt.options)
+                .preselect_opt(prompt.default.as_ref())
+                .on_submit(move |_| on_submit())
+                .build();
+            PromptInput::Select(select.into())
+        };
         Self {
             title: prompt.message,
             channel: prompt.channel,
             submit,
-            text_box,
+            input,
         }
     }
 }
@@ -105,26 +136,60 @@ impl Modal for PromptModal {
     }
 
     fn dimensions(&self) -> (Constraint, Constraint) {
-        (Constraint::Percentage(60), Constraint::Length(1))
+        let height = match &self.input {
+            PromptInput::Text {
+                multiline: false, ..
+            } => 1,
+            PromptInput::Text {
+                multiline: true, ..
+            } => MULTILINE_HEIGHT,
+            PromptInput::Select(select) => {
+                select.data().items().len().min(MAX_SELECT_HEIGHT) as u16
+            }
+        };
+        (Constraint::Percentage(60), Constraint::Length(height))
     }
 
     fn on_close(self: Box<Self>) {
         if self.submit.get() {
             // Return the user's value and close the prompt
-            self.channel.respond(self.text_box.into_data().into_text());
+            let value = match self.input {
+                PromptInput::Text { text_box, .. } => {
+                    Some(text_box.into_data().into_text())
+                }
+                PromptInput::Select(select) => {
+                    select.data().selected().cloned()
+                }
+            };
+            if let Some(value) = value {
+                self.channel.respond(value);
+            }
         }
     }
 }
 
 impl EventHandler for PromptModal {
     fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
-        vec![self.text_box.as_child()]
+        match &mut self.input {
+            PromptInput::Text { text_box, .. } => vec![text_box.as_child()],
+            PromptInput::Select(select) => vec![select.as_child()],
+        }
     }
 }
 
 impl Draw for PromptModal {
     fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
-        self.text_box.draw(frame, (), metadata.area(), true);
+        match &self.input {
+            PromptInput::Text { text_box, .. } => {
+                text_box.draw(frame, (), metadata.area(), true)
+            }
+            PromptInput::Select(select) => select.draw(
+                frame,
+                List::new(select.data().items()),
+                metadata.area(),
+                true,
+            ),
+        }
     }
 }
 
@@ -1,18 +1,24 @@
 //! Components for the "primary" view, which is the paned request/response view
 
 use crate::{
-    collection::{Collection, Profile, ProfileId, Recipe, RecipeId},
+    collection::{
+        Collection, Profile, ProfileId, ProfileOverrides, Recipe, RecipeId,
+        RecipeKind, RecipeNode,
+    },
+    http::{ExchangeSummary, RequestId},
     tui::{
         input::Action,
         message::{Message, RequestConfig},
         view::{
             common::actions::ActionsModal,
             component::{
+                command_palette::CommandPalette,
                 exchange_pane::{ExchangePane, ExchangePaneProps},
                 help::HelpModal,
                 profile_select::ProfilePane,
-                recipe_list::RecipeListPane,
+                recipe_list::{RecipeListPane, RecipeListPaneProps},
                 recipe_pane::{RecipeMenuAction, RecipePane, RecipePaneProps},
+                websocket_console::{WebSocketMenuAction, WebSocketSend},
             },
             draw::{Draw, DrawMetadata},
             event::{Event, EventHandler, Update},
@@ -23,10 +29,12 @@ use crate::{
                 },
                 RequestState,
             },
-            Component, ViewContext,
+            Component, ModalPriority, ViewContext,
         },
     },
+    ws::{ConnectionStatus, WebSocketConnection},
 };
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
 use derive_more::Display;
 use itertools::Itertools;
 use ratatui::{
@@ -35,6 +43,7 @@ use ratatui::{
     Frame,
 };
 use serde::{Deserialize, Serialize};
+use std::{cell::Cell, collections::HashMap, ops::RangeInclusive};
 use strum::{EnumCount, EnumIter};
 
 /// Primary TUI view, which shows request/response panes
@@ -43,6 +52,15 @@ pub struct PrimaryView {
     // Own state
     selected_pane: Persistent<FixedSelectState<PrimaryPane>>,
     fullscreen_mode: Persistent<Option<FullscreenMode>>,
+    layout: Persistent<PaneLayout>,
+    /// Where the panes were drawn last, so we can tell when the mouse grabs
+    /// a pane border. `None` when the borders aren't visible
+    pane_areas: Cell<Option<PaneAreas>>,
+    /// Pane border currently being dragged with the mouse
+    dragging: Option<Divider>,
+    /// WebSocket connections opened this session, keyed by profile+recipe.
+    /// These are owned by the controller; we just hold handles to display
+    websockets: HashMap<(Option<ProfileId>, RecipeId), WebSocketConnection>,
 
     // Children
     profile_pane: Component<ProfilePane>,
@@ -54,6 +72,10 @@ pub struct PrimaryView {
 #[cfg_attr(test, derive(Clone))]
 pub struct PrimaryViewProps<'a> {
     pub selected_request: Option<&'a RequestState>,
+    /// Exchanges pinned as tabs in the exchange pane
+    pub pinned_requests: Vec<&'a RequestState>,
+    /// Index of the pinned exchange to show in place of the selected request
+    pub selected_pin: Option<usize>,
 }
 
 /// Selectable panes in the primary view mode
@@ -89,14 +111,103 @@ enum FullscreenMode {
 }
 impl_persistable!(Option<FullscreenMode>);
 
+/// User-adjustable arrangement of the primary panes
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct PaneLayout {
+    preset: LayoutPreset,
+    /// Width of the left column, which holds the profile and recipe list
+    sidebar_width: u16,
+    /// Percentage of the right column given to the recipe pane, with the rest
+    /// going to the exchange pane. `None` expands whichever is selected.
+    recipe_share: Option<u16>,
+}
+impl_persistable!(PaneLayout);
+
+impl PaneLayout {
+    const SIDEBAR_STEP: i32 = 2;
+    const SIDEBAR_WIDTH: RangeInclusive<u16> = 20..=120;
+    const SHARE_STEP: i32 = 5;
+    const RECIPE_SHARE: RangeInclusive<u16> = 10..=90;
+
+    fn set_sidebar_width(&mut self, width: i32) {
+        self.sidebar_width = clamp(width, &Self::SIDEBAR_WIDTH);
+    }
+
+    fn set_recipe_share(&mut self, share: i32) {
+        self.recipe_share = Some(clamp(share, &Self::RECIPE_SHARE));
+    }
+}
+
+impl Default for PaneLayout {
+    fn default() -> Self {
+        Self {
+            preset: LayoutPreset::default(),
+            sidebar_width: 40,
+            recipe_share: None,
+        }
+    }
+}
+
+/// Clamp a signed value into an unsigned range
+fn clamp(value: i32, range: &RangeInclusive<u16>) -> u16 {
+    value.clamp((*range.start()).into(), (*range.end()).into()) as u16
+}
+
+/// Ways to arrange the recipe and exchange panes
+#[derive(
+    Copy, Clone, Debug, Default, Display, PartialEq, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+enum LayoutPreset {
+    /// Recipe pane above the exchange pane
+    #[default]
+    Vertical,
+    /// Recipe pane beside the exchange pane
+    Horizontal,
+    /// Only the selected pane is shown
+    Zoomed,
+}
+
+impl LayoutPreset {
+    fn next(self) -> Self {
+        match self {
+            Self::Vertical => Self::Horizontal,
+            Self::Horizontal => Self::Zoomed,
+            Self::Zoomed => Self::Vertical,
+        }
+    }
+}
+
+/// Areas of the visible panes, from the last draw
+#[derive(Copy, Clone, Debug)]
+struct PaneAreas {
+    sidebar: Rect,
+    /// Column holding both the recipe and exchange panes
+    right: Rect,
+    recipe: Rect,
+}
+
+/// A pane border that can be dragged to resize panes
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Divider {
+    /// Between the sidebar and the right column
+    Sidebar,
+    /// Between the recipe and exchange panes
+    RecipeExchange,
+}
+
 /// Sentinel type for propagating an even that closes fullscreen mode
 #[derive(Debug)]
 struct ExitFullscreen;
 
 impl PrimaryView {
-    pub fn new(collection: &Collection) -> Self {
+    pub fn new(
+        collection: &Collection,
+        profile_overrides: &ProfileOverrides,
+    ) -> Self {
         let profile_pane = ProfilePane::new(
             collection.profiles.values().cloned().collect_vec(),
+            profile_overrides.clone(),
         )
         .into();
         let recipe_list_pane = RecipeListPane::new(&collection.recipes).into();
@@ -116,6 +227,13 @@ impl PrimaryView {
                 PersistentKey::FullscreenMode,
                 None,
             ),
+            layout: Persistent::new(
+                PersistentKey::PaneLayout,
+                PaneLayout::default(),
+            ),
+            pane_areas: Cell::default(),
+            dragging: None,
+            websockets: HashMap::new(),
 
             recipe_list_pane,
             profile_pane,
@@ -124,6 +242,11 @@ impl PrimaryView {
         }
     }
 
+    /// Select a recipe in the recipe list
+    pub fn select_recipe(&mut self, recipe_id: &RecipeId) {
+        self.recipe_list_pane.data_mut().select_recipe(recipe_id);
+    }
+
     /// Which recipe in the recipe list is selected? `None` iff the list is
     /// empty OR a folder is selected.
     pub fn selected_recipe(&self) -> Option<&Recipe> {
@@ -134,6 +257,60 @@ impl PrimaryView {
         self.selected_recipe().map(|recipe| &recipe.id)
     }
 
+    /// Mark a recipe as having a request complete while it wasn't selected.
+    /// See [RecipeListPane::mark_completed]
+    pub fn mark_recipe_completed(&mut self, recipe_id: RecipeId) {
+        self.recipe_list_pane.data_mut().mark_completed(recipe_id);
+    }
+
+    /// Show a newly received exchange in the recipe list. See
+    /// [RecipeListPane::record_exchange]
+    pub fn record_exchange(&mut self, summary: ExchangeSummary) {
+        self.recipe_list_pane.data_mut().record_exchange(summary);
+    }
+
+    /// Update profiles after their session overrides changed, and re-render
+    /// any template previews that may depend on them
+    pub fn set_profile_overrides(
+        &mut self,
+        profiles: Vec<Profile>,
+        profile_overrides: ProfileOverrides,
+    ) {
+        self.profile_pane
+            .data_mut()
+            .set_profiles(profiles, profile_overrides);
+        self.recipe_pane.data_mut().refresh_previews();
+    }
+
+    /// Show an edited recipe note. See [RecipePane::set_note]
+    pub fn set_recipe_note(
+        &mut self,
+        recipe_id: &RecipeId,
+        note: Option<String>,
+    ) {
+        self.recipe_pane.data_mut().set_note(recipe_id, note);
+    }
+
+    /// Show an edited exchange note. See [ExchangePane::set_note]
+    pub fn set_exchange_note(
+        &mut self,
+        request_id: RequestId,
+        note: Option<String>,
+    ) {
+        self.exchange_pane.data_mut().set_note(request_id, note);
+    }
+
+    /// Clear the background completion badge for the selected recipe
+    pub fn clear_selected_recipe_completed(&mut self) {
+        let recipe_list_pane = self.recipe_list_pane.data_mut();
+        if let Some(recipe_id) = recipe_list_pane
+            .selected_recipe()
+            .map(|recipe| recipe.id.clone())
+        {
+            recipe_list_pane.clear_completed(&recipe_id);
+        }
+    }
+
     /// Which profile in the list is selected? `None` iff the list is empty
     pub fn selected_profile(&self) -> Option<&Profile> {
         self.profile_pane.data().selected_profile()
@@ -143,6 +320,48 @@ impl PrimaryView {
         self.selected_profile().map(|profile| &profile.id)
     }
 
+    /// Connection for the selected profile+recipe, if one has been opened
+    fn selected_websocket(&self) -> Option<&WebSocketConnection> {
+        let recipe_id = self.selected_recipe_id()?;
+        self.websockets
+            .get(&(self.selected_profile_id().cloned(), recipe_id.clone()))
+    }
+
+    /// Build a request config from the selected profile+recipe. `None` if no
+    /// recipe is selected
+    fn request_config(&self) -> Option<RequestConfig> {
+        Some(RequestConfig {
+            recipe_id: self.selected_recipe_id()?.clone(),
+            profile_id: self.selected_profile_id().cloned(),
+            options: self.recipe_pane.data().build_options(),
+        })
+    }
+
+    /// Open a modal to compare the selected profile against another
+    pub fn open_profile_compare(&self) {
+        self.profile_pane.data().open_compare_modal();
+    }
+
+    /// Build props for the exchange pane, which shows either the selected
+    /// request or a pinned one
+    fn exchange_pane_props<'a>(
+        &'a self,
+        props: &'a PrimaryViewProps<'a>,
+    ) -> ExchangePaneProps<'a> {
+        let request_state = match props.selected_pin {
+            Some(index) => props.pinned_requests.get(index).copied(),
+            None => props.selected_request,
+        };
+        ExchangePaneProps {
+            selected_recipe_node: self.recipe_list_pane.data().selected_node(),
+            selected_profile_id: self.selected_profile_id(),
+            request_state,
+            pinned_requests: &props.pinned_requests,
+            selected_pin: props.selected_pin,
+            websocket: self.selected_websocket(),
+        }
+    }
+
     /// Draw the "normal" view, when nothing is full
     fn draw_all_panes(
         &self,
@@ -150,25 +369,44 @@ impl PrimaryView {
         props: PrimaryViewProps,
         area: Rect,
     ) {
-        // Split the main pane horizontally
-        let [left_area, right_area] =
-            Layout::horizontal([Constraint::Max(40), Constraint::Min(40)])
-                .areas(area);
+        if self.layout.preset == LayoutPreset::Zoomed {
+            self.pane_areas.set(None);
+            match self.selected_pane.selected() {
+                PrimaryPane::RecipeList => self.draw_sidebar(frame, area),
+                PrimaryPane::Recipe => self.recipe_pane.draw(
+                    frame,
+                    RecipePaneProps {
+                        selected_recipe: self.selected_recipe(),
+                        selected_profile_id: self.selected_profile_id(),
+                    },
+                    area,
+                    true,
+                ),
+                PrimaryPane::Exchange => self.exchange_pane.draw(
+                    frame,
+                    self.exchange_pane_props(&props),
+                    area,
+                    true,
+                ),
+            }
+            return;
+        }
 
-        let [profile_area, recipes_area] =
-            Layout::vertical([Constraint::Length(3), Constraint::Min(0)])
-                .areas(left_area);
+        // Split the main pane horizontally
+        let [left_area, right_area] = Layout::horizontal([
+            Constraint::Max(self.layout.sidebar_width),
+            Constraint::Min(40),
+        ])
+        .areas(area);
         let [recipe_area, request_response_area] =
             self.get_right_column_layout(right_area);
+        self.pane_areas.set(Some(PaneAreas {
+            sidebar: left_area,
+            right: right_area,
+            recipe: recipe_area,
+        }));
 
-        self.profile_pane.draw(frame, (), profile_area, true);
-        self.recipe_list_pane.draw(
-            frame,
-            (),
-            recipes_area,
-            self.is_selected(PrimaryPane::RecipeList),
-        );
-
+        self.draw_sidebar(frame, left_area);
         self.recipe_pane.draw(
             frame,
             RecipePaneProps {
@@ -181,18 +419,28 @@ impl PrimaryView {
 
         self.exchange_pane.draw(
             frame,
-            ExchangePaneProps {
-                selected_recipe_node: self
-                    .recipe_list_pane
-                    .data()
-                    .selected_node(),
-                request_state: props.selected_request,
-            },
+            self.exchange_pane_props(&props),
             request_response_area,
             self.is_selected(PrimaryPane::Exchange),
         );
     }
 
+    /// Draw the profile and recipe list panes
+    fn draw_sidebar(&self, frame: &mut Frame, area: Rect) {
+        let [profile_area, recipes_area] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(0)])
+                .areas(area);
+        self.profile_pane.draw(frame, (), profile_area, true);
+        self.recipe_list_pane.draw(
+            frame,
+            RecipeListPaneProps {
+                selected_profile_id: self.selected_profile_id(),
+            },
+            recipes_area,
+            self.is_selected(PrimaryPane::RecipeList),
+        );
+    }
+
     fn toggle_fullscreen(&mut self, mode: FullscreenMode) {
         // If we're already in the given mode, exit
         *self.fullscreen_mode = if Some(mode) == *self.fullscreen_mode {
@@ -209,23 +457,171 @@ impl PrimaryView {
 
     /// Get layout for the right column of panes
     fn get_right_column_layout(&self, area: Rect) -> [Rect; 2] {
-        // Split right column vertically. Expand the currently selected pane
-        let (top, bottom) = match self.selected_pane.selected() {
-            PrimaryPane::Recipe => (2, 1),
-            PrimaryPane::Exchange | PrimaryPane::RecipeList => (1, 2),
+        let constraints = if let Some(share) = self.layout.recipe_share {
+            [
+                Constraint::Percentage(share),
+                Constraint::Percentage(100 - share),
+            ]
+        } else {
+            // Expand the currently selected pane
+            let (top, bottom) = match self.selected_pane.selected() {
+                PrimaryPane::Recipe => (2, 1),
+                PrimaryPane::Exchange | PrimaryPane::RecipeList => (1, 2),
+            };
+            let denominator = top + bottom;
+            [
+                Constraint::Ratio(top, denominator),
+                Constraint::Ratio(bottom, denominator),
+            ]
         };
-        let denominator = top + bottom;
-        Layout::vertical([
-            Constraint::Ratio(top, denominator),
-            Constraint::Ratio(bottom, denominator),
-        ])
-        .areas(area)
+        match self.layout.preset {
+            LayoutPreset::Horizontal => {
+                Layout::horizontal(constraints).areas(area)
+            }
+            LayoutPreset::Vertical | LayoutPreset::Zoomed => {
+                Layout::vertical(constraints).areas(area)
+            }
+        }
+    }
+
+    /// Percentage of the right column currently given to the recipe pane
+    fn recipe_share(&self) -> u16 {
+        self.layout.recipe_share.unwrap_or_else(|| {
+            match self.selected_pane.selected() {
+                PrimaryPane::Recipe => 67,
+                PrimaryPane::Exchange | PrimaryPane::RecipeList => 33,
+            }
+        })
+    }
+
+    /// Grow or shrink the recipe pane by one step
+    fn resize_recipe_pane(&mut self, steps: i32) {
+        let share =
+            i32::from(self.recipe_share()) + steps * PaneLayout::SHARE_STEP;
+        self.layout.set_recipe_share(share);
+    }
+
+    /// Get the pane border at a position. Each border is two cells thick,
+    /// because adjacent panes each draw their own.
+    fn divider_at(&self, areas: PaneAreas, x: u16, y: u16) -> Option<Divider> {
+        let on_edge =
+            |position: u16, edge: u16| position == edge || position + 1 == edge;
+        let rows = areas.right.y..areas.right.bottom();
+        let columns = areas.right.x..areas.right.right();
+        let on_recipe_edge = match self.layout.preset {
+            LayoutPreset::Horizontal => {
+                on_edge(x, areas.recipe.right()) && rows.contains(&y)
+            }
+            LayoutPreset::Vertical | LayoutPreset::Zoomed => {
+                on_edge(y, areas.recipe.bottom()) && columns.contains(&x)
+            }
+        };
+        if on_edge(x, areas.right.x) && rows.contains(&y) {
+            Some(Divider::Sidebar)
+        } else if on_recipe_edge {
+            Some(Divider::RecipeExchange)
+        } else {
+            None
+        }
+    }
+
+    /// Handle a mouse press/drag, to resize panes by dragging their borders.
+    /// Return `false` if the event wasn't part of a drag.
+    fn handle_drag(&mut self, mouse_event: &MouseEvent) -> bool {
+        let Some(areas) = self.pane_areas.get() else {
+            return false;
+        };
+        let x = mouse_event.column;
+        let y = mouse_event.row;
+        match mouse_event.kind {
+            // Grab whichever border is under the mouse, if any
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.dragging = self.divider_at(areas, x, y);
+                self.dragging.is_some()
+            }
+            MouseEventKind::Drag(MouseButton::Left) => match self.dragging {
+                Some(Divider::Sidebar) => {
+                    self.layout.set_sidebar_width(
+                        i32::from(x) - i32::from(areas.sidebar.x) + 1,
+                    );
+                    true
+                }
+                Some(Divider::RecipeExchange) => {
+                    // Mouse can be outside the column, so use signed math
+                    let (position, start, size) =
+                        if self.layout.preset == LayoutPreset::Horizontal {
+                            (x, areas.right.x, areas.right.width)
+                        } else {
+                            (y, areas.right.y, areas.right.height)
+                        };
+                    let offset = i32::from(position) - i32::from(start) + 1;
+                    self.layout.set_recipe_share(
+                        offset * 100 / i32::from(size.max(1)),
+                    );
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        }
     }
 
     /// Handle menu actions for recipe list or detail panes. We handle this here
     /// for code de-duplication, and because we have access to all the needed
     /// context.
     fn handle_recipe_menu_action(&self, action: RecipeMenuAction) {
+        // Folders can be run, so check this before requiring a recipe
+        if let RecipeMenuAction::RunFolder = action {
+            let message = match self.recipe_list_pane.data().selected_node() {
+                Some(RecipeNode::Folder(folder)) => Message::RunFolder {
+                    folder_id: folder.id.clone(),
+                    profile_id: self.selected_profile_id().cloned(),
+                },
+                _ => Message::Notify("Select a folder to run".into()),
+            };
+            ViewContext::send_message(message);
+            return;
+        }
+
+        // These apply to folders too
+        let recipe_list = self.recipe_list_pane.data();
+        let selected_id = recipe_list.selected_node().map(RecipeNode::id);
+        match action {
+            RecipeMenuAction::NewFolder => {
+                // New folders go next to the selected recipe, or inside the
+                // selected folder
+                let parent_id = match recipe_list.selected_node() {
+                    Some(RecipeNode::Folder(folder)) => Some(&folder.id),
+                    Some(RecipeNode::Recipe(recipe)) => {
+                        recipe_list.parent_id(&recipe.id)
+                    }
+                    None => None,
+                };
+                ViewContext::send_message(Message::FolderCreateStart(
+                    parent_id.cloned(),
+                ));
+                return;
+            }
+            RecipeMenuAction::RenameRecipe => {
+                if let Some(id) = selected_id {
+                    ViewContext::send_message(Message::RecipeRenameStart(
+                        id.clone(),
+                    ));
+                }
+                return;
+            }
+            RecipeMenuAction::MoveToFolder => {
+                if let Some(id) = selected_id {
+                    ViewContext::send_message(Message::RecipeMoveStart(
+                        id.clone(),
+                        recipe_list.parent_id(id).cloned(),
+                    ));
+                }
+                return;
+            }
+            _ => {}
+        }
+
         // If no recipes are available, we can't do anything
         let Some(recipe_id) = self.selected_recipe_id().cloned() else {
             return;
@@ -246,6 +642,48 @@ impl PrimaryView {
             RecipeMenuAction::CopyCurl => {
                 Message::CopyRequestCurl(request_config)
             }
+            RecipeMenuAction::EditBody => {
+                Message::EditRequestBody(request_config)
+            }
+            RecipeMenuAction::ExplainRequest => {
+                Message::ExplainRequest(request_config)
+            }
+            RecipeMenuAction::FetchGraphqlSchema => {
+                Message::GraphqlIntrospect(request_config)
+            }
+            RecipeMenuAction::EditGraphqlQuery => {
+                Message::GraphqlEditStart(request_config)
+            }
+            RecipeMenuAction::ToggleWatch => {
+                Message::ToggleWatch(request_config)
+            }
+            RecipeMenuAction::ScheduleSend => {
+                Message::ScheduleStart(request_config)
+            }
+            RecipeMenuAction::RunFolder
+            | RecipeMenuAction::NewFolder
+            | RecipeMenuAction::RenameRecipe
+            | RecipeMenuAction::MoveToFolder => unreachable!("Handled above"),
+            // This one opens a modal instead of sending a message
+            RecipeMenuAction::SendAllProfiles => {
+                self.profile_pane.data().open_batch_modal(
+                    request_config.recipe_id,
+                    request_config.options,
+                );
+                return;
+            }
+            RecipeMenuAction::DuplicateRecipe => {
+                Message::RecipeDuplicate(request_config.recipe_id)
+            }
+            RecipeMenuAction::DeleteRecipe => {
+                Message::RecipeDeleteStart(request_config.recipe_id)
+            }
+            RecipeMenuAction::SaveAsRecipe => {
+                Message::ScratchSaveStart(request_config.recipe_id)
+            }
+            RecipeMenuAction::EditNote => {
+                Message::EditRecipeNote(request_config.recipe_id)
+            }
         };
         ViewContext::send_message(message);
     }
@@ -254,6 +692,16 @@ impl PrimaryView {
 impl EventHandler for PrimaryView {
     fn update(&mut self, event: Event) -> Update {
         match &event {
+            // Dragging pane borders
+            Event::Input {
+                action: None,
+                event: crossterm::event::Event::Mouse(mouse_event),
+            } => {
+                if !self.handle_drag(mouse_event) {
+                    return Update::Propagate(event);
+                }
+            }
+
             // Input messages
             Event::Input {
                 action: Some(action),
@@ -263,17 +711,33 @@ impl EventHandler for PrimaryView {
                 Action::NextPane => self.selected_pane.next(),
                 Action::Submit => {
                     // Send a request from anywhere
+                    let is_websocket =
+                        self.selected_recipe().is_some_and(|recipe| {
+                            recipe.kind == RecipeKind::WebSocket
+                        });
+                    let is_connected =
+                        self.selected_websocket().is_some_and(|connection| {
+                            connection.state().status == ConnectionStatus::Open
+                        });
+                    if is_websocket && is_connected {
+                        // Already connected, so jump to the console to send
+                        // a message instead
+                        self.selected_pane.select(&PrimaryPane::Exchange);
+                        self.exchange_pane.data_mut().focus_websocket_console();
+                    } else if let Some(request_config) = self.request_config() {
+                        ViewContext::send_message(if is_websocket {
+                            Message::WebSocketConnect(request_config)
+                        } else {
+                            Message::HttpBeginRequest(request_config)
+                        });
+                    }
+                }
+                Action::DiffGolden => {
                     if let Some(recipe_id) = self.selected_recipe_id() {
-                        ViewContext::send_message(Message::HttpBeginRequest(
-                            RequestConfig {
-                                recipe_id: recipe_id.clone(),
-                                profile_id: self.selected_profile_id().cloned(),
-                                options: self
-                                    .recipe_pane
-                                    .data()
-                                    .build_options(),
-                            },
-                        ));
+                        ViewContext::send_message(Message::GoldenDiff {
+                            profile_id: self.selected_profile_id().cloned(),
+                            recipe_id: recipe_id.clone(),
+                        });
                     }
                 }
                 Action::OpenActions => {
@@ -282,6 +746,16 @@ impl EventHandler for PrimaryView {
                 Action::OpenHelp => {
                     ViewContext::open_modal_default::<HelpModal>();
                 }
+                Action::CommandPalette => {
+                    let recipe_list = self.recipe_list_pane.data();
+                    ViewContext::open_modal(
+                        CommandPalette::new(
+                            recipe_list.recipes(),
+                            recipe_list.selected_node().is_some(),
+                        ),
+                        ModalPriority::Low,
+                    );
+                }
 
                 // Pane hotkeys
                 Action::SelectProfileList => {
@@ -311,6 +785,23 @@ impl EventHandler for PrimaryView {
                         }
                     }
                 }
+                // Layout
+                Action::CycleLayout => {
+                    self.layout.preset = self.layout.preset.next();
+                }
+                Action::ShrinkSidebar => {
+                    let width = i32::from(self.layout.sidebar_width)
+                        - PaneLayout::SIDEBAR_STEP;
+                    self.layout.set_sidebar_width(width);
+                }
+                Action::GrowSidebar => {
+                    let width = i32::from(self.layout.sidebar_width)
+                        + PaneLayout::SIDEBAR_STEP;
+                    self.layout.set_sidebar_width(width);
+                }
+                Action::ShrinkRecipePane => self.resize_recipe_pane(-1),
+                Action::GrowRecipePane => self.resize_recipe_pane(1),
+
                 // Exit fullscreen
                 Action::Cancel if self.fullscreen_mode.is_some() => {
                     *self.fullscreen_mode = None;
@@ -318,6 +809,16 @@ impl EventHandler for PrimaryView {
                 _ => return Update::Propagate(event),
             },
 
+            Event::WebSocketSetConnection(connection) => {
+                self.websockets.insert(
+                    (
+                        connection.profile_id().cloned(),
+                        connection.recipe_id().clone(),
+                    ),
+                    connection.clone(),
+                );
+            }
+
             Event::Local(local) => {
                 if let Some(ExitFullscreen) = local.downcast_ref() {
                     *self.fullscreen_mode = None;
@@ -328,6 +829,29 @@ impl EventHandler for PrimaryView {
                     local.downcast_ref::<RecipeMenuAction>()
                 {
                     self.handle_recipe_menu_action(*action);
+                } else if let Some(WebSocketSend(text)) = local.downcast_ref() {
+                    if let Some(connection) = self.selected_websocket() {
+                        connection.send(text.clone());
+                    }
+                } else if let Some(action) =
+                    local.downcast_ref::<WebSocketMenuAction>()
+                {
+                    match action {
+                        WebSocketMenuAction::Connect => {
+                            if let Some(request_config) = self.request_config()
+                            {
+                                ViewContext::send_message(
+                                    Message::WebSocketConnect(request_config),
+                                );
+                            }
+                        }
+                        WebSocketMenuAction::Disconnect => {
+                            if let Some(connection) = self.selected_websocket()
+                            {
+                                connection.close();
+                            }
+                        }
+                    }
                 } else {
                     return Update::Propagate(event);
                 }
@@ -368,13 +892,7 @@ impl<'a> Draw<PrimaryViewProps<'a>> for PrimaryView {
             ),
             Some(FullscreenMode::Exchange) => self.exchange_pane.draw(
                 frame,
-                ExchangePaneProps {
-                    selected_recipe_node: self
-                        .recipe_list_pane
-                        .data()
-                        .selected_node(),
-                    request_state: props.selected_request,
-                },
+                self.exchange_pane_props(&props),
                 metadata.area(),
                 true,
             ),
@@ -394,6 +912,7 @@ mod tests {
             view::test_util::TestComponent,
         },
     };
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
     use rstest::{fixture, rstest};
 
     /// Create component to be tested
@@ -404,9 +923,11 @@ mod tests {
         let collection = Collection::factory(());
         let mut component = TestComponent::new(
             harness,
-            PrimaryView::new(&collection),
+            PrimaryView::new(&collection, &Default::default()),
             PrimaryViewProps {
                 selected_request: None,
+                pinned_requests: Vec::new(),
+                selected_pin: None,
             },
         );
         // Clear template preview messages so we can test what we want
@@ -485,4 +1006,161 @@ mod tests {
             }
         );
     }
+
+    /// Test "Edit Body & Send" action, which is available via the Recipe List
+    /// or Recipe panes
+    #[rstest]
+    fn test_edit_body(
+        mut component: TestComponent<PrimaryView, PrimaryViewProps<'static>>,
+    ) {
+        component
+            .update_draw(Event::new_local(RecipeMenuAction::EditBody))
+            .assert_empty();
+
+        let request_config = assert_matches!(
+            component.harness_mut().pop_message_now(),
+            Message::EditRequestBody(request_config) => request_config,
+        );
+        assert_eq!(
+            request_config,
+            RequestConfig {
+                recipe_id: "recipe1".into(),
+                profile_id: Some("profile1".into()),
+                options: BuildOptions::default()
+            }
+        );
+    }
+
+    /// Test "Explain Request" action, which is available via the Recipe List
+    /// or Recipe panes
+    #[rstest]
+    fn test_explain_request(
+        mut component: TestComponent<PrimaryView, PrimaryViewProps<'static>>,
+    ) {
+        component
+            .update_draw(Event::new_local(RecipeMenuAction::ExplainRequest))
+            .assert_empty();
+
+        let request_config = assert_matches!(
+            component.harness_mut().pop_message_now(),
+            Message::ExplainRequest(request_config) => request_config,
+        );
+        assert_eq!(
+            request_config,
+            RequestConfig {
+                recipe_id: "recipe1".into(),
+                profile_id: Some("profile1".into()),
+                options: BuildOptions::default()
+            }
+        );
+    }
+
+    /// Test "Duplicate Recipe" action, which is available via the Recipe List
+    /// or Recipe panes
+    #[rstest]
+    fn test_duplicate_recipe(
+        mut component: TestComponent<PrimaryView, PrimaryViewProps<'static>>,
+    ) {
+        component
+            .update_draw(Event::new_local(RecipeMenuAction::DuplicateRecipe))
+            .assert_empty();
+
+        let recipe_id = assert_matches!(
+            component.harness_mut().pop_message_now(),
+            Message::RecipeDuplicate(recipe_id) => recipe_id,
+        );
+        assert_eq!(recipe_id, RecipeId::from("recipe1"));
+    }
+
+    /// "Connect" from the WebSocket console menu should open a connection for
+    /// the selected recipe
+    #[rstest]
+    fn test_websocket_connect(
+        mut component: TestComponent<PrimaryView, PrimaryViewProps<'static>>,
+    ) {
+        component
+            .update_draw(Event::new_local(WebSocketMenuAction::Connect))
+            .assert_empty();
+
+        let request_config = assert_matches!(
+            component.harness_mut().pop_message_now(),
+            Message::WebSocketConnect(request_config) => request_config,
+        );
+        assert_eq!(
+            request_config,
+            RequestConfig {
+                recipe_id: "recipe1".into(),
+                profile_id: Some("profile1".into()),
+                options: BuildOptions::default()
+            }
+        );
+    }
+
+    /// Resize panes and switch layouts with the keyboard
+    #[rstest]
+    fn test_layout_keys(
+        mut component: TestComponent<PrimaryView, PrimaryViewProps<'static>>,
+    ) {
+        let mut send = |code| {
+            let _ = component.send_input(crossterm::event::Event::Key(
+                KeyEvent::new(code, KeyModifiers::CONTROL),
+            ));
+        };
+        send(KeyCode::Right);
+        send(KeyCode::Down);
+        send(KeyCode::Char('l'));
+        assert_eq!(
+            *component.data().layout,
+            PaneLayout {
+                preset: LayoutPreset::Horizontal,
+                sidebar_width: 42,
+                // Recipe list is selected, so the recipe pane starts small
+                recipe_share: Some(38),
+            }
+        );
+    }
+
+    /// Drag pane borders with the mouse
+    #[rstest]
+    fn test_drag(#[with(100, 30)] harness: TestHarness) {
+        let mut component = TestComponent::new(
+            harness,
+            PrimaryView::new(&Collection::factory(()), &Default::default()),
+            PrimaryViewProps {
+                selected_request: None,
+                pinned_requests: Vec::new(),
+                selected_pin: None,
+            },
+        );
+        let mut mouse = |kind, column, row| {
+            let _ = component.send_input(crossterm::event::Event::Mouse(
+                MouseEvent {
+                    kind,
+                    column,
+                    row,
+                    modifiers: KeyModifiers::NONE,
+                },
+            ));
+        };
+        let down = MouseEventKind::Down(MouseButton::Left);
+        let drag = MouseEventKind::Drag(MouseButton::Left);
+
+        // Sidebar is 40 wide, so its border is at 39/40
+        mouse(down, 40, 5);
+        mouse(drag, 49, 5);
+        // Recipe pane is 1/3 of the height
+        mouse(down, 60, 10);
+        mouse(drag, 60, 14);
+        // Not on a border, so does nothing
+        mouse(down, 70, 20);
+        mouse(drag, 80, 25);
+        assert_eq!(
+            *component.data().layout,
+            PaneLayout {
+                preset: LayoutPreset::Vertical,
+                sidebar_width: 50,
+                recipe_share: Some(50),
+            }
+        );
+    }
 }
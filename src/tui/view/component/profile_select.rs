@@ -1,15 +1,23 @@
 //! Components related to the selection of profiles
 
 use crate::{
-    collection::{Profile, ProfileId},
+    collection::{Profile, ProfileId, ProfileOverrides, RecipeId},
+    http::BuildOptions,
+    template::Template,
     tui::{
         context::TuiContext,
         input::Action,
+        message::Message,
         view::{
             common::{
-                list::List, modal::Modal, table::Table,
-                template_preview::TemplatePreview, Pane,
+                list::List,
+                modal::{IntoModal, Modal},
+                table::Table,
+                template_preview::TemplatePreview,
+                text_box::TextBox,
+                Pane,
             },
+            component::batch_send::BatchSendModal,
             draw::{Draw, DrawMetadata, Generate},
             event::{Event, EventHandler, Update},
             state::{
@@ -25,7 +33,8 @@ use crate::{
 use itertools::Itertools;
 use ratatui::{
     layout::{Constraint, Layout},
-    text::{Line, Text},
+    text::{Line, Span, Text},
+    widgets::Row,
     Frame,
 };
 
@@ -61,13 +70,20 @@ pub struct ProfilePane {
     /// into the modal, means duplicating SelectState and cloning the contents
     /// is the best way to go.
     profiles: Persistent<SelectState<Profile>>,
+    /// Session-only field overrides. These have already been applied to the
+    /// profiles; we just need them to mark which fields are overridden
+    profile_overrides: ProfileOverrides,
 }
 
 impl ProfilePane {
-    pub fn new(profiles: Vec<Profile>) -> Self {
+    pub fn new(
+        profiles: Vec<Profile>,
+        profile_overrides: ProfileOverrides,
+    ) -> Self {
         let profiles = SelectState::builder(profiles).build();
         Self {
             profiles: Persistent::new(PersistentKey::ProfileId, profiles),
+            profile_overrides,
         }
     }
 
@@ -75,6 +91,21 @@ impl ProfilePane {
         self.profiles.selected()
     }
 
+    /// Replace the list of profiles after their overrides have changed. The
+    /// selected profile stays selected
+    pub fn set_profiles(
+        &mut self,
+        profiles: Vec<Profile>,
+        profile_overrides: ProfileOverrides,
+    ) {
+        let selected = self.profiles.selected().map(|profile| &profile.id);
+        let select = SelectState::builder(profiles)
+            .preselect_opt(selected)
+            .build();
+        *self.profiles = select;
+        self.profile_overrides = profile_overrides;
+    }
+
     /// Open the profile list modal
     pub fn open_modal(&self) {
         ViewContext::open_modal(
@@ -82,10 +113,42 @@ impl ProfilePane {
                 // See self.profiles doc comment for why we need to clone
                 self.profiles.items().to_owned(),
                 self.profiles.selected().map(|profile| &profile.id),
+                self.profile_overrides.clone(),
             ),
             ModalPriority::Low,
         );
     }
+
+    /// Open a modal to compare the selected profile against the others
+    pub fn open_compare_modal(&self) {
+        if let Some(profile) = self.profiles.selected() {
+            ViewContext::open_modal(
+                ProfileCompareModal::new(
+                    profile.clone(),
+                    self.profiles.items().to_owned(),
+                ),
+                ModalPriority::Low,
+            );
+        } else {
+            ViewContext::send_message(Message::Notify(
+                "No profiles to compare".into(),
+            ));
+        }
+    }
+
+    /// Open a modal to send a recipe once for each profile
+    pub fn open_batch_modal(&self, recipe_id: RecipeId, options: BuildOptions) {
+        if self.profiles.items().is_empty() {
+            ViewContext::send_message(Message::Notify(
+                "No profiles to send with".into(),
+            ));
+        } else {
+            ViewContext::open_modal(
+                BatchSendModal::new(recipe_id, options, self.profiles.items()),
+                ModalPriority::Low,
+            );
+        }
+    }
 }
 
 impl EventHandler for ProfilePane {
@@ -137,12 +200,14 @@ struct SelectProfile(ProfileId);
 pub struct ProfileListModal {
     select: Component<SelectState<Profile>>,
     detail: Component<ProfileDetail>,
+    profile_overrides: ProfileOverrides,
 }
 
 impl ProfileListModal {
     pub fn new(
         profiles: Vec<Profile>,
         selected_profile: Option<&ProfileId>,
+        profile_overrides: ProfileOverrides,
     ) -> Self {
         // Loaded request depends on the profile, so refresh on change
         fn on_submit(profile: &mut Profile) {
@@ -161,6 +226,7 @@ impl ProfileListModal {
         Self {
             select: select.into(),
             detail: Default::default(),
+            profile_overrides,
         }
     }
 }
@@ -208,7 +274,10 @@ impl Draw for ProfileListModal {
         if let Some(profile) = select.selected() {
             self.detail.draw(
                 frame,
-                ProfileDetailProps { profile },
+                ProfileDetailProps {
+                    profile,
+                    profile_overrides: &self.profile_overrides,
+                },
                 detail_area,
                 false,
             )
@@ -224,6 +293,7 @@ pub struct ProfileDetail {
 
 pub struct ProfileDetailProps<'a> {
     pub profile: &'a Profile,
+    pub profile_overrides: &'a ProfileOverrides,
 }
 
 impl<'a> Draw<ProfileDetailProps<'a>> for ProfileDetail {
@@ -257,7 +327,24 @@ impl<'a> Draw<ProfileDetailProps<'a>> for ProfileDetail {
             header: Some(["Field", "Value"]),
             rows: fields
                 .iter()
-                .map(|(key, value)| [key.as_str().into(), value.generate()])
+                .map(|(key, value)| {
+                    let key: Text = if props
+                        .profile_overrides
+                        .is_overridden(&props.profile.id, key)
+                    {
+                        Line::from(vec![
+                            key.as_str().into(),
+                            Span::styled(
+                                " (overridden)",
+                                TuiContext::get().styles.text.primary,
+                            ),
+                        ])
+                        .into()
+                    } else {
+                        key.as_str().into()
+                    };
+                    [key, value.generate()]
+                })
                 .collect_vec(),
             alternate_row_style: true,
             ..Default::default()
@@ -266,6 +353,493 @@ impl<'a> Draw<ProfileDetailProps<'a>> for ProfileDetail {
     }
 }
 
+/// Modal to edit session-only overrides for the fields of a profile. Each
+/// field is shown with its rendered value. Changes are sent to the controller
+/// as soon as they're submitted, so template previews throughout the app
+/// update while the modal stays open. Nothing is written to the collection
+/// file.
+#[derive(Debug)]
+pub struct ProfileOverrideModal {
+    /// The profile as defined in the collection, *without* overrides
+    profile: Profile,
+    profile_overrides: ProfileOverrides,
+    select: Component<SelectState<OverrideRow>>,
+    /// What the text box is being used for. `None` while browsing the list
+    editing: Option<OverrideEdit>,
+    text_box: Component<TextBox>,
+}
+
+/// One row in the override editor
+#[derive(Debug)]
+enum OverrideRow {
+    Field {
+        field: String,
+        /// Current value, including overrides
+        template: Template,
+        preview: TemplatePreview,
+        overridden: bool,
+    },
+    /// Placeholder to add a field that isn't in the profile yet
+    New,
+}
+
+/// What is the user typing in the override editor?
+#[derive(Debug)]
+enum OverrideEdit {
+    /// Name of a new field
+    FieldName,
+    /// Value of the given field
+    Value(String),
+}
+
+/// All callback events from the override editor's text box
+#[derive(Debug)]
+enum EditCallback {
+    /// Preview the value as the user types
+    Change(String),
+    Cancel,
+    Submit,
+}
+
+impl ProfileOverrideModal {
+    pub fn new(profile: Profile, profile_overrides: ProfileOverrides) -> Self {
+        Self {
+            select: build_override_select(&profile, &profile_overrides, None),
+            profile,
+            profile_overrides,
+            editing: None,
+            text_box: build_text_box(String::new()),
+        }
+    }
+
+    /// Rebuild the list after the overrides change
+    fn rebuild_select(&mut self, selected_field: &String) {
+        self.select = build_override_select(
+            &self.profile,
+            &self.profile_overrides,
+            Some(selected_field),
+        );
+    }
+
+    /// Start editing the selected row
+    fn start_edit(&mut self) {
+        let (editing, text) = match self.select.data().selected() {
+            Some(OverrideRow::Field {
+                field, template, ..
+            }) => (OverrideEdit::Value(field.clone()), template.to_string()),
+            Some(OverrideRow::New) => (OverrideEdit::FieldName, String::new()),
+            None => return,
+        };
+        self.text_box = build_text_box(text);
+        self.editing = Some(editing);
+    }
+
+    /// Handle a submission from the text box. For a new field, this moves on
+    /// to its value. For a value, the override is applied.
+    fn submit(&mut self) {
+        let text = self.text_box.data().text().trim().to_owned();
+        match self.editing.take() {
+            Some(OverrideEdit::FieldName) if text.is_empty() => {}
+            Some(OverrideEdit::FieldName) => {
+                // If the field already exists, edit its current value
+                let mut profile = self.profile.clone();
+                self.profile_overrides.apply_profile(&mut profile);
+                let value = profile
+                    .data
+                    .get(&text)
+                    .map(Template::to_string)
+                    .unwrap_or_default();
+                self.text_box = build_text_box(value);
+                self.editing = Some(OverrideEdit::Value(text));
+            }
+            Some(OverrideEdit::Value(field)) => {
+                // The validator already checked the template, so this
+                // shouldn't fail
+                let Ok(value) = Template::try_from(text) else {
+                    return;
+                };
+                self.profile_overrides.set(
+                    self.profile.id.clone(),
+                    field.clone(),
+                    value.clone(),
+                );
+                // Send this *before* rebuilding the rows, so their previews
+                // are rendered with the new value
+                ViewContext::send_message(Message::ProfileOverride {
+                    profile_id: self.profile.id.clone(),
+                    field: field.clone(),
+                    value,
+                });
+                self.rebuild_select(&field);
+            }
+            None => {}
+        }
+    }
+
+    /// Preview the value being typed in its row, without applying it
+    fn preview_edit(&mut self, text: &str) {
+        let (Some(OverrideEdit::Value(field)), Ok(template)) =
+            (&self.editing, Template::parse(text.to_owned()))
+        else {
+            return;
+        };
+        let profile_id = self.profile.id.clone();
+        if let Some(OverrideRow::Field { preview, .. }) = self
+            .select
+            .data_mut()
+            .items_mut()
+            .iter_mut()
+            .find(|row| matches!(row, OverrideRow::Field { field: f, .. } if f == field))
+        {
+            *preview = TemplatePreview::new(template, Some(profile_id));
+        }
+    }
+
+    /// Stop editing, throwing away any unsubmitted preview
+    fn cancel_edit(&mut self) {
+        if let Some(OverrideEdit::Value(field)) = self.editing.take() {
+            self.rebuild_select(&field);
+        }
+    }
+
+    /// Remove the override for the selected field, restoring the value from
+    /// the collection
+    fn reset_selected(&mut self) {
+        let Some(OverrideRow::Field {
+            field,
+            overridden: true,
+            ..
+        }) = self.select.data().selected()
+        else {
+            return;
+        };
+        let field = field.clone();
+        self.profile_overrides.remove(&self.profile.id, &field);
+        ViewContext::send_message(Message::ProfileRemoveOverride {
+            profile_id: self.profile.id.clone(),
+            field: field.clone(),
+        });
+        self.rebuild_select(&field);
+    }
+}
+
+/// Build the text box for the override editor. We rebuild instead of calling
+/// [TextBox::set_text], because that triggers a submission
+fn build_text_box(default: String) -> Component<TextBox> {
+    TextBox::default()
+        .with_default(default)
+        // Field names don't need to be templates, but it doesn't hurt
+        .with_validator(|text| Template::parse(text.to_owned()).is_ok())
+        .with_on_change(|text_box| {
+            ViewContext::push_event(Event::new_local(EditCallback::Change(
+                text_box.text().to_owned(),
+            )))
+        })
+        .with_on_cancel(|_| {
+            ViewContext::push_event(Event::new_local(EditCallback::Cancel))
+        })
+        .with_on_submit(|_| {
+            ViewContext::push_event(Event::new_local(EditCallback::Submit))
+        })
+        .into()
+}
+
+/// Build the list of fields in the override editor, with overrides applied
+fn build_override_select(
+    profile: &Profile,
+    profile_overrides: &ProfileOverrides,
+    selected_field: Option<&String>,
+) -> Component<SelectState<OverrideRow>> {
+    let mut profile = profile.clone();
+    profile_overrides.apply_profile(&mut profile);
+    let rows = profile
+        .data
+        .into_iter()
+        .map(|(field, template)| OverrideRow::Field {
+            overridden: profile_overrides.is_overridden(&profile.id, &field),
+            preview: TemplatePreview::new(
+                template.clone(),
+                Some(profile.id.clone()),
+            ),
+            field,
+            template,
+        })
+        .chain([OverrideRow::New])
+        .collect();
+    SelectState::builder(rows)
+        .preselect_opt(selected_field)
+        .build()
+        .into()
+}
+
+impl Modal for ProfileOverrideModal {
+    fn title(&self) -> Line<'_> {
+        let input_engine = &TuiContext::get().input_engine;
+        format!(
+            "Override {} | {} | {}",
+            self.profile.name(),
+            input_engine.add_hint("Edit", Action::Submit),
+            input_engine.add_hint("Reset", Action::CancelRequest),
+        )
+        .into()
+    }
+
+    fn dimensions(&self) -> (Constraint, Constraint) {
+        (
+            Constraint::Percentage(60),
+            // Leave room for the text box
+            Constraint::Length(
+                self.select.data().items().len().min(20) as u16 + 1,
+            ),
+        )
+    }
+}
+
+impl EventHandler for ProfileOverrideModal {
+    fn update(&mut self, event: Event) -> Update {
+        if let Some(callback) = event.local::<EditCallback>() {
+            match callback {
+                EditCallback::Change(text) => self.preview_edit(text),
+                EditCallback::Cancel => self.cancel_edit(),
+                EditCallback::Submit => self.submit(),
+            }
+            return Update::Consumed;
+        }
+
+        match event.action() {
+            Some(Action::Submit) => self.start_edit(),
+            Some(Action::CancelRequest) => self.reset_selected(),
+            _ => return Update::Propagate(event),
+        }
+        Update::Consumed
+    }
+
+    fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
+        vec![self.text_box.as_child(), self.select.as_child()]
+    }
+}
+
+impl Draw for ProfileOverrideModal {
+    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+        let [list_area, edit_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)])
+                .areas(metadata.area());
+        self.select.draw(
+            frame,
+            List::new(self.select.data().items()),
+            list_area,
+            self.editing.is_none(),
+        );
+
+        if let Some(editing) = &self.editing {
+            let label = match editing {
+                OverrideEdit::FieldName => "New field: ".to_owned(),
+                OverrideEdit::Value(field) => format!("{field} = "),
+            };
+            let [label_area, text_box_area] = Layout::horizontal([
+                Constraint::Length(label.len() as u16),
+                Constraint::Min(0),
+            ])
+            .areas(edit_area);
+            frame.render_widget(label, label_area);
+            self.text_box.draw(frame, (), text_box_area, true);
+        }
+    }
+}
+
+impl Generate for &OverrideRow {
+    type Output<'this>
+        = Line<'this>
+    where
+        Self: 'this;
+
+    fn generate<'this>(self) -> Self::Output<'this>
+    where
+        Self: 'this,
+    {
+        let styles = &TuiContext::get().styles;
+        match self {
+            OverrideRow::Field {
+                field,
+                preview,
+                overridden,
+                ..
+            } => {
+                let mut spans = vec![Span::from(format!("{field:<20} "))];
+                // Multi-line values would throw off the list, so just show
+                // the first line
+                if let Some(line) = preview.generate().lines.into_iter().next()
+                {
+                    spans.extend(line.spans);
+                }
+                if *overridden {
+                    spans.push(Span::styled(
+                        " (overridden)",
+                        styles.text.primary,
+                    ));
+                }
+                spans.into()
+            }
+            OverrideRow::New => {
+                Span::styled("+ Add field", styles.text.highlight).into()
+            }
+        }
+    }
+}
+
+/// Allow selection by field name
+impl PartialEq<OverrideRow> for String {
+    fn eq(&self, other: &OverrideRow) -> bool {
+        matches!(other, OverrideRow::Field { field, .. } if field == self)
+    }
+}
+
+impl IntoModal for (Profile, ProfileOverrides) {
+    type Target = ProfileOverrideModal;
+
+    fn into_modal(self) -> Self::Target {
+        ProfileOverrideModal::new(self.0, self.1)
+    }
+}
+
+/// Modal to compare the fields of two profiles side by side. The left profile
+/// is fixed (the active profile), and the right one is selected from a list.
+/// Rows with differing values are highlighted.
+#[derive(Debug)]
+pub struct ProfileCompareModal {
+    left: Profile,
+    right: Component<SelectState<Profile>>,
+}
+
+impl ProfileCompareModal {
+    pub fn new(left: Profile, profiles: Vec<Profile>) -> Self {
+        let profiles = profiles
+            .into_iter()
+            .filter(|profile| profile.id != left.id)
+            .collect();
+        Self {
+            left,
+            right: SelectState::builder(profiles).build().into(),
+        }
+    }
+}
+
+impl Modal for ProfileCompareModal {
+    fn title(&self) -> Line<'_> {
+        vec![
+            "Compare ".into(),
+            Span::styled(
+                self.left.name(),
+                TuiContext::get().styles.text.primary,
+            ),
+        ]
+        .into()
+    }
+
+    fn dimensions(&self) -> (Constraint, Constraint) {
+        (Constraint::Percentage(80), Constraint::Percentage(60))
+    }
+}
+
+impl EventHandler for ProfileCompareModal {
+    fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
+        vec![self.right.as_child()]
+    }
+}
+
+impl Draw for ProfileCompareModal {
+    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+        let select = self.right.data();
+        if select.items().is_empty() {
+            frame.render_widget(
+                "No other profiles to compare against",
+                metadata.area(),
+            );
+            return;
+        }
+
+        let [list_area, _, table_area] = Layout::vertical([
+            Constraint::Length(select.items().len().min(5) as u16),
+            Constraint::Length(1), // Padding
+            Constraint::Min(0),
+        ])
+        .areas(metadata.area());
+
+        self.right
+            .draw(frame, List::new(select.items()), list_area, true);
+        let Some(right) = select.selected() else {
+            return;
+        };
+
+        let styles = &TuiContext::get().styles;
+        let table = Table {
+            header: Some(["Field", self.left.name(), right.name()]),
+            rows: compare_profiles(&self.left, right)
+                .into_iter()
+                .map(|comparison| {
+                    let row = Row::new([
+                        comparison.field.to_owned(),
+                        comparison.left.unwrap_or_default().to_owned(),
+                        comparison.right.unwrap_or_default().to_owned(),
+                    ]);
+                    if comparison.is_different() {
+                        row.style(styles.table.diff)
+                    } else {
+                        row
+                    }
+                })
+                .collect_vec(),
+            column_widths: &[
+                Constraint::Percentage(20),
+                Constraint::Percentage(40),
+                Constraint::Percentage(40),
+            ],
+            ..Default::default()
+        };
+        frame.render_widget(table.generate(), table_area);
+    }
+}
+
+/// One field in a side-by-side profile comparison
+#[derive(Debug, PartialEq)]
+struct FieldComparison<'a> {
+    field: &'a str,
+    /// Raw template from the left profile, `None` if the field is missing
+    left: Option<&'a str>,
+    /// Raw template from the right profile, `None` if the field is missing
+    right: Option<&'a str>,
+}
+
+impl<'a> FieldComparison<'a> {
+    fn is_different(&self) -> bool {
+        self.left != self.right
+    }
+}
+
+/// Line up the fields of two profiles. Fields are ordered as they are in the
+/// left profile, followed by any fields that only the right profile has.
+/// Templates are compared in their raw form, because that's what the user
+/// actually wrote, and rendering may have side effects (e.g. prompts).
+fn compare_profiles<'a>(
+    left: &'a Profile,
+    right: &'a Profile,
+) -> Vec<FieldComparison<'a>> {
+    left.data
+        .keys()
+        .chain(
+            right
+                .data
+                .keys()
+                .filter(|key| !left.data.contains_key(*key)),
+        )
+        .map(|field| FieldComparison {
+            field,
+            left: left.data.get(field).map(Template::as_str),
+            right: right.data.get(field).map(Template::as_str),
+        })
+        .collect()
+}
+
 impl Persistable for Profile {
     type Persisted = ProfileId;
 
@@ -280,3 +854,188 @@ impl PartialEq<Profile> for ProfileId {
         self == &other.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_util::assert_matches,
+        tui::{
+            test_util::{harness, TestHarness},
+            view::test_util::TestComponent,
+        },
+    };
+    use crossterm::event::KeyCode;
+    use indexmap::indexmap;
+    use rstest::rstest;
+
+    /// Fields should line up, including fields missing from either side
+    #[test]
+    fn test_compare_profiles() {
+        let left = Profile {
+            id: "dev".into(),
+            name: None,
+            base_url: None,
+            proxy: None,
+            no_proxy: None,
+            dotenv: None,
+            sensitive: Vec::new(),
+            data: indexmap! {
+                "host".into() => "localhost:3000".parse().unwrap(),
+                "user".into() => "admin".parse().unwrap(),
+                "debug".into() => "true".parse().unwrap(),
+            },
+        };
+        let right = Profile {
+            id: "prod".into(),
+            name: None,
+            base_url: None,
+            proxy: None,
+            no_proxy: None,
+            dotenv: None,
+            sensitive: Vec::new(),
+            data: indexmap! {
+                "user".into() => "admin".parse().unwrap(),
+                "host".into() => "example.com".parse().unwrap(),
+                "api_key".into() => "{{chains.key}}".parse().unwrap(),
+            },
+        };
+
+        let comparisons = compare_profiles(&left, &right);
+        assert_eq!(
+            comparisons,
+            vec![
+                FieldComparison {
+                    field: "host",
+                    left: Some("localhost:3000"),
+                    right: Some("example.com"),
+                },
+                FieldComparison {
+                    field: "user",
+                    left: Some("admin"),
+                    right: Some("admin"),
+                },
+                FieldComparison {
+                    field: "debug",
+                    left: Some("true"),
+                    right: None,
+                },
+                FieldComparison {
+                    field: "api_key",
+                    left: None,
+                    right: Some("{{chains.key}}"),
+                },
+            ]
+        );
+        assert_eq!(
+            comparisons
+                .iter()
+                .map(FieldComparison::is_different)
+                .collect_vec(),
+            vec![true, false, true, true]
+        );
+    }
+
+    /// Pop the next message that isn't a template preview. Previews are
+    /// rendered for every row, so they clog up the queue
+    fn pop_override_message(harness: &mut TestHarness) -> Message {
+        loop {
+            match harness.pop_message_now() {
+                Message::TemplatePreview { .. } => {}
+                message => break message,
+            }
+        }
+    }
+
+    /// Edit a field, reset it, then add a new field. Each change is sent to
+    /// the controller immediately, and the modal stays open
+    #[rstest]
+    fn test_override_editor(harness: TestHarness) {
+        let profile = Profile {
+            id: "dev".into(),
+            name: None,
+            base_url: None,
+            proxy: None,
+            no_proxy: None,
+            dotenv: None,
+            sensitive: Vec::new(),
+            data: indexmap! {"host".into() => "localhost".parse().unwrap()},
+        };
+        let mut component = TestComponent::new(
+            harness,
+            (profile, ProfileOverrides::default()).into_modal(),
+            (),
+        );
+        let fields = |component: &TestComponent<ProfileOverrideModal, ()>| {
+            component
+                .data()
+                .select
+                .data()
+                .items()
+                .iter()
+                .filter_map(|row| match row {
+                    OverrideRow::Field {
+                        field,
+                        template,
+                        overridden,
+                        ..
+                    } => {
+                        Some((field.clone(), template.to_string(), *overridden))
+                    }
+                    OverrideRow::New => None,
+                })
+                .collect_vec()
+        };
+
+        // Edit the existing value
+        component.send_key(KeyCode::Enter).assert_empty();
+        assert_eq!(component.data().text_box.data().text(), "localhost");
+        for _ in 0.."localhost".len() {
+            component.send_key(KeyCode::Backspace).assert_empty();
+        }
+        component.send_text("remote").assert_empty();
+        component.send_key(KeyCode::Enter).assert_empty();
+        let (field, value) = assert_matches!(
+            pop_override_message(component.harness_mut()),
+            Message::ProfileOverride { field, value, .. } => (field, value),
+        );
+        assert_eq!(field, "host");
+        assert_eq!(value, "remote".parse().unwrap());
+        assert_eq!(
+            fields(&component),
+            vec![("host".into(), "remote".into(), true)]
+        );
+
+        // Reset it
+        component.send_key(KeyCode::Delete).assert_empty();
+        let field = assert_matches!(
+            pop_override_message(component.harness_mut()),
+            Message::ProfileRemoveOverride { field, .. } => field,
+        );
+        assert_eq!(field, "host");
+        assert_eq!(
+            fields(&component),
+            vec![("host".into(), "localhost".into(), false)]
+        );
+
+        // Add a new field
+        component.send_key(KeyCode::Down).assert_empty();
+        component.send_key(KeyCode::Enter).assert_empty();
+        component.send_text("port").assert_empty();
+        component.send_key(KeyCode::Enter).assert_empty();
+        component.send_text("3000").assert_empty();
+        component.send_key(KeyCode::Enter).assert_empty();
+        let field = assert_matches!(
+            pop_override_message(component.harness_mut()),
+            Message::ProfileOverride { field, .. } => field,
+        );
+        assert_eq!(field, "port");
+        assert_eq!(
+            fields(&component),
+            vec![
+                ("host".into(), "localhost".into(), false),
+                ("port".into(), "3000".into(), true),
+            ]
+        );
+    }
+}
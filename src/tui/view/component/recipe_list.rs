@@ -1,10 +1,15 @@
 use crate::{
-    collection::{Recipe, RecipeId, RecipeLookupKey, RecipeNode, RecipeTree},
+    collection::{
+        ProfileId, Recipe, RecipeId, RecipeLookupKey, RecipeNode, RecipeTree,
+    },
+    http::ExchangeSummary,
     tui::{
         context::TuiContext,
         input::Action,
         view::{
-            common::{actions::ActionsModal, list::List, Pane},
+            common::{
+                actions::ActionsModal, list::List, text_box::TextBox, Pane,
+            },
             component::{primary::PrimaryPane, recipe_pane::RecipeMenuAction},
             draw::{Draw, DrawMetadata, Generate},
             event::{Event, EventHandler, Update},
@@ -13,16 +18,26 @@ use crate::{
                     impl_persistable, Persistable, Persistent, PersistentKey,
                 },
                 select::SelectState,
+                StateCell,
             },
             Component, ViewContext,
         },
     },
+    util::{format_age, format_duration, ResultExt},
 };
+use chrono::Utc;
 use derive_more::{Deref, DerefMut};
 use itertools::Itertools;
-use ratatui::Frame;
+use ratatui::{
+    layout::{Constraint, Layout},
+    text::{Line, Span},
+    Frame,
+};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::{
+    collections::{HashMap, HashSet},
+    iter,
+};
 
 /// List/tree of recipes and folders. This is mostly just a list, but with some
 /// extra logic to allow expanding/collapsing nodes. This could be made into a
@@ -45,6 +60,40 @@ pub struct RecipeListPane {
     /// Set of all folders that are collapsed
     /// Invariant: No recipes, only folders
     collapsed: Persistent<Collapsed>,
+    /// Custom order of each folder's children, keyed by folder ID (`None` for
+    /// the top level). This has already been applied to the recipe tree
+    orders: HashMap<Option<RecipeId>, Persistent<RecipeOrder>>,
+    /// Are we currently typing in the filter box?
+    filter_focused: bool,
+    /// Only show recipes whose content contains this text. Empty means no
+    /// filter
+    filter: String,
+    /// Where the user enters their filter
+    filter_text_box: Component<TextBox>,
+    /// Recipes with a request that completed while they weren't selected.
+    /// These get a badge until the user selects them
+    completed: HashSet<RecipeId>,
+    /// Most recent exchange for each recipe under the selected profile, shown
+    /// next to the recipe name. Loaded from the DB when first drawn, and
+    /// again whenever the profile changes
+    last_exchanges:
+        StateCell<Option<ProfileId>, HashMap<RecipeId, ExchangeSummary>>,
+}
+
+#[derive(Clone)]
+pub struct RecipeListPaneProps<'a> {
+    /// Show the latest exchange of each recipe under this profile
+    pub selected_profile_id: Option<&'a ProfileId>,
+}
+
+/// All callback events from the filter text box
+#[derive(Debug)]
+enum FilterCallback {
+    Focus,
+    /// The filter is applied as the user types
+    Change(String),
+    Cancel,
+    Submit,
 }
 
 /// Set of collapsed folders. This newtype is really only necessary so we can
@@ -53,6 +102,12 @@ pub struct RecipeListPane {
 #[serde(transparent)]
 struct Collapsed(HashSet<RecipeId>);
 
+/// Order of the children of a single folder, as arranged by the user. This
+/// newtype is really only necessary so we can implement [Persistable] on it
+#[derive(Debug, Default, Deref, DerefMut, Serialize, Deserialize)]
+#[serde(transparent)]
+struct RecipeOrder(Vec<RecipeId>);
+
 /// Ternary state for modifying node collapse state
 enum CollapseState {
     Expand,
@@ -63,22 +118,122 @@ enum CollapseState {
 impl RecipeListPane {
     pub fn new(recipes: &RecipeTree) -> Self {
         // This clone is unfortunate, but we can't hold onto a reference to the
-        // recipes
+        // recipes. It lets us apply the user's ordering though
+        let mut recipes = recipes.clone();
+        let folder_ids = iter::once(None)
+            .chain(recipes.iter().filter_map(|(_, node)| {
+                node.folder().map(|folder| Some(folder.id.clone()))
+            }))
+            .collect_vec();
+        let orders: HashMap<_, _> = folder_ids
+            .into_iter()
+            .map(|folder_id| {
+                let order = Persistent::new(
+                    PersistentKey::RecipeOrder(folder_id.clone()),
+                    RecipeOrder::default(),
+                );
+                recipes.sort_children(folder_id.as_ref(), &order);
+                (folder_id, order)
+            })
+            .collect();
         let collapsed = Persistent::new(
             PersistentKey::RecipeCollapsed,
             Collapsed::default(),
         );
         let persistent = Persistent::new(
             PersistentKey::RecipeId,
-            build_select_state(recipes, &collapsed),
+            build_select_state(&recipes, &collapsed, ""),
         );
+        let filter_text_box = TextBox::default()
+            .with_placeholder("'/' to filter by name, URL, tag, or content")
+            .with_on_click(|_| {
+                ViewContext::push_event(Event::new_local(FilterCallback::Focus))
+            })
+            .with_on_cancel(|_| {
+                ViewContext::push_event(Event::new_local(
+                    FilterCallback::Cancel,
+                ))
+            })
+            .with_on_change(|text_box| {
+                ViewContext::push_event(Event::new_local(
+                    FilterCallback::Change(text_box.text().to_owned()),
+                ))
+            })
+            .with_on_submit(|_| {
+                ViewContext::push_event(Event::new_local(
+                    FilterCallback::Submit,
+                ))
+            });
         Self {
-            recipes: recipes.clone(),
+            recipes,
             select: persistent.into(),
             collapsed,
+            orders,
+            filter_focused: false,
+            filter: String::new(),
+            filter_text_box: filter_text_box.into(),
+            completed: HashSet::new(),
+            last_exchanges: StateCell::default(),
         }
     }
 
+    /// Mark a recipe as having a request complete in the background, so it
+    /// gets a badge in the list
+    pub fn mark_completed(&mut self, recipe_id: RecipeId) {
+        self.completed.insert(recipe_id);
+    }
+
+    /// Remove the background completion badge from a recipe, once the user
+    /// has seen it
+    pub fn clear_completed(&mut self, recipe_id: &RecipeId) {
+        self.completed.remove(recipe_id);
+    }
+
+    /// Show a newly received exchange in the list, if it's from the profile
+    /// whose exchanges are currently shown
+    pub fn record_exchange(&mut self, summary: ExchangeSummary) {
+        if let Some(last_exchanges) =
+            self.last_exchanges.get_mut_for(&summary.profile_id)
+        {
+            last_exchanges.insert(summary.recipe_id.clone(), summary);
+        }
+    }
+
+    /// Select a recipe/folder by ID, expanding its parent folders. If it's
+    /// hidden by the filter, do nothing
+    pub fn select_recipe(&mut self, recipe_id: &RecipeId) {
+        // Expand any collapsed parents, so the recipe is visible
+        if let Some(lookup_key) = self.recipes.get_lookup_key(recipe_id) {
+            let (_, parents) = lookup_key
+                .as_slice()
+                .split_last()
+                .expect("Lookup key cannot be empty");
+            let mut changed = false;
+            for parent in parents {
+                changed |= self.collapsed.remove(parent);
+            }
+            if changed {
+                self.rebuild_select_state();
+            }
+        }
+        self.select.data_mut().select(recipe_id);
+    }
+
+    /// Get every recipe and folder in the collection, regardless of what's
+    /// visible in the list
+    pub fn recipes(&self) -> &RecipeTree {
+        &self.recipes
+    }
+
+    /// Get the ID of the folder containing a recipe/folder. `None` if it's at
+    /// the top level, or not in the tree
+    pub fn parent_id(&self, recipe_id: &RecipeId) -> Option<&RecipeId> {
+        // Lookup key is [...parents, id]
+        self.recipes
+            .get_lookup_key(recipe_id)
+            .and_then(|lookup_key| lookup_key.as_slice().iter().nth_back(1))
+    }
+
     /// Which recipe/folder in the list is selected? `None` iff the list is
     /// empty
     pub fn selected_node(&self) -> Option<&RecipeNode> {
@@ -117,21 +272,89 @@ impl RecipeListPane {
 
         // If we changed the set of what is visible, rebuild the list state
         if changed {
-            let mut new_select_state =
-                build_select_state(&self.recipes, &self.collapsed);
-            // Carry over the selection
-            if let Some(selected) = select.selected() {
-                new_select_state.select(selected.id());
-            }
-            **select = new_select_state;
+            self.rebuild_select_state();
         }
 
         changed
     }
+
+    /// Move the selected recipe/folder up or down one spot among its siblings.
+    /// Folders move along with all their children. Returns whether a change
+    /// was made.
+    fn move_selected(&mut self, up: bool) -> bool {
+        let Some(id) = self.selected_node().map(RecipeNode::id).cloned() else {
+            return false;
+        };
+        let parent = self.parent_id(&id).cloned();
+        let Some(mut siblings) = self.recipes.child_ids(parent.as_ref()) else {
+            return false;
+        };
+
+        let Some(index) = siblings.iter().position(|sibling| sibling == &id)
+        else {
+            return false;
+        };
+        let new_index = if up {
+            index.checked_sub(1)
+        } else {
+            Some(index + 1).filter(|index| *index < siblings.len())
+        };
+        let Some(new_index) = new_index else {
+            return false;
+        };
+        siblings.swap(index, new_index);
+
+        self.recipes.sort_children(parent.as_ref(), &siblings);
+        if let Some(order) = self.orders.get_mut(&parent) {
+            **order = RecipeOrder(siblings);
+        }
+        self.rebuild_select_state();
+        true
+    }
+
+    /// Rebuild the list of visible items, after a change to the collapsed
+    /// folders or filter. The current selection is carried over if it's still
+    /// visible. Otherwise, the recipe that best matches the filter is selected
+    fn rebuild_select_state(&mut self) {
+        let select = self.select.data_mut();
+        let mut new_select_state =
+            build_select_state(&self.recipes, &self.collapsed, &self.filter);
+        // Carry over the selection
+        let selected = select
+            .selected()
+            .map(RecipeNode::id)
+            .filter(|id| {
+                new_select_state.items().iter().any(|node| node.id() == *id)
+            })
+            .or_else(|| best_match(&self.recipes, &self.filter));
+        if let Some(selected) = selected {
+            new_select_state.select(selected);
+        }
+        **select = new_select_state;
+    }
 }
 
 impl EventHandler for RecipeListPane {
     fn update(&mut self, event: Event) -> Update {
+        if let Some(callback) = event.local::<FilterCallback>() {
+            match callback {
+                FilterCallback::Focus => self.filter_focused = true,
+                FilterCallback::Change(text) => {
+                    self.filter = text.trim().to_owned();
+                    self.rebuild_select_state();
+                }
+                // Cancelling clears the filter entirely
+                FilterCallback::Cancel => {
+                    self.filter_text_box.data_mut().set_text(String::new());
+                    self.filter.clear();
+                    self.filter_focused = false;
+                    self.rebuild_select_state();
+                }
+                FilterCallback::Submit => self.filter_focused = false,
+            }
+            return Update::Consumed;
+        }
+
         let Some(action) = event.action() else {
             return Update::Propagate(event);
         };
@@ -154,6 +377,13 @@ impl EventHandler for RecipeListPane {
             Action::OpenActions => ViewContext::open_modal_default::<
                 ActionsModal<RecipeMenuAction>,
             >(),
+            Action::MoveUp => {
+                self.move_selected(true);
+            }
+            Action::MoveDown => {
+                self.move_selected(false);
+            }
+            Action::Search => self.filter_focused = true,
             _ => return Update::Propagate(event),
         }
 
@@ -161,12 +391,17 @@ impl EventHandler for RecipeListPane {
     }
 
     fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
-        vec![self.select.as_child()]
+        vec![self.filter_text_box.as_child(), self.select.as_child()]
     }
 }
 
-impl Draw for RecipeListPane {
-    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+impl<'a> Draw<RecipeListPaneProps<'a>> for RecipeListPane {
+    fn draw(
+        &self,
+        frame: &mut Frame,
+        props: RecipeListPaneProps<'a>,
+        metadata: DrawMetadata,
+    ) {
         let select = self.select.data();
         let context = TuiContext::get();
 
@@ -180,6 +415,27 @@ impl Draw for RecipeListPane {
         .generate();
         let area = block.inner(metadata.area());
         frame.render_widget(block, metadata.area());
+        // Only show the filter box while it's in use
+        let show_filter = self.filter_focused || !self.filter.is_empty();
+        let [list_area, filter_area] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(if show_filter { 1 } else { 0 }),
+        ])
+        .areas(area);
+
+        let last_exchanges = self.last_exchanges.get_or_update(
+            props.selected_profile_id.cloned(),
+            || {
+                ViewContext::with_database(|database| {
+                    database.get_latest_summaries(props.selected_profile_id)
+                })
+                .reported(&ViewContext::messages_tx())
+                .unwrap_or_default()
+                .into_iter()
+                .map(|summary| (summary.recipe_id.clone(), summary))
+                .collect()
+            },
+        );
 
         // We have to build this manually instead of using our own List type,
         // because we need outside context during the render
@@ -194,9 +450,22 @@ impl Draw for RecipeListPane {
                         } else {
                             "▼"
                         };
-                        (icon, folder.name())
+                        // Include nested recipes in the count
+                        let name = format!(
+                            "{} ({})",
+                            folder.name(),
+                            folder.recipes().len()
+                        );
+                        (icon, name)
                     }
-                    RecipeNode::Recipe(recipe) => ("", recipe.name()),
+                    RecipeNode::Recipe(recipe) => {
+                        ("", recipe.name().to_owned())
+                    }
+                };
+                let badge = if self.completed.contains(node.id()) {
+                    " ●"
+                } else {
+                    ""
                 };
                 let depth = self
                     .recipes
@@ -209,15 +478,86 @@ impl Draw for RecipeListPane {
                     - 1;
 
                 // Apply indentation
-                format!(
-                    "{indent:width$}{icon}{name}",
+                let label = format!(
+                    "{indent:width$}{icon}{name}{badge}",
                     indent = "",
                     width = depth
-                )
+                );
+                RecipeListRow {
+                    label,
+                    last_exchange: last_exchanges.get(node.id()),
+                    width: list_area.width,
+                }
             })
             .collect_vec();
 
-        self.select.draw(frame, List::new(items), area, true);
+        self.select.draw(frame, List::new(items), list_area, true);
+        if show_filter {
+            self.filter_text_box.draw(
+                frame,
+                (),
+                filter_area,
+                self.filter_focused,
+            );
+        }
+    }
+}
+
+/// A recipe or folder in the list, with stats from the latest exchange
+/// right-aligned after the name
+struct RecipeListRow<'a> {
+    label: String,
+    last_exchange: Option<&'a ExchangeSummary>,
+    /// Width of the list, so stats can be right-aligned
+    width: u16,
+}
+
+impl RecipeListRow<'_> {
+    /// Width of the stats columns: status, duration, and age
+    const STATS_WIDTH: usize = 3 + 1 + 7 + 1 + 3;
+    /// Don't squeeze the label smaller than this to fit stats
+    const MIN_LABEL_WIDTH: usize = 10;
+}
+
+impl<'a> Generate for RecipeListRow<'a> {
+    type Output<'this>
+        = Line<'this>
+    where
+        Self: 'this;
+
+    fn generate<'this>(self) -> Self::Output<'this>
+    where
+        Self: 'this,
+    {
+        // Leave a column for the scrollbar, and one between label and stats
+        let label_width =
+            (self.width as usize).saturating_sub(Self::STATS_WIDTH + 2);
+        let Some(exchange) = self
+            .last_exchange
+            .filter(|_| label_width >= Self::MIN_LABEL_WIDTH)
+        else {
+            return self.label.into();
+        };
+
+        let label = if self.label.chars().count() > label_width {
+            let truncated: String =
+                self.label.chars().take(label_width - 1).collect();
+            format!("{truncated}…")
+        } else {
+            self.label
+        };
+        let styles = &TuiContext::get().styles;
+        let age = format_age(&(Utc::now() - exchange.start_time));
+        vec![
+            format!("{label:<label_width$} ").into(),
+            Span::styled(
+                exchange.status.as_str().to_owned(),
+                styles.status_code.get(exchange.status),
+            ),
+            format!(" {:>7} {age:>3}", format_duration(&exchange.duration()))
+                .into(),
+        ]
+        .into()
     }
 }
 
@@ -260,10 +600,18 @@ impl PartialEq<RecipeNode> for RecipeId {
 // the user ever adds the folder back. Not worth working around.
 impl_persistable!(Collapsed);
 
-/// Construct select list based on which nodes are currently visible
+// Persistence for the order of a folder's children. Recipes that were added
+// since the order was saved go at the end of the folder, and removed ones are
+// ignored.
+impl_persistable!(RecipeOrder);
+
+/// Construct select list based on which nodes are currently visible. If a
+/// filter is given, only recipes matching it (and their parent folders) are
+/// visible, regardless of which folders are collapsed.
 fn build_select_state(
     recipes: &RecipeTree,
     collapsed: &Collapsed,
+    filter: &str,
 ) -> SelectState<RecipeNode> {
     // When highlighting a new recipe, load it from the repo
     fn on_select(_: &mut RecipeNode) {
@@ -271,11 +619,469 @@ fn build_select_state(
         ViewContext::push_event(Event::HttpSelectRequest(None));
     }
 
-    let items = recipes
-        .iter()
-        // Filter out hidden nodes
-        .filter(|(lookup_key, _)| collapsed.is_visible(lookup_key))
-        .map(|(_, node)| node.clone())
-        .collect();
+    let items = if filter.is_empty() {
+        recipes
+            .iter()
+            // Filter out hidden nodes
+            .filter(|(lookup_key, _)| collapsed.is_visible(lookup_key))
+            .map(|(_, node)| node.clone())
+            .collect()
+    } else {
+        // Include every matching recipe, plus all its ancestors
+        let visible: HashSet<RecipeId> = recipes
+            .iter()
+            .filter(|(_, node)| {
+                node.recipe()
+                    .is_some_and(|recipe| recipe_matches(recipe, filter))
+            })
+            .flat_map(|(lookup_key, _)| lookup_key.as_slice().to_vec())
+            .collect();
+        recipes
+            .iter()
+            .filter(|(_, node)| visible.contains(node.id()))
+            .map(|(_, node)| node.clone())
+            .collect()
+    };
     SelectState::builder(items).on_select(on_select).build()
 }
+
+/// Does a recipe match the filter? Name, ID, URL, and tags are fuzzy matched.
+/// Headers and body have to contain the filter exactly, because fuzzy matching
+/// that much text would match almost anything
+fn recipe_matches(recipe: &Recipe, filter: &str) -> bool {
+    recipe.fuzzy_score(filter).is_some() || recipe.matches(filter)
+}
+
+/// Get the recipe that best fuzzy-matches the filter, if any
+fn best_match<'a>(
+    recipes: &'a RecipeTree,
+    filter: &str,
+) -> Option<&'a RecipeId> {
+    if filter.is_empty() {
+        return None;
+    }
+    recipes
+        .iter()
+        .filter_map(|(_, node)| {
+            let recipe = node.recipe()?;
+            Some((recipe.fuzzy_score(filter)?, &recipe.id))
+        })
+        // min_by_key keeps the first of equal elements, so the earliest
+        // recipe wins ties
+        .min_by_key(|(score, _)| -score)
+        .map(|(_, id)| id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collection::Folder,
+        http::{Exchange, ResponseRecord},
+        test_util::Factory,
+        tui::{
+            test_util::{harness, TestHarness},
+            view::test_util::TestComponent,
+        },
+    };
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use indexmap::indexmap;
+    use reqwest::StatusCode;
+    use rstest::rstest;
+
+    fn props() -> RecipeListPaneProps<'static> {
+        RecipeListPaneProps {
+            selected_profile_id: None,
+        }
+    }
+
+    /// Filtering should show matching recipes, plus the folders that contain
+    /// them
+    #[rstest]
+    fn test_filter(harness: TestHarness) {
+        let recipe = |id: &str, url: &str| -> RecipeNode {
+            Recipe {
+                id: id.into(),
+                url: url.parse().unwrap(),
+                ..Recipe::factory(())
+            }
+            .into()
+        };
+        let recipes = RecipeTree::new(indexmap! {
+            "r1".into() => recipe("r1", "{{host}}/v1/login"),
+            "f1".into() => Folder {
+                id: "f1".into(),
+                children: indexmap! {
+                    "r2".into() => recipe("r2", "{{host}}/v2/users"),
+                    "r3".into() => recipe("r3", "{{host}}/v1/users"),
+                },
+                ..Folder::factory(())
+            }
+            .into(),
+            "f2".into() => Folder {
+                id: "f2".into(),
+                children: indexmap! {
+                    "r4".into() => recipe("r4", "{{host}}/v2/health"),
+                },
+                ..Folder::factory(())
+            }
+            .into(),
+        })
+        .unwrap();
+        let mut component =
+            TestComponent::new(harness, RecipeListPane::new(&recipes), props());
+        let visible_ids =
+            |component: &TestComponent<RecipeListPane, RecipeListPaneProps>| {
+                component
+                    .data()
+                    .select
+                    .data()
+                    .items()
+                    .iter()
+                    .map(|node| node.id().to_string())
+                    .collect_vec()
+            };
+        assert_eq!(
+            visible_ids(&component),
+            ["r1", "f1", "r2", "r3", "f2", "r4"]
+        );
+
+        component.send_key(KeyCode::Char('/')).assert_empty();
+        // The filter applies as we type. Rebuilding the list triggers an event
+        // to reload the selected recipe's request, which the parent would
+        // handle
+        let _ = component.send_text("V1");
+        assert_eq!(visible_ids(&component), ["r1", "f1", "r3"]);
+        component.send_key(KeyCode::Enter).assert_empty();
+        assert_eq!(visible_ids(&component), ["r1", "f1", "r3"]);
+
+        // Clearing the filter shows everything again
+        component.send_key(KeyCode::Char('/')).assert_empty();
+        for _ in 0..2 {
+            let _ = component.send_key(KeyCode::Backspace);
+        }
+        component.send_key(KeyCode::Enter).assert_empty();
+        assert_eq!(
+            visible_ids(&component),
+            ["r1", "f1", "r2", "r3", "f2", "r4"]
+        );
+    }
+
+    /// Name and tags are fuzzy matched, and the best match is selected.
+    /// Cancelling clears the filter
+    #[rstest]
+    fn test_filter_fuzzy(harness: TestHarness) {
+        let recipe = |id: &str, name: &str, tags: &[&str]| -> RecipeNode {
+            Recipe {
+                id: id.into(),
+                name: Some(name.into()),
+                tags: tags.iter().map(|tag| (*tag).to_owned()).collect(),
+                ..Recipe::factory(())
+            }
+            .into()
+        };
+        let recipes = RecipeTree::new(indexmap! {
+            "r1".into() => recipe("r1", "List Users", &[]),
+            "f1".into() => Folder {
+                id: "f1".into(),
+                children: indexmap! {
+                    "r2".into() => recipe("r2", "Sign In", &["auth"]),
+                    "r3".into() => recipe("r3", "Sign Out", &["auth"]),
+                },
+                ..Folder::factory(())
+            }
+            .into(),
+        })
+        .unwrap();
+        let mut component =
+            TestComponent::new(harness, RecipeListPane::new(&recipes), props());
+        let visible_ids =
+            |component: &TestComponent<RecipeListPane, RecipeListPaneProps>| {
+                component
+                    .data()
+                    .select
+                    .data()
+                    .items()
+                    .iter()
+                    .map(|node| node.id().to_string())
+                    .collect_vec()
+            };
+
+        component.send_key(KeyCode::Char('/')).assert_empty();
+        let _ = component.send_text("sgnout");
+        assert_eq!(visible_ids(&component), ["f1", "r3"]);
+        assert_eq!(
+            component.data().selected_node().map(RecipeNode::id),
+            Some(&"r3".into())
+        );
+
+        // Tags
+        for _ in 0.."sgnout".len() {
+            let _ = component.send_key(KeyCode::Backspace);
+        }
+        let _ = component.send_text("ath");
+        assert_eq!(visible_ids(&component), ["f1", "r2", "r3"]);
+
+        let _ = component.send_key(KeyCode::Esc);
+        assert_eq!(visible_ids(&component), ["r1", "f1", "r2", "r3"]);
+        assert_eq!(component.data().filter_text_box.data().text(), "");
+    }
+
+    /// Clicking a row selects it, and focuses the pane
+    #[rstest]
+    fn test_click(harness: TestHarness) {
+        let recipes = RecipeTree::new(indexmap! {
+            "r1".into() => Recipe {
+                id: "r1".into(),
+                ..Recipe::factory(())
+            }
+            .into(),
+            "r2".into() => Recipe {
+                id: "r2".into(),
+                ..Recipe::factory(())
+            }
+            .into(),
+        })
+        .unwrap();
+        let mut component =
+            TestComponent::new(harness, RecipeListPane::new(&recipes), props());
+        assert_eq!(
+            component.data().selected_node().map(RecipeNode::id),
+            Some(&"r1".into())
+        );
+        // Account for the pane border
+        let _ = component.click(2, 2);
+        assert_eq!(
+            component.data().selected_node().map(RecipeNode::id),
+            Some(&"r2".into())
+        );
+        // Clicking below the last item does nothing
+        let _ = component.click(2, 5);
+        assert_eq!(
+            component.data().selected_node().map(RecipeNode::id),
+            Some(&"r2".into())
+        );
+    }
+
+    /// Each recipe shows stats from its latest exchange under the selected
+    /// profile
+    #[rstest]
+    fn test_last_exchange(harness: TestHarness) {
+        let recipes = RecipeTree::new(indexmap! {
+            "r1".into() => Recipe {
+                id: "r1".into(),
+                ..Recipe::factory(())
+            }
+            .into(),
+            "r2".into() => Recipe {
+                id: "r2".into(),
+                ..Recipe::factory(())
+            }
+            .into(),
+        })
+        .unwrap();
+        // Only the r1 exchange is for the selected (empty) profile
+        let exchange = Exchange::factory((None, "r1".into()));
+        harness.database.insert_exchange(&exchange).unwrap();
+        let exchange =
+            Exchange::factory((Some("profile1".into()), "r2".into()));
+        harness.database.insert_exchange(&exchange).unwrap();
+
+        let mut component =
+            TestComponent::new(harness, RecipeListPane::new(&recipes), props());
+        let row = |component: &mut TestComponent<_, _>, y: u16| {
+            let buffer = component.harness_mut().terminal.backend().buffer();
+            (1..39)
+                .map(|x| buffer.get(x, y).symbol())
+                .collect::<String>()
+                .trim_end()
+                .to_owned()
+        };
+        assert!(row(&mut component, 1)
+            .starts_with("r1                    200     0ms"));
+        assert_eq!(row(&mut component, 2), "r2");
+
+        // New exchanges show up without going back to the DB
+        let exchange = Exchange {
+            response: ResponseRecord {
+                status: StatusCode::NOT_FOUND,
+                ..ResponseRecord::factory(())
+            }
+            .into(),
+            ..Exchange::factory((None, "r2".into()))
+        };
+        component
+            .data_mut()
+            .record_exchange(ExchangeSummary::from(&exchange));
+        let _ = component.send_key(KeyCode::Down);
+        assert!(row(&mut component, 2)
+            .starts_with("r2                    404     0ms"));
+    }
+
+    /// Folders show how many recipes they contain, including nested ones
+    #[rstest]
+    fn test_folder_counts(harness: TestHarness) {
+        let recipe = |id: &str| -> RecipeNode {
+            Recipe {
+                id: id.into(),
+                ..Recipe::factory(())
+            }
+            .into()
+        };
+        let recipes = RecipeTree::new(indexmap! {
+            "f1".into() => Folder {
+                id: "f1".into(),
+                children: indexmap! {
+                    "r1".into() => recipe("r1"),
+                    "f2".into() => Folder {
+                        id: "f2".into(),
+                        name: Some("Folder 2".into()),
+                        children: indexmap! {
+                            "r2".into() => recipe("r2"),
+                            "r3".into() => recipe("r3"),
+                        },
+                        ..Folder::factory(())
+                    }
+                    .into(),
+                    "f3".into() => Folder {
+                        id: "f3".into(),
+                        ..Folder::factory(())
+                    }
+                    .into(),
+                },
+                ..Folder::factory(())
+            }
+            .into(),
+        })
+        .unwrap();
+        let mut component =
+            TestComponent::new(harness, RecipeListPane::new(&recipes), props());
+        let buffer = component.harness_mut().terminal.backend().buffer();
+        let rows = (1..7)
+            .map(|y| {
+                (1..39)
+                    .map(|x| buffer.get(x, y).symbol())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_owned()
+            })
+            .collect_vec();
+        assert_eq!(
+            rows,
+            [
+                "▼f1 (3)",
+                " r1",
+                " ▼Folder 2 (2)",
+                "  r2",
+                "  r3",
+                " ▼f3 (0)"
+            ]
+        );
+    }
+
+    /// Recipes and folders can be moved within their folder, and the order is
+    /// persisted between sessions
+    #[rstest]
+    fn test_move(harness: TestHarness) {
+        let recipe = |id: &str| -> RecipeNode {
+            Recipe {
+                id: id.into(),
+                ..Recipe::factory(())
+            }
+            .into()
+        };
+        let recipes = RecipeTree::new(indexmap! {
+            "r1".into() => recipe("r1"),
+            "f1".into() => Folder {
+                id: "f1".into(),
+                children: indexmap! {
+                    "r2".into() => recipe("r2"),
+                    "r3".into() => recipe("r3"),
+                },
+                ..Folder::factory(())
+            }
+            .into(),
+            "r4".into() => recipe("r4"),
+        })
+        .unwrap();
+        let mut component =
+            TestComponent::new(harness, RecipeListPane::new(&recipes), props());
+        let visible_ids = |pane: &RecipeListPane| {
+            pane.select
+                .data()
+                .items()
+                .iter()
+                .map(|node| node.id().to_string())
+                .collect_vec()
+        };
+        let shift = |code| {
+            crossterm::event::Event::Key(KeyEvent::new(
+                code,
+                KeyModifiers::SHIFT,
+            ))
+        };
+
+        // Recipes only move among their siblings
+        for _ in 0..3 {
+            let _ = component.send_key(KeyCode::Down);
+        }
+        let _ = component.send_input(shift(KeyCode::Up));
+        assert_eq!(
+            visible_ids(component.data()),
+            ["r1", "f1", "r3", "r2", "r4"]
+        );
+        let _ = component.send_input(shift(KeyCode::Up));
+        assert_eq!(
+            visible_ids(component.data()),
+            ["r1", "f1", "r3", "r2", "r4"]
+        );
+
+        // Folders bring their children along
+        let _ = component.send_key(KeyCode::Up);
+        let _ = component.send_input(shift(KeyCode::Down));
+        assert_eq!(
+            visible_ids(component.data()),
+            ["r1", "r4", "f1", "r3", "r2"]
+        );
+        assert_eq!(
+            component.data().selected_node().map(RecipeNode::id),
+            Some(&"f1".into())
+        );
+
+        // Order is loaded in the next session
+        drop(component);
+        let pane = RecipeListPane::new(&recipes);
+        assert_eq!(visible_ids(&pane), ["r1", "r4", "f1", "r3", "r2"]);
+    }
+
+    /// Selecting a recipe in a collapsed folder expands the folder
+    #[rstest]
+    fn test_select_recipe_expand(_harness: TestHarness) {
+        let recipes = RecipeTree::new(indexmap! {
+            "f1".into() => Folder {
+                id: "f1".into(),
+                children: indexmap! {
+                    "r1".into() => Recipe {
+                        id: "r1".into(),
+                        ..Recipe::factory(())
+                    }
+                    .into(),
+                },
+                ..Folder::factory(())
+            }
+            .into(),
+        })
+        .unwrap();
+        let mut pane = RecipeListPane::new(&recipes);
+        pane.select_recipe(&"f1".into());
+        assert!(pane.set_selected_collapsed(CollapseState::Collapse));
+        assert_eq!(pane.select.data().items().len(), 1);
+
+        pane.select_recipe(&"r1".into());
+        assert_eq!(pane.select.data().items().len(), 2);
+        assert_eq!(
+            pane.selected_node().map(RecipeNode::id),
+            Some(&"r1".into())
+        );
+    }
+}
@@ -1,41 +1,55 @@
 use crate::{
-    collection::{Authentication, ProfileId, Recipe, RecipeId},
-    http::BuildOptions,
+    collection::{
+        Authentication, MultipartField, OAuth2GrantType, ProfileId, Recipe,
+        RecipeBody, RecipeId, RecipeKind,
+    },
+    http::{BuildOptions, OAuth2Token},
+    template::Template,
     tui::{
         context::TuiContext,
         input::Action,
+        message::{Message, RequestConfig},
         view::{
             common::{
                 actions::ActionsModal,
                 table::{Table, ToggleRow},
                 tabs::Tabs,
                 template_preview::TemplatePreview,
-                text_window::{TextWindow, TextWindowProps},
+                text_window::{TextWindow, TextWindowProps, TextWindowToggles},
                 Pane,
             },
-            component::primary::PrimaryPane,
+            component::{
+                primary::PrimaryPane, request_preview::RequestPreview,
+            },
             draw::{Draw, DrawMetadata, Generate, ToStringGenerate},
             event::{Event, EventHandler, Update},
             state::{
                 fixed_select::FixedSelect,
-                persistence::{Persistable, Persistent, PersistentKey},
+                persistence::{
+                    BodyView, Persistable, Persistent, PersistentKey,
+                },
                 select::SelectState,
                 StateCell,
             },
             Component, ViewContext,
         },
     },
+    util::ResultExt,
 };
 use derive_more::Display;
 use itertools::Itertools;
 use ratatui::{
-    layout::Layout,
+    layout::{Layout, Rect},
     prelude::Constraint,
+    text::{Line, Text},
     widgets::{Paragraph, Row, TableState},
     Frame,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::{
+    collections::HashSet,
+    sync::{Arc, OnceLock},
+};
 use strum::{EnumCount, EnumIter};
 
 /// Display a request recipe
@@ -45,6 +59,16 @@ pub struct RecipePane {
     /// All UI state derived from the recipe is stored together, and reset when
     /// the recipe or profile changes
     recipe_state: StateCell<RecipeStateKey, RecipeState>,
+    /// User's note for the recipe, loaded from the DB. This doesn't depend on
+    /// the profile, so it's stored separately from the rest of the state.
+    /// `None` if the recipe has no note
+    note: StateCell<RecipeId, Option<Component<TextWindow<String>>>>,
+    /// The fully built request. This is only built when the Preview tab is
+    /// shown, because building can have side effects (e.g. chains)
+    preview: StateCell<PreviewKey, Component<RequestPreview>>,
+    /// Wrap/line number settings for the body. These apply to every recipe,
+    /// so they're stored outside the recipe state
+    body_toggles: Persistent<TextWindowToggles>,
 }
 
 impl Default for RecipePane {
@@ -52,6 +76,12 @@ impl Default for RecipePane {
         Self {
             tabs: Tabs::new(PersistentKey::RecipeTab).into(),
             recipe_state: Default::default(),
+            note: Default::default(),
+            preview: Default::default(),
+            body_toggles: Persistent::new(
+                PersistentKey::BodyToggles(BodyView::Recipe),
+                Default::default(),
+            ),
         }
     }
 }
@@ -68,12 +98,22 @@ struct RecipeStateKey {
     recipe_id: RecipeId,
 }
 
+/// The request preview will be rebuilt when any of these fields change
+#[derive(Debug, PartialEq)]
+struct PreviewKey {
+    recipe: RecipeStateKey,
+    disabled_headers: HashSet<String>,
+    disabled_query_parameters: HashSet<String>,
+}
+
 #[derive(Debug)]
 struct RecipeState {
+    /// Profile the previews are rendered with
+    profile_id: Option<ProfileId>,
     url: TemplatePreview,
     query: Component<Persistent<SelectState<RowState, TableState>>>,
     headers: Component<Persistent<SelectState<RowState, TableState>>>,
-    body: Option<Component<TextWindow<TemplatePreview>>>,
+    body: Option<BodyDisplay>,
     authentication: Option<Component<AuthenticationDisplay>>,
 }
 
@@ -95,6 +135,8 @@ enum Tab {
     Query,
     Headers,
     Authentication,
+    Preview,
+    Notes,
 }
 impl FixedSelect for Tab {}
 
@@ -117,11 +159,121 @@ pub enum RecipeMenuAction {
     CopyBody,
     #[display("Copy as cURL")]
     CopyCurl,
+    /// Open the body in the user's editor, then send the edited request
+    #[display("Edit Body & Send")]
+    EditBody,
+    /// Send every recipe in the selected folder, one at a time
+    #[display("Run Folder")]
+    RunFolder,
+    /// Re-send the request on an interval, until toggled off
+    #[display("Toggle Watch")]
+    ToggleWatch,
+    /// Send the request after a delay or at a specific time
+    #[display("Schedule Send")]
+    ScheduleSend,
+    /// Send the request once per profile and compare the results
+    #[display("Send to All Profiles")]
+    SendAllProfiles,
+    /// Show where each templated value in the request comes from
+    #[display("Explain Request")]
+    ExplainRequest,
+    /// Run the introspection query for a GraphQL recipe, and store the schema
+    #[display("Fetch GraphQL Schema")]
+    FetchGraphqlSchema,
+    /// Edit a GraphQL query in place, with completion from the stored schema
+    #[display("Edit GraphQL Query")]
+    EditGraphqlQuery,
+    /// Open the recipe's note in the user's editor
+    #[display("Edit Note")]
+    EditNote,
+    #[display("Duplicate Recipe")]
+    DuplicateRecipe,
+    /// Change the ID of the selected recipe or folder
+    #[display("Rename Recipe")]
+    RenameRecipe,
+    /// Move the selected recipe or folder into another folder
+    #[display("Move to Folder")]
+    MoveToFolder,
+    #[display("Delete Recipe")]
+    DeleteRecipe,
+    /// Create a folder next to the selected recipe, or inside the selected
+    /// folder
+    #[display("New Folder")]
+    NewFolder,
+    /// Write the scratch request to the collection file
+    #[display("Save as Recipe")]
+    SaveAsRecipe,
 }
 
 impl ToStringGenerate for RecipeMenuAction {}
 
 impl RecipePane {
+    /// Replace the displayed note for a recipe, after it's been edited. If a
+    /// different recipe is displayed, do nothing; the note will be loaded from
+    /// the DB when that recipe is selected.
+    pub fn set_note(&mut self, recipe_id: &RecipeId, note: Option<String>) {
+        if let Some(state) = self.note.get_mut_for(recipe_id) {
+            *state = note.map(|note| TextWindow::new(note).into());
+        }
+    }
+
+    /// Rebuild all template previews, e.g. because the profile values they
+    /// depend on have changed. Toggled rows are persisted, so they survive
+    pub fn refresh_previews(&mut self) {
+        self.recipe_state.reset();
+        self.preview.reset();
+    }
+
+    /// Open the chain inspector for the chains referenced by the focused
+    /// template: the selected query parameter or header, the body, or the
+    /// authentication, depending on the tab. Chains in the URL are always
+    /// included, after those.
+    fn inspect_chains(&self) {
+        let Some(state) = self.recipe_state.get() else {
+            return;
+        };
+        let focused = match self.tabs.data().selected() {
+            Tab::Body => state
+                .body
+                .as_ref()
+                .map(BodyDisplay::templates)
+                .unwrap_or_default(),
+            Tab::Query => state
+                .query
+                .data()
+                .selected()
+                .map(|row| vec![row.value.template()])
+                .unwrap_or_default(),
+            Tab::Headers => state
+                .headers
+                .data()
+                .selected()
+                .map(|row| vec![row.value.template()])
+                .unwrap_or_default(),
+            Tab::Authentication => state
+                .authentication
+                .as_ref()
+                .map(|authentication| authentication.data().templates())
+                .unwrap_or_default(),
+            Tab::Preview | Tab::Notes => vec![],
+        };
+        let chain_ids = focused
+            .into_iter()
+            .chain([state.url.template()])
+            .flat_map(Template::chain_ids)
+            .unique()
+            .collect_vec();
+        let message = if chain_ids.is_empty() {
+            Message::Notify("No chains referenced here".into())
+        } else {
+            Message::ChainInspect {
+                chain_ids: Some(chain_ids),
+                profile_id: state.profile_id.clone(),
+            }
+        };
+        ViewContext::send_message(message);
+    }
+
     /// Generate a [BuildOptions] instance based on current UI state
     pub fn build_options(&self) -> BuildOptions {
         if let Some(state) = self.recipe_state.get() {
@@ -140,6 +292,7 @@ impl RecipePane {
             BuildOptions {
                 disabled_headers: to_disabled_set(state.headers.data()),
                 disabled_query_parameters: to_disabled_set(state.query.data()),
+                ..Default::default()
             }
         } else {
             // Shouldn't be possible, because state is initialized on first
@@ -161,6 +314,16 @@ impl EventHandler for RecipePane {
                 Action::OpenActions => ViewContext::open_modal_default::<
                     ActionsModal<RecipeMenuAction>,
                 >(),
+                Action::EditBody => ViewContext::push_event(Event::new_local(
+                    RecipeMenuAction::EditBody,
+                )),
+                Action::EditNote => ViewContext::push_event(Event::new_local(
+                    RecipeMenuAction::EditNote,
+                )),
+                Action::InspectChain => self.inspect_chains(),
+                // Toggles only apply to the body
+                _ if *self.tabs.data().selected() == Tab::Body
+                    && self.body_toggles.handle_action(action) => {}
                 _ => return Update::Propagate(event),
             }
         } else {
@@ -176,7 +339,7 @@ impl EventHandler for RecipePane {
         if let Some(state) = self.recipe_state.get_mut() {
             children.extend(
                 [
-                    state.body.as_mut().map(Component::as_child),
+                    state.body.as_mut().and_then(BodyDisplay::as_child),
                     Some(state.query.as_child()),
                     Some(state.headers.as_child()),
                 ]
@@ -184,6 +347,12 @@ impl EventHandler for RecipePane {
                 .flatten(),
             );
         }
+        if let Some(Some(note)) = self.note.get_mut() {
+            children.push(note.as_child());
+        }
+        if let Some(preview) = self.preview.get_mut() {
+            children.push(preview.as_child());
+        }
 
         children
     }
@@ -210,7 +379,10 @@ impl<'a> Draw<RecipePaneProps<'a>> for RecipePane {
 
         // Render request contents
         if let Some(recipe) = props.selected_recipe {
-            let method = recipe.method.to_string();
+            let method = match recipe.kind {
+                RecipeKind::Http => recipe.method.to_string(),
+                RecipeKind::WebSocket => "WS".to_owned(),
+            };
 
             let [metadata_area, tabs_area, content_area] = Layout::vertical([
                 Constraint::Length(1),
@@ -249,14 +421,7 @@ impl<'a> Draw<RecipePaneProps<'a>> for RecipePane {
             match self.tabs.data().selected() {
                 Tab::Body => {
                     if let Some(body) = &recipe_state.body {
-                        body.draw(
-                            frame,
-                            TextWindowProps {
-                                has_search_box: false,
-                            },
-                            content_area,
-                            true,
-                        );
+                        body.draw(frame, content_area, *self.body_toggles);
                     }
                 }
                 Tab::Query => recipe_state.query.draw(
@@ -284,6 +449,64 @@ impl<'a> Draw<RecipePaneProps<'a>> for RecipePane {
                         authentication.draw(frame, (), content_area, true)
                     }
                 }
+                Tab::Preview => {
+                    // Rebuild whenever rows are toggled, so the preview
+                    // matches what would be sent
+                    let options = self.build_options();
+                    let key = PreviewKey {
+                        recipe: RecipeStateKey {
+                            selected_profile_id: props
+                                .selected_profile_id
+                                .cloned(),
+                            recipe_id: recipe.id.clone(),
+                        },
+                        disabled_headers: options.disabled_headers.clone(),
+                        disabled_query_parameters: options
+                            .disabled_query_parameters
+                            .clone(),
+                    };
+                    let preview = self.preview.get_or_update(key, || {
+                        RequestPreview::new(RequestConfig {
+                            profile_id: props.selected_profile_id.cloned(),
+                            recipe_id: recipe.id.clone(),
+                            options,
+                        })
+                        .into()
+                    });
+                    preview.draw(frame, (), content_area, true);
+                }
+                Tab::Notes => {
+                    let note =
+                        self.note.get_or_update(recipe.id.clone(), || {
+                            ViewContext::with_database(|database| {
+                                database.get_recipe_note(&recipe.id)
+                            })
+                            .reported(&ViewContext::messages_tx())
+                            .flatten()
+                            .map(|note| TextWindow::new(note).into())
+                        });
+                    if let Some(note) = note.as_ref() {
+                        note.draw(
+                            frame,
+                            TextWindowProps {
+                                has_search_box: false,
+                                toggles: Default::default(),
+                            },
+                            content_area,
+                            true,
+                        );
+                    } else {
+                        let input_engine = &TuiContext::get().input_engine;
+                        frame.render_widget(
+                            format!(
+                                "No note; {}",
+                                input_engine
+                                    .add_hint("add one", Action::EditNote)
+                            ),
+                            content_area,
+                        );
+                    }
+                }
             }
         }
     }
@@ -329,6 +552,7 @@ impl RecipeState {
             .collect();
 
         Self {
+            profile_id: selected_profile_id.cloned(),
             url: TemplatePreview::new(
                 recipe.url.clone(),
                 selected_profile_id.cloned(),
@@ -347,13 +571,10 @@ impl RecipeState {
                     .build(),
             )
             .into(),
-            body: recipe.body.as_ref().map(|body| {
-                TextWindow::new(TemplatePreview::new(
-                    body.clone(),
-                    selected_profile_id.cloned(),
-                ))
-                .into()
-            }),
+            body: recipe
+                .body
+                .as_ref()
+                .map(|body| BodyDisplay::new(body, selected_profile_id)),
             // Map authentication type
             authentication: recipe.authentication.as_ref().map(
                 |authentication| {
@@ -372,12 +593,68 @@ impl RecipeState {
                                 }),
                             }
                         }
+                        Authentication::Digest { username, password } => {
+                            AuthenticationDisplay::Digest {
+                                username: TemplatePreview::new(
+                                    username.clone(),
+                                    selected_profile_id.cloned(),
+                                ),
+                                password: password.clone().map(|password| {
+                                    TemplatePreview::new(
+                                        password,
+                                        selected_profile_id.cloned(),
+                                    )
+                                }),
+                            }
+                        }
                         Authentication::Bearer(token) => {
                             AuthenticationDisplay::Bearer(TemplatePreview::new(
                                 token.clone(),
                                 selected_profile_id.cloned(),
                             ))
                         }
+                        Authentication::OAuth2(oauth2) => {
+                            let token = Arc::new(OnceLock::new());
+                            ViewContext::send_message(
+                                Message::OAuth2TokenPreview {
+                                    oauth2: oauth2.clone(),
+                                    profile_id: selected_profile_id.cloned(),
+                                    destination: Arc::clone(&token),
+                                },
+                            );
+                            AuthenticationDisplay::OAuth2 {
+                                grant_type: oauth2.grant_type,
+                                token_url: TemplatePreview::new(
+                                    oauth2.token_url.clone(),
+                                    selected_profile_id.cloned(),
+                                ),
+                                client_id: TemplatePreview::new(
+                                    oauth2.client_id.clone(),
+                                    selected_profile_id.cloned(),
+                                ),
+                                scopes: oauth2.scopes.join(" "),
+                                token,
+                            }
+                        }
+                        Authentication::AwsSigV4 {
+                            access_key_id,
+                            region,
+                            service,
+                            ..
+                        } => AuthenticationDisplay::AwsSigV4 {
+                            access_key_id: TemplatePreview::new(
+                                access_key_id.clone(),
+                                selected_profile_id.cloned(),
+                            ),
+                            region: TemplatePreview::new(
+                                region.clone(),
+                                selected_profile_id.cloned(),
+                            ),
+                            service: TemplatePreview::new(
+                                service.clone(),
+                                selected_profile_id.cloned(),
+                            ),
+                        },
                     }
                     .into() // Convert to Component
                 },
@@ -386,6 +663,103 @@ impl RecipeState {
     }
 }
 
+/// Display a recipe body. Raw bodies are shown in a scrollable text window,
+/// while multipart forms are shown as a table of fields
+#[derive(Debug)]
+enum BodyDisplay {
+    Raw(Component<TextWindow<TemplatePreview>>),
+    FormMultipart(Vec<(String, TemplatePreview)>),
+    /// Path of the file to stream
+    File(TemplatePreview),
+}
+
+impl BodyDisplay {
+    fn new(body: &RecipeBody, selected_profile_id: Option<&ProfileId>) -> Self {
+        let preview = |template: &Template| {
+            TemplatePreview::new(template.clone(), selected_profile_id.cloned())
+        };
+        match body {
+            RecipeBody::Raw(body) => {
+                Self::Raw(TextWindow::new(preview(body)).into())
+            }
+            RecipeBody::FormMultipart(fields) => Self::FormMultipart(
+                fields
+                    .iter()
+                    .map(|(name, field)| {
+                        let value = match field {
+                            MultipartField::Text(template) => preview(template),
+                            // Show the path; file content is loaded at send
+                            MultipartField::File(file) => preview(&file.path),
+                        };
+                        (name.clone(), value)
+                    })
+                    .collect(),
+            ),
+            RecipeBody::File(path) => Self::File(preview(path)),
+        }
+    }
+
+    /// Get every template in the body
+    fn templates(&self) -> Vec<&Template> {
+        match self {
+            Self::Raw(text_window) => {
+                vec![text_window.data().text().template()]
+            }
+            Self::FormMultipart(fields) => {
+                fields.iter().map(|(_, value)| value.template()).collect()
+            }
+            Self::File(path) => vec![path.template()],
+        }
+    }
+
+    /// Get the child component for event handling, if there is one
+    fn as_child(&mut self) -> Option<Component<&mut dyn EventHandler>> {
+        match self {
+            Self::Raw(text_window) => Some(text_window.as_child()),
+            Self::FormMultipart(_) | Self::File(_) => None,
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame, area: Rect, toggles: TextWindowToggles) {
+        match self {
+            Self::Raw(text_window) => text_window.draw(
+                frame,
+                TextWindowProps {
+                    has_search_box: false,
+                    toggles,
+                },
+                area,
+                true,
+            ),
+            Self::FormMultipart(fields) => {
+                let table = Table {
+                    rows: fields
+                        .iter()
+                        .map(|(name, value)| {
+                            [name.as_str().into(), value.generate()]
+                        })
+                        .collect_vec(),
+                    header: Some(["Field", "Value"]),
+                    column_widths: &[
+                        Constraint::Percentage(50),
+                        Constraint::Percentage(50),
+                    ],
+                    ..Default::default()
+                };
+                frame.render_widget(table.generate(), area)
+            }
+            Self::File(path) => {
+                let table = Table {
+                    rows: vec![["File".into(), path.generate()]],
+                    column_widths: &[Constraint::Length(4), Constraint::Min(0)],
+                    ..Default::default()
+                };
+                frame.render_widget(table.generate(), area)
+            }
+        }
+    }
+}
+
 /// Display authentication settings. This is basically the underlying
 /// [Authentication] type, but the templates have been rendered
 #[derive(Debug)]
@@ -395,15 +769,71 @@ enum AuthenticationDisplay {
         password: Option<TemplatePreview>,
     },
     Bearer(TemplatePreview),
+    Digest {
+        username: TemplatePreview,
+        password: Option<TemplatePreview>,
+    },
+    OAuth2 {
+        grant_type: OAuth2GrantType,
+        token_url: TemplatePreview,
+        client_id: TemplatePreview,
+        scopes: String,
+        /// Cached token, loaded in the background. Inner `None` means no token
+        /// has been fetched yet
+        token: Arc<OnceLock<Option<OAuth2Token>>>,
+    },
+    /// Secrets are omitted, since they aren't needed to identify the
+    /// credentials
+    AwsSigV4 {
+        access_key_id: TemplatePreview,
+        region: TemplatePreview,
+        service: TemplatePreview,
+    },
+}
+
+impl AuthenticationDisplay {
+    /// Get every displayed template
+    fn templates(&self) -> Vec<&Template> {
+        let previews = match self {
+            Self::Basic { username, password }
+            | Self::Digest { username, password } => {
+                [Some(username), password.as_ref()]
+                    .into_iter()
+                    .flatten()
+                    .collect()
+            }
+            Self::Bearer(token) => vec![token],
+            Self::OAuth2 {
+                token_url,
+                client_id,
+                ..
+            } => vec![token_url, client_id],
+            Self::AwsSigV4 {
+                access_key_id,
+                region,
+                service,
+            } => vec![access_key_id, region, service],
+        };
+        previews
+            .into_iter()
+            .map(TemplatePreview::template)
+            .collect()
+    }
 }
 
 impl Draw for AuthenticationDisplay {
     fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
         match self {
-            AuthenticationDisplay::Basic { username, password } => {
+            AuthenticationDisplay::Basic { username, password }
+            | AuthenticationDisplay::Digest { username, password } => {
+                let kind = if let AuthenticationDisplay::Digest { .. } = self {
+                    "Digest"
+                } else {
+                    "Basic"
+                };
                 let table = Table {
                     rows: vec![
-                        ["Type".into(), "Bearer".into()],
+                        ["Type".into(), kind.into()],
                         ["Username".into(), username.generate()],
                         [
                             "Password".into(),
@@ -429,6 +859,68 @@ impl Draw for AuthenticationDisplay {
                 };
                 frame.render_widget(table.generate(), metadata.area())
             }
+            AuthenticationDisplay::OAuth2 {
+                grant_type,
+                token_url,
+                client_id,
+                scopes,
+                token,
+            } => {
+                let token_status: Text = match token.get() {
+                    None => "Loading...".into(),
+                    Some(None) => "None (fetched on next request)".into(),
+                    Some(Some(token)) if token.is_expired() => {
+                        if token.refresh_token.is_some() {
+                            "Expired (refreshed on next request)".into()
+                        } else {
+                            "Expired (fetched on next request)".into()
+                        }
+                    }
+                    Some(Some(token)) => match token.expires_at {
+                        Some(expires_at) => Line::from(vec![
+                            "Valid until ".into(),
+                            expires_at.generate(),
+                        ])
+                        .into(),
+                        None => "Valid".into(),
+                    },
+                };
+                let table = Table {
+                    rows: vec![
+                        [
+                            "Type".into(),
+                            format!("OAuth 2.0 ({grant_type})").into(),
+                        ],
+                        ["Token URL".into(), token_url.generate()],
+                        ["Client ID".into(), client_id.generate()],
+                        ["Scopes".into(), scopes.as_str().into()],
+                        ["Token".into(), token_status],
+                    ],
+                    column_widths: &[Constraint::Length(9), Constraint::Min(0)],
+                    ..Default::default()
+                };
+                frame.render_widget(table.generate(), metadata.area())
+            }
+            AuthenticationDisplay::AwsSigV4 {
+                access_key_id,
+                region,
+                service,
+            } => {
+                let table = Table {
+                    rows: vec![
+                        ["Type".into(), "AWS Signature V4".into()],
+                        ["Access Key ID".into(), access_key_id.generate()],
+                        ["Region".into(), region.generate()],
+                        ["Service".into(), service.generate()],
+                    ],
+                    column_widths: &[
+                        Constraint::Length(13),
+                        Constraint::Min(0),
+                    ],
+                    ..Default::default()
+                };
+                frame.render_widget(table.generate(), metadata.area())
+            }
         }
     }
 }
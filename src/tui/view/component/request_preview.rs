@@ -0,0 +1,203 @@
+//! Preview of a request as it will be sent
+
+use crate::tui::{
+    context::TuiContext,
+    message::{Message, RequestConfig, RequestPreviewResult},
+    view::{
+        common::text_window::{TextWindow, TextWindowProps},
+        draw::{Draw, DrawMetadata, Generate},
+        event::EventHandler,
+        Component, ViewContext,
+    },
+};
+use ratatui::{
+    text::{Line, Span, Text},
+    Frame,
+};
+use std::sync::{Arc, OnceLock};
+
+/// The fully built request for a recipe: method, URL with query, headers
+/// (including authentication), and body, exactly as they'll be sent. The
+/// request is built in the background, without being sent.
+#[derive(Debug)]
+pub struct RequestPreview {
+    text_window: Component<TextWindow<PreviewState>>,
+}
+
+#[derive(Debug)]
+enum PreviewState {
+    /// Building can trigger chains, so it follows the same config switch as
+    /// template previews
+    Disabled,
+    /// On init we send a message which will trigger a task to build the
+    /// request. When the task is done, it'll dump the result back here.
+    Enabled(Arc<OnceLock<RequestPreviewResult>>),
+}
+
+impl RequestPreview {
+    pub fn new(request_config: RequestConfig) -> Self {
+        let state = if TuiContext::get().config.preview_templates {
+            let destination = Arc::new(OnceLock::new());
+            ViewContext::send_message(Message::RequestPreview {
+                request_config,
+                destination: Arc::clone(&destination),
+            });
+            PreviewState::Enabled(destination)
+        } else {
+            PreviewState::Disabled
+        };
+        Self {
+            text_window: TextWindow::new(state).into(),
+        }
+    }
+}
+
+impl EventHandler for RequestPreview {
+    fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
+        vec![self.text_window.as_child()]
+    }
+}
+
+impl Draw for RequestPreview {
+    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+        self.text_window.draw(
+            frame,
+            TextWindowProps {
+                has_search_box: false,
+                toggles: Default::default(),
+            },
+            metadata.area(),
+            true,
+        );
+    }
+}
+
+impl Generate for &PreviewState {
+    type Output<'this>
+        = Text<'this>
+    where
+        Self: 'this;
+
+    fn generate<'this>(self) -> Self::Output<'this>
+    where
+        Self: 'this,
+    {
+        let styles = &TuiContext::get().styles;
+        let destination = match self {
+            PreviewState::Disabled => {
+                return "Request previews are disabled by the \
+                    `preview_templates` config field"
+                    .into()
+            }
+            PreviewState::Enabled(destination) => destination,
+        };
+        let request = match destination.get() {
+            None => return "Building request...".into(),
+            Some(Err(error)) => {
+                return Text::styled(error.as_str(), styles.text.error)
+            }
+            Some(Ok(request)) => request,
+        };
+
+        let mut lines: Vec<Line> = vec![
+            vec![
+                Span::styled(request.method.to_string(), styles.text.primary),
+                " ".into(),
+                request.url.to_string().into(),
+            ]
+            .into(),
+            "".into(),
+        ];
+        lines.extend(request.headers.iter().map(|(name, value)| {
+            Line::from(vec![
+                Span::styled(format!("{name}: "), styles.text.highlight),
+                String::from_utf8_lossy(value.as_bytes())
+                    .into_owned()
+                    .into(),
+            ])
+        }));
+        if let Some(body) = &request.body {
+            lines.push("".into());
+            match body.text() {
+                Some(text) => lines.extend(text.lines().map(Line::from)),
+                None => lines.push(format!("<binary, {}>", body.size()).into()),
+            }
+        }
+        lines.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::{BuildOptions, RequestRecord},
+        test_util::{assert_matches, Factory},
+        tui::{
+            test_util::{harness, TestHarness},
+            view::test_util::TestComponent,
+        },
+    };
+    use reqwest::header::{self, HeaderMap, HeaderValue};
+    use rstest::rstest;
+
+    /// The request is built in the background, then shown once it's ready
+    #[rstest]
+    fn test_preview(harness: TestHarness) {
+        let mut component = TestComponent::new(
+            harness,
+            RequestPreview::new(RequestConfig {
+                profile_id: None,
+                recipe_id: "recipe1".into(),
+                options: BuildOptions::default(),
+            }),
+            (),
+        );
+        let lines = |component: &TestComponent<RequestPreview, ()>| {
+            component
+                .data()
+                .text_window
+                .data()
+                .text()
+                .generate()
+                .lines
+                .into_iter()
+                .map(|line| {
+                    line.spans
+                        .iter()
+                        .map(|span| span.content.as_ref())
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lines(&component), vec!["Building request..."]);
+
+        let destination = assert_matches!(
+            component.harness_mut().pop_message_now(),
+            Message::RequestPreview { destination, .. } => destination,
+        );
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        destination
+            .set(Ok(RequestRecord {
+                headers,
+                body: Some(b"{\"a\": 1}".to_vec().into()),
+                ..RequestRecord::factory(())
+            }
+            .into()))
+            .unwrap();
+        assert_eq!(
+            lines(&component),
+            vec![
+                "GET http://localhost/url",
+                "",
+                "content-type: application/json",
+                "",
+                "{\"a\": 1}",
+            ]
+        );
+    }
+}
@@ -0,0 +1,236 @@
+use crate::{
+    http::RequestId,
+    tui::{
+        context::TuiContext,
+        input::Action,
+        message::Message,
+        view::{
+            common::{list::List, modal::Modal},
+            component::Component,
+            draw::{Draw, DrawMetadata, Generate},
+            event::{Event, EventHandler, Update},
+            state::{select::SelectState, RequestStateSummary},
+            ViewContext,
+        },
+    },
+};
+use chrono::Utc;
+use ratatui::{
+    layout::Constraint,
+    text::{Line, Span},
+    Frame,
+};
+
+/// Every request sent during this session, including those still being built,
+/// waiting for a free slot, or in flight. Rows update live as requests
+/// progress, and in-progress requests can be cancelled from here.
+#[derive(Debug)]
+pub struct RequestQueue {
+    select: Component<SelectState<RequestQueueItem>>,
+}
+
+/// One row in the queue
+#[derive(Debug)]
+struct RequestQueueItem {
+    summary: RequestStateSummary,
+}
+
+impl RequestQueue {
+    /// Construct a new queue modal with the session's requests. Parent is
+    /// responsible for pulling these from the request store, most recent first.
+    pub fn new(requests: Vec<RequestStateSummary>) -> Self {
+        Self {
+            select: build_select(requests, None),
+        }
+    }
+
+    /// Update the row for a request. A request we haven't seen yet goes at the
+    /// top, since it's the most recent
+    fn update_request(&mut self, summary: RequestStateSummary) {
+        let selected = self.select.data().selected().map(RequestQueueItem::id);
+        let mut requests: Vec<RequestStateSummary> = self
+            .select
+            .data()
+            .items()
+            .iter()
+            .map(|item| item.summary.clone())
+            .collect();
+        match requests
+            .iter_mut()
+            .find(|request| request.id() == summary.id())
+        {
+            Some(request) => *request = summary,
+            None => requests.insert(0, summary),
+        }
+        self.select = build_select(requests, selected);
+    }
+
+    /// Cancel the highlighted request, if it's still in progress
+    fn cancel_selected(&self) {
+        if let Some(item) = self
+            .select
+            .data()
+            .selected()
+            .filter(|item| item.is_in_progress())
+        {
+            ViewContext::send_message(Message::HttpCancel(item.id()));
+        }
+    }
+}
+
+/// Build the list of requests
+fn build_select(
+    requests: Vec<RequestStateSummary>,
+    selected_request_id: Option<RequestId>,
+) -> Component<SelectState<RequestQueueItem>> {
+    let items = requests
+        .into_iter()
+        .map(|summary| RequestQueueItem { summary })
+        .collect();
+    SelectState::builder(items)
+        .preselect_opt(selected_request_id.as_ref())
+        // Show the request in the exchange pane
+        .on_submit(|item| {
+            ViewContext::push_event(Event::HttpSelectRequest(Some(item.id())));
+            ViewContext::push_event(Event::CloseModal);
+        })
+        .build()
+        .into()
+}
+
+impl Modal for RequestQueue {
+    fn title(&self) -> Line<'_> {
+        let input_engine = &TuiContext::get().input_engine;
+        format!(
+            "Request Queue | {}",
+            input_engine.add_hint("Cancel", Action::CancelRequest)
+        )
+        .into()
+    }
+
+    fn dimensions(&self) -> (Constraint, Constraint) {
+        (
+            Constraint::Length(90),
+            Constraint::Length(
+                self.select.data().items().len().clamp(1, 20) as u16
+            ),
+        )
+    }
+}
+
+impl EventHandler for RequestQueue {
+    fn update(&mut self, event: Event) -> Update {
+        match event {
+            Event::Input {
+                action: Some(Action::CancelRequest),
+                ..
+            } => self.cancel_selected(),
+            // Keep rows up to date, but let the root store the new state too
+            Event::HttpSetState(ref state) => {
+                self.update_request(state.into());
+                return Update::Propagate(event);
+            }
+            _ => return Update::Propagate(event),
+        }
+        Update::Consumed
+    }
+
+    fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
+        vec![self.select.as_child()]
+    }
+}
+
+impl Draw for RequestQueue {
+    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+        if self.select.data().items().is_empty() {
+            frame.render_widget(
+                "No requests sent this session",
+                metadata.area(),
+            );
+        } else {
+            self.select.draw(
+                frame,
+                List::new(self.select.data().items()),
+                metadata.area(),
+                true,
+            );
+        }
+    }
+}
+
+impl RequestQueueItem {
+    fn id(&self) -> RequestId {
+        self.summary.id()
+    }
+
+    fn is_in_progress(&self) -> bool {
+        matches!(
+            self.summary,
+            RequestStateSummary::Building { .. }
+                | RequestStateSummary::Loading { .. }
+        )
+    }
+}
+
+impl Generate for &RequestQueueItem {
+    type Output<'this>
+        = Line<'this>
+    where
+        Self: 'this;
+
+    fn generate<'this>(self) -> Self::Output<'this>
+    where
+        Self: 'this,
+    {
+        let styles = &TuiContext::get().styles;
+        let summary = &self.summary;
+        let status: Span = match summary {
+            RequestStateSummary::Building { .. } => "Building".into(),
+            RequestStateSummary::BuildError { .. } => {
+                Span::styled("Build error", styles.text.error)
+            }
+            RequestStateSummary::Loading { queued: true, .. } => {
+                "Queued".into()
+            }
+            RequestStateSummary::Loading { .. } => "In flight".into(),
+            RequestStateSummary::Response(exchange) => {
+                exchange.status.generate()
+            }
+            RequestStateSummary::RequestError { .. } => {
+                Span::styled("Failed", styles.text.error)
+            }
+        };
+        // Pad the status so the following columns line up
+        let status =
+            Span::styled(format!("{:<24}", status.content), status.style);
+        // In-progress requests show a running total
+        let duration = summary
+            .duration()
+            .or_else(|| {
+                self.is_in_progress().then(|| Utc::now() - summary.time())
+            })
+            .generate();
+        let profile = summary
+            .profile_id()
+            .map(ToString::to_string)
+            .unwrap_or_else(|| "No profile".into());
+
+        vec![
+            summary.time().generate(),
+            "  ".into(),
+            status,
+            format!("{:>8}  ", duration.content).into(),
+            Span::styled(summary.recipe_id().to_string(), styles.text.primary),
+            "  ".into(),
+            profile.into(),
+        ]
+        .into()
+    }
+}
+
+/// Allow selection by ID
+impl PartialEq<RequestQueueItem> for RequestId {
+    fn eq(&self, other: &RequestQueueItem) -> bool {
+        self == &other.id()
+    }
+}
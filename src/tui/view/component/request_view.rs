@@ -1,14 +1,22 @@
 use crate::{
-    http::{RequestId, RequestRecord},
+    http::{RequestId, RequestRecord, SelectorType},
     tui::{
         input::Action,
         message::Message,
         view::{
-            common::{actions::ActionsModal, header_table::HeaderTable},
-            component::exchange_body::{ExchangeBody, ExchangeBodyProps},
+            common::{
+                actions::ActionsModal, header_table::HeaderTable,
+                text_window::TextWindowToggles,
+            },
+            component::exchange_body::{
+                BodySource, ExchangeBody, ExchangeBodyProps,
+            },
             draw::{Draw, DrawMetadata, Generate, ToStringGenerate},
             event::{Event, EventHandler, Update},
-            state::StateCell,
+            state::{
+                persistence::{BodyView, Persistent, PersistentKey},
+                StateCell,
+            },
             Component, ViewContext,
         },
     },
@@ -20,9 +28,24 @@ use strum::{EnumCount, EnumIter};
 
 /// Display rendered HTTP request state. The request could still be in flight,
 /// it just needs to have been built successfully.
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct RequestView {
     state: StateCell<RequestId, State>,
+    /// Wrap/line number settings for the body. These apply to every request,
+    /// not just the current one
+    toggles: Persistent<TextWindowToggles>,
+}
+
+impl Default for RequestView {
+    fn default() -> Self {
+        Self {
+            state: Default::default(),
+            toggles: Persistent::new(
+                PersistentKey::BodyToggles(BodyView::Request),
+                Default::default(),
+            ),
+        }
+    }
 }
 
 pub struct RequestViewProps {
@@ -54,6 +77,11 @@ impl EventHandler for RequestView {
     fn update(&mut self, event: Event) -> Update {
         if let Some(Action::OpenActions) = event.action() {
             ViewContext::open_modal_default::<ActionsModal<MenuAction>>()
+        } else if event
+            .action()
+            .is_some_and(|action| self.toggles.handle_action(action))
+        {
+            // Wrap or line numbers were toggled. Next draw will pick it up
         } else if let Some(action) = event.local::<MenuAction>() {
             match action {
                 MenuAction::CopyUrl => {
@@ -100,7 +128,7 @@ impl Draw<RequestViewProps> for RequestView {
     ) {
         let state = self.state.get_or_update(props.request.id, || State {
             request: Arc::clone(&props.request),
-            body: ExchangeBody::new(None).into(),
+            body: ExchangeBody::new(None, SelectorType::default()).into(),
         });
 
         let [url_area, headers_area, body_area] = Layout::vertical([
@@ -121,9 +149,15 @@ impl Draw<RequestViewProps> for RequestView {
             headers_area,
         );
         if let Some(body) = &state.request.body {
-            state
-                .body
-                .draw(frame, ExchangeBodyProps { body }, body_area, true);
+            state.body.draw(
+                frame,
+                ExchangeBodyProps {
+                    body: BodySource::Request(body),
+                    toggles: *self.toggles,
+                },
+                body_area,
+                true,
+            );
         }
     }
 }
@@ -2,31 +2,63 @@
 
 use crate::{
     collection::RecipeId,
-    http::{RequestId, ResponseRecord},
+    http::{
+        BuildOptions, ContentType, RequestError, RequestId, ResponseRecord,
+    },
     tui::{
+        context::TuiContext,
         input::Action,
-        message::Message,
+        message::{Message, RequestConfig},
         view::{
-            common::{actions::ActionsModal, header_table::HeaderTable},
-            component::exchange_body::{ExchangeBody, ExchangeBodyProps},
+            common::{
+                actions::ActionsModal, header_table::HeaderTable,
+                text_window::TextWindowToggles,
+            },
+            component::exchange_body::{
+                BodySource, ExchangeBody, ExchangeBodyProps,
+            },
             draw::{Draw, DrawMetadata, Generate, ToStringGenerate},
             event::{Event, EventHandler, Update},
-            state::{persistence::PersistentKey, StateCell},
+            state::{
+                persistence::{BodyView, Persistent, PersistentKey},
+                StateCell,
+            },
             Component, ViewContext,
         },
     },
 };
+use bytesize::ByteSize;
 use derive_more::Display;
-use ratatui::Frame;
+use ratatui::{
+    layout::{Constraint, Layout},
+    text::Line,
+    widgets::Paragraph,
+    Frame,
+};
 use std::sync::Arc;
 use strum::{EnumCount, EnumIter};
 
 /// Display response body
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct ResponseBodyView {
     /// Persist the response body to track view state. Update whenever the
     /// loaded request changes
     state: StateCell<RequestId, State>,
+    /// Wrap/line number settings. These are stored out here so they apply
+    /// to every response, not just the current one
+    toggles: Persistent<TextWindowToggles>,
+}
+
+impl Default for ResponseBodyView {
+    fn default() -> Self {
+        Self {
+            state: Default::default(),
+            toggles: Persistent::new(
+                PersistentKey::BodyToggles(BodyView::Response),
+                Default::default(),
+            ),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -41,8 +73,29 @@ pub struct ResponseBodyViewProps<'a> {
 enum BodyMenuAction {
     #[display("Copy Body")]
     CopyBody,
+    /// Copy the JSONPath of the node at the top of the body window, to paste
+    /// into a body query or chain selector
+    #[display("Copy JSON Path")]
+    CopyJsonPath,
     #[display("Save Body as File")]
     SaveBody,
+    /// Save the raw body, including the parts of large bodies that aren't
+    /// loaded into memory
+    #[display("Save Full Body")]
+    SaveFullBody,
+    #[display("Copy Report")]
+    CopyReport,
+    #[display("Save Report as File")]
+    SaveReport,
+    #[display("Export as HAR")]
+    ExportHar,
+    /// Open the exchange's note in the user's editor
+    #[display("Edit Note")]
+    EditNote,
+    /// Make this exchange the baseline for its recipe, to diff later responses
+    /// against
+    #[display("Mark as Golden")]
+    MarkGolden,
 }
 
 impl ToStringGenerate for BodyMenuAction {}
@@ -50,6 +103,8 @@ impl ToStringGenerate for BodyMenuAction {}
 /// Internal state
 #[derive(Debug)]
 struct State {
+    /// Needed to generate the exchange report
+    request_id: RequestId,
     /// Use Arc so we're not cloning large responses
     response: Arc<ResponseRecord>,
     /// The presentable version of the response body, which may or may not
@@ -62,6 +117,11 @@ impl EventHandler for ResponseBodyView {
     fn update(&mut self, event: Event) -> Update {
         if let Some(Action::OpenActions) = event.action() {
             ViewContext::open_modal_default::<ActionsModal<BodyMenuAction>>();
+        } else if event
+            .action()
+            .is_some_and(|action| self.toggles.handle_action(action))
+        {
+            // Wrap or line numbers were toggled. Next draw will pick it up
         } else if let Some(action) = event.local::<BodyMenuAction>() {
             match action {
                 BodyMenuAction::CopyBody => {
@@ -74,6 +134,58 @@ impl EventHandler for ResponseBodyView {
                         ViewContext::send_message(Message::CopyText(body));
                     }
                 }
+                BodyMenuAction::CopyJsonPath => {
+                    if let Some(state) = self.state.get() {
+                        let message = match state
+                            .body
+                            .data()
+                            .json_path(&state.response.body)
+                        {
+                            Some(path) => Message::CopyText(path),
+                            None => Message::Notify(
+                                "JSON path is only available for JSON bodies \
+                                with no query applied"
+                                    .into(),
+                            ),
+                        };
+                        ViewContext::send_message(message);
+                    }
+                }
+                BodyMenuAction::CopyReport => {
+                    if let Some(state) = self.state.get() {
+                        ViewContext::send_message(Message::CopyExchangeReport(
+                            state.request_id,
+                        ));
+                    }
+                }
+                BodyMenuAction::SaveReport => {
+                    if let Some(state) = self.state.get() {
+                        ViewContext::send_message(Message::SaveExchangeReport(
+                            state.request_id,
+                        ));
+                    }
+                }
+                BodyMenuAction::ExportHar => {
+                    if let Some(state) = self.state.get() {
+                        ViewContext::send_message(Message::ExportExchangeHar(
+                            state.request_id,
+                        ));
+                    }
+                }
+                BodyMenuAction::EditNote => {
+                    if let Some(state) = self.state.get() {
+                        ViewContext::send_message(Message::EditExchangeNote(
+                            state.request_id,
+                        ));
+                    }
+                }
+                BodyMenuAction::MarkGolden => {
+                    if let Some(state) = self.state.get() {
+                        ViewContext::send_message(Message::GoldenMarkStart(
+                            state.request_id,
+                        ));
+                    }
+                }
                 BodyMenuAction::SaveBody => {
                     // For text, use whatever is visible to the user. For
                     // binary, use the raw value
@@ -102,6 +214,14 @@ impl EventHandler for ResponseBodyView {
                         });
                     }
                 }
+                BodyMenuAction::SaveFullBody => {
+                    if let Some(state) = self.state.get() {
+                        ViewContext::send_message(Message::SaveResponseBody {
+                            default_path: state.response.file_name(),
+                            response: Arc::clone(&state.response),
+                        });
+                    }
+                }
             }
         } else {
             return Update::Propagate(event);
@@ -127,19 +247,55 @@ impl<'a> Draw<ResponseBodyViewProps<'a>> for ResponseBodyView {
     ) {
         let response = &props.response;
         let state = self.state.get_or_update(props.request_id, || State {
+            request_id: props.request_id,
             response: Arc::clone(&props.response),
-            body: ExchangeBody::new(Some(PersistentKey::ResponseBodyQuery(
-                props.recipe_id.clone(),
-            )))
+            body: ExchangeBody::new(
+                Some(PersistentKey::ResponseBodyQuery(props.recipe_id.clone())),
+                // Query language depends on the type of the body. The body
+                // hasn't been parsed yet, so go by the headers
+                ContentType::from_response(response)
+                    .map(ContentType::selector_type)
+                    .unwrap_or_default(),
+            )
             .into(),
         });
 
+        // Large bodies only have a preview loaded, so let the user know
+        let area = if response.body.is_truncated() {
+            let [notice_area, body_area] =
+                Layout::vertical([Constraint::Length(1), Constraint::Min(0)])
+                    .areas(metadata.area());
+            let preview_size = ByteSize(response.body.bytes().len() as u64);
+            let size = response.body.size();
+            // The full body only sticks around until the app exits
+            let notice = if response.body.is_spooled() {
+                format!(
+                    "Showing first {preview_size} of {size}. Use \
+                    \"Save Full Body\" for the rest"
+                )
+            } else {
+                format!(
+                    "Showing first {preview_size} of {size}. The rest was \
+                    not stored in history"
+                )
+            };
+            frame.render_widget(
+                Paragraph::new(notice)
+                    .style(TuiContext::get().styles.text.highlight),
+                notice_area,
+            );
+            body_area
+        } else {
+            metadata.area()
+        };
+
         state.body.draw(
             frame,
             ExchangeBodyProps {
-                body: &response.body,
+                body: BodySource::Response(&props.response),
+                toggles: *self.toggles,
             },
-            metadata.area(),
+            area,
             true,
         );
     }
@@ -169,17 +325,107 @@ impl<'a> Draw<ResponseHeadersViewProps<'a>> for ResponseHeadersView {
     }
 }
 
+/// Display an error that occurred while sending a request or loading its
+/// response. If the response body was partially downloaded, this offers an
+/// action to resume it.
+#[derive(Debug, Default)]
+pub struct RequestErrorView {
+    /// Config needed to resume the failed download, if possible. Update
+    /// whenever the displayed error changes
+    resume: StateCell<RequestId, Option<RequestConfig>>,
+}
+
+#[derive(Clone)]
+pub struct RequestErrorViewProps<'a> {
+    pub error: &'a RequestError,
+}
+
+/// Items in the actions popup menu for a failed request
+#[derive(Copy, Clone, Debug, Display, EnumCount, EnumIter, PartialEq)]
+enum ErrorMenuAction {
+    #[display("Resume Download")]
+    ResumeDownload,
+}
+
+impl ToStringGenerate for ErrorMenuAction {}
+
+impl EventHandler for RequestErrorView {
+    fn update(&mut self, event: Event) -> Update {
+        if let Some(Action::OpenActions) = event.action() {
+            ViewContext::open_modal_default::<ActionsModal<ErrorMenuAction>>();
+        } else if let Some(action) = event.local::<ErrorMenuAction>() {
+            match action {
+                ErrorMenuAction::ResumeDownload => {
+                    let config =
+                        self.resume.get().and_then(|config| config.clone());
+                    let message = match config {
+                        Some(config) => Message::HttpBeginRequest(config),
+                        None => Message::Notify(
+                            "No partial download to resume; set \
+                            `save_response_to` on the recipe to enable"
+                                .into(),
+                        ),
+                    };
+                    ViewContext::send_message(message);
+                }
+            }
+        } else {
+            return Update::Propagate(event);
+        }
+        Update::Consumed
+    }
+}
+
+impl<'a> Draw<RequestErrorViewProps<'a>> for RequestErrorView {
+    fn draw(
+        &self,
+        frame: &mut Frame,
+        props: RequestErrorViewProps,
+        metadata: DrawMetadata,
+    ) {
+        let error = props.error;
+        self.resume.get_or_update(error.request.id, || {
+            error.resume_path.as_ref().map(|path| RequestConfig {
+                profile_id: error.request.profile_id.clone(),
+                recipe_id: error.request.recipe_id.clone(),
+                // Resend the same request, just for the rest of the body
+                options: BuildOptions {
+                    resume_download: Some(path.clone()),
+                    ..(*error.options).clone()
+                },
+            })
+        });
+
+        let mut text = error.generate();
+        if let Some(path) = &error.resume_path {
+            text.lines.push(Line::default());
+            text.lines.push(
+                format!(
+                    "Partial response saved to {}; \
+                    use the Resume Download action to continue it",
+                    path.display()
+                )
+                .into(),
+            );
+        }
+        frame.render_widget(Paragraph::new(text), metadata.area());
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
-        http::Exchange,
+        http::{Exchange, RequestRecord},
         test_util::{assert_matches, header_map, Factory},
         tui::{
             test_util::{harness, TestHarness},
             view::test_util::TestComponent,
         },
     };
+    use anyhow::anyhow;
+    use chrono::Utc;
+    use crossterm::event::KeyCode;
     use indexmap::indexmap;
     use rstest::rstest;
 
@@ -208,7 +454,6 @@ mod tests {
         #[case] response: ResponseRecord,
         #[case] expected_body: &str,
     ) {
-        response.parse_body(); // Normally the view does this
         let exchange = Exchange {
             response: response.into(),
             ..Exchange::factory(())
@@ -222,6 +467,8 @@ mod tests {
                 response: exchange.response,
             },
         );
+        // Normally this happens in the background
+        component.harness_mut().prettify_body_now();
 
         component
             .update_draw(Event::new_local(BodyMenuAction::CopyBody))
@@ -234,6 +481,57 @@ mod tests {
         assert_eq!(body, expected_body);
     }
 
+    /// Test "Copy JSON Path" menu action. The path is for whichever node is
+    /// at the top of the window
+    #[rstest]
+    #[case::root(0, "$")]
+    #[case::nested(3, "$.users[0].id")]
+    #[case::closing_bracket(5, "$.users[0]")]
+    #[tokio::test]
+    async fn test_copy_json_path(
+        // One line of body, so each Down scrolls one line
+        #[with(40, 2)] harness: TestHarness,
+        #[case] scroll: usize,
+        #[case] expected_path: &str,
+    ) {
+        let exchange = Exchange {
+            response: ResponseRecord {
+                headers: header_map(
+                    indexmap! {"content-type" => "application/json"},
+                ),
+                body: br#"{"users":[{"id":1,"name":"Barry"}]}"#.to_vec().into(),
+                ..ResponseRecord::factory(())
+            }
+            .into(),
+            ..Exchange::factory(())
+        };
+        let mut component = TestComponent::new(
+            harness,
+            ResponseBodyView::default(),
+            ResponseBodyViewProps {
+                request_id: exchange.id,
+                recipe_id: &exchange.request.recipe_id,
+                response: exchange.response,
+            },
+        );
+        // Normally this happens in the background
+        component.harness_mut().prettify_body_now();
+        component.redraw();
+        for _ in 0..scroll {
+            component.send_key(KeyCode::Down).assert_empty();
+        }
+
+        component
+            .update_draw(Event::new_local(BodyMenuAction::CopyJsonPath))
+            .assert_empty();
+
+        let path = assert_matches!(
+            component.harness_mut().pop_message_now(),
+            Message::CopyText(path) => path,
+        );
+        assert_eq!(path, expected_path);
+    }
+
     /// Test "Save Body as File" menu action
     #[rstest]
     #[case::json_body(
@@ -274,7 +572,6 @@ mod tests {
         #[case] expected_body: &[u8],
         #[case] expected_path: &str,
     ) {
-        response.parse_body(); // Normally the view does this
         let exchange = Exchange {
             response: response.into(),
             ..Exchange::factory(())
@@ -288,6 +585,8 @@ mod tests {
                 response: exchange.response,
             },
         );
+        // Normally this happens in the background
+        component.harness_mut().prettify_body_now();
 
         component
             .update_draw(Event::new_local(BodyMenuAction::SaveBody))
@@ -300,4 +599,54 @@ mod tests {
         assert_eq!(data, expected_body);
         assert_eq!(default_path.as_deref(), Some(expected_path));
     }
+
+    /// Test "Resume Download" menu action, which should re-send the request
+    /// with the partial download path, and the rest of the original options
+    #[rstest]
+    #[tokio::test]
+    async fn test_resume_download(harness: TestHarness) {
+        let request = RequestRecord::factory(());
+        let error = RequestError {
+            error: anyhow!("connection reset"),
+            request: request.into(),
+            retries: Vec::new(),
+            start_time: Utc::now(),
+            end_time: Utc::now(),
+            partial_response: Some(ResponseRecord::factory(()).into()),
+            resume_path: Some("download.txt".into()),
+            options: BuildOptions {
+                disabled_headers: ["Accept".to_owned()].into(),
+                body: Some("{}".into()),
+                ..Default::default()
+            }
+            .into(),
+        };
+        let mut component = TestComponent::new(
+            harness,
+            RequestErrorView::default(),
+            RequestErrorViewProps { error: &error },
+        );
+
+        component
+            .update_draw(Event::new_local(ErrorMenuAction::ResumeDownload))
+            .assert_empty();
+
+        let config = assert_matches!(
+            component.harness_mut().pop_message_now(),
+            Message::HttpBeginRequest(config) => config,
+        );
+        assert_eq!(
+            config,
+            RequestConfig {
+                profile_id: None,
+                recipe_id: error.request.recipe_id.clone(),
+                options: BuildOptions {
+                    disabled_headers: ["Accept".to_owned()].into(),
+                    resume_download: Some("download.txt".into()),
+                    body: Some("{}".into()),
+                    ..Default::default()
+                },
+            }
+        );
+    }
 }
@@ -1,5 +1,5 @@
 use crate::{
-    collection::Collection,
+    collection::{Collection, Profile, ProfileOverrides, RecipeId},
     http::RequestId,
     tui::{
         input::Action,
@@ -9,8 +9,10 @@ use crate::{
             component::{
                 help::HelpFooter,
                 history::History,
+                message_log::MessageLog,
                 misc::NotificationText,
                 primary::{PrimaryView, PrimaryViewProps},
+                request_queue::RequestQueue,
             },
             draw::{Draw, DrawMetadata, Generate},
             event::{Event, EventHandler, Update},
@@ -19,7 +21,8 @@ use crate::{
                     Persistable, Persistent, PersistentContainer, PersistentKey,
                 },
                 request_store::RequestStore,
-                RequestState, RequestStateSummary,
+                Notification, NotificationLevel, RequestState,
+                RequestStateSummary,
             },
             Component, ModalPriority, ViewContext,
         },
@@ -37,6 +40,19 @@ pub struct Root {
     request_store: RequestStore,
     /// Which request are we showing in the request/response panel?
     selected_request: Persistent<SelectedRequestId>,
+    /// Exchanges pinned by the user, shown as tabs in the request/response
+    /// panel so they can be revisited without re-sending. Pins only last for
+    /// the current session
+    pinned_requests: Vec<RequestId>,
+    /// Index of the pinned exchange being shown, instead of the selected
+    /// request. `None` shows the selected request as normal
+    selected_pin: Option<usize>,
+    /// Every request sent during this session, in the order they were sent.
+    /// Used to populate the request queue
+    session_requests: Vec<RequestId>,
+    /// Every notification and error from this session, so they can be read
+    /// after they've disappeared
+    message_log: MessageLog,
 
     // ==== Children =====
     /// We hold onto the primary view even when it's not visible, because we
@@ -47,10 +63,13 @@ pub struct Root {
 }
 
 impl Root {
-    pub fn new(collection: &Collection) -> Self {
+    pub fn new(
+        collection: &Collection,
+        profile_overrides: &ProfileOverrides,
+    ) -> Self {
         // Load the selected request *second*, so it will take precedence over
         // the event that attempts to load the latest request for the recipe
-        let primary_view = PrimaryView::new(collection);
+        let primary_view = PrimaryView::new(collection, profile_overrides);
         let selected_request = Persistent::new(
             PersistentKey::RequestId,
             SelectedRequestId::default(),
@@ -59,6 +78,10 @@ impl Root {
             // State
             request_store: RequestStore::default(),
             selected_request,
+            pinned_requests: Vec::new(),
+            selected_pin: None,
+            session_requests: Vec::new(),
+            message_log: MessageLog::default(),
 
             // Children
             primary_view: primary_view.into(),
@@ -67,16 +90,81 @@ impl Root {
         }
     }
 
+    /// Select a recipe in the recipe list
+    pub fn select_recipe(&mut self, recipe_id: &RecipeId) {
+        self.primary_view.data_mut().select_recipe(recipe_id);
+    }
+
+    /// Update profiles after their session overrides changed. See
+    /// [PrimaryView::set_profile_overrides]
+    pub fn set_profile_overrides(
+        &mut self,
+        profiles: Vec<Profile>,
+        profile_overrides: ProfileOverrides,
+    ) {
+        self.primary_view
+            .data_mut()
+            .set_profile_overrides(profiles, profile_overrides);
+    }
+
+    /// See [PrimaryView::set_recipe_note]
+    pub fn set_recipe_note(
+        &mut self,
+        recipe_id: &RecipeId,
+        note: Option<String>,
+    ) {
+        self.primary_view
+            .data_mut()
+            .set_recipe_note(recipe_id, note);
+    }
+
+    /// See [PrimaryView::set_exchange_note]
+    pub fn set_exchange_note(
+        &mut self,
+        request_id: RequestId,
+        note: Option<String>,
+    ) {
+        self.primary_view
+            .data_mut()
+            .set_exchange_note(request_id, note);
+    }
+
     /// Select the given request. This will ensure the request data is loaded
     /// in memory.
     fn select_request(
         &mut self,
         request_id: Option<RequestId>,
     ) -> anyhow::Result<()> {
+        // Selecting something new takes us off any pinned exchange
+        self.selected_pin = None;
+        // The user is looking at this recipe now, so any background completion
+        // has been seen
+        self.primary_view
+            .data_mut()
+            .clear_selected_recipe_completed();
         let primary_view = self.primary_view.data();
         **self.selected_request = if let Some(request_id) = request_id {
             // Make sure the given ID is valid, and the request is loaded
             self.request_store.load(request_id)?;
+            // A request from history may belong to a different recipe. Select
+            // that recipe, so the request is shown alongside it
+            let recipe_id = self
+                .request_store
+                .get(request_id)
+                .map(|state| state.recipe_id().clone());
+            if let Some(recipe_id) = recipe_id.filter(|recipe_id| {
+                primary_view.selected_recipe_id() != Some(recipe_id)
+            }) {
+                let primary_view = self.primary_view.data_mut();
+                primary_view.select_recipe(&recipe_id);
+                // Selecting the recipe queued a load of its latest request.
+                // Queue this request again so it wins
+                if primary_view.selected_recipe_id() == Some(&recipe_id) {
+                    ViewContext::push_event(Event::HttpSelectRequest(Some(
+                        request_id,
+                    )));
+                }
+            }
             Some(request_id)
         } else if let Some(recipe_id) = primary_view.selected_recipe_id() {
             // Find the most recent request by recipe+profile
@@ -96,20 +184,119 @@ impl Root {
             .and_then(|request_id| self.request_store.get(request_id))
     }
 
-    /// Open the history modal for current recipe+profile. Return an error if
-    /// the harness.database load failed.
+    /// ID of the request being displayed. This is the selected pin if there is
+    /// one, otherwise the selected request
+    fn displayed_request_id(&self) -> Option<RequestId> {
+        match self.selected_pin {
+            Some(index) => self.pinned_requests.get(index).copied(),
+            None => **self.selected_request,
+        }
+    }
+
+    /// Pin the displayed exchange, or unpin it if it's already pinned
+    fn toggle_pin(&mut self) {
+        let Some(request_id) = self.displayed_request_id() else {
+            return;
+        };
+        if let Some(index) = self
+            .pinned_requests
+            .iter()
+            .position(|pinned| *pinned == request_id)
+        {
+            self.pinned_requests.remove(index);
+            // Fall back to the selected request, since the pin is gone
+            self.selected_pin = None;
+        } else {
+            self.pinned_requests.push(request_id);
+        }
+    }
+
+    /// Cycle through the pinned exchanges. The selected request comes before
+    /// the first pin, so it's included in the cycle
+    fn cycle_pin(&mut self, forward: bool) {
+        // Position 0 is the selected request, 1..=n are pins
+        let len = self.pinned_requests.len() + 1;
+        let position = self.selected_pin.map_or(0, |index| index + 1);
+        let position = if forward {
+            (position + 1) % len
+        } else {
+            (position + len - 1) % len
+        };
+        self.selected_pin = position.checked_sub(1);
+    }
+
+    /// If a request just finished for a recipe that isn't selected, badge the
+    /// recipe in the list and notify the user, so slow requests that the user
+    /// navigated away from don't complete unnoticed
+    fn alert_background_completion(&mut self, state: &RequestState) {
+        let outcome = match state {
+            RequestState::Response { exchange } => {
+                exchange.response.status.to_string()
+            }
+            RequestState::RequestError { .. } => "failed".to_owned(),
+            _ => return,
+        };
+        let recipe_id = state.recipe_id();
+        let primary_view = self.primary_view.data_mut();
+        if primary_view.selected_recipe_id() != Some(recipe_id) {
+            ViewContext::push_event(Event::Notify(Notification::new(format!(
+                "Request for `{recipe_id}` completed: {outcome}"
+            ))));
+            primary_view.mark_recipe_completed(recipe_id.clone());
+        }
+    }
+
+    /// Cancel the displayed request, if it's still being built or sent
+    fn cancel_request(&self) {
+        if let Some(state) = self
+            .displayed_request_id()
+            .and_then(|request_id| self.request_store.get(request_id))
+            .filter(|state| state.is_in_progress())
+        {
+            ViewContext::send_message(Message::HttpCancel(state.id()));
+        }
+    }
+
+    /// Open the request queue modal, with every request from this session
+    fn open_request_queue(&self) {
+        let requests = self
+            .session_requests
+            .iter()
+            .rev()
+            .filter_map(|request_id| self.request_store.get(*request_id))
+            .map(RequestStateSummary::from)
+            .collect();
+        ViewContext::open_modal(
+            RequestQueue::new(requests),
+            ModalPriority::Low,
+        );
+    }
+
+    /// Open the history modal for current recipe+profile. The modal can also
+    /// show history for the entire collection, so load that too. Return an
+    /// error if the database load failed.
     fn open_history(&mut self) -> anyhow::Result<()> {
         let primary_view = self.primary_view.data();
         if let Some(recipe) = primary_view.selected_recipe() {
             // Make sure all requests for this profile+recipe are loaded
-            let requests = self
+            let recipe_requests = self
                 .request_store
                 .load_summaries(primary_view.selected_profile_id(), &recipe.id)?
-                .map(RequestStateSummary::from)
                 .collect();
+            let all_requests =
+                self.request_store.load_all_summaries()?.collect();
+            let notes = ViewContext::with_database(|database| {
+                database.get_exchange_notes()
+            })?;
 
             ViewContext::open_modal(
-                History::new(recipe, requests, **self.selected_request),
+                History::new(
+                    recipe,
+                    recipe_requests,
+                    all_requests,
+                    notes,
+                    **self.selected_request,
+                ),
                 ModalPriority::Low,
             );
         }
@@ -128,15 +315,28 @@ impl EventHandler for Root {
             // Update state of in-progress HTTP request
             Event::HttpSetState(state) => {
                 let id = state.id();
+                self.alert_background_completion(&state);
+                if let RequestState::Response { exchange } = &state {
+                    self.primary_view
+                        .data_mut()
+                        .record_exchange(exchange.into());
+                }
                 // If this request is *new*, select it
                 if self.request_store.update(state) {
                     **self.selected_request = Some(id);
+                    self.selected_pin = None;
+                    self.session_requests.push(id);
                 }
             }
 
             Event::Notify(notification) => {
-                self.notification_text =
-                    Some(NotificationText::new(notification).into())
+                self.message_log.push(notification.clone());
+                // Errors were already shown in a modal, so they only go in
+                // the log
+                if notification.level == NotificationLevel::Info {
+                    self.notification_text =
+                        Some(NotificationText::new(notification).into())
+                }
             }
 
             Event::Input {
@@ -147,6 +347,15 @@ impl EventHandler for Root {
                 Action::History => {
                     self.open_history().reported(&ViewContext::messages_tx());
                 }
+                // Pins are handled here for the same reason
+                Action::TogglePin => self.toggle_pin(),
+                Action::PreviousPin => self.cycle_pin(false),
+                Action::NextPin => self.cycle_pin(true),
+                Action::CancelRequest => self.cancel_request(),
+                Action::MessageLog => ViewContext::open_modal(
+                    self.message_log.to_modal(),
+                    ModalPriority::Low,
+                ),
                 Action::Quit => ViewContext::send_message(Message::Quit),
                 Action::ReloadCollection => {
                     ViewContext::send_message(Message::CollectionStartReload)
@@ -163,6 +372,59 @@ impl EventHandler for Root {
                     Some(GlobalAction::EditCollection) => {
                         ViewContext::send_message(Message::CollectionEdit)
                     }
+                    Some(GlobalAction::FindReplace) => {
+                        ViewContext::send_message(
+                            Message::CollectionFindReplace,
+                        )
+                    }
+                    Some(GlobalAction::NewRecipe) => {
+                        ViewContext::send_message(Message::RecipeCreateStart)
+                    }
+                    Some(GlobalAction::ScratchRequest) => {
+                        ViewContext::send_message(Message::ScratchStart)
+                    }
+                    Some(GlobalAction::PasteCurl) => {
+                        ViewContext::send_message(Message::ScratchCurlStart)
+                    }
+                    Some(GlobalAction::CompareProfiles) => {
+                        self.primary_view.data().open_profile_compare()
+                    }
+                    Some(GlobalAction::OverrideProfileField) => {
+                        if let Some(profile_id) =
+                            self.primary_view.data().selected_profile_id()
+                        {
+                            ViewContext::send_message(
+                                Message::ProfileOverrideStart(
+                                    profile_id.clone(),
+                                ),
+                            )
+                        } else {
+                            ViewContext::send_message(Message::Notify(
+                                "No profile selected".into(),
+                            ))
+                        }
+                    }
+                    Some(GlobalAction::ClearProfileOverrides) => {
+                        ViewContext::send_message(
+                            Message::ProfileClearOverrides,
+                        )
+                    }
+                    Some(GlobalAction::RequestQueue) => {
+                        self.open_request_queue()
+                    }
+                    Some(GlobalAction::ScheduledRequests) => {
+                        ViewContext::send_message(Message::ScheduleOpen)
+                    }
+                    Some(GlobalAction::InspectChains) => {
+                        ViewContext::send_message(Message::ChainInspect {
+                            chain_ids: None,
+                            profile_id: self
+                                .primary_view
+                                .data()
+                                .selected_profile_id()
+                                .cloned(),
+                        })
+                    }
                     None => return Update::Propagate(event),
                 }
             }
@@ -190,6 +452,14 @@ impl Draw for Root {
             frame,
             PrimaryViewProps {
                 selected_request: self.selected_request(),
+                pinned_requests: self
+                    .pinned_requests
+                    .iter()
+                    .filter_map(|request_id| {
+                        self.request_store.get(*request_id)
+                    })
+                    .collect(),
+                selected_pin: self.selected_pin,
             },
             main_area,
             !self.modal_queue.data().is_open(),
@@ -236,6 +506,7 @@ impl PersistentContainer for SelectedRequestId {
 mod tests {
     use super::*;
     use crate::{
+        collection::{Profile, Recipe},
         http::Exchange,
         test_util::{assert_matches, Factory},
         tui::{
@@ -243,8 +514,11 @@ mod tests {
             view::test_util::TestComponent,
         },
     };
+    use anyhow::anyhow;
     use crossterm::event::KeyCode;
+    use indexmap::indexmap;
     use rstest::rstest;
+    use std::sync::Arc;
 
     /// Test that, on first render, the view loads the most recent historical
     /// request for the first recipe+profile
@@ -258,7 +532,11 @@ mod tests {
             Exchange::factory((Some(profile_id.clone()), recipe_id.clone()));
         harness.database.insert_exchange(&exchange).unwrap();
 
-        let component = TestComponent::new(harness, Root::new(&collection), ());
+        let component = TestComponent::new(
+            harness,
+            Root::new(&collection, &Default::default()),
+            (),
+        );
 
         // Make sure profile+recipe were preselected correctly
         let primary_view = component.data().primary_view.data();
@@ -292,7 +570,11 @@ mod tests {
             .set_ui(PersistentKey::RequestId, old_exchange.id)
             .unwrap();
 
-        let component = TestComponent::new(harness, Root::new(&collection), ());
+        let component = TestComponent::new(
+            harness,
+            Root::new(&collection, &Default::default()),
+            (),
+        );
 
         // Make sure everything was preselected correctly
         assert_eq!(
@@ -314,8 +596,11 @@ mod tests {
     #[rstest]
     fn test_edit_collection(harness: TestHarness) {
         let collection = Collection::factory(());
-        let mut component =
-            TestComponent::new(harness, Root::new(&collection), ());
+        let mut component = TestComponent::new(
+            harness,
+            Root::new(&collection, &Default::default()),
+            (),
+        );
 
         component.harness_mut().clear_messages(); // Clear init junk
 
@@ -329,4 +614,187 @@ mod tests {
             Message::CollectionEdit
         );
     }
+    /// Completed requests for recipes other than the selected one should
+    /// notify the user
+    #[rstest]
+    fn test_background_completion(harness: TestHarness) {
+        let collection = Collection::factory(());
+        let profile_id = collection.first_profile_id().clone();
+        let recipe_id = collection.first_recipe_id().clone();
+        let mut component = TestComponent::new(
+            harness,
+            Root::new(&collection, &Default::default()),
+            (),
+        );
+
+        // Selected recipe - user can already see it
+        let exchange = Exchange::factory((Some(profile_id.clone()), recipe_id));
+        component
+            .update_draw(Event::HttpSetState(RequestState::response(exchange)))
+            .assert_empty();
+        assert!(component.data().notification_text.is_none());
+
+        let exchange =
+            Exchange::factory((Some(profile_id), RecipeId::from("other")));
+        component
+            .update_draw(Event::HttpSetState(RequestState::response(exchange)))
+            .assert_empty();
+        assert!(component.data().notification_text.is_some());
+    }
+
+    /// Notifications and errors are both logged, but only notifications are
+    /// shown in the footer
+    #[rstest]
+    fn test_message_log(harness: TestHarness) {
+        let collection = Collection::factory(());
+        let mut component = TestComponent::new(
+            harness,
+            Root::new(&collection, &Default::default()),
+            (),
+        );
+
+        component
+            .update_draw(Event::Notify(Notification::error(&anyhow!("bad"))))
+            .assert_empty();
+        assert!(component.data().notification_text.is_none());
+        component
+            .update_draw(Event::Notify(Notification::new("good".into())))
+            .assert_empty();
+        assert!(component.data().notification_text.is_some());
+
+        component.send_key(KeyCode::Char('m')).assert_empty();
+        assert!(component.data().modal_queue.data().is_open());
+    }
+
+    /// Pin an exchange, then flip between it and the selected request
+    #[rstest]
+    fn test_pin(harness: TestHarness) {
+        let collection = Collection::factory(());
+        let profile_id = collection.first_profile_id().clone();
+        let recipe_id = collection.first_recipe_id().clone();
+        let mut component = TestComponent::new(
+            harness,
+            Root::new(&collection, &Default::default()),
+            (),
+        );
+
+        let old_exchange =
+            Exchange::factory((Some(profile_id.clone()), recipe_id.clone()));
+        let old_id = old_exchange.id;
+        component
+            .update_draw(Event::HttpSetState(RequestState::response(
+                old_exchange,
+            )))
+            .assert_empty();
+        component.send_key(KeyCode::Char('b')).assert_empty();
+        assert_eq!(component.data().pinned_requests, vec![old_id]);
+
+        // A new request is shown in place of the pin
+        let new_exchange = Exchange::factory((Some(profile_id), recipe_id));
+        let new_id = new_exchange.id;
+        component
+            .update_draw(Event::HttpSetState(RequestState::response(
+                new_exchange,
+            )))
+            .assert_empty();
+        assert_eq!(component.data().displayed_request_id(), Some(new_id));
+
+        // Cycle to the pin and back, in both directions
+        component.send_key(KeyCode::Char(']')).assert_empty();
+        assert_eq!(component.data().displayed_request_id(), Some(old_id));
+        component.send_key(KeyCode::Char(']')).assert_empty();
+        assert_eq!(component.data().displayed_request_id(), Some(new_id));
+        component.send_key(KeyCode::Char('[')).assert_empty();
+        assert_eq!(component.data().displayed_request_id(), Some(old_id));
+
+        // Unpinning goes back to the selected request
+        component.send_key(KeyCode::Char('b')).assert_empty();
+        assert!(component.data().pinned_requests.is_empty());
+        assert_eq!(component.data().displayed_request_id(), Some(new_id));
+    }
+
+    /// The cancel action should cancel the displayed request, but only while
+    /// it's in progress
+    #[rstest]
+    fn test_cancel_request(harness: TestHarness) {
+        let collection = Collection::factory(());
+        let profile_id = collection.first_profile_id().clone();
+        let recipe_id = collection.first_recipe_id().clone();
+        let mut component = TestComponent::new(
+            harness,
+            Root::new(&collection, &Default::default()),
+            (),
+        );
+
+        let exchange = Exchange::factory((Some(profile_id), recipe_id));
+        let id = exchange.id;
+        component
+            .update_draw(Event::HttpSetState(RequestState::queued(Arc::clone(
+                &exchange.request,
+            ))))
+            .assert_empty();
+        component.harness_mut().clear_messages();
+        component.send_key(KeyCode::Delete).assert_empty();
+        let request_id = assert_matches!(
+            component.harness_mut().pop_message_now(),
+            Message::HttpCancel(request_id) => request_id
+        );
+        assert_eq!(request_id, id);
+
+        // Completed requests can't be cancelled
+        component
+            .update_draw(Event::HttpSetState(RequestState::response(exchange)))
+            .assert_empty();
+        component.send_key(KeyCode::Delete).assert_empty();
+        component.harness_mut().assert_messages_empty();
+        assert_eq!(component.data().session_requests, vec![id]);
+    }
+
+    /// Selecting a request for a different recipe, e.g. from the history
+    /// modal, should select that recipe too
+    #[rstest]
+    fn test_select_request_other_recipe(harness: TestHarness) {
+        let recipe1 = Recipe::factory(());
+        let recipe2 = Recipe::factory(());
+        let profile = Profile::factory(());
+        let old_exchange =
+            Exchange::factory((Some(profile.id.clone()), recipe2.id.clone()));
+        let new_exchange =
+            Exchange::factory((Some(profile.id.clone()), recipe2.id.clone()));
+        harness.database.insert_exchange(&old_exchange).unwrap();
+        harness.database.insert_exchange(&new_exchange).unwrap();
+        let collection = Collection {
+            recipes: indexmap! {
+                recipe1.id.clone() => recipe1.clone(),
+                recipe2.id.clone() => recipe2.clone(),
+            }
+            .into(),
+            profiles: indexmap! {profile.id.clone() => profile},
+            ..Collection::default()
+        };
+        let mut component = TestComponent::new(
+            harness,
+            Root::new(&collection, &Default::default()),
+            (),
+        );
+        assert_eq!(
+            component.data().primary_view.data().selected_recipe_id(),
+            Some(&recipe1.id)
+        );
+
+        component
+            .update_draw(Event::HttpSelectRequest(Some(old_exchange.id)))
+            .assert_empty();
+        assert_eq!(
+            component.data().primary_view.data().selected_recipe_id(),
+            Some(&recipe2.id)
+        );
+        // The requested exchange wins over the recipe's latest
+        assert_eq!(
+            component.data().selected_request(),
+            Some(&RequestState::Response {
+                exchange: old_exchange
+            })
+        );
+    }
 }
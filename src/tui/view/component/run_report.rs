@@ -0,0 +1,107 @@
+//! Modal summarizing a folder run
+
+use crate::{
+    collection::RecipeId,
+    http::{RunOutcome, RunResult},
+    tui::{
+        context::TuiContext,
+        view::{
+            common::{
+                modal::{IntoModal, Modal},
+                table::Table,
+            },
+            draw::{Draw, DrawMetadata, Generate},
+            event::EventHandler,
+        },
+    },
+};
+use itertools::Itertools;
+use ratatui::{
+    layout::Constraint,
+    text::{Line, Span},
+    widgets::Row,
+    Frame,
+};
+
+/// Show the outcome of each recipe in a folder run
+#[derive(Debug)]
+pub struct RunReportModal {
+    folder_id: RecipeId,
+    results: Vec<RunResult>,
+}
+
+impl RunReportModal {
+    pub fn new(folder_id: RecipeId, results: Vec<RunResult>) -> Self {
+        Self { folder_id, results }
+    }
+}
+
+impl Modal for RunReportModal {
+    fn title(&self) -> Line<'_> {
+        let passed = self.results.iter().filter(|r| r.is_success()).count();
+        let skipped = self
+            .results
+            .iter()
+            .filter(|result| matches!(result.outcome, RunOutcome::Skipped))
+            .count();
+        format!(
+            "Run `{}` | {passed} passed, {} failed, {skipped} skipped",
+            self.folder_id,
+            self.results.len() - passed - skipped
+        )
+        .into()
+    }
+
+    fn dimensions(&self) -> (Constraint, Constraint) {
+        (
+            Constraint::Percentage(80),
+            // Leave room for the header row
+            Constraint::Length(self.results.len().min(20) as u16 + 1),
+        )
+    }
+}
+
+impl EventHandler for RunReportModal {}
+
+impl Draw for RunReportModal {
+    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+        let styles = &TuiContext::get().styles;
+        let table = Table {
+            header: Some(["Recipe", "Duration", "Status"]),
+            rows: self
+                .results
+                .iter()
+                .map(|result| {
+                    let status: Span = match &result.outcome {
+                        RunOutcome::Response(status) => status.generate(),
+                        RunOutcome::Error(_) => Span::styled(
+                            result.outcome.to_string(),
+                            styles.text.error,
+                        ),
+                        RunOutcome::Skipped => "Skipped".into(),
+                    };
+                    Row::new([
+                        result.recipe_id.to_string().into(),
+                        result.duration.generate(),
+                        status,
+                    ])
+                })
+                .collect_vec(),
+            column_widths: &[
+                Constraint::Percentage(30),
+                Constraint::Length(10),
+                Constraint::Min(0),
+            ],
+            ..Default::default()
+        };
+        frame.render_widget(table.generate(), metadata.area());
+    }
+}
+
+impl IntoModal for (RecipeId, Vec<RunResult>) {
+    type Target = RunReportModal;
+
+    fn into_modal(self) -> Self::Target {
+        RunReportModal::new(self.0, self.1)
+    }
+}
@@ -0,0 +1,237 @@
+//! Modal listing requests that are scheduled to be sent later
+
+use crate::{
+    tui::{
+        context::TuiContext,
+        input::Action,
+        message::{Message, ScheduleId, ScheduledRequest},
+        view::{
+            common::{
+                list::List,
+                modal::{IntoModal, Modal},
+            },
+            component::Component,
+            draw::{Draw, DrawMetadata, Generate},
+            event::{Event, EventHandler, Update},
+            state::select::SelectState,
+            ViewContext,
+        },
+    },
+    util::format_duration,
+};
+use chrono::Local;
+use ratatui::{
+    layout::Constraint,
+    text::{Line, Span},
+    Frame,
+};
+
+/// Every request that's scheduled but hasn't been sent yet, soonest first.
+/// Schedules can be cancelled from here.
+#[derive(Debug)]
+pub struct ScheduledRequestsModal {
+    select: Component<SelectState<ScheduledRequest>>,
+}
+
+impl ScheduledRequestsModal {
+    pub fn new(mut requests: Vec<ScheduledRequest>) -> Self {
+        requests.sort_by_key(|request| request.send_at);
+        Self {
+            select: build_select(requests, None),
+        }
+    }
+
+    /// Cancel the highlighted schedule. The row is removed immediately, rather
+    /// than waiting for a new list from the parent
+    fn cancel_selected(&mut self) {
+        let Some(id) = self.select.data().selected().map(|request| request.id)
+        else {
+            return;
+        };
+        ViewContext::send_message(Message::ScheduleCancel(id));
+        let index = self.select.data().selected_index();
+        let requests: Vec<ScheduledRequest> = self
+            .select
+            .data()
+            .items()
+            .iter()
+            .filter(|request| request.id != id)
+            .cloned()
+            .collect();
+        // Keep the cursor in about the same spot
+        let selected = index
+            .and_then(|index| requests.get(index).or(requests.last()))
+            .map(|request| request.id);
+        self.select = build_select(requests, selected);
+    }
+}
+
+fn build_select(
+    requests: Vec<ScheduledRequest>,
+    selected: Option<ScheduleId>,
+) -> Component<SelectState<ScheduledRequest>> {
+    SelectState::builder(requests)
+        .preselect_opt(selected.as_ref())
+        .build()
+        .into()
+}
+
+impl Modal for ScheduledRequestsModal {
+    fn title(&self) -> Line<'_> {
+        let input_engine = &TuiContext::get().input_engine;
+        format!(
+            "Scheduled Requests | {}",
+            input_engine.add_hint("Cancel", Action::CancelRequest)
+        )
+        .into()
+    }
+
+    fn dimensions(&self) -> (Constraint, Constraint) {
+        (
+            Constraint::Length(80),
+            Constraint::Length(
+                self.select.data().items().len().clamp(1, 20) as u16
+            ),
+        )
+    }
+}
+
+impl EventHandler for ScheduledRequestsModal {
+    fn update(&mut self, event: Event) -> Update {
+        match event {
+            Event::Input {
+                action: Some(Action::CancelRequest),
+                ..
+            } => self.cancel_selected(),
+            _ => return Update::Propagate(event),
+        }
+        Update::Consumed
+    }
+
+    fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
+        vec![self.select.as_child()]
+    }
+}
+
+impl Draw for ScheduledRequestsModal {
+    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+        if self.select.data().items().is_empty() {
+            frame.render_widget("No scheduled requests", metadata.area());
+        } else {
+            self.select.draw(
+                frame,
+                List::new(self.select.data().items()),
+                metadata.area(),
+                true,
+            );
+        }
+    }
+}
+
+impl Generate for &ScheduledRequest {
+    type Output<'this>
+        = Line<'this>
+    where
+        Self: 'this;
+
+    fn generate<'this>(self) -> Self::Output<'this>
+    where
+        Self: 'this,
+    {
+        let styles = &TuiContext::get().styles;
+        let remaining = (self.send_at - Local::now()).max(Default::default());
+        let profile = self
+            .request_config
+            .profile_id
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_else(|| "No profile".into());
+        vec![
+            self.send_at.format("%b %-d %H:%M:%S").to_string().into(),
+            format!("  in {:>8}  ", format_duration(&remaining)).into(),
+            Span::styled(
+                self.request_config.recipe_id.to_string(),
+                styles.text.primary,
+            ),
+            "  ".into(),
+            profile.into(),
+        ]
+        .into()
+    }
+}
+
+impl IntoModal for Vec<ScheduledRequest> {
+    type Target = ScheduledRequestsModal;
+
+    fn into_modal(self) -> Self::Target {
+        ScheduledRequestsModal::new(self)
+    }
+}
+
+/// Allow selection by ID
+impl PartialEq<ScheduledRequest> for ScheduleId {
+    fn eq(&self, other: &ScheduledRequest) -> bool {
+        *self == other.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::BuildOptions,
+        test_util::assert_matches,
+        tui::{
+            message::RequestConfig,
+            test_util::{harness, TestHarness},
+            view::test_util::TestComponent,
+        },
+    };
+    use chrono::Duration;
+    use crossterm::event::KeyCode;
+    use itertools::Itertools;
+    use rstest::rstest;
+
+    /// Schedules are sorted by time, and cancelling one removes its row
+    #[rstest]
+    fn test_cancel(harness: TestHarness) {
+        let now = Local::now();
+        let requests = [(1, 60), (2, 30), (3, 90)]
+            .into_iter()
+            .map(|(id, seconds)| ScheduledRequest {
+                id,
+                request_config: RequestConfig {
+                    profile_id: None,
+                    recipe_id: "recipe1".into(),
+                    options: BuildOptions::default(),
+                },
+                send_at: now + Duration::seconds(seconds),
+            })
+            .collect_vec();
+        let mut component =
+            TestComponent::new(harness, requests.into_modal(), ());
+        let ids = |component: &TestComponent<ScheduledRequestsModal, ()>| {
+            component
+                .data()
+                .select
+                .data()
+                .items()
+                .iter()
+                .map(|request| request.id)
+                .collect_vec()
+        };
+        assert_eq!(ids(&component), vec![2, 1, 3]);
+
+        component.send_key(KeyCode::Down).assert_empty();
+        component.send_key(KeyCode::Delete).assert_empty();
+        assert_matches!(
+            component.harness_mut().pop_message_now(),
+            Message::ScheduleCancel(1),
+        );
+        assert_eq!(ids(&component), vec![2, 3]);
+        assert_eq!(
+            component.data().select.data().selected().map(|r| r.id),
+            Some(3)
+        );
+    }
+}
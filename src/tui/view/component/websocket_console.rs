@@ -0,0 +1,290 @@
+//! Interactive console for WebSocket recipes
+
+use crate::{
+    collection::{ProfileId, RecipeId},
+    tui::{
+        context::TuiContext,
+        input::Action,
+        view::{
+            common::{actions::ActionsModal, text_box::TextBox},
+            draw::{Draw, DrawMetadata, Generate, ToStringGenerate},
+            event::{Event, EventHandler, Update},
+            state::StateCell,
+            Component, ViewContext,
+        },
+    },
+    util::ResultExt,
+    ws::{
+        ConnectionStatus, Direction, MessageContent, WebSocketConnection,
+        WebSocketExchange, WebSocketMessage,
+    },
+};
+use chrono::Local;
+use derive_more::Display;
+use itertools::Itertools;
+use ratatui::{
+    layout::{Alignment, Constraint, Layout},
+    text::{Line, Span},
+    widgets::Paragraph,
+    Frame,
+};
+use strum::{EnumCount, EnumIter};
+
+/// Message log and input for a WebSocket recipe. The connection itself is
+/// owned further up the tree, so sending and menu actions are emitted as local
+/// events for the parent to handle.
+#[derive(Debug)]
+pub struct WebSocketConsole {
+    /// Is the user typing in the input box?
+    input_focused: bool,
+    input: Component<TextBox>,
+    /// Most recent conversation from history, shown when there hasn't been a
+    /// connection for this profile+recipe during this session
+    history: StateCell<HistoryKey, Option<WebSocketExchange>>,
+}
+
+pub struct WebSocketConsoleProps<'a> {
+    pub profile_id: Option<&'a ProfileId>,
+    pub recipe_id: &'a RecipeId,
+    /// Connection for the selected profile+recipe, if one has been opened
+    pub connection: Option<&'a WebSocketConnection>,
+}
+
+#[derive(Debug, PartialEq)]
+struct HistoryKey {
+    profile_id: Option<ProfileId>,
+    recipe_id: RecipeId,
+}
+
+/// Emitted when the user submits a message to be sent over the connection
+#[derive(Debug)]
+pub struct WebSocketSend(pub String);
+
+/// Items in the actions popup menu
+#[derive(Copy, Clone, Debug, Display, EnumCount, EnumIter, PartialEq)]
+pub enum WebSocketMenuAction {
+    #[display("Connect")]
+    Connect,
+    #[display("Disconnect")]
+    Disconnect,
+}
+
+impl ToStringGenerate for WebSocketMenuAction {}
+
+/// All callback events from the input box
+#[derive(Debug)]
+enum InputCallback {
+    Focus,
+    Cancel,
+    Submit(String),
+}
+
+impl Default for WebSocketConsole {
+    fn default() -> Self {
+        let input = TextBox::default()
+            .with_placeholder("Type a message and press Enter to send")
+            .with_on_click(|_| {
+                ViewContext::push_event(Event::new_local(InputCallback::Focus))
+            })
+            .with_on_cancel(|_| {
+                ViewContext::push_event(Event::new_local(InputCallback::Cancel))
+            })
+            .with_on_submit(|text_box| {
+                ViewContext::push_event(Event::new_local(
+                    InputCallback::Submit(text_box.text().to_owned()),
+                ))
+            });
+        Self {
+            input_focused: false,
+            input: input.into(),
+            history: Default::default(),
+        }
+    }
+}
+
+impl WebSocketConsole {
+    /// Start typing a message
+    pub fn focus_input(&mut self) {
+        self.input_focused = true;
+    }
+}
+
+impl EventHandler for WebSocketConsole {
+    fn update(&mut self, event: Event) -> Update {
+        if let Some(callback) = event.local::<InputCallback>() {
+            match callback {
+                InputCallback::Focus => self.input_focused = true,
+                InputCallback::Cancel => self.input_focused = false,
+                // Clearing the text triggers another (empty) submission, so
+                // ignore those. Stay focused so the user can keep typing
+                InputCallback::Submit(text) if !text.is_empty() => {
+                    ViewContext::push_event(Event::new_local(WebSocketSend(
+                        text.clone(),
+                    )));
+                    self.input.data_mut().set_text(String::new());
+                }
+                InputCallback::Submit(_) => {}
+            }
+            return Update::Consumed;
+        }
+
+        match event.action() {
+            Some(Action::OpenActions) => ViewContext::open_modal_default::<
+                ActionsModal<WebSocketMenuAction>,
+            >(),
+            _ => return Update::Propagate(event),
+        }
+        Update::Consumed
+    }
+
+    fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
+        vec![self.input.as_child()]
+    }
+}
+
+impl<'a> Draw<WebSocketConsoleProps<'a>> for WebSocketConsole {
+    fn draw(
+        &self,
+        frame: &mut Frame,
+        props: WebSocketConsoleProps<'a>,
+        metadata: DrawMetadata,
+    ) {
+        let context = TuiContext::get();
+        let styles = &context.styles;
+        let [status_area, messages_area, input_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(metadata.area());
+
+        // Only render what fits, to avoid cloning the whole log
+        let visible_messages = messages_area.height as usize;
+        let connect_hint = || {
+            Span::from(context.input_engine.add_hint("Connect", Action::Submit))
+        };
+        let mut is_open = false;
+        let (status, messages, message_count) = if let Some(connection) =
+            props.connection
+        {
+            let state = connection.state();
+            let status = match &state.status {
+                ConnectionStatus::Connecting => "Connecting...".into(),
+                ConnectionStatus::Open => {
+                    is_open = true;
+                    Line::from(vec![
+                        Span::styled("Connected", styles.text.primary),
+                        " / ".into(),
+                        state.start_time.generate(),
+                    ])
+                }
+                ConnectionStatus::Closed { code, reason } => {
+                    let mut line = Line::from("Closed");
+                    if let Some(code) = code {
+                        line.push_span(format!(" ({code})"));
+                    }
+                    if !reason.is_empty() {
+                        line.push_span(format!(": {reason}"));
+                    }
+                    line.push_span(" / ");
+                    line.push_span(connect_hint());
+                    line
+                }
+                ConnectionStatus::Failed(error) => Line::from(vec![
+                    Span::styled(error.clone(), styles.text.error),
+                    " / ".into(),
+                    connect_hint(),
+                ]),
+            };
+            let messages = message_lines(&state.messages, visible_messages);
+            (status, messages, state.messages.len())
+        } else {
+            let history = self.history.get_or_update(
+                HistoryKey {
+                    profile_id: props.profile_id.cloned(),
+                    recipe_id: props.recipe_id.clone(),
+                },
+                || {
+                    ViewContext::with_database(|database| {
+                        database.get_latest_websocket_exchange(
+                            props.profile_id,
+                            props.recipe_id,
+                        )
+                    })
+                    .reported(&ViewContext::messages_tx())
+                    .flatten()
+                },
+            );
+            match history.as_ref() {
+                Some(exchange) => (
+                    Line::from(vec![
+                        "Last connected ".into(),
+                        exchange.start_time.generate(),
+                        " / ".into(),
+                        connect_hint(),
+                    ]),
+                    message_lines(&exchange.messages, visible_messages),
+                    exchange.messages.len(),
+                ),
+                None => (
+                    Line::from(vec!["Not connected / ".into(), connect_hint()]),
+                    Vec::new(),
+                    0,
+                ),
+            }
+        };
+
+        frame.render_widget(status, status_area);
+        frame.render_widget(
+            Line::from(format!("{message_count} messages"))
+                .alignment(Alignment::Right),
+            status_area,
+        );
+        frame.render_widget(Paragraph::new(messages), messages_area);
+        // Input is only useful while the connection is open
+        if is_open {
+            self.input.draw(
+                frame,
+                (),
+                input_area,
+                self.input_focused && metadata.has_focus(),
+            );
+        }
+    }
+}
+
+/// Render the last `count` messages, one per line
+fn message_lines(
+    messages: &[WebSocketMessage],
+    count: usize,
+) -> Vec<Line<'static>> {
+    let styles = &TuiContext::get().styles;
+    messages[messages.len().saturating_sub(count)..]
+        .iter()
+        .map(|message| {
+            let arrow = match message.direction {
+                Direction::Sent => Span::styled("→ ", styles.text.primary),
+                Direction::Received => {
+                    Span::styled("← ", styles.text.highlight)
+                }
+            };
+            let content = match &message.content {
+                // Keep each message on one line
+                MessageContent::Text(text) => text.lines().join(" "),
+                MessageContent::Binary(data) => {
+                    format!("<binary {} bytes>", data.len())
+                }
+            };
+            Line::from(vec![
+                message
+                    .time
+                    .with_timezone(&Local)
+                    .format("%H:%M:%S ")
+                    .to_string()
+                    .into(),
+                arrow,
+                content.into(),
+            ])
+        })
+        .collect()
+}
@@ -11,6 +11,7 @@ use crate::{
             Component,
         },
     },
+    ws::WebSocketConnection,
 };
 use std::{any::Any, collections::VecDeque, fmt::Debug};
 use tracing::trace;
@@ -111,6 +112,10 @@ pub enum Event {
     /// Update the state of an in-progress HTTP request
     HttpSetState(RequestState),
 
+    /// Hand the view a WebSocket connection to display. This replaces any
+    /// previous connection for the same profile+recipe
+    WebSocketSetConnection(WebSocketConnection),
+
     /// Show a modal to the user
     OpenModal {
         #[debug(skip)]
@@ -8,14 +8,18 @@ pub mod select;
 use crate::{
     collection::{ProfileId, RecipeId},
     http::{
-        Exchange, ExchangeSummary, RequestBuildError, RequestError, RequestId,
-        RequestRecord,
+        EventStream, Exchange, ExchangeSummary, RequestBuildError,
+        RequestError, RequestId, RequestRecord,
     },
 };
 use bytesize::ByteSize;
 use chrono::{DateTime, Duration, Utc};
 use derive_more::Deref;
-use reqwest::StatusCode;
+use itertools::Itertools;
+use reqwest::{
+    header::{self, HeaderMap},
+    StatusCode, Version,
+};
 use std::{
     cell::{Ref, RefCell},
     sync::Arc,
@@ -70,12 +74,31 @@ impl<K, V> StateCell<K, V> {
         .ok()
     }
 
+    /// Get a mutable reference to the state value, if it was created with the
+    /// given key. Returns `None` if the state is uninitialized or stale.
+    pub fn get_mut_for(&mut self, key: &K) -> Option<&mut V>
+    where
+        K: PartialEq,
+    {
+        self.state
+            .get_mut()
+            .as_mut()
+            .filter(|(state_key, _)| state_key == key)
+            .map(|(_, value)| value)
+    }
+
     /// Get a mutable reference to the state value. This will never panic
     /// because `&mut self` guarantees exclusive access. Returns `None` iff
     /// the state cell is uninitialized.
     pub fn get_mut(&mut self) -> Option<&mut V> {
         self.state.get_mut().as_mut().map(|state| &mut state.1)
     }
+
+    /// Throw away the state value, so it will be rebuilt on the next call to
+    /// [Self::get_or_update]
+    pub fn reset(&mut self) {
+        *self.state.get_mut() = None;
+    }
 }
 
 /// Derive impl applies unnecessary bound on the generic parameter
@@ -113,6 +136,13 @@ pub enum RequestState {
         /// pointer to the request as well
         request: Arc<RequestRecord>,
         start_time: DateTime<Utc>,
+        /// Is the request waiting for a free slot, because too many requests
+        /// are already in flight? See
+        /// [Config::max_concurrent_requests](crate::config::Config::max_concurrent_requests)
+        queued: bool,
+        /// If the response is an event stream, this is populated once the
+        /// headers are received, and fills up as events arrive
+        stream: Option<Arc<EventStream>>,
     },
 
     /// A resolved HTTP response, with all content loaded and ready to be
@@ -133,15 +163,27 @@ pub struct RequestMetadata {
     /// Elapsed time for the active request. If pending, this is a running
     /// total. Otherwise end time - start time.
     pub duration: Duration,
+    /// Number of failed attempts before the final one
+    pub retries: usize,
 }
 
 /// Metadata derived from a response. This is only available for requests that
 /// have completed successfully.
 #[derive(Debug)]
 pub struct ResponseMetadata {
+    /// HTTP version negotiated with the server
+    pub version: Version,
     pub status: StatusCode,
     /// Size of the response *body*
     pub size: ByteSize,
+    /// Size of the body as transferred, if it was decompressed
+    pub encoded_size: Option<ByteSize>,
+    /// Was the body reused from a previous response? See
+    /// [ResponseRecord::from_cache](crate::http::ResponseRecord::from_cache)
+    pub from_cache: bool,
+    /// MIME type from the `Content-Type` header, without parameters such as
+    /// `charset`
+    pub content_type: Option<String>,
 }
 
 impl RequestState {
@@ -189,36 +231,49 @@ impl RequestState {
             Self::Loading { start_time, .. } => Some(RequestMetadata {
                 start_time: *start_time,
                 duration: Utc::now() - start_time,
+                retries: 0,
             }),
             Self::Response { exchange, .. } => Some(RequestMetadata {
                 start_time: exchange.start_time,
                 duration: exchange.duration(),
+                retries: exchange.retries.len(),
             }),
             Self::RequestError { error } => Some(RequestMetadata {
                 start_time: error.start_time,
                 duration: error.end_time - error.start_time,
+                retries: error.retries.len(),
             }),
         }
     }
 
     /// Get metadata about the request. Return `None` if the response hasn't
-    /// been received, or the request failed.
+    /// been received, or the request failed. For an in-progress event stream,
+    /// the size is a running total.
     pub fn response_metadata(&self) -> Option<ResponseMetadata> {
-        if let RequestState::Response { exchange } = self {
-            Some(ResponseMetadata {
+        match self {
+            Self::Loading {
+                stream: Some(stream),
+                ..
+            } => Some(ResponseMetadata {
+                version: stream.version,
+                status: stream.status,
+                size: ByteSize::b(stream.content().size),
+                encoded_size: None,
+                from_cache: false,
+                content_type: mime_type(&stream.headers),
+            }),
+            Self::Response { exchange } => Some(ResponseMetadata {
+                version: exchange.response.version,
                 status: exchange.response.status,
                 size: exchange.response.body.size(),
-            })
-        } else {
-            None
+                encoded_size: exchange.response.encoded_size,
+                from_cache: exchange.response.from_cache,
+                content_type: mime_type(&exchange.response.headers),
+            }),
+            _ => None,
         }
     }
 
-    /// Is the initial stage in a request life cycle?
-    pub fn is_initial(&self) -> bool {
-        matches!(self, Self::Building { .. })
-    }
-
     /// Create a loading state with the current timestamp. This will generally
     /// be slightly off from when the request was actually launched, but it
     /// shouldn't matter. See [crate::http::RequestTicket::send] for why it
@@ -227,40 +282,88 @@ impl RequestState {
         Self::Loading {
             request,
             start_time: Utc::now(),
+            queued: false,
+            stream: None,
         }
     }
 
-    /// Create a request state from a completed response. This is **expensive**,
-    /// don't call it unless you need the value.
+    /// Create a loading state for a request that's waiting for a free slot
+    /// before it can be sent. Once it gets one, it moves to [Self::loading]
+    pub fn queued(request: Arc<RequestRecord>) -> Self {
+        Self::Loading {
+            request,
+            start_time: Utc::now(),
+            queued: true,
+            stream: None,
+        }
+    }
+
+    /// Is the request still being built or sent? These are the states that
+    /// can be cancelled
+    pub fn is_in_progress(&self) -> bool {
+        matches!(self, Self::Building { .. } | Self::Loading { .. })
+    }
+
+    /// Create a loading state for an event stream that's being received. The
+    /// start time comes from the stream, so it's accurate (unlike
+    /// [Self::loading])
+    pub fn streaming(
+        request: Arc<RequestRecord>,
+        stream: Arc<EventStream>,
+    ) -> Self {
+        Self::Loading {
+            request,
+            start_time: stream.start_time,
+            queued: false,
+            stream: Some(stream),
+        }
+    }
+
+    /// Create a request state from a completed response. The body isn't
+    /// parsed here; the body view does that in a background task when it's
+    /// shown, so large bodies don't block the main thread.
     pub fn response(exchange: Exchange) -> Self {
-        // Pre-parse the body so the view doesn't have to do it. We're in the
-        // main thread still here though so large bodies may take a while. Maybe
-        // we want to punt this into a separate task?
-        exchange.response.parse_body();
         Self::Response { exchange }
     }
 }
 
+/// Get the MIME type from a `Content-Type` header, e.g. `application/json` from
+/// `application/json; charset=utf-8`
+fn mime_type(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+    let mime = value.split(';').next()?.trim();
+    (!mime.is_empty()).then(|| mime.to_owned())
+}
+
 /// A simplified version of [RequestState], which only stores metadata. This is
 /// useful when you want to show a list of requests and don't need the entire
 /// request/response data for each one.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum RequestStateSummary {
     Building {
         id: RequestId,
+        profile_id: Option<ProfileId>,
+        recipe_id: RecipeId,
         start_time: DateTime<Utc>,
     },
     BuildError {
         id: RequestId,
+        profile_id: Option<ProfileId>,
+        recipe_id: RecipeId,
         time: DateTime<Utc>,
     },
     Loading {
         id: RequestId,
+        profile_id: Option<ProfileId>,
+        recipe_id: RecipeId,
         start_time: DateTime<Utc>,
+        queued: bool,
     },
     Response(ExchangeSummary),
     RequestError {
         id: RequestId,
+        profile_id: Option<ProfileId>,
+        recipe_id: RecipeId,
         time: DateTime<Utc>,
     },
 }
@@ -291,32 +394,74 @@ impl RequestStateSummary {
             Self::Response(exchange) => exchange.start_time,
         }
     }
+
+    /// The profile that was selected when the request was built
+    pub fn profile_id(&self) -> Option<&ProfileId> {
+        match self {
+            Self::Building { profile_id, .. }
+            | Self::BuildError { profile_id, .. }
+            | Self::Loading { profile_id, .. }
+            | Self::RequestError { profile_id, .. } => profile_id.as_ref(),
+            Self::Response(exchange) => exchange.profile_id.as_ref(),
+        }
+    }
+
+    /// The recipe that the request was rendered from
+    pub fn recipe_id(&self) -> &RecipeId {
+        match self {
+            Self::Building { recipe_id, .. }
+            | Self::BuildError { recipe_id, .. }
+            | Self::Loading { recipe_id, .. }
+            | Self::RequestError { recipe_id, .. } => recipe_id,
+            Self::Response(exchange) => &exchange.recipe_id,
+        }
+    }
+
+    /// Elapsed time for the request. `None` if it hasn't finished
+    pub fn duration(&self) -> Option<Duration> {
+        match self {
+            Self::Response(exchange) => Some(exchange.duration()),
+            _ => None,
+        }
+    }
 }
 
 impl From<&RequestState> for RequestStateSummary {
     fn from(state: &RequestState) -> Self {
+        let profile_id = state.profile_id().cloned();
+        let recipe_id = state.recipe_id().clone();
         match state {
             RequestState::Building { id, start_time, .. } => Self::Building {
                 id: *id,
+                profile_id,
+                recipe_id,
                 start_time: *start_time,
             },
             RequestState::BuildError { error } => Self::BuildError {
                 id: error.id,
+                profile_id,
+                recipe_id,
                 time: error.time,
             },
             RequestState::Loading {
                 request,
                 start_time,
+                queued,
                 ..
             } => Self::Loading {
                 id: request.id,
+                profile_id,
+                recipe_id,
                 start_time: *start_time,
+                queued: *queued,
             },
             RequestState::Response { exchange } => {
                 Self::Response(exchange.into())
             }
             RequestState::RequestError { error } => Self::RequestError {
                 id: error.request.id,
+                profile_id,
+                recipe_id,
                 time: error.start_time,
             },
         }
@@ -326,10 +471,23 @@ impl From<&RequestState> for RequestStateSummary {
 /// A notification is an ephemeral informational message generated by some async
 /// action. It doesn't grab focus, but will be useful to the user nonetheless.
 /// It should be shown for a short period of time, then disappear on its own.
-#[derive(Debug)]
+///
+/// Every notification is also kept in the message log, so it can be read after
+/// it disappears.
+#[derive(Clone, Debug)]
 pub struct Notification {
     pub message: String,
     pub timestamp: DateTime<Utc>,
+    pub level: NotificationLevel,
+}
+
+/// How serious is a notification?
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum NotificationLevel {
+    #[default]
+    Info,
+    /// An error that was shown to the user in a modal
+    Error,
 }
 
 impl Notification {
@@ -337,6 +495,54 @@ impl Notification {
         Self {
             message,
             timestamp: Utc::now(),
+            level: NotificationLevel::Info,
         }
     }
+
+    /// Log an error. The message includes every error in the chain, one per
+    /// line
+    pub fn error(error: &anyhow::Error) -> Self {
+        Self {
+            message: error.chain().map(ToString::to_string).join("\n"),
+            timestamp: Utc::now(),
+            level: NotificationLevel::Error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{http::ResponseRecord, test_util::Factory};
+    use reqwest::header::HeaderValue;
+    use rstest::rstest;
+
+    /// Content type in the response metadata omits parameters
+    #[rstest]
+    #[case::none(None, None)]
+    #[case::plain(Some("application/json"), Some("application/json"))]
+    #[case::params(Some("text/html; charset=utf-8"), Some("text/html"))]
+    #[case::empty(Some(""), None)]
+    fn test_response_metadata_content_type(
+        #[case] header: Option<&'static str>,
+        #[case] expected: Option<&str>,
+    ) {
+        let mut headers = HeaderMap::new();
+        if let Some(header) = header {
+            headers
+                .insert(header::CONTENT_TYPE, HeaderValue::from_static(header));
+        }
+        let exchange = Exchange {
+            response: ResponseRecord {
+                headers,
+                ..ResponseRecord::factory(())
+            }
+            .into(),
+            ..Exchange::factory(())
+        };
+        let metadata = RequestState::Response { exchange }
+            .response_metadata()
+            .unwrap();
+        assert_eq!(metadata.content_type.as_deref(), expected);
+    }
 }
@@ -88,6 +88,8 @@ pub enum PersistentKey {
     ExchangeTab,
     /// Which pane (if any) is fullscreened?
     FullscreenMode,
+    /// Arrangement and sizes of the primary panes
+    PaneLayout,
     /// Selected profile in the list
     ProfileId,
     /// Selected recipe/folder in the tree
@@ -96,6 +98,9 @@ pub enum PersistentKey {
     RequestId,
     /// Set of folders that are collapsed in the recipe tree
     RecipeCollapsed,
+    /// Custom order of the children of a folder in the recipe tree. `None` is
+    /// the top level of the tree
+    RecipeOrder(Option<RecipeId>),
     /// Selected tab in the recipe pane
     RecipeTab,
     /// Selected query param, per recipe. Value is the query param name
@@ -108,6 +113,20 @@ pub enum PersistentKey {
     RecipeHeader { recipe: RecipeId, header: String },
     /// Response body JSONPath query (**not** related to query params)
     ResponseBodyQuery(RecipeId),
+    /// Word wrap and line number settings for a body view
+    BodyToggles(BodyView),
+}
+
+/// A view that displays a request or response body, with its own display
+/// settings
+#[derive(Copy, Clone, Debug, Serialize)]
+pub enum BodyView {
+    /// Body of the selected recipe, in the recipe pane
+    Recipe,
+    /// Body of a sent request
+    Request,
+    /// Body of a received response
+    Response,
 }
 
 /// A value type that can be persisted to the database
@@ -1,6 +1,6 @@
 use crate::{
     collection::{ProfileId, RecipeId},
-    http::RequestId,
+    http::{ExchangeSummary, RequestId},
     tui::view::{
         context::ViewContext, state::RequestStateSummary, RequestState,
     },
@@ -91,15 +91,34 @@ impl RequestStore {
         let loaded = ViewContext::with_database(|database| {
             database.get_all_requests(profile_id, recipe_id)
         })?;
+        Ok(self.merge_summaries(loaded, move |state| {
+            state.profile_id() == profile_id && state.recipe_id() == recipe_id
+        }))
+    }
+
+    /// Load all historical requests for the collection, across every profile
+    /// and recipe. Like [Self::load_summaries], this includes in-progress
+    /// requests and is sorted by start time, descending.
+    pub fn load_all_summaries(
+        &self,
+    ) -> anyhow::Result<impl '_ + Iterator<Item = RequestStateSummary>> {
+        let loaded = ViewContext::with_database(|database| {
+            database.get_collection_requests()
+        })?;
+        Ok(self.merge_summaries(loaded, |_| true))
+    }
 
+    /// Combine summaries loaded from the DB with the in-memory requests that
+    /// match a filter
+    fn merge_summaries<'a>(
+        &'a self,
+        loaded: Vec<ExchangeSummary>,
+        filter: impl 'a + Fn(&RequestState) -> bool,
+    ) -> impl 'a + Iterator<Item = RequestStateSummary> {
         // Find what we have in memory already
-        let iter = self
-            .requests
+        self.requests
             .values()
-            .filter(move |state| {
-                state.profile_id() == profile_id
-                    && state.recipe_id() == recipe_id
-            })
+            .filter(move |state| filter(state))
             .map(RequestStateSummary::from)
             // Add what we loaded from the DB
             .chain(loaded.into_iter().map(RequestStateSummary::Response))
@@ -107,8 +126,7 @@ impl RequestStore {
             .sorted_by_key(RequestStateSummary::time)
             .rev()
             // De-duplicate double-loaded requests
-            .unique_by(RequestStateSummary::id);
-        Ok(iter)
+            .unique_by(RequestStateSummary::id)
     }
 }
 
@@ -157,6 +175,8 @@ mod tests {
         assert!(!store.update(RequestState::Loading {
             request: Arc::clone(&exchange.request),
             start_time: exchange.start_time,
+            queued: false,
+            stream: None,
         }));
         assert_matches!(store.get(id), Some(RequestState::Loading { .. }));
 
@@ -289,6 +309,8 @@ mod tests {
         store.update(RequestState::Loading {
             request: request.into(),
             start_time: Utc::now(),
+            queued: false,
+            stream: None,
         });
 
         let request = RequestRecord::factory((
@@ -300,8 +322,12 @@ mod tests {
             error: RequestError {
                 error: anyhow!("oh no!"),
                 request: request.into(),
+                retries: Vec::new(),
                 start_time: Utc::now(),
                 end_time: Utc::now(),
+                partial_response: None,
+                resume_path: None,
+                options: Default::default(),
             },
         });
 
@@ -359,6 +385,44 @@ mod tests {
         );
     }
 
+    /// Loading the entire collection includes every profile and recipe
+    #[rstest]
+    fn test_load_all_summaries(harness: TestHarness) {
+        let exchanges = (0..3)
+            .map(|_| create_exchange(&harness, None, None))
+            .collect_vec();
+        let exchange_ids =
+            exchanges.iter().map(|exchange| exchange.id).collect_vec();
+
+        let mut store = RequestStore::default();
+        // Pre-load one from the DB, to make sure it gets de-duped
+        store.update(RequestState::response(
+            exchanges.into_iter().next().unwrap(),
+        ));
+        let building_id = RequestId::new();
+        store.update(RequestState::Building {
+            id: building_id,
+            start_time: Utc::now(),
+            profile_id: None,
+            recipe_id: RecipeId::factory(()),
+        });
+
+        let ids = store
+            .load_all_summaries()
+            .unwrap()
+            .map(|summary| summary.id())
+            .collect_vec();
+        assert_eq!(
+            ids,
+            [
+                building_id,
+                exchange_ids[2],
+                exchange_ids[1],
+                exchange_ids[0]
+            ]
+        );
+    }
+
     /// Create a exchange with the given profile+recipe ID (or random if
     /// None), and insert it into the DB
     fn create_exchange(
@@ -6,11 +6,17 @@ use crate::tui::{
         state::persistence::{Persistable, PersistentContainer},
     },
 };
+use crossterm::event::MouseEvent;
 use ratatui::{
+    layout::{Position, Rect},
     widgets::{ListState, StatefulWidget, TableState},
     Frame,
 };
-use std::{cell::RefCell, fmt::Debug, marker::PhantomData};
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Debug,
+    marker::PhantomData,
+};
 
 /// State manager for a dynamic list of items.
 ///
@@ -27,6 +33,8 @@ where
     /// rendering without a mutable reference.
     state: RefCell<State>,
     items: Vec<Item>,
+    /// Area the list was last drawn to, for mapping clicks to items
+    area: Cell<Rect>,
     /// Callback when an item is highlighted
     #[debug(skip)]
     on_select: Option<Callback<Item>>,
@@ -102,6 +110,7 @@ impl<Item, State> SelectStateBuilder<Item, State> {
         let mut select = SelectState {
             state: RefCell::default(),
             items: self.items,
+            area: Cell::default(),
             on_select: self.on_select,
             on_submit: self.on_submit,
         };
@@ -134,6 +143,12 @@ impl<Item, State: SelectStateData> SelectState<Item, State> {
         &self.items
     }
 
+    /// Get all items in the list, mutably. The list can't be resized, so the
+    /// selection stays valid
+    pub fn items_mut(&mut self) -> &mut [Item] {
+        &mut self.items
+    }
+
     /// Get the index of the currently selected item (if any)
     pub fn selected_index(&self) -> Option<usize> {
         self.state.borrow().selected()
@@ -144,6 +159,11 @@ impl<Item, State: SelectStateData> SelectState<Item, State> {
         self.items.get(self.state.borrow().selected()?)
     }
 
+    /// Get the currently selected item (if any), mutably
+    pub fn selected_mut(&mut self) -> Option<&mut Item> {
+        self.items.get_mut(self.state.get_mut().selected()?)
+    }
+
     /// Select an item by value. Context is required for callbacks. Generally
     /// the given value will be the type `Item`, but it could be anything that
     /// compares to `Item` (e.g. an ID type).
@@ -204,6 +224,25 @@ impl<Item, State: SelectStateData> SelectState<Item, State> {
         }
     }
 
+    /// Select the item under a mouse click, if any
+    fn click(&mut self, mouse_event: &MouseEvent) {
+        let area = self.area.get();
+        let position = Position {
+            x: mouse_event.column,
+            y: mouse_event.row,
+        };
+        if !area.contains(position) {
+            return;
+        }
+        let row = (position.y - area.y).checked_sub(State::HEADER_HEIGHT);
+        let index = row
+            .map(|row| self.state.get_mut().offset() + row as usize)
+            .filter(|index| *index < self.items.len());
+        if let Some(index) = index {
+            self.select_index(index);
+        }
+    }
+
     /// Kind-agnostic helper for the selected item
     fn selected_opt(&self) -> Option<&Item> {
         self.items.get(self.state.borrow().selected()?)
@@ -235,6 +274,18 @@ where
         match action {
             Action::Up | Action::ScrollUp => self.previous(),
             Action::Down | Action::ScrollDown => self.next(),
+            // Select the clicked item, but let the parent see the click too,
+            // so it can grab focus
+            Action::LeftClick => {
+                if let Event::Input {
+                    event: crossterm::event::Event::Mouse(mouse_event),
+                    ..
+                } = &event
+                {
+                    self.click(mouse_event);
+                }
+                return Update::Propagate(event);
+            }
             Action::Submit => {
                 // If we have an on_submit, our parent wants us to handle
                 // submit events so consume it even if nothing is selected
@@ -267,6 +318,7 @@ where
     W: StatefulWidget<State = State>,
 {
     fn draw(&self, frame: &mut Frame, props: W, metadata: DrawMetadata) {
+        self.area.set(metadata.area());
         frame.render_stateful_widget(
             props,
             metadata.area(),
@@ -299,6 +351,9 @@ where
 /// multiple state "backends" from Ratatui, to enable usage with different
 /// stateful widgets.
 pub trait SelectStateData: Default {
+    /// Number of rows drawn above the first item, e.g. a table header
+    const HEADER_HEIGHT: u16 = 0;
+
     /// Index of the selected element
     fn selected(&self) -> Option<usize>;
 
@@ -324,6 +379,9 @@ impl SelectStateData for ListState {
 }
 
 impl SelectStateData for TableState {
+    /// Every table in the view has a header row
+    const HEADER_HEIGHT: u16 = 1;
+
     fn selected(&self) -> Option<usize> {
         self.selected()
     }
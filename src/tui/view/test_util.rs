@@ -72,6 +72,12 @@ where
         self.component.data()
     }
 
+    /// Get a mutable reference to the wrapped component's inner data. Changes
+    /// won't be visible until the next draw
+    pub fn data_mut(&mut self) -> &mut T {
+        self.component.data_mut()
+    }
+
     /// Alias for
     /// [TestBackend::assert_buffer_lines](ratatui::backend::TestBackend::assert_buffer_lines)
     pub fn assert_buffer_lines<'a>(
@@ -84,6 +90,12 @@ where
             .assert_buffer_lines(expected)
     }
 
+    /// Draw again with the props from the last draw. Useful when state has
+    /// changed outside the event loop, e.g. a background task finished
+    pub fn redraw(&mut self) {
+        self.draw(None);
+    }
+
     /// Draw this component onto the terminal, using the entire terminal frame
     /// as the draw area. If props are given, use them for the draw. If not,
     /// use the same props from the last draw.
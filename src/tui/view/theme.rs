@@ -1,12 +1,114 @@
+use crate::util::{parse_yaml, paths::DataDirectory, ResultExt};
+use anyhow::{anyhow, Context};
 use ratatui::{
     style::{Color, Modifier, Style},
     widgets::BorderType,
 };
-use serde::{Deserialize, Serialize};
+use reqwest::StatusCode;
+use serde::{
+    de::{value::MapAccessDeserializer, MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use std::{fmt, fs, io};
+use tracing::info;
+
+/// Theme selection in the config. Either the name of a theme, or the theme
+/// itself defined inline
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum ThemeConfig {
+    /// A theme file in the `themes/` data directory, or a built-in theme
+    Named(String),
+    Inline(Theme),
+}
+
+impl ThemeConfig {
+    /// Name of the default built-in theme
+    const DEFAULT: &'static str = "dark";
+    /// Names of the built-in themes
+    const BUILT_IN: &'static [&'static str] = &["dark", "light", "solarized"];
+
+    /// Resolve the theme. For a named theme, a user theme file takes priority
+    /// over a built-in theme of the same name, so built-ins can be customized.
+    pub fn load(&self) -> anyhow::Result<Theme> {
+        let name = match self {
+            Self::Named(name) => name,
+            Self::Inline(theme) => return Ok(theme.clone()),
+        };
+
+        let path = DataDirectory::root()
+            .file(format!("themes/{name}.yml"))
+            .create_parent()?;
+        match fs::read(&path) {
+            Ok(bytes) => {
+                info!(?path, "Loading theme file");
+                return parse_yaml(&bytes)
+                    .context(format!("Error loading theme from {path:?}"))
+                    .traced();
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => {
+                return Err(anyhow::Error::from(error)
+                    .context(format!("Error loading theme from {path:?}")))
+            }
+        }
+
+        Theme::built_in(name).ok_or_else(|| {
+            anyhow!(
+                "Unknown theme `{name}`; expected one of {:?} or a theme file \
+                at {path:?}",
+                Self::BUILT_IN
+            )
+        })
+    }
+}
+
+// Manual impl instead of untagged, so errors within an inline theme are
+// reported instead of a generic "did not match any variant"
+impl<'de> Deserialize<'de> for ThemeConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ThemeConfigVisitor;
+
+        impl<'de> Visitor<'de> for ThemeConfigVisitor {
+            type Value = ThemeConfig;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "theme name or mapping")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(ThemeConfig::Named(value.to_owned()))
+            }
+
+            fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                Theme::deserialize(MapAccessDeserializer::new(map))
+                    .map(ThemeConfig::Inline)
+            }
+        }
+
+        deserializer.deserialize_any(ThemeConfigVisitor)
+    }
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self::Named(Self::DEFAULT.into())
+    }
+}
 
 /// User-configurable visual settings. These are used to generate the full style
-/// set.
-#[derive(Debug, Serialize, Deserialize)]
+/// set. Any field not specified falls back to the default (`dark`) theme.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
 #[serde(default)]
 pub struct Theme {
     pub primary_color: Color,
@@ -16,8 +118,59 @@ pub struct Theme {
     /// fallible to just have the user specify it.
     pub primary_text_color: Color,
     pub secondary_color: Color,
+    /// 2xx status codes
     pub success_color: Color,
+    /// 3xx status codes
+    pub redirect_color: Color,
+    /// 4xx status codes
+    pub warning_color: Color,
+    /// 5xx status codes and other errors
     pub error_color: Color,
+    /// Border of unfocused panes and modals
+    pub border_color: Color,
+    /// Background of text boxes and alternating table rows
+    pub surface_color: Color,
+    /// De-emphasized text, such as line numbers
+    pub muted_color: Color,
+    /// Rendered template values. Defaults to the secondary color
+    pub template_preview_color: Option<Color>,
+}
+
+impl Theme {
+    /// Get a built-in theme by name
+    fn built_in(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::default()),
+            "light" => Some(Self {
+                primary_color: Color::Blue,
+                primary_text_color: Color::White,
+                secondary_color: Color::Magenta,
+                success_color: Color::Green,
+                redirect_color: Color::Cyan,
+                warning_color: Color::Yellow,
+                error_color: Color::Red,
+                border_color: Color::DarkGray,
+                surface_color: Color::Gray,
+                muted_color: Color::Gray,
+                template_preview_color: None,
+            }),
+            // https://ethanschoonover.com/solarized/
+            "solarized" => Some(Self {
+                primary_color: Color::Rgb(0x26, 0x8b, 0xd2),
+                primary_text_color: Color::Rgb(0xfd, 0xf6, 0xe3),
+                secondary_color: Color::Rgb(0xb5, 0x89, 0x00),
+                success_color: Color::Rgb(0x85, 0x99, 0x00),
+                redirect_color: Color::Rgb(0x6c, 0x71, 0xc4),
+                warning_color: Color::Rgb(0xcb, 0x4b, 0x16),
+                error_color: Color::Rgb(0xdc, 0x32, 0x2f),
+                border_color: Color::Rgb(0x58, 0x6e, 0x75),
+                surface_color: Color::Rgb(0x07, 0x36, 0x42),
+                muted_color: Color::Rgb(0x58, 0x6e, 0x75),
+                template_preview_color: Some(Color::Rgb(0x2a, 0xa1, 0x98)),
+            }),
+            _ => None,
+        }
+    }
 }
 
 impl Default for Theme {
@@ -27,7 +180,13 @@ impl Default for Theme {
             primary_text_color: Color::White,
             secondary_color: Color::Yellow,
             success_color: Color::Green,
+            redirect_color: Color::Cyan,
+            warning_color: Color::Yellow,
             error_color: Color::Red,
+            border_color: Color::Reset,
+            surface_color: Color::DarkGray,
+            muted_color: Color::DarkGray,
+            template_preview_color: None,
         }
     }
 }
@@ -91,8 +250,29 @@ impl PaneStyles {
 /// Styles for HTTP status code display
 #[derive(Debug)]
 pub struct StatusCodeStyles {
+    /// 1xx and 2xx
     pub success: Style,
-    pub error: Style,
+    /// 3xx
+    pub redirect: Style,
+    /// 4xx
+    pub client_error: Style,
+    /// 5xx
+    pub server_error: Style,
+}
+
+impl StatusCodeStyles {
+    /// Get the style for a status code, based on its class
+    pub fn get(&self, status: StatusCode) -> Style {
+        if status.is_server_error() {
+            self.server_error
+        } else if status.is_client_error() {
+            self.client_error
+        } else if status.is_redirection() {
+            self.redirect
+        } else {
+            self.success
+        }
+    }
 }
 
 /// Styles for Tab component
@@ -112,6 +292,8 @@ pub struct TableStyles {
     pub disabled: Style,
     pub highlight: Style,
     pub title: Style,
+    /// Row whose values differ between columns
+    pub diff: Style,
 }
 
 /// Styles for TemplatePreview component
@@ -158,11 +340,11 @@ impl Styles {
                     .add_modifier(Modifier::BOLD),
             },
             modal: ModalStyles {
-                border: Style::default(),
+                border: Style::default().fg(theme.border_color),
                 border_type: BorderType::Double,
             },
             pane: PaneStyles {
-                border: Style::default(),
+                border: Style::default().fg(theme.border_color),
                 border_selected: Style::default()
                     .fg(theme.primary_color)
                     .add_modifier(Modifier::BOLD),
@@ -173,7 +355,13 @@ impl Styles {
                 success: Style::default()
                     .fg(Color::Black)
                     .bg(theme.success_color),
-                error: Style::default().bg(theme.error_color),
+                redirect: Style::default()
+                    .fg(Color::Black)
+                    .bg(theme.redirect_color),
+                client_error: Style::default()
+                    .fg(Color::Black)
+                    .bg(theme.warning_color),
+                server_error: Style::default().bg(theme.error_color),
             },
             tab: TabStyles {
                 highlight: Style::default()
@@ -186,7 +374,7 @@ impl Styles {
                     .add_modifier(Modifier::BOLD)
                     .add_modifier(Modifier::UNDERLINED),
                 text: Style::default(),
-                alt: Style::default().bg(Color::DarkGray),
+                alt: Style::default().bg(theme.surface_color),
                 disabled: Style::default().add_modifier(Modifier::DIM),
                 highlight: Style::default()
                     .bg(theme.primary_color)
@@ -194,9 +382,14 @@ impl Styles {
                     .add_modifier(Modifier::BOLD)
                     .add_modifier(Modifier::UNDERLINED),
                 title: Style::default().add_modifier(Modifier::BOLD),
+                diff: Style::default()
+                    .fg(theme.error_color)
+                    .add_modifier(Modifier::BOLD),
             },
             template_preview: TemplatePreviewStyles {
-                text: Style::default().fg(theme.secondary_color),
+                text: Style::default().fg(theme
+                    .template_preview_color
+                    .unwrap_or(theme.secondary_color)),
                 error: Style::default().bg(theme.error_color),
             },
             text: TextStyle {
@@ -207,14 +400,60 @@ impl Styles {
                 error: Style::default().bg(theme.error_color),
             },
             text_box: TextBoxStyle {
-                text: Style::default().bg(Color::DarkGray),
+                text: Style::default().bg(theme.surface_color),
                 cursor: Style::default().bg(Color::White).fg(Color::Black),
                 placeholder: Style::default().fg(Color::Black),
                 invalid: Style::default().bg(Color::LightRed),
             },
             text_window: TextWindowStyle {
-                gutter: Style::default().fg(Color::DarkGray),
+                gutter: Style::default().fg(theme.muted_color),
             },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_err, assert_matches};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::dark("dark")]
+    #[case::light("light")]
+    #[case::solarized("solarized")]
+    fn test_built_in(#[case] name: &str) {
+        assert!(ThemeConfig::BUILT_IN.contains(&name));
+        assert!(Theme::built_in(name).is_some());
+    }
+
+    /// A named theme or an inline theme can be given in the config. Missing
+    /// fields fall back to the default theme
+    #[test]
+    fn test_deserialize_theme_config() {
+        let name = assert_matches!(
+            parse_yaml::<ThemeConfig>(b"solarized").unwrap(),
+            ThemeConfig::Named(name) => name,
+        );
+        assert_eq!(name, "solarized");
+        let theme = ThemeConfig::Inline(Theme {
+            primary_color: Color::Green,
+            ..Theme::default()
+        });
+        assert_eq!(
+            parse_yaml::<ThemeConfig>(b"primary_color: green")
+                .unwrap()
+                .load()
+                .unwrap(),
+            theme.load().unwrap()
+        );
+        assert_err!(
+            parse_yaml::<ThemeConfig>(b"primary_color: not-a-color"),
+            "Failed to parse Colors"
+        );
+        assert_err!(
+            parse_yaml::<ThemeConfig>(b"3"),
+            "expected theme name or mapping"
+        );
+    }
+}
@@ -1,10 +1,12 @@
 pub mod paths;
 
 use crate::{
+    collection::serde_duration,
     http::RequestError,
     template::ChainError,
     tui::message::{Message, MessageSender},
 };
+use chrono::{DateTime, Duration, Local, NaiveTime};
 use derive_more::{DerefMut, Display};
 use serde::de::DeserializeOwned;
 use std::{
@@ -39,6 +41,128 @@ pub fn parse_yaml<T: DeserializeOwned>(bytes: &[u8]) -> serde_yaml::Result<T> {
     serde_yaml::from_value(yaml_value)
 }
 
+/// Format a duration for display, e.g. `150ms` or `1.25s`
+pub fn format_duration(duration: &Duration) -> String {
+    let ms = duration.num_milliseconds();
+    if ms < 1000 {
+        format!("{ms}ms")
+    } else {
+        format!("{:.2}s", ms as f64 / 1000.0)
+    }
+}
+
+/// Format how long ago something happened, in the largest whole unit, e.g.
+/// `45s`, `12m`, or `3d`
+pub fn format_age(age: &Duration) -> String {
+    let seconds = age.num_seconds().max(0);
+    if seconds < 60 {
+        format!("{seconds}s")
+    } else if seconds < 3600 {
+        format!("{}m", seconds / 60)
+    } else if seconds < 86400 {
+        format!("{}h", seconds / 3600)
+    } else {
+        format!("{}d", seconds / 86400)
+    }
+}
+
+/// Parse when to send a scheduled request: either a local time of day
+/// (`HH:MM` or `HH:MM:SS`) or an RFC 3339 timestamp. A time of day that has
+/// already passed today refers to tomorrow.
+pub fn parse_send_at(
+    input: &str,
+    now: DateTime<Local>,
+) -> Result<DateTime<Local>, String> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(input) {
+        return Ok(timestamp.with_timezone(&Local));
+    }
+    let time = NaiveTime::parse_from_str(input, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(input, "%H:%M"))
+        .map_err(|_| {
+            format!(
+                "Invalid time `{input}`; expected `HH:MM`, `HH:MM:SS`, or an \
+                RFC 3339 timestamp"
+            )
+        })?;
+    let mut date = now.date_naive();
+    if time <= now.time() {
+        date = date
+            .succ_opt()
+            .ok_or_else(|| "Date out of range".to_owned())?;
+    }
+    date.and_time(time)
+        .and_local_timezone(Local)
+        .earliest()
+        .ok_or_else(|| format!("Time `{input}` doesn't exist in local time"))
+}
+
+/// Parse when to send a scheduled request, from either a delay (e.g. `30s`) or
+/// anything accepted by [parse_send_at]
+pub fn parse_send_time(
+    input: &str,
+    now: DateTime<Local>,
+) -> Result<DateTime<Local>, String> {
+    match serde_duration::parse(input) {
+        Ok(delay) => Duration::from_std(delay)
+            .ok()
+            .and_then(|delay| now.checked_add_signed(delay))
+            .ok_or_else(|| format!("Delay `{input}` is too large")),
+        Err(_) => parse_send_at(input, now),
+    }
+}
+
+/// Score how well a query fuzzy-matches some text. Every character of the
+/// query must appear in the text in order, ignoring case. Matches that are
+/// consecutive or at the start of a word score higher, and skipped characters
+/// score lower. Return `None` if the text doesn't match. An empty query
+/// matches everything.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<i64> {
+    let mut query = query.chars().flat_map(char::to_lowercase).peekable();
+    let mut score = 0;
+    let mut previous: Option<char> = None;
+    let mut consecutive = false;
+    for c in text.chars() {
+        let Some(&target) = query.peek() else {
+            break;
+        };
+        if c.to_lowercase().next() == Some(target) {
+            query.next();
+            score += 1;
+            if consecutive {
+                score += 5;
+            }
+            let word_start = previous.map_or(true, |previous| {
+                !previous.is_alphanumeric()
+                    || (previous.is_lowercase() && c.is_uppercase())
+            });
+            if word_start {
+                score += 8;
+            }
+            consecutive = true;
+        } else {
+            score -= 1;
+            consecutive = false;
+        }
+        previous = Some(c);
+    }
+    query.peek().is_none().then_some(score)
+}
+
+/// Get the longest prefix shared by all the given strings
+pub fn common_prefix(values: &[String]) -> &str {
+    let Some((first, rest)) = values.split_first() else {
+        return "";
+    };
+    let len = rest.iter().fold(first.len(), |len, value| {
+        first[..len]
+            .char_indices()
+            .zip(value.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(len.min(value.len()), |((index, _), _)| index)
+    });
+    &first[..len]
+}
+
 /// A value that can be replaced in-place. This is useful for two purposes:
 /// - Transferring ownership of values from old to new
 /// - Dropping the old value before creating the new one
@@ -331,6 +455,8 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
+    use rstest::{fixture, rstest};
     use strum::EnumIter;
 
     #[derive(Clone, Debug, PartialEq, EnumIter)]
@@ -391,4 +517,100 @@ mod tests {
         iter.next();
         assert_eq!(iter.len(), 0);
     }
+
+    /// Noon on New Year's Day
+    #[fixture]
+    fn now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+    }
+
+    #[rstest]
+    #[case::later_today("14:30", (2024, 1, 1, 14, 30, 0))]
+    #[case::seconds("12:00:05", (2024, 1, 1, 12, 0, 5))]
+    #[case::passed("09:00", (2024, 1, 2, 9, 0, 0))]
+    #[case::now("12:00", (2024, 1, 2, 12, 0, 0))]
+    #[case::delay("90s", (2024, 1, 1, 12, 1, 30))]
+    #[case::delay_ms("1500ms", (2024, 1, 1, 12, 0, 1))]
+    fn test_parse_send_time(
+        now: DateTime<Local>,
+        #[case] input: &str,
+        #[case] expected: (i32, u32, u32, u32, u32, u32),
+    ) {
+        let (year, month, day, hour, minute, second) = expected;
+        let expected = Local
+            .with_ymd_and_hms(year, month, day, hour, minute, second)
+            .unwrap();
+        let actual = parse_send_time(input, now).unwrap();
+        // Drop sub-second precision from the `ms` delay
+        assert_eq!(actual.timestamp(), expected.timestamp());
+    }
+
+    #[rstest]
+    fn test_parse_send_time_timestamp(now: DateTime<Local>) {
+        assert_eq!(
+            parse_send_time("2024-01-02T03:04:05Z", now).unwrap(),
+            DateTime::parse_from_rfc3339("2024-01-02T03:04:05Z").unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case::garbage("soon")]
+    #[case::bad_time("25:00")]
+    fn test_parse_send_time_error(now: DateTime<Local>, #[case] input: &str) {
+        assert_eq!(
+            parse_send_time(input, now).unwrap_err(),
+            format!(
+                "Invalid time `{input}`; expected `HH:MM`, `HH:MM:SS`, or an \
+                RFC 3339 timestamp"
+            )
+        );
+    }
+
+    #[rstest]
+    #[case::negative(-5, "0s")]
+    #[case::seconds(59, "59s")]
+    #[case::minutes(60, "1m")]
+    #[case::hours(3 * 3600 + 59 * 60, "3h")]
+    #[case::days(10 * 86400, "10d")]
+    fn test_format_age(#[case] seconds: i64, #[case] expected: &str) {
+        assert_eq!(format_age(&Duration::seconds(seconds)), expected);
+    }
+
+    #[rstest]
+    #[case::empty("", "Anything", true)]
+    #[case::exact("history", "History", true)]
+    #[case::subsequence("rlc", "Reload Collection", true)]
+    #[case::out_of_order("cr", "Reload", false)]
+    #[case::too_long("historyy", "History", false)]
+    fn test_fuzzy_match(
+        #[case] query: &str,
+        #[case] text: &str,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(fuzzy_match(query, text).is_some(), expected);
+    }
+
+    /// Word starts and consecutive characters are preferred
+    #[rstest]
+    #[case::word_start("rl", "Recipe List", "Reload Collection")]
+    #[case::consecutive("his", "History", "Help: Inspect Stuff")]
+    #[case::camel_case("gf", "getFish", "golf")]
+    fn test_fuzzy_match_ranking(
+        #[case] query: &str,
+        #[case] better: &str,
+        #[case] worse: &str,
+    ) {
+        assert!(fuzzy_match(query, better) > fuzzy_match(query, worse));
+    }
+
+    #[rstest]
+    #[case::empty(&[], "")]
+    #[case::single(&["users"], "users")]
+    #[case::shared(&["user_count", "users"], "user")]
+    #[case::none(&["data", "meta"], "")]
+    #[case::shorter(&["users", "user"], "user")]
+    fn test_common_prefix(#[case] values: &[&str], #[case] expected: &str) {
+        let values = values.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(common_prefix(&values), expected);
+    }
 }
@@ -0,0 +1,545 @@
+//! WebSocket connections. A WebSocket recipe is built exactly like an HTTP
+//! request, using [HttpEngine::build]. The built request is then used for the
+//! opening handshake, and the connection stays open until either side closes
+//! it. Every frame sent and received is recorded, and the whole conversation
+//! is saved to history as a [WebSocketExchange] once the connection ends.
+
+use crate::{
+    collection::{ProfileId, RecipeId},
+    db::CollectionDatabase,
+    http::{HttpEngine, RequestId, RequestRecord, RequestSeed},
+    template::TemplateContext,
+};
+use anyhow::{bail, Context};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::{SinkExt, StreamExt};
+use reqwest::{header::HeaderName, Request};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    time,
+};
+use tokio_tungstenite::tungstenite::{
+    self, client::IntoClientRequest, protocol::frame::coding::CloseCode,
+};
+use tracing::{debug, info, info_span, Instrument};
+
+/// A complete WebSocket conversation. This is the WebSocket variant of
+/// [Exchange](crate::http::Exchange): the handshake request, plus every message
+/// sent in either direction until the connection closed.
+#[derive(Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct WebSocketExchange {
+    /// ID to uniquely refer to this exchange. This is the ID of the handshake
+    /// request
+    pub id: RequestId,
+    /// The handshake request
+    pub request: Arc<RequestRecord>,
+    /// When was the handshake request sent?
+    pub start_time: DateTime<Utc>,
+    /// When was the connection closed?
+    pub end_time: DateTime<Utc>,
+    /// Every data frame, in the order they were sent/received
+    pub messages: Vec<WebSocketMessage>,
+}
+
+/// A single data frame sent over a WebSocket connection
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct WebSocketMessage {
+    pub time: DateTime<Utc>,
+    pub direction: Direction,
+    pub content: MessageContent,
+}
+
+/// Did we send or receive a message?
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// Payload of a data frame. Control frames (ping, pong, close) aren't recorded
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MessageContent {
+    Text(String),
+    Binary(Bytes),
+}
+
+/// Lifecycle of a connection
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConnectionStatus {
+    /// Building the request or waiting on the handshake
+    Connecting,
+    Open,
+    /// Closed by either side. Code and reason are from the close frame, if
+    /// the server sent one
+    Closed {
+        code: Option<u16>,
+        reason: String,
+    },
+    /// The request failed to build, the handshake failed, or the connection
+    /// was dropped with an error
+    Failed(String),
+}
+
+impl ConnectionStatus {
+    /// Is the connection opening or open?
+    pub fn is_live(&self) -> bool {
+        matches!(self, Self::Connecting | Self::Open)
+    }
+}
+
+/// Everything known about a connection so far. This is updated by the
+/// background task, and read by whoever is displaying the connection
+#[derive(Debug)]
+pub struct ConnectionState {
+    pub status: ConnectionStatus,
+    /// The handshake request. `None` until the connection is open
+    pub request: Option<Arc<RequestRecord>>,
+    pub start_time: DateTime<Utc>,
+    pub messages: Vec<WebSocketMessage>,
+}
+
+/// A handle to a WebSocket connection. The connection runs in a background
+/// task; this handle can be used to check its state, and send commands to it.
+/// Cheap to clone. The connection is closed once every handle is dropped.
+#[derive(Clone, Debug)]
+pub struct WebSocketConnection {
+    profile_id: Option<ProfileId>,
+    recipe_id: RecipeId,
+    state: Arc<Mutex<ConnectionState>>,
+    commands: UnboundedSender<Command>,
+}
+
+/// Commands from the user to the connection task
+#[derive(Debug)]
+enum Command {
+    Send(String),
+    Close,
+}
+
+impl WebSocketConnection {
+    /// Build the handshake request for a recipe, then open a connection in a
+    /// background task. `on_update` is called from the task whenever the
+    /// connection state changes, e.g. when a message is received.
+    pub fn open(
+        http_engine: HttpEngine,
+        seed: RequestSeed,
+        template_context: TemplateContext,
+        database: CollectionDatabase,
+        on_update: impl 'static + Fn() + Send,
+    ) -> Self {
+        let (commands_tx, commands_rx) = mpsc::unbounded_channel();
+        let connection = Self {
+            profile_id: template_context.selected_profile.clone(),
+            recipe_id: seed.recipe.id.clone(),
+            state: Arc::new(Mutex::new(ConnectionState {
+                status: ConnectionStatus::Connecting,
+                request: None,
+                start_time: Utc::now(),
+                messages: Vec::new(),
+            })),
+            commands: commands_tx,
+        };
+
+        let task = ConnectionTask {
+            state: Arc::clone(&connection.state),
+            commands: commands_rx,
+            on_update: Box::new(on_update),
+        };
+        let span = info_span!("WebSocket", request_id = %seed.id);
+        tokio::spawn(
+            task.run(http_engine, seed, template_context, database)
+                .instrument(span),
+        );
+        connection
+    }
+
+    pub fn profile_id(&self) -> Option<&ProfileId> {
+        self.profile_id.as_ref()
+    }
+
+    pub fn recipe_id(&self) -> &RecipeId {
+        &self.recipe_id
+    }
+
+    /// Get the current state of the connection. The state is locked while the
+    /// guard is held, so don't hang onto it
+    pub fn state(&self) -> MutexGuard<'_, ConnectionState> {
+        self.state.lock().expect("WebSocket state lock poisoned")
+    }
+
+    /// Queue a text frame to be sent. If the connection isn't open, the
+    /// message is dropped
+    pub fn send(&self, text: String) {
+        let _ = self.commands.send(Command::Send(text));
+    }
+
+    /// Start closing the connection. The connection isn't closed until the
+    /// server responds
+    pub fn close(&self) {
+        let _ = self.commands.send(Command::Close);
+    }
+}
+
+/// The background half of a [WebSocketConnection]
+struct ConnectionTask {
+    state: Arc<Mutex<ConnectionState>>,
+    commands: UnboundedReceiver<Command>,
+    on_update: Box<dyn Fn() + Send>,
+}
+
+impl ConnectionTask {
+    async fn run(
+        mut self,
+        http_engine: HttpEngine,
+        seed: RequestSeed,
+        template_context: TemplateContext,
+        database: CollectionDatabase,
+    ) {
+        let id = seed.id;
+        let result = self.run_inner(http_engine, seed, template_context).await;
+        let status = match result {
+            Ok(status) => status,
+            Err(error) => ConnectionStatus::Failed(format!("{error:#}")),
+        };
+        info!(?status, "WebSocket connection ended");
+
+        // Save the conversation, as long as we got far enough to connect
+        let exchange = {
+            let mut state = self.lock_state();
+            state.status = status;
+            state.request.as_ref().map(|request| WebSocketExchange {
+                id,
+                request: Arc::clone(request),
+                start_time: state.start_time,
+                end_time: Utc::now(),
+                messages: state.messages.clone(),
+            })
+        };
+        if let Some(exchange) = exchange {
+            let _ = database.insert_websocket_exchange(&exchange);
+        }
+        (self.on_update)();
+    }
+
+    /// Connect, then pass messages back and forth until the connection
+    /// closes. Return the final status of the connection
+    async fn run_inner(
+        &mut self,
+        http_engine: HttpEngine,
+        seed: RequestSeed,
+        template_context: TemplateContext,
+    ) -> anyhow::Result<ConnectionStatus> {
+        let timeout = seed.recipe.timeout;
+        let ticket = http_engine.build(seed, &template_context).await?;
+        let (record, request) = ticket.into_request();
+        let handshake = handshake_request(&request)?;
+
+        debug!(url = %handshake.uri(), "Opening WebSocket connection");
+        let connect = tokio_tungstenite::connect_async(handshake);
+        let (socket, _) = match timeout {
+            Some(timeout) => time::timeout(timeout, connect)
+                .await
+                .context("Timed out waiting for handshake")?,
+            None => connect.await,
+        }
+        .context("Error opening WebSocket connection")?;
+        {
+            let mut state = self.lock_state();
+            state.status = ConnectionStatus::Open;
+            state.request = Some(record);
+            state.start_time = Utc::now();
+        }
+        (self.on_update)();
+
+        let (mut sink, mut stream) = socket.split();
+        // Once we start closing, we stop accepting commands but continue
+        // reading until the server acknowledges the close
+        let mut closing = false;
+        loop {
+            tokio::select! {
+                frame = stream.next() => {
+                    let content = match frame.transpose()? {
+                        Some(tungstenite::Message::Text(text)) => {
+                            MessageContent::Text(text)
+                        }
+                        Some(tungstenite::Message::Binary(data)) => {
+                            MessageContent::Binary(data.into())
+                        }
+                        Some(tungstenite::Message::Close(frame)) => {
+                            return Ok(ConnectionStatus::Closed {
+                                code: frame.as_ref().map(|frame| frame.code.into()),
+                                reason: frame
+                                    .map(|frame| frame.reason.into_owned())
+                                    .unwrap_or_default(),
+                            });
+                        }
+                        // Pings are answered automatically
+                        Some(_) => continue,
+                        None => {
+                            return Ok(ConnectionStatus::Closed {
+                                code: None,
+                                reason: String::new(),
+                            })
+                        }
+                    };
+                    self.push_message(Direction::Received, content);
+                }
+                command = self.commands.recv(), if !closing => match command {
+                    Some(Command::Send(text)) => {
+                        sink.send(tungstenite::Message::Text(text.clone()))
+                            .await
+                            .context("Error sending message")?;
+                        self.push_message(
+                            Direction::Sent,
+                            MessageContent::Text(text),
+                        );
+                    }
+                    // If all handles are dropped, no one's listening anymore
+                    Some(Command::Close) | None => {
+                        closing = true;
+                        sink.send(tungstenite::Message::Close(Some(
+                            tungstenite::protocol::CloseFrame {
+                                code: CloseCode::Normal,
+                                reason: "".into(),
+                            },
+                        )))
+                        .await
+                        .context("Error closing connection")?;
+                    }
+                },
+            }
+        }
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, ConnectionState> {
+        self.state.lock().expect("WebSocket state lock poisoned")
+    }
+
+    fn push_message(&self, direction: Direction, content: MessageContent) {
+        self.lock_state().messages.push(WebSocketMessage {
+            time: Utc::now(),
+            direction,
+            content,
+        });
+        (self.on_update)();
+    }
+}
+
+/// Convert a built HTTP request into a WebSocket handshake. The handshake
+/// headers (`Upgrade`, `Sec-WebSocket-Key`, etc.) are generated from the URL,
+/// then all headers from the recipe are added on top. `http` and `https` URLs
+/// are accepted as aliases for `ws` and `wss`.
+fn handshake_request(
+    request: &Request,
+) -> anyhow::Result<tungstenite::handshake::client::Request> {
+    let mut url = request.url().clone();
+    let scheme = match url.scheme() {
+        "ws" | "http" => "ws",
+        "wss" | "https" => "wss",
+        scheme => {
+            bail!("Unsupported URL scheme `{scheme}`; expected `ws` or `wss`")
+        }
+    };
+    // Only fails when switching between special and non-special schemes,
+    // which these all are
+    let _ = url.set_scheme(scheme);
+
+    let mut handshake = url
+        .as_str()
+        .into_client_request()
+        .with_context(|| format!("Invalid WebSocket URL `{url}`"))?;
+    let generated: Vec<HeaderName> =
+        handshake.headers().keys().cloned().collect();
+    for (name, value) in request.headers() {
+        if !generated.contains(name) {
+            handshake.headers_mut().append(name, value.clone());
+        }
+    }
+    Ok(handshake)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collection::{Recipe, RecipeKind},
+        config::Config,
+        http::BuildOptions,
+        test_util::{assert_err, Factory},
+    };
+    use indexmap::indexmap;
+    use rstest::rstest;
+    use std::time::Duration;
+    use tokio::{net::TcpListener, sync::Notify};
+
+    /// Recipe headers should be merged into the generated handshake headers,
+    /// but can't override them
+    #[rstest]
+    #[case::ws("ws://localhost/socket", "ws://localhost/socket")]
+    #[case::http("http://localhost/socket", "ws://localhost/socket")]
+    #[case::https("https://localhost/socket", "wss://localhost/socket")]
+    fn test_handshake_request(#[case] url: &str, #[case] expected_url: &str) {
+        let request = reqwest::Client::new()
+            .get(url)
+            .header("Authorization", "Bearer token")
+            .header("Sec-WebSocket-Version", "1")
+            .build()
+            .unwrap();
+        let handshake = handshake_request(&request).unwrap();
+        assert_eq!(handshake.uri().to_string(), expected_url);
+        let headers = handshake.headers();
+        assert_eq!(headers["Authorization"], "Bearer token");
+        assert_eq!(headers["Upgrade"], "websocket");
+        assert_eq!(headers["Sec-WebSocket-Version"], "13");
+    }
+
+    #[test]
+    fn test_handshake_request_invalid_scheme() {
+        let request = reqwest::Client::new()
+            .get("ftp://localhost/socket")
+            .build()
+            .unwrap();
+        assert_err!(
+            handshake_request(&request),
+            "Unsupported URL scheme `ftp`"
+        );
+    }
+
+    /// Connect to an echo server, send a message, then close. The conversation
+    /// should be recorded in the DB
+    #[tokio::test]
+    async fn test_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket =
+                tokio_tungstenite::accept_async(stream).await.unwrap();
+            while let Some(Ok(message)) = socket.next().await {
+                if message.is_text() {
+                    socket.send(message).await.unwrap();
+                }
+            }
+        });
+
+        let recipe = Recipe {
+            kind: RecipeKind::WebSocket,
+            url: format!("ws://{address}/").parse().unwrap(),
+            headers: indexmap! {"X-Test".into() => "test".into()},
+            ..Recipe::factory(())
+        };
+        let recipe_id = recipe.id.clone();
+        let template_context = TemplateContext::factory(());
+        let database = template_context.database.clone();
+        let notify = Arc::new(Notify::new());
+        let connection = WebSocketConnection::open(
+            HttpEngine::new(&Config::default()),
+            RequestSeed::new(recipe, BuildOptions::default()),
+            template_context,
+            database.clone(),
+            {
+                let notify = Arc::clone(&notify);
+                move || notify.notify_one()
+            },
+        );
+
+        // Wait until the connection reaches some state
+        let wait_for = |predicate: fn(&ConnectionState) -> bool| {
+            let connection = connection.clone();
+            let notify = Arc::clone(&notify);
+            async move {
+                time::timeout(Duration::from_secs(5), async {
+                    while !predicate(&connection.state()) {
+                        notify.notified().await;
+                    }
+                })
+                .await
+                .expect("Timed out waiting for connection state")
+            }
+        };
+
+        wait_for(|state| state.status == ConnectionStatus::Open).await;
+        connection.send("hello".into());
+        wait_for(|state| state.messages.len() == 2).await;
+        connection.close();
+        wait_for(|state| !state.status.is_live()).await;
+
+        let expected = [
+            (Direction::Sent, MessageContent::Text("hello".into())),
+            (Direction::Received, MessageContent::Text("hello".into())),
+        ];
+        let exchange = database
+            .get_latest_websocket_exchange(None, &recipe_id)
+            .unwrap()
+            .expect("Exchange not saved");
+        assert_eq!(exchange.request.headers["X-Test"], "test");
+        assert_eq!(
+            exchange
+                .messages
+                .into_iter()
+                .map(|message| (message.direction, message.content))
+                .collect::<Vec<_>>(),
+            expected
+        );
+        assert_eq!(
+            connection.state().status,
+            ConnectionStatus::Closed {
+                code: Some(1000),
+                reason: "".into()
+            }
+        );
+    }
+
+    /// Handshake failure should be reported in the status, and nothing should
+    /// be saved
+    #[tokio::test]
+    async fn test_connection_error() {
+        // Grab a free port, then close it so the connection is refused
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+
+        let recipe = Recipe {
+            kind: RecipeKind::WebSocket,
+            url: format!("ws://{address}/").parse().unwrap(),
+            ..Recipe::factory(())
+        };
+        let recipe_id = recipe.id.clone();
+        let template_context = TemplateContext::factory(());
+        let database = template_context.database.clone();
+        let notify = Arc::new(Notify::new());
+        let connection = WebSocketConnection::open(
+            HttpEngine::new(&Config::default()),
+            RequestSeed::new(recipe, BuildOptions::default()),
+            template_context,
+            database.clone(),
+            {
+                let notify = Arc::clone(&notify);
+                move || notify.notify_one()
+            },
+        );
+        time::timeout(Duration::from_secs(5), async {
+            while connection.state().status.is_live() {
+                notify.notified().await;
+            }
+        })
+        .await
+        .unwrap();
+
+        let status = connection.state().status.clone();
+        assert!(
+            matches!(&status, ConnectionStatus::Failed(error)
+                if error.starts_with("Error opening WebSocket connection")),
+            "{status:?}"
+        );
+        assert!(database
+            .get_latest_websocket_exchange(None, &recipe_id)
+            .unwrap()
+            .is_none());
+    }
+}